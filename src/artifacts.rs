@@ -0,0 +1,194 @@
+use crate::corpus::{slug, RecordedRequest};
+use crate::models::client::{RawResponse, SentRequest, ServerResponse};
+use reqwest::header::HeaderMap;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum ArtifactError {
+    #[error("Failed to write artifacts: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Failed to write repro: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+// Everything known about each finding, in a directory of its own named by the
+// input's content hash:
+//
+//   repro.json               the request, as `fuzzer1531 replay --corpus` reads it
+//   summary.txt              error, seed, statuses and timing
+//   test-request.http        exactly what went to each server, when captured
+//   test-response.http       status, headers and the raw body from each server
+//   reference-request.http
+//   reference-response.http
+//
+// The same input found again overwrites its directory.
+#[derive(Debug, Clone)]
+pub struct Artifacts {
+    dir: PathBuf,
+}
+
+impl Artifacts {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    // `client` is the server under test's response, `actual` the reference's.
+    pub fn write(
+        &self,
+        request: &RecordedRequest,
+        client: Option<&ServerResponse>,
+        actual: Option<&ServerResponse>,
+    ) -> Result<PathBuf, ArtifactError> {
+        let dir = self.dir.join(format!(
+            "{}-{}-{:016x}",
+            request.method.to_lowercase(),
+            slug(&request.endpoint),
+            request.content_hash()
+        ));
+        std::fs::create_dir_all(&dir)?;
+        std::fs::write(dir.join("repro.json"), serde_json::to_vec_pretty(request)?)?;
+        std::fs::write(dir.join("summary.txt"), summary(request, client, actual))?;
+        for (name, response) in [("test", client), ("reference", actual)] {
+            let Some(response) = response else {
+                continue;
+            };
+            if let Some(exchange) = &response.exchange {
+                std::fs::write(
+                    dir.join(format!("{}-request.http", name)),
+                    request_dump(&exchange.request),
+                )?;
+            }
+            std::fs::write(
+                dir.join(format!("{}-response.http", name)),
+                response_dump(response),
+            )?;
+        }
+        Ok(dir)
+    }
+}
+
+fn summary(
+    request: &RecordedRequest,
+    client: Option<&ServerResponse>,
+    actual: Option<&ServerResponse>,
+) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "{} {}", request.method, request.endpoint);
+    let _ = writeln!(out, "seed: {}", request.seed);
+    for (name, response) in [("test", client), ("reference", actual)] {
+        match response {
+            Some(response) => {
+                let _ = write!(
+                    out,
+                    "{}: {} in {:.1}ms",
+                    name,
+                    response.status,
+                    response.latency.as_secs_f64() * 1000.0
+                );
+                if let Some(sent_at) = response.sent_at {
+                    let _ = write!(out, ", sent {}", httpdate::fmt_http_date(sent_at));
+                }
+                out.push('\n');
+            }
+            None => {
+                let _ = writeln!(out, "{}: no response", name);
+            }
+        }
+    }
+    if let Some(error) = &request.error {
+        let _ = writeln!(out, "\n{}", error);
+    }
+    out
+}
+
+fn request_dump(request: &SentRequest) -> Vec<u8> {
+    let mut out = format!("{} {}\n", request.method, request.url);
+    write_headers(&mut out, &request.headers);
+    let mut out = out.into_bytes();
+    out.extend_from_slice(&request.body);
+    out
+}
+
+// Uncaptured responses fall back to the body as read: raw bytes, or the JSON
+// re-encoded.
+fn response_dump(response: &ServerResponse) -> Vec<u8> {
+    let mut out = format!("{}\n", response.status);
+    write_headers(&mut out, &response.headers);
+    let mut out = out.into_bytes();
+    match &response.exchange {
+        Some(exchange) => out.extend_from_slice(&exchange.response_body),
+        None => match &response.raw {
+            Some(RawResponse::Bytes { bytes, .. } | RawResponse::Streamed { head: bytes }) => {
+                out.extend_from_slice(bytes)
+            }
+            Some(RawResponse::Redirect { .. }) => {}
+            None => out.extend_from_slice(response.body.to_string().as_bytes()),
+        },
+    }
+    out
+}
+
+fn write_headers(out: &mut String, headers: &HeaderMap) {
+    for (name, value) in headers {
+        let _ = writeln!(
+            out,
+            "{}: {}",
+            name,
+            String::from_utf8_lossy(value.as_bytes())
+        );
+    }
+    out.push('\n');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::client::tests::raw_server;
+    use crate::models::client::{RequestClient, TestCase};
+    use reqwest::Method;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn findings_get_a_directory_with_every_exchange() {
+        const ANSWER: &str = "HTTP/1.1 400 Bad Request\r\nconnection: close\r\ncontent-type: application/json\r\ncontent-length: 13\r\n\r\n{\"error\": 1 }";
+        let client = RequestClient::new(raw_server(ANSWER).await).with_capture();
+        let case = TestCase::new("/v1/admin/quiz", Method::POST, Some(json!({ "name": "q" })));
+        let test = client.fetch(&case).await.unwrap();
+        let mut reference = test.clone();
+        reference.exchange = None;
+        let mut request = RecordedRequest::new(&case, 9);
+        request.error = Some("status mismatch".to_string());
+
+        let dir = std::env::temp_dir().join(format!("fuzzer1531-artifacts-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let written = Artifacts::new(&dir)
+            .write(&request, Some(&test), Some(&reference))
+            .unwrap();
+        assert!(written.starts_with(&dir));
+        let read = |name: &str| std::fs::read_to_string(written.join(name)).unwrap();
+
+        let sent = read("test-request.http");
+        assert!(sent.starts_with("POST http://127.0.0.1:"), "{}", sent);
+        assert!(sent.ends_with("\n\n{\"name\":\"q\"}"), "{}", sent);
+        let answered = read("test-response.http");
+        assert!(answered.starts_with("400\n"));
+        assert!(answered.ends_with("\n\n{\"error\": 1 }"), "{}", answered);
+        // Without a capture, the body is dumped as read and the request left out.
+        assert!(!written.join("reference-request.http").exists());
+        assert!(read("reference-response.http").ends_with("\n\n{\"error\":1}"));
+
+        let summary = read("summary.txt");
+        assert!(summary.starts_with("POST /v1/admin/quiz\nseed: 9\ntest: 400 in "));
+        assert!(summary.ends_with("\nstatus mismatch\n"));
+        let repro: RecordedRequest = serde_json::from_str(&read("repro.json")).unwrap();
+        assert_eq!(repro.content_hash(), request.content_hash());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,105 @@
+use crate::corpus::RecordedRequest;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::Path;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum CampaignError {
+    #[error("Campaign state I/O failed: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Invalid campaign state: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("The campaign state doesn't fit this run: {0}")]
+    Mismatch(String),
+}
+
+// Where a run got to, saved after every iteration so an interrupted run can
+// carry on from there. Generating a case only depends on the seed, the
+// schedule so far and the corpus inputs the run started with, so the cases
+// already sent are generated again and skipped rather than stored.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Campaign {
+    pub seed: u64,
+    // `METHOD path` of every target, in order, to tell a changed config.
+    pub targets: Vec<String>,
+    // Cases sent so far, over every session of the campaign.
+    pub cases: usize,
+    // The iteration in which the weighted schedule saw a registration succeed.
+    #[serde(default)]
+    pub registered_at: Option<usize>,
+    // Corpus bodies waiting to be mutated when the campaign began, by target
+    // index.
+    #[serde(default)]
+    pub pending: BTreeMap<usize, Vec<Value>>,
+    // Corpus features reached so far, as (method, target, feature).
+    #[serde(default)]
+    pub seen: Vec<(String, String, String)>,
+    // Every finding so far, with its error.
+    #[serde(default)]
+    pub findings: Vec<RecordedRequest>,
+}
+
+impl Campaign {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, CampaignError> {
+        Ok(serde_json::from_slice(&std::fs::read(path)?)?)
+    }
+
+    // Written next to `path` first and moved over it, so a run killed while
+    // saving still leaves the previous state.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), CampaignError> {
+        let path = path.as_ref();
+        let mut partial = path.as_os_str().to_owned();
+        partial.push(".partial");
+        std::fs::write(&partial, serde_json::to_vec_pretty(self)?)?;
+        std::fs::rename(&partial, path)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scratch(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("fuzzer1531-campaign-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir.join(name)
+    }
+
+    #[test]
+    fn saved_state_loads_back_without_leaving_a_partial_file() {
+        let path = scratch("state.json");
+        let campaign = Campaign {
+            seed: 7,
+            targets: vec!["GET /a".to_string()],
+            cases: 12,
+            registered_at: Some(1),
+            pending: BTreeMap::from([(0, vec![serde_json::json!({ "a": 1 })])]),
+            seen: vec![("GET".to_string(), "/a".to_string(), "200".to_string())],
+            findings: Vec::new(),
+        };
+        campaign.save(&path).unwrap();
+        assert_eq!(Campaign::load(&path).unwrap(), campaign);
+        assert!(!scratch("state.json.partial").exists());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn corrupt_or_foreign_state_is_refused() {
+        let path = scratch("bad.json");
+        // Cut off mid-write by something other than `save`.
+        std::fs::write(&path, r#"{ "seed": 7, "targets": ["GET /a""#).unwrap();
+        assert!(matches!(Campaign::load(&path), Err(CampaignError::Json(_))));
+        // Another tool's JSON, e.g. a baseline.
+        std::fs::write(&path, r#"{ "findings": [{ "signature": "x" }] }"#).unwrap();
+        assert!(matches!(Campaign::load(&path), Err(CampaignError::Json(_))));
+        std::fs::write(&path, "[]").unwrap();
+        assert!(matches!(Campaign::load(&path), Err(CampaignError::Json(_))));
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(Campaign::load(&path), Err(CampaignError::Io(_))));
+    }
+}
@@ -0,0 +1,497 @@
+use crate::fuzz::presets::Preset;
+use crate::models::auth::AuthStrategy;
+use crate::models::client::{ServerResponse, TestCase, Tester, TesterError};
+use crate::models::endpoints::{self, ApiVersion};
+use crate::rng::FuzzRng;
+use crate::schema::Schema;
+use reqwest::Method;
+use serde_json::{json, Value};
+use std::fmt;
+use std::sync::Arc;
+use thiserror::Error;
+
+const INVALID_TOKEN: &str = "not-a-valid-token";
+
+// A way of getting a request wrong that the spec gives one status code for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InvalidInput {
+    MissingToken,
+    // A token no session was ever given.
+    InvalidToken,
+    // A quiz ID that doesn't refer to any quiz.
+    UnknownQuiz,
+    // A quiz that exists but belongs to another user.
+    ForeignQuiz,
+    // The named body or query field, with each value its preset rejects.
+    Field(String),
+}
+
+impl InvalidInput {
+    // `missing_token`, `invalid_token`, `unknown_quiz`, `foreign_quiz` or
+    // `field:<name>`.
+    pub fn parse(input: &str) -> Option<Self> {
+        match input {
+            "missing_token" => Some(InvalidInput::MissingToken),
+            "invalid_token" => Some(InvalidInput::InvalidToken),
+            "unknown_quiz" => Some(InvalidInput::UnknownQuiz),
+            "foreign_quiz" => Some(InvalidInput::ForeignQuiz),
+            _ => input
+                .strip_prefix("field:")
+                .filter(|name| !name.is_empty())
+                .map(|name| InvalidInput::Field(name.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for InvalidInput {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            InvalidInput::MissingToken => write!(f, "missing token"),
+            InvalidInput::InvalidToken => write!(f, "invalid token"),
+            InvalidInput::UnknownQuiz => write!(f, "unknown quiz"),
+            InvalidInput::ForeignQuiz => write!(f, "another user's quiz"),
+            InvalidInput::Field(name) => write!(f, "invalid `{}`", name),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rule {
+    pub method: Method,
+    // A path template, e.g. `/admin/quiz/{quizid}`.
+    pub path: String,
+    pub input: InvalidInput,
+    pub status: u16,
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} {} with {} -> {}",
+            self.method, self.path, self.input, self.status
+        )
+    }
+}
+
+// Which status each kind of invalid input must get, per route.
+#[derive(Debug, Clone, Default)]
+pub struct Catalogue {
+    rules: Vec<Rule>,
+}
+
+impl Catalogue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // The quiz API's rules from the spec: 401 for a missing or invalid token,
+    // 403 for a quiz the user doesn't own (or that doesn't exist) and 400 for
+    // fields that fail validation. Question routes only get the first two,
+    // since their bodies have no presets, and GET routes take no fields (the
+    // ones listed for `/admin/user/details` are its PUT's).
+    pub fn quiz_api() -> Self {
+        let mut catalogue = Self::new();
+        for endpoint in endpoints::all() {
+            for (method, path) in endpoint.routes() {
+                let mut rule = |input, status| {
+                    catalogue.rules.push(Rule {
+                        method: method.clone(),
+                        path: path.to_string(),
+                        input,
+                        status,
+                    })
+                };
+                if endpoint.requires_auth {
+                    rule(InvalidInput::MissingToken, 401);
+                    rule(InvalidInput::InvalidToken, 401);
+                }
+                if endpoint.path_param("quizid").is_some() {
+                    rule(InvalidInput::UnknownQuiz, 403);
+                    rule(InvalidInput::ForeignQuiz, 403);
+                }
+                // Only fields the route validates like that: a player's `name`
+                // isn't a quiz name.
+                let presets = match endpoint
+                    .method(&method)
+                    .and_then(|route| route.request_schema())
+                {
+                    Some(Schema::Object { properties, .. }) => properties,
+                    _ => Default::default(),
+                };
+                for field in endpoint.params {
+                    let preset = matches!(presets.get(*field), Some(Schema::Preset(_)));
+                    if method != Method::GET && preset && Preset::for_field(field).is_some() {
+                        rule(InvalidInput::Field(field.to_string()), 400);
+                    }
+                }
+            }
+        }
+        catalogue
+    }
+
+    // Replaces any rule for the same route and input.
+    pub fn with_rule(mut self, rule: Rule) -> Self {
+        match self.rules.iter_mut().find(|existing| {
+            existing.method == rule.method
+                && existing.path == rule.path
+                && existing.input == rule.input
+        }) {
+            Some(existing) => *existing = rule,
+            None => self.rules.push(rule),
+        }
+        self
+    }
+
+    pub fn rules(&self) -> &[Rule] {
+        &self.rules
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum ProbeError {
+    // The user or quiz the probe needed couldn't be created.
+    #[error("setup failed: {0}")]
+    Setup(String),
+
+    #[error("{0}")]
+    Request(Box<TesterError>),
+}
+
+impl From<TesterError> for ProbeError {
+    fn from(err: TesterError) -> Self {
+        ProbeError::Request(Box::new(err))
+    }
+}
+
+// One request breaking a rule, as answered by each server.
+#[derive(Debug)]
+pub struct Probe {
+    pub rule: Rule,
+    // The invalid field value sent, for field rules.
+    pub value: Option<Value>,
+    pub statuses: Vec<(String, Result<u16, ProbeError>)>,
+}
+
+impl Probe {
+    // Servers that answered with the wrong status, or couldn't be probed.
+    pub fn violations(&self) -> impl Iterator<Item = (&str, &Result<u16, ProbeError>)> {
+        self.statuses
+            .iter()
+            .filter(|(_, status)| status.as_ref().ok() != Some(&self.rule.status))
+            .map(|(server, status)| (server.as_str(), status))
+    }
+}
+
+struct Account {
+    email: String,
+    password: String,
+    token: String,
+}
+
+// Sends requests that each break exactly one catalogue rule, on an otherwise
+// valid request from a freshly registered user, and checks that every server
+// answers with the catalogued status. Each probe starts from the before hooks
+// (by default `DELETE /clear`).
+pub struct ErrorChecker {
+    catalogue: Catalogue,
+    servers: Vec<(String, Tester)>,
+    auth: Arc<dyn AuthStrategy>,
+}
+
+impl ErrorChecker {
+    pub fn new(catalogue: Catalogue, version: Option<ApiVersion>) -> Self {
+        Self {
+            catalogue,
+            servers: Vec::new(),
+            auth: version.unwrap_or(ApiVersion::V1).auth_strategy(),
+        }
+    }
+
+    // Only `tester`'s server under test is probed.
+    pub fn with_server(mut self, name: impl Into<String>, tester: Tester) -> Self {
+        self.servers.push((name.into(), tester));
+        self
+    }
+
+    pub fn with_auth(mut self, auth: Arc<dyn AuthStrategy>) -> Self {
+        self.auth = auth;
+        self
+    }
+
+    // Every server gets the same valid values for a probe, drawn from `rng`.
+    pub async fn run(&self, rng: &mut FuzzRng) -> Vec<Probe> {
+        let mut probes = Vec::new();
+        for rule in self.catalogue.rules() {
+            let values = match &rule.input {
+                InvalidInput::Field(name) => Preset::for_field(name)
+                    .map(|preset| preset.invalid().into_iter().map(Some).collect())
+                    .unwrap_or_default(),
+                _ => vec![None],
+            };
+            for value in values {
+                let seed = rng.fork().seed();
+                let mut statuses = Vec::with_capacity(self.servers.len());
+                for (name, tester) in &self.servers {
+                    let status = self
+                        .probe(tester, rule, value.as_ref(), FuzzRng::new(seed))
+                        .await;
+                    statuses.push((name.clone(), status));
+                }
+                probes.push(Probe {
+                    rule: rule.clone(),
+                    value,
+                    statuses,
+                });
+            }
+        }
+        probes
+    }
+
+    async fn probe(
+        &self,
+        tester: &Tester,
+        rule: &Rule,
+        value: Option<&Value>,
+        mut rng: FuzzRng,
+    ) -> Result<u16, ProbeError> {
+        tester.reset().await?;
+        let owner = self.register(tester, &mut rng).await?;
+        let mut endpoint = rule.path.replace("{questionid}", "0");
+        if rule.path.contains("{quizid}") {
+            let quiz = match rule.input {
+                InvalidInput::ForeignQuiz => {
+                    let other = self.register(tester, &mut rng).await?;
+                    self.create_quiz(tester, &other, &mut rng).await?
+                }
+                InvalidInput::UnknownQuiz => self.create_quiz(tester, &owner, &mut rng).await? + 1,
+                _ => self.create_quiz(tester, &owner, &mut rng).await?,
+            };
+            endpoint = endpoint.replace("{quizid}", &quiz.to_string());
+        }
+
+        let params = match endpoints::resolve(&rule.path) {
+            Some(endpoint) if rule.method != Method::GET => endpoint.params,
+            _ => &[],
+        };
+        let fields = params.iter().map(|name| {
+            let value = match (&rule.input, value) {
+                (InvalidInput::Field(field), Some(value)) if field == name => value.clone(),
+                _ => valid_value(&rule.path, name, &owner, &mut rng),
+            };
+            (name.to_string(), value)
+        });
+        let mut case = if rule.method == Method::GET || rule.method == Method::DELETE {
+            let query: Vec<(String, String)> = fields
+                .map(|(name, value)| match value {
+                    Value::String(s) => (name, s),
+                    other => (name, other.to_string()),
+                })
+                .collect();
+            TestCase::new(endpoint, rule.method.clone(), None).with_query(query)
+        } else {
+            let body: serde_json::Map<String, Value> = fields.collect();
+            TestCase::new(endpoint, rule.method.clone(), Some(Value::Object(body)))
+        };
+        match rule.input {
+            InvalidInput::MissingToken => {}
+            InvalidInput::InvalidToken => self.auth.apply(INVALID_TOKEN, &mut case),
+            _ => self.auth.apply(&owner.token, &mut case),
+        }
+        Ok(tester.send(&case).await?.status)
+    }
+
+    async fn register(&self, tester: &Tester, rng: &mut FuzzRng) -> Result<Account, ProbeError> {
+        let email = string(Preset::Email.valid(rng));
+        let password = string(Preset::Password.valid(rng));
+        let case = TestCase::new(
+            "/admin/auth/register",
+            Method::POST,
+            Some(json!({
+                "email": email,
+                "password": password,
+                "nameFirst": Preset::Name.valid(rng),
+                "nameLast": Preset::Name.valid(rng),
+            })),
+        );
+        let response = tester.send(&case).await?;
+        let token = field(&response, "token", "registering a user")?
+            .as_str()
+            .ok_or_else(|| setup_error("registering a user", "token", &response))?
+            .to_string();
+        Ok(Account {
+            email,
+            password,
+            token,
+        })
+    }
+
+    async fn create_quiz(
+        &self,
+        tester: &Tester,
+        owner: &Account,
+        rng: &mut FuzzRng,
+    ) -> Result<u64, ProbeError> {
+        let mut case = TestCase::new(
+            "/admin/quiz",
+            Method::POST,
+            Some(json!({
+                "name": Preset::QuizName.valid(rng),
+                "description": Preset::QuizDescription.valid(rng),
+            })),
+        );
+        self.auth.apply(&owner.token, &mut case);
+        let response = tester.send(&case).await?;
+        field(&response, "quizId", "creating a quiz")?
+            .as_u64()
+            .ok_or_else(|| setup_error("creating a quiz", "quizId", &response))
+    }
+}
+
+// A value that passes validation for `name` on the route `path`, so that only
+// the rule's own input is wrong. Fields without a preset get a placeholder.
+fn valid_value(path: &str, name: &str, owner: &Account, rng: &mut FuzzRng) -> Value {
+    let login = path == "/admin/auth/login";
+    match name {
+        "email" if login => Value::from(owner.email.as_str()),
+        "password" if login => Value::from(owner.password.as_str()),
+        "oldPassword" => Value::from(owner.password.as_str()),
+        "quizIds" => json!([]),
+        "newPosition" => json!(0),
+        "autoStartNum" => json!(0),
+        "action" => json!("END"),
+        "answerIds" => json!([]),
+        _ => Preset::for_field(name).map_or(json!({}), |preset| preset.valid(rng)),
+    }
+}
+
+fn field<'a>(
+    response: &'a ServerResponse,
+    name: &str,
+    step: &str,
+) -> Result<&'a Value, ProbeError> {
+    match response.body.get(name) {
+        Some(value) if response.status == 200 => Ok(value),
+        _ => Err(setup_error(step, name, response)),
+    }
+}
+
+fn setup_error(step: &str, name: &str, response: &ServerResponse) -> ProbeError {
+    ProbeError::Setup(format!(
+        "{} gave status {} without a `{}`: {}",
+        step, response.status, name, response.body
+    ))
+}
+
+fn string(value: Value) -> String {
+    match value {
+        Value::String(s) => s,
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn statuses(catalogue: &Catalogue, method: Method, path: &str) -> Vec<(String, u16)> {
+        catalogue
+            .rules()
+            .iter()
+            .filter(|rule| rule.method == method && rule.path == path)
+            .map(|rule| (rule.input.to_string(), rule.status))
+            .collect()
+    }
+
+    #[test]
+    fn inputs_parse_from_their_config_names() {
+        assert_eq!(
+            InvalidInput::parse("foreign_quiz"),
+            Some(InvalidInput::ForeignQuiz)
+        );
+        assert_eq!(
+            InvalidInput::parse("field:name"),
+            Some(InvalidInput::Field("name".to_string()))
+        );
+        assert_eq!(InvalidInput::parse("field:"), None);
+        assert_eq!(InvalidInput::parse("bad_quiz"), None);
+    }
+
+    #[test]
+    fn the_quiz_api_catalogue_follows_the_spec() {
+        let catalogue = Catalogue::quiz_api();
+        let register = statuses(&catalogue, Method::POST, "/admin/auth/register");
+        assert!(register.contains(&("invalid `email`".to_string(), 400)));
+        assert!(register.iter().all(|(_, status)| *status == 400));
+
+        let quiz = statuses(&catalogue, Method::PUT, "/admin/quiz/{quizid}/name");
+        assert_eq!(
+            quiz,
+            [
+                ("missing token".to_string(), 401),
+                ("invalid token".to_string(), 401),
+                ("unknown quiz".to_string(), 403),
+                ("another user's quiz".to_string(), 403),
+                ("invalid `name`".to_string(), 400),
+            ]
+        );
+        // GET routes take no fields.
+        let list = statuses(&catalogue, Method::GET, "/admin/quiz/list");
+        assert_eq!(list.len(), 2);
+    }
+
+    #[test]
+    fn only_fields_the_route_validates_as_presets_get_rules() {
+        let catalogue = Catalogue::quiz_api();
+        // A player's name is any string, not a quiz name.
+        assert!(statuses(&catalogue, Method::POST, "/player/join").is_empty());
+        let start = statuses(
+            &catalogue,
+            Method::POST,
+            "/admin/quiz/{quizid}/session/start",
+        );
+        assert!(start
+            .iter()
+            .all(|(input, _)| !input.starts_with("invalid `")));
+        assert!(start.contains(&("unknown quiz".to_string(), 403)));
+    }
+
+    #[test]
+    fn rules_replace_the_one_for_the_same_input() {
+        let rule = |status| Rule {
+            method: Method::GET,
+            path: "/admin/quiz/list".to_string(),
+            input: InvalidInput::InvalidToken,
+            status,
+        };
+        let catalogue = Catalogue::quiz_api().with_rule(rule(403));
+        let before = Catalogue::quiz_api().rules().len();
+        assert_eq!(catalogue.rules().len(), before);
+        assert!(catalogue.rules().contains(&rule(403)));
+        assert!(!catalogue.rules().contains(&rule(401)));
+        assert_eq!(
+            rule(403).to_string(),
+            "GET /admin/quiz/list with invalid token -> 403"
+        );
+    }
+
+    #[test]
+    fn probes_report_servers_with_other_statuses() {
+        let probe = Probe {
+            rule: Rule {
+                method: Method::GET,
+                path: "/".to_string(),
+                input: InvalidInput::MissingToken,
+                status: 401,
+            },
+            value: None,
+            statuses: vec![
+                ("good".to_string(), Ok(401)),
+                ("wrong".to_string(), Ok(400)),
+                ("down".to_string(), Err(ProbeError::Setup("no".to_string()))),
+            ],
+        };
+        let servers: Vec<&str> = probe.violations().map(|(server, _)| server).collect();
+        assert_eq!(servers, ["wrong", "down"]);
+    }
+}
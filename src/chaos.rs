@@ -0,0 +1,330 @@
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use reqwest::Url;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use thiserror::Error;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio_util::sync::CancellationToken;
+
+#[derive(Debug, Error)]
+pub enum ChaosError {
+    #[error("Can't proxy `{0}`: expected an http:// or https:// URL with a host")]
+    InvalidUrl(String),
+
+    #[error("Failed to start the chaos proxy: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+// What a `ChaosProxy` does to the responses passing through it. Every
+// response is held back by `latency` plus up to `jitter` more, and may be
+// dropped (the connection closed before any of it is sent, after the server
+// has handled the request) or truncated (half of what had arrived sent, then
+// the connection closed).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Chaos {
+    pub seed: u64,
+    pub latency: Duration,
+    pub jitter: Duration,
+    pub drop_rate: f64,
+    pub truncate_rate: f64,
+}
+
+impl Chaos {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            latency: Duration::ZERO,
+            jitter: Duration::ZERO,
+            drop_rate: 0.0,
+            truncate_rate: 0.0,
+        }
+    }
+
+    pub fn with_latency(mut self, latency: Duration) -> Self {
+        self.latency = latency;
+        self
+    }
+
+    pub fn with_jitter(mut self, jitter: Duration) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    // Chances of each fault per response, from 0 to 1.
+    pub fn with_drop_rate(mut self, rate: f64) -> Self {
+        self.drop_rate = rate.clamp(0.0, 1.0);
+        self
+    }
+
+    pub fn with_truncate_rate(mut self, rate: f64) -> Self {
+        self.truncate_rate = rate.clamp(0.0, 1.0);
+        self
+    }
+
+    fn roll(&self, rng: &mut StdRng) -> Fault {
+        let jitter = if self.jitter.is_zero() {
+            Duration::ZERO
+        } else {
+            self.jitter.mul_f64(rng.gen::<f64>())
+        };
+        let roll = rng.gen::<f64>();
+        if roll < self.drop_rate {
+            Fault::Drop
+        } else if roll < self.drop_rate + self.truncate_rate {
+            Fault::Truncate(self.latency + jitter)
+        } else {
+            Fault::Delay(self.latency + jitter)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Fault {
+    Delay(Duration),
+    Drop,
+    Truncate(Duration),
+}
+
+// Faults injected so far.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ChaosCounts {
+    pub delayed: usize,
+    pub dropped: usize,
+    pub truncated: usize,
+}
+
+#[derive(Debug, Default)]
+struct Counters {
+    delayed: AtomicUsize,
+    dropped: AtomicUsize,
+    truncated: AtomicUsize,
+}
+
+struct Shared {
+    chaos: Chaos,
+    upstream: String,
+    rng: Mutex<StdRng>,
+    counters: Counters,
+}
+
+// A local TCP proxy in front of one server that passes requests through
+// untouched and degrades the responses as `Chaos` says, for checking the
+// fuzzer's own retries and timeouts and how both servers come across behind
+// an unreliable network. It works below HTTP, so HTTPS passes through too, but
+// the certificate then won't match the proxy's address. Faults are rolled
+// from the seed in the order responses start to arrive. Runs until `stop` is
+// called, the proxy is dropped or the runtime shuts down.
+pub struct ChaosProxy {
+    url: String,
+    shared: Arc<Shared>,
+    cancel: CancellationToken,
+}
+
+impl ChaosProxy {
+    // On a free local port, for `server_url`, whose path is kept in `url`.
+    // Must be called from within a Tokio runtime.
+    pub fn start(server_url: &str, chaos: Chaos) -> Result<Self, ChaosError> {
+        let invalid = || ChaosError::InvalidUrl(server_url.to_string());
+        let mut url = Url::parse(server_url).map_err(|_| invalid())?;
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(invalid());
+        }
+        let host = url.host_str().ok_or_else(invalid)?.to_string();
+        let port = url.port_or_known_default().ok_or_else(invalid)?;
+        let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+        listener.set_nonblocking(true)?;
+        let addr: SocketAddr = listener.local_addr()?;
+        let listener = TcpListener::from_std(listener)?;
+        url.set_host(Some("127.0.0.1")).map_err(|_| invalid())?;
+        url.set_port(Some(addr.port())).map_err(|_| invalid())?;
+
+        let shared = Arc::new(Shared {
+            rng: Mutex::new(StdRng::seed_from_u64(chaos.seed)),
+            chaos,
+            upstream: format!("{}:{}", host, port),
+            counters: Counters::default(),
+        });
+        let cancel = CancellationToken::new();
+        tokio::spawn(serve(listener, shared.clone(), cancel.clone()));
+        Ok(Self {
+            url: url.as_str().trim_end_matches('/').to_string(),
+            shared,
+            cancel,
+        })
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    pub fn upstream(&self) -> &str {
+        &self.shared.upstream
+    }
+
+    pub fn counts(&self) -> ChaosCounts {
+        let counters = &self.shared.counters;
+        ChaosCounts {
+            delayed: counters.delayed.load(Ordering::Relaxed),
+            dropped: counters.dropped.load(Ordering::Relaxed),
+            truncated: counters.truncated.load(Ordering::Relaxed),
+        }
+    }
+
+    // Stops accepting connections; those already open carry on.
+    pub fn stop(&self) {
+        self.cancel.cancel();
+    }
+}
+
+impl Drop for ChaosProxy {
+    fn drop(&mut self) {
+        self.cancel.cancel();
+    }
+}
+
+async fn serve(listener: TcpListener, shared: Arc<Shared>, cancel: CancellationToken) {
+    loop {
+        let client = tokio::select! {
+            _ = cancel.cancelled() => return,
+            accepted = listener.accept() => match accepted {
+                Ok((client, _)) => client,
+                Err(err) => {
+                    tracing::warn!("chaos proxy: {}", err);
+                    continue;
+                }
+            },
+        };
+        tokio::spawn(relay(client, shared.clone()));
+    }
+}
+
+async fn relay(client: TcpStream, shared: Arc<Shared>) {
+    let server = match TcpStream::connect(&shared.upstream).await {
+        Ok(server) => server,
+        // The client sees its connection closed, as it would the server's.
+        Err(err) => {
+            tracing::debug!("chaos proxy: {}: {}", shared.upstream, err);
+            return;
+        }
+    };
+    let (mut client_read, mut client_write) = client.into_split();
+    let (mut server_read, mut server_write) = server.into_split();
+    // Set whenever the client has sent something since the last response
+    // began, so each response of a keep-alive connection gets its own roll.
+    let requested = Arc::new(AtomicBool::new(false));
+
+    let flag = requested.clone();
+    let upload = tokio::spawn(async move {
+        let mut buf = [0; 8192];
+        loop {
+            match client_read.read(&mut buf).await {
+                Ok(0) | Err(_) => break,
+                Ok(n) => {
+                    flag.store(true, Ordering::SeqCst);
+                    if server_write.write_all(&buf[..n]).await.is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+        let _ = server_write.shutdown().await;
+    });
+
+    let mut buf = vec![0; 8192];
+    loop {
+        let n = match server_read.read(&mut buf).await {
+            Ok(0) | Err(_) => break,
+            Ok(n) => n,
+        };
+        let mut sent = n;
+        if requested.swap(false, Ordering::SeqCst) {
+            let fault = {
+                let mut rng = shared.rng.lock().unwrap();
+                shared.chaos.roll(&mut rng)
+            };
+            let counters = &shared.counters;
+            match fault {
+                Fault::Drop => {
+                    counters.dropped.fetch_add(1, Ordering::Relaxed);
+                    tracing::debug!("chaos proxy: dropped a response from {}", shared.upstream);
+                    break;
+                }
+                Fault::Delay(delay) | Fault::Truncate(delay) if !delay.is_zero() => {
+                    counters.delayed.fetch_add(1, Ordering::Relaxed);
+                    tokio::time::sleep(delay).await;
+                }
+                _ => {}
+            }
+            if let Fault::Truncate(_) = fault {
+                counters.truncated.fetch_add(1, Ordering::Relaxed);
+                tracing::debug!("chaos proxy: truncated a response from {}", shared.upstream);
+                sent = n / 2;
+            }
+        }
+        if client_write.write_all(&buf[..sent]).await.is_err() || sent < n {
+            break;
+        }
+    }
+    upload.abort();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn faults_follow_the_rates_and_the_seed() {
+        let chaos = Chaos::new(1)
+            .with_latency(Duration::from_millis(10))
+            .with_jitter(Duration::from_millis(5))
+            .with_drop_rate(0.2)
+            .with_truncate_rate(0.3);
+        let mut rng = StdRng::seed_from_u64(chaos.seed);
+        let (mut dropped, mut truncated) = (0, 0);
+        for _ in 0..1000 {
+            match chaos.roll(&mut rng) {
+                Fault::Drop => dropped += 1,
+                Fault::Truncate(delay) => {
+                    truncated += 1;
+                    assert!(
+                        delay >= Duration::from_millis(10) && delay <= Duration::from_millis(15)
+                    );
+                }
+                Fault::Delay(delay) => {
+                    assert!(
+                        delay >= Duration::from_millis(10) && delay <= Duration::from_millis(15)
+                    );
+                }
+            }
+        }
+        assert!((150..250).contains(&dropped), "{}", dropped);
+        assert!((230..370).contains(&truncated), "{}", truncated);
+
+        let rolls = |seed| {
+            let mut rng = StdRng::seed_from_u64(seed);
+            (0..20)
+                .map(|_| format!("{:?}", chaos.roll(&mut rng)))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(rolls(3), rolls(3));
+        assert_eq!(Chaos::new(0).with_drop_rate(7.0).drop_rate, 1.0);
+    }
+
+    #[tokio::test]
+    async fn only_http_urls_with_a_host_can_be_proxied() {
+        for url in ["ftp://example.com", "not a url", "unix:/tmp/socket"] {
+            assert!(matches!(
+                ChaosProxy::start(url, Chaos::new(0)),
+                Err(ChaosError::InvalidUrl(_))
+            ));
+        }
+        let proxy = ChaosProxy::start("https://example.com/api/", Chaos::new(0)).unwrap();
+        assert_eq!(proxy.upstream(), "example.com:443");
+        assert!(proxy.url().starts_with("https://127.0.0.1:"));
+        assert!(proxy.url().ends_with("/api"));
+    }
+}
@@ -0,0 +1,1845 @@
+use crate::catalogue::{Catalogue, InvalidInput, Rule};
+use crate::chaos::{Chaos, ChaosError, ChaosProxy};
+use crate::corpus::CorpusError;
+use crate::fuzz::generator::{GeneratorProfile, ProfilePreset};
+use crate::fuzz::presets::Preset;
+use crate::fuzz::scheduler::{Schedule, Weights};
+use crate::fuzz::template::TemplateError;
+use crate::learn::{LearnError, LearnedSchemas};
+use crate::logging::LogFormat;
+use crate::models::auth::{AuthStrategy, BearerHeader, BodyField, Header};
+use crate::models::body::BodyKind;
+use crate::models::client::{HttpVersion, TestCase, Tester};
+use crate::models::compare::{
+    ArrayMode, ComparisonMode, FieldAnnotation, NonJsonMode, NumberRange, NumericOptions,
+};
+use crate::models::endpoints::ApiVersion;
+use crate::models::hooks::{clear_case, Hooks, CLEAR_ENDPOINT};
+use crate::models::multi::MultiTester;
+use crate::models::network::Network;
+use crate::models::oracle::{Expectation, StaticOracle};
+use crate::models::pointer::PointerPattern;
+use crate::models::retry::RetryPolicy;
+use crate::schema::{ResponseSchemas, Schema, SchemaError, SchemaRegistry, SchemaSource};
+use crate::spec::{Spec, SpecError};
+use crate::teardown::Teardown;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::Method;
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("Failed to read config: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Failed to parse TOML config: {0}")]
+    Toml(#[from] toml::de::Error),
+
+    #[error("Failed to parse YAML config: {0}")]
+    Yaml(#[from] serde_yaml::Error),
+
+    #[error("Unsupported config format `{0}` (expected .toml, .yaml or .yml)")]
+    UnsupportedFormat(String),
+
+    #[error("Failed to load corpus: {0}")]
+    Corpus(#[from] CorpusError),
+
+    #[error("Failed to load spec: {0}")]
+    Spec(#[from] SpecError),
+
+    #[error("Invalid response schema: {0}")]
+    Schema(#[from] SchemaError),
+
+    #[error("Invalid HTTP method `{0}`")]
+    InvalidMethod(String),
+
+    #[error("Invalid header `{0}`")]
+    InvalidHeader(String),
+
+    #[error("Invalid `{0}`: {1}")]
+    InvalidValue(&'static str, String),
+
+    #[error("`candidates` are marked against `server_url`, which is not set")]
+    NoReference,
+
+    #[error("No server under test: set `test_url` or `[project]` in the config, {ENV_TEST_URL} or --test-url")]
+    NoTestUrl,
+
+    #[error("Invalid value {1:?} for {0}")]
+    InvalidEnv(&'static str, String),
+
+    #[error("Invalid line {0} in .env file")]
+    InvalidDotenv(usize),
+
+    #[error("Invalid template in `endpoints`: {0}")]
+    Template(#[from] TemplateError),
+
+    #[error("Invalid project config `{}`: {message}", path.display())]
+    ProjectConfig { path: PathBuf, message: String },
+
+    #[error("Invalid input `{0}` in `errors` (expected missing_token, invalid_token, unknown_quiz, foreign_quiz or field:<name>)")]
+    InvalidInput(String),
+
+    #[error("Failed to load learned schemas: {0}")]
+    Learned(#[from] LearnError),
+
+    #[error("Invalid root certificate `{}`: {message}", path.display())]
+    Certificate { path: PathBuf, message: String },
+
+    #[error("Invalid `[chaos]`: {0}")]
+    InvalidChaos(String),
+
+    #[error(transparent)]
+    Chaos(#[from] ChaosError),
+
+    #[error("Invalid proxy `{url}`: {source}")]
+    Proxy {
+        url: String,
+        #[source]
+        source: reqwest::Error,
+    },
+}
+
+// Environment variables that override the config file; see `Config::resolve`.
+pub const ENV_TEST_URL: &str = "FUZZER_TEST_URL";
+pub const ENV_SERVER_URL: &str = "FUZZER_SERVER_URL";
+pub const ENV_SEED: &str = "FUZZER_SEED";
+pub const ENV_TOKEN: &str = "FUZZER_TOKEN";
+const ENV_VARS: [&str; 4] = [ENV_TEST_URL, ENV_SERVER_URL, ENV_SEED, ENV_TOKEN];
+
+// Values given on the command line, which win over every other layer.
+#[derive(Debug, Clone, Default)]
+pub struct Overrides {
+    pub test_url: Option<String>,
+    pub server_url: Option<String>,
+    pub seed: Option<u64>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Config {
+    // May be left out of the file when `project`, the environment or the
+    // command line sets it.
+    #[serde(default)]
+    pub test_url: String,
+    // The reference server. Without one, responses are checked against each
+    // endpoint's `expect_status` and the spec instead.
+    #[serde(default)]
+    pub server_url: Option<String>,
+    // Servers marked against `server_url` in place of `test_url`, by name,
+    // e.g. one per student submission.
+    #[serde(default)]
+    pub candidates: BTreeMap<String, String>,
+    #[serde(default)]
+    pub spec: Option<PathBuf>,
+    // Sends every path under `/v1` or `/v2` and, unless `auth.location` says
+    // otherwise, puts the token where that version expects it.
+    #[serde(default)]
+    pub api_version: Option<ApiVersion>,
+    #[serde(default)]
+    pub endpoints: Vec<EndpointConfig>,
+    #[serde(default = "default_iterations")]
+    pub iterations: usize,
+    // Fixes every random choice made during the run; a random seed is picked
+    // (and printed) when absent.
+    #[serde(default)]
+    pub seed: Option<u64>,
+    #[serde(default)]
+    pub shuffle: bool,
+    // How targets are picked each iteration.
+    #[serde(default)]
+    pub schedule: Schedule,
+    // Relative weights for the weighted schedule, keyed by `METHOD /path` or
+    // just `/path` for every method.
+    #[serde(default)]
+    pub weights: BTreeMap<String, f64>,
+    // Fraction of cases sent with a deliberately malformed body.
+    #[serde(default)]
+    pub protocol_fuzzing: Option<f64>,
+    // Fraction of cases sent with the token's header perturbed.
+    #[serde(default)]
+    pub header_fuzzing: Option<f64>,
+    // Fraction of cases sent with a path parameter replaced by an adversarial
+    // value.
+    #[serde(default)]
+    pub path_fuzzing: Option<f64>,
+    // Also send every unsupported method to each known path and compare how
+    // the servers reject them.
+    #[serde(default)]
+    pub probe_methods: bool,
+    // Report 5xx responses and dropped connections from the server under test
+    // as crashes, saving their inputs to the corpus even when both servers agree.
+    #[serde(default)]
+    pub crash_detection: bool,
+    // Upper bound on mutations applied to an example body per case.
+    #[serde(default = "default_max_mutations")]
+    pub max_mutations: usize,
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    #[serde(default = "default_concurrency")]
+    pub concurrency: usize,
+    // Maximum requests per second sent to each server, and how many may be
+    // sent at once before that rate applies.
+    #[serde(default)]
+    pub rate_limit: Option<f64>,
+    #[serde(default = "default_rate_limit_burst")]
+    pub rate_limit_burst: u32,
+    // Seconds before a single request is abandoned and reported as timed out.
+    #[serde(default)]
+    pub timeout: Option<f64>,
+    // Bytes of a response body read before it is reported as too large.
+    #[serde(default)]
+    pub max_body_size: Option<usize>,
+    // `run` compares JSON bodies longer than this many bytes as they arrive
+    // instead of reading them first; see `Tester::with_streaming`.
+    #[serde(default)]
+    pub stream_above: Option<usize>,
+    // Fail the run when the server under test is more than this many times
+    // slower (by median latency) than the reference on any endpoint.
+    #[serde(default)]
+    pub max_slowdown: Option<f64>,
+    // Seconds before the whole run is stopped.
+    #[serde(default)]
+    pub run_timeout: Option<f64>,
+    // Cases sent in all, however many iterations that leaves.
+    #[serde(default)]
+    pub max_cases: Option<usize>,
+    // Failed or crashed cases before the run stops; `fail_fast` stops at the
+    // first.
+    #[serde(default)]
+    pub max_findings: Option<usize>,
+    #[serde(default)]
+    pub fail_fast: bool,
+    #[serde(default)]
+    pub retry: RetryConfig,
+    // Session token added to every request.
+    #[serde(default)]
+    pub auth: Option<AuthConfig>,
+    // Directory that failing cases are written to for later replay.
+    #[serde(default)]
+    pub corpus: Option<PathBuf>,
+    // Where `run` saves how far it got after every iteration, for picking an
+    // interrupted run up again with `run --resume`.
+    #[serde(default)]
+    pub campaign: Option<PathBuf>,
+    // Findings of an earlier run, saved with `run --update-baseline`; runs
+    // then only report findings that are new or fixed since.
+    #[serde(default)]
+    pub baseline: Option<PathBuf>,
+    // Directory that every failure and crash is dumped to, a subdirectory per
+    // finding with the exact bytes exchanged and a `repro.json` for replay.
+    #[serde(default)]
+    pub artifacts: Option<PathBuf>,
+    // Directory of recorded reference responses for `fuzzer1531 snapshot`.
+    #[serde(default)]
+    pub snapshots: Option<PathBuf>,
+    // Minimize failing bodies before reporting and recording them.
+    #[serde(default)]
+    pub shrink: bool,
+    // Rerun diverging cases and only report those that keep diverging; off
+    // unless this table is present.
+    #[serde(default)]
+    pub flaky: Option<FlakyConfig>,
+    #[serde(default)]
+    pub comparison: ComparisonMode,
+    // How arrays are compared under `types_only`.
+    #[serde(default)]
+    pub arrays: ArrayMode,
+    // How responses that aren't JSON (images, HTML, plain text) are compared.
+    #[serde(default)]
+    pub non_json: NonJsonMode,
+    // JSON pointers skipped during comparison; `*` matches any key or index.
+    #[serde(default)]
+    pub ignore: Vec<PointerPattern>,
+    // Fields that may be null on one server and absent on the other.
+    #[serde(default)]
+    pub optional: Vec<PointerPattern>,
+    // Keep null and absent distinct even for `optional` fields.
+    #[serde(default)]
+    pub strict_optional: bool,
+    // Response headers that must match between the two servers.
+    #[serde(default)]
+    pub compare_headers: Vec<String>,
+    // Keep a cookie jar per server, for servers with cookie-based sessions.
+    #[serde(default)]
+    pub cookies: bool,
+    // "auto" (the default), "http1" or "http2" (prior knowledge).
+    #[serde(default)]
+    pub http_version: HttpVersion,
+    // Proxies and certificates, separately for each server.
+    #[serde(default)]
+    pub network: NetworkConfig,
+    // Removes what `run` created on both servers once it is over; none by
+    // default.
+    #[serde(default)]
+    pub teardown: Option<Teardown>,
+    // Response schemas saved by `learn`, which responses must then fit where
+    // no `[endpoints.responses]` says otherwise.
+    #[serde(default)]
+    pub learned_schemas: Option<PathBuf>,
+    // Consult the quiz API's endpoint registry; see `Tester::with_registry`.
+    #[serde(default)]
+    pub registry: bool,
+    // Set-Cookie names and attributes (not values) must match between servers.
+    #[serde(default)]
+    pub compare_cookies: bool,
+    // Read JSON bodies strictly: duplicate keys, `NaN`/`Infinity` and trailing
+    // data must appear on both servers or neither.
+    #[serde(default)]
+    pub strict_json: bool,
+    // Route requests through local chaos proxies that delay, drop and
+    // truncate responses; off unless this table is present.
+    #[serde(default)]
+    pub chaos: Option<ChaosConfig>,
+    // Send GET, PUT and DELETE cases twice in a row to each server and compare
+    // the repeats as well.
+    #[serde(default)]
+    pub idempotency_checks: bool,
+    #[serde(default)]
+    pub numbers: NumbersConfig,
+    // Special comparisons by JSON pointer, such as timestamps compared against
+    // the request time; `*` matches any key or index.
+    #[serde(default)]
+    pub fields: BTreeMap<String, FieldAnnotation>,
+    #[serde(default)]
+    pub report: ReportConfig,
+    // Structured logs of every request; off unless this table is present.
+    #[serde(default)]
+    pub log: Option<LogConfig>,
+    #[serde(default)]
+    pub hooks: HooksConfig,
+    #[serde(default)]
+    pub stateful: StatefulConfig,
+    // Wait for the servers to answer a health check before starting; off
+    // unless this table is present.
+    #[serde(default)]
+    pub ready: Option<ReadyConfig>,
+    // Status codes for `fuzzer1531 errors`, added to or replacing the quiz
+    // API's built-in catalogue.
+    #[serde(default)]
+    pub errors: Vec<ErrorRuleConfig>,
+    // YAML scenario files for `fuzzer1531 scenarios`.
+    #[serde(default)]
+    pub scenarios: Vec<PathBuf>,
+    // Scenarios for `fuzzer1531 scenarios` generated from which of the spec's
+    // responses feed which requests; off unless this table is present.
+    #[serde(default)]
+    pub infer: Option<InferConfig>,
+    // Sizes and edge-case rate of generated values, for every endpoint
+    // without a `generator` of its own.
+    #[serde(default)]
+    pub generator: GeneratorConfig,
+    // Where the server under test binds, read from the student repo when
+    // `test_url` isn't set; off unless this table is present.
+    #[serde(default)]
+    pub project: Option<ProjectConfig>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ProjectConfig {
+    // The repo's `config.json`, with a `port` (number or string) and
+    // optionally a `url` such as `http://localhost`.
+    #[serde(default = "default_project_config")]
+    pub config: PathBuf,
+    // Appended to the URL, for servers mounted under a prefix.
+    #[serde(default)]
+    pub base_path: String,
+}
+
+fn default_project_config() -> PathBuf {
+    PathBuf::from("config.json")
+}
+
+// The course's `src/config.json` format.
+#[derive(Debug, Deserialize)]
+struct ProjectFile {
+    port: Value,
+    #[serde(default)]
+    url: Option<String>,
+}
+
+impl ProjectConfig {
+    pub fn base_url(&self) -> Result<String, ConfigError> {
+        let invalid = |message: String| ConfigError::ProjectConfig {
+            path: self.config.clone(),
+            message,
+        };
+        let source =
+            std::fs::read_to_string(&self.config).map_err(|err| invalid(err.to_string()))?;
+        let file: ProjectFile =
+            serde_json::from_str(&source).map_err(|err| invalid(err.to_string()))?;
+        let port = match &file.port {
+            Value::Number(port) => port.as_u64().and_then(|port| u16::try_from(port).ok()),
+            Value::String(port) => port.trim().parse::<u16>().ok(),
+            _ => None,
+        }
+        .ok_or_else(|| invalid(format!("`port` must be a port number, not {}", file.port)))?;
+        let url = file.url.as_deref().unwrap_or("http://127.0.0.1");
+        let base_path = self.base_path.trim_end_matches('/');
+        let separator = if base_path.is_empty() || base_path.starts_with('/') {
+            ""
+        } else {
+            "/"
+        };
+        Ok(format!(
+            "{}:{}{}{}",
+            url.trim_end_matches('/'),
+            port,
+            separator,
+            base_path
+        ))
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ErrorRuleConfig {
+    pub method: String,
+    pub path: String,
+    // `missing_token`, `invalid_token`, `unknown_quiz`, `foreign_quiz` or
+    // `field:<name>`.
+    pub input: String,
+    pub status: u16,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct FlakyConfig {
+    #[serde(default = "default_flaky_reruns")]
+    pub reruns: usize,
+    // Reruns that must diverge again for the case to be reported; the rest
+    // are labelled flaky.
+    #[serde(default = "default_flaky_reproduce")]
+    pub reproduce: usize,
+}
+
+fn default_flaky_reruns() -> usize {
+    3
+}
+
+fn default_flaky_reproduce() -> usize {
+    2
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct NetworkConfig {
+    // The server under test, and candidates.
+    #[serde(default)]
+    pub test: ServerNetwork,
+    // The reference server.
+    #[serde(default)]
+    pub server: ServerNetwork,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ServerNetwork {
+    // For both HTTP and HTTPS, e.g. `http://proxy.example.com:3128`.
+    #[serde(default)]
+    pub proxy: Option<String>,
+    // PEM files, each holding one or more certificates to trust along with the
+    // system's.
+    #[serde(default)]
+    pub root_certificates: Vec<PathBuf>,
+    // Accept any certificate at all, e.g. a self-signed one.
+    #[serde(default)]
+    pub danger_accept_invalid_certs: bool,
+}
+
+impl ServerNetwork {
+    pub fn network(&self) -> Result<Network, ConfigError> {
+        let mut network = Network::new();
+        if let Some(url) = &self.proxy {
+            let proxy = reqwest::Proxy::all(url).map_err(|source| ConfigError::Proxy {
+                url: url.clone(),
+                source,
+            })?;
+            network = network.with_proxy(proxy);
+        }
+        for path in &self.root_certificates {
+            let invalid = |message: String| ConfigError::Certificate {
+                path: path.clone(),
+                message,
+            };
+            let pem = std::fs::read(path).map_err(|err| invalid(err.to_string()))?;
+            let certificates = reqwest::Certificate::from_pem_bundle(&pem)
+                .map_err(|err| invalid(err.to_string()))?;
+            if certificates.is_empty() {
+                return Err(invalid("no PEM certificates in the file".to_string()));
+            }
+            for certificate in certificates {
+                network = network.with_root_certificate(certificate);
+            }
+        }
+        if self.danger_accept_invalid_certs {
+            network = network.with_invalid_certs();
+        }
+        Ok(network)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ChaosConfig {
+    // Seconds every response is held back, plus up to `jitter` more at random.
+    #[serde(default)]
+    pub latency: f64,
+    #[serde(default)]
+    pub jitter: f64,
+    // Chances per response, from 0 to 1, of closing the connection instead of
+    // sending it and of sending only part of it.
+    #[serde(default)]
+    pub drop_rate: f64,
+    #[serde(default)]
+    pub truncate_rate: f64,
+    // Put the reference server behind a proxy as well, so both servers see
+    // the same faults.
+    #[serde(default = "default_chaos_reference")]
+    pub reference: bool,
+    // The run's seed when unset.
+    #[serde(default)]
+    pub seed: Option<u64>,
+}
+
+impl ChaosConfig {
+    pub fn chaos(&self, seed: u64) -> Result<Chaos, ConfigError> {
+        let seconds = |name: &str, value: f64| {
+            if value.is_finite() && value >= 0.0 {
+                Ok(Duration::from_secs_f64(value))
+            } else {
+                Err(ConfigError::InvalidChaos(format!(
+                    "`{}` must be a number of seconds, not {}",
+                    name, value
+                )))
+            }
+        };
+        for (name, rate) in [
+            ("drop_rate", self.drop_rate),
+            ("truncate_rate", self.truncate_rate),
+        ] {
+            if !(0.0..=1.0).contains(&rate) {
+                return Err(ConfigError::InvalidChaos(format!(
+                    "`{}` must be between 0 and 1, not {}",
+                    name, rate
+                )));
+            }
+        }
+        Ok(Chaos::new(self.seed.unwrap_or(seed))
+            .with_latency(seconds("latency", self.latency)?)
+            .with_jitter(seconds("jitter", self.jitter)?)
+            .with_drop_rate(self.drop_rate)
+            .with_truncate_rate(self.truncate_rate))
+    }
+}
+
+fn default_chaos_reference() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ReadyConfig {
+    // Seconds to keep polling before giving up.
+    #[serde(default = "default_ready_timeout")]
+    pub timeout: f64,
+    // Polled with GET (or `method`) until it answers with a 2xx status. When
+    // unset, any answer to `GET /` counts as up.
+    #[serde(default)]
+    pub path: Option<String>,
+    #[serde(default)]
+    pub method: Option<String>,
+}
+
+fn default_ready_timeout() -> f64 {
+    30.0
+}
+
+impl ReadyConfig {
+    pub fn timeout(&self) -> Duration {
+        Duration::from_secs_f64(self.timeout)
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ReportConfig {
+    // Where to write a standalone HTML summary of the run.
+    #[serde(default)]
+    pub html: Option<PathBuf>,
+    // Where to write JUnit XML for CI test result views.
+    #[serde(default)]
+    pub junit: Option<PathBuf>,
+    // Where to write every request and response of the run as a HAR file.
+    #[serde(default)]
+    pub har: Option<PathBuf>,
+    // Where to write one row per case, as CSV and as JSON Lines.
+    #[serde(default)]
+    pub csv: Option<PathBuf>,
+    #[serde(default)]
+    pub jsonl: Option<PathBuf>,
+    // Where to write request counts and latencies in Prometheus' text format,
+    // e.g. for node_exporter's textfile collector.
+    #[serde(default)]
+    pub prometheus: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuthLocation {
+    // A body field, or a query parameter for GET and DELETE (iteration 2).
+    Body,
+    // A header named after `name` (iteration 3).
+    Header,
+    // `Authorization: Bearer <token>`.
+    Bearer,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct AuthConfig {
+    // Defaults to the API version's location, or `body` without a version.
+    #[serde(default)]
+    pub location: Option<AuthLocation>,
+    // Body field or header name; unused for `bearer`.
+    #[serde(default = "default_auth_name")]
+    pub name: String,
+    pub token: String,
+}
+
+impl AuthConfig {
+    pub fn strategy(
+        &self,
+        version: Option<ApiVersion>,
+    ) -> Result<Arc<dyn AuthStrategy>, ConfigError> {
+        let location = match (self.location, version) {
+            (Some(location), _) => location,
+            (None, Some(version)) => return Ok(version.auth_strategy()),
+            (None, None) => AuthLocation::Body,
+        };
+        Ok(match location {
+            AuthLocation::Body => Arc::new(BodyField(self.name.clone())),
+            AuthLocation::Header => Arc::new(Header(
+                HeaderName::from_bytes(self.name.as_bytes())
+                    .map_err(|_| ConfigError::InvalidHeader(self.name.clone()))?,
+            )),
+            AuthLocation::Bearer => Arc::new(BearerHeader),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct NumbersConfig {
+    // Tell integers and floats apart, so `3.5` (or `3.0`) against `3` fails.
+    #[serde(default)]
+    pub strict_integers: bool,
+    // Numbers under keys ending in `Id` must be non-negative integers.
+    #[serde(default)]
+    pub non_negative_ids: bool,
+    // Allowed `min`/`max` per JSON pointer; `*` matches any key or index.
+    #[serde(default)]
+    pub ranges: BTreeMap<String, NumberRange>,
+}
+
+impl NumbersConfig {
+    pub fn options(&self) -> NumericOptions {
+        NumericOptions {
+            strict_integers: self.strict_integers,
+            non_negative_ids: self.non_negative_ids,
+            ranges: self
+                .ranges
+                .iter()
+                .map(|(path, range)| (PointerPattern::parse(path), *range))
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct LogConfig {
+    #[serde(default)]
+    pub format: LogFormat,
+    // A `tracing` filter such as `debug` or `fuzzer1531=debug`.
+    #[serde(default = "default_log_level")]
+    pub level: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct RetryConfig {
+    // Retries after a connection failure, or after a timeout for requests that
+    // are safe to repeat; none by default.
+    #[serde(default)]
+    pub max_retries: u32,
+    // Seconds before the first retry, doubling on each one after.
+    #[serde(default = "default_initial_backoff")]
+    pub initial_backoff: f64,
+    #[serde(default = "default_max_backoff")]
+    pub max_backoff: f64,
+    // Retries after a 429 Too Many Requests, waiting for `Retry-After` when the
+    // server sends one.
+    #[serde(default = "default_max_rate_limited")]
+    pub max_rate_limited: u32,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            max_retries: 0,
+            initial_backoff: default_initial_backoff(),
+            max_backoff: default_max_backoff(),
+            max_rate_limited: default_max_rate_limited(),
+        }
+    }
+}
+
+impl RetryConfig {
+    pub fn policy(&self) -> RetryPolicy {
+        RetryPolicy::new(self.max_retries)
+            .with_initial_backoff(Duration::from_secs_f64(self.initial_backoff))
+            .with_max_backoff(Duration::from_secs_f64(self.max_backoff))
+            .with_max_rate_limited(self.max_rate_limited)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct HooksConfig {
+    // Call `clear_endpoint` on both servers before every case. Disable for
+    // scenarios that depend on state built up by earlier cases.
+    #[serde(default = "default_clear")]
+    pub clear: bool,
+    #[serde(default = "default_clear_endpoint")]
+    pub clear_endpoint: String,
+    #[serde(default)]
+    pub before: Vec<HookConfig>,
+    #[serde(default)]
+    pub after: Vec<HookConfig>,
+}
+
+impl Default for HooksConfig {
+    fn default() -> Self {
+        Self {
+            clear: default_clear(),
+            clear_endpoint: default_clear_endpoint(),
+            before: Vec::new(),
+            after: Vec::new(),
+        }
+    }
+}
+
+// Sizes for `fuzzer1531 model`, which checks server state against a model.
+#[derive(Debug, Clone, Deserialize)]
+pub struct StatefulConfig {
+    // Independent runs, each starting from a cleared server.
+    #[serde(default = "default_sequences")]
+    pub sequences: usize,
+    // Operations per run.
+    #[serde(default = "default_steps")]
+    pub steps: usize,
+    // Rounds of `fuzzer1531 race`, each one pair of conflicting operations.
+    #[serde(default = "default_races")]
+    pub races: usize,
+}
+
+impl Default for StatefulConfig {
+    fn default() -> Self {
+        Self {
+            sequences: default_sequences(),
+            steps: default_steps(),
+            races: default_races(),
+        }
+    }
+}
+
+// A profile preset (`smoke`, `default` or `aggressive`) with any of its
+// settings overridden.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct GeneratorConfig {
+    // The run's profile when left out of an endpoint's table, and `default`
+    // when left out of the run's.
+    #[serde(default)]
+    pub profile: Option<ProfilePreset>,
+    #[serde(default)]
+    pub max_string_len: Option<usize>,
+    #[serde(default)]
+    pub max_array_len: Option<usize>,
+    #[serde(default)]
+    pub max_object_fields: Option<usize>,
+    #[serde(default)]
+    pub max_depth: Option<usize>,
+    #[serde(default)]
+    pub edge_rate: Option<f64>,
+}
+
+impl GeneratorConfig {
+    pub fn profile(&self, base: &GeneratorProfile) -> GeneratorProfile {
+        let base = self
+            .profile
+            .map_or_else(|| base.clone(), |preset| preset.profile());
+        GeneratorProfile {
+            max_string_len: self.max_string_len.unwrap_or(base.max_string_len),
+            max_array_len: self.max_array_len.unwrap_or(base.max_array_len),
+            max_object_fields: self.max_object_fields.unwrap_or(base.max_object_fields),
+            max_depth: self.max_depth.unwrap_or(base.max_depth),
+            // `clamp` keeps NaN, which `gen_bool` panics on.
+            edge_rate: match self.edge_rate.unwrap_or(base.edge_rate) {
+                rate if rate.is_nan() => 0.0,
+                rate => rate.clamp(0.0, 1.0),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct InferConfig {
+    #[serde(default = "default_inferred_scenarios")]
+    pub scenarios: usize,
+    // Calls per scenario, fewer if nothing else can be called.
+    #[serde(default = "default_inferred_steps")]
+    pub steps: usize,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct HookConfig {
+    pub method: String,
+    pub path: String,
+    #[serde(default)]
+    pub body: Option<Value>,
+    #[serde(default)]
+    pub body_kind: BodyKind,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ResponsesConfig {
+    #[serde(default)]
+    pub success: Option<SchemaSource>,
+    #[serde(default)]
+    pub error: Option<SchemaSource>,
+}
+
+impl ResponsesConfig {
+    pub fn schemas(&self) -> Result<ResponseSchemas, ConfigError> {
+        Ok(ResponseSchemas {
+            success: self
+                .success
+                .as_ref()
+                .map(SchemaSource::to_schema)
+                .transpose()?,
+            error: self
+                .error
+                .as_ref()
+                .map(SchemaSource::to_schema)
+                .transpose()?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct EndpointConfig {
+    pub method: String,
+    pub path: String,
+    // Contracts checked against each server's responses on their own.
+    #[serde(default)]
+    pub responses: ResponsesConfig,
+    // Status the server under test must return when there is no `server_url`.
+    #[serde(default)]
+    pub expect_status: Option<u16>,
+    // Query parameters sent with every case, e.g. `{ token = "..." }`.
+    #[serde(default)]
+    pub query: BTreeMap<String, String>,
+    // A fixed body sent on every iteration instead of a generated one.
+    #[serde(default)]
+    pub body: Option<Value>,
+    // How the body is encoded: json (default), form, multipart or raw.
+    #[serde(default)]
+    pub body_kind: BodyKind,
+    // Known-good bodies that are mutated to produce each case.
+    #[serde(default)]
+    pub examples: Vec<Value>,
+    // Body fields generated from a boundary-value preset, overriding the spec
+    // schema for that field (or forming the whole body when there is no spec).
+    #[serde(default)]
+    pub presets: BTreeMap<String, Preset>,
+    // Overrides the run's `[generator]` for this endpoint's cases.
+    #[serde(default)]
+    pub generator: Option<GeneratorConfig>,
+}
+
+fn default_iterations() -> usize {
+    1
+}
+
+fn default_max_mutations() -> usize {
+    3
+}
+
+fn default_concurrency() -> usize {
+    1
+}
+
+fn default_rate_limit_burst() -> u32 {
+    1
+}
+
+fn default_initial_backoff() -> f64 {
+    0.1
+}
+
+fn default_max_backoff() -> f64 {
+    5.0
+}
+
+fn default_max_rate_limited() -> u32 {
+    3
+}
+
+fn default_log_level() -> String {
+    "info".to_string()
+}
+
+fn default_auth_name() -> String {
+    "token".to_string()
+}
+
+fn default_sequences() -> usize {
+    10
+}
+
+fn default_steps() -> usize {
+    20
+}
+
+fn default_inferred_scenarios() -> usize {
+    10
+}
+
+fn default_inferred_steps() -> usize {
+    8
+}
+
+fn default_races() -> usize {
+    20
+}
+
+fn default_clear() -> bool {
+    true
+}
+
+fn default_clear_endpoint() -> String {
+    CLEAR_ENDPOINT.to_string()
+}
+
+impl Config {
+    // With `[chaos]`, starts a proxy in front of the server under test (and
+    // the reference, unless `reference = false`) and points the config at
+    // it. The proxies run until they are dropped, within the Tokio runtime
+    // this is called in.
+    pub fn start_chaos(&mut self) -> Result<Vec<ChaosProxy>, ConfigError> {
+        let Some(chaos) = &self.chaos else {
+            return Ok(Vec::new());
+        };
+        let settings = chaos.chaos(self.seed.unwrap_or_else(rand::random))?;
+        let mut proxies = vec![ChaosProxy::start(&self.test_url, settings.clone())?];
+        self.test_url = proxies[0].url().to_string();
+        if let Some(server_url) = self.server_url.as_mut().filter(|_| chaos.reference) {
+            let proxy = ChaosProxy::start(server_url, settings)?;
+            *server_url = proxy.url().to_string();
+            proxies.push(proxy);
+        }
+        Ok(proxies)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        let path = path.as_ref();
+        let source = std::fs::read_to_string(path)?;
+        let mut config: Config = match path.extension().and_then(|e| e.to_str()) {
+            Some("toml") => toml::from_str(&source)?,
+            Some("yaml") | Some("yml") => serde_yaml::from_str(&source)?,
+            other => {
+                return Err(ConfigError::UnsupportedFormat(
+                    other.unwrap_or("").to_string(),
+                ))
+            }
+        };
+
+        // Paths are relative to the config file, not the working directory.
+        if let Some(dir) = path.parent() {
+            let paths = [
+                &mut config.spec,
+                &mut config.corpus,
+                &mut config.campaign,
+                &mut config.baseline,
+                &mut config.artifacts,
+                &mut config.snapshots,
+                &mut config.learned_schemas,
+                &mut config.report.html,
+                &mut config.report.junit,
+                &mut config.report.har,
+                &mut config.report.csv,
+                &mut config.report.jsonl,
+                &mut config.report.prometheus,
+            ];
+            let project = config.project.as_mut().map(|project| &mut project.config);
+            let network = &mut config.network;
+            let paths = paths
+                .into_iter()
+                .flatten()
+                .chain(&mut config.scenarios)
+                .chain(&mut network.test.root_certificates)
+                .chain(&mut network.server.root_certificates)
+                .chain(project);
+            for path in paths {
+                if path.is_relative() {
+                    *path = dir.join(&*path);
+                }
+            }
+        }
+        if let (true, Some(project)) = (config.test_url.is_empty(), &config.project) {
+            config.test_url = project.base_url()?;
+        }
+
+        config.validate()?;
+        Ok(config)
+    }
+
+    // Numbers that would otherwise panic or misbehave once the run starts;
+    // `load` checks them already.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if let Some(rps) = self
+            .rate_limit
+            .filter(|rps| !(rps.is_finite() && *rps > 0.0))
+        {
+            return Err(ConfigError::InvalidValue(
+                "rate_limit",
+                format!(
+                    "expected a positive number of requests per second, not {}",
+                    rps
+                ),
+            ));
+        }
+        if let Some(ratio) = self
+            .max_slowdown
+            .filter(|ratio| !(ratio.is_finite() && *ratio > 0.0))
+        {
+            return Err(ConfigError::InvalidValue(
+                "max_slowdown",
+                format!("expected a positive ratio, not {}", ratio),
+            ));
+        }
+        let mut durations = vec![
+            ("retry.initial_backoff", self.retry.initial_backoff),
+            ("retry.max_backoff", self.retry.max_backoff),
+        ];
+        durations.extend(self.timeout.map(|value| ("timeout", value)));
+        durations.extend(self.run_timeout.map(|value| ("run_timeout", value)));
+        durations.extend(
+            self.ready
+                .as_ref()
+                .map(|ready| ("ready.timeout", ready.timeout)),
+        );
+        for (name, value) in durations {
+            seconds(name, value)?;
+        }
+        let rates = [
+            ("protocol_fuzzing", self.protocol_fuzzing),
+            ("header_fuzzing", self.header_fuzzing),
+            ("path_fuzzing", self.path_fuzzing),
+            ("generator.edge_rate", self.generator.edge_rate),
+        ];
+        for (name, rate) in rates {
+            if let Some(rate) = rate.filter(|rate| !rate.is_finite()) {
+                return Err(ConfigError::InvalidValue(
+                    name,
+                    format!("expected a fraction of cases, not {}", rate),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    // The full configuration, from lowest to highest precedence: built-in
+    // defaults, the file at `path`, `FUZZER_*` variables in a `.env` file in the
+    // working directory, the same variables in the process environment, and
+    // finally `overrides` from the command line.
+    pub fn resolve(path: impl AsRef<Path>, overrides: &Overrides) -> Result<Self, ConfigError> {
+        let mut config = Self::load(path)?;
+        let mut vars = match std::fs::read_to_string(".env") {
+            Ok(source) => parse_dotenv(&source)?,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(err) => return Err(err.into()),
+        };
+        vars.extend(
+            ENV_VARS
+                .iter()
+                .filter_map(|name| Some((name.to_string(), std::env::var(name).ok()?))),
+        );
+        config.apply_env(&vars)?;
+        config.apply_overrides(overrides);
+        if config.test_url.is_empty() {
+            return Err(ConfigError::NoTestUrl);
+        }
+        Ok(config)
+    }
+
+    pub fn apply_env(&mut self, vars: &BTreeMap<String, String>) -> Result<(), ConfigError> {
+        if let Some(url) = vars.get(ENV_TEST_URL) {
+            self.test_url = url.clone();
+        }
+        if let Some(url) = vars.get(ENV_SERVER_URL) {
+            self.server_url = Some(url.clone());
+        }
+        if let Some(seed) = vars.get(ENV_SEED) {
+            let seed = seed
+                .parse()
+                .map_err(|_| ConfigError::InvalidEnv(ENV_SEED, seed.clone()))?;
+            self.seed = Some(seed);
+        }
+        if let Some(token) = vars.get(ENV_TOKEN) {
+            match &mut self.auth {
+                Some(auth) => auth.token = token.clone(),
+                None => {
+                    self.auth = Some(AuthConfig {
+                        location: None,
+                        name: default_auth_name(),
+                        token: token.clone(),
+                    })
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn apply_overrides(&mut self, overrides: &Overrides) {
+        if let Some(url) = &overrides.test_url {
+            self.test_url = url.clone();
+        }
+        if let Some(url) = &overrides.server_url {
+            self.server_url = Some(url.clone());
+        }
+        if overrides.seed.is_some() {
+            self.seed = overrides.seed;
+        }
+    }
+
+    pub fn load_spec(&self) -> Result<Option<Spec>, ConfigError> {
+        match &self.spec {
+            Some(path) => Ok(Some(Spec::from_file(path)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub fn tester(&self, spec: Option<Spec>) -> Result<Tester, ConfigError> {
+        let tester = match &self.server_url {
+            Some(server_url) => Tester::new(self.test_url.clone(), server_url.clone()),
+            None => Tester::against_oracle(self.test_url.clone(), self.oracle(spec.as_ref())?),
+        };
+        let mut tester = tester
+            .with_headers(self.header_map()?)
+            .with_concurrency(self.concurrency)
+            .with_mode(self.comparison.clone())
+            .with_ignored(self.ignore.clone())
+            .with_numeric(self.numbers.options())
+            .with_array_mode(self.arrays)
+            .with_non_json(self.non_json)
+            .with_optional(self.optional.clone(), self.strict_optional)
+            .with_compared_headers(self.compared_headers()?)
+            .with_hooks(self.hooks.hooks()?)
+            .with_response_schemas(self.response_schemas()?)
+            .with_retry(self.retry.policy());
+        if let Some(version) = self.api_version {
+            tester = tester.with_version(version);
+        }
+        if let Some(ready) = &self.ready {
+            if let Some(path) = &ready.path {
+                let method = parse_method(ready.method.as_deref().unwrap_or("GET"))?;
+                tester = tester.with_health_check(TestCase::new(path.clone(), method, None));
+            }
+        }
+        for (path, annotation) in &self.fields {
+            tester = tester.with_field(path.as_str(), annotation.clone());
+        }
+        if let Some(auth) = &self.auth {
+            tester = tester.with_auth(auth.strategy(self.api_version)?, auth.token.clone());
+        }
+        if let Some(bytes) = self.max_body_size {
+            tester = tester.with_max_body_size(bytes);
+        }
+        if self.http_version != HttpVersion::Auto {
+            tester = tester.with_http_version(self.http_version);
+        }
+        tester = tester.with_networks(self.network.test.network()?, self.network.server.network()?);
+        if self.registry {
+            tester = tester.with_registry();
+        }
+        if self.cookies {
+            tester = tester.with_cookies();
+        }
+        if self.strict_json {
+            tester = tester.with_strict_json();
+        }
+        if self.compare_cookies {
+            tester = tester.with_cookie_comparison();
+        }
+        if self.idempotency_checks {
+            tester = tester.with_idempotency_checks();
+        }
+        if let Some(rps) = self.rate_limit {
+            tester = tester.with_rate_limit(rps, self.rate_limit_burst);
+        }
+        if let Some(seconds) = self.timeout {
+            tester = tester.with_timeout(Duration::from_secs_f64(seconds));
+        }
+        if let Some(spec) = spec {
+            tester = tester.with_spec(spec);
+        }
+        Ok(tester)
+    }
+
+    // Every candidate with `tester`'s settings, compared against `server_url`.
+    pub fn multi_tester(&self, tester: &Tester) -> Result<MultiTester, ConfigError> {
+        let reference = self.server_url.clone().ok_or(ConfigError::NoReference)?;
+        Ok(MultiTester::new(
+            tester,
+            reference,
+            self.candidates
+                .iter()
+                .map(|(name, url)| (name.clone(), url.clone())),
+        ))
+    }
+
+    // Expected responses for endpoints with an `expect_status`, with the body
+    // schema taken from the spec's response for that status where there is one.
+    pub fn oracle(&self, spec: Option<&Spec>) -> Result<StaticOracle, ConfigError> {
+        let mut oracle = StaticOracle::new();
+        for endpoint in &self.endpoints {
+            let Some(status) = endpoint.expect_status else {
+                continue;
+            };
+            let method = endpoint.method()?;
+            let schema = spec
+                .and_then(|spec| {
+                    spec.operations()
+                        .iter()
+                        .find(|op| op.method == method && op.path == endpoint.path)
+                })
+                .and_then(|op| op.response_schema(status))
+                .cloned()
+                .unwrap_or(Schema::Any);
+            oracle = oracle.with_rule(
+                method,
+                endpoint.path.clone(),
+                Expectation::new(status).with_body(schema),
+            );
+        }
+        Ok(oracle)
+    }
+
+    pub fn response_schemas(&self) -> Result<SchemaRegistry, ConfigError> {
+        let mut registry = SchemaRegistry::new();
+        for endpoint in &self.endpoints {
+            let schemas = endpoint.responses.schemas()?;
+            if schemas != ResponseSchemas::default() {
+                registry = registry.with(endpoint.method()?, endpoint.path.clone(), schemas);
+            }
+        }
+        if let Some(path) = &self.learned_schemas {
+            registry = registry.with_all(LearnedSchemas::load(path)?.registry());
+        }
+        Ok(registry)
+    }
+
+    pub fn weights(&self) -> Result<Weights, ConfigError> {
+        let mut weights = Weights::new();
+        for (route, weight) in &self.weights {
+            weights = match route.split_once(' ') {
+                Some((method, path)) => {
+                    weights.with(Some(parse_method(method)?), path.trim(), *weight)
+                }
+                None => weights.with(None, route.clone(), *weight),
+            };
+        }
+        Ok(weights)
+    }
+
+    pub fn catalogue(&self) -> Result<Catalogue, ConfigError> {
+        let mut catalogue = Catalogue::quiz_api();
+        for rule in &self.errors {
+            let input = InvalidInput::parse(&rule.input)
+                .ok_or_else(|| ConfigError::InvalidInput(rule.input.clone()))?;
+            catalogue = catalogue.with_rule(Rule {
+                method: parse_method(&rule.method)?,
+                path: rule.path.clone(),
+                input,
+                status: rule.status,
+            });
+        }
+        Ok(catalogue)
+    }
+
+    pub fn compared_headers(&self) -> Result<Vec<HeaderName>, ConfigError> {
+        self.compare_headers
+            .iter()
+            .map(|name| {
+                HeaderName::from_bytes(name.as_bytes())
+                    .map_err(|_| ConfigError::InvalidHeader(name.clone()))
+            })
+            .collect()
+    }
+
+    pub fn header_map(&self) -> Result<HeaderMap, ConfigError> {
+        let mut headers = HeaderMap::new();
+        for (name, value) in &self.headers {
+            let name = HeaderName::from_bytes(name.as_bytes())
+                .map_err(|_| ConfigError::InvalidHeader(name.clone()))?;
+            let value = HeaderValue::from_str(value)
+                .map_err(|_| ConfigError::InvalidHeader(name.to_string()))?;
+            headers.insert(name, value);
+        }
+        Ok(headers)
+    }
+}
+
+impl HooksConfig {
+    pub fn hooks(&self) -> Result<Hooks, ConfigError> {
+        let mut hooks = Hooks::new();
+        if self.clear {
+            hooks = hooks.with_before(clear_case(&self.clear_endpoint));
+        }
+        for hook in &self.before {
+            hooks = hooks.with_before(hook.case()?);
+        }
+        for hook in &self.after {
+            hooks = hooks.with_after(hook.case()?);
+        }
+        Ok(hooks)
+    }
+}
+
+impl HookConfig {
+    pub fn case(&self) -> Result<TestCase, ConfigError> {
+        let method = parse_method(&self.method)?;
+        Ok(TestCase::new(self.path.clone(), method, self.body.clone())
+            .with_body_kind(self.body_kind))
+    }
+}
+
+impl EndpointConfig {
+    pub fn method(&self) -> Result<Method, ConfigError> {
+        parse_method(&self.method)
+    }
+}
+
+fn seconds(name: &'static str, value: f64) -> Result<Duration, ConfigError> {
+    Duration::try_from_secs_f64(value).map_err(|_| {
+        ConfigError::InvalidValue(name, format!("expected a number of seconds, not {}", value))
+    })
+}
+
+fn parse_method(method: &str) -> Result<Method, ConfigError> {
+    method
+        .to_uppercase()
+        .parse::<Method>()
+        .map_err(|_| ConfigError::InvalidMethod(method.to_string()))
+}
+
+// `NAME=value` lines, optionally prefixed with `export` and with the value in
+// single or double quotes. Blank lines and `#` comments are skipped.
+fn parse_dotenv(source: &str) -> Result<BTreeMap<String, String>, ConfigError> {
+    let mut vars = BTreeMap::new();
+    for (index, line) in source.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let (name, value) = line
+            .split_once('=')
+            .ok_or(ConfigError::InvalidDotenv(index + 1))?;
+        let value = value.trim();
+        let value = ['"', '\'']
+            .iter()
+            .find_map(|quote| {
+                value
+                    .strip_prefix(*quote)
+                    .and_then(|rest| rest.strip_suffix(*quote))
+            })
+            .unwrap_or(value);
+        vars.insert(name.trim().to_string(), value.to_string());
+    }
+    Ok(vars)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Writes `source` to a scratch file named `name` and loads it.
+    fn load(name: &str, source: &str) -> Result<Config, ConfigError> {
+        let dir = std::env::temp_dir().join(format!("fuzzer1531-config-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(name);
+        std::fs::write(&path, source).unwrap();
+        let config = Config::load(&path);
+        std::fs::remove_file(&path).unwrap();
+        config
+    }
+
+    #[test]
+    fn toml_and_yaml_load_the_same_config() {
+        let toml = load(
+            "same.toml",
+            r#"
+test_url = "http://localhost:3200"
+server_url = "http://localhost:3201"
+spec = "swagger.yaml"
+
+[[endpoints]]
+method = "post"
+path = "/v1/admin/auth/register"
+"#,
+        )
+        .unwrap();
+        let yaml = load(
+            "same.yml",
+            r#"
+test_url: http://localhost:3200
+server_url: http://localhost:3201
+spec: swagger.yaml
+endpoints:
+  - method: post
+    path: /v1/admin/auth/register
+"#,
+        )
+        .unwrap();
+
+        for config in [&toml, &yaml] {
+            assert_eq!(config.iterations, 1);
+            assert_eq!(config.endpoints[0].method().unwrap(), Method::POST);
+            // Relative to the config file's directory.
+            let spec = config.spec.as_ref().unwrap();
+            assert!(spec.is_absolute() && spec.ends_with("swagger.yaml"));
+        }
+    }
+
+    fn parse(extra: &str) -> Config {
+        toml::from_str(&format!("test_url = \"a\"\nserver_url = \"b\"\n{}", extra)).unwrap()
+    }
+
+    #[test]
+    fn unusable_numbers_are_rejected() {
+        for source in [
+            "rate_limit = 0",
+            "rate_limit = -2",
+            "rate_limit = inf",
+            "rate_limit = nan",
+            "timeout = -1",
+            "run_timeout = nan",
+            "timeout = 1e300",
+            "[retry]\nmax_backoff = -0.5",
+            "max_slowdown = nan",
+            "max_slowdown = 0",
+            "protocol_fuzzing = nan",
+            "header_fuzzing = -inf",
+            "path_fuzzing = nan",
+            "[generator]\nedge_rate = nan",
+            "[ready]\ntimeout = -1",
+        ] {
+            match parse(source).validate() {
+                Err(ConfigError::InvalidValue(..)) => {}
+                outcome => panic!("expected `{}` to be rejected, got {:?}", source, outcome),
+            }
+        }
+        parse("rate_limit = 0.5\ntimeout = 0.5\nrun_timeout = 60")
+            .validate()
+            .unwrap();
+        parse("").validate().unwrap();
+        assert!(matches!(
+            load(
+                "zero.toml",
+                "test_url = \"a\"\nserver_url = \"b\"\nrate_limit = 0"
+            ),
+            Err(ConfigError::InvalidValue("rate_limit", _))
+        ));
+    }
+
+    #[test]
+    fn hooks_default_to_clearing_before_each_case() {
+        let hooks = parse("").hooks.hooks().unwrap();
+        assert_eq!(hooks.before.len(), 1);
+        assert_eq!(hooks.before[0].endpoint, CLEAR_ENDPOINT);
+
+        let hooks = parse(
+            r#"
+[hooks]
+clear = false
+after = [{ method = "post", path = "/logout", body = { token = "t" } }]
+"#,
+        )
+        .hooks
+        .hooks()
+        .unwrap();
+        assert!(hooks.before.is_empty());
+        assert_eq!(hooks.after[0].method, Method::POST);
+        assert_eq!(hooks.after[0].body, Some(serde_json::json!({"token": "t"})));
+
+        let bad = parse("[hooks]\nbefore = [{ method = \"not a method\", path = \"/\" }]");
+        assert!(matches!(
+            bad.hooks.hooks(),
+            Err(ConfigError::InvalidMethod(_))
+        ));
+    }
+
+    #[test]
+    fn expected_statuses_become_oracle_rules() {
+        use crate::models::oracle::Oracle;
+
+        let config: Config = toml::from_str(
+            r#"
+test_url = "a"
+
+[[endpoints]]
+method = "get"
+path = "/quiz/{id}"
+expect_status = 400
+
+[[endpoints]]
+method = "get"
+path = "/quiz/list"
+"#,
+        )
+        .unwrap();
+        assert_eq!(config.server_url, None);
+
+        let oracle = config.oracle(None).unwrap();
+        let case = |endpoint| TestCase::new(endpoint, Method::GET, None);
+        assert_eq!(oracle.expect(&case("/quiz/1")), Some(Expectation::new(400)));
+        assert_eq!(oracle.expect(&case("/quiz/list/extra")), None);
+    }
+
+    #[test]
+    fn unknown_formats_and_bad_values_are_rejected() {
+        assert!(matches!(
+            load("config.json", "{}"),
+            Err(ConfigError::UnsupportedFormat(ext)) if ext == "json"
+        ));
+        assert!(matches!(
+            load("broken.toml", "test_url = "),
+            Err(ConfigError::Toml(_))
+        ));
+
+        let config = load(
+            "bad.toml",
+            r#"
+test_url = "a"
+server_url = "b"
+headers = { "bad header" = "x" }
+
+[[endpoints]]
+method = "not a method"
+path = "/"
+"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            config.header_map(),
+            Err(ConfigError::InvalidHeader(name)) if name == "bad header"
+        ));
+        assert!(matches!(
+            config.endpoints[0].method(),
+            Err(ConfigError::InvalidMethod(_))
+        ));
+    }
+
+    #[test]
+    fn auth_names_must_be_valid_headers() {
+        let config = parse("[auth]\nlocation = \"header\"\nname = \"bad name\"\ntoken = \"t\"");
+        assert!(matches!(
+            config.auth.unwrap().strategy(None),
+            Err(ConfigError::InvalidHeader(name)) if name == "bad name"
+        ));
+
+        let auth = parse("[auth]\ntoken = \"t\"").auth.unwrap();
+        assert_eq!(auth.location, None);
+        assert_eq!(auth.name, "token");
+        // Without a location the version decides, so a bad name is not used.
+        let config = parse("[auth]\nname = \"bad name\"\ntoken = \"t\"");
+        assert!(config.auth.unwrap().strategy(Some(ApiVersion::V2)).is_ok());
+    }
+
+    #[test]
+    fn candidates_need_a_reference() {
+        let config: Config =
+            toml::from_str("test_url = \"a\"\n[candidates]\nalice = \"http://localhost:4000\"")
+                .unwrap();
+        let tester = Tester::new(config.test_url.clone(), String::new());
+        assert!(matches!(
+            config.multi_tester(&tester),
+            Err(ConfigError::NoReference)
+        ));
+        let multi = parse("[candidates]\nalice = \"c\"\nbob = \"d\"")
+            .multi_tester(&tester)
+            .unwrap();
+        assert_eq!(multi.candidates().collect::<Vec<_>>(), ["alice", "bob"]);
+    }
+
+    #[test]
+    fn logging_is_off_until_configured() {
+        assert!(parse("").log.is_none());
+        let log = parse("[log]").log.unwrap();
+        assert_eq!(
+            (log.format, log.level.as_str()),
+            (LogFormat::Pretty, "info")
+        );
+        let log = parse("[log]\nformat = \"json\"\nlevel = \"debug\"")
+            .log
+            .unwrap();
+        assert_eq!((log.format, log.level.as_str()), (LogFormat::Json, "debug"));
+    }
+
+    #[test]
+    fn weights_are_keyed_by_route_with_an_optional_method() {
+        let config = parse(
+            "schedule = \"weighted\"\n[weights]\n\"post /admin/quiz\" = 4.0\n\"/admin/quiz\" = 2.0",
+        );
+        assert_eq!(config.schedule, Schedule::Weighted);
+        let weights = config.weights().unwrap();
+        assert_eq!(weights.weight(&Method::POST, "/admin/quiz"), 4.0);
+        assert_eq!(weights.weight(&Method::GET, "/admin/quiz"), 2.0);
+
+        let config = parse("[weights]\n\"B@D /x\" = 1.0");
+        assert!(matches!(
+            config.weights(),
+            Err(ConfigError::InvalidMethod(_))
+        ));
+    }
+
+    #[test]
+    fn timestamp_fields_default_their_tolerance() {
+        let config = parse(
+            "[fields]\n\"/createdAt\" = { type = \"timestamp\" }\n\"/*/at\" = { type = \"timestamp\", tolerance = 0.5 }",
+        );
+        assert_eq!(
+            config.fields["/createdAt"],
+            FieldAnnotation::Timestamp { tolerance: 5.0 }
+        );
+        assert_eq!(
+            config.fields["/*/at"],
+            FieldAnnotation::Timestamp { tolerance: 0.5 }
+        );
+    }
+
+    #[test]
+    fn field_patterns_must_compile() {
+        let config = parse(
+            "[fields]\n\"/error\" = { type = \"non_empty\" }\n\"/name\" = { type = \"matches\", pattern = \"^Quiz\" }",
+        );
+        assert_eq!(config.fields["/error"], FieldAnnotation::NonEmpty);
+        assert_eq!(
+            config.fields["/name"],
+            FieldAnnotation::Matches {
+                pattern: crate::models::compare::FieldPattern::new("^Quiz").unwrap()
+            }
+        );
+        let source =
+            "test_url = \"a\"\n[fields]\n\"/name\" = { type = \"matches\", pattern = \"(\" }";
+        let err = toml::from_str::<Config>(source).unwrap_err();
+        assert!(err.to_string().contains("invalid pattern `(`"), "{}", err);
+    }
+
+    #[test]
+    fn dotenv_lines_may_be_exported_and_quoted() {
+        let vars = parse_dotenv(
+            "# comment\n\nexport FUZZER_SEED=7\nFUZZER_TOKEN = 'a b'\nFUZZER_TEST_URL=\"http://x\"",
+        )
+        .unwrap();
+        assert_eq!(vars[ENV_SEED], "7");
+        assert_eq!(vars[ENV_TOKEN], "a b");
+        assert_eq!(vars[ENV_TEST_URL], "http://x");
+        assert!(matches!(
+            parse_dotenv("A=1\nnot a pair"),
+            Err(ConfigError::InvalidDotenv(2))
+        ));
+    }
+
+    #[test]
+    fn overrides_win_over_the_environment_over_the_file() {
+        let mut config = parse("seed = 1");
+        let vars = BTreeMap::from([
+            (ENV_SEED.to_string(), "2".to_string()),
+            (ENV_SERVER_URL.to_string(), "env".to_string()),
+            (ENV_TOKEN.to_string(), "secret".to_string()),
+        ]);
+        config.apply_env(&vars).unwrap();
+        assert_eq!(config.seed, Some(2));
+        assert_eq!(config.server_url.as_deref(), Some("env"));
+        assert_eq!(config.auth.as_ref().unwrap().token, "secret");
+
+        config.apply_overrides(&Overrides {
+            seed: Some(3),
+            ..Overrides::default()
+        });
+        assert_eq!((config.seed, config.test_url.as_str()), (Some(3), "a"));
+
+        let bad = BTreeMap::from([(ENV_SEED.to_string(), "many".to_string())]);
+        assert!(matches!(
+            parse("").apply_env(&bad),
+            Err(ConfigError::InvalidEnv(ENV_SEED, _))
+        ));
+    }
+
+    #[test]
+    fn error_rules_extend_the_catalogue() {
+        let config = parse(
+            "[[errors]]\nmethod = \"get\"\npath = \"/custom\"\ninput = \"missing_token\"\nstatus = 403",
+        );
+        let catalogue = config.catalogue().unwrap();
+        assert_eq!(
+            catalogue.rules().len(),
+            Catalogue::quiz_api().rules().len() + 1
+        );
+        let config =
+            parse("[[errors]]\nmethod = \"get\"\npath = \"/\"\ninput = \"no_token\"\nstatus = 401");
+        assert!(matches!(
+            config.catalogue(),
+            Err(ConfigError::InvalidInput(input)) if input == "no_token"
+        ));
+    }
+
+    #[test]
+    fn projects_give_the_test_url_from_their_port() {
+        let dir = std::env::temp_dir().join(format!("fuzzer1531-project-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("config.json"), r#"{ "port": "3200" }"#).unwrap();
+        let path = dir.join("fuzzer.toml");
+        std::fs::write(&path, "[project]\nbase_path = \"api/\"").unwrap();
+        let config = Config::load(&path).unwrap();
+        assert_eq!(config.test_url, "http://127.0.0.1:3200/api");
+
+        let project = |port: &str, url: Option<&str>| {
+            let source = serde_json::json!({ "port": serde_json::from_str::<Value>(port).unwrap(), "url": url });
+            std::fs::write(dir.join("config.json"), source.to_string()).unwrap();
+            ProjectConfig {
+                config: dir.join("config.json"),
+                base_path: String::new(),
+            }
+            .base_url()
+        };
+        assert_eq!(
+            project("49152", Some("http://localhost/")).unwrap(),
+            "http://localhost:49152"
+        );
+        assert!(matches!(
+            project("70000", None),
+            Err(ConfigError::ProjectConfig { message, .. }) if message.contains("port number")
+        ));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn endpoint_generators_override_the_runs_profile() {
+        let config = parse(
+            "[generator]\nprofile = \"smoke\"\nmax_depth = 1\n\n[[endpoints]]\npath = \"/a\"\nmethod = \"GET\"\ngenerator = { max_array_len = 9 }",
+        );
+        let run = config.generator.profile(&GeneratorProfile::default());
+        assert_eq!(run.max_string_len, 8);
+        assert_eq!(run.max_depth, 1);
+        let endpoint = config.endpoints[0]
+            .generator
+            .as_ref()
+            .unwrap()
+            .profile(&run);
+        assert_eq!(endpoint.max_array_len, 9);
+        assert_eq!(endpoint.max_depth, 1);
+        // A preset of its own starts over from that preset.
+        let aggressive = GeneratorConfig {
+            profile: Some(ProfilePreset::Aggressive),
+            edge_rate: Some(f64::NAN),
+            ..Default::default()
+        }
+        .profile(&run);
+        assert_eq!(aggressive.max_depth, 16);
+        assert_eq!(aggressive.edge_rate, 0.0);
+    }
+
+    #[test]
+    fn each_server_gets_its_own_network_settings() {
+        let config = parse(
+            "[network.server]\nproxy = \"http://127.0.0.1:3128\"\ndanger_accept_invalid_certs = true",
+        );
+        assert_eq!(config.network.test.proxy, None);
+        assert!(!config.network.test.danger_accept_invalid_certs);
+        assert!(config.network.server.danger_accept_invalid_certs);
+        assert!(config.tester(None).is_ok());
+
+        let proxy = ServerNetwork {
+            proxy: Some("not a url".to_string()),
+            ..Default::default()
+        };
+        assert!(
+            matches!(proxy.network(), Err(ConfigError::Proxy { url, .. }) if url == "not a url")
+        );
+
+        let dir = std::env::temp_dir().join(format!("fuzzer1531-certs-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("empty.pem"), "not a certificate").unwrap();
+        let path = dir.join("fuzzer.toml");
+        std::fs::write(
+            &path,
+            "test_url = \"a\"\n[network.test]\nroot_certificates = [\"empty.pem\"]",
+        )
+        .unwrap();
+        let config = Config::load(&path).unwrap();
+        assert_eq!(
+            config.network.test.root_certificates,
+            [dir.join("empty.pem")]
+        );
+        match config.network.test.network() {
+            Err(ConfigError::Certificate { path, message }) => {
+                assert_eq!(path, dir.join("empty.pem"));
+                assert_eq!(message, "no PEM certificates in the file");
+            }
+            other => panic!("expected a certificate error, got {:?}", other.map(|_| ())),
+        }
+        let missing = ServerNetwork {
+            root_certificates: vec![dir.join("missing.pem")],
+            ..Default::default()
+        };
+        assert!(matches!(
+            missing.network(),
+            Err(ConfigError::Certificate { .. })
+        ));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn chaos_settings_are_checked() {
+        let chaos = |extra: &str| parse(&format!("[chaos]\n{}", extra)).chaos.unwrap();
+        let settings = chaos("latency = 0.5\ndrop_rate = 0.1\nseed = 9")
+            .chaos(1)
+            .unwrap();
+        assert_eq!(settings.seed, 9);
+        assert_eq!(settings.latency, Duration::from_millis(500));
+        assert_eq!(chaos("").chaos(1).unwrap().seed, 1);
+        assert!(chaos("").reference);
+
+        for (extra, message) in [
+            (
+                "latency = -1.0",
+                "`latency` must be a number of seconds, not -1",
+            ),
+            (
+                "jitter = nan",
+                "`jitter` must be a number of seconds, not NaN",
+            ),
+            (
+                "drop_rate = 1.5",
+                "`drop_rate` must be between 0 and 1, not 1.5",
+            ),
+            (
+                "truncate_rate = nan",
+                "`truncate_rate` must be between 0 and 1, not NaN",
+            ),
+        ] {
+            match chaos(extra).chaos(1) {
+                Err(ConfigError::InvalidChaos(got)) => assert_eq!(got, message),
+                other => panic!("{}: expected invalid chaos, got {:?}", extra, other),
+            }
+        }
+    }
+}
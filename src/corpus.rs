@@ -0,0 +1,368 @@
+use crate::fuzz::headers::HeaderPerturbation;
+use crate::fuzz::protocol::Malformation;
+use crate::models::body::BodyKind;
+use crate::models::client::{TestCase, Tester, TesterError};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::Method;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum CorpusError {
+    #[error("Corpus I/O failed: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Invalid corpus entry: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("Invalid HTTP method `{0}` in corpus entry")]
+    InvalidMethod(String),
+
+    #[error("Invalid header `{0}` in corpus entry")]
+    InvalidHeader(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordedRequest {
+    pub endpoint: String,
+    pub method: String,
+    #[serde(default)]
+    pub query: Vec<(String, String)>,
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    #[serde(default)]
+    pub body: Option<Value>,
+    #[serde(default)]
+    pub body_kind: BodyKind,
+    #[serde(default)]
+    pub malformation: Option<Malformation>,
+    #[serde(default)]
+    pub header_perturbation: Option<HeaderPerturbation>,
+    pub seed: u64,
+    // The error seen when the case was recorded, kept for context only.
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+impl RecordedRequest {
+    pub fn new(case: &TestCase, seed: u64) -> Self {
+        Self {
+            endpoint: case.endpoint.clone(),
+            method: case.method.to_string(),
+            query: case.query.clone(),
+            headers: case
+                .headers
+                .iter()
+                .map(|(name, value)| {
+                    (
+                        name.to_string(),
+                        String::from_utf8_lossy(value.as_bytes()).into_owned(),
+                    )
+                })
+                .collect(),
+            body: case.body.clone(),
+            body_kind: case.body_kind,
+            malformation: case.malformation,
+            header_perturbation: case.header_perturbation,
+            seed,
+            error: None,
+        }
+    }
+
+    // Covers everything that is sent, but not the seed or recorded error, so
+    // the same input found twice hashes the same.
+    pub fn content_hash(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.endpoint.hash(&mut hasher);
+        self.method.hash(&mut hasher);
+        self.query.hash(&mut hasher);
+        self.headers.hash(&mut hasher);
+        self.body.as_ref().map(Value::to_string).hash(&mut hasher);
+        (self.body_kind as u8).hash(&mut hasher);
+        self.malformation.map(|m| m as u8).hash(&mut hasher);
+        self.header_perturbation.map(|p| p as u8).hash(&mut hasher);
+        hasher.finish()
+    }
+
+    pub fn with_error(mut self, error: &TesterError) -> Self {
+        self.error = Some(error.to_string());
+        self
+    }
+
+    pub fn to_case(&self) -> Result<TestCase, CorpusError> {
+        let method = self
+            .method
+            .parse::<Method>()
+            .map_err(|_| CorpusError::InvalidMethod(self.method.clone()))?;
+
+        let mut headers = HeaderMap::new();
+        for (name, value) in &self.headers {
+            let header = HeaderName::from_bytes(name.as_bytes())
+                .map_err(|_| CorpusError::InvalidHeader(name.clone()))?;
+            let value = HeaderValue::from_str(value)
+                .map_err(|_| CorpusError::InvalidHeader(name.clone()))?;
+            headers.append(header, value);
+        }
+
+        Ok(TestCase {
+            headers,
+            malformation: self.malformation,
+            header_perturbation: self.header_perturbation,
+            ..TestCase::new(self.endpoint.clone(), method, self.body.clone())
+                .with_query(self.query.clone())
+                .with_body_kind(self.body_kind)
+                .with_seed(self.seed)
+        })
+    }
+}
+
+// An input that reached behaviour no earlier input had, kept to seed the
+// mutator in later runs.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CorpusInput {
+    // The path template the input was generated for.
+    pub target: String,
+    pub request: RecordedRequest,
+    // What made it interesting, e.g. `client:500` or `mismatch:/quizId`.
+    #[serde(default)]
+    pub features: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Corpus {
+    dir: PathBuf,
+}
+
+impl Corpus {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn record(&self, request: &RecordedRequest) -> Result<PathBuf, CorpusError> {
+        std::fs::create_dir_all(&self.dir)?;
+        let path = self.dir.join(format!(
+            "{}-{}-{:016x}.json",
+            request.method.to_lowercase(),
+            slug(&request.endpoint),
+            request.seed
+        ));
+        std::fs::write(&path, serde_json::to_vec_pretty(request)?)?;
+        Ok(path)
+    }
+
+    // Inputs live in their own subdirectory so replays only re-send failures.
+    fn inputs_dir(&self) -> PathBuf {
+        self.dir.join("inputs")
+    }
+
+    // Named by content hash; `None` when the same input is already stored.
+    pub fn save_input(&self, input: &CorpusInput) -> Result<Option<PathBuf>, CorpusError> {
+        let dir = self.inputs_dir();
+        let path = dir.join(format!(
+            "{}-{}-{:016x}.json",
+            input.request.method.to_lowercase(),
+            slug(&input.request.endpoint),
+            input.request.content_hash()
+        ));
+        if path.exists() {
+            return Ok(None);
+        }
+        std::fs::create_dir_all(&dir)?;
+        std::fs::write(&path, serde_json::to_vec_pretty(input)?)?;
+        Ok(Some(path))
+    }
+
+    pub fn load_inputs(&self) -> Result<Vec<CorpusInput>, CorpusError> {
+        let dir = self.inputs_dir();
+        if !dir.exists() {
+            return Ok(Vec::new());
+        }
+        json_files(&dir)?
+            .iter()
+            .map(|path| Ok(serde_json::from_slice(&std::fs::read(path)?)?))
+            .collect()
+    }
+
+    // Entries are returned sorted by file name so replays run in a stable order.
+    pub fn load(&self) -> Result<Vec<RecordedRequest>, CorpusError> {
+        json_files(&self.dir)?
+            .iter()
+            .map(|path| Ok(serde_json::from_slice(&std::fs::read(path)?)?))
+            .collect()
+    }
+}
+
+// A file-name-safe version of an endpoint path.
+pub(crate) fn slug(endpoint: &str) -> String {
+    endpoint
+        .trim_matches('/')
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect()
+}
+
+// Every `.json` file in `dir`, sorted by name.
+pub(crate) fn json_files(dir: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let mut paths = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension().and_then(|e| e.to_str()) == Some("json") {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+    Ok(paths)
+}
+
+pub async fn replay(
+    tester: &Tester,
+    recorded: Vec<RecordedRequest>,
+) -> Result<Vec<(RecordedRequest, Result<(), TesterError>)>, CorpusError> {
+    let cases = recorded
+        .iter()
+        .map(RecordedRequest::to_case)
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let results = tester.compare_all(cases).await;
+    Ok(recorded
+        .into_iter()
+        .zip(results)
+        .map(|(request, (_, comparison))| (request, comparison.outcome))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn scratch(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("fuzzer1531-corpus-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn case() -> TestCase {
+        let mut case = TestCase::new(
+            "/v1/admin/quiz/7",
+            Method::PUT,
+            Some(json!({"name": "q", "description": ""})),
+        );
+        case.headers
+            .append("token", HeaderValue::from_static("abc"));
+        case.headers
+            .append("x-extra", HeaderValue::from_static("1"));
+        case.headers
+            .append("x-extra", HeaderValue::from_static("2"));
+        case
+    }
+
+    #[test]
+    fn recorded_requests_turn_back_into_the_same_case() {
+        let original = case().with_body_kind(BodyKind::Multipart);
+        let replayed = RecordedRequest::new(&original, 42).to_case().unwrap();
+        assert_eq!(replayed.endpoint, original.endpoint);
+        assert_eq!(replayed.method, original.method);
+        assert_eq!(replayed.body, original.body);
+        assert_eq!(replayed.body_kind, BodyKind::Multipart);
+        assert_eq!(replayed.seed, Some(42));
+        assert_eq!(replayed.headers["token"], "abc");
+        // Only the last of a repeated header survives the map.
+        assert_eq!(replayed.headers.get_all("x-extra").iter().count(), 1);
+    }
+
+    #[test]
+    fn bad_methods_and_headers_are_reported() {
+        let mut recorded = RecordedRequest::new(&case(), 1);
+        recorded.method = "GE T".to_string();
+        assert!(matches!(
+            recorded.to_case(),
+            Err(CorpusError::InvalidMethod(_))
+        ));
+
+        let mut recorded = RecordedRequest::new(&case(), 1);
+        recorded
+            .headers
+            .insert("bad header".to_string(), "x".to_string());
+        assert!(matches!(
+            recorded.to_case(),
+            Err(CorpusError::InvalidHeader(name)) if name == "bad header"
+        ));
+    }
+
+    #[test]
+    fn entries_are_named_after_the_case_and_load_in_order() {
+        let dir = scratch("order");
+        let corpus = Corpus::new(&dir);
+        let second = corpus
+            .record(&RecordedRequest::new(&case(), 0xbeef))
+            .unwrap();
+        let first = corpus
+            .record(&RecordedRequest::new(
+                &TestCase::new("/v1/admin/auth/login", Method::POST, None),
+                1,
+            ))
+            .unwrap();
+        assert_eq!(
+            second.file_name().unwrap(),
+            "put-v1_admin_quiz_7-000000000000beef.json"
+        );
+        std::fs::write(dir.join("notes.txt"), "not an entry").unwrap();
+
+        let loaded = corpus.load().unwrap();
+        assert_eq!(loaded.len(), 2);
+        assert_eq!(
+            loaded[0],
+            serde_json::from_slice(&std::fs::read(first).unwrap()).unwrap()
+        );
+        assert_eq!(loaded[1].seed, 0xbeef);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn recording_the_same_seed_again_overwrites_the_entry() {
+        let dir = scratch("overwrite");
+        let corpus = Corpus::new(&dir);
+        let once = corpus.record(&RecordedRequest::new(&case(), 3)).unwrap();
+        let twice = corpus.record(&RecordedRequest::new(&case(), 3)).unwrap();
+        assert_eq!(once, twice);
+        assert_eq!(corpus.load().unwrap().len(), 1);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn inputs_are_stored_once_per_content() {
+        let corpus = Corpus::new(scratch("inputs"));
+        assert!(corpus.load_inputs().unwrap().is_empty());
+
+        let input = CorpusInput {
+            target: "/v1/admin/quiz/{quizid}".to_string(),
+            request: RecordedRequest::new(&case(), 1),
+            features: vec!["client:400".to_string()],
+        };
+        let mut again = input.clone();
+        again.request.seed = 2;
+        again.request.error = Some("different".to_string());
+        assert_eq!(input.request.content_hash(), again.request.content_hash());
+
+        assert!(corpus.save_input(&input).unwrap().is_some());
+        assert!(corpus.save_input(&again).unwrap().is_none());
+        let mut other = input.clone();
+        other.request.body = Some(json!({"name": "other"}));
+        assert!(corpus.save_input(&other).unwrap().is_some());
+
+        assert_eq!(corpus.load_inputs().unwrap().len(), 2);
+        // Failure replays don't pick up the inputs.
+        assert!(corpus.load().unwrap().is_empty());
+        std::fs::remove_dir_all(corpus.dir()).unwrap();
+    }
+}
@@ -0,0 +1,371 @@
+use crate::fuzz::paths::encode_segment;
+use crate::schema::Schema;
+use rand::distributions::Alphanumeric;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::str::FromStr;
+
+// How big and how hostile generated values get.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GeneratorProfile {
+    pub max_string_len: usize,
+    pub max_array_len: usize,
+    // Optional fields past this many in an object are left out; required
+    // ones are always generated.
+    pub max_object_fields: usize,
+    pub max_depth: usize,
+    // Chance of a string, number or preset field being an edge case instead,
+    // like an empty or huge string or `i64::MAX`.
+    pub edge_rate: f64,
+}
+
+impl Default for GeneratorProfile {
+    fn default() -> Self {
+        ProfilePreset::Default.profile()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProfilePreset {
+    // Small values and no edge cases, for quick CI runs.
+    Smoke,
+    #[default]
+    Default,
+    // Long strings and arrays, deep nesting and frequent edge cases.
+    Aggressive,
+}
+
+impl ProfilePreset {
+    pub fn profile(&self) -> GeneratorProfile {
+        match self {
+            ProfilePreset::Smoke => GeneratorProfile {
+                max_string_len: 8,
+                max_array_len: 2,
+                max_object_fields: 8,
+                max_depth: 3,
+                edge_rate: 0.0,
+            },
+            ProfilePreset::Default => GeneratorProfile {
+                max_string_len: 24,
+                max_array_len: 4,
+                max_object_fields: usize::MAX,
+                max_depth: 6,
+                edge_rate: 0.0,
+            },
+            ProfilePreset::Aggressive => GeneratorProfile {
+                max_string_len: 512,
+                max_array_len: 32,
+                max_object_fields: usize::MAX,
+                max_depth: 16,
+                edge_rate: 0.2,
+            },
+        }
+    }
+}
+
+impl FromStr for ProfilePreset {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "smoke" => Ok(ProfilePreset::Smoke),
+            "default" => Ok(ProfilePreset::Default),
+            "aggressive" => Ok(ProfilePreset::Aggressive),
+            other => Err(format!(
+                "unknown profile `{}` (expected smoke, default or aggressive)",
+                other
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Generator {
+    pub profile: GeneratorProfile,
+}
+
+impl Generator {
+    pub fn new(profile: GeneratorProfile) -> Self {
+        Self { profile }
+    }
+
+    pub fn generate<R: Rng + ?Sized>(&self, schema: &Schema, rng: &mut R) -> Value {
+        self.generate_at(schema, rng, 0)
+    }
+
+    fn generate_at<R: Rng + ?Sized>(&self, schema: &Schema, rng: &mut R, depth: usize) -> Value {
+        // Only drawn when enabled, so seeds under the default profile keep
+        // reproducing.
+        let edge_rate = self.profile.edge_rate;
+        if edge_rate > 0.0 && rng.gen_bool(edge_rate) {
+            if let Some(edge) = self.edge_case(schema, rng) {
+                return edge;
+            }
+        }
+        match schema {
+            Schema::Any => self.primitive(rng),
+            Schema::Null => Value::Null,
+            Schema::Preset(preset) => preset.generate(rng),
+            Schema::Boolean => Value::Bool(rng.gen()),
+            Schema::Integer => Value::from(rng.gen_range(-10i64..1000)),
+            Schema::Number => Value::from(rng.gen_range(-10.0..1000.0)),
+            Schema::String => Value::String(self.string(rng)),
+            Schema::Nullable(inner) => {
+                if rng.gen_bool(0.1) {
+                    Value::Null
+                } else {
+                    self.generate_at(inner, rng, depth)
+                }
+            }
+            Schema::OneOf(options) if !options.is_empty() => {
+                let choice = &options[rng.gen_range(0..options.len())];
+                self.generate_at(choice, rng, depth)
+            }
+            Schema::OneOf(_) => Value::Null,
+            Schema::Array(items) => {
+                if depth >= self.profile.max_depth {
+                    return Value::Array(Vec::new());
+                }
+                let len = rng.gen_range(0..=self.profile.max_array_len);
+                Value::Array(
+                    (0..len)
+                        .map(|_| self.generate_at(items, rng, depth + 1))
+                        .collect(),
+                )
+            }
+            Schema::Object {
+                properties,
+                required,
+            } => {
+                let mut map = Map::new();
+                let mut optional = 0;
+                for (key, prop) in properties {
+                    // Optional fields are left out some of the time so absence gets exercised too.
+                    let is_required = required.contains(key);
+                    let keep = is_required || rng.gen_bool(0.7);
+                    if keep && !is_required {
+                        optional += 1;
+                    }
+                    let within = is_required || optional <= self.profile.max_object_fields;
+                    if keep && within && depth < self.profile.max_depth {
+                        map.insert(key.clone(), self.generate_at(prop, rng, depth + 1));
+                    }
+                }
+                Value::Object(map)
+            }
+        }
+    }
+
+    // `None` for schemas without edge cases of their own.
+    fn edge_case<R: Rng + ?Sized>(&self, schema: &Schema, rng: &mut R) -> Option<Value> {
+        let edges = match schema {
+            Schema::Integer => vec![
+                Value::from(0),
+                Value::from(-1),
+                Value::from(i64::MAX),
+                Value::from(i64::MIN),
+                // The first integer a JavaScript number can't hold.
+                Value::from(9_007_199_254_740_993i64),
+            ],
+            Schema::Number => vec![
+                Value::from(0.0),
+                Value::from(-0.0),
+                Value::from(f64::MAX),
+                Value::from(f64::MIN_POSITIVE),
+            ],
+            Schema::String => vec![
+                Value::from(""),
+                Value::from("   "),
+                Value::from("\u{0}"),
+                Value::from("é🦀\u{200b}"),
+                Value::from("a".repeat(self.profile.max_string_len.max(1) * 64)),
+            ],
+            Schema::Preset(preset) => preset.edge_cases(),
+            _ => return None,
+        };
+        edges.choose(rng).cloned()
+    }
+
+    fn primitive<R: Rng + ?Sized>(&self, rng: &mut R) -> Value {
+        match rng.gen_range(0..4) {
+            0 => Value::Null,
+            1 => Value::Bool(rng.gen()),
+            2 => Value::from(rng.gen_range(-10i64..1000)),
+            _ => Value::String(self.string(rng)),
+        }
+    }
+
+    fn string<R: Rng + ?Sized>(&self, rng: &mut R) -> String {
+        let len = rng.gen_range(0..=self.profile.max_string_len);
+        (0..len).map(|_| rng.sample(Alphanumeric) as char).collect()
+    }
+
+    // Replaces each `{param}` segment of a path template with a generated
+    // value, percent-encoded so that it stays one segment.
+    pub fn fill_path<R: Rng + ?Sized>(
+        &self,
+        template: &str,
+        param_schema: impl Fn(&str) -> Schema,
+        rng: &mut R,
+    ) -> String {
+        template
+            .split('/')
+            .map(
+                |segment| match segment.strip_prefix('{').and_then(|s| s.strip_suffix('}')) {
+                    Some(name) => encode_segment(&self.generate_param(&param_schema(name), rng)),
+                    None => segment.to_string(),
+                },
+            )
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+
+    // Path and query parameters are plain text, so strings are used as is and
+    // anything else is sent as its JSON text.
+    pub fn generate_param<R: Rng + ?Sized>(&self, schema: &Schema, rng: &mut R) -> String {
+        match self.generate(schema, rng) {
+            Value::String(s) => s,
+            other => other.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use std::collections::BTreeMap;
+
+    fn quiz() -> Schema {
+        Schema::Object {
+            properties: BTreeMap::from([
+                ("quizId".to_string(), Schema::Integer),
+                ("name".to_string(), Schema::String),
+                (
+                    "questions".to_string(),
+                    Schema::Array(Box::new(Schema::OneOf(vec![
+                        Schema::Boolean,
+                        Schema::Nullable(Box::new(Schema::Number)),
+                    ]))),
+                ),
+            ]),
+            required: vec!["quizId".to_string(), "name".to_string()],
+        }
+    }
+
+    fn depth(value: &Value) -> usize {
+        match value {
+            Value::Array(items) => 1 + items.iter().map(depth).max().unwrap_or(0),
+            Value::Object(map) => 1 + map.values().map(depth).max().unwrap_or(0),
+            _ => 0,
+        }
+    }
+
+    #[test]
+    fn generated_values_match_their_schema() {
+        let generator = Generator::default();
+        let schema = quiz();
+        for seed in 0..200 {
+            let value = generator.generate(&schema, &mut StdRng::seed_from_u64(seed));
+            assert_eq!(schema.validate(&value), Ok(()), "seed {}: {}", seed, value);
+        }
+    }
+
+    #[test]
+    fn limits_bound_strings_arrays_and_nesting() {
+        let generator = Generator::new(GeneratorProfile {
+            max_string_len: 3,
+            max_array_len: 2,
+            max_depth: 2,
+            ..ProfilePreset::Smoke.profile()
+        });
+        let nested = Schema::Array(Box::new(Schema::Array(Box::new(Schema::Array(Box::new(
+            Schema::String,
+        ))))));
+        let mut rng = StdRng::seed_from_u64(7);
+        for _ in 0..100 {
+            let value = generator.generate(&nested, &mut rng);
+            // Containers at the depth limit come out empty.
+            assert!(depth(&value) <= 3, "{}", value);
+            assert!(value.as_array().unwrap().len() <= 2);
+            match generator.generate(&Schema::String, &mut rng) {
+                Value::String(s) => assert!(s.len() <= 3),
+                other => panic!("expected a string, got {}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn path_placeholders_are_filled_from_their_schemas() {
+        let generator = Generator::default();
+        let mut rng = StdRng::seed_from_u64(1);
+        let path = generator.fill_path(
+            "/v1/admin/quiz/{quizid}/question/{questionid}",
+            |name| match name {
+                "quizid" => Schema::Integer,
+                _ => Schema::Null,
+            },
+            &mut rng,
+        );
+        let segments: Vec<&str> = path.split('/').collect();
+        assert_eq!(segments[..4], ["", "v1", "admin", "quiz"]);
+        assert!(segments[4].parse::<i64>().is_ok(), "{}", path);
+        assert_eq!(segments[5..], ["question", "null"]);
+    }
+
+    #[test]
+    fn optional_fields_past_the_limit_are_left_out() {
+        let wide = Schema::Object {
+            properties: (0..20)
+                .map(|i| (format!("f{:02}", i), Schema::Boolean))
+                .collect(),
+            required: vec!["f19".to_string()],
+        };
+        let generator = Generator::new(ProfilePreset::Smoke.profile());
+        let mut rng = StdRng::seed_from_u64(3);
+        for _ in 0..50 {
+            let value = generator.generate(&wide, &mut rng);
+            let fields = value.as_object().unwrap();
+            assert!(fields.contains_key("f19"));
+            assert!(fields.len() <= 9, "{}", value);
+        }
+    }
+
+    #[test]
+    fn edge_cases_replace_values_at_the_edge_rate() {
+        let edgy = Generator::new(GeneratorProfile {
+            edge_rate: 1.0,
+            ..GeneratorProfile::default()
+        });
+        let mut rng = StdRng::seed_from_u64(5);
+        let integers: Vec<Value> = (0..50)
+            .map(|_| edgy.generate(&Schema::Integer, &mut rng))
+            .collect();
+        assert!(integers.contains(&Value::from(i64::MAX)));
+        assert!(integers
+            .iter()
+            .all(|value| [0, -1, i64::MAX, i64::MIN, 9_007_199_254_740_993]
+                .contains(&value.as_i64().unwrap())));
+        // Containers have none of their own, but their contents do.
+        let value = edgy.generate(&Schema::Array(Box::new(Schema::Boolean)), &mut rng);
+        assert!(value.is_array());
+    }
+
+    #[test]
+    fn profiles_are_named_by_their_preset() {
+        assert_eq!(
+            "aggressive".parse::<ProfilePreset>(),
+            Ok(ProfilePreset::Aggressive)
+        );
+        assert!("wild".parse::<ProfilePreset>().is_err());
+        assert_eq!(
+            GeneratorProfile::default(),
+            ProfilePreset::Default.profile()
+        );
+        assert_eq!(ProfilePreset::Smoke.profile().edge_rate, 0.0);
+    }
+}
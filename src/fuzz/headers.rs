@@ -0,0 +1,121 @@
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use serde::{Deserialize, Serialize};
+
+// Size of the value sent by `HeaderPerturbation::Oversized`, past the 16 KiB
+// Node allows for all headers together.
+pub const OVERSIZED_HEADER_BYTES: usize = 64 * 1024;
+
+// Ways of mangling the session token's header, applied to whatever token the
+// case would otherwise send (or none). The servers' header parsing is compared
+// like anything else.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HeaderPerturbation {
+    Missing,
+    Empty,
+    // Surrounded by spaces and tabs.
+    Padded,
+    // With a zero-width space and an accented letter, sent as UTF-8.
+    Unicode,
+    // The header sent twice.
+    Duplicated,
+    Oversized,
+}
+
+impl HeaderPerturbation {
+    pub const ALL: [HeaderPerturbation; 6] = [
+        HeaderPerturbation::Missing,
+        HeaderPerturbation::Empty,
+        HeaderPerturbation::Padded,
+        HeaderPerturbation::Unicode,
+        HeaderPerturbation::Duplicated,
+        HeaderPerturbation::Oversized,
+    ];
+
+    pub fn apply(&self, headers: &mut HeaderMap, name: &HeaderName) {
+        let token = headers
+            .get(name)
+            .map_or_else(Vec::new, |value| value.as_bytes().to_vec());
+        let value = |bytes: Vec<u8>| {
+            HeaderValue::from_bytes(&bytes).expect("perturbed tokens are valid header bytes")
+        };
+        match self {
+            HeaderPerturbation::Missing => {
+                headers.remove(name);
+            }
+            HeaderPerturbation::Empty => {
+                headers.insert(name.clone(), HeaderValue::from_static(""));
+            }
+            HeaderPerturbation::Padded => {
+                let mut padded = b" \t".to_vec();
+                padded.extend_from_slice(&token);
+                padded.extend_from_slice(b"\t ");
+                headers.insert(name.clone(), value(padded));
+            }
+            HeaderPerturbation::Unicode => {
+                let mut unicode = token;
+                unicode.extend_from_slice("\u{200b}é".as_bytes());
+                headers.insert(name.clone(), value(unicode));
+            }
+            HeaderPerturbation::Duplicated => {
+                let token = value(token);
+                headers.insert(name.clone(), token.clone());
+                headers.append(name.clone(), token);
+            }
+            HeaderPerturbation::Oversized => {
+                let mut oversized = token;
+                oversized.resize(OVERSIZED_HEADER_BYTES.max(oversized.len()), b'a');
+                headers.insert(name.clone(), value(oversized));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn perturbed(perturbation: HeaderPerturbation, token: Option<&'static str>) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        if let Some(token) = token {
+            headers.insert("token", HeaderValue::from_static(token));
+        }
+        perturbation.apply(&mut headers, &HeaderName::from_static("token"));
+        headers
+    }
+
+    #[test]
+    fn perturbations_mangle_the_token_or_its_absence() {
+        let values = |headers: HeaderMap| -> Vec<Vec<u8>> {
+            headers
+                .get_all("token")
+                .iter()
+                .map(|value| value.as_bytes().to_vec())
+                .collect()
+        };
+        let token = Some("abc");
+        assert!(values(perturbed(HeaderPerturbation::Missing, token)).is_empty());
+        assert_eq!(values(perturbed(HeaderPerturbation::Empty, token)), [b""]);
+        assert_eq!(
+            values(perturbed(HeaderPerturbation::Padded, token)),
+            [b" \tabc\t "]
+        );
+        assert_eq!(
+            values(perturbed(HeaderPerturbation::Unicode, token)),
+            ["abc\u{200b}é".as_bytes()]
+        );
+        assert_eq!(
+            values(perturbed(HeaderPerturbation::Duplicated, token)),
+            [b"abc", b"abc"]
+        );
+        let oversized = values(perturbed(HeaderPerturbation::Oversized, token));
+        assert_eq!(oversized[0].len(), OVERSIZED_HEADER_BYTES);
+        assert!(oversized[0].starts_with(b"abca"));
+
+        // Without a token, the perturbation is of an empty one.
+        assert_eq!(
+            values(perturbed(HeaderPerturbation::Padded, None)),
+            [b" \t\t "]
+        );
+    }
+}
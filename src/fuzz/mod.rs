@@ -0,0 +1,9 @@
+pub mod generator;
+pub mod headers;
+pub mod mutator;
+pub mod paths;
+pub mod presets;
+pub mod protocol;
+pub mod scheduler;
+pub mod shrink;
+pub mod template;
@@ -0,0 +1,307 @@
+use crate::models::pointer::pointer_child;
+use rand::{Rng, RngCore};
+use serde_json::{Map, Value};
+
+// A single kind of mutation applied to a known-good example body. Custom
+// mutations can be plugged in with `MutationEngine::with`.
+pub trait Mutator: Send + Sync {
+    fn name(&self) -> &str;
+    fn mutate(&self, value: &mut Value, rng: &mut dyn RngCore);
+}
+
+pub struct MutationEngine {
+    mutators: Vec<Box<dyn Mutator>>,
+    max_mutations: usize,
+}
+
+impl Default for MutationEngine {
+    fn default() -> Self {
+        Self {
+            mutators: vec![
+                Box::new(DropKey),
+                Box::new(ChangeType),
+                Box::new(HugeString),
+                Box::new(NegativeNumber),
+                Box::new(Unicode),
+                Box::new(Injection),
+            ],
+            max_mutations: 3,
+        }
+    }
+}
+
+impl MutationEngine {
+    pub fn empty() -> Self {
+        Self {
+            mutators: Vec::new(),
+            max_mutations: 3,
+        }
+    }
+
+    pub fn with(mut self, mutator: impl Mutator + 'static) -> Self {
+        self.mutators.push(Box::new(mutator));
+        self
+    }
+
+    pub fn with_max_mutations(mut self, max_mutations: usize) -> Self {
+        self.max_mutations = max_mutations.max(1);
+        self
+    }
+
+    pub fn mutators(&self) -> impl Iterator<Item = &dyn Mutator> {
+        self.mutators.iter().map(|m| m.as_ref())
+    }
+
+    // Applies between one and `max_mutations` randomly chosen mutations.
+    pub fn mutate(&self, example: &Value, rng: &mut dyn RngCore) -> Value {
+        let mut value = example.clone();
+        if self.mutators.is_empty() {
+            return value;
+        }
+        for _ in 0..rng.gen_range(1..=self.max_mutations) {
+            let mutator = &self.mutators[rng.gen_range(0..self.mutators.len())];
+            mutator.mutate(&mut value, rng);
+        }
+        value
+    }
+}
+
+// Picks a random node matching `filter`, falling back to any node at all so
+// that every mutation always changes something.
+fn pick<'a>(
+    value: &'a mut Value,
+    rng: &mut dyn RngCore,
+    filter: impl Fn(&Value) -> bool,
+) -> &'a mut Value {
+    let mut all = Vec::new();
+    collect_pointers(value, String::new(), &mut all);
+    let matching: Vec<&String> = all
+        .iter()
+        .filter(|p| value.pointer(p).is_some_and(&filter))
+        .collect();
+
+    let pointer = if matching.is_empty() {
+        all[rng.gen_range(0..all.len())].clone()
+    } else {
+        matching[rng.gen_range(0..matching.len())].clone()
+    };
+    value
+        .pointer_mut(&pointer)
+        .expect("pointer was just collected")
+}
+
+fn collect_pointers(value: &Value, path: String, out: &mut Vec<String>) {
+    match value {
+        Value::Object(map) => {
+            for (key, child) in map {
+                collect_pointers(child, pointer_child(&path, key), out);
+            }
+        }
+        Value::Array(items) => {
+            for (i, child) in items.iter().enumerate() {
+                collect_pointers(child, pointer_child(&path, &i.to_string()), out);
+            }
+        }
+        _ => {}
+    }
+    out.push(path);
+}
+
+fn choose<'a>(options: &[&'a str], rng: &mut dyn RngCore) -> &'a str {
+    options[rng.gen_range(0..options.len())]
+}
+
+pub struct DropKey;
+
+impl Mutator for DropKey {
+    fn name(&self) -> &str {
+        "drop-key"
+    }
+
+    fn mutate(&self, value: &mut Value, rng: &mut dyn RngCore) {
+        let node = pick(value, rng, |v| v.as_object().is_some_and(|m| !m.is_empty()));
+        if let Value::Object(map) = node {
+            if !map.is_empty() {
+                let key = map.keys().nth(rng.gen_range(0..map.len())).cloned();
+                if let Some(key) = key {
+                    map.remove(&key);
+                }
+            }
+        }
+    }
+}
+
+pub struct ChangeType;
+
+impl Mutator for ChangeType {
+    fn name(&self) -> &str {
+        "change-type"
+    }
+
+    fn mutate(&self, value: &mut Value, rng: &mut dyn RngCore) {
+        let node = pick(value, rng, |_| true);
+        let options = [
+            Value::Null,
+            Value::Bool(true),
+            Value::from(0),
+            Value::from(1.5),
+            Value::String(String::new()),
+            Value::Array(Vec::new()),
+            Value::Object(Map::new()),
+        ];
+        let different: Vec<&Value> = options
+            .iter()
+            .filter(|o| std::mem::discriminant(*o) != std::mem::discriminant(&*node))
+            .collect();
+        *node = different[rng.gen_range(0..different.len())].clone();
+    }
+}
+
+pub struct HugeString;
+
+impl Mutator for HugeString {
+    fn name(&self) -> &str {
+        "huge-string"
+    }
+
+    fn mutate(&self, value: &mut Value, rng: &mut dyn RngCore) {
+        let node = pick(value, rng, Value::is_string);
+        let len = 1 << rng.gen_range(10..=16);
+        *node = Value::String("A".repeat(len));
+    }
+}
+
+pub struct NegativeNumber;
+
+impl Mutator for NegativeNumber {
+    fn name(&self) -> &str {
+        "negative-number"
+    }
+
+    fn mutate(&self, value: &mut Value, rng: &mut dyn RngCore) {
+        let node = pick(value, rng, Value::is_number);
+        *node = match rng.gen_range(0..3) {
+            0 => Value::from(-1),
+            1 => Value::from(i64::MIN),
+            _ => {
+                let current = node.as_f64().unwrap_or(1.0).abs().max(1.0);
+                Value::from(-(current as i64))
+            }
+        };
+    }
+}
+
+pub struct Unicode;
+
+impl Mutator for Unicode {
+    fn name(&self) -> &str {
+        "unicode"
+    }
+
+    fn mutate(&self, value: &mut Value, rng: &mut dyn RngCore) {
+        let node = pick(value, rng, Value::is_string);
+        let samples = [
+            "ñandú",
+            "日本語のテキスト",
+            "🙂🙃🔥",
+            "e\u{301}\u{301}\u{301}",
+            "\u{202e}txet lortnoc",
+            "\u{0}",
+            "\u{feff}bom",
+            "Ω≈ç√∫",
+        ];
+        let sample = choose(&samples, rng);
+        *node = match node.as_str() {
+            Some(existing) if rng.gen_bool(0.5) => Value::String(format!("{}{}", existing, sample)),
+            _ => Value::String(sample.to_string()),
+        };
+    }
+}
+
+pub struct Injection;
+
+impl Mutator for Injection {
+    fn name(&self) -> &str {
+        "injection"
+    }
+
+    fn mutate(&self, value: &mut Value, rng: &mut dyn RngCore) {
+        let node = pick(value, rng, Value::is_string);
+        let payloads = [
+            "' OR '1'='1",
+            "'; DROP TABLE users; --",
+            "\" OR \"\"=\"",
+            "<script>alert(1)</script>",
+            "${7*7}",
+            "{{7*7}}",
+            "__proto__",
+            "constructor",
+            "../../../../etc/passwd",
+            "%s%s%s%n",
+        ];
+        *node = Value::String(choose(&payloads, rng).to_string());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::FuzzRng;
+    use serde_json::json;
+
+    fn example() -> Value {
+        json!({"name": "quiz", "score": 10, "tags": ["a", "b"], "meta": {"draft": false}})
+    }
+
+    #[test]
+    fn every_mutator_changes_the_example() {
+        for mutator in MutationEngine::default().mutators() {
+            for seed in 0..20 {
+                let mut value = example();
+                mutator.mutate(&mut value, &mut FuzzRng::new(seed));
+                assert_ne!(
+                    value,
+                    example(),
+                    "{} left the example alone",
+                    mutator.name()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn mutators_prefer_nodes_of_their_kind() {
+        let mut rng = FuzzRng::new(3);
+        let mut value = example();
+        NegativeNumber.mutate(&mut value, &mut rng);
+        assert!(value["score"].as_i64().is_some_and(|n| n < 0));
+
+        let mut value = json!({"name": "quiz", "score": 10});
+        HugeString.mutate(&mut value, &mut rng);
+        assert!(value["name"].as_str().is_some_and(|s| s.len() >= 1024));
+        assert_eq!(value["score"], json!(10));
+    }
+
+    #[test]
+    fn scalars_are_mutated_in_place() {
+        let mut value = json!("quiz");
+        ChangeType.mutate(&mut value, &mut FuzzRng::new(0));
+        assert!(!value.is_string());
+    }
+
+    #[test]
+    fn mutation_is_deterministic_and_leaves_the_example_untouched() {
+        let engine = MutationEngine::default().with_max_mutations(5);
+        let example = example();
+        let first = engine.mutate(&example, &mut FuzzRng::new(9));
+        let again = engine.mutate(&example, &mut FuzzRng::new(9));
+        assert_eq!(first, again);
+        assert_eq!(example, self::example());
+    }
+
+    #[test]
+    fn an_empty_engine_returns_the_example() {
+        let engine = MutationEngine::empty().with_max_mutations(0);
+        assert_eq!(engine.mutate(&example(), &mut FuzzRng::new(0)), example());
+    }
+}
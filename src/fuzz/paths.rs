@@ -0,0 +1,142 @@
+use std::fmt::Write as _;
+
+// Length `PathPerturbation::Oversized` pads a segment to, well past what a
+// route parameter ever holds but under Node's 16 KiB limit on the request
+// line and headers together.
+pub const LONG_SEGMENT_BYTES: usize = 8 * 1024;
+
+// Adversarial values for one path parameter, built on the value generated for
+// it and already percent-encoded, so each stays a single segment whatever it
+// decodes to. Which route the servers pick for it, if any, is compared like
+// anything else.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PathPerturbation {
+    // `1%2F1`, a slash once decoded.
+    EncodedSlash,
+    // `..%2F..%2Fclear`, climbing out of the route once decoded. A bare `..`
+    // would never reach the server: URLs drop dot segments while parsing.
+    Traversal,
+    Oversized,
+    Emoji,
+    // An `e` followed by a combining acute accent rather than a precomposed
+    // `é`.
+    Decomposed,
+    // `%00` after the value.
+    NullByte,
+    // `1%252F1`, which is only a slash if decoded twice.
+    DoubleEncoded,
+}
+
+impl PathPerturbation {
+    pub const ALL: [PathPerturbation; 7] = [
+        PathPerturbation::EncodedSlash,
+        PathPerturbation::Traversal,
+        PathPerturbation::Oversized,
+        PathPerturbation::Emoji,
+        PathPerturbation::Decomposed,
+        PathPerturbation::NullByte,
+        PathPerturbation::DoubleEncoded,
+    ];
+
+    // `segment` is the encoded value generated for the parameter.
+    pub fn apply(&self, segment: &str) -> String {
+        match self {
+            PathPerturbation::EncodedSlash => format!("{}%2F{}", segment, segment),
+            PathPerturbation::Traversal => "..%2F..%2Fclear".to_string(),
+            PathPerturbation::Oversized => {
+                let mut long = segment.to_string();
+                while long.len() < LONG_SEGMENT_BYTES {
+                    long.push('a');
+                }
+                long
+            }
+            PathPerturbation::Emoji => format!("{}{}", segment, encode_segment("🦀")),
+            PathPerturbation::Decomposed => {
+                format!("{}{}", segment, encode_segment("e\u{301}"))
+            }
+            PathPerturbation::NullByte => format!("{}%00", segment),
+            PathPerturbation::DoubleEncoded => format!("{}%252F{}", segment, segment),
+        }
+    }
+}
+
+// Characters a path segment may hold as they are (RFC 3986 `pchar`, less `%`).
+fn is_segment_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "-._~!$&'()*+,;=:@".contains(c)
+}
+
+// `value` as one path segment: everything else, including `/` and `%`, is
+// percent-encoded.
+pub fn encode_segment(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for c in value.chars() {
+        if is_segment_char(c) {
+            out.push(c);
+        } else {
+            push_encoded(&mut out, c);
+        }
+    }
+    out
+}
+
+// `path` with anything a URL parser would strip, reinterpret or mangle (such
+// as whitespace, control characters, `#` or a `%` not starting an escape)
+// percent-encoded. Slashes, escapes already in place and a query after `?`
+// are left alone, so encoded paths come through unchanged.
+pub fn escape_path(path: &str) -> String {
+    let bytes = path.as_bytes();
+    let mut out = String::with_capacity(path.len());
+    for (i, c) in path.char_indices() {
+        let escape = c == '%'
+            && bytes.len() > i + 2
+            && bytes[i + 1].is_ascii_hexdigit()
+            && bytes[i + 2].is_ascii_hexdigit();
+        if escape || c == '/' || c == '?' || is_segment_char(c) {
+            out.push(c);
+        } else {
+            push_encoded(&mut out, c);
+        }
+    }
+    out
+}
+
+fn push_encoded(out: &mut String, c: char) {
+    let mut buf = [0; 4];
+    for byte in c.encode_utf8(&mut buf).bytes() {
+        let _ = write!(out, "%{:02X}", byte);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn perturbations_stay_one_segment() {
+        for perturbation in PathPerturbation::ALL {
+            let segment = perturbation.apply("12");
+            assert!(!segment.contains('/'), "{:?}: {}", perturbation, segment);
+            assert!(segment.is_ascii(), "{:?}: {}", perturbation, segment);
+        }
+        assert_eq!(PathPerturbation::EncodedSlash.apply("1"), "1%2F1");
+        assert_eq!(PathPerturbation::DoubleEncoded.apply("1"), "1%252F1");
+        assert_eq!(PathPerturbation::Emoji.apply("1"), "1%F0%9F%A6%80");
+        assert_eq!(PathPerturbation::Decomposed.apply("1"), "1e%CC%81");
+        assert_eq!(
+            PathPerturbation::Oversized.apply("1").len(),
+            LONG_SEGMENT_BYTES
+        );
+    }
+
+    #[test]
+    fn paths_are_escaped_without_double_encoding() {
+        assert_eq!(encode_segment("a/b c%"), "a%2Fb%20c%25");
+        assert_eq!(encode_segment("quiz-1_~"), "quiz-1_~");
+        assert_eq!(
+            escape_path("quiz/a b#c/1%2F1?x=1"),
+            "quiz/a%20b%23c/1%2F1?x=1"
+        );
+        assert_eq!(escape_path("50%"), "50%25");
+        assert_eq!(escape_path("é"), "%C3%A9");
+    }
+}
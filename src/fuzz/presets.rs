@@ -0,0 +1,317 @@
+use crate::schema::Schema;
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use serde::Deserialize;
+use serde_json::Value;
+
+// Field types from the COMP1531 spec with known validation rules. Generated
+// values are a mix of valid inputs and values sitting just either side of each
+// rule, which is where student implementations tend to disagree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Preset {
+    Email,
+    // At least 8 characters with at least one letter and one number.
+    Password,
+    // nameFirst / nameLast: 2-20 letters, spaces, hyphens or apostrophes.
+    Name,
+    // 3-30 alphanumeric characters or spaces.
+    QuizName,
+    // At most 100 characters.
+    QuizDescription,
+    Id,
+}
+
+impl Preset {
+    pub fn parse(name: &str) -> Option<Self> {
+        serde_json::from_value(Value::String(name.to_string())).ok()
+    }
+
+    // The preset for a body or query field of the quiz API, by its name.
+    pub fn for_field(name: &str) -> Option<Self> {
+        match name {
+            "email" | "userEmail" => Some(Preset::Email),
+            "password" | "oldPassword" | "newPassword" => Some(Preset::Password),
+            "nameFirst" | "nameLast" => Some(Preset::Name),
+            "name" => Some(Preset::QuizName),
+            "description" => Some(Preset::QuizDescription),
+            _ => None,
+        }
+    }
+
+    pub fn base_schema(&self) -> Schema {
+        match self {
+            Preset::Id => Schema::Integer,
+            _ => Schema::String,
+        }
+    }
+
+    pub fn generate<R: Rng + ?Sized>(&self, rng: &mut R) -> Value {
+        let edge_cases = self.edge_cases();
+        if rng.gen_bool(0.5) && !edge_cases.is_empty() {
+            edge_cases[rng.gen_range(0..edge_cases.len())].clone()
+        } else {
+            self.valid(rng)
+        }
+    }
+
+    pub fn valid<R: Rng + ?Sized>(&self, rng: &mut R) -> Value {
+        match self {
+            Preset::Email => Value::String(format!(
+                "{}@{}.com",
+                alphanumeric(rng, 3..12).to_lowercase(),
+                alphanumeric(rng, 3..8).to_lowercase()
+            )),
+            Preset::Password => {
+                // At least 7 letters and a digit, meeting the 8 character minimum.
+                let mut password = letters(rng, 7..12);
+                password.push_str(&rng.gen_range(0..1000).to_string());
+                Value::String(password)
+            }
+            Preset::Name => Value::String(capitalise(&letters(rng, 2..=20))),
+            Preset::QuizName => Value::String(alphanumeric(rng, 3..=30)),
+            Preset::QuizDescription => Value::String(letters(rng, 0..=100)),
+            Preset::Id => Value::from(rng.gen_range(0..10_000)),
+        }
+    }
+
+    // A valid value whose length (for `Id`, the value itself) is between `min`
+    // and `max`, inclusive, even where the spec would reject that length.
+    pub fn valid_within<R: Rng + ?Sized>(&self, rng: &mut R, min: i64, max: i64) -> Value {
+        let len = || min.max(0) as usize..=max.max(0) as usize;
+        match self {
+            Preset::Email => Value::String(format!(
+                "{}@unsw.edu.au",
+                alphanumeric(rng, len()).to_lowercase()
+            )),
+            Preset::Password => {
+                let len = rng.gen_range(len());
+                let mut password = letters(rng, len.saturating_sub(1)..=len.saturating_sub(1));
+                if len > 0 {
+                    password.push(rng.gen_range(b'0'..=b'9') as char);
+                }
+                Value::String(password)
+            }
+            Preset::Name => Value::String(capitalise(&letters(rng, len()))),
+            Preset::QuizName => Value::String(alphanumeric(rng, len())),
+            Preset::QuizDescription => Value::String(letters(rng, len())),
+            Preset::Id => Value::from(rng.gen_range(min..=max)),
+        }
+    }
+
+    // Values the spec requires a 400 for, unlike `edge_cases`, which also has
+    // valid values right at the limits.
+    pub fn invalid(&self) -> Vec<Value> {
+        let strings: Vec<String> = match self {
+            Preset::Email => vec![
+                String::new(),
+                "not-an-email".to_string(),
+                "user@".to_string(),
+                "@unsw.edu.au".to_string(),
+                "user name@unsw.edu.au".to_string(),
+                "user@@unsw.edu.au".to_string(),
+            ],
+            Preset::Password => vec![
+                String::new(),
+                "abc1234".to_string(),
+                "abcdefgh".to_string(),
+                "12345678".to_string(),
+            ],
+            Preset::Name => vec![
+                String::new(),
+                "A".to_string(),
+                "A".repeat(21),
+                "N@me".to_string(),
+                "Name1".to_string(),
+            ],
+            Preset::QuizName => vec![
+                String::new(),
+                "ab".to_string(),
+                "a".repeat(31),
+                "Quiz!".to_string(),
+                "Quiz_Name".to_string(),
+            ],
+            Preset::QuizDescription => vec!["a".repeat(101), "a".repeat(1000)],
+            Preset::Id => return Vec::new(),
+        };
+        strings.into_iter().map(Value::String).collect()
+    }
+
+    pub fn edge_cases(&self) -> Vec<Value> {
+        let strings: Vec<String> = match self {
+            Preset::Email => vec![
+                String::new(),
+                "not-an-email".to_string(),
+                "user@".to_string(),
+                "@unsw.edu.au".to_string(),
+                "user name@unsw.edu.au".to_string(),
+                "user@@unsw.edu.au".to_string(),
+                "user@unsw".to_string(),
+                format!("{}@unsw.edu.au", "a".repeat(300)),
+                "HAYDEN@UNSW.EDU.AU".to_string(),
+            ],
+            Preset::Password => vec![
+                String::new(),
+                "abc1234".to_string(),
+                "abcdefg1".to_string(),
+                "abcdefgh".to_string(),
+                "12345678".to_string(),
+                "        ".to_string(),
+                "pässwörd1".to_string(),
+            ],
+            Preset::Name => vec![
+                String::new(),
+                "A".to_string(),
+                "Al".to_string(),
+                "A".repeat(20),
+                "A".repeat(21),
+                "Mary-Jane O'Neil".to_string(),
+                "N@me".to_string(),
+                "Name1".to_string(),
+                "名字".to_string(),
+                "  ".to_string(),
+            ],
+            Preset::QuizName => vec![
+                String::new(),
+                "ab".to_string(),
+                "abc".to_string(),
+                "a".repeat(30),
+                "a".repeat(31),
+                "Quiz!".to_string(),
+                "Quiz_Name".to_string(),
+                "   ".to_string(),
+            ],
+            Preset::QuizDescription => vec![
+                String::new(),
+                "a".repeat(100),
+                "a".repeat(101),
+                "a".repeat(1000),
+            ],
+            Preset::Id => {
+                return vec![
+                    Value::from(0),
+                    Value::from(-1),
+                    Value::from(i64::MAX),
+                    Value::from(1.5),
+                    Value::String("1".to_string()),
+                ]
+            }
+        };
+        strings.into_iter().map(Value::String).collect()
+    }
+}
+
+fn alphanumeric<R: Rng + ?Sized>(
+    rng: &mut R,
+    len: impl rand::distributions::uniform::SampleRange<usize>,
+) -> String {
+    let len = rng.gen_range(len);
+    (0..len).map(|_| rng.sample(Alphanumeric) as char).collect()
+}
+
+fn letters<R: Rng + ?Sized>(
+    rng: &mut R,
+    len: impl rand::distributions::uniform::SampleRange<usize>,
+) -> String {
+    let len = rng.gen_range(len);
+    (0..len)
+        .map(|_| rng.gen_range(b'a'..=b'z') as char)
+        .collect()
+}
+
+fn capitalise(s: &str) -> String {
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::FuzzRng;
+
+    fn valid_strings(preset: Preset) -> Vec<String> {
+        let mut rng = FuzzRng::new(0);
+        (0..200)
+            .map(|_| preset.valid(&mut rng).as_str().unwrap().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn valid_values_follow_the_spec_rules() {
+        for password in valid_strings(Preset::Password) {
+            assert!(password.len() >= 8, "{password}");
+            assert!(password.chars().any(|c| c.is_ascii_alphabetic()));
+            assert!(password.chars().any(|c| c.is_ascii_digit()));
+        }
+        for name in valid_strings(Preset::Name) {
+            assert!((2..=20).contains(&name.len()), "{name}");
+        }
+        for name in valid_strings(Preset::QuizName) {
+            assert!((3..=30).contains(&name.len()), "{name}");
+            assert!(name.chars().all(|c| c.is_ascii_alphanumeric()));
+        }
+        for description in valid_strings(Preset::QuizDescription) {
+            assert!(description.len() <= 100);
+        }
+        for email in valid_strings(Preset::Email) {
+            assert_eq!(email.matches('@').count(), 1, "{email}");
+        }
+    }
+
+    #[test]
+    fn generated_values_mix_valid_inputs_and_edge_cases() {
+        let mut rng = FuzzRng::new(0);
+        let edge_cases = Preset::QuizName.edge_cases();
+        let values: Vec<Value> = (0..100)
+            .map(|_| Preset::QuizName.generate(&mut rng))
+            .collect();
+        assert!(values.iter().any(|v| edge_cases.contains(v)));
+        assert!(values.iter().any(|v| !edge_cases.contains(v)));
+    }
+
+    #[test]
+    fn presets_parse_from_their_config_names() {
+        assert_eq!(Preset::parse("quiz_name"), Some(Preset::QuizName));
+        assert_eq!(Preset::parse("email"), Some(Preset::Email));
+        assert_eq!(Preset::parse("quizName"), None);
+        assert_eq!(Preset::Id.base_schema(), Schema::Integer);
+    }
+
+    #[test]
+    fn invalid_values_are_never_valid_ones() {
+        for preset in [
+            Preset::Email,
+            Preset::Password,
+            Preset::Name,
+            Preset::QuizName,
+            Preset::QuizDescription,
+        ] {
+            let invalid = preset.invalid();
+            assert!(!invalid.is_empty());
+            for valid in valid_strings(preset) {
+                assert!(!invalid.contains(&Value::String(valid)));
+            }
+        }
+        assert!(Preset::Id.invalid().is_empty());
+        assert_eq!(Preset::for_field("newPassword"), Some(Preset::Password));
+        assert_eq!(Preset::for_field("quizId"), None);
+    }
+
+    #[test]
+    fn valid_values_within_a_range_have_that_length() {
+        let mut rng = FuzzRng::new(0);
+        for _ in 0..50 {
+            let name = Preset::QuizName.valid_within(&mut rng, 40, 45);
+            assert!((40..=45).contains(&name.as_str().unwrap().len()));
+            let password = Preset::Password.valid_within(&mut rng, 4, 4);
+            let password = password.as_str().unwrap();
+            assert_eq!(password.len(), 4);
+            assert!(password.ends_with(|c: char| c.is_ascii_digit()));
+            let id = Preset::Id.valid_within(&mut rng, -2, 2).as_i64().unwrap();
+            assert!((-2..=2).contains(&id));
+        }
+    }
+}
@@ -0,0 +1,122 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+// Size of the body sent by `Malformation::Huge`.
+pub const HUGE_BODY_BYTES: usize = 16 * 1024 * 1024;
+
+// Ways of breaking a request below the JSON layer. Each is derived from the
+// case's JSON body, so a recorded case can be reproduced from the body and the
+// malformation alone.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Malformation {
+    TruncatedJson,
+    InvalidUtf8,
+    // Valid JSON sent as `text/plain`.
+    TextPlainJson,
+    Huge,
+    Empty,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RawBody {
+    pub bytes: Vec<u8>,
+    pub content_type: Option<&'static str>,
+}
+
+impl Malformation {
+    pub const ALL: [Malformation; 5] = [
+        Malformation::TruncatedJson,
+        Malformation::InvalidUtf8,
+        Malformation::TextPlainJson,
+        Malformation::Huge,
+        Malformation::Empty,
+    ];
+
+    pub fn apply(&self, body: Option<&Value>) -> RawBody {
+        let json = body
+            .map(Value::to_string)
+            .unwrap_or_else(|| "{\"token\":\"\"}".to_string());
+        let (bytes, content_type) = match self {
+            Malformation::TruncatedJson => {
+                let mut bytes = json.into_bytes();
+                bytes.truncate(bytes.len() / 2);
+                (bytes, "application/json")
+            }
+            Malformation::InvalidUtf8 => {
+                // Lone continuation bytes inside the first string literal, or
+                // at the end if there is none.
+                let mut bytes = json.into_bytes();
+                let at = bytes
+                    .iter()
+                    .position(|&b| b == b'"')
+                    .map_or(bytes.len(), |i| i + 1);
+                bytes.splice(at..at, [0xff, 0xfe, 0x80]);
+                (bytes, "application/json")
+            }
+            Malformation::TextPlainJson => (json.into_bytes(), "text/plain"),
+            Malformation::Huge => {
+                let padding = HUGE_BODY_BYTES.saturating_sub(json.len());
+                let mut bytes = Vec::with_capacity(HUGE_BODY_BYTES + 16);
+                bytes.extend_from_slice(b"{\"padding\":\"");
+                bytes.resize(bytes.len() + padding, b'a');
+                bytes.extend_from_slice(b"\",\"body\":");
+                bytes.extend_from_slice(json.as_bytes());
+                bytes.push(b'}');
+                (bytes, "application/json")
+            }
+            Malformation::Empty => (Vec::new(), "application/json"),
+        };
+        RawBody {
+            bytes,
+            content_type: Some(content_type),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn malformed_bodies_are_not_valid_json() {
+        let body = json!({"name": "quiz"});
+        for malformation in [
+            Malformation::TruncatedJson,
+            Malformation::InvalidUtf8,
+            Malformation::Empty,
+        ] {
+            let raw = malformation.apply(Some(&body));
+            assert!(serde_json::from_slice::<Value>(&raw.bytes).is_err());
+            assert_eq!(raw.content_type, Some("application/json"));
+        }
+    }
+
+    #[test]
+    fn invalid_utf8_goes_inside_the_first_string() {
+        let raw = Malformation::InvalidUtf8.apply(Some(&json!({"a": 1})));
+        assert_eq!(raw.bytes, b"{\"\xff\xfe\x80a\":1}");
+        let raw = Malformation::InvalidUtf8.apply(Some(&json!(1)));
+        assert_eq!(raw.bytes, b"1\xff\xfe\x80");
+    }
+
+    #[test]
+    fn well_formed_variants_keep_the_body() {
+        let body = json!({"name": "quiz"});
+        let raw = Malformation::TextPlainJson.apply(Some(&body));
+        assert_eq!(raw.content_type, Some("text/plain"));
+        assert_eq!(serde_json::from_slice::<Value>(&raw.bytes).unwrap(), body);
+
+        let raw = Malformation::Huge.apply(Some(&body));
+        assert!(raw.bytes.len() >= HUGE_BODY_BYTES);
+        let parsed: Value = serde_json::from_slice(&raw.bytes).unwrap();
+        assert_eq!(parsed["body"], body);
+    }
+
+    #[test]
+    fn a_missing_body_falls_back_to_a_placeholder() {
+        let raw = Malformation::TextPlainJson.apply(None);
+        assert_eq!(raw.bytes, b"{\"token\":\"\"}");
+    }
+}
@@ -0,0 +1,112 @@
+use crate::models::endpoints;
+use crate::rng::FuzzRng;
+use rand::distributions::{Distribution, WeightedIndex};
+use rand::seq::SliceRandom;
+use reqwest::Method;
+use serde::Deserialize;
+
+pub const REGISTER_PATH: &str = "/admin/auth/register";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Schedule {
+    // Every target once per iteration, in order (or shuffled).
+    #[default]
+    RoundRobin,
+    // As many draws per iteration as there are targets, each in proportion to
+    // the target's weight. Targets that need a session are held back until a
+    // registration has succeeded on the server under test.
+    Weighted,
+    // As many draws per iteration as there are targets, all equally likely.
+    Uniform,
+}
+
+// Relative weights by route; targets matching no rule weigh 1. A rule without
+// a method covers every method of its path.
+#[derive(Debug, Clone, Default)]
+pub struct Weights {
+    rules: Vec<(Option<Method>, String, f64)>,
+}
+
+impl Weights {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with(mut self, method: Option<Method>, path: impl Into<String>, weight: f64) -> Self {
+        self.rules.push((method, path.into(), weight.max(0.0)));
+        self
+    }
+
+    // A rule naming the method wins over one for the whole path.
+    pub fn weight(&self, method: &Method, path: &str) -> f64 {
+        let rule = |with_method: Option<&Method>| {
+            self.rules
+                .iter()
+                .find(|(m, p, _)| p == path && m.as_ref() == with_method)
+        };
+        rule(Some(method))
+            .or_else(|| rule(None))
+            .map_or(1.0, |(_, _, weight)| *weight)
+    }
+}
+
+pub fn is_register(method: &Method, path: &str) -> bool {
+    *method == Method::POST && path == REGISTER_PATH
+}
+
+// Routes of the quiz API that fail without a session anyway.
+pub fn needs_session(path: &str) -> bool {
+    endpoints::resolve(path).is_some_and(|endpoint| endpoint.requires_auth)
+}
+
+// `count` draws from `eligible` (indices with their weights). When every
+// weight is zero, each index is equally likely instead.
+pub fn draw(rng: &mut FuzzRng, eligible: &[(usize, f64)], count: usize) -> Vec<usize> {
+    match WeightedIndex::new(eligible.iter().map(|(_, weight)| *weight)) {
+        Ok(distribution) => (0..count)
+            .map(|_| eligible[distribution.sample(rng)].0)
+            .collect(),
+        Err(_) => (0..count)
+            .filter_map(|_| eligible.choose(rng).map(|(index, _)| *index))
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn method_rules_win_over_path_rules() {
+        let weights = Weights::new()
+            .with(None, "/admin/quiz", 2.0)
+            .with(Some(Method::POST), "/admin/quiz", 5.0)
+            .with(None, "/clear", -3.0);
+        assert_eq!(weights.weight(&Method::POST, "/admin/quiz"), 5.0);
+        assert_eq!(weights.weight(&Method::GET, "/admin/quiz"), 2.0);
+        assert_eq!(weights.weight(&Method::DELETE, "/clear"), 0.0);
+        assert_eq!(weights.weight(&Method::GET, "/admin/quiz/list"), 1.0);
+    }
+
+    #[test]
+    fn draws_follow_the_weights_or_fall_back_to_uniform() {
+        let mut rng = FuzzRng::new(7);
+        let drawn = draw(&mut rng, &[(0, 0.0), (1, 1.0), (2, 0.0)], 50);
+        assert_eq!(drawn.len(), 50);
+        assert!(drawn.iter().all(|index| *index == 1));
+
+        let drawn = draw(&mut rng, &[(3, 0.0), (4, 0.0)], 50);
+        assert!(drawn.contains(&3) && drawn.contains(&4));
+        assert!(draw(&mut rng, &[], 5).is_empty());
+    }
+
+    #[test]
+    fn only_quiz_routes_need_a_session() {
+        assert!(is_register(&Method::POST, REGISTER_PATH));
+        assert!(!is_register(&Method::GET, REGISTER_PATH));
+        assert!(needs_session("/admin/quiz/list"));
+        assert!(!needs_session(REGISTER_PATH));
+        assert!(!needs_session("/not/a/route"));
+    }
+}
@@ -0,0 +1,241 @@
+use crate::models::client::{TestCase, Tester, TesterError};
+use serde_json::{Number, Value};
+use std::future::Future;
+use std::mem::discriminant;
+
+pub const DEFAULT_MAX_ATTEMPTS: usize = 200;
+
+// Greedily applies the first simplification that still reproduces, until no
+// candidate does or the attempt budget runs out.
+pub async fn shrink<F, Fut>(value: Value, mut reproduces: F, max_attempts: usize) -> Value
+where
+    F: FnMut(Value) -> Fut,
+    Fut: Future<Output = bool>,
+{
+    let mut current = value;
+    let mut attempts = 0;
+
+    'outer: loop {
+        for candidate in candidates(&current) {
+            if attempts >= max_attempts {
+                break 'outer;
+            }
+            attempts += 1;
+            if reproduces(candidate.clone()).await {
+                current = candidate;
+                continue 'outer;
+            }
+        }
+        break;
+    }
+
+    current
+}
+
+// Candidates are ordered roughly from the largest reduction to the smallest.
+pub fn candidates(value: &Value) -> Vec<Value> {
+    let mut out = Vec::new();
+
+    match value {
+        Value::Object(map) => {
+            for key in map.keys() {
+                let mut smaller = map.clone();
+                smaller.remove(key);
+                out.push(Value::Object(smaller));
+            }
+            for (key, child) in map {
+                for replacement in candidates(child) {
+                    let mut smaller = map.clone();
+                    smaller.insert(key.clone(), replacement);
+                    out.push(Value::Object(smaller));
+                }
+            }
+        }
+        Value::Array(items) => {
+            if !items.is_empty() {
+                out.push(Value::Array(Vec::new()));
+            }
+            if items.len() > 1 {
+                let mid = items.len() / 2;
+                out.push(Value::Array(items[..mid].to_vec()));
+                out.push(Value::Array(items[mid..].to_vec()));
+                for i in 0..items.len() {
+                    let mut smaller = items.clone();
+                    smaller.remove(i);
+                    out.push(Value::Array(smaller));
+                }
+            }
+            for (i, child) in items.iter().enumerate() {
+                for replacement in candidates(child) {
+                    let mut smaller = items.clone();
+                    smaller[i] = replacement;
+                    out.push(Value::Array(smaller));
+                }
+            }
+        }
+        Value::String(s) => {
+            if !s.is_empty() {
+                out.push(Value::String(String::new()));
+            }
+            let len = s.chars().count();
+            if len > 1 {
+                out.push(Value::String(s.chars().take(len / 2).collect()));
+            }
+        }
+        Value::Number(n) => {
+            if let Some(i) = n.as_i64() {
+                if i != 0 {
+                    out.push(Value::from(0));
+                }
+                if i.abs() > 1 {
+                    out.push(Value::from(i / 2));
+                }
+            } else if let Some(f) = n.as_f64() {
+                out.push(Value::from(0));
+                // `2.0` truncates to itself, which would never shrink.
+                if f.trunc() != f {
+                    if let Some(truncated) = Number::from_f64(f.trunc()) {
+                        out.push(Value::Number(truncated));
+                    }
+                }
+            }
+        }
+        Value::Bool(true) => out.push(Value::Bool(false)),
+        Value::Bool(false) | Value::Null => {}
+    }
+
+    out
+}
+
+// Two outcomes are the same failure if they fail with the same error variant,
+// so a shrink step can't turn a type mismatch into, say, a connection error.
+pub fn same_failure(a: &TesterError, b: &TesterError) -> bool {
+    discriminant(a) == discriminant(b)
+}
+
+pub async fn shrink_case(
+    tester: &Tester,
+    case: &TestCase,
+    failure: &TesterError,
+    max_attempts: usize,
+) -> Option<Value> {
+    let body = case.body.clone()?;
+    let minimized = shrink(
+        body,
+        |candidate| async move {
+            // Everything but the body is kept, so query parameters, encoding
+            // and any malformation still apply to each candidate.
+            let candidate = TestCase {
+                body: Some(candidate),
+                ..case.clone()
+            };
+            match tester.compare_case(&candidate).await.outcome {
+                Err(err) => same_failure(&err, failure),
+                Ok(()) => false,
+            }
+        },
+        max_attempts,
+    )
+    .await;
+    Some(minimized)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::client::TesterError;
+    use reqwest::Method;
+    use serde_json::json;
+    use std::cell::Cell;
+
+    fn mismatch() -> TesterError {
+        TesterError::TypeMismatch {
+            endpoint: "/".to_string(),
+            method: Method::GET,
+            path: String::new(),
+            client_value: Box::new(Value::Null),
+            actual_value: Box::new(json!(1)),
+        }
+    }
+
+    #[test]
+    fn candidates_are_smaller_and_removals_come_first() {
+        let value = json!({"name": "quiz", "tags": [1, 2.5, true]});
+        let all = candidates(&value);
+        assert!(!all.contains(&value));
+        assert_eq!(all[0], json!({"tags": [1, 2.5, true]}));
+        assert_eq!(all[1], json!({"name": "quiz"}));
+        assert!(all.contains(&json!({"name": "", "tags": [1, 2.5, true]})));
+        assert!(all.contains(&json!({"name": "quiz", "tags": [1, 2.0, true]})));
+        assert!(all.contains(&json!({"name": "quiz", "tags": [1, 2.5, false]})));
+    }
+
+    #[test]
+    fn scalars_shrink_towards_zero_and_empty() {
+        assert_eq!(candidates(&json!(-9)), vec![json!(0), json!(-4)]);
+        assert_eq!(candidates(&json!(1)), vec![json!(0)]);
+        assert_eq!(candidates(&json!(2.0)), vec![json!(0)]);
+        assert_eq!(candidates(&json!("abcd")), vec![json!(""), json!("ab")]);
+        // Halving counts characters, not bytes.
+        assert_eq!(candidates(&json!("éé")), vec![json!(""), json!("é")]);
+        for minimal in [
+            json!(0),
+            json!(""),
+            json!([]),
+            json!({}),
+            json!(false),
+            json!(null),
+        ] {
+            assert_eq!(candidates(&minimal), Vec::<Value>::new(), "{}", minimal);
+        }
+    }
+
+    #[tokio::test]
+    async fn shrinking_keeps_only_what_reproduces() {
+        // Fails whenever `password` is a string of 3 or more characters.
+        let reproduces = |value: Value| async move {
+            value["password"]
+                .as_str()
+                .is_some_and(|password| password.chars().count() >= 3)
+        };
+        let value = json!({
+            "email": "someone@example.com",
+            "password": "hunter2hunter2",
+            "names": ["a", "b", {"nested": [1, 2, 3]}],
+        });
+        let minimized = shrink(value, reproduces, DEFAULT_MAX_ATTEMPTS).await;
+        assert_eq!(minimized, json!({"password": "hun"}));
+    }
+
+    #[tokio::test]
+    async fn shrinking_stops_at_the_attempt_budget() {
+        let calls = Cell::new(0);
+        let value = json!({"a": [1, 2, 3, 4], "b": "long string"});
+        let minimized = shrink(
+            value.clone(),
+            |_| {
+                calls.set(calls.get() + 1);
+                async { false }
+            },
+            5,
+        )
+        .await;
+        assert_eq!(calls.get(), 5);
+        assert_eq!(minimized, value);
+    }
+
+    #[test]
+    fn failures_match_by_error_variant() {
+        let violation = TesterError::SpecViolation {
+            endpoint: "/".to_string(),
+            method: Method::GET,
+            server: "client",
+            status: 200,
+            path: "/".to_string(),
+            expected: "string".to_string(),
+            actual_value: Box::new(Value::Null),
+        };
+        assert!(same_failure(&mismatch(), &mismatch()));
+        assert!(!same_failure(&mismatch(), &violation));
+    }
+}
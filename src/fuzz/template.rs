@@ -0,0 +1,349 @@
+use crate::fuzz::presets::Preset;
+use crate::rng::FuzzRng;
+use rand::seq::SliceRandom;
+use rand::RngCore;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum TemplateError {
+    #[error("Invalid placeholder `{placeholder}`: {message}")]
+    Invalid {
+        placeholder: String,
+        message: String,
+    },
+
+    #[error("Unknown data provider `{0}`")]
+    UnknownProvider(String),
+}
+
+// One `{{ ... }}` in a template: a provider name, then optionally a variant
+// after a dot and integer arguments in parentheses, as in `email`,
+// `password.invalid` or `name(3,30)`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Call {
+    pub provider: String,
+    pub variant: Option<String>,
+    pub args: Vec<i64>,
+}
+
+impl Call {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let text = text.trim();
+        let (head, args) = match text.split_once('(') {
+            Some((head, rest)) => {
+                let args = rest
+                    .strip_suffix(')')
+                    .ok_or_else(|| "unclosed `(`".to_string())?;
+                let args = args
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|arg| !arg.is_empty())
+                    .map(|arg| {
+                        arg.parse()
+                            .map_err(|_| format!("`{}` is not an integer", arg))
+                    })
+                    .collect::<Result<Vec<i64>, String>>()?;
+                (head.trim(), args)
+            }
+            None => (text, Vec::new()),
+        };
+        let (provider, variant) = match head.split_once('.') {
+            Some((provider, variant)) => (provider, Some(variant)),
+            None => (head, None),
+        };
+        let is_name =
+            |s: &str| !s.is_empty() && s.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !is_name(provider) || variant.is_some_and(|variant| !is_name(variant)) {
+            return Err("expected `provider`, `provider.variant` or `provider(args)`".to_string());
+        }
+        Ok(Self {
+            provider: provider.to_string(),
+            variant: variant.map(str::to_string),
+            args,
+        })
+    }
+}
+
+// Makes up a value for a placeholder. Errors are about the call, e.g. an
+// unknown variant or the wrong number of arguments.
+pub trait Provider: Send + Sync {
+    fn provide(&self, call: &Call, rng: &mut dyn RngCore) -> Result<Value, String>;
+}
+
+impl<F> Provider for F
+where
+    F: Fn(&Call, &mut dyn RngCore) -> Result<Value, String> + Send + Sync,
+{
+    fn provide(&self, call: &Call, rng: &mut dyn RngCore) -> Result<Value, String> {
+        self(call, rng)
+    }
+}
+
+// Presets give valid values by default, or within a length (for `id`, a value)
+// range with two arguments. `.invalid` picks a value the spec rejects and
+// `.edge` one sitting at or just past a limit.
+impl Provider for Preset {
+    fn provide(&self, call: &Call, rng: &mut dyn RngCore) -> Result<Value, String> {
+        let pick = |values: Vec<Value>, rng: &mut dyn RngCore| {
+            values
+                .choose(rng)
+                .cloned()
+                .ok_or_else(|| format!("`{}` has no such values", call.provider))
+        };
+        match (call.variant.as_deref(), &call.args[..]) {
+            (None | Some("valid"), []) => Ok(self.valid(rng)),
+            (None | Some("valid"), &[min, max]) if 0 <= min && min <= max => {
+                Ok(self.valid_within(rng, min, max))
+            }
+            (None | Some("valid"), _) => Err("takes no arguments or a `(min,max)` range".into()),
+            (Some("invalid"), []) => pick(self.invalid(), rng),
+            (Some("edge"), []) => pick(self.edge_cases(), rng),
+            (Some("invalid" | "edge"), _) => Err("takes no arguments".to_string()),
+            (Some(variant), _) => Err(format!(
+                "unknown variant `{}` (expected valid, invalid or edge)",
+                variant
+            )),
+        }
+    }
+}
+
+// Expands placeholders in request bodies (and any other JSON) with values from
+// named providers, so configured endpoint bodies and scenario files draw on the
+// same realistic data. `{{ steps.* }}` references are scenario syntax and are
+// left for the scenario runner.
+#[derive(Clone)]
+pub struct Templates {
+    providers: BTreeMap<String, Arc<dyn Provider>>,
+}
+
+impl Default for Templates {
+    // The quiz API's presets: `email`, `password`, `name`, `quiz_name`,
+    // `description` and `id`.
+    fn default() -> Self {
+        Self::new()
+            .with_provider("email", Preset::Email)
+            .with_provider("password", Preset::Password)
+            .with_provider("name", Preset::Name)
+            .with_provider("quiz_name", Preset::QuizName)
+            .with_provider("description", Preset::QuizDescription)
+            .with_provider("id", Preset::Id)
+    }
+}
+
+impl Templates {
+    // Without any providers; see `default` for the built-in ones.
+    pub fn new() -> Self {
+        Self {
+            providers: BTreeMap::new(),
+        }
+    }
+
+    // Replaces any provider already registered under `name`.
+    pub fn with_provider(
+        mut self,
+        name: impl Into<String>,
+        provider: impl Provider + 'static,
+    ) -> Self {
+        self.providers.insert(name.into(), Arc::new(provider));
+        self
+    }
+
+    // `value` with every placeholder in its strings (and nested values)
+    // expanded. A string that is nothing but a placeholder takes the value's
+    // JSON type.
+    pub fn expand(&self, value: &Value, rng: &mut dyn RngCore) -> Result<Value, TemplateError> {
+        match value {
+            Value::String(s) => self.expand_str(s, rng),
+            Value::Array(items) => items.iter().map(|item| self.expand(item, rng)).collect(),
+            Value::Object(fields) => fields
+                .iter()
+                .map(|(key, value)| Ok((key.clone(), self.expand(value, rng)?)))
+                .collect::<Result<serde_json::Map<_, _>, TemplateError>>()
+                .map(Value::Object),
+            other => Ok(other.clone()),
+        }
+    }
+
+    // Whether `expand` would succeed on `value`. Providers are tried with a
+    // throwaway RNG, so every variant and argument gets checked.
+    pub fn check(&self, value: &Value) -> Result<(), TemplateError> {
+        let mut result = Ok(());
+        strings(value, &mut |s| {
+            if result.is_ok() {
+                result = self.check_str(s);
+            }
+        });
+        result
+    }
+
+    fn check_str(&self, template: &str) -> Result<(), TemplateError> {
+        for piece in pieces(template).map_err(|message| invalid(template, message))? {
+            if let Piece::Reference(reference) = piece {
+                if let Some((provider, call)) = self.call(reference)? {
+                    provider
+                        .provide(&call, &mut FuzzRng::new(0))
+                        .map_err(|message| invalid(reference, message))?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn expand_str(&self, template: &str, rng: &mut dyn RngCore) -> Result<Value, TemplateError> {
+        if !template.contains("{{") {
+            return Ok(Value::from(template));
+        }
+        let pieces = pieces(template).map_err(|message| invalid(template, message))?;
+        if let [Piece::Reference(reference)] = pieces[..] {
+            if let Some((provider, call)) = self.call(reference)? {
+                return provider
+                    .provide(&call, rng)
+                    .map_err(|message| invalid(reference, message));
+            }
+        }
+        let mut out = String::new();
+        for piece in pieces {
+            match piece {
+                Piece::Text(text) => out.push_str(text),
+                Piece::Reference(reference) => match self.call(reference)? {
+                    Some((provider, call)) => {
+                        match provider
+                            .provide(&call, rng)
+                            .map_err(|message| invalid(reference, message))?
+                        {
+                            Value::String(s) => out.push_str(&s),
+                            other => out.push_str(&other.to_string()),
+                        }
+                    }
+                    None => {
+                        out.push_str("{{ ");
+                        out.push_str(reference);
+                        out.push_str(" }}");
+                    }
+                },
+            }
+        }
+        Ok(Value::String(out))
+    }
+
+    // `None` for scenario references.
+    fn call(&self, reference: &str) -> Result<Option<(&dyn Provider, Call)>, TemplateError> {
+        if is_step_reference(reference) {
+            return Ok(None);
+        }
+        let call = Call::parse(reference).map_err(|message| invalid(reference, message))?;
+        let provider = self
+            .providers
+            .get(&call.provider)
+            .ok_or_else(|| TemplateError::UnknownProvider(call.provider.clone()))?;
+        Ok(Some((&**provider, call)))
+    }
+}
+
+pub fn is_step_reference(reference: &str) -> bool {
+    reference.starts_with("steps.")
+}
+
+fn invalid(placeholder: &str, message: String) -> TemplateError {
+    TemplateError::Invalid {
+        placeholder: placeholder.to_string(),
+        message,
+    }
+}
+
+pub(crate) fn strings<'a>(value: &'a Value, visit: &mut impl FnMut(&'a str)) {
+    match value {
+        Value::String(s) => visit(s),
+        Value::Array(items) => items.iter().for_each(|item| strings(item, visit)),
+        Value::Object(fields) => fields.values().for_each(|value| strings(value, visit)),
+        _ => {}
+    }
+}
+
+pub(crate) enum Piece<'a> {
+    Text(&'a str),
+    Reference(&'a str),
+}
+
+// `template` split into text and the trimmed insides of each `{{ ... }}`.
+pub(crate) fn pieces(template: &str) -> Result<Vec<Piece<'_>>, String> {
+    let mut pieces = Vec::new();
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let end = rest[start..]
+            .find("}}")
+            .ok_or_else(|| format!("unclosed `{{{{` in {:?}", template))?;
+        if start > 0 {
+            pieces.push(Piece::Text(&rest[..start]));
+        }
+        pieces.push(Piece::Reference(rest[start + 2..start + end].trim()));
+        rest = &rest[start + end + 2..];
+    }
+    if !rest.is_empty() || pieces.is_empty() {
+        pieces.push(Piece::Text(rest));
+    }
+    Ok(pieces)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn calls_parse_a_provider_variant_and_arguments() {
+        assert_eq!(
+            Call::parse(" name.valid(3, 30) "),
+            Ok(Call {
+                provider: "name".to_string(),
+                variant: Some("valid".to_string()),
+                args: vec![3, 30],
+            })
+        );
+        assert_eq!(Call::parse("email").unwrap().variant, None);
+        assert!(Call::parse("name(3").is_err());
+        assert!(Call::parse("name(a)").is_err());
+        assert!(Call::parse("two words").is_err());
+    }
+
+    #[test]
+    fn placeholders_expand_and_step_references_are_left_alone() {
+        let templates = Templates::default()
+            .with_provider("answer", |_: &Call, _: &mut dyn RngCore| Ok(json!(42)));
+        let mut rng = FuzzRng::new(0);
+        let body = json!({
+            "n": "{{ answer }}",
+            "label": "q{{answer}}",
+            "password": "{{ password(9, 9) }}",
+            "token": "{{ steps.register.body.token }}",
+            "kept": [1, "plain"],
+        });
+        let expanded = templates.expand(&body, &mut rng).unwrap();
+        assert_eq!(expanded["n"], 42);
+        assert_eq!(expanded["label"], "q42");
+        assert_eq!(expanded["password"].as_str().unwrap().len(), 9);
+        assert_eq!(expanded["token"], "{{ steps.register.body.token }}");
+        assert_eq!(expanded["kept"], json!([1, "plain"]));
+    }
+
+    #[test]
+    fn checks_catch_unknown_providers_and_bad_calls() {
+        let templates = Templates::default();
+        assert!(templates
+            .check(&json!(["{{ email.invalid }}", "{{ id(1,5) }}"]))
+            .is_ok());
+        assert!(matches!(
+            templates.check(&json!("{{ phone }}")),
+            Err(TemplateError::UnknownProvider(name)) if name == "phone"
+        ));
+        assert!(matches!(
+            templates.check(&json!({"a": "{{ email.weird }}"})),
+            Err(TemplateError::Invalid { placeholder, .. }) if placeholder == "email.weird"
+        ));
+        assert!(templates.check(&json!("{{ name(1) }}")).is_err());
+        assert!(templates.check(&json!("{{ email")).is_err());
+    }
+}
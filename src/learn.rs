@@ -0,0 +1,320 @@
+use crate::models::client::ServerResponse;
+use crate::models::endpoints;
+use crate::schema::{ResponseSchemas, Schema, SchemaRegistry};
+use crate::spec::{parse_schema, without_query, SpecError};
+use reqwest::Method;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::Path;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum LearnError {
+    #[error("Learned schemas I/O failed: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Invalid learned schemas: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("Invalid learned schema for `{route}`: {source}")]
+    Schema {
+        route: String,
+        #[source]
+        source: SpecError,
+    },
+}
+
+// What one route's responses have looked like so far.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LearnedRoute {
+    // For 2xx responses, then for everything else.
+    pub success: Option<Schema>,
+    pub error: Option<Schema>,
+    pub samples: usize,
+}
+
+// Response schemas inferred from what a (reference) server actually sent,
+// keyed by method and registry path template, for checking another server
+// against them later without the first one running. A field is required only
+// if every response had it, and nullable if any had it null.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LearnedSchemas {
+    pub routes: BTreeMap<(String, String), LearnedRoute>,
+}
+
+// How learned schemas are saved: JSON Schema documents that `json_schema` in
+// `[endpoints.responses]` would also take.
+#[derive(Serialize, Deserialize)]
+struct SavedRoute {
+    method: String,
+    path: String,
+    samples: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    success: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    error: Option<Value>,
+}
+
+impl LearnedSchemas {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Concrete paths are filed under the registry route they belong to;
+    // non-JSON responses are skipped.
+    pub fn observe(&mut self, method: &Method, endpoint: &str, response: &ServerResponse) {
+        if response.raw.is_some() {
+            return;
+        }
+        let path = without_query(endpoint);
+        let template = endpoints::resolve(path).map_or(path, |endpoint| endpoint.path);
+        let route = self
+            .routes
+            .entry((method.to_string(), template.to_string()))
+            .or_default();
+        let schema = if (200..300).contains(&response.status) {
+            &mut route.success
+        } else {
+            &mut route.error
+        };
+        *schema = Some(match schema.take() {
+            Some(learned) => merge(learned, &response.body),
+            None => infer(&response.body),
+        });
+        route.samples += 1;
+    }
+
+    // Routes whose method doesn't parse are left out.
+    pub fn registry(&self) -> SchemaRegistry {
+        let mut registry = SchemaRegistry::new();
+        for ((method, path), route) in &self.routes {
+            let Ok(method) = method.parse::<Method>() else {
+                continue;
+            };
+            let schemas = ResponseSchemas {
+                success: route.success.clone(),
+                error: route.error.clone(),
+            };
+            registry = registry.with(method, path.clone(), schemas);
+        }
+        registry
+    }
+
+    pub fn load(path: &Path) -> Result<Self, LearnError> {
+        let saved: Vec<SavedRoute> = serde_json::from_slice(&std::fs::read(path)?)?;
+        let mut learned = Self::new();
+        for route in saved {
+            let name = format!("{} {}", route.method, route.path);
+            if route.method.parse::<Method>().is_err() {
+                return Err(LearnError::Schema {
+                    route: name,
+                    source: SpecError::Invalid(format!("invalid method `{}`", route.method)),
+                });
+            }
+            let parse = |schema: Option<Value>| {
+                schema
+                    .map(|schema| parse_schema(&schema))
+                    .transpose()
+                    .map_err(|source| LearnError::Schema {
+                        route: name.clone(),
+                        source,
+                    })
+            };
+            let learned_route = LearnedRoute {
+                success: parse(route.success)?,
+                error: parse(route.error)?,
+                samples: route.samples,
+            };
+            learned
+                .routes
+                .insert((route.method, route.path), learned_route);
+        }
+        Ok(learned)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), LearnError> {
+        let saved: Vec<SavedRoute> = self
+            .routes
+            .iter()
+            .map(|((method, path), route)| SavedRoute {
+                method: method.clone(),
+                path: path.clone(),
+                samples: route.samples,
+                success: route.success.as_ref().map(Schema::to_json_schema),
+                error: route.error.as_ref().map(Schema::to_json_schema),
+            })
+            .collect();
+        std::fs::write(path, serde_json::to_vec_pretty(&saved)?)?;
+        Ok(())
+    }
+}
+
+// The narrowest schema `value` fits. Empty arrays get items of `Any`, which
+// the first element seen later replaces.
+pub fn infer(value: &Value) -> Schema {
+    match value {
+        Value::Null => Schema::Null,
+        Value::Bool(_) => Schema::Boolean,
+        Value::Number(n) if n.is_i64() || n.is_u64() => Schema::Integer,
+        Value::Number(_) => Schema::Number,
+        Value::String(_) => Schema::String,
+        Value::Array(items) => Schema::Array(Box::new(items.iter().fold(Schema::Any, merge))),
+        Value::Object(fields) => Schema::Object {
+            properties: fields
+                .iter()
+                .map(|(name, field)| (name.clone(), infer(field)))
+                .collect(),
+            required: fields.keys().cloned().collect(),
+        },
+    }
+}
+
+// `schema` widened just enough to also fit `value`.
+pub fn merge(schema: Schema, value: &Value) -> Schema {
+    match (schema, value) {
+        (Schema::Any, value) => infer(value),
+        (Schema::Null, Value::Null) => Schema::Null,
+        (Schema::Null, value) => Schema::Nullable(Box::new(infer(value))),
+        (Schema::Nullable(inner), Value::Null) => Schema::Nullable(inner),
+        (Schema::Nullable(inner), value) => Schema::Nullable(Box::new(merge(*inner, value))),
+        (schema, Value::Null) => Schema::Nullable(Box::new(schema)),
+        (Schema::Integer, Value::Number(n)) if !(n.is_i64() || n.is_u64()) => Schema::Number,
+        (Schema::Array(items), Value::Array(values)) => {
+            Schema::Array(Box::new(values.iter().fold(*items, merge)))
+        }
+        (
+            Schema::Object {
+                mut properties,
+                mut required,
+            },
+            Value::Object(fields),
+        ) => {
+            required.retain(|name| fields.contains_key(name));
+            for (name, field) in fields {
+                let schema = match properties.remove(name) {
+                    Some(schema) => merge(schema, field),
+                    // Earlier responses went without it.
+                    None => infer(field),
+                };
+                properties.insert(name.clone(), schema);
+            }
+            Schema::Object {
+                properties,
+                required,
+            }
+        }
+        (Schema::OneOf(mut options), value) => {
+            match options.iter().position(|option| same_kind(option, value)) {
+                Some(index) => {
+                    let option = options.remove(index);
+                    options.insert(index, merge(option, value));
+                }
+                None => options.push(infer(value)),
+            }
+            Schema::OneOf(options)
+        }
+        (schema, value) if same_kind(&schema, value) => schema,
+        (schema, value) => Schema::OneOf(vec![schema, infer(value)]),
+    }
+}
+
+// Whether merging `value` into `schema` keeps it the same kind of thing.
+fn same_kind(schema: &Schema, value: &Value) -> bool {
+    matches!(
+        (schema, value),
+        (Schema::Any, _)
+            | (Schema::Null, Value::Null)
+            | (Schema::Boolean, Value::Bool(_))
+            | (Schema::Integer | Schema::Number, Value::Number(_))
+            | (Schema::String, Value::String(_))
+            | (Schema::Array(_), Value::Array(_))
+            | (Schema::Object { .. }, Value::Object(_))
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::client::RawResponse;
+    use crate::runner::tests::response;
+    use serde_json::json;
+
+    #[test]
+    fn fields_stay_required_only_while_every_response_has_them() {
+        let schema = infer(&json!({ "quizId": 1, "name": "a", "tags": [] }));
+        let schema = merge(schema, &json!({ "quizId": 2.5, "tags": ["x"] }));
+        let schema = merge(schema, &json!({ "quizId": 3, "name": null, "tags": [] }));
+        assert_eq!(
+            schema,
+            Schema::Object {
+                properties: BTreeMap::from([
+                    (
+                        "name".to_string(),
+                        Schema::Nullable(Box::new(Schema::String))
+                    ),
+                    ("quizId".to_string(), Schema::Number),
+                    ("tags".to_string(), Schema::Array(Box::new(Schema::String))),
+                ]),
+                required: vec!["quizId".to_string(), "tags".to_string()],
+            }
+        );
+        assert_eq!(
+            merge(Schema::String, &json!(1)),
+            Schema::OneOf(vec![Schema::String, Schema::Integer])
+        );
+    }
+
+    #[test]
+    fn responses_are_filed_under_their_route() {
+        let mut learned = LearnedSchemas::new();
+        learned.observe(
+            &Method::GET,
+            "/admin/quiz/1?token=a",
+            &response(200, json!({ "quizId": 1 }), 1),
+        );
+        learned.observe(
+            &Method::GET,
+            "/admin/quiz/2",
+            &response(400, json!({ "error": "no" }), 1),
+        );
+        let mut raw = response(200, Value::Null, 1);
+        raw.raw = Some(RawResponse::Bytes {
+            content_type: "image/png".to_string(),
+            bytes: b"png".to_vec(),
+        });
+        learned.observe(&Method::GET, "/admin/quiz/3", &raw);
+
+        let route = &learned.routes[&("GET".to_string(), "/admin/quiz/{quizid}".to_string())];
+        assert_eq!(route.samples, 2);
+        assert!(route.success.is_some());
+        assert!(route.error.is_some());
+        assert_eq!(learned.routes.len(), 1);
+    }
+
+    #[test]
+    fn saved_schemas_load_back_the_same() {
+        let mut learned = LearnedSchemas::new();
+        for body in [
+            json!({ "quizzes": [{ "quizId": 1, "name": "a" }], "next": null }),
+            json!({ "quizzes": [], "next": "b" }),
+        ] {
+            learned.observe(&Method::GET, "/admin/quiz/list", &response(200, body, 1));
+        }
+        let path =
+            std::env::temp_dir().join(format!("fuzzer1531-learned-{}.json", std::process::id()));
+        learned.save(&path).unwrap();
+        assert_eq!(LearnedSchemas::load(&path).unwrap(), learned);
+
+        std::fs::write(
+            &path,
+            r#"[{ "method": "NOT A METHOD", "path": "/a", "samples": 1 }]"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            LearnedSchemas::load(&path),
+            Err(LearnError::Schema { route, .. }) if route == "NOT A METHOD /a"
+        ));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,23 @@
+pub mod artifacts;
+pub mod campaign;
+pub mod catalogue;
+pub mod chaos;
+pub mod config;
+pub mod corpus;
+pub mod fuzz;
+pub mod learn;
+pub mod logging;
+pub mod models;
+pub mod pretty;
+pub mod repl;
+pub mod report;
+pub mod rng;
+pub mod runner;
+pub mod scenario;
+pub mod schema;
+pub mod snapshot;
+pub mod spec;
+pub mod stateful;
+pub mod teardown;
+#[cfg(feature = "testing")]
+pub mod testing;
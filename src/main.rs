@@ -1,5 +1,1525 @@
+use clap::{Args, Parser, Subcommand};
+use fuzzer1531::campaign::Campaign;
+use fuzzer1531::catalogue::ErrorChecker;
+use fuzzer1531::chaos::ChaosProxy;
+use fuzzer1531::config::{Config, Overrides};
+use fuzzer1531::corpus::{self, Corpus, RecordedRequest};
+use fuzzer1531::fuzz::generator::ProfilePreset;
+use fuzzer1531::learn::LearnedSchemas;
+use fuzzer1531::logging;
+use fuzzer1531::models::client::{NotReady, Tester};
+use fuzzer1531::repl::{self, Flow, Repl};
+use fuzzer1531::report::baseline::Baseline;
+use fuzzer1531::report::progress::Progress;
+use fuzzer1531::report::prometheus::PrometheusMetrics;
+use fuzzer1531::report::{har, html, junit, results};
+use fuzzer1531::rng::FuzzRng;
+use fuzzer1531::runner::{CaseResult, Category, Runner};
+use fuzzer1531::scenario::infer::DependencyGraph;
+use fuzzer1531::scenario::{pack, yaml, Failure, Scenario, ScenarioRunner};
+use fuzzer1531::snapshot::{self, SnapshotError, SnapshotStore};
+use fuzzer1531::stateful::race::RaceChecker;
+use fuzzer1531::stateful::ModelChecker;
+use fuzzer1531::teardown::{tear_down, Created};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::Arc;
+use tokio_util::sync::CancellationToken;
 
+#[derive(Parser)]
+#[command(
+    name = "fuzzer1531",
+    about = "Differential fuzzer for the COMP1531 quiz API"
+)]
+struct Cli {
+    /// Server under test, overriding `test_url` and FUZZER_TEST_URL
+    #[arg(long, global = true)]
+    test_url: Option<String>,
+    /// Reference server, overriding `server_url` and FUZZER_SERVER_URL
+    #[arg(long, global = true)]
+    server_url: Option<String>,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Compare the test server against the reference server using a config file
+    Run {
+        /// Path to a .toml, .yaml or .yml config file
+        config: PathBuf,
+        /// Seed for all random choices, overriding `seed` and FUZZER_SEED
+        #[arg(long)]
+        seed: Option<u64>,
+        #[command(flatten)]
+        flags: RunFlags,
+    },
+    /// Re-send recorded failing cases to both servers to check whether they still diverge
+    Replay {
+        /// Path to a .toml, .yaml or .yml config file
+        config: PathBuf,
+        /// Corpus directory to replay, overriding `corpus` from the config
+        #[arg(long)]
+        corpus: Option<PathBuf>,
+    },
+    /// Compare the test server against recorded reference responses, or record them with --record
+    Snapshot {
+        /// Path to a .toml, .yaml or .yml config file
+        config: PathBuf,
+        /// Snapshot directory, overriding `snapshots` from the config
+        #[arg(long)]
+        dir: Option<PathBuf>,
+        /// Record the reference server's responses instead of comparing against them
+        #[arg(long)]
+        record: bool,
+        /// Seed for the recorded suite, overriding `seed` and FUZZER_SEED
+        #[arg(long)]
+        seed: Option<u64>,
+    },
+    /// Apply random quiz lifecycle operations and check the server's state against a model
+    Model {
+        /// Path to a .toml, .yaml or .yml config file
+        config: PathBuf,
+        /// Seed for all random choices, overriding `seed` and FUZZER_SEED
+        #[arg(long)]
+        seed: Option<u64>,
+        /// Check the reference server (`server_url`) instead, to validate the model itself
+        #[arg(long)]
+        reference: bool,
+    },
+    /// Send conflicting operations at the same moment and check the server's state afterwards
+    Race {
+        /// Path to a .toml, .yaml or .yml config file
+        config: PathBuf,
+        /// Seed for all random choices, overriding `seed` and FUZZER_SEED
+        #[arg(long)]
+        seed: Option<u64>,
+        /// Race the reference server (`server_url`) instead
+        #[arg(long)]
+        reference: bool,
+    },
+    /// Type requests interactively and see both servers' responses side by side
+    Repl {
+        /// Path to a .toml, .yaml or .yml config file
+        config: PathBuf,
+    },
+    /// Check that both servers answer invalid requests with the catalogued 400, 401 and 403 codes
+    Errors {
+        /// Path to a .toml, .yaml or .yml config file
+        config: PathBuf,
+        /// Seed for all random choices, overriding `seed` and FUZZER_SEED
+        #[arg(long)]
+        seed: Option<u64>,
+    },
+    /// Run YAML scenarios (or the built-in logout scenarios) against both servers
+    Scenarios {
+        /// Path to a .toml, .yaml or .yml config file
+        config: PathBuf,
+        /// Scenario files to run along with `scenarios` from the config
+        files: Vec<PathBuf>,
+    },
+    /// Run scenarios against the reference server and save the response schemas they show
+    Learn {
+        /// Path to a .toml, .yaml or .yml config file
+        config: PathBuf,
+        /// Scenario files to run along with `scenarios` from the config
+        files: Vec<PathBuf>,
+        /// Where to save the schemas, overriding `learned_schemas` from the config
+        #[arg(long)]
+        out: Option<PathBuf>,
+    },
+}
+
+#[derive(Args)]
+struct RunFlags {
+    /// Print the requests that would be sent without sending any
+    #[arg(long)]
+    dry_run: bool,
+    /// Stop at the first failed or crashed case, overriding `max_findings`
+    #[arg(long)]
+    fail_fast: bool,
+    /// Generator profile (smoke, default or aggressive), overriding `[generator]`'s
+    #[arg(long)]
+    profile: Option<ProfilePreset>,
+    /// Save this run's findings to `baseline` instead of comparing against it
+    #[arg(long)]
+    update_baseline: bool,
+    /// Print only failures and the summary, without progress or passing cases
+    #[arg(long)]
+    quiet: bool,
+    /// Exit 0 as long as there are at most this many findings (new ones, with a baseline)
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    allow_findings: usize,
+    /// Carry on from a campaign state file (see `campaign`) without resending its cases
+    #[arg(long, value_name = "FILE")]
+    resume: Option<PathBuf>,
+}
+
+// Exit statuses, so CI can tell a server that disagrees from a run that never
+// got going.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Exit {
+    Findings = 1,
+    // A config, spec or other input that couldn't be loaded or used.
+    Config = 2,
+    // A server wasn't ready in time or refused connections.
+    Unreachable = 3,
+}
+
+impl From<Exit> for ExitCode {
+    fn from(exit: Exit) -> Self {
+        ExitCode::from(exit as u8)
+    }
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let overrides = |seed| Overrides {
+        test_url: cli.test_url.clone(),
+        server_url: cli.server_url.clone(),
+        seed,
+    };
+
+    match cli.command {
+        Command::Run {
+            config,
+            seed,
+            flags,
+        } => run(config, overrides(seed), flags).await,
+        Command::Replay { config, corpus } => replay(config, overrides(None), corpus).await,
+        Command::Snapshot {
+            config,
+            dir,
+            record,
+            seed,
+        } => snapshot(config, overrides(seed), dir, record).await,
+        Command::Model {
+            config,
+            seed,
+            reference,
+        } => model(config, overrides(seed), reference).await,
+        Command::Race {
+            config,
+            seed,
+            reference,
+        } => race(config, overrides(seed), reference).await,
+        Command::Repl { config } => interactive(config, overrides(None)).await,
+        Command::Errors { config, seed } => errors(config, overrides(seed)).await,
+        Command::Scenarios { config, files } => scenarios(config, overrides(None), files).await,
+        Command::Learn { config, files, out } => learn(config, overrides(None), files, out).await,
+    }
+}
+
+// The first Ctrl-C stops the run after writing out what it has so far; a
+// second one exits straight away.
+fn cancel_on_ctrl_c() -> CancellationToken {
+    let cancel = CancellationToken::new();
+    let token = cancel.clone();
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_err() {
+            return;
+        }
+        eprintln!("\ninterrupted: writing results so far (Ctrl-C again to quit now)");
+        token.cancel();
+        if tokio::signal::ctrl_c().await.is_ok() {
+            std::process::exit(130);
+        }
+    });
+    cancel
+}
+
+// Along with the chaos proxies the config now points at, which stop when
+// dropped.
+fn load_config(path: &PathBuf, overrides: &Overrides) -> Option<(Config, Vec<ChaosProxy>)> {
+    let mut config = match Config::resolve(path, overrides) {
+        Ok(config) => config,
+        Err(err) => {
+            eprintln!("error: {}", err);
+            return None;
+        }
+    };
+    if let Some(log) = &config.log {
+        logging::init(log.format, &log.level);
+    }
+    match config.start_chaos() {
+        Ok(proxies) => {
+            for proxy in &proxies {
+                eprintln!("chaos: {} through {}", proxy.upstream(), proxy.url());
+            }
+            if config.concurrency > 1 && config.hooks.hooks().is_ok_and(|hooks| !hooks.is_empty()) {
+                eprintln!(
+                    "warning: cases run one at a time while hooks are set, so `concurrency = {}` has no \
+                     effect; set `[hooks] clear = false` (with no `before` or `after`) to run them in parallel",
+                    config.concurrency
+                );
+            }
+            Some((config, proxies))
+        }
+        Err(err) => {
+            eprintln!("error: {}", err);
+            None
+        }
+    }
+}
+
+// With a `[ready]` table in the config, waits for `tester`'s servers first.
+// `server` names a tester's only server in the error, for testers aimed at the
+// reference.
+async fn wait_ready(config: &Config, tester: &Tester, server: Option<&str>) -> bool {
+    let Some(ready) = &config.ready else {
+        return true;
+    };
+    match tester.wait_ready(ready.timeout()).await {
+        Ok(()) => true,
+        Err(err) => {
+            let err = match server {
+                Some(server) => NotReady {
+                    server: server.to_string(),
+                    ..err
+                },
+                None => err,
+            };
+            eprintln!("error: {}", err);
+            false
+        }
+    }
+}
+
+fn print_case(case: &CaseResult, show_passes: bool) {
+    let err = match &case.outcome {
+        Ok(()) => {
+            if show_passes {
+                println!("PASS {} {}", case.method, case.endpoint);
+            }
+            return;
+        }
+        Err(err) => err,
+    };
+    let label = if case.flaky.is_some() {
+        "FLAKY"
+    } else {
+        "FAIL"
+    };
+    println!("{} {} {}", label, case.method, case.endpoint);
+    if let Some(flaky) = &case.flaky {
+        println!("  {}", flaky);
+    }
+    if !case.query.is_empty() {
+        println!("  query: {:?}", case.query);
+    }
+    if let Some(body) = &case.body {
+        println!("  body: {}", body);
+    }
+    if let Some(malformation) = &case.malformation {
+        println!("  malformed: {:?}", malformation);
+    }
+    if let Some(perturbation) = &case.header_perturbation {
+        println!("  token header: {:?}", perturbation);
+    }
+    println!("  seed: {}", case.seed);
+    println!("  {}", err.to_string().replace('\n', "\n  "));
+    if let Some(minimized) = &case.minimized {
+        println!("  minimized body: {}", minimized);
+    }
+    match &case.recorded {
+        Some(Ok(path)) => println!("  recorded: {}", path.display()),
+        Some(Err(err)) => println!("  failed to record: {}", err),
+        None => {}
+    }
+    match &case.artifacts {
+        Some(Ok(path)) => println!("  artifacts: {}", path.display()),
+        Some(Err(err)) => println!("  {}", err),
+        None => {}
+    }
+}
+
+async fn run(path: PathBuf, overrides: Overrides, flags: RunFlags) -> ExitCode {
+    let RunFlags {
+        dry_run,
+        fail_fast,
+        profile,
+        update_baseline,
+        quiet,
+        allow_findings,
+        resume,
+    } = flags;
+    let Some((mut config, _chaos)) = load_config(&path, &overrides) else {
+        return Exit::Config.into();
+    };
+    let campaign = match &resume {
+        Some(path) => match Campaign::load(path) {
+            Ok(campaign) => Some(campaign),
+            Err(err) => {
+                eprintln!("error: {}: {}", path.display(), err);
+                return Exit::Config.into();
+            }
+        },
+        None => None,
+    };
+    if let Some(campaign) = &campaign {
+        // A different seed in the config is caught below.
+        config.seed = config.seed.or(Some(campaign.seed));
+        config.campaign = resume.clone();
+    }
+    config.fail_fast |= fail_fast;
+    if profile.is_some() {
+        config.generator.profile = profile;
+    }
+    if update_baseline && config.baseline.is_none() {
+        eprintln!("error: --update-baseline needs `baseline` in the config");
+        return Exit::Config.into();
+    }
+    let mut runner = match Runner::from_config(&config) {
+        Ok(runner) => runner,
+        Err(err) => {
+            eprintln!("error: {}", err);
+            return Exit::Config.into();
+        }
+    };
+    if let Some(campaign) = campaign {
+        let (cases, findings) = (campaign.cases, campaign.findings.len());
+        runner = match runner.resume(campaign) {
+            Ok(runner) => runner,
+            Err(err) => {
+                eprintln!("error: {}", err);
+                return Exit::Config.into();
+            }
+        };
+        println!(
+            "resuming after {} cases and {} {}",
+            cases,
+            findings,
+            if findings == 1 { "finding" } else { "findings" }
+        );
+    }
+
+    println!("seed: {}\n", runner.seed());
+
+    if dry_run {
+        print_plan(&mut runner);
+        return ExitCode::SUCCESS;
+    }
+    let mut runner = runner.with_cancellation(cancel_on_ctrl_c());
+    let metrics = config
+        .report
+        .prometheus
+        .as_ref()
+        .map(|_| Arc::new(PrometheusMetrics::new()));
+    if let Some(metrics) = &metrics {
+        runner = runner.with_observer(metrics.clone());
+    }
+    if !config.candidates.is_empty() {
+        let exit = run_candidates(&config, &mut runner).await;
+        write_metrics(&config, metrics.as_deref());
+        return exit;
+    }
+    if !wait_ready(&config, runner.tester(), None).await {
+        return Exit::Unreachable.into();
+    }
+
+    // On a terminal, a progress display stands in for the passing cases.
+    let mut progress = Progress::for_stderr(runner.planned(), quiet);
+    let show_passes = !quiet && progress.is_none();
+    if let Some(progress) = &mut progress {
+        progress.draw(true);
+    }
+    let report = runner
+        .run(|case| {
+            if let Some(progress) = &mut progress {
+                progress.record(case);
+                if case.outcome.is_err() {
+                    progress.clear();
+                }
+            }
+            print_case(case, show_passes);
+            if let Some(progress) = &mut progress {
+                progress.draw(case.outcome.is_err());
+            }
+        })
+        .await;
+    if let Some(progress) = &mut progress {
+        progress.clear();
+    }
+
+    let flaky = report.flaky().count();
+    if flaky > 0 {
+        println!(
+            "\n{} passed ({} flaky), {} failed",
+            report.passed(),
+            flaky,
+            report.failed()
+        );
+    } else {
+        println!("\n{} passed, {} failed", report.passed(), report.failed());
+    }
+    let unreachable = report.count(Category::Unreachable);
+    if unreachable > 0 {
+        println!(
+            "{} {} couldn't reach a server",
+            unreachable,
+            if unreachable == 1 { "case" } else { "cases" }
+        );
+    }
+    if report.timed_out {
+        println!("run timed out after {:.2?}", report.duration);
+    }
+    if report.cancelled {
+        println!("run interrupted after {:.2?}", report.duration);
+    }
+    if report.finding_limit {
+        let findings = report
+            .cases
+            .iter()
+            .filter(|case| case.failed() || case.crash.is_some())
+            .count();
+        println!(
+            "run stopped after {} failed or crashed {}",
+            findings,
+            if findings == 1 { "case" } else { "cases" }
+        );
+    }
+    if report.resumed + report.cases.len() < report.planned {
+        println!(
+            "ran {} of {} planned cases",
+            report.resumed + report.cases.len(),
+            report.planned
+        );
+    }
+    let slowdowns = match config.max_slowdown {
+        Some(max_ratio) => report.slowdowns(max_ratio),
+        None => Vec::new(),
+    };
+    for slowdown in &slowdowns {
+        println!(
+            "SLOW {} {}: {:.1}x slower than the reference (p50 {:.1?} vs {:.1?})",
+            slowdown.method,
+            slowdown.path,
+            slowdown.ratio,
+            slowdown.client_p50,
+            slowdown.actual_p50
+        );
+    }
+    let coverage = report.coverage();
+    println!(
+        "\ncoverage: {}/{} routes exercised",
+        coverage.tested(),
+        coverage.total()
+    );
+    for ((method, path), classes) in &coverage.hits {
+        let classes: Vec<String> = classes.iter().map(ToString::to_string).collect();
+        println!("  {} {}: {}", method, path, classes.join(", "));
+    }
+    for (method, path) in &coverage.untested {
+        println!("  {} {}: untested", method, path);
+    }
+    let crashes: Vec<_> = report
+        .crashes()
+        .filter(|case| case.category() == Category::Finding)
+        .collect();
+    if !crashes.is_empty() {
+        println!("\n{} crash-inducing inputs:", crashes.len());
+    }
+    for case in &crashes {
+        let crash = case.crash.expect("crashes have a crash");
+        println!("  {} {} ({})", case.method, case.endpoint, crash);
+        if let Some(body) = &case.body {
+            println!("    body: {}", body);
+        }
+        println!("    seed: {}", case.seed);
+        if let Some(Ok(path)) = &case.recorded {
+            println!("    recorded: {}", path.display());
+        }
+        if let Some(Ok(path)) = &case.artifacts {
+            println!("    artifacts: {}", path.display());
+        }
+    }
+    if !report.earlier_findings.is_empty() {
+        println!("\nfindings before resuming:");
+    }
+    for finding in &report.earlier_findings {
+        let error = finding.error.as_deref().unwrap_or_default();
+        println!(
+            "  {} {} (seed {}): {}",
+            finding.method,
+            finding.endpoint,
+            finding.seed,
+            error.lines().next().unwrap_or_default()
+        );
+    }
+    let groups = report.triage();
+    let baseline = match &config.baseline {
+        Some(path) if !update_baseline && path.exists() => match Baseline::load(path) {
+            Ok(baseline) => Some(baseline),
+            Err(err) => {
+                eprintln!("error: {}: {}", path.display(), err);
+                return Exit::Config.into();
+            }
+        },
+        Some(path) if !update_baseline => {
+            println!(
+                "\nno baseline at {} yet; save one with --update-baseline",
+                path.display()
+            );
+            None
+        }
+        _ => None,
+    };
+    // With a baseline, only new findings count against the run.
+    let findings = match baseline {
+        Some(baseline) => {
+            let diff = baseline.diff(groups);
+            if !diff.new.is_empty() {
+                println!("\nnew findings by root cause:");
+            }
+            for group in &diff.new {
+                println!("  {}", group);
+            }
+            if !diff.fixed.is_empty() {
+                println!("\nfixed since the baseline:");
+            }
+            for finding in &diff.fixed {
+                println!("  {}", finding);
+            }
+            println!(
+                "\n{} new, {} known and {} fixed findings",
+                diff.new.len(),
+                diff.known.len(),
+                diff.fixed.len()
+            );
+            diff.new.iter().map(|group| group.count).sum()
+        }
+        None => {
+            if !groups.is_empty() {
+                println!("\nfindings by root cause:");
+            }
+            for group in &groups {
+                println!("  {}", group);
+            }
+            report.count(Category::Finding) + report.earlier_findings.len()
+        }
+    };
+    let findings = findings + slowdowns.len();
+    if findings > 0 && findings <= allow_findings {
+        println!(
+            "\n{} {}, within the {} allowed",
+            findings,
+            if findings == 1 { "finding" } else { "findings" },
+            allow_findings
+        );
+    }
+    if let Some(path) = config.baseline.as_ref().filter(|_| update_baseline) {
+        let baseline = Baseline::of(&report);
+        match baseline.save(path) {
+            Ok(()) => println!(
+                "baseline of {} findings saved to {}",
+                baseline.findings.len(),
+                path.display()
+            ),
+            Err(err) => eprintln!("error: failed to save baseline: {}", err),
+        }
+    }
+    if let Some(path) = &config.report.html {
+        match html::write(&report, path) {
+            Ok(()) => println!("HTML report written to {}", path.display()),
+            Err(err) => eprintln!("error: failed to write HTML report: {}", err),
+        }
+    }
+    if let Some(path) = &config.report.junit {
+        match junit::write(&report, path) {
+            Ok(()) => println!("JUnit report written to {}", path.display()),
+            Err(err) => eprintln!("error: failed to write JUnit report: {}", err),
+        }
+    }
+    if let Some(path) = &config.report.har {
+        let servers = har::Servers {
+            client: &config.test_url,
+            actual: config.server_url.as_deref(),
+            version: config.api_version,
+        };
+        match har::write(&report, servers, path) {
+            Ok(()) => println!("HAR written to {}", path.display()),
+            Err(err) => eprintln!("error: failed to write HAR: {}", err),
+        }
+    }
+    if let Some(path) = &config.report.csv {
+        match results::write_csv(&report, path) {
+            Ok(()) => println!("CSV results written to {}", path.display()),
+            Err(err) => eprintln!("error: failed to write CSV results: {}", err),
+        }
+    }
+    if let Some(path) = &config.report.jsonl {
+        match results::write_json_lines(&report, path) {
+            Ok(()) => println!("JSON Lines results written to {}", path.display()),
+            Err(err) => eprintln!("error: failed to write JSON Lines results: {}", err),
+        }
+    }
+    write_metrics(&config, metrics.as_deref());
+    if let Some(teardown) = config.teardown {
+        let tester = runner.tester();
+        let servers = [
+            (
+                "server under test",
+                Some(tester.for_server(config.test_url.clone())),
+                Created::from_cases(&report.cases, |case| case.client.as_ref()),
+            ),
+            (
+                "reference server",
+                tester.for_reference(),
+                Created::from_cases(&report.cases, |case| case.actual.as_ref()),
+            ),
+        ];
+        for (name, tester, created) in servers {
+            let Some(tester) = tester else {
+                continue;
+            };
+            let cleanup = tear_down(&tester, &created, teardown).await;
+            println!(
+                "\nteardown on the {}: {} removed, {} left behind",
+                name,
+                cleanup.removed,
+                cleanup.leftovers.len()
+            );
+            for leftover in &cleanup.leftovers {
+                println!("  {}", leftover);
+            }
+        }
+    }
+    // A run that didn't finish can't vouch for the server, so it fails as if
+    // it had found something.
+    if findings > allow_findings {
+        Exit::Findings.into()
+    } else if unreachable > 0 {
+        Exit::Unreachable.into()
+    } else if report.timed_out || report.cancelled {
+        Exit::Findings.into()
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+async fn run_candidates(config: &Config, runner: &mut Runner) -> ExitCode {
+    let multi = match config.multi_tester(runner.tester()) {
+        Ok(multi) => multi,
+        Err(err) => {
+            eprintln!("error: {}", err);
+            return Exit::Config.into();
+        }
+    };
+    if let Some(ready) = &config.ready {
+        if let Err(err) = multi.wait_ready(ready.timeout()).await {
+            eprintln!("error: {}", err);
+            return Exit::Unreachable.into();
+        }
+    }
+    let report = runner.run_candidates(&multi).await;
+
+    for (planned, err) in &report.reference_failures {
+        println!(
+            "REFERENCE FAILED {} {}",
+            planned.case.method, planned.case.endpoint
+        );
+        println!("  {}", err.to_string().replace('\n', "\n  "));
+    }
+
+    let mut failing = 0;
+    for (name, candidate) in &report.candidates {
+        println!(
+            "\n{}: {} passed, {} failed{}",
+            name,
+            candidate.passed(),
+            candidate.failed(),
+            if candidate.timed_out {
+                " (timed out)"
+            } else if candidate.cancelled {
+                " (interrupted)"
+            } else {
+                ""
+            }
+        );
+        for case in candidate.failures() {
+            let err = case.outcome.as_ref().expect_err("failures have an error");
+            let summary = err.to_string();
+            println!(
+                "  FAIL {} {} (seed {}): {}",
+                case.method,
+                case.endpoint,
+                case.seed,
+                summary.lines().next().unwrap_or_default()
+            );
+        }
+        if candidate.failed() > 0 || candidate.timed_out || candidate.cancelled {
+            failing += 1;
+        }
+        if let Some(path) = &config.report.html {
+            let path = candidate_path(path, name);
+            if let Err(err) = html::write(candidate, &path) {
+                eprintln!("error: failed to write HTML report: {}", err);
+            }
+        }
+        if let Some(path) = &config.report.junit {
+            let path = candidate_path(path, name);
+            if let Err(err) = junit::write(candidate, &path) {
+                eprintln!("error: failed to write JUnit report: {}", err);
+            }
+        }
+        if let Some(path) = &config.report.har {
+            let servers = har::Servers {
+                client: &config.candidates[name],
+                actual: config.server_url.as_deref(),
+                version: config.api_version,
+            };
+            if let Err(err) = har::write(candidate, servers, candidate_path(path, name)) {
+                eprintln!("error: failed to write HAR: {}", err);
+            }
+        }
+        if let Some(path) = &config.report.csv {
+            if let Err(err) = results::write_csv(candidate, candidate_path(path, name)) {
+                eprintln!("error: failed to write CSV results: {}", err);
+            }
+        }
+        if let Some(path) = &config.report.jsonl {
+            if let Err(err) = results::write_json_lines(candidate, candidate_path(path, name)) {
+                eprintln!("error: failed to write JSON Lines results: {}", err);
+            }
+        }
+    }
+
+    println!(
+        "\n{}/{} candidates passed",
+        report.candidates.len() - failing,
+        report.candidates.len()
+    );
+    if failing > 0 || !report.reference_failures.is_empty() {
+        Exit::Findings.into()
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+fn write_metrics(config: &Config, metrics: Option<&PrometheusMetrics>) {
+    let (Some(path), Some(metrics)) = (&config.report.prometheus, metrics) else {
+        return;
+    };
+    match metrics.write(path) {
+        Ok(()) => println!("Prometheus metrics written to {}", path.display()),
+        Err(err) => eprintln!("error: failed to write Prometheus metrics: {}", err),
+    }
+}
+
+// `report.html` becomes `report-<name>.html`.
+fn candidate_path(path: &Path, name: &str) -> PathBuf {
+    let stem = path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("report");
+    let file = match path.extension().and_then(|e| e.to_str()) {
+        Some(ext) => format!("{}-{}.{}", stem, name, ext),
+        None => format!("{}-{}", stem, name),
+    };
+    path.with_file_name(file)
+}
+
+fn print_plan(runner: &mut Runner) {
+    let plan = runner.plan();
+    let hooks = runner.tester().hooks();
+    for planned in &plan {
+        let case = &planned.case;
+        println!(
+            "[{}] {} {} (seed {})",
+            planned.iteration, case.method, case.endpoint, planned.seed
+        );
+        if !case.query.is_empty() {
+            println!("  query: {:?}", case.query);
+        }
+        if let Some(body) = &case.body {
+            println!("  body: {}", body);
+        }
+        if let Some(malformation) = &case.malformation {
+            println!("  malformed: {:?}", malformation);
+        }
+        if let Some(perturbation) = &case.header_perturbation {
+            println!("  token header: {:?}", perturbation);
+        }
+    }
+    println!(
+        "\n{} cases planned, each with {} before and {} after hooks",
+        plan.len(),
+        hooks.before.len(),
+        hooks.after.len()
+    );
+}
+
+async fn replay(path: PathBuf, overrides: Overrides, corpus_dir: Option<PathBuf>) -> ExitCode {
+    let Some((config, _chaos)) = load_config(&path, &overrides) else {
+        return Exit::Config.into();
+    };
+    let Some(dir) = corpus_dir.or_else(|| config.corpus.clone()) else {
+        eprintln!("error: no corpus directory given (use --corpus or set `corpus` in the config)");
+        return Exit::Config.into();
+    };
+
+    let tester = match config.load_spec().and_then(|spec| config.tester(spec)) {
+        Ok(tester) => tester,
+        Err(err) => {
+            eprintln!("error: {}", err);
+            return Exit::Config.into();
+        }
+    };
+    if !wait_ready(&config, &tester, None).await {
+        return Exit::Unreachable.into();
+    }
+    let results = match Corpus::new(dir).load() {
+        Ok(recorded) => corpus::replay(&tester, recorded).await,
+        Err(err) => Err(err),
+    };
+    let results = match results {
+        Ok(results) => results,
+        Err(err) => {
+            eprintln!("error: {}", err);
+            return Exit::Config.into();
+        }
+    };
+
+    let mut reproduced = 0;
+    for (request, outcome) in &results {
+        match outcome {
+            Ok(()) => println!("FIXED {} {}", request.method, request.endpoint),
+            Err(err) => {
+                reproduced += 1;
+                println!("FAIL  {} {}", request.method, request.endpoint);
+                println!("  {}", err.to_string().replace('\n', "\n  "));
+            }
+        }
+    }
+
+    println!(
+        "\n{} fixed, {} still failing",
+        results.len() - reproduced,
+        reproduced
+    );
+    if reproduced > 0 {
+        Exit::Findings.into()
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+async fn snapshot(
+    path: PathBuf,
+    overrides: Overrides,
+    dir: Option<PathBuf>,
+    record: bool,
+) -> ExitCode {
+    let Some((config, _chaos)) = load_config(&path, &overrides) else {
+        return Exit::Config.into();
+    };
+    let Some(dir) = dir.or_else(|| config.snapshots.clone()) else {
+        eprintln!(
+            "error: no snapshot directory given (use --dir or set `snapshots` in the config)"
+        );
+        return Exit::Config.into();
+    };
+    let store = SnapshotStore::new(dir);
+
+    let tester = match config.load_spec().and_then(|spec| config.tester(spec)) {
+        Ok(tester) => tester,
+        Err(err) => {
+            eprintln!("error: {}", err);
+            return Exit::Config.into();
+        }
+    };
+
+    if record {
+        let mut runner = match Runner::from_config(&config) {
+            Ok(runner) => runner,
+            Err(err) => {
+                eprintln!("error: {}", err);
+                return Exit::Config.into();
+            }
+        };
+        if let Some(reference) = tester.for_reference() {
+            if !wait_ready(&config, &reference, Some("reference server")).await {
+                return Exit::Unreachable.into();
+            }
+        }
+        println!("seed: {}\n", runner.seed());
+        let requests = runner
+            .plan()
+            .iter()
+            .map(|planned| RecordedRequest::new(&planned.case, planned.seed))
+            .collect();
+        return match snapshot::record(&tester, requests, &store).await {
+            Ok(paths) => {
+                println!(
+                    "{} snapshots written to {}",
+                    paths.len(),
+                    store.dir().display()
+                );
+                ExitCode::SUCCESS
+            }
+            Err(err) => {
+                eprintln!("error: {}", err);
+                match err {
+                    SnapshotError::Reference(_) => Exit::Unreachable.into(),
+                    _ => Exit::Config.into(),
+                }
+            }
+        };
+    }
+
+    // Only the server under test is needed to compare against recordings.
+    if !wait_ready(&config, &tester.for_server(config.test_url.clone()), None).await {
+        return Exit::Unreachable.into();
+    }
+    let results = match store.load() {
+        Ok(snapshots) => snapshot::compare(&tester, snapshots).await,
+        Err(err) => Err(err),
+    };
+    let results = match results {
+        Ok(results) => results,
+        Err(err) => {
+            eprintln!("error: {}", err);
+            return Exit::Config.into();
+        }
+    };
+
+    let mut failed = 0;
+    for (snapshot, comparison) in &results {
+        let request = &snapshot.request;
+        match &comparison.outcome {
+            Ok(()) => println!("PASS {} {}", request.method, request.endpoint),
+            Err(err) => {
+                failed += 1;
+                println!("FAIL {} {}", request.method, request.endpoint);
+                println!("  {}", err.to_string().replace('\n', "\n  "));
+            }
+        }
+    }
+
+    println!("\n{} passed, {} failed", results.len() - failed, failed);
+    if failed > 0 {
+        Exit::Findings.into()
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+// A tester for the server under test alone, or with `reference` for the
+// reference server alone, once it is up.
+async fn single_server(config: &Config, reference: bool) -> Result<Tester, Exit> {
+    let tester = match config.load_spec().and_then(|spec| config.tester(spec)) {
+        Ok(tester) => tester,
+        Err(err) => {
+            eprintln!("error: {}", err);
+            return Err(Exit::Config);
+        }
+    };
+    let tester = match (tester.for_reference(), reference) {
+        (Some(reference), true) => reference,
+        (None, true) => {
+            eprintln!("error: --reference needs a reference server (`server_url`)");
+            return Err(Exit::Config);
+        }
+        (_, false) => tester.for_server(config.test_url.clone()),
+    };
+    let server = reference.then_some("reference server");
+    if wait_ready(config, &tester, server).await {
+        Ok(tester)
+    } else {
+        Err(Exit::Unreachable)
+    }
+}
+
+async fn model(path: PathBuf, overrides: Overrides, reference: bool) -> ExitCode {
+    let Some((config, _chaos)) = load_config(&path, &overrides) else {
+        return Exit::Config.into();
+    };
+    let tester = match single_server(&config, reference).await {
+        Ok(tester) => tester,
+        Err(exit) => return exit.into(),
+    };
+    let mut checker = ModelChecker::new(tester, config.stateful.sequences, config.stateful.steps);
+    if let Some(auth) = &config.auth {
+        match auth.strategy(config.api_version) {
+            Ok(strategy) => checker = checker.with_auth(strategy),
+            Err(err) => {
+                eprintln!("error: {}", err);
+                return Exit::Config.into();
+            }
+        }
+    }
+
+    let mut rng = FuzzRng::from_seed_or_entropy(config.seed);
+    println!("seed: {}\n", rng.seed());
+    let results = checker.run(&mut rng).await;
+
+    let mut failed = 0;
+    for (index, result) in results.iter().enumerate() {
+        match &result.outcome {
+            Ok(()) => println!(
+                "PASS sequence {} (seed {}): {} steps",
+                index,
+                result.seed,
+                result.operations.len()
+            ),
+            Err(err) => {
+                failed += 1;
+                println!("FAIL sequence {} (seed {})", index, result.seed);
+                for (step, operation) in result.operations.iter().enumerate() {
+                    println!("  {}. {}", step + 1, operation);
+                }
+                println!("  {}", err.to_string().replace('\n', "\n  "));
+            }
+        }
+    }
+
+    println!("\n{} passed, {} failed", results.len() - failed, failed);
+    if failed > 0 {
+        Exit::Findings.into()
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+async fn race(path: PathBuf, overrides: Overrides, reference: bool) -> ExitCode {
+    let Some((config, _chaos)) = load_config(&path, &overrides) else {
+        return Exit::Config.into();
+    };
+    let tester = match single_server(&config, reference).await {
+        Ok(tester) => tester,
+        Err(exit) => return exit.into(),
+    };
+    let mut checker = RaceChecker::new(tester, config.stateful.races);
+    if let Some(auth) = &config.auth {
+        match auth.strategy(config.api_version) {
+            Ok(strategy) => checker = checker.with_auth(strategy),
+            Err(err) => {
+                eprintln!("error: {}", err);
+                return Exit::Config.into();
+            }
+        }
+    }
+
+    let mut rng = FuzzRng::from_seed_or_entropy(config.seed);
+    println!("seed: {}\n", rng.seed());
+    let results = checker.run(&mut rng).await;
+
+    let mut failed = 0;
+    for (index, result) in results.iter().enumerate() {
+        match &result.outcome {
+            Ok(()) => println!(
+                "PASS round {} (seed {}): {}, statuses {:?}",
+                index, result.seed, result.race, result.statuses
+            ),
+            Err(err) => {
+                failed += 1;
+                println!(
+                    "FAIL round {} (seed {}): {}",
+                    index, result.seed, result.race
+                );
+                // Setup that failed never got to the race.
+                let racing = if result.statuses.is_empty() {
+                    usize::MAX
+                } else {
+                    result.operations.len() - 2
+                };
+                for (step, operation) in result.operations.iter().enumerate() {
+                    let marker = if step >= racing { " (concurrent)" } else { "" };
+                    println!("  {}. {}{}", step + 1, operation, marker);
+                }
+                println!("  {}", err.to_string().replace('\n', "\n  "));
+            }
+        }
+    }
+
+    println!("\n{} passed, {} failed", results.len() - failed, failed);
+    if failed > 0 {
+        Exit::Findings.into()
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+async fn interactive(path: PathBuf, overrides: Overrides) -> ExitCode {
+    use std::io::Write;
+    use tokio::io::{AsyncBufReadExt, BufReader};
+
+    let Some((config, _chaos)) = load_config(&path, &overrides) else {
+        return Exit::Config.into();
+    };
+    let tester = match config.load_spec().and_then(|spec| config.tester(spec)) {
+        Ok(tester) => tester,
+        Err(err) => {
+            eprintln!("error: {}", err);
+            return Exit::Config.into();
+        }
+    };
+    let mut servers = vec![(
+        "server under test",
+        tester.for_server(config.test_url.clone()),
+    )];
+    if let Some(reference) = tester.for_reference() {
+        servers.push(("reference server", reference));
+    }
+    let mut session = Repl::new(config.api_version);
+    for (name, tester) in servers {
+        if !wait_ready(&config, &tester, Some(name)).await {
+            return Exit::Unreachable.into();
+        }
+        session = session.with_server(name, tester);
+    }
+    if let Some(auth) = &config.auth {
+        match auth.strategy(config.api_version) {
+            Ok(strategy) => session = session.with_auth(strategy),
+            Err(err) => {
+                eprintln!("error: {}", err);
+                return Exit::Config.into();
+            }
+        }
+    }
+
+    println!("{}\n", repl::HELP);
+    let mut lines = BufReader::new(tokio::io::stdin()).lines();
+    loop {
+        print!("> ");
+        let _ = std::io::stdout().flush();
+        let line = match lines.next_line().await {
+            Ok(Some(line)) => line,
+            Ok(None) => break,
+            Err(err) => {
+                eprintln!("error: {}", err);
+                return ExitCode::FAILURE;
+            }
+        };
+        let (output, flow) = session.execute(&line).await;
+        if !output.is_empty() {
+            println!("{}", output);
+        }
+        if flow == Flow::Quit {
+            break;
+        }
+    }
+    ExitCode::SUCCESS
+}
+
+async fn errors(path: PathBuf, overrides: Overrides) -> ExitCode {
+    let Some((config, _chaos)) = load_config(&path, &overrides) else {
+        return Exit::Config.into();
+    };
+    let (tester, catalogue) = match config
+        .load_spec()
+        .and_then(|spec| Ok((config.tester(spec)?, config.catalogue()?)))
+    {
+        Ok(loaded) => loaded,
+        Err(err) => {
+            eprintln!("error: {}", err);
+            return Exit::Config.into();
+        }
+    };
+    let mut servers = vec![(
+        "server under test",
+        tester.for_server(config.test_url.clone()),
+    )];
+    if let Some(reference) = tester.for_reference() {
+        servers.push(("reference server", reference));
+    }
+    let mut checker = ErrorChecker::new(catalogue, config.api_version);
+    for (name, tester) in servers {
+        if !wait_ready(&config, &tester, Some(name)).await {
+            return Exit::Unreachable.into();
+        }
+        checker = checker.with_server(name, tester);
+    }
+    if let Some(auth) = &config.auth {
+        match auth.strategy(config.api_version) {
+            Ok(strategy) => checker = checker.with_auth(strategy),
+            Err(err) => {
+                eprintln!("error: {}", err);
+                return Exit::Config.into();
+            }
+        }
+    }
+
+    let mut rng = FuzzRng::from_seed_or_entropy(config.seed);
+    println!("seed: {}\n", rng.seed());
+    let probes = checker.run(&mut rng).await;
+
+    let mut failed = 0;
+    for probe in &probes {
+        let value = probe
+            .value
+            .as_ref()
+            .map(|value| format!(" = {}", value))
+            .unwrap_or_default();
+        let violations: Vec<_> = probe.violations().collect();
+        if violations.is_empty() {
+            println!("PASS {}{}", probe.rule, value);
+            continue;
+        }
+        failed += 1;
+        println!("FAIL {}{}", probe.rule, value);
+        for (server, status) in violations {
+            match status {
+                Ok(status) => println!("  {} returned {}", server, status),
+                Err(err) => println!("  {}: {}", server, err.to_string().replace('\n', "\n  ")),
+            }
+        }
+    }
+
+    println!("\n{} passed, {} failed", probes.len() - failed, failed);
+    if failed > 0 {
+        Exit::Findings.into()
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+// The scenarios from `files` and the config (or the built-in pack without
+// any), a graph to infer more from if the config asks for them, and a runner
+// for them once its servers are up. `reference_only` leaves out the server
+// under test.
+async fn prepare_scenarios(
+    config: &Config,
+    files: &[PathBuf],
+    reference_only: bool,
+) -> Result<(Vec<Scenario>, Option<DependencyGraph>, ScenarioRunner), Exit> {
+    let files: Vec<&PathBuf> = config.scenarios.iter().chain(files).collect();
+    let scenarios = if files.is_empty() && config.infer.is_none() {
+        pack::builtin(config.api_version)
+    } else {
+        match files.into_iter().map(yaml::load).collect() {
+            Ok(scenarios) => scenarios,
+            Err(err) => {
+                eprintln!("error: {}", err);
+                return Err(Exit::Config);
+            }
+        }
+    };
+    let spec = match config.load_spec() {
+        Ok(spec) => spec,
+        Err(err) => {
+            eprintln!("error: {}", err);
+            return Err(Exit::Config);
+        }
+    };
+    let graph = match (&config.infer, &spec) {
+        (Some(_), Some(spec)) => Some(DependencyGraph::from_spec(spec)),
+        (Some(_), None) => {
+            eprintln!("error: inferring scenarios needs a `spec`");
+            return Err(Exit::Config);
+        }
+        (None, _) => None,
+    };
+    let tester = match config.tester(spec) {
+        Ok(tester) => tester,
+        Err(err) => {
+            eprintln!("error: {}", err);
+            return Err(Exit::Config);
+        }
+    };
+    let mut servers = Vec::new();
+    if !reference_only {
+        servers.push((
+            "server under test",
+            tester.for_server(config.test_url.clone()),
+        ));
+    }
+    match tester.for_reference() {
+        Some(reference) => servers.push(("reference server", reference)),
+        None if reference_only => {
+            eprintln!("error: this needs a reference server (`server_url`)");
+            return Err(Exit::Config);
+        }
+        None => {}
+    }
+    let mut runner = ScenarioRunner::new(config.api_version);
+    for (name, tester) in servers {
+        if !wait_ready(config, &tester, Some(name)).await {
+            return Err(Exit::Unreachable);
+        }
+        runner = runner.with_server(name, tester);
+    }
+    if let Some(auth) = &config.auth {
+        match auth.strategy(config.api_version) {
+            Ok(strategy) => runner = runner.with_auth(strategy),
+            Err(err) => {
+                eprintln!("error: {}", err);
+                return Err(Exit::Config);
+            }
+        }
+    }
+    Ok((scenarios, graph, runner))
+}
+
+fn add_inferred(
+    config: &Config,
+    graph: Option<&DependencyGraph>,
+    scenarios: &mut Vec<Scenario>,
+    rng: &mut FuzzRng,
+) {
+    let (Some(infer), Some(graph)) = (&config.infer, graph) else {
+        return;
+    };
+    println!(
+        "inferring {} scenarios from {} links between endpoints\n",
+        infer.scenarios,
+        graph.links().len()
+    );
+    for index in 0..infer.scenarios {
+        let name = format!("inferred {}", index + 1);
+        scenarios.push(graph.generate(name, infer.steps, rng));
+    }
+}
+
+async fn scenarios(path: PathBuf, overrides: Overrides, files: Vec<PathBuf>) -> ExitCode {
+    let Some((config, _chaos)) = load_config(&path, &overrides) else {
+        return Exit::Config.into();
+    };
+    let (mut scenarios, graph, runner) = match prepare_scenarios(&config, &files, false).await {
+        Ok(prepared) => prepared,
+        Err(exit) => return exit.into(),
+    };
+
+    let mut rng = FuzzRng::from_seed_or_entropy(config.seed);
+    println!("seed: {}\n", rng.seed());
+    add_inferred(&config, graph.as_ref(), &mut scenarios, &mut rng);
+    let results = runner.run(&scenarios, &mut rng).await;
+    let mut failed = 0;
+    for result in &results {
+        let steps = &result.scenario.steps;
+        if result.passed() {
+            println!("PASS {}: {} steps", result.scenario.name, steps.len());
+            continue;
+        }
+        failed += 1;
+        println!("FAIL {}", result.scenario.name);
+        for failure in result.failures() {
+            match failure {
+                Failure::Unexpected {
+                    step,
+                    server,
+                    expected,
+                    status,
+                } => println!(
+                    "  step {} {}: {} returned {}, expected {}",
+                    step + 1,
+                    steps[step],
+                    server,
+                    status,
+                    expected
+                ),
+                Failure::Disagreement { step, statuses } => {
+                    let statuses: Vec<String> = statuses
+                        .iter()
+                        .map(|(server, status)| format!("{} {}", server, status))
+                        .collect();
+                    println!(
+                        "  step {} {}: servers disagree ({})",
+                        step + 1,
+                        steps[step],
+                        statuses.join(", ")
+                    );
+                }
+                Failure::Invariant {
+                    step,
+                    server,
+                    message,
+                } => println!(
+                    "  step {} {}: {}: {}",
+                    step + 1,
+                    steps[step],
+                    server,
+                    message
+                ),
+            }
+        }
+        for run in &result.runs {
+            if let Err(err) = &run.outcome {
+                println!(
+                    "  {} stopped: {}",
+                    run.server,
+                    err.to_string().replace('\n', "\n  ")
+                );
+            }
+        }
+    }
+
+    println!("\n{} passed, {} failed", results.len() - failed, failed);
+    if failed > 0 {
+        Exit::Findings.into()
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+async fn learn(
+    path: PathBuf,
+    overrides: Overrides,
+    files: Vec<PathBuf>,
+    out: Option<PathBuf>,
+) -> ExitCode {
+    let Some((mut config, _chaos)) = load_config(&path, &overrides) else {
+        return Exit::Config.into();
+    };
+    let Some(out) = out.or_else(|| config.learned_schemas.clone()) else {
+        eprintln!(
+            "error: nowhere to save the schemas (use --out or set `learned_schemas` in the config)"
+        );
+        return Exit::Config.into();
+    };
+    // What was learned before doesn't constrain what is learned now.
+    config.learned_schemas = None;
+    let (mut scenarios, graph, runner) = match prepare_scenarios(&config, &files, true).await {
+        Ok(prepared) => prepared,
+        Err(exit) => return exit.into(),
+    };
+
+    let mut rng = FuzzRng::from_seed_or_entropy(config.seed);
+    println!("seed: {}\n", rng.seed());
+    add_inferred(&config, graph.as_ref(), &mut scenarios, &mut rng);
+    let results = runner.run(&scenarios, &mut rng).await;
+    let mut learned = LearnedSchemas::new();
+    for result in &results {
+        for run in &result.runs {
+            for (case, response) in &run.responses {
+                learned.observe(&case.method, &case.endpoint, response);
+            }
+            if let Err(err) = &run.outcome {
+                println!(
+                    "{} stopped early: {}",
+                    result.scenario.name,
+                    err.to_string().replace('\n', "\n  ")
+                );
+            }
+        }
+    }
 
-fn main() {
-    println!("Hello, world!");
+    for ((method, path), route) in &learned.routes {
+        println!(
+            "{} {}: {} {}",
+            method,
+            path,
+            route.samples,
+            if route.samples == 1 {
+                "response"
+            } else {
+                "responses"
+            }
+        );
+    }
+    match learned.save(&out) {
+        Ok(()) => {
+            println!(
+                "\nschemas for {} routes saved to {}",
+                learned.routes.len(),
+                out.display()
+            );
+            ExitCode::SUCCESS
+        }
+        Err(err) => {
+            eprintln!("error: {}", err);
+            Exit::Config.into()
+        }
+    }
 }
@@ -0,0 +1,131 @@
+use crate::corpus::RecordedRequest;
+use crate::spec::template_matches;
+use reqwest::Method;
+use serde_json::Value;
+use std::fmt;
+use std::sync::Arc;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Finding {
+    pub message: String,
+}
+
+impl Finding {
+    pub fn new(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+// A custom invariant checked after the built-in comparisons pass. `resp_a` is
+// the server under test's body and `resp_b` the reference's (null when an
+// oracle stands in for the reference). Assertions see cases in schedule
+// order, so ones spanning several requests can keep state between calls.
+pub trait Assertion: Send + Sync {
+    fn check(&self, req: &RecordedRequest, resp_a: &Value, resp_b: &Value) -> Vec<Finding>;
+}
+
+impl<F> Assertion for F
+where
+    F: Fn(&RecordedRequest, &Value, &Value) -> Vec<Finding> + Send + Sync,
+{
+    fn check(&self, req: &RecordedRequest, resp_a: &Value, resp_b: &Value) -> Vec<Finding> {
+        self(req, resp_a, resp_b)
+    }
+}
+
+// Assertions registered per (method, path template). Every matching one runs.
+#[derive(Clone, Default)]
+pub struct Assertions {
+    rules: Vec<(Method, String, Arc<dyn Assertion>)>,
+}
+
+impl Assertions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with(
+        mut self,
+        method: Method,
+        template: impl Into<String>,
+        assertion: impl Assertion + 'static,
+    ) -> Self {
+        self.rules
+            .push((method, template.into(), Arc::new(assertion)));
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    pub fn check(&self, req: &RecordedRequest, resp_a: &Value, resp_b: &Value) -> Vec<Finding> {
+        let method = req.method.as_str();
+        self.rules
+            .iter()
+            .filter(|(m, template, _)| {
+                m.as_str() == method && template_matches(template, &req.endpoint)
+            })
+            .flat_map(|(_, _, assertion)| assertion.check(req, resp_a, resp_b))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::client::TestCase;
+    use serde_json::json;
+
+    fn request(method: Method, endpoint: &str) -> RecordedRequest {
+        RecordedRequest::new(&TestCase::new(endpoint, method, None), 0)
+    }
+
+    #[test]
+    fn every_assertion_for_the_route_runs() {
+        let same_name = |_: &RecordedRequest, a: &Value, b: &Value| {
+            if a["name"] == b["name"] {
+                Vec::new()
+            } else {
+                vec![Finding::new("names differ")]
+            }
+        };
+        let assertions = Assertions::new()
+            .with(Method::GET, "/admin/quiz/{quizid}", same_name)
+            .with(
+                Method::GET,
+                "/admin/quiz/{quizid}",
+                |_: &RecordedRequest, _: &Value, _: &Value| vec![Finding::new("always")],
+            )
+            .with(
+                Method::DELETE,
+                "/admin/quiz/{quizid}",
+                |_: &RecordedRequest, _: &Value, _: &Value| vec![Finding::new("wrong method")],
+            );
+
+        let findings = assertions.check(
+            &request(Method::GET, "/admin/quiz/7"),
+            &json!({"name": "a"}),
+            &json!({"name": "b"}),
+        );
+        assert_eq!(
+            findings,
+            [Finding::new("names differ"), Finding::new("always")]
+        );
+        assert!(assertions
+            .check(
+                &request(Method::GET, "/admin/quiz/list/extra"),
+                &json!({}),
+                &json!({})
+            )
+            .is_empty());
+    }
+}
@@ -0,0 +1,130 @@
+use crate::models::client::TestCase;
+use reqwest::header::{HeaderName, HeaderValue, AUTHORIZATION};
+use reqwest::Method;
+use serde_json::Value;
+use std::fmt::Debug;
+
+// Where a session token goes on each request. Cases that already carry a token
+// in that place are left alone.
+pub trait AuthStrategy: Debug + Send + Sync {
+    fn apply(&self, token: &str, case: &mut TestCase);
+
+    // The header the token goes in, for strategies that use one.
+    fn header(&self) -> Option<HeaderName> {
+        None
+    }
+}
+
+// Iteration 2 style: a body field, or a query parameter for GET and DELETE.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BodyField(pub String);
+
+impl AuthStrategy for BodyField {
+    fn apply(&self, token: &str, case: &mut TestCase) {
+        let name = &self.0;
+        if case.method == Method::GET || case.method == Method::DELETE {
+            if !case.query.iter().any(|(key, _)| key == name) {
+                case.query.push((name.clone(), token.to_string()));
+            }
+            return;
+        }
+        match &mut case.body {
+            Some(Value::Object(map)) => {
+                map.entry(name.clone())
+                    .or_insert_with(|| Value::String(token.to_string()));
+            }
+            None => {
+                let mut map = serde_json::Map::new();
+                map.insert(name.clone(), Value::String(token.to_string()));
+                case.body = Some(Value::Object(map));
+            }
+            // A mutated non-object body has nowhere to put the token.
+            Some(_) => {}
+        }
+    }
+}
+
+// Iteration 3 style: the token in a header of its own.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Header(pub HeaderName);
+
+impl AuthStrategy for Header {
+    fn apply(&self, token: &str, case: &mut TestCase) {
+        if let Ok(value) = HeaderValue::from_str(token) {
+            case.headers.entry(self.0.clone()).or_insert(value);
+        }
+    }
+
+    fn header(&self) -> Option<HeaderName> {
+        Some(self.0.clone())
+    }
+}
+
+// `Authorization: Bearer <token>`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BearerHeader;
+
+impl AuthStrategy for BearerHeader {
+    fn apply(&self, token: &str, case: &mut TestCase) {
+        if let Ok(value) = HeaderValue::from_str(&format!("Bearer {}", token)) {
+            case.headers.entry(AUTHORIZATION).or_insert(value);
+        }
+    }
+
+    fn header(&self) -> Option<HeaderName> {
+        Some(AUTHORIZATION)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn body_tokens_go_in_the_query_for_gets_and_deletes() {
+        let strategy = BodyField("token".to_string());
+        let mut get = TestCase::new("/quiz", Method::GET, None);
+        strategy.apply("abc", &mut get);
+        assert_eq!(get.query, [("token".to_string(), "abc".to_string())]);
+        assert_eq!(get.body, None);
+
+        let mut delete = TestCase::new("/quiz", Method::DELETE, None)
+            .with_query(vec![("token".to_string(), "mine".to_string())]);
+        strategy.apply("abc", &mut delete);
+        assert_eq!(delete.query, [("token".to_string(), "mine".to_string())]);
+    }
+
+    #[test]
+    fn body_tokens_do_not_replace_one_already_there() {
+        let strategy = BodyField("token".to_string());
+        let mut post = TestCase::new("/quiz", Method::POST, Some(json!({"title": "t"})));
+        strategy.apply("abc", &mut post);
+        assert_eq!(post.body, Some(json!({"title": "t", "token": "abc"})));
+
+        let mut own = TestCase::new("/quiz", Method::POST, Some(json!({"token": "mine"})));
+        strategy.apply("abc", &mut own);
+        assert_eq!(own.body, Some(json!({"token": "mine"})));
+
+        let mut empty = TestCase::new("/quiz", Method::PUT, None);
+        strategy.apply("abc", &mut empty);
+        assert_eq!(empty.body, Some(json!({"token": "abc"})));
+
+        let mut array = TestCase::new("/quiz", Method::POST, Some(json!([1])));
+        strategy.apply("abc", &mut array);
+        assert_eq!(array.body, Some(json!([1])));
+    }
+
+    #[test]
+    fn header_tokens_keep_an_existing_header() {
+        let name = HeaderName::from_static("x-token");
+        let mut case = TestCase::new("/quiz", Method::GET, None);
+        Header(name.clone()).apply("abc", &mut case);
+        BearerHeader.apply("abc", &mut case);
+        assert_eq!(case.headers[&name], "abc");
+        assert_eq!(case.headers[AUTHORIZATION], "Bearer abc");
+
+        BearerHeader.apply("other", &mut case);
+        assert_eq!(case.headers[AUTHORIZATION], "Bearer abc");
+    }
+}
@@ -0,0 +1,141 @@
+use reqwest::multipart::{Form, Part};
+use reqwest::RequestBuilder;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+// How a case's body is encoded on the wire. Bodies are always generated and
+// mutated as JSON values; this only decides how they are sent.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BodyKind {
+    #[default]
+    Json,
+    // application/x-www-form-urlencoded, one field per top-level key.
+    Form,
+    // multipart/form-data, one part per top-level key. A value of the form
+    // `{ "filename": ..., "content": ..., "content_type": ... }` becomes a file.
+    Multipart,
+    // Strings are sent verbatim, anything else as its JSON text, with no
+    // Content-Type set.
+    Raw,
+}
+
+impl BodyKind {
+    pub fn apply(self, builder: RequestBuilder, body: &Value) -> RequestBuilder {
+        match self {
+            BodyKind::Json => builder.json(body),
+            BodyKind::Form => match body {
+                Value::Object(map) => {
+                    let fields: Vec<(&str, String)> = map
+                        .iter()
+                        .map(|(key, value)| (key.as_str(), field_text(value)))
+                        .collect();
+                    builder.form(&fields)
+                }
+                // Not representable as fields, but still worth sending.
+                other => builder
+                    .header("content-type", "application/x-www-form-urlencoded")
+                    .body(field_text(other)),
+            },
+            BodyKind::Multipart => builder.multipart(multipart(body)),
+            BodyKind::Raw => builder.body(field_text(body)),
+        }
+    }
+}
+
+fn multipart(body: &Value) -> Form {
+    let Value::Object(map) = body else {
+        return Form::new().text("body", field_text(body));
+    };
+
+    let mut form = Form::new();
+    for (key, value) in map {
+        form = match file_part(value) {
+            Some(part) => form.part(key.clone(), part),
+            None => form.text(key.clone(), field_text(value)),
+        };
+    }
+    form
+}
+
+fn file_part(value: &Value) -> Option<Part> {
+    let filename = value.get("filename")?.as_str()?.to_string();
+    let content = field_text(value.get("content")?).into_bytes();
+    let part = || Part::bytes(content.clone()).file_name(filename.clone());
+    match value.get("content_type").and_then(Value::as_str) {
+        // An unparseable MIME type is left off rather than dropping the file.
+        Some(mime) => Some(part().mime_str(mime).unwrap_or_else(|_| part())),
+        None => Some(part()),
+    }
+}
+
+fn field_text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::CONTENT_TYPE;
+    use reqwest::{Client, Request};
+    use serde_json::json;
+
+    fn encode(kind: BodyKind, body: Value) -> Request {
+        let builder = Client::new().post("http://localhost/quiz");
+        kind.apply(builder, &body).build().unwrap()
+    }
+
+    fn content_type(request: &Request) -> Option<&str> {
+        request
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+    }
+
+    fn text(request: &Request) -> &str {
+        std::str::from_utf8(request.body().unwrap().as_bytes().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn forms_send_one_field_per_key() {
+        let request = encode(BodyKind::Form, json!({"name": "quiz one", "count": 2}));
+        assert_eq!(
+            content_type(&request),
+            Some("application/x-www-form-urlencoded")
+        );
+        assert_eq!(text(&request), "count=2&name=quiz+one");
+
+        let request = encode(BodyKind::Form, json!([1, 2]));
+        assert_eq!(text(&request), "[1,2]");
+    }
+
+    #[test]
+    fn raw_bodies_are_sent_verbatim_without_a_content_type() {
+        let request = encode(BodyKind::Raw, json!("not { json"));
+        assert_eq!(content_type(&request), None);
+        assert_eq!(text(&request), "not { json");
+        assert_eq!(text(&encode(BodyKind::Raw, json!({"a": 1}))), r#"{"a":1}"#);
+    }
+
+    #[test]
+    fn multipart_bodies_set_a_boundary() {
+        let request = encode(
+            BodyKind::Multipart,
+            json!({"name": "quiz", "thumbnail": {"filename": "a.png", "content": "png", "content_type": "image/png"}}),
+        );
+        assert!(content_type(&request)
+            .is_some_and(|ct| ct.starts_with("multipart/form-data; boundary=")));
+        assert!(file_part(&json!({"filename": "a.txt", "content": 1})).is_some());
+        assert!(file_part(&json!({"content": "no name"})).is_none());
+    }
+
+    #[test]
+    fn kinds_parse_from_snake_case() {
+        let kind: BodyKind = serde_json::from_value(json!("multipart")).unwrap();
+        assert_eq!(kind, BodyKind::Multipart);
+        assert_eq!(BodyKind::default(), BodyKind::Json);
+    }
+}
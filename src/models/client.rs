@@ -1,164 +1,3018 @@
-use reqwest::{Client, Method, Response};
+use crate::fuzz::headers::HeaderPerturbation;
+use crate::fuzz::paths::escape_path;
+use crate::fuzz::protocol::Malformation;
+use crate::models::assertion::Finding;
+use crate::models::auth::AuthStrategy;
+use crate::models::body::BodyKind;
+use crate::models::compare::{
+    compare_headers, compare_json, compare_raw, ArrayMode, CompareOptions, ComparisonMode,
+    FieldAnnotation, NonJsonMode, NumericOptions,
+};
+use crate::models::endpoints::{self, versioned_path, ApiVersion};
+use crate::models::hooks::Hooks;
+use crate::models::hygiene::{self, JsonIssue};
+use crate::models::network::Network;
+use crate::models::observer::Observer;
+use crate::models::oracle::Oracle;
+use crate::models::pointer::PointerPattern;
+use crate::models::rate_limit::RateLimiter;
+use crate::models::retry::RetryPolicy;
+use crate::models::stream;
+use crate::models::transport::{self, Transport};
+use crate::pretty;
+use crate::report::RunReport;
+use crate::rng::FuzzRng;
+use crate::runner::{CaseResult, Crash};
+use crate::schema::SchemaRegistry;
+use crate::spec::{without_query, Spec};
+use reqwest::header::{HeaderMap, HeaderName, CONTENT_TYPE, LOCATION, RETRY_AFTER};
+use reqwest::redirect::Policy;
+use reqwest::{Client, Method, Response, StatusCode, Version};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use std::collections::BTreeSet;
+use std::fmt;
+use std::hash::{DefaultHasher, Hash, Hasher};
 use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
 use thiserror::Error;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+use tracing::{Instrument, Span};
 
+// Every way a single case can fail. Each variant names the request it came
+// from so errors can be reported without the case alongside them.
 #[derive(Debug, Error)]
-pub enum RequestError {
-    #[error("HTTP request failed: {0}")]
-    HttpError(#[from] reqwest::Error),
+pub enum TesterError {
+    #[error("Request `{method} {endpoint}` failed: {source}")]
+    Network {
+        endpoint: String,
+        method: Method,
+        #[source]
+        source: reqwest::Error,
+    },
+
+    #[error("Request `{method} {endpoint}` to `{url}` timed out after {timeout:?}")]
+    Timeout {
+        endpoint: String,
+        method: Method,
+        url: String,
+        timeout: Duration,
+    },
+
+    #[error("Invalid JSON for `{method} {endpoint}`: {source}")]
+    Json {
+        endpoint: String,
+        method: Method,
+        #[source]
+        source: serde_json::Error,
+    },
+
+    // One server hung while the other answered, which usually points at a
+    // handler that never responds rather than a slow machine.
+    #[error(
+        "Request `{method} {endpoint}` to the {hung} (`{url}`) timed out after {elapsed:?}, but the other server answered with status {status}.\nPayload: {}",
+        .payload.as_ref().map_or_else(|| "none".to_string(), ToString::to_string)
+    )]
+    TimeoutDivergence {
+        endpoint: String,
+        method: Method,
+        // "server under test" or "reference server".
+        hung: &'static str,
+        url: String,
+        elapsed: Duration,
+        // The other server's.
+        status: u16,
+        // Boxed to keep the error small.
+        payload: Option<Box<Value>>,
+    },
+
+    #[error("Non-JSON response body from `{url}` for `{method} {endpoint}` (status {status}): {raw_body:?}")]
+    NonJsonBody {
+        endpoint: String,
+        method: Method,
+        url: String,
+        status: u16,
+        raw_body: String,
+    },
+
+    #[error("Non-standard JSON from the {server} for `{method} {endpoint}`: {issue}")]
+    JsonHygiene {
+        endpoint: String,
+        method: Method,
+        // "server under test" or "reference server".
+        server: &'static str,
+        issue: JsonIssue,
+    },
+
+    #[error("Response body from `{url}` for `{method} {endpoint}` (status {status}) exceeded {limit} bytes")]
+    BodyTooLarge {
+        endpoint: String,
+        method: Method,
+        url: String,
+        status: u16,
+        limit: usize,
+    },
+
+    #[error("Status code mismatch for `{method} {endpoint}`.\nClient Status: {client_status}\nActual Status: {actual_status}")]
+    StatusMismatch {
+        endpoint: String,
+        method: Method,
+        client_status: u16,
+        actual_status: u16,
+    },
+
+    #[error("Assertions failed for `{method} {endpoint}`:\n{}", findings.iter().map(|f| format!("- {}", f)).collect::<Vec<_>>().join("\n"))]
+    AssertionFailed {
+        endpoint: String,
+        method: Method,
+        findings: Vec<Finding>,
+    },
+
+    #[error("Number out of range for `{method} {endpoint}` (path `{path}`, server: {server}): {value} is not {expected}")]
+    NumberOutOfRange {
+        endpoint: String,
+        method: Method,
+        server: &'static str,
+        path: String,
+        value: Box<Value>,
+        expected: String,
+    },
+
+    #[error("Field rule broken for `{method} {endpoint}` (path `{path}`, server: {server}): {} is not {expected}", pretty::compact(value))]
+    FieldRuleViolation {
+        endpoint: String,
+        method: Method,
+        server: &'static str,
+        path: String,
+        value: Box<Value>,
+        expected: String,
+    },
+
+    #[error("Timestamp out of range for `{method} {endpoint}` (path `{path}`, server: {server}): {} is not {expected}", pretty::compact(value))]
+    TimestampOutOfRange {
+        endpoint: String,
+        method: Method,
+        server: &'static str,
+        path: String,
+        value: Box<Value>,
+        expected: String,
+    },
+
+    #[error("Array element with a different shape for `{method} {endpoint}` (path `{path}`, server: {server}).\n{}", pretty::side_by_side("Expected Like", expected, "Actual Value", actual_value))]
+    ArrayElementMismatch {
+        endpoint: String,
+        method: Method,
+        server: &'static str,
+        path: String,
+        expected: Box<Value>,
+        actual_value: Box<Value>,
+    },
+
+    #[error(
+        "JSON type mismatch for `{method} {endpoint}` (path `{path}`).\n{}",
+        pretty::side_by_side("Client Value", client_value, "Actual Value", actual_value)
+    )]
+    TypeMismatch {
+        endpoint: String,
+        method: Method,
+        path: String,
+        client_value: Box<Value>,
+        actual_value: Box<Value>,
+    },
+
+    #[error(
+        "JSON value mismatch for `{method} {endpoint}` (path `{path}`).\n{}",
+        pretty::side_by_side("Client Value", client_value, "Actual Value", actual_value)
+    )]
+    ValueMismatch {
+        endpoint: String,
+        method: Method,
+        path: String,
+        client_value: Box<Value>,
+        actual_value: Box<Value>,
+    },
+
+    #[error("JSON array length mismatch for `{method} {endpoint}` (path `{path}`): client has {client_len} items, actual has {actual_len}")]
+    ArrayLengthMismatch {
+        endpoint: String,
+        method: Method,
+        path: String,
+        client_len: usize,
+        actual_len: usize,
+    },
+
+    #[error("Spec violation for `{method} {endpoint}` by {server} server (status {status}): expected {expected} at `{path}`, got {}", pretty::compact(actual_value))]
+    SpecViolation {
+        endpoint: String,
+        method: Method,
+        server: &'static str,
+        status: u16,
+        path: String,
+        expected: String,
+        actual_value: Box<Value>,
+    },
+
+    #[error("Response schema violation for `{method} {endpoint}` (server: {server}, status {status}): expected {expected} at `{path}`, got {}", pretty::compact(actual_value))]
+    SchemaViolation {
+        endpoint: String,
+        method: Method,
+        server: &'static str,
+        status: u16,
+        path: String,
+        expected: String,
+        actual_value: Box<Value>,
+    },
+
+    #[error("Sending `{method} {endpoint}` again straight away diverged: {source}")]
+    RepeatMismatch {
+        endpoint: String,
+        method: Method,
+        #[source]
+        source: Box<TesterError>,
+    },
+
+    #[error("Redirect target mismatch for `{method} {endpoint}`.\nClient Location: {client_location:?}\nActual Location: {actual_location:?}")]
+    RedirectMismatch {
+        endpoint: String,
+        method: Method,
+        client_location: Option<String>,
+        actual_location: Option<String>,
+    },
+
+    #[error("Response content mismatch for `{method} {endpoint}`: {message}")]
+    ContentMismatch {
+        endpoint: String,
+        method: Method,
+        message: String,
+    },
+
+    #[error("HTTP version mismatch for `{method} {endpoint}`.\nClient Version: {client_version}\nActual Version: {actual_version}")]
+    HttpVersionMismatch {
+        endpoint: String,
+        method: Method,
+        client_version: String,
+        actual_version: String,
+    },
+
+    // From the typed helpers, `RequestClient::request_json` and
+    // `Tester::compare_as`.
+    #[error("Unexpected body for `{method} {endpoint}` (server: {server}, status {status}): not a `{type_name}`: {message}")]
+    UnexpectedBody {
+        endpoint: String,
+        method: Method,
+        server: String,
+        status: u16,
+        type_name: &'static str,
+        message: String,
+    },
+
+    // Caught by the endpoint registry before anything was sent.
+    #[error("Invalid request `{method} {endpoint}`: {message}")]
+    InvalidUsage {
+        endpoint: String,
+        method: Method,
+        message: String,
+    },
+
+    #[error("Response header mismatch for `{method} {endpoint}` on `{header}`.\nClient Value: {client_value:?}\nActual Value: {actual_value:?}")]
+    HeaderMismatch {
+        endpoint: String,
+        method: Method,
+        header: String,
+        client_value: Option<String>,
+        actual_value: Option<String>,
+    },
+}
+
+impl TesterError {
+    // Errors converted with `From` don't know which request they came from
+    // until this fills it in; every other variant is built with its request.
+    pub fn with_case(mut self, case: &TestCase) -> Self {
+        if let TesterError::Network {
+            endpoint, method, ..
+        }
+        | TesterError::Json {
+            endpoint, method, ..
+        } = &mut self
+        {
+            *endpoint = case.endpoint.clone();
+            *method = case.method.clone();
+        }
+        self
+    }
+
+    // The JSON pointer the failure was found at, for failures tied to one.
+    pub fn path(&self) -> Option<&str> {
+        match self {
+            TesterError::SpecViolation { path, .. }
+            | TesterError::SchemaViolation { path, .. }
+            | TesterError::NumberOutOfRange { path, .. }
+            | TesterError::TimestampOutOfRange { path, .. }
+            | TesterError::FieldRuleViolation { path, .. }
+            | TesterError::ArrayElementMismatch { path, .. }
+            | TesterError::ArrayLengthMismatch { path, .. }
+            | TesterError::TypeMismatch { path, .. }
+            | TesterError::ValueMismatch { path, .. } => Some(path),
+            TesterError::RepeatMismatch { source, .. } => source.path(),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for TesterError {
+    fn from(source: reqwest::Error) -> Self {
+        TesterError::Network {
+            endpoint: String::new(),
+            method: Method::GET,
+            source,
+        }
+    }
+}
+
+impl From<serde_json::Error> for TesterError {
+    fn from(source: serde_json::Error) -> Self {
+        TesterError::Json {
+            endpoint: String::new(),
+            method: Method::GET,
+            source,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TestCase {
+    pub endpoint: String,
+    pub method: Method,
+    pub query: Vec<(String, String)>,
+    pub body: Option<Value>,
+    pub body_kind: BodyKind,
+    // Sends a broken version of `body` instead of encoding it normally.
+    pub malformation: Option<Malformation>,
+    // Mangles the session token's header after everything else is in place.
+    pub header_perturbation: Option<HeaderPerturbation>,
+    pub headers: HeaderMap,
+    // The seed the case was generated from, if any; only used for logging.
+    pub seed: Option<u64>,
+    // Compares this case's responses this way instead of as the tester would.
+    pub options: Option<CompareOptions>,
+}
+
+impl TestCase {
+    pub fn new(endpoint: impl Into<String>, method: Method, body: Option<Value>) -> Self {
+        Self {
+            endpoint: endpoint.into(),
+            method,
+            query: Vec::new(),
+            body,
+            body_kind: BodyKind::default(),
+            malformation: None,
+            header_perturbation: None,
+            headers: HeaderMap::new(),
+            seed: None,
+            options: None,
+        }
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    // Start from `Tester::options` to change only part of them.
+    pub fn with_options(mut self, options: CompareOptions) -> Self {
+        self.options = Some(options);
+        self
+    }
+
+    // Identifies the payload in logs without printing it; stable for a given
+    // body within one build.
+    pub fn payload_hash(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.body.as_ref().map(Value::to_string).hash(&mut hasher);
+        self.malformation.map(|m| m as u8).hash(&mut hasher);
+        self.header_perturbation.map(|p| p as u8).hash(&mut hasher);
+        hasher.finish()
+    }
+
+    fn span(&self) -> Span {
+        tracing::info_span!(
+            "case",
+            endpoint = %self.endpoint,
+            method = %self.method,
+            seed = self.seed,
+            payload_hash = %format!("{:016x}", self.payload_hash()),
+        )
+    }
+
+    // Each pair becomes its own query parameter, in order; repeated names are
+    // sent repeatedly.
+    pub fn with_query(
+        mut self,
+        params: impl IntoIterator<Item = (impl Into<String>, impl Into<String>)>,
+    ) -> Self {
+        self.query
+            .extend(params.into_iter().map(|(k, v)| (k.into(), v.into())));
+        self
+    }
+
+    pub fn with_body_kind(mut self, kind: BodyKind) -> Self {
+        self.body_kind = kind;
+        self
+    }
+
+    pub fn with_malformation(mut self, malformation: Malformation) -> Self {
+        self.malformation = Some(malformation);
+        self
+    }
+
+    pub fn with_header_perturbation(mut self, perturbation: HeaderPerturbation) -> Self {
+        self.header_perturbation = Some(perturbation);
+        self
+    }
+}
+
+impl ServerResponse {
+    // The body read as a `T`, for assertions on a typed value instead of a
+    // `Value`. `server` names the server in the error.
+    pub fn json<T: DeserializeOwned>(
+        &self,
+        case: &TestCase,
+        server: &str,
+    ) -> Result<T, TesterError> {
+        let decoded = match &self.raw {
+            None => T::deserialize(&self.body).map_err(|err| err.to_string()),
+            Some(raw) => Err(format!("got {}", raw)),
+        };
+        decoded.map_err(|message| TesterError::UnexpectedBody {
+            endpoint: case.endpoint.clone(),
+            method: case.method.clone(),
+            server: server.to_string(),
+            status: self.status,
+            type_name: std::any::type_name::<T>(),
+            message,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ServerResponse {
+    pub status: u16,
+    pub headers: HeaderMap,
+    // `Value::Null` when `raw` is set.
+    pub body: Value,
+    // Redirects, and bodies with a content type other than JSON (images,
+    // HTML, plain text), which are compared as they are instead of decoded.
+    pub raw: Option<RawResponse>,
+    // Time from sending the request until the whole body was read.
+    pub latency: Duration,
+    // Unknown for recorded responses that don't say.
+    pub sent_at: Option<SystemTime>,
+    // Unknown for recorded responses.
+    pub http_version: Option<Version>,
+    // Only kept with `Tester::with_capture`.
+    pub exchange: Option<Arc<Exchange>>,
+    // Only looked for with `Tester::with_strict_json`.
+    pub json_issues: Vec<JsonIssue>,
+}
+
+// Exactly what went over the wire for one response, apart from the headers
+// the HTTP client adds itself (`host`, `content-length`, cookies).
+#[derive(Debug, Clone)]
+pub struct Exchange {
+    pub request: SentRequest,
+    pub response_body: Vec<u8>,
+}
+
+#[derive(Debug, Clone)]
+pub struct SentRequest {
+    pub method: Method,
+    pub url: String,
+    pub headers: HeaderMap,
+    pub body: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RawResponse {
+    // Any 3xx. A `Location` pointing back at the server that sent it is made
+    // relative, so two servers redirecting to the same path match.
+    Redirect {
+        location: Option<String>,
+    },
+    Bytes {
+        content_type: String,
+        bytes: Vec<u8>,
+    },
+    // A JSON body over `Tester::with_streaming`'s size, compared as it
+    // arrived and not kept; `head` is what was read before that was known.
+    Streamed {
+        head: Vec<u8>,
+    },
+}
+
+impl RawResponse {
+    // `base_url` is the server's own URL.
+    fn read(status: u16, headers: &HeaderMap, bytes: &[u8], base_url: &str) -> Option<Self> {
+        if (300..400).contains(&status) {
+            let location = headers
+                .get(LOCATION)
+                .map(|value| String::from_utf8_lossy(value.as_bytes()).into_owned())
+                .map(
+                    |location| match location.strip_prefix(base_url.trim_end_matches('/')) {
+                        Some(path) if path.is_empty() || path.starts_with(['/', '?']) => {
+                            format!("/{}", path.trim_start_matches('/'))
+                        }
+                        _ => location,
+                    },
+                );
+            return Some(RawResponse::Redirect { location });
+        }
+        // Without a content type the body is still expected to be JSON.
+        let content_type = headers.get(CONTENT_TYPE)?.to_str().ok()?;
+        let essence = content_type.split(';').next().unwrap_or_default().trim();
+        if essence.eq_ignore_ascii_case("application/json") || essence.ends_with("+json") {
+            return None;
+        }
+        Some(RawResponse::Bytes {
+            content_type: essence.to_ascii_lowercase(),
+            bytes: bytes.to_vec(),
+        })
+    }
+}
+
+impl fmt::Display for RawResponse {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RawResponse::Redirect {
+                location: Some(location),
+            } => write!(f, "a redirect to {}", location),
+            RawResponse::Redirect { location: None } => {
+                f.write_str("a redirect without a Location")
+            }
+            RawResponse::Bytes {
+                content_type,
+                bytes,
+            } => write!(f, "{} ({} bytes)", content_type, bytes.len()),
+            RawResponse::Streamed { head } => {
+                write!(f, "JSON streamed after its first {} bytes", head.len())
+            }
+        }
+    }
+}
+
+// A server that never answered its health check, from `Tester::wait_ready`.
+#[derive(Debug, Error)]
+#[error("The {server} at `{url}` did not come up within {waited:?} (last attempt: {last})")]
+pub struct NotReady {
+    pub server: String,
+    pub url: String,
+    pub waited: Duration,
+    pub last: String,
+}
+
+#[derive(Debug)]
+pub struct Comparison {
+    pub client: Option<ServerResponse>,
+    pub actual: Option<ServerResponse>,
+    pub outcome: Result<(), TesterError>,
+}
+
+#[derive(Clone)]
+pub struct Tester {
+    client: Arc<RequestClient>,
+    // Exactly one of `actual` and `oracle` is set.
+    actual: Option<Arc<RequestClient>>,
+    oracle: Option<Arc<dyn Oracle>>,
+    spec: Option<Arc<Spec>>,
+    schemas: Arc<SchemaRegistry>,
+    concurrency: usize,
+    options: CompareOptions,
+    hooks: Hooks,
+    version: Option<ApiVersion>,
+    repeat_idempotent: bool,
+    // Polled by `wait_ready`, which needs a 2xx answer; when unset, any answer
+    // to `GET /` will do, so waiting never changes a server's state.
+    health: Option<TestCase>,
+}
+
+impl Tester {
+    pub fn new(test_url: String, server_url: String) -> Self {
+        Self {
+            client: Arc::new(RequestClient::new(test_url)),
+            actual: Some(Arc::new(RequestClient::new(server_url))),
+            oracle: None,
+            spec: None,
+            schemas: Arc::new(SchemaRegistry::new()),
+            concurrency: 1,
+            options: CompareOptions::default(),
+            hooks: Hooks::default(),
+            version: None,
+            repeat_idempotent: false,
+            health: None,
+        }
+    }
+
+    pub fn versioned(test_url: String, server_url: String, version: ApiVersion) -> Self {
+        Self::new(test_url, server_url).with_version(version)
+    }
+
+    // Checks the server under test against a model instead of a second server.
+    pub fn against_oracle(test_url: String, oracle: impl Oracle + 'static) -> Self {
+        Self {
+            actual: None,
+            oracle: Some(Arc::new(oracle)),
+            ..Self::new(test_url, String::new())
+        }
+    }
+
+    // A copy of this tester, with the same settings, aimed at another server
+    // under test and with no reference of its own.
+    pub fn for_server(&self, test_url: String) -> Self {
+        Self {
+            client: Arc::new((*self.client).clone().with_base_url(test_url)),
+            actual: None,
+            oracle: None,
+            ..self.clone()
+        }
+    }
+
+    // A copy of this tester aimed at its reference server alone, reaching it
+    // the way the reference is reached here. `None` without a reference.
+    pub fn for_reference(&self) -> Option<Self> {
+        Some(Self {
+            client: self.actual.clone()?,
+            actual: None,
+            oracle: None,
+            ..self.clone()
+        })
+    }
+
+    pub fn concurrency(&self) -> usize {
+        self.concurrency
+    }
+
+    pub fn options(&self) -> &CompareOptions {
+        &self.options
+    }
 
-    #[error("Failed to serialize query parameters: {0}")]
-    UrlEncodeError(#[from] serde_urlencoded::ser::Error),
+    pub fn version(&self) -> Option<ApiVersion> {
+        self.version
+    }
+
+    pub fn with_mode(mut self, mode: ComparisonMode) -> Self {
+        self.options.mode = mode;
+        self
+    }
+
+    // Fields at these paths may be null on one server and absent on the other.
+    // `strict` keeps them distinct anyway.
+    pub fn with_optional(
+        mut self,
+        paths: impl IntoIterator<Item = impl Into<PointerPattern>>,
+        strict: bool,
+    ) -> Self {
+        self.options
+            .optional
+            .extend(paths.into_iter().map(Into::into));
+        self.options.strict_optional = strict;
+        self
+    }
+
+    pub fn with_array_mode(mut self, arrays: ArrayMode) -> Self {
+        self.options.arrays = arrays;
+        self
+    }
+
+    pub fn with_non_json(mut self, non_json: NonJsonMode) -> Self {
+        self.options.non_json = non_json;
+        self
+    }
+
+    pub fn with_numeric(mut self, numeric: NumericOptions) -> Self {
+        self.options.numeric = numeric;
+        self
+    }
+
+    // Compares the fields at `path` as `annotation` says rather than by the
+    // comparison mode.
+    pub fn with_field(
+        mut self,
+        path: impl Into<PointerPattern>,
+        annotation: FieldAnnotation,
+    ) -> Self {
+        self.options.fields.push((path.into(), annotation));
+        self
+    }
+
+    // Opts in to comparing these response headers; none are compared by default.
+    pub fn with_compared_headers(mut self, headers: impl IntoIterator<Item = HeaderName>) -> Self {
+        self.options.headers.extend(headers);
+        self
+    }
+
+    // Fields at these paths are skipped entirely, even when missing on one side.
+    pub fn with_ignored(
+        mut self,
+        paths: impl IntoIterator<Item = impl Into<PointerPattern>>,
+    ) -> Self {
+        self.options
+            .ignored
+            .extend(paths.into_iter().map(Into::into));
+        self
+    }
+
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    // Each server gets its own limiter, so a slow reference does not throttle
+    // the server under test or vice versa.
+    pub fn with_rate_limit(mut self, requests_per_second: f64, burst: u32) -> Self {
+        self.client = Arc::new(
+            (*self.client)
+                .clone()
+                .with_rate_limit(requests_per_second, burst),
+        );
+        self.actual = self.actual.map(|actual| {
+            Arc::new(
+                (*actual)
+                    .clone()
+                    .with_rate_limit(requests_per_second, burst),
+            )
+        });
+        self
+    }
+
+    pub fn with_spec(mut self, spec: Spec) -> Self {
+        self.spec = Some(Arc::new(spec));
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.client = Arc::new((*self.client).clone().with_timeout(timeout));
+        self.actual = self
+            .actual
+            .map(|actual| Arc::new((*actual).clone().with_timeout(timeout)));
+        self
+    }
+
+    // Both servers' requests, hooks and health checks included, are shown to
+    // `observer`. `for_server` copies keep it.
+    pub fn with_observer(mut self, observer: Arc<dyn Observer>) -> Self {
+        self.client = Arc::new((*self.client).clone().with_observer(observer.clone()));
+        self.actual = self
+            .actual
+            .map(|actual| Arc::new((*actual).clone().with_observer(observer)));
+        self
+    }
+
+    // Delivers the server under test's requests through `transport`, e.g. to a
+    // handler in this process. `for_server` copies keep it.
+    pub fn with_transport(mut self, transport: impl Transport + 'static) -> Self {
+        self.client = Arc::new((*self.client).clone().with_transport(Arc::new(transport)));
+        self
+    }
+
+    // The same for the reference server, if there is one.
+    pub fn with_reference_transport(mut self, transport: impl Transport + 'static) -> Self {
+        let transport: Arc<dyn Transport> = Arc::new(transport);
+        self.actual = self
+            .actual
+            .map(|actual| Arc::new((*actual).clone().with_transport(transport)));
+        self
+    }
+
+    pub fn with_retry(mut self, policy: RetryPolicy) -> Self {
+        self.client = Arc::new((*self.client).clone().with_retry(policy.clone()));
+        self.actual = self
+            .actual
+            .map(|actual| Arc::new((*actual).clone().with_retry(policy)));
+        self
+    }
+
+    // The same strategy is used for both servers, so one suite can run against
+    // either API iteration by swapping it.
+    pub fn with_auth(mut self, strategy: Arc<dyn AuthStrategy>, token: impl Into<String>) -> Self {
+        let token = token.into();
+        self.client = Arc::new(
+            (*self.client)
+                .clone()
+                .with_auth(strategy.clone(), token.clone()),
+        );
+        self.actual = self
+            .actual
+            .map(|actual| Arc::new((*actual).clone().with_auth(strategy, token)));
+        self
+    }
+
+    // Sends every path under the version's prefix. Call before `with_token` so
+    // the token goes where that version expects it.
+    pub fn with_version(mut self, version: ApiVersion) -> Self {
+        self.version = Some(version);
+        self.client = Arc::new((*self.client).clone().with_version(version));
+        self.actual = self
+            .actual
+            .map(|actual| Arc::new((*actual).clone().with_version(version)));
+        self
+    }
+
+    // Authenticates with the strategy of the configured version, or as a body
+    // field without one.
+    pub fn with_token(self, token: impl Into<String>) -> Self {
+        let strategy = self.version.unwrap_or(ApiVersion::V1).auth_strategy();
+        self.with_auth(strategy, token)
+    }
+
+    // Every server keeps its own jar, so sessions never leak between them.
+    pub fn with_cookies(mut self) -> Self {
+        self.client = Arc::new((*self.client).clone().with_cookies());
+        self.actual = self
+            .actual
+            .map(|actual| Arc::new((*actual).clone().with_cookies()));
+        self
+    }
+
+    // Each server is reached its own way, e.g. only the reference through a
+    // proxy.
+    pub fn with_networks(mut self, test: Network, reference: Network) -> Self {
+        self.client = Arc::new((*self.client).clone().with_network(test));
+        self.actual = self
+            .actual
+            .map(|actual| Arc::new((*actual).clone().with_network(reference)));
+        self
+    }
+
+    // Both servers are held to the same versions.
+    pub fn with_http_version(mut self, http_version: HttpVersion) -> Self {
+        self.client = Arc::new((*self.client).clone().with_http_version(http_version));
+        self.actual = self
+            .actual
+            .map(|actual| Arc::new((*actual).clone().with_http_version(http_version)));
+        self
+    }
+
+    // Compares which cookies both servers set and with which attributes,
+    // ignoring their values.
+    pub fn with_cookie_comparison(mut self) -> Self {
+        self.options.cookies = true;
+        self
+    }
+
+    // Sends GET, PUT and DELETE cases twice in a row to each server and compares
+    // the second responses too, so e.g. deleting something already deleted or
+    // repeating an update must behave the same on both. Only with a reference
+    // server; an oracle can't tell a first request from a repeat.
+    pub fn with_idempotency_checks(mut self) -> Self {
+        self.repeat_idempotent = true;
+        self
+    }
+
+    // Keeps the exact request and response body of every exchange in its
+    // `ServerResponse`, for dumping failed cases.
+    pub fn with_capture(mut self) -> Self {
+        self.client = Arc::new((*self.client).clone().with_capture());
+        self.actual = self
+            .actual
+            .map(|actual| Arc::new((*actual).clone().with_capture()));
+        self
+    }
+
+    // Each server's JSON must be as clean as the other's: a duplicate key, a
+    // `NaN` or `Infinity` or trailing data in one response and not the other
+    // is a mismatch. Against an oracle, any of them is.
+    pub fn with_strict_json(mut self) -> Self {
+        self.client = Arc::new((*self.client).clone().with_strict_json());
+        self.actual = self
+            .actual
+            .map(|actual| Arc::new((*actual).clone().with_strict_json()));
+        self
+    }
+
+    // When comparing against a reference server, JSON bodies over `bytes` long
+    // aren't read in full but compared by type as both arrive, and neither is
+    // read past the first difference; see `stream`. Comparison options that
+    // need the values (exact modes, `[fields]`, number checks) read and decode
+    // them as usual. Spec, schema and strict JSON checks skip such bodies, and
+    // their latency is only up to their first `bytes`.
+    pub fn with_streaming(mut self, bytes: usize) -> Self {
+        self.client = Arc::new((*self.client).clone().with_streaming(bytes));
+        self.actual = self
+            .actual
+            .map(|actual| Arc::new((*actual).clone().with_streaming(bytes)));
+        self
+    }
+
+    pub fn with_max_body_size(mut self, bytes: usize) -> Self {
+        self.client = Arc::new((*self.client).clone().with_max_body_size(bytes));
+        self.actual = self
+            .actual
+            .map(|actual| Arc::new((*actual).clone().with_max_body_size(bytes)));
+        self
+    }
+
+    pub fn with_hooks(mut self, hooks: Hooks) -> Self {
+        self.hooks = hooks;
+        self
+    }
+
+    pub fn hooks(&self) -> &Hooks {
+        &self.hooks
+    }
+
+    pub fn with_health_check(mut self, case: TestCase) -> Self {
+        self.health = Some(case);
+        self
+    }
+
+    // Polls the health check on every server, backing off between attempts,
+    // until each is up. Fails with the first server still down after
+    // `timeout`, instead of letting the run start and trip over connection
+    // errors.
+    pub async fn wait_ready(&self, timeout: Duration) -> Result<(), NotReady> {
+        let deadline = Instant::now() + timeout;
+        let (probe, any_status) = match &self.health {
+            Some(case) => (case.clone(), false),
+            None => (TestCase::new("/", Method::GET, None), true),
+        };
+        let actual = async {
+            match &self.actual {
+                Some(actual) => actual.wait_ready(&probe, any_status, deadline).await,
+                None => Ok(()),
+            }
+        };
+        let (client, actual) =
+            tokio::join!(self.client.wait_ready(&probe, any_status, deadline), actual);
+        let not_ready = |server: &str, client: &RequestClient, last| NotReady {
+            server: server.to_string(),
+            url: client.url(&probe.endpoint),
+            waited: timeout,
+            last,
+        };
+        client.map_err(|last| not_ready("server under test", &self.client, last))?;
+        if let (Err(last), Some(server)) = (actual, &self.actual) {
+            return Err(not_ready("reference server", server, last));
+        }
+        Ok(())
+    }
+
+    // Each server's responses are validated against these on their own, so a
+    // contract violation is caught even when both servers agree.
+    pub fn with_response_schemas(mut self, schemas: SchemaRegistry) -> Self {
+        self.schemas = Arc::new(schemas);
+        self
+    }
+
+    // Consults the endpoint registry: tokens only go to routes that need one,
+    // requests no route takes (a method the route doesn't have, a `{...}`
+    // left unfilled) fail before they are sent, and responses are held to the
+    // registry's schemas wherever no others are set. Call after
+    // `with_response_schemas`.
+    pub fn with_registry(mut self) -> Self {
+        self.client = Arc::new((*self.client).clone().with_registry());
+        self.actual = self
+            .actual
+            .map(|actual| Arc::new((*actual).clone().with_registry()));
+        self.schemas = Arc::new(
+            (*self.schemas)
+                .clone()
+                .with_all(endpoints::response_schemas()),
+        );
+        self
+    }
+
+    // A request to the registry route `path` with values like those it
+    // accepts (though the IDs may well not exist).
+    pub fn valid_case(
+        &self,
+        method: Method,
+        path: &str,
+        rng: &mut FuzzRng,
+    ) -> Result<TestCase, TesterError> {
+        let invalid = |message: &str| TesterError::InvalidUsage {
+            endpoint: path.to_string(),
+            method: method.clone(),
+            message: message.to_string(),
+        };
+        let endpoint = endpoints::find(path).ok_or_else(|| invalid("not a registry route"))?;
+        endpoint
+            .valid_case(&method, rng)
+            .ok_or_else(|| invalid("the route doesn't take this method"))
+    }
+
+    pub fn with_headers(mut self, headers: HeaderMap) -> Self {
+        self.client = Arc::new((*self.client).clone().with_default_headers(headers.clone()));
+        self.actual = self
+            .actual
+            .map(|actual| Arc::new((*actual).clone().with_default_headers(headers)));
+        self
+    }
+
+    pub async fn compare(
+        &self,
+        endpoint: &str,
+        method: Method,
+        body: Option<Value>,
+    ) -> Result<(), TesterError> {
+        self.compare_with_headers(endpoint, method, body, &HeaderMap::new())
+            .await
+    }
+
+    pub async fn compare_with_headers(
+        &self,
+        endpoint: &str,
+        method: Method,
+        body: Option<Value>,
+        headers: &HeaderMap,
+    ) -> Result<(), TesterError> {
+        let case = TestCase {
+            headers: headers.clone(),
+            ..TestCase::new(endpoint, method, body)
+        };
+        self.compare_case(&case).await.outcome
+    }
+
+    pub async fn compare_with_query(
+        &self,
+        endpoint: &str,
+        method: Method,
+        query: impl IntoIterator<Item = (impl Into<String>, impl Into<String>)>,
+        body: Option<Value>,
+    ) -> Result<(), TesterError> {
+        let case = TestCase::new(endpoint, method, body).with_query(query);
+        self.compare_case(&case).await.outcome
+    }
+
+    // Compares the case as `compare_case` does and, when both responses
+    // match, reads each body as a `T`: (server under test, reference). Needs
+    // a reference server, as an oracle only expects a status and a schema.
+    // Bodies are read in full even over `with_streaming`'s size.
+    pub async fn compare_as<T: DeserializeOwned>(
+        &self,
+        case: &TestCase,
+    ) -> Result<(T, T), TesterError> {
+        let no_reference = || TesterError::InvalidUsage {
+            endpoint: case.endpoint.clone(),
+            method: case.method.clone(),
+            message: "compare_as needs a reference server".to_string(),
+        };
+        if self.actual.is_none() {
+            return Err(no_reference());
+        }
+        let comparison = self.compare_logged(case, false).await;
+        comparison.outcome?;
+        let (Some(client), Some(actual)) = (&comparison.client, &comparison.actual) else {
+            return Err(no_reference());
+        };
+        Ok((client.json(case, "client")?, actual.json(case, "actual")?))
+    }
+
+    pub async fn compare_case(&self, case: &TestCase) -> Comparison {
+        self.compare_logged(case, true).await
+    }
+
+    async fn compare_logged(&self, case: &TestCase, streaming: bool) -> Comparison {
+        let span = case.span();
+        let comparison = self
+            .compare_case_inner(case, streaming)
+            .instrument(span.clone())
+            .await;
+        span.in_scope(|| log_outcome(&comparison.outcome));
+        comparison
+    }
+
+    async fn compare_case_inner(&self, case: &TestCase, streaming: bool) -> Comparison {
+        let mut client = None;
+        let mut actual = None;
+        let outcome = match self.run_hooks(&self.hooks.before).await {
+            Ok(()) => {
+                self.compare_into(case, streaming, &mut client, &mut actual)
+                    .await
+            }
+            Err(err) => Err(err),
+        };
+        // After hooks always run so a failing case still leaves clean state,
+        // but the case's own failure takes precedence over a hook's.
+        let after = self.run_hooks(&self.hooks.after).await;
+        let outcome = outcome.and(after);
+        Comparison {
+            client,
+            actual,
+            outcome,
+        }
+    }
+
+    // Responses are stored as soon as they are read so callers still get them
+    // when a later step of the comparison fails.
+    async fn compare_into(
+        &self,
+        case: &TestCase,
+        streaming: bool,
+        client: &mut Option<ServerResponse>,
+        actual: &mut Option<ServerResponse>,
+    ) -> Result<(), TesterError> {
+        let repeat = self.repeat_idempotent
+            && self.actual.is_some()
+            && matches!(case.method, Method::GET | Method::PUT | Method::DELETE);
+        let streaming = streaming
+            && self.actual.is_some()
+            && stream::supports(case.options.as_ref().unwrap_or(&self.options));
+        let started = Instant::now();
+        let (response_client, body_client) =
+            match (self.client.receive(case, streaming).await, &self.actual) {
+                (Err(err @ TesterError::Timeout { .. }), Some(server)) => {
+                    let elapsed = started.elapsed();
+                    // Without the reference's answer this is an ordinary timeout.
+                    let Ok(response) = server.fetch(case).await else {
+                        return Err(err);
+                    };
+                    let response = &*actual.insert(response);
+                    return Err(timeout_divergence(
+                        case,
+                        "server under test",
+                        err,
+                        elapsed,
+                        response,
+                    ));
+                }
+                (response, _) => {
+                    let (response, body) = response?;
+                    (&*client.insert(response), body)
+                }
+            };
+        let repeat_client = if repeat {
+            Some(self.client.receive(case, streaming).await?)
+        } else {
+            None
+        };
+        let (response_actual, body_actual) = match (&self.actual, &self.oracle) {
+            (Some(server), _) => {
+                let started = Instant::now();
+                match server.receive(case, streaming).await {
+                    Ok((response, body)) => (&*actual.insert(response), body),
+                    Err(err) => {
+                        let elapsed = started.elapsed();
+                        return Err(timeout_divergence(
+                            case,
+                            "reference server",
+                            err,
+                            elapsed,
+                            response_client,
+                        ));
+                    }
+                }
+            }
+            (None, Some(oracle)) => {
+                return self.check_oracle(oracle.as_ref(), case, response_client)
+            }
+            (None, None) => return Ok(()),
+        };
+        let repeat_actual = match (&self.actual, repeat) {
+            (Some(server), true) => Some(server.receive(case, streaming).await?),
+            _ => None,
+        };
+        self.check_received(
+            case,
+            (response_client, body_client),
+            (response_actual, body_actual),
+        )
+        .await?;
+        if let (Some((repeat_client, body_client)), Some((repeat_actual, body_actual))) =
+            (repeat_client, repeat_actual)
+        {
+            self.check_received(
+                case,
+                (&repeat_client, body_client),
+                (&repeat_actual, body_actual),
+            )
+            .await
+            .map_err(|err| TesterError::RepeatMismatch {
+                endpoint: case.endpoint.clone(),
+                method: case.method.clone(),
+                source: Box::new(err),
+            })?;
+        }
+        Ok(())
+    }
+
+    // Compares the server under test against a reference response obtained
+    // elsewhere, e.g. a recorded snapshot. Hooks only run on the server under test.
+    pub async fn compare_against(&self, case: &TestCase, expected: ServerResponse) -> Comparison {
+        let span = case.span();
+        let comparison = self
+            .compare_against_inner(case, expected)
+            .instrument(span.clone())
+            .await;
+        span.in_scope(|| log_outcome(&comparison.outcome));
+        comparison
+    }
+
+    async fn compare_against_inner(&self, case: &TestCase, expected: ServerResponse) -> Comparison {
+        let mut client = None;
+        let servers = [&*self.client];
+        let outcome = match send_hooks(&servers, &self.hooks.before).await {
+            Ok(()) => match self.client.fetch(case).await {
+                Ok(response) => self.check_pair(case, client.insert(response), &expected),
+                Err(err) => Err(err),
+            },
+            Err(err) => Err(err),
+        };
+        let after = send_hooks(&servers, &self.hooks.after).await;
+        Comparison {
+            client,
+            actual: Some(expected),
+            outcome: outcome.and(after),
+        }
+    }
+
+    // Sends the case, with its hooks, to the reference server alone. `None`
+    // when the tester has no reference server.
+    pub async fn fetch_reference(
+        &self,
+        case: &TestCase,
+    ) -> Option<Result<ServerResponse, TesterError>> {
+        Some(self.fetch_with_hooks(self.actual.as_ref()?, case).await)
+    }
+
+    // Sends the case, with its hooks, to the server under test alone.
+    pub async fn fetch(&self, case: &TestCase) -> Result<ServerResponse, TesterError> {
+        self.fetch_with_hooks(&self.client, case).await
+    }
+
+    // Sends the case to the server under test alone and without hooks, so state
+    // left by earlier requests is kept.
+    pub async fn send(&self, case: &TestCase) -> Result<ServerResponse, TesterError> {
+        self.client.fetch(case).await
+    }
+
+    // Runs the before hooks against the server under test alone.
+    pub async fn reset(&self) -> Result<(), TesterError> {
+        send_hooks(&[&*self.client], &self.hooks.before).await
+    }
+
+    async fn fetch_with_hooks(
+        &self,
+        server: &RequestClient,
+        case: &TestCase,
+    ) -> Result<ServerResponse, TesterError> {
+        let servers = [server];
+        let response = match send_hooks(&servers, &self.hooks.before).await {
+            Ok(()) => server.fetch(case).await,
+            Err(err) => Err(err),
+        };
+        let after = send_hooks(&servers, &self.hooks.after).await;
+        response.and_then(|response| after.map(|()| response))
+    }
+
+    // `check_pair`, unless either body is still to be read by `stream`, when
+    // everything else is checked first so a difference there leaves both
+    // unread.
+    async fn check_received(
+        &self,
+        case: &TestCase,
+        (response_client, body_client): (&ServerResponse, Option<stream::Body>),
+        (response_actual, body_actual): (&ServerResponse, Option<stream::Body>),
+    ) -> Result<(), TesterError> {
+        if body_client.is_none() && body_actual.is_none() {
+            return self.check_pair(case, response_client, response_actual);
+        }
+        self.check_status(case, response_client, response_actual)?;
+        let options = case.options.as_ref().unwrap_or(&self.options);
+        let raw = |response: &ServerResponse| {
+            !matches!(response.raw, None | Some(RawResponse::Streamed { .. }))
+        };
+        if raw(response_client) || raw(response_actual) {
+            compare_raw(response_client, response_actual, case, options)?;
+        } else {
+            // A side read in full is encoded again, being the smaller.
+            let body = |response: &ServerResponse, body: Option<stream::Body>| {
+                body.unwrap_or_else(|| {
+                    let bytes = serde_json::to_vec(&response.body).unwrap_or_default();
+                    stream::Body::new(bytes, None, None)
+                })
+            };
+            let reference = self
+                .actual
+                .as_deref()
+                .expect("streamed without a reference");
+            stream::compare(
+                body(response_client, body_client),
+                body(response_actual, body_actual),
+                case,
+                options,
+            )
+            .await
+            .map_err(|failure| match failure {
+                stream::Failure::Diverged(err) => err,
+                stream::Failure::Client(fault) => {
+                    self.client.fault(case, response_client.status, fault)
+                }
+                stream::Failure::Actual(fault) => {
+                    reference.fault(case, response_actual.status, fault)
+                }
+            })?;
+        }
+        compare_headers(
+            &response_client.headers,
+            &response_actual.headers,
+            case,
+            options,
+        )
+    }
+
+    fn check_status(
+        &self,
+        case: &TestCase,
+        response_client: &ServerResponse,
+        response_actual: &ServerResponse,
+    ) -> Result<(), TesterError> {
+        if response_client.status != response_actual.status {
+            return Err(TesterError::StatusMismatch {
+                endpoint: case.endpoint.clone(),
+                method: case.method.clone(),
+                client_status: response_client.status,
+                actual_status: response_actual.status,
+            });
+        }
+        // Even when both allow either, the servers should settle on the same one.
+        if let (Some(client_version), Some(actual_version)) =
+            (response_client.http_version, response_actual.http_version)
+        {
+            if client_version != actual_version {
+                return Err(TesterError::HttpVersionMismatch {
+                    endpoint: case.endpoint.clone(),
+                    method: case.method.clone(),
+                    client_version: format!("{:?}", client_version),
+                    actual_version: format!("{:?}", actual_version),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn check_pair(
+        &self,
+        case: &TestCase,
+        response_client: &ServerResponse,
+        response_actual: &ServerResponse,
+    ) -> Result<(), TesterError> {
+        self.check_status(case, response_client, response_actual)?;
+        let options = case.options.as_ref().unwrap_or(&self.options);
+        if response_client.raw.is_some() || response_actual.raw.is_some() {
+            compare_raw(response_client, response_actual, case, options)?;
+            return compare_headers(
+                &response_client.headers,
+                &response_actual.headers,
+                case,
+                options,
+            );
+        }
+
+        compare_hygiene(case, response_client, Some(response_actual))?;
+        if let Some(spec) = &self.spec {
+            self.check_spec(spec, case, "client", response_client)?;
+            self.check_spec(spec, case, "actual", response_actual)?;
+        }
+        self.check_schemas(case, response_client, Some(response_actual))?;
+
+        compare_json(response_client, response_actual, case, options)?;
+        compare_headers(
+            &response_client.headers,
+            &response_actual.headers,
+            case,
+            options,
+        )?;
+        Ok(())
+    }
+
+    async fn run_hooks(&self, hooks: &[TestCase]) -> Result<(), TesterError> {
+        let servers: Vec<&RequestClient> = std::iter::once(&*self.client)
+            .chain(self.actual.as_deref())
+            .collect();
+        send_hooks(&servers, hooks).await
+    }
+
+    // Results are returned in the same order as `cases`, regardless of the order
+    // in which the requests complete. Hooks reset shared server state, so cases
+    // run one at a time while any are set.
+    pub async fn compare_all(&self, cases: Vec<TestCase>) -> Vec<(TestCase, Comparison)> {
+        let permits = if self.hooks.is_empty() {
+            self.concurrency
+        } else {
+            1
+        };
+        let semaphore = Arc::new(Semaphore::new(permits));
+        let mut tasks = JoinSet::new();
+
+        for (index, case) in cases.into_iter().enumerate() {
+            let tester = self.clone();
+            let semaphore = semaphore.clone();
+            tasks.spawn(async move {
+                let _permit = semaphore.acquire_owned().await.expect("semaphore closed");
+                let comparison = tester.compare_case(&case).await;
+                (index, case, comparison)
+            });
+        }
+
+        let mut results = Vec::with_capacity(tasks.len());
+        while let Some(joined) = tasks.join_next().await {
+            results.push(joined.expect("comparison task panicked"));
+        }
+        results.sort_by_key(|(index, _, _)| *index);
+        results
+            .into_iter()
+            .map(|(_, case, comparison)| (case, comparison))
+            .collect()
+    }
+
+    // Compares cases made elsewhere, read from a CSV file, say, or drawn from
+    // a property-test generator, into the same report as a fuzzing run. They
+    // are taken `concurrency` at a time, so the source is only drawn from as
+    // cases are needed, and each batch is one iteration. A case's target is
+    // its registry path template when there is one, and its seed 0 unless it
+    // has one. 5xx responses and lost connections are reported as crashes.
+    pub async fn run<I: IntoIterator<Item = TestCase>>(&self, cases: I) -> RunReport {
+        let started = Instant::now();
+        let mut report = RunReport::new(0);
+        let mut routes = BTreeSet::new();
+        let mut cases = cases.into_iter();
+        for iteration in 0.. {
+            let batch: Vec<TestCase> = cases.by_ref().take(self.concurrency()).collect();
+            if batch.is_empty() {
+                break;
+            }
+            for (case, comparison) in self.compare_all(batch).await {
+                let path = without_query(&case.endpoint);
+                let target = endpoints::resolve(path).map_or(path, |endpoint| endpoint.path);
+                routes.insert((case.method.to_string(), target.to_string()));
+                let crash = Crash::detect(&comparison);
+                report.cases.push(CaseResult {
+                    iteration,
+                    seed: case.seed.unwrap_or_default(),
+                    method: case.method,
+                    target: target.to_string(),
+                    endpoint: case.endpoint,
+                    query: case.query,
+                    body: case.body,
+                    malformation: case.malformation,
+                    header_perturbation: case.header_perturbation,
+                    client: comparison.client,
+                    actual: comparison.actual,
+                    outcome: comparison.outcome,
+                    minimized: None,
+                    recorded: None,
+                    artifacts: None,
+                    crash,
+                    flaky: None,
+                });
+            }
+        }
+        report.planned = report.cases.len();
+        report.routes = routes.into_iter().collect();
+        report.duration = started.elapsed();
+        report
+    }
+
+    fn check_oracle(
+        &self,
+        oracle: &dyn Oracle,
+        case: &TestCase,
+        response: &ServerResponse,
+    ) -> Result<(), TesterError> {
+        // Neither the spec nor the oracle knows anything but JSON bodies.
+        if response.raw.is_none() {
+            compare_hygiene(case, response, None)?;
+            if let Some(spec) = &self.spec {
+                self.check_spec(spec, case, "client", response)?;
+            }
+            self.check_schemas(case, response, None)?;
+        }
+        let Some(expected) = oracle.expect(case) else {
+            return Ok(());
+        };
+        if response.status != expected.status {
+            return Err(TesterError::StatusMismatch {
+                endpoint: case.endpoint.clone(),
+                method: case.method.clone(),
+                client_status: response.status,
+                actual_status: expected.status,
+            });
+        }
+        if response.raw.is_some() {
+            return Ok(());
+        }
+        expected
+            .body
+            .validate(&response.body)
+            .map_err(|violation| TesterError::SpecViolation {
+                endpoint: case.endpoint.clone(),
+                method: case.method.clone(),
+                server: "client",
+                status: response.status,
+                path: violation.path,
+                expected: violation.expected,
+                actual_value: Box::new(violation.actual),
+            })
+    }
+
+    fn check_schemas(
+        &self,
+        case: &TestCase,
+        client: &ServerResponse,
+        actual: Option<&ServerResponse>,
+    ) -> Result<(), TesterError> {
+        let Some(schemas) = self.schemas.find(&case.method, &case.endpoint) else {
+            return Ok(());
+        };
+        let check = |response: &ServerResponse| match schemas.for_status(response.status) {
+            Some(schema) => schema.validate(&response.body),
+            None => Ok(()),
+        };
+
+        let client_result = check(client);
+        let actual_result = actual.map(check).unwrap_or(Ok(()));
+        let (server, status, violation) = match (client_result, actual_result) {
+            (Ok(()), Ok(())) => return Ok(()),
+            (Err(violation), Ok(())) => ("client", client.status, violation),
+            (Ok(()), Err(violation)) => ("actual", actual.map_or(0, |r| r.status), violation),
+            (Err(violation), Err(_)) => ("both", client.status, violation),
+        };
+        Err(TesterError::SchemaViolation {
+            endpoint: case.endpoint.clone(),
+            method: case.method.clone(),
+            server,
+            status,
+            path: violation.path,
+            expected: violation.expected,
+            actual_value: Box::new(violation.actual),
+        })
+    }
+
+    fn check_spec(
+        &self,
+        spec: &Spec,
+        case: &TestCase,
+        server: &'static str,
+        response: &ServerResponse,
+    ) -> Result<(), TesterError> {
+        spec.validate_response(
+            &case.method,
+            &case.endpoint,
+            response.status,
+            &response.body,
+        )
+        .map_err(|violation| TesterError::SpecViolation {
+            endpoint: case.endpoint.clone(),
+            method: case.method.clone(),
+            server,
+            status: response.status,
+            path: violation.path,
+            expected: violation.expected,
+            actual_value: Box::new(violation.actual),
+        })
+    }
+}
+
+fn log_outcome(outcome: &Result<(), TesterError>) {
+    if let Err(err) = outcome {
+        tracing::warn!(error = %err, "mismatch found");
+    }
+}
 
-    #[error("Tester error: {0}")]
-    TesterError(#[from] TesterError),
+// Only transport failures count; a server without the hook's route still
+// gets tested.
+async fn send_hooks(servers: &[&RequestClient], hooks: &[TestCase]) -> Result<(), TesterError> {
+    for hook in hooks {
+        for server in servers {
+            server.request(hook).await?;
+        }
+    }
+    Ok(())
 }
 
-#[derive(Debug, Error)]
-pub enum TesterError {
-    #[error("JSON type mismatch at endpoint `{endpoint}`.\nClient Value: {client_value:?}\nActual Value: {actual_value:?}")]
-    JsonTypeMismatch {
-        endpoint: String,
-        client_value: Value,
-        actual_value: Value,
-    },
+// Which HTTP versions requests may go over.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HttpVersion {
+    // HTTP/2 where TLS negotiates it, HTTP/1.1 otherwise.
+    #[default]
+    Auto,
+    Http1,
+    // HTTP/2 from the first byte, without upgrading, so also over plain HTTP.
+    // Servers that only speak HTTP/1.1 drop the connection.
+    Http2,
 }
 
-pub struct Tester {
-    client: Arc<RequestClient>,
-    actual: Arc<RequestClient>,
+#[derive(Clone)]
+pub struct RequestClient {
+    base_url: String,
+    client: Client,
+    default_headers: HeaderMap,
+    rate_limiter: Option<Arc<RateLimiter>>,
+    timeout: Option<Duration>,
+    retry: RetryPolicy,
+    auth: Option<(Arc<dyn AuthStrategy>, String)>,
+    version: Option<ApiVersion>,
+    max_body_size: Option<usize>,
+    cookies: bool,
+    http_version: HttpVersion,
+    network: Network,
+    capture: bool,
+    registry: bool,
+    strict_json: bool,
+    stream_above: Option<usize>,
+    observers: Vec<Arc<dyn Observer>>,
+    // Sent through `client` when unset.
+    transport: Option<Arc<dyn Transport>>,
 }
 
-impl Tester {
-    pub fn new(test_url: String, server_url: String) -> Self {
+impl RequestClient {
+    pub fn new(base_url: String) -> Self {
         Self {
-            client: Arc::new(RequestClient::new(test_url)),
-            actual: Arc::new(RequestClient::new(server_url)),
+            base_url,
+            client: http_client(false, None, HttpVersion::Auto, &Network::new()),
+            default_headers: HeaderMap::new(),
+            rate_limiter: None,
+            timeout: None,
+            retry: RetryPolicy::none(),
+            auth: None,
+            version: None,
+            max_body_size: None,
+            cookies: false,
+            http_version: HttpVersion::Auto,
+            network: Network::new(),
+            capture: false,
+            registry: false,
+            strict_json: false,
+            stream_above: None,
+            observers: Vec::new(),
+            transport: None,
         }
     }
 
-    pub async fn compare(
-        &self,
-        endpoint: &str,
-        method: Method,
-        body: Option<Value>,
-    ) -> Result<(), RequestError> {
-        let response_client = self.client.request(method.clone(), endpoint, body.clone()).await?;
-        let response_actual = self.actual.request(method, endpoint, body).await?;
+    // A client re-based onto another server starts with an empty cookie jar.
+    pub fn with_base_url(mut self, base_url: String) -> Self {
+        self.base_url = base_url;
+        if self.cookies {
+            self.client = self.http_client();
+        }
+        self
+    }
+
+    // Covers the whole request, including reading the response body.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self.client = self.http_client();
+        self
+    }
+
+    // Keeps the cookies each response sets and sends them back on later
+    // requests to the same server, for servers with cookie-based sessions.
+    pub fn with_cookies(mut self) -> Self {
+        self.cookies = true;
+        self.client = self.http_client();
+        self
+    }
+
+    pub fn with_http_version(mut self, http_version: HttpVersion) -> Self {
+        self.http_version = http_version;
+        self.client = self.http_client();
+        self
+    }
+
+    pub fn with_network(mut self, network: Network) -> Self {
+        self.network = network;
+        self.client = self.http_client();
+        self
+    }
+
+    pub fn with_retry(mut self, policy: RetryPolicy) -> Self {
+        self.retry = policy;
+        self
+    }
+
+    fn http_client(&self) -> Client {
+        http_client(self.cookies, self.timeout, self.http_version, &self.network)
+    }
+
+    // Requests are still built and limited here, but delivered by `transport`.
+    // Cookies set here only apply to HTTP; other transports keep their own.
+    // The timeout applies to each attempt either way.
+    pub fn with_transport(mut self, transport: Arc<dyn Transport>) -> Self {
+        self.transport = Some(transport);
+        self
+    }
+
+    // Observers see requests in the order they were added.
+    pub fn with_observer(mut self, observer: Arc<dyn Observer>) -> Self {
+        self.observers.push(observer);
+        self
+    }
+
+    // Every request carries `token` wherever the strategy puts it.
+    pub fn with_auth(mut self, strategy: Arc<dyn AuthStrategy>, token: impl Into<String>) -> Self {
+        self.auth = Some((strategy, token.into()));
+        self
+    }
+
+    // Reading stops as soon as a response passes `bytes`, so a server streaming
+    // an endless body cannot exhaust memory.
+    pub fn with_max_body_size(mut self, bytes: usize) -> Self {
+        self.max_body_size = Some(bytes);
+        self
+    }
+
+    pub fn with_capture(mut self) -> Self {
+        self.capture = true;
+        self
+    }
+
+    // JSON bodies are read strictly, noting duplicate keys, `NaN` and
+    // `Infinity` literals and trailing data instead of hiding or refusing
+    // them.
+    pub fn with_strict_json(mut self) -> Self {
+        self.strict_json = true;
+        self
+    }
+
+    // See `Tester::with_streaming`.
+    pub fn with_streaming(mut self, bytes: usize) -> Self {
+        self.stream_above = Some(bytes);
+        self
+    }
+
+    // Routes in the endpoint registry only get the token if they need one,
+    // and requests the registry says no route takes fail without being sent.
+    pub fn with_registry(mut self) -> Self {
+        self.registry = true;
+        self
+    }
+
+    // Paths are sent under the version's prefix, e.g. `/v2/admin/quiz/list`.
+    pub fn with_version(mut self, version: ApiVersion) -> Self {
+        self.version = Some(version);
+        self
+    }
+
+    pub fn with_rate_limit(mut self, requests_per_second: f64, burst: u32) -> Self {
+        self.rate_limiter = Some(Arc::new(RateLimiter::with_burst(
+            requests_per_second,
+            burst,
+        )));
+        self
+    }
+
+    // Headers passed here are merged into the existing defaults, replacing any
+    // with the same name.
+    pub fn with_default_headers(mut self, headers: HeaderMap) -> Self {
+        for (name, value) in headers.iter() {
+            self.default_headers.insert(name.clone(), value.clone());
+        }
+        self
+    }
 
-        let body_client: Value = response_client.json().await?;
-        let body_actual: Value = response_actual.json().await?;
+    pub fn default_headers(&self) -> &HeaderMap {
+        &self.default_headers
+    }
 
-        self.compare_json_types(&body_client, &body_actual, endpoint).map_err(RequestError::from)
+    pub async fn request(&self, case: &TestCase) -> Result<Response, TesterError> {
+        match self.exchange(case).await {
+            Ok((response, _)) => Ok(response),
+            Err(err) => Err(self.error(case, err)),
+        }
     }
 
-    fn compare_json_types(
+    // Sends the case as `fetch` does and reads the body as a `T`, whatever
+    // the status.
+    pub async fn request_json<T: DeserializeOwned>(
         &self,
-        a: &Value,
-        b: &Value,
-        endpoint: &str,
-    ) -> Result<(), TesterError> {
-        match (a, b) {
-            (Value::Object(map_a), Value::Object(map_b)) => {
-                self.compare_json_objects(map_a, map_b, endpoint)
-            }
-            (Value::Array(arr_a), Value::Array(arr_b)) => {
-                self.compare_json_arrays(arr_a, arr_b, endpoint)
-            }
-            (Value::String(_), Value::String(_)) => Ok(()),
-            (Value::Number(_), Value::Number(_)) => Ok(()),
-            (Value::Bool(_), Value::Bool(_)) => Ok(()),
-            (Value::Null, Value::Null) => Ok(()),
-            _ => Err(TesterError::JsonTypeMismatch {
-                endpoint: endpoint.to_string(),
-                client_value: a.clone(),
-                actual_value: b.clone(),
-            }),
+        case: &TestCase,
+    ) -> Result<T, TesterError> {
+        self.fetch(case).await?.json(case, &self.base_url)
+    }
+
+    // Routes outside the registry always get the token.
+    fn needs_auth(&self, case: &TestCase) -> bool {
+        !self.registry
+            || endpoints::resolve(&case.endpoint).is_none_or(|endpoint| endpoint.requires_auth)
+    }
+
+    // The response, and with capture on, the request that got it.
+    async fn exchange(
+        &self,
+        case: &TestCase,
+    ) -> Result<(Response, Option<SentRequest>), SendError> {
+        let authorized;
+        let case = match &self.auth {
+            Some((strategy, token)) if self.needs_auth(case) => {
+                let mut with_token = case.clone();
+                strategy.apply(token, &mut with_token);
+                authorized = with_token;
+                &authorized
+            }
+            _ => case,
+        };
+        let url = self.url(&case.endpoint);
+        let mut attempt = 0;
+        let mut rate_limited = 0;
+        loop {
+            tracing::debug!(server = %self.base_url, url = %url, attempt, "request sent");
+            match self.send(&url, case).await {
+                Err(err)
+                    if attempt < self.retry.max_retries && is_transient(&err, &case.method) =>
+                {
+                    tokio::time::sleep(self.retry.backoff(attempt)).await;
+                    attempt += 1;
+                }
+                Ok((response, _))
+                    if response.status() == StatusCode::TOO_MANY_REQUESTS
+                        && rate_limited < self.retry.max_rate_limited =>
+                {
+                    // Capped so a server asking for hours doesn't stall the run.
+                    let delay = retry_after(response.headers())
+                        .map(|delay| delay.min(self.retry.max_backoff))
+                        .unwrap_or_else(|| self.retry.backoff(rate_limited));
+                    tracing::warn!(
+                        server = %self.base_url,
+                        delay_ms = delay.as_secs_f64() * 1000.0,
+                        "rate limited"
+                    );
+                    // With a limiter, every request to this server waits, not
+                    // just the one that was turned away.
+                    match &self.rate_limiter {
+                        Some(limiter) => limiter.pause(delay).await,
+                        None => tokio::time::sleep(delay).await,
+                    }
+                    rate_limited += 1;
+                }
+                result => return result,
+            }
         }
     }
 
-    fn compare_json_objects(
+    // Where the session token goes, or would go: `token` unless the auth
+    // strategy uses another header.
+    fn token_header(&self) -> HeaderName {
+        self.auth
+            .as_ref()
+            .and_then(|(strategy, _)| strategy.header())
+            .or_else(|| self.version?.auth_strategy().header())
+            .unwrap_or_else(|| HeaderName::from_static("token"))
+    }
+
+    fn url(&self, endpoint: &str) -> String {
+        let endpoint = match self.version {
+            Some(version) => versioned_path(version, endpoint),
+            None => endpoint.to_string(),
+        };
+        format!(
+            "{}/{}",
+            self.base_url.trim_end_matches('/'),
+            escape_path(endpoint.trim_start_matches('/'))
+        )
+    }
+
+    // Until `case` gets a 2xx answer, or any answer with `any_status`. `Err`
+    // holds what went wrong with the last attempt, which is given a moment to
+    // answer even at the deadline.
+    async fn wait_ready(
         &self,
-        map_a: &serde_json::Map<String, Value>,
-        map_b: &serde_json::Map<String, Value>,
-        endpoint: &str,
-    ) -> Result<(), TesterError> {
-        for (key, value_a) in map_a {
-            if let Some(value_b) = map_b.get(key) {
-                self.compare_json_types(value_a, value_b, endpoint)?;
-            } else {
-                return Err(TesterError::JsonTypeMismatch {
-                    endpoint: endpoint.to_string(),
-                    client_value: value_a.clone(),
-                    actual_value: Value::Null,
-                });
+        case: &TestCase,
+        any_status: bool,
+        deadline: Instant,
+    ) -> Result<(), String> {
+        let mut backoff = Duration::from_millis(100);
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let attempt = tokio::time::timeout(
+                remaining.max(Duration::from_millis(250)),
+                self.request(case),
+            );
+            let last = match attempt.await {
+                Ok(Ok(response)) if any_status || response.status().is_success() => return Ok(()),
+                Ok(Ok(response)) => format!("status {}", response.status().as_u16()),
+                Ok(Err(err)) => error_chain(&err),
+                Err(_) => "no response in time".to_string(),
+            };
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Err(last);
             }
+            tokio::time::sleep(backoff.min(remaining)).await;
+            backoff = (backoff * 2).min(Duration::from_millis(500));
+        }
+    }
+
+    async fn send(
+        &self,
+        url: &str,
+        case: &TestCase,
+    ) -> Result<(Response, Option<SentRequest>), SendError> {
+        // Overrides are applied before the body so a custom Content-Type wins
+        // over the one the body encoding would otherwise set.
+        let mut headers = self.default_headers.clone();
+        headers.extend(case.headers.clone());
+        if let Some(perturbation) = case.header_perturbation {
+            perturbation.apply(&mut headers, &self.token_header());
         }
+        let configured = headers.contains_key(CONTENT_TYPE);
+        let mut request_builder = self
+            .client
+            .request(case.method.clone(), url)
+            .headers(headers)
+            .query(&case.query);
 
-        for key in map_b.keys() {
-            if !map_a.contains_key(key) {
-                return Err(TesterError::JsonTypeMismatch {
-                    endpoint: endpoint.to_string(),
-                    client_value: Value::Null,
-                    actual_value: map_b.get(key).unwrap().clone(),
-                });
+        // Malformed bodies bypass encoding entirely; their Content-Type is only
+        // used when none was configured.
+        if let Some(malformation) = case.malformation {
+            let raw = malformation.apply(case.body.as_ref());
+            if let Some(content_type) = raw.content_type.filter(|_| !configured) {
+                request_builder = request_builder.header(CONTENT_TYPE, content_type);
             }
+            request_builder = request_builder.body(raw.bytes);
+        } else if let Some(data) = &case.body {
+            request_builder = case.body_kind.apply(request_builder, data);
         }
 
-        Ok(())
+        if let Some(limiter) = &self.rate_limiter {
+            limiter.acquire().await;
+        }
+
+        let mut request = request_builder.build()?;
+        let sent = if self.capture {
+            let body = transport::take_body(&mut request).await?;
+            *request.body_mut() = Some(body.clone().into());
+            Some(SentRequest {
+                method: request.method().clone(),
+                url: request.url().to_string(),
+                headers: request.headers().clone(),
+                body,
+            })
+        } else {
+            None
+        };
+        // reqwest times out its own requests, but a hung in-process handler
+        // would wait forever.
+        let response = match (&self.transport, self.timeout) {
+            (Some(transport), Some(timeout)) => {
+                tokio::time::timeout(timeout, transport.execute(request))
+                    .await
+                    .map_err(|_| SendError::TimedOut(timeout))??
+            }
+            (Some(transport), None) => transport.execute(request).await?,
+            (None, _) => self.client.execute(request).await?,
+        };
+        Ok((response, sent))
     }
 
-    fn compare_json_arrays(
+    pub async fn fetch(&self, case: &TestCase) -> Result<ServerResponse, TesterError> {
+        Ok(self.receive(case, false).await?.0)
+    }
+
+    // Like `fetch`, but with `streaming`, a JSON body over `with_streaming`'s
+    // size is only read that far and handed back to be read by `stream`.
+    async fn receive(
         &self,
-        arr_a: &[Value],
-        arr_b: &[Value],
-        endpoint: &str,
-    ) -> Result<(), TesterError> {
-        if arr_a.len() != arr_b.len() {
-            return Err(TesterError::JsonTypeMismatch {
-                endpoint: endpoint.to_string(),
-                client_value: Value::Array(arr_a.to_vec()),
-                actual_value: Value::Array(arr_b.to_vec()),
+        case: &TestCase,
+        streaming: bool,
+    ) -> Result<(ServerResponse, Option<stream::Body>), TesterError> {
+        if self.registry {
+            check_usage(case)?;
+        }
+        for observer in &self.observers {
+            observer.on_request(&self.base_url, case);
+        }
+        let response = self.fetch_once(case, streaming).await;
+        for observer in &self.observers {
+            observer.on_response(
+                &self.base_url,
+                case,
+                response.as_ref().map(|(response, _)| response),
+            );
+        }
+        response
+    }
+
+    async fn fetch_once(
+        &self,
+        case: &TestCase,
+        streaming: bool,
+    ) -> Result<(ServerResponse, Option<stream::Body>), TesterError> {
+        let sent_at = SystemTime::now();
+        let start = Instant::now();
+        let (response, sent) = self
+            .exchange(case)
+            .await
+            .map_err(|err| self.error(case, err))?;
+        let status = response.status().as_u16();
+        let headers = response.headers().clone();
+        let http_version = response.version();
+        // Whether a body is JSON, so may be streamed, is told by the headers.
+        let stream_above = self
+            .stream_above
+            .filter(|_| streaming && RawResponse::read(status, &headers, &[], "").is_none());
+        let (raw_body, rest) = match stream_above {
+            Some(bytes) => self.read_head(case, status, response, bytes).await?,
+            None => (self.read_body(case, status, response).await?, None),
+        };
+        let latency = start.elapsed();
+        tracing::debug!(
+            server = %self.base_url,
+            status,
+            bytes = raw_body.len(),
+            latency_ms = latency.as_secs_f64() * 1000.0,
+            "response received"
+        );
+
+        if let Some(rest) = rest {
+            let exchange = sent.map(|request| {
+                Arc::new(Exchange {
+                    request,
+                    response_body: raw_body.clone(),
+                })
             });
+            let response = ServerResponse {
+                status,
+                headers,
+                body: Value::Null,
+                raw: Some(RawResponse::Streamed {
+                    head: raw_body.clone(),
+                }),
+                latency,
+                sent_at: Some(sent_at),
+                http_version: Some(http_version),
+                exchange,
+                json_issues: Vec::new(),
+            };
+            let body = stream::Body::new(raw_body, Some(rest), self.max_body_size);
+            return Ok((response, Some(body)));
         }
 
-        for (elem_a, elem_b) in arr_a.iter().zip(arr_b.iter()) {
-            self.compare_json_types(elem_a, elem_b, endpoint)?;
+        let raw = RawResponse::read(status, &headers, &raw_body, &self.base_url);
+        let non_json = || TesterError::NonJsonBody {
+            endpoint: case.endpoint.clone(),
+            method: case.method.clone(),
+            url: self.url(&case.endpoint),
+            status,
+            raw_body: String::from_utf8_lossy(&raw_body).into_owned(),
+        };
+        let mut json_issues = Vec::new();
+        let body = match raw {
+            Some(_) => Value::Null,
+            None if self.strict_json => {
+                let scanned = hygiene::scan(&raw_body).ok_or_else(non_json)?;
+                json_issues = scanned.issues;
+                scanned.value
+            }
+            None => serde_json::from_slice(&raw_body).map_err(|_| non_json())?,
+        };
+        let exchange = sent.map(|request| {
+            Arc::new(Exchange {
+                request,
+                response_body: raw_body,
+            })
+        });
+
+        Ok((
+            ServerResponse {
+                status,
+                headers,
+                body,
+                raw,
+                latency,
+                sent_at: Some(sent_at),
+                http_version: Some(http_version),
+                exchange,
+                json_issues,
+            },
+            None,
+        ))
+    }
+
+    async fn read_body(
+        &self,
+        case: &TestCase,
+        status: u16,
+        mut response: Response,
+    ) -> Result<Vec<u8>, TesterError> {
+        let too_large = |limit| self.too_large(case, status, limit);
+        let Some(limit) = self.max_body_size else {
+            let bytes = response
+                .bytes()
+                .await
+                .map_err(|err| self.error(case, err))?;
+            return Ok(bytes.to_vec());
+        };
+
+        if response
+            .content_length()
+            .is_some_and(|len| len > limit as u64)
+        {
+            return Err(too_large(limit));
+        }
+        let mut body = Vec::new();
+        while let Some(chunk) = response
+            .chunk()
+            .await
+            .map_err(|err| self.error(case, err))?
+        {
+            if body.len() + chunk.len() > limit {
+                return Err(too_large(limit));
+            }
+            body.extend_from_slice(&chunk);
         }
+        Ok(body)
+    }
 
-        Ok(())
+    // Reads up to `bytes` of the body and a chunk over, and the response
+    // itself should there be more to come.
+    async fn read_head(
+        &self,
+        case: &TestCase,
+        status: u16,
+        mut response: Response,
+        bytes: usize,
+    ) -> Result<(Vec<u8>, Option<Response>), TesterError> {
+        let limit = self.max_body_size;
+        if limit.is_some_and(|limit| {
+            response
+                .content_length()
+                .is_some_and(|len| len > limit as u64)
+        }) {
+            return Err(self.too_large(case, status, limit.unwrap_or_default()));
+        }
+        let mut head = Vec::new();
+        while head.len() <= bytes {
+            let Some(chunk) = response
+                .chunk()
+                .await
+                .map_err(|err| self.error(case, err))?
+            else {
+                return Ok((head, None));
+            };
+            match limit {
+                Some(limit) if head.len() + chunk.len() > limit => {
+                    return Err(self.too_large(case, status, limit))
+                }
+                _ => head.extend_from_slice(&chunk),
+            }
+        }
+        Ok((head, Some(response)))
+    }
+
+    fn too_large(&self, case: &TestCase, status: u16, limit: usize) -> TesterError {
+        TesterError::BodyTooLarge {
+            endpoint: case.endpoint.clone(),
+            method: case.method.clone(),
+            url: self.url(&case.endpoint),
+            status,
+            limit,
+        }
+    }
+
+    // For what stopped a body `stream` was reading.
+    fn fault(&self, case: &TestCase, status: u16, fault: stream::Fault) -> TesterError {
+        match fault {
+            stream::Fault::Read(err) => self.error(case, err),
+            stream::Fault::TooLarge(limit) => self.too_large(case, status, limit),
+            stream::Fault::Malformed(near) => TesterError::NonJsonBody {
+                endpoint: case.endpoint.clone(),
+                method: case.method.clone(),
+                url: self.url(&case.endpoint),
+                status,
+                raw_body: String::from_utf8_lossy(&near).into_owned(),
+            },
+        }
+    }
+
+    fn error(&self, case: &TestCase, err: impl Into<SendError>) -> TesterError {
+        let timeout = match err.into() {
+            SendError::TimedOut(timeout) => timeout,
+            SendError::Http(err) => match self.timeout {
+                Some(timeout) if err.is_timeout() => timeout,
+                _ => return TesterError::from(err).with_case(case),
+            },
+        };
+        TesterError::Timeout {
+            endpoint: case.endpoint.clone(),
+            method: case.method.clone(),
+            url: self.url(&case.endpoint),
+            timeout,
+        }
     }
 }
 
-#[derive(Clone)]
-pub struct RequestClient {
-    base_url: String,
-    client: Client,
+// The first issue in one response's JSON that the other's doesn't share.
+fn compare_hygiene(
+    case: &TestCase,
+    client: &ServerResponse,
+    actual: Option<&ServerResponse>,
+) -> Result<(), TesterError> {
+    let none = Vec::new();
+    let actual_issues = actual.map_or(&none, |actual| &actual.json_issues);
+    for (server, issues, others) in [
+        ("server under test", &client.json_issues, actual_issues),
+        ("reference server", actual_issues, &client.json_issues),
+    ] {
+        if let Some(issue) = issues
+            .iter()
+            .find(|issue| !others.iter().any(|other| issue.same(other)))
+        {
+            return Err(TesterError::JsonHygiene {
+                endpoint: case.endpoint.clone(),
+                method: case.method.clone(),
+                server,
+                issue: issue.clone(),
+            });
+        }
+    }
+    Ok(())
 }
 
-impl RequestClient {
-    pub fn new(base_url: String) -> Self {
-        Self {
-            base_url,
-            client: Client::new(),
+// `err` as a divergence when it is a timeout on the `hung` server only, the
+// other having sent `answered`.
+fn timeout_divergence(
+    case: &TestCase,
+    hung: &'static str,
+    err: TesterError,
+    elapsed: Duration,
+    answered: &ServerResponse,
+) -> TesterError {
+    match err {
+        TesterError::Timeout { url, .. } => TesterError::TimeoutDivergence {
+            endpoint: case.endpoint.clone(),
+            method: case.method.clone(),
+            hung,
+            url,
+            elapsed,
+            status: answered.status,
+            payload: case.body.clone().map(Box::new),
+        },
+        err => err,
+    }
+}
+
+// Redirects aren't followed, so their targets can be compared.
+fn http_client(
+    cookies: bool,
+    timeout: Option<Duration>,
+    http_version: HttpVersion,
+    network: &Network,
+) -> Client {
+    let mut builder = network.apply(
+        Client::builder()
+            .cookie_store(cookies)
+            .redirect(Policy::none()),
+    );
+    if let Some(timeout) = timeout {
+        builder = builder.timeout(timeout);
+    }
+    builder = match http_version {
+        HttpVersion::Auto => builder,
+        HttpVersion::Http1 => builder.http1_only(),
+        HttpVersion::Http2 => builder.http2_prior_knowledge(),
+    };
+    builder.build().expect("failed to build HTTP client")
+}
+
+// Why an attempt got no response.
+#[derive(Debug)]
+enum SendError {
+    Http(reqwest::Error),
+    // A transport other than HTTP didn't answer within the timeout.
+    TimedOut(Duration),
+}
+
+impl From<reqwest::Error> for SendError {
+    fn from(err: reqwest::Error) -> Self {
+        SendError::Http(err)
+    }
+}
+
+// A request that timed out may still have been handled, so only methods that
+// are safe to repeat are retried after a timeout. PUT and DELETE are idempotent
+// on paper, but a second DELETE of a quiz finds nothing and answers differently.
+fn is_transient(err: &SendError, method: &Method) -> bool {
+    match err {
+        SendError::Http(err) => err.is_connect() || (err.is_timeout() && method.is_safe()),
+        // The handler may still be working on it, as with an HTTP timeout.
+        SendError::TimedOut(_) => method.is_safe(),
+    }
+}
+
+// `Retry-After` is either a number of seconds or an HTTP date.
+fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = httpdate::parse_http_date(value).ok()?;
+    Some(date.duration_since(SystemTime::now()).unwrap_or_default())
+}
+
+// reqwest's own message rarely says why, e.g. that the connection was refused;
+// its sources do, though they often repeat each other.
+fn error_chain(err: &(dyn std::error::Error + 'static)) -> String {
+    let mut message = err.to_string();
+    let mut source = err.source();
+    while let Some(err) = source {
+        let text = err.to_string();
+        if !message.contains(&text) {
+            message.push_str(": ");
+            message.push_str(&text);
         }
+        source = err.source();
     }
+    message
+}
 
-    pub async fn request(
-        &self,
-        method: Method,
-        endpoint: &str,
-        body: Option<Value>,
-    ) -> Result<Response, reqwest::Error> {
-        let url = format!("{}/{}", self.base_url, endpoint);
-        let mut request_builder = self.client.request(method, &url);
+// Requests the endpoint registry knows no route would take.
+fn check_usage(case: &TestCase) -> Result<(), TesterError> {
+    let invalid = |message: String| TesterError::InvalidUsage {
+        endpoint: case.endpoint.clone(),
+        method: case.method.clone(),
+        message,
+    };
+    let path = without_query(&case.endpoint);
+    if let Some(param) = path
+        .split('/')
+        .find(|segment| segment.starts_with('{') && segment.ends_with('}'))
+    {
+        return Err(invalid(format!(
+            "path parameter `{}` was never filled",
+            param
+        )));
+    }
+    match endpoints::resolve(path) {
+        Some(endpoint) if endpoint.method(&case.method).is_none() => {
+            let methods: Vec<&str> = endpoint
+                .methods
+                .iter()
+                .map(|route| route.method.as_str())
+                .collect();
+            Err(invalid(format!(
+                "`{}` only takes {}",
+                endpoint.path,
+                methods.join(", ")
+            )))
+        }
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use reqwest::header::{HeaderValue, AUTHORIZATION, USER_AGENT};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tokio::net::TcpListener;
+
+    // A server that accepts connections but never answers, counting how many
+    // requests were attempted.
+    async fn silent_server() -> (String, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let attempts = Arc::new(AtomicUsize::new(0));
+        let counter = attempts.clone();
+        tokio::spawn(async move {
+            let mut open = Vec::new();
+            while let Ok((socket, _)) = listener.accept().await {
+                counter.fetch_add(1, Ordering::SeqCst);
+                open.push(socket);
+            }
+        });
+        (url, attempts)
+    }
+
+    // A server that answers every request with `response` as written.
+    pub(crate) async fn raw_server(response: &'static str) -> String {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut request = [0; 4096];
+                let _ = socket.read(&mut request).await;
+                let _ = socket.write_all(response.as_bytes()).await;
+            }
+        });
+        url
+    }
+
+    // Like `raw_server`, but leaving each connection open once `response` is
+    // written.
+    async fn stalling_server(response: &'static str) -> String {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let mut open = Vec::new();
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut request = [0; 4096];
+                let _ = socket.read(&mut request).await;
+                let _ = socket.write_all(response.as_bytes()).await;
+                open.push(socket);
+            }
+        });
+        url
+    }
+
+    // Like `raw_server`, answering with each of `responses` in turn and then
+    // the last one from there on.
+    async fn scripted_server(responses: &'static [&'static str]) -> String {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let mut served = 0;
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut request = [0; 4096];
+                let _ = socket.read(&mut request).await;
+                let response = responses[served.min(responses.len() - 1)];
+                let _ = socket.write_all(response.as_bytes()).await;
+                served += 1;
+            }
+        });
+        url
+    }
+
+    fn headers(pairs: &[(reqwest::header::HeaderName, &'static str)]) -> HeaderMap {
+        pairs
+            .iter()
+            .map(|(name, value)| (name.clone(), HeaderValue::from_static(value)))
+            .collect()
+    }
+
+    #[test]
+    fn default_headers_merge_and_replace_by_name() {
+        let client = RequestClient::new("http://localhost".to_string())
+            .with_default_headers(headers(&[(AUTHORIZATION, "a"), (USER_AGENT, "ua")]))
+            .with_default_headers(headers(&[(AUTHORIZATION, "b")]));
+        assert_eq!(client.default_headers().len(), 2);
+        assert_eq!(client.default_headers()[AUTHORIZATION], "b");
+        assert_eq!(client.default_headers()[USER_AGENT], "ua");
+    }
+
+    #[test]
+    fn versioned_clients_prefix_every_path() {
+        let client = RequestClient::new("http://localhost/".to_string());
+        assert_eq!(
+            client.url("/admin/quiz/list"),
+            "http://localhost/admin/quiz/list"
+        );
+        let client = client.with_version(ApiVersion::V2);
+        assert_eq!(
+            client.url("/admin/quiz/list"),
+            "http://localhost/v2/admin/quiz/list"
+        );
+        assert_eq!(
+            client.url("/admin/auth/login"),
+            "http://localhost/v1/admin/auth/login"
+        );
+    }
+
+    fn retrying(url: String) -> RequestClient {
+        RequestClient::new(url)
+            .with_timeout(Duration::from_millis(50))
+            .with_retry(RetryPolicy::new(2).with_initial_backoff(Duration::from_millis(1)))
+    }
+
+    #[tokio::test]
+    async fn only_safe_methods_are_retried_after_a_timeout() {
+        let (url, attempts) = silent_server().await;
+        let client = retrying(url);
+
+        let err = client
+            .fetch(&TestCase::new("/quiz", Method::GET, None))
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            TesterError::Timeout { ref url, ref method, .. } if url.ends_with("/quiz") && method == Method::GET
+        ));
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+
+        for method in [Method::POST, Method::PUT, Method::DELETE] {
+            attempts.store(0, Ordering::SeqCst);
+            client
+                .fetch(&TestCase::new("/quiz", method, None))
+                .await
+                .unwrap_err();
+            assert_eq!(attempts.load(Ordering::SeqCst), 1);
+        }
+    }
+
+    #[tokio::test]
+    async fn refused_connections_are_retried_for_every_method() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        drop(listener);
+
+        let started = std::time::Instant::now();
+        let client = RequestClient::new(url)
+            .with_retry(RetryPolicy::new(2).with_initial_backoff(Duration::from_millis(30)));
+        let err = client
+            .fetch(&TestCase::new("/quiz", Method::POST, None))
+            .await
+            .unwrap_err();
+        match err {
+            TesterError::Network {
+                endpoint,
+                method,
+                source,
+            } => {
+                assert_eq!((endpoint.as_str(), method), ("/quiz", Method::POST));
+                assert!(source.is_connect());
+            }
+            other => panic!("expected a network error, got {:?}", other),
+        }
+        // Two retries, 30ms and then 60ms apart.
+        assert!(started.elapsed() >= Duration::from_millis(90));
+    }
+
+    #[test]
+    fn converted_errors_take_the_case_they_came_from() {
+        let source = serde_json::from_str::<Value>("{").unwrap_err();
+        let case = TestCase::new("/quiz/1", Method::DELETE, None);
+        let err = TesterError::from(source).with_case(&case);
+        assert!(matches!(
+            &err,
+            TesterError::Json { endpoint, method, .. } if endpoint == "/quiz/1" && method == Method::DELETE
+        ));
+        assert!(err
+            .to_string()
+            .starts_with("Invalid JSON for `DELETE /quiz/1`"));
+
+        // Errors built with their request keep it.
+        let status = TesterError::StatusMismatch {
+            endpoint: "/quiz/2".to_string(),
+            method: Method::GET,
+            client_status: 200,
+            actual_status: 400,
+        };
+        assert!(matches!(
+            status.with_case(&case),
+            TesterError::StatusMismatch { endpoint, .. } if endpoint == "/quiz/2"
+        ));
+    }
+
+    #[test]
+    fn schema_violations_name_the_server_that_broke_the_contract() {
+        use crate::runner::tests::response;
+        use crate::schema::{ResponseSchemas, Schema, SchemaRegistry};
+        use serde_json::json;
+
+        let tester = Tester::new("http://a".into(), "http://b".into()).with_response_schemas(
+            SchemaRegistry::new().with(
+                Method::GET,
+                "/quiz/{id}",
+                ResponseSchemas {
+                    success: Some(Schema::Array(Box::new(Schema::Integer))),
+                    error: None,
+                },
+            ),
+        );
+        let case = TestCase::new("/quiz/1", Method::GET, None);
+        let good = response(200, json!([1]), 0);
+        let bad = response(200, json!(["1"]), 0);
+        let server = |client: &ServerResponse, actual: Option<&ServerResponse>| match tester
+            .check_schemas(&case, client, actual)
+        {
+            Err(TesterError::SchemaViolation { server, path, .. }) => {
+                assert_eq!(path, "/0");
+                Some(server)
+            }
+            Ok(()) => None,
+            Err(other) => panic!("expected a schema violation, got {:?}", other),
+        };
+
+        assert_eq!(server(&good, Some(&good)), None);
+        assert_eq!(server(&bad, Some(&good)), Some("client"));
+        assert_eq!(server(&good, Some(&bad)), Some("actual"));
+        assert_eq!(server(&bad, Some(&bad)), Some("both"));
+        assert_eq!(server(&bad, None), Some("client"));
+        // Error responses have no contract here.
+        assert_eq!(server(&response(400, json!("no"), 0), None), None);
+    }
+
+    #[tokio::test]
+    async fn bodies_over_the_limit_are_rejected_with_or_without_a_length() {
+        let case = TestCase::new("/quiz", Method::GET, None);
+        let sized = raw_server("HTTP/1.1 200 OK\r\ncontent-length: 10\r\n\r\n[1,2,3,45]").await;
+        let chunked = raw_server(
+            "HTTP/1.1 200 OK\r\ntransfer-encoding: chunked\r\nconnection: close\r\n\r\n5\r\n[1,2,\r\n5\r\n3,45]\r\n0\r\n\r\n",
+        )
+        .await;
+
+        for url in [sized, chunked] {
+            let client = RequestClient::new(url.clone()).with_max_body_size(8);
+            match client.fetch(&case).await {
+                Err(TesterError::BodyTooLarge { status, limit, .. }) => {
+                    assert_eq!((status, limit), (200, 8));
+                }
+                outcome => panic!("expected {} to be too large, got {:?}", url, outcome),
+            }
+            let client = RequestClient::new(url).with_max_body_size(10);
+            let response = client.fetch(&case).await.unwrap();
+            assert_eq!(response.body, serde_json::json!([1, 2, 3, 45]));
+        }
+    }
+
+    #[test]
+    fn payload_hashes_follow_the_body_and_its_malformation() {
+        let case = |body| TestCase::new("/quiz", Method::POST, Some(body));
+        let quiz = case(serde_json::json!({"name": "q"}));
+        assert_eq!(
+            quiz.payload_hash(),
+            quiz.clone().with_seed(9).payload_hash()
+        );
+        assert_ne!(
+            quiz.payload_hash(),
+            case(serde_json::json!({"name": "r"})).payload_hash()
+        );
+        assert_ne!(
+            quiz.payload_hash(),
+            quiz.clone()
+                .with_malformation(Malformation::Empty)
+                .payload_hash()
+        );
+    }
+
+    #[test]
+    fn retry_after_takes_seconds_or_a_date() {
+        let header = |value: &'static str| headers(&[(RETRY_AFTER, value)]);
+        assert_eq!(retry_after(&header("3")), Some(Duration::from_secs(3)));
+        assert_eq!(
+            retry_after(&header("Wed, 21 Oct 2015 07:28:00 GMT")),
+            Some(Duration::ZERO)
+        );
+        let later = httpdate::fmt_http_date(std::time::SystemTime::now() + Duration::from_secs(60));
+        let mut dated = HeaderMap::new();
+        dated.insert(RETRY_AFTER, HeaderValue::from_str(&later).unwrap());
+        let wait = retry_after(&dated).unwrap();
+        assert!(wait > Duration::from_secs(50) && wait <= Duration::from_secs(60));
+        assert_eq!(retry_after(&header("soon")), None);
+        assert_eq!(retry_after(&HeaderMap::new()), None);
+    }
+
+    #[tokio::test]
+    async fn rate_limited_requests_wait_no_longer_than_max_backoff() {
+        let url = raw_server(
+            "HTTP/1.1 429 Too Many Requests\r\nretry-after: 4294967295\r\ncontent-length: 2\r\n\r\n{}",
+        )
+        .await;
+        let client = RequestClient::new(url)
+            .with_rate_limit(100.0, 1)
+            .with_retry(
+                RetryPolicy::new(0)
+                    .with_max_rate_limited(2)
+                    .with_max_backoff(Duration::from_millis(50)),
+            );
+        let started = Instant::now();
+        let response = tokio::time::timeout(
+            Duration::from_secs(5),
+            client.fetch(&TestCase::new("/quiz", Method::GET, None)),
+        )
+        .await
+        .expect("a huge Retry-After stalled the request")
+        .unwrap();
+        assert_eq!(response.status, 429);
+        assert!(started.elapsed() >= Duration::from_millis(100));
+    }
+
+    #[tokio::test]
+    async fn cookie_jars_send_back_what_their_server_set() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        // Sets a cookie and answers with whether the request carried it.
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let mut request = [0; 4096];
+                    while let Ok(read) = socket.read(&mut request).await {
+                        if read == 0 {
+                            break;
+                        }
+                        let text = String::from_utf8_lossy(&request[..read]).to_lowercase();
+                        let body = if text.contains("cookie: sid=1") {
+                            "true"
+                        } else {
+                            "false"
+                        };
+                        let response = format!(
+                            "HTTP/1.1 200 OK\r\nset-cookie: sid=1\r\ncontent-length: {}\r\n\r\n{}",
+                            body.len(),
+                            body
+                        );
+                        let _ = socket.write_all(response.as_bytes()).await;
+                    }
+                });
+            }
+        });
+
+        let case = TestCase::new("/", Method::GET, None);
+        let jar = RequestClient::new(url.clone()).with_cookies();
+        assert_eq!(jar.fetch(&case).await.unwrap().body, Value::Bool(false));
+        assert_eq!(jar.fetch(&case).await.unwrap().body, Value::Bool(true));
+        // Re-basing starts over with an empty jar.
+        let rebased = jar.with_base_url(url.clone());
+        assert_eq!(rebased.fetch(&case).await.unwrap().body, Value::Bool(false));
+
+        let plain = RequestClient::new(url);
+        plain.fetch(&case).await.unwrap();
+        assert_eq!(plain.fetch(&case).await.unwrap().body, Value::Bool(false));
+    }
+
+    #[tokio::test]
+    async fn idempotent_requests_are_repeated_and_compared_again() {
+        const FOUND: &str = "HTTP/1.1 200 OK\r\nconnection: close\r\ncontent-length: 1\r\n\r\n1";
+        const GONE: &str =
+            "HTTP/1.1 404 Not Found\r\nconnection: close\r\ncontent-length: 1\r\n\r\n1";
+        let reference = raw_server(FOUND).await;
+        let fresh = || TestCase::new("/", Method::DELETE, None);
+
+        let client = scripted_server(&[FOUND, GONE]).await;
+        let tester = Tester::new(client, reference.clone()).with_idempotency_checks();
+        let comparison = tester.compare_case(&fresh()).await;
+        assert!(matches!(
+            comparison.outcome,
+            Err(TesterError::RepeatMismatch { .. })
+        ));
+        // The first responses are the ones kept.
+        assert_eq!(comparison.client.unwrap().status, 200);
+
+        let client = scripted_server(&[FOUND, GONE]).await;
+        let tester = Tester::new(client, reference).with_idempotency_checks();
+        let post = TestCase::new("/", Method::POST, None);
+        assert!(tester.compare_case(&post).await.outcome.is_ok());
+        // The POST went once, so this is the first 404, not a repeat.
+        let outcome = tester.compare_case(&fresh()).await.outcome;
+        assert!(outcome.is_err());
+        assert!(!matches!(outcome, Err(TesterError::RepeatMismatch { .. })));
+    }
+
+    #[tokio::test]
+    async fn servers_are_up_once_they_answer_the_health_check() {
+        const MISSING: &str = "HTTP/1.1 404 Not Found\r\ncontent-length: 0\r\n\r\n";
+        let url = raw_server(MISSING).await;
+        // Without a health check, any answer means the server is up.
+        let tester = Tester::new(url.clone(), url.clone());
+        tester.wait_ready(Duration::from_secs(5)).await.unwrap();
+
+        let tester = tester.with_health_check(TestCase::new("/health", Method::GET, None));
+        let err = tester
+            .wait_ready(Duration::from_millis(100))
+            .await
+            .unwrap_err();
+        assert_eq!(err.server, "server under test");
+        assert_eq!(err.url, format!("{}/health", url));
+        assert_eq!(err.last, "status 404");
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let down = format!("http://{}", listener.local_addr().unwrap());
+        drop(listener);
+        let err = Tester::new(url, down)
+            .wait_ready(Duration::from_millis(100))
+            .await
+            .unwrap_err();
+        assert_eq!(err.server, "reference server");
+    }
+
+    #[test]
+    fn redirects_and_non_json_bodies_are_kept_raw() {
+        let read = |status, pairs: &[(HeaderName, &'static str)], bytes: &[u8]| {
+            RawResponse::read(status, &headers(pairs), bytes, "http://host:1/")
+        };
+        let redirect = |location| RawResponse::Redirect {
+            location: Some(String::from(location)),
+        };
+        assert_eq!(
+            read(302, &[(LOCATION, "http://host:1/login?next=1")], b""),
+            Some(redirect("/login?next=1"))
+        );
+        assert_eq!(
+            read(301, &[(LOCATION, "http://host:10/x")], b""),
+            Some(redirect("http://host:10/x"))
+        );
+        assert_eq!(
+            read(200, &[(CONTENT_TYPE, "Text/HTML; charset=utf-8")], b"<p>"),
+            Some(RawResponse::Bytes {
+                content_type: "text/html".to_string(),
+                bytes: b"<p>".to_vec(),
+            })
+        );
+        assert_eq!(
+            read(200, &[(CONTENT_TYPE, "application/problem+json")], b"{}"),
+            None
+        );
+        assert_eq!(read(200, &[], b"{}"), None);
+    }
+
+    #[tokio::test]
+    async fn redirects_are_not_followed() {
+        let url = raw_server(
+            "HTTP/1.1 303 See Other\r\nlocation: /elsewhere\r\ncontent-length: 0\r\n\r\n",
+        )
+        .await;
+        let response = RequestClient::new(url)
+            .fetch(&TestCase::new("/", Method::POST, None))
+            .await
+            .unwrap();
+        assert_eq!(response.status, 303);
+        assert_eq!(
+            response.raw,
+            Some(RawResponse::Redirect {
+                location: Some("/elsewhere".to_string())
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn servers_must_settle_on_the_same_http_version() {
+        const OK: &str = "HTTP/1.1 200 OK\r\nconnection: close\r\ncontent-length: 2\r\n\r\n{}";
+        let url = raw_server(OK).await;
+        let case = TestCase::new("/", Method::GET, None);
+        let http1 = RequestClient::new(url.clone()).with_http_version(HttpVersion::Http1);
+        let response = http1.fetch(&case).await.unwrap();
+        assert_eq!(response.http_version, Some(Version::HTTP_11));
+
+        let tester = Tester::new(url.clone(), url);
+        let mut http2 = response.clone();
+        http2.http_version = Some(Version::HTTP_2);
+        assert!(matches!(
+            tester.check_pair(&case, &response, &http2),
+            Err(TesterError::HttpVersionMismatch { client_version, actual_version, .. })
+                if client_version == "HTTP/1.1" && actual_version == "HTTP/2.0"
+        ));
+        // Recorded responses don't say, so anything goes.
+        http2.http_version = None;
+        assert!(tester.check_pair(&case, &response, &http2).is_ok());
+    }
+
+    #[test]
+    fn usage_is_checked_against_the_route_without_its_query() {
+        let trash = TestCase::new("/admin/quiz/trash?token=abc", Method::GET, None);
+        assert!(check_usage(&trash).is_ok());
+
+        let wrong = TestCase::new("/admin/quiz/trash?token=abc", Method::POST, None);
+        match check_usage(&wrong) {
+            Err(TesterError::InvalidUsage { message, .. }) => {
+                assert_eq!(message, "`/admin/quiz/trash` only takes GET")
+            }
+            other => panic!("expected invalid usage, got {:?}", other),
+        }
+
+        let unfilled = TestCase::new("/admin/quiz/{quizid}?token=abc", Method::GET, None);
+        assert!(check_usage(&unfilled).is_err());
+    }
+
+    #[tokio::test]
+    async fn requests_go_through_the_servers_proxy() {
+        let proxy = raw_server(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: 16\r\n\r\n{\"proxied\":true}",
+        )
+        .await;
+        let network = Network::new().with_proxy(reqwest::Proxy::all(&proxy).unwrap());
+        // Nothing listens there; only the proxy can answer.
+        let client =
+            RequestClient::new("http://unreachable.invalid".to_string()).with_network(network);
+        let response = client
+            .fetch(&TestCase::new("/quiz", Method::GET, None))
+            .await
+            .unwrap();
+        assert_eq!(response.body, serde_json::json!({ "proxied": true }));
+    }
+
+    #[test]
+    fn only_issues_the_other_server_lacks_are_mismatches() {
+        let case = TestCase::new("/quiz", Method::GET, None);
+        let mut client = crate::runner::tests::response(200, Value::Null, 1);
+        let mut actual = client.clone();
+        client.json_issues = vec![JsonIssue::TrailingData { offset: 3 }];
+        assert!(matches!(
+            compare_hygiene(&case, &client, Some(&actual)),
+            Err(TesterError::JsonHygiene {
+                server: "server under test",
+                ..
+            })
+        ));
+        actual.json_issues = vec![JsonIssue::TrailingData { offset: 8 }];
+        assert!(compare_hygiene(&case, &client, Some(&actual)).is_ok());
+        // An oracle's answer has none to share.
+        assert!(compare_hygiene(&case, &client, None).is_err());
+        assert!(matches!(
+            compare_hygiene(
+                &case,
+                &crate::runner::tests::response(200, Value::Null, 1),
+                Some(&actual)
+            ),
+            Err(TesterError::JsonHygiene {
+                server: "reference server",
+                ..
+            })
+        ));
+    }
 
-        if let Some(data) = body {
-            request_builder = request_builder.json(&data);
+    #[tokio::test]
+    async fn streamed_comparisons_stop_at_the_first_difference() {
+        // Both promise far more than they send and then go quiet, so only a
+        // comparison that stops at the difference can finish.
+        let client = stalling_server(
+            "HTTP/1.1 200 OK\r\ncontent-length: 1000000\r\n\r\n{\"quizzes\": [{\"quizId\": 1, \"name\": \"a\"}, ",
+        )
+        .await;
+        let reference = stalling_server(
+            "HTTP/1.1 200 OK\r\ncontent-length: 1000000\r\n\r\n{\"quizzes\": [{\"quizId\": 1, \"name\": 2}, ",
+        )
+        .await;
+        let tester = Tester::new(client, reference).with_streaming(8);
+        let list = TestCase::new("/admin/quiz/list", Method::GET, None);
+        let comparison = tokio::time::timeout(Duration::from_secs(5), tester.compare_case(&list))
+            .await
+            .expect("the comparison waited for the rest of the bodies");
+        match comparison.outcome {
+            Err(TesterError::TypeMismatch { path, .. }) => assert_eq!(path, "/quizzes/0/name"),
+            outcome => panic!("expected a type mismatch, got {:?}", outcome),
         }
+        assert!(matches!(
+            comparison.client.unwrap().raw,
+            Some(RawResponse::Streamed { head }) if head.len() > 8
+        ));
+    }
+
+    #[tokio::test]
+    async fn streamed_bodies_must_be_json_within_the_size_limit() {
+        const LIST: &str = "HTTP/1.1 200 OK\r\ntransfer-encoding: chunked\r\nconnection: close\r\n\r\n6\r\n[1,2,3\r\n6\r\n,4,5,6\r\n6\r\n,7,8,9\r\n4\r\n,10]\r\n0\r\n\r\n";
+        const BROKEN: &str = "HTTP/1.1 200 OK\r\ntransfer-encoding: chunked\r\nconnection: close\r\n\r\n6\r\n[1,2,3\r\n6\r\n,4,5,6\r\n6\r\n,7,8,9\r\n4\r\n,10}\r\n0\r\n\r\n";
+        let list = TestCase::new("/admin/quiz/list", Method::GET, None);
+        let (client, reference) = (raw_server(LIST).await, raw_server(LIST).await);
+        let tester = Tester::new(client.clone(), reference.clone()).with_streaming(4);
+        tester.compare_case(&list).await.outcome.unwrap();
+        let limited = tester.with_max_body_size(16);
+        match limited.compare_case(&list).await.outcome {
+            Err(TesterError::BodyTooLarge { limit, .. }) => assert_eq!(limit, 16),
+            outcome => panic!("expected a body too large, got {:?}", outcome),
+        }
+
+        let broken = raw_server(BROKEN).await;
+        let tester = Tester::new(broken, reference).with_streaming(4);
+        match tester.compare_case(&list).await.outcome {
+            Err(TesterError::NonJsonBody { raw_body, .. }) => assert!(raw_body.contains('}')),
+            outcome => panic!("expected a body that isn't JSON, got {:?}", outcome),
+        }
+    }
+
+    #[test]
+    fn bodies_are_read_as_typed_values_or_say_why_not() {
+        #[derive(Debug, PartialEq, Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct Quiz {
+            quiz_id: u32,
+        }
+        let case = TestCase::new("/admin/quiz/1", Method::GET, None);
+        let response = crate::runner::tests::response(200, serde_json::json!({"quizId": 1}), 0);
+        assert_eq!(
+            response.json::<Quiz>(&case, "client").unwrap(),
+            Quiz { quiz_id: 1 }
+        );
 
-        let response = request_builder.send().await?;
-        Ok(response)
+        let wrong = crate::runner::tests::response(200, serde_json::json!({"quizId": "1"}), 0);
+        let image = ServerResponse {
+            raw: Some(RawResponse::Bytes {
+                content_type: "image/png".to_string(),
+                bytes: vec![1, 2],
+            }),
+            ..crate::runner::tests::response(200, Value::Null, 0)
+        };
+        for (response, expected) in [(wrong, "invalid type"), (image, "got image/png")] {
+            match response.json::<Quiz>(&case, "actual") {
+                Err(TesterError::UnexpectedBody {
+                    server, message, ..
+                }) => {
+                    assert_eq!(server, "actual");
+                    assert!(message.contains(expected), "{}", message);
+                }
+                outcome => panic!("expected an unexpected body, got {:?}", outcome),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn typed_comparisons_need_a_reference_server() {
+        use crate::models::oracle::{Expectation, StaticOracle};
+        let (url, attempts) = silent_server().await;
+        let oracle = StaticOracle::new().with_rule(Method::GET, "/quiz", Expectation::new(200));
+        let tester = Tester::against_oracle(url, oracle);
+        let case = TestCase::new("/quiz", Method::GET, None);
+        match tester.compare_as::<Value>(&case).await {
+            Err(TesterError::InvalidUsage { message, .. }) => {
+                assert_eq!(message, "compare_as needs a reference server")
+            }
+            outcome => panic!("expected invalid usage, got {:?}", outcome),
+        }
+        assert_eq!(attempts.load(Ordering::SeqCst), 0);
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,1354 @@
+use crate::models::client::{RawResponse, ServerResponse, TestCase, TesterError};
+use crate::models::pointer::{pointer_child, PointerPattern};
+use regex_automata::meta::Regex;
+use reqwest::header::{HeaderMap, HeaderName, SET_COOKIE};
+use serde::Deserialize;
+use serde_json::{Number, Value};
+use std::fmt;
+use std::time::UNIX_EPOCH;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ComparisonMode {
+    // Only the shape of the JSON has to match.
+    #[default]
+    TypesOnly,
+    // Every leaf value has to be equal as well.
+    Exact,
+    // Like `Exact`, but the listed JSON pointers (and everything below them)
+    // are only compared by type.
+    ExactIgnoringFields(Vec<PointerPattern>),
+}
+
+impl ComparisonMode {
+    fn is_exact_at(&self, path: &str) -> bool {
+        match self {
+            ComparisonMode::TypesOnly => false,
+            ComparisonMode::Exact => true,
+            ComparisonMode::ExactIgnoringFields(ignored) => {
+                !ignored.iter().any(|field| field.contains(path))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ArrayMode {
+    // Arrays must be the same length and are compared index by index.
+    #[default]
+    ElementWise,
+    // Every element of both arrays must have the shape of the first one, and
+    // lengths may differ. Only applies in `TypesOnly` mode; exact comparisons
+    // stay element-wise.
+    ElementSchema,
+}
+
+// How bodies that aren't JSON are compared; content types always have to
+// match.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NonJsonMode {
+    #[default]
+    Bytes,
+    // Only the lengths, for content like generated images that legitimately
+    // differs byte for byte.
+    Length,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+pub struct NumberRange {
+    #[serde(default)]
+    pub min: Option<f64>,
+    #[serde(default)]
+    pub max: Option<f64>,
+}
+
+impl NumberRange {
+    fn contains(&self, n: f64) -> bool {
+        self.min.is_none_or(|min| n >= min) && self.max.is_none_or(|max| n <= max)
+    }
+
+    fn describe(&self) -> String {
+        match (self.min, self.max) {
+            (Some(min), Some(max)) => format!("between {} and {}", min, max),
+            (Some(min), None) => format!("at least {}", min),
+            (None, Some(max)) => format!("at most {}", max),
+            (None, None) => "any number".to_string(),
+        }
+    }
+}
+
+// Checks on numbers beyond "both sides are a number". All are off by default.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NumericOptions {
+    // An integer on one side and a float (even `3.0`) on the other is a type
+    // mismatch.
+    pub strict_integers: bool,
+    // Numbers under keys ending in `Id` (`quizId`, `authUserId`, ...) must be
+    // non-negative integers on both servers.
+    pub non_negative_ids: bool,
+    pub ranges: Vec<(PointerPattern, NumberRange)>,
+}
+
+// Overrides how the values at a JSON pointer are compared.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum FieldAnnotation {
+    // Unix time in seconds, set by each server as it handles the request, so
+    // the two never agree exactly. Each side must instead be within
+    // `tolerance` seconds of the time its own request was in flight.
+    Timestamp {
+        #[serde(default = "default_tolerance")]
+        tolerance: f64,
+    },
+    // An array whose order means nothing, like a list of quizzes, compared as
+    // a multiset: same length, and each client element paired with a distinct
+    // actual one. Elements with the same `key` field are paired, or without a
+    // key, the first one that compares equal.
+    Unordered {
+        #[serde(default)]
+        key: Option<String>,
+    },
+    // Free text such as an error message, which the servers needn't word
+    // alike: both must be of the same type and not empty (`""`, `[]`, `{}` or
+    // null), whatever the comparison mode.
+    NonEmpty,
+    // Both must be strings matching `pattern`, and are otherwise taken as
+    // equivalent.
+    Matches {
+        pattern: FieldPattern,
+    },
+}
+
+// A regular expression for `FieldAnnotation::Matches`, unanchored unless it
+// says `^...$`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(try_from = "String")]
+pub struct FieldPattern {
+    source: String,
+    regex: Regex,
+}
+
+impl FieldPattern {
+    pub fn new(pattern: &str) -> Result<Self, String> {
+        let regex =
+            Regex::new(pattern).map_err(|err| format!("invalid pattern `{}`: {}", pattern, err))?;
+        Ok(Self {
+            source: pattern.to_string(),
+            regex,
+        })
+    }
+
+    pub fn is_match(&self, text: &str) -> bool {
+        self.regex.is_match(text)
+    }
+}
+
+impl TryFrom<String> for FieldPattern {
+    type Error = String;
+
+    fn try_from(pattern: String) -> Result<Self, Self::Error> {
+        Self::new(&pattern)
+    }
+}
+
+impl PartialEq for FieldPattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.source == other.source
+    }
+}
+
+impl fmt::Display for FieldPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+fn default_tolerance() -> f64 {
+    5.0
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CompareOptions {
+    pub mode: ComparisonMode,
+    pub ignored: Vec<PointerPattern>,
+    pub headers: Vec<HeaderName>,
+    pub numeric: NumericOptions,
+    pub arrays: ArrayMode,
+    pub non_json: NonJsonMode,
+    // Fields that may be either null or absent, so one server leaving out a
+    // field the other sends as null is not a mismatch.
+    pub optional: Vec<PointerPattern>,
+    // Treat `optional` fields like any other, keeping null and absent distinct.
+    pub strict_optional: bool,
+    // Compare the names and attributes of the cookies set by each response.
+    pub cookies: bool,
+    // The first matching pattern wins.
+    pub fields: Vec<(PointerPattern, FieldAnnotation)>,
+}
+
+impl CompareOptions {
+    pub(crate) fn is_ignored(&self, path: &str) -> bool {
+        self.ignored.iter().any(|pattern| pattern.matches(path))
+    }
+
+    // True when a pattern like `/items/*` skips every element of the array at
+    // `path`, so its length doesn't matter either.
+    pub(crate) fn ignores_every_index(&self, path: &str) -> bool {
+        self.ignored
+            .iter()
+            .any(|pattern| pattern.matches_every_child(path))
+    }
+
+    fn annotation(&self, path: &str) -> Option<&FieldAnnotation> {
+        self.fields
+            .iter()
+            .find(|(pattern, _)| pattern.matches(path))
+            .map(|(_, annotation)| annotation)
+    }
+
+    // Whether a field that is absent on one side and `present` on the other
+    // counts as matching.
+    pub(crate) fn absent_matches(&self, path: &str, present: &Value) -> bool {
+        !self.strict_optional
+            && present.is_null()
+            && self.optional.iter().any(|pattern| pattern.matches(path))
+    }
+}
+
+pub fn compare_json(
+    client: &ServerResponse,
+    actual: &ServerResponse,
+    case: &TestCase,
+    options: &CompareOptions,
+) -> Result<(), TesterError> {
+    Comparator {
+        case,
+        options,
+        in_flight: [InFlight::of(client), InFlight::of(actual)],
+    }
+    .compare_json_types(&client.body, &actual.body, "")
+}
+
+// For responses where either side is a redirect or isn't JSON; see
+// `RawResponse`.
+pub fn compare_raw(
+    client: &ServerResponse,
+    actual: &ServerResponse,
+    case: &TestCase,
+    options: &CompareOptions,
+) -> Result<(), TesterError> {
+    let mismatch = |message: String| TesterError::ContentMismatch {
+        endpoint: case.endpoint.clone(),
+        method: case.method.clone(),
+        message,
+    };
+    match (&client.raw, &actual.raw) {
+        (
+            Some(RawResponse::Redirect {
+                location: client_location,
+            }),
+            Some(RawResponse::Redirect {
+                location: actual_location,
+            }),
+        ) => {
+            if client_location != actual_location {
+                return Err(TesterError::RedirectMismatch {
+                    endpoint: case.endpoint.clone(),
+                    method: case.method.clone(),
+                    client_location: client_location.clone(),
+                    actual_location: actual_location.clone(),
+                });
+            }
+        }
+        (
+            Some(RawResponse::Bytes {
+                content_type: client_type,
+                bytes: client_bytes,
+            }),
+            Some(RawResponse::Bytes {
+                content_type: actual_type,
+                bytes: actual_bytes,
+            }),
+        ) => {
+            if client_type != actual_type
+                || (options.non_json == NonJsonMode::Length
+                    && client_bytes.len() != actual_bytes.len())
+            {
+                return Err(mismatch(format!(
+                    "client sent {}, actual sent {}",
+                    client.raw.as_ref().expect("client is raw"),
+                    actual.raw.as_ref().expect("actual is raw")
+                )));
+            }
+            if options.non_json == NonJsonMode::Bytes && client_bytes != actual_bytes {
+                let offset = client_bytes
+                    .iter()
+                    .zip(actual_bytes)
+                    .position(|(c, a)| c != a)
+                    .unwrap_or(client_bytes.len().min(actual_bytes.len()));
+                return Err(mismatch(format!(
+                    "{} ({} vs {} bytes) differs from byte {}",
+                    client_type,
+                    client_bytes.len(),
+                    actual_bytes.len(),
+                    offset
+                )));
+            }
+        }
+        (client_raw, actual_raw) => {
+            let describe = |raw: &Option<RawResponse>| match raw {
+                Some(raw) => raw.to_string(),
+                None => "JSON".to_string(),
+            };
+            return Err(mismatch(format!(
+                "client sent {}, actual sent {}",
+                describe(client_raw),
+                describe(actual_raw)
+            )));
+        }
+    }
+    Ok(())
+}
+
+pub fn compare_headers(
+    client: &HeaderMap,
+    actual: &HeaderMap,
+    case: &TestCase,
+    options: &CompareOptions,
+) -> Result<(), TesterError> {
+    for name in &options.headers {
+        let client_value = header_value(client, name);
+        let actual_value = header_value(actual, name);
+        if client_value != actual_value {
+            return Err(TesterError::HeaderMismatch {
+                endpoint: case.endpoint.clone(),
+                method: case.method.clone(),
+                header: name.to_string(),
+                client_value,
+                actual_value,
+            });
+        }
+    }
+    if options.cookies {
+        let client_value = set_cookies(client);
+        let actual_value = set_cookies(actual);
+        if client_value != actual_value {
+            return Err(TesterError::HeaderMismatch {
+                endpoint: case.endpoint.clone(),
+                method: case.method.clone(),
+                header: SET_COOKIE.to_string(),
+                client_value,
+                actual_value,
+            });
+        }
+    }
+    Ok(())
+}
+
+// Each cookie as its name and attributes, e.g. `sid; HttpOnly; Path=/`.
+// Values and expiry times differ between any two sessions, so they are left
+// out; only the flags that change how a browser treats the cookie are kept.
+fn set_cookies(headers: &HeaderMap) -> Option<String> {
+    let mut cookies: Vec<String> = headers
+        .get_all(SET_COOKIE)
+        .iter()
+        .map(|value| {
+            let value = String::from_utf8_lossy(value.as_bytes());
+            let mut parts = value.split(';').map(str::trim);
+            let name = parts
+                .next()
+                .and_then(|pair| pair.split('=').next())
+                .unwrap_or_default()
+                .to_string();
+            let mut attributes: Vec<String> = parts
+                .filter_map(|attribute| {
+                    let (key, value) = attribute.split_once('=').unwrap_or((attribute, ""));
+                    match key.to_ascii_lowercase().as_str() {
+                        "httponly" => Some("HttpOnly".to_string()),
+                        "secure" => Some("Secure".to_string()),
+                        "partitioned" => Some("Partitioned".to_string()),
+                        "samesite" => Some(format!("SameSite={}", value.to_ascii_lowercase())),
+                        "path" => Some(format!("Path={value}")),
+                        "domain" => Some("Domain".to_string()),
+                        "expires" | "max-age" => Some("Persistent".to_string()),
+                        _ => None,
+                    }
+                })
+                .collect();
+            attributes.sort();
+            attributes.dedup();
+            std::iter::once(name)
+                .chain(attributes)
+                .collect::<Vec<_>>()
+                .join("; ")
+        })
+        .collect();
+    if cookies.is_empty() {
+        return None;
+    }
+    cookies.sort();
+    Some(cookies.join(", "))
+}
+
+// Repeated headers are joined the same way a proxy would fold them.
+fn header_value(headers: &HeaderMap, name: &HeaderName) -> Option<String> {
+    let values: Vec<String> = headers
+        .get_all(name)
+        .iter()
+        .map(|v| String::from_utf8_lossy(v.as_bytes()).into_owned())
+        .collect();
+    if values.is_empty() {
+        None
+    } else {
+        Some(values.join(", "))
+    }
+}
+
+fn is_non_empty(value: &Value) -> bool {
+    match value {
+        Value::Null => false,
+        Value::String(s) => !s.trim().is_empty(),
+        Value::Array(items) => !items.is_empty(),
+        Value::Object(fields) => !fields.is_empty(),
+        Value::Bool(_) | Value::Number(_) => true,
+    }
+}
+
+fn is_id_key(path: &str) -> bool {
+    let key = path.rsplit('/').next().unwrap_or_default();
+    key.ends_with("Id") || key == "id"
+}
+
+// When a request was sent and when its response had been read, in seconds
+// since the epoch.
+#[derive(Debug, Clone, Copy)]
+struct InFlight {
+    from: f64,
+    to: f64,
+}
+
+impl InFlight {
+    fn of(response: &ServerResponse) -> Option<Self> {
+        let from = response
+            .sent_at?
+            .duration_since(UNIX_EPOCH)
+            .ok()?
+            .as_secs_f64();
+        Some(Self {
+            from,
+            to: from + response.latency.as_secs_f64(),
+        })
+    }
+}
+
+struct Comparator<'a> {
+    case: &'a TestCase,
+    options: &'a CompareOptions,
+    // The client's and the actual server's; unknown for recorded responses.
+    in_flight: [Option<InFlight>; 2],
+}
+
+impl Comparator<'_> {
+    fn compare_json_types(&self, a: &Value, b: &Value, path: &str) -> Result<(), TesterError> {
+        if self.options.is_ignored(path) {
+            return Ok(());
+        }
+        match self.options.annotation(path) {
+            Some(&FieldAnnotation::Timestamp { tolerance }) => {
+                self.check_timestamp(a, "client", self.in_flight[0], tolerance, path)?;
+                return self.check_timestamp(b, "actual", self.in_flight[1], tolerance, path);
+            }
+            Some(FieldAnnotation::NonEmpty) => {
+                if std::mem::discriminant(a) != std::mem::discriminant(b) {
+                    return Err(self.type_mismatch(path, a, b));
+                }
+                self.check_rule(a, "client", path, is_non_empty(a), "non-empty")?;
+                return self.check_rule(b, "actual", path, is_non_empty(b), "non-empty");
+            }
+            Some(FieldAnnotation::Matches { pattern }) => {
+                let expected = format!("a string matching `{}`", pattern);
+                let matches = |value: &Value| value.as_str().is_some_and(|s| pattern.is_match(s));
+                self.check_rule(a, "client", path, matches(a), &expected)?;
+                return self.check_rule(b, "actual", path, matches(b), &expected);
+            }
+            _ => {}
+        }
+
+        if let (Value::Number(x), Value::Number(y)) = (a, b) {
+            self.check_number(x, "client", path)?;
+            self.check_number(y, "actual", path)?;
+            if self.options.numeric.strict_integers && x.is_f64() != y.is_f64() {
+                return Err(self.type_mismatch(path, a, b));
+            }
+        }
+
+        match (a, b) {
+            (Value::Object(map_a), Value::Object(map_b)) => {
+                self.compare_json_objects(map_a, map_b, path)
+            }
+            (Value::Array(arr_a), Value::Array(arr_b)) => {
+                self.compare_json_arrays(arr_a, arr_b, path)
+            }
+            (Value::String(_), Value::String(_))
+            | (Value::Number(_), Value::Number(_))
+            | (Value::Bool(_), Value::Bool(_))
+                if a != b && self.options.mode.is_exact_at(path) =>
+            {
+                Err(TesterError::ValueMismatch {
+                    endpoint: self.case.endpoint.clone(),
+                    method: self.case.method.clone(),
+                    path: path.to_string(),
+                    client_value: Box::new(a.clone()),
+                    actual_value: Box::new(b.clone()),
+                })
+            }
+            (Value::String(_), Value::String(_)) => Ok(()),
+            (Value::Number(_), Value::Number(_)) => Ok(()),
+            (Value::Bool(_), Value::Bool(_)) => Ok(()),
+            (Value::Null, Value::Null) => Ok(()),
+            _ => Err(self.type_mismatch(path, a, b)),
+        }
+    }
+
+    fn type_mismatch(&self, path: &str, a: &Value, b: &Value) -> TesterError {
+        TesterError::TypeMismatch {
+            endpoint: self.case.endpoint.clone(),
+            method: self.case.method.clone(),
+            path: path.to_string(),
+            client_value: Box::new(a.clone()),
+            actual_value: Box::new(b.clone()),
+        }
+    }
+
+    fn check_number(
+        &self,
+        n: &Number,
+        server: &'static str,
+        path: &str,
+    ) -> Result<(), TesterError> {
+        let numeric = &self.options.numeric;
+        let value = n.as_f64().unwrap_or_default();
+        let expected = if numeric.non_negative_ids && is_id_key(path) && (n.is_f64() || value < 0.0)
+        {
+            Some("a non-negative integer".to_string())
+        } else {
+            numeric
+                .ranges
+                .iter()
+                .find(|(pattern, range)| pattern.matches(path) && !range.contains(value))
+                .map(|(_, range)| range.describe())
+        };
+        match expected {
+            Some(expected) => Err(TesterError::NumberOutOfRange {
+                endpoint: self.case.endpoint.clone(),
+                method: self.case.method.clone(),
+                server,
+                path: path.to_string(),
+                value: Box::new(Value::Number(n.clone())),
+                expected,
+            }),
+            None => Ok(()),
+        }
+    }
+
+    fn check_rule(
+        &self,
+        value: &Value,
+        server: &'static str,
+        path: &str,
+        holds: bool,
+        expected: &str,
+    ) -> Result<(), TesterError> {
+        if holds {
+            return Ok(());
+        }
+        Err(TesterError::FieldRuleViolation {
+            endpoint: self.case.endpoint.clone(),
+            method: self.case.method.clone(),
+            server,
+            path: path.to_string(),
+            value: Box::new(value.clone()),
+            expected: expected.to_string(),
+        })
+    }
+
+    fn check_timestamp(
+        &self,
+        value: &Value,
+        server: &'static str,
+        in_flight: Option<InFlight>,
+        tolerance: f64,
+        path: &str,
+    ) -> Result<(), TesterError> {
+        let within = |time: f64| {
+            in_flight.is_none_or(|in_flight| {
+                time >= in_flight.from - tolerance && time <= in_flight.to + tolerance
+            })
+        };
+        if value.as_f64().is_some_and(within) {
+            return Ok(());
+        }
+        let expected = match in_flight {
+            Some(in_flight) => format!(
+                "within {}s of the request ({:.0} to {:.0})",
+                tolerance, in_flight.from, in_flight.to
+            ),
+            None => "a Unix timestamp".to_string(),
+        };
+        Err(TesterError::TimestampOutOfRange {
+            endpoint: self.case.endpoint.clone(),
+            method: self.case.method.clone(),
+            server,
+            path: path.to_string(),
+            value: Box::new(value.clone()),
+            expected,
+        })
+    }
+
+    fn compare_json_objects(
+        &self,
+        map_a: &serde_json::Map<String, Value>,
+        map_b: &serde_json::Map<String, Value>,
+        path: &str,
+    ) -> Result<(), TesterError> {
+        for (key, value_a) in map_a {
+            let child = pointer_child(path, key);
+            if self.options.is_ignored(&child) {
+                continue;
+            }
+            if let Some(value_b) = map_b.get(key) {
+                self.compare_json_types(value_a, value_b, &child)?;
+            } else if !self.options.absent_matches(&child, value_a) {
+                return Err(TesterError::TypeMismatch {
+                    endpoint: self.case.endpoint.clone(),
+                    method: self.case.method.clone(),
+                    path: child,
+                    client_value: Box::new(value_a.clone()),
+                    actual_value: Box::new(Value::Null),
+                });
+            }
+        }
+
+        for (key, value_b) in map_b {
+            let child = pointer_child(path, key);
+            if !map_a.contains_key(key)
+                && !self.options.is_ignored(&child)
+                && !self.options.absent_matches(&child, value_b)
+            {
+                return Err(TesterError::TypeMismatch {
+                    endpoint: self.case.endpoint.clone(),
+                    method: self.case.method.clone(),
+                    path: child,
+                    client_value: Box::new(Value::Null),
+                    actual_value: Box::new(value_b.clone()),
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    fn compare_json_arrays(
+        &self,
+        arr_a: &[Value],
+        arr_b: &[Value],
+        path: &str,
+    ) -> Result<(), TesterError> {
+        if self.options.ignores_every_index(path) {
+            return Ok(());
+        }
+
+        let unordered = match self.options.annotation(path) {
+            Some(FieldAnnotation::Unordered { key }) => Some(key.as_deref()),
+            _ => None,
+        };
+        if unordered.is_none()
+            && self.options.arrays == ArrayMode::ElementSchema
+            && self.options.mode == ComparisonMode::TypesOnly
+        {
+            return self.compare_json_element_schemas(arr_a, arr_b, path);
+        }
+
+        if arr_a.len() != arr_b.len() {
+            return Err(TesterError::ArrayLengthMismatch {
+                endpoint: self.case.endpoint.clone(),
+                method: self.case.method.clone(),
+                path: path.to_string(),
+                client_len: arr_a.len(),
+                actual_len: arr_b.len(),
+            });
+        }
+        if let Some(key) = unordered {
+            return self.compare_json_multisets(arr_a, arr_b, key, path);
+        }
+
+        for (i, (elem_a, elem_b)) in arr_a.iter().zip(arr_b.iter()).enumerate() {
+            self.compare_json_types(elem_a, elem_b, &pointer_child(path, &i.to_string()))?;
+        }
+
+        Ok(())
+    }
+
+    // Pairs each client element with the first unpaired actual element that
+    // has the same `key` (and then compares the two), or without a key, that
+    // compares equal. A client element with nothing to pair with is reported
+    // as missing from the actual array when keyed, and otherwise by how it
+    // differs from the first actual element still unpaired.
+    fn compare_json_multisets(
+        &self,
+        arr_a: &[Value],
+        arr_b: &[Value],
+        key: Option<&str>,
+        path: &str,
+    ) -> Result<(), TesterError> {
+        let mut unpaired: Vec<&Value> = arr_b.iter().collect();
+        for (i, elem_a) in arr_a.iter().enumerate() {
+            let child = pointer_child(path, &i.to_string());
+            let position = match key {
+                Some(key) => {
+                    let position = elem_a.get(key).and_then(|id| {
+                        unpaired
+                            .iter()
+                            .position(|elem_b| elem_b.get(key) == Some(id))
+                    });
+                    if let Some(position) = position {
+                        self.compare_json_types(elem_a, unpaired[position], &child)?;
+                    }
+                    position
+                }
+                None => unpaired
+                    .iter()
+                    .position(|elem_b| self.compare_json_types(elem_a, elem_b, &child).is_ok()),
+            };
+            match position {
+                Some(position) => {
+                    unpaired.remove(position);
+                }
+                // Lengths match, so there is always one left.
+                None if key.is_none() => {
+                    return self.compare_json_types(elem_a, unpaired[0], &child)
+                }
+                None => {
+                    return Err(TesterError::TypeMismatch {
+                        endpoint: self.case.endpoint.clone(),
+                        method: self.case.method.clone(),
+                        path: child,
+                        client_value: Box::new(elem_a.clone()),
+                        actual_value: Box::new(Value::Null),
+                    })
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // The first element, from the client's array if it has one, stands in for
+    // the element schema.
+    fn compare_json_element_schemas(
+        &self,
+        arr_a: &[Value],
+        arr_b: &[Value],
+        path: &str,
+    ) -> Result<(), TesterError> {
+        let Some(template) = arr_a.first().or_else(|| arr_b.first()) else {
+            return Ok(());
+        };
+        for (server, elements) in [("client", arr_a), ("actual", arr_b)] {
+            for (i, element) in elements.iter().enumerate() {
+                let child = pointer_child(path, &i.to_string());
+                match self.compare_json_types(template, element, &child) {
+                    Err(TesterError::TypeMismatch {
+                        path,
+                        client_value,
+                        actual_value,
+                        ..
+                    }) => {
+                        return Err(TesterError::ArrayElementMismatch {
+                            endpoint: self.case.endpoint.clone(),
+                            method: self.case.method.clone(),
+                            server,
+                            path,
+                            expected: client_value,
+                            actual_value,
+                        })
+                    }
+                    result => result?,
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runner::tests::response;
+    use reqwest::Method;
+    use serde_json::json;
+    use std::time::Duration;
+
+    fn options(mode: ComparisonMode, ignored: &[&str]) -> CompareOptions {
+        CompareOptions {
+            mode,
+            ignored: ignored.iter().map(|&p| p.into()).collect(),
+            ..Default::default()
+        }
+    }
+
+    fn case() -> TestCase {
+        TestCase::new("/e", Method::GET, None)
+    }
+
+    fn compare(options: &CompareOptions, a: Value, b: Value) -> Result<(), TesterError> {
+        compare_json(&response(200, a, 0), &response(200, b, 0), &case(), options)
+    }
+
+    #[test]
+    fn types_only_ignores_values_but_not_shape() {
+        let types = CompareOptions::default();
+        assert!(compare(&types, json!({"a": [1, "x"]}), json!({"a": [2, "y"]})).is_ok());
+        assert!(matches!(
+            compare(&types, json!({"a": [1, "x"]}), json!({"a": [1, 2]})),
+            Err(TesterError::TypeMismatch { path, .. }) if path == "/a/1"
+        ));
+        assert!(matches!(
+            compare(&types, json!({"a": 1}), json!({"a": 1, "b": null})),
+            Err(TesterError::TypeMismatch { path, .. }) if path == "/b"
+        ));
+    }
+
+    #[test]
+    fn exact_compares_leaf_values() {
+        let exact = options(ComparisonMode::Exact, &[]);
+        assert!(compare(&exact, json!({"a": [1]}), json!({"a": [1]})).is_ok());
+        assert!(matches!(
+            compare(&exact, json!({"a": [1, true]}), json!({"a": [1, false]})),
+            Err(TesterError::ValueMismatch { path, .. }) if path == "/a/1"
+        ));
+        // A type difference is still reported as one.
+        assert!(matches!(
+            compare(&exact, json!("1"), json!(1)),
+            Err(TesterError::TypeMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn exact_ignoring_fields_compares_their_subtree_by_type() {
+        let mode = ComparisonMode::ExactIgnoringFields(vec!["/quiz".into()]);
+        assert!(mode.is_exact_at(""));
+        assert!(!mode.is_exact_at("/quiz"));
+        assert!(!mode.is_exact_at("/quiz/0/id"));
+        assert!(mode.is_exact_at("/quizId"));
+
+        let options = options(mode, &[]);
+        let a = json!({"quiz": {"id": 1}, "quizId": 2});
+        assert!(compare(&options, a.clone(), json!({"quiz": {"id": 9}, "quizId": 2})).is_ok());
+        assert!(matches!(
+            compare(&options, a.clone(), json!({"quiz": {"id": "9"}, "quizId": 2})),
+            Err(TesterError::TypeMismatch { path, .. }) if path == "/quiz/id"
+        ));
+        assert!(matches!(
+            compare(&options, a, json!({"quiz": {"id": 1}, "quizId": 3})),
+            Err(TesterError::ValueMismatch { path, .. }) if path == "/quizId"
+        ));
+    }
+
+    #[test]
+    fn ignored_fields_may_differ_or_be_missing_on_either_side() {
+        let options = options(
+            ComparisonMode::Exact,
+            &["/timeCreated", "/quizzes/*/quizId"],
+        );
+        assert!(compare(
+            &options,
+            json!({"timeCreated": 1, "quizzes": [{"quizId": 1, "name": "a"}]}),
+            json!({"quizzes": [{"quizId": "x", "name": "a"}]}),
+        )
+        .is_ok());
+        assert!(compare(
+            &options,
+            json!({"quizzes": [{"name": "a"}]}),
+            json!({"timeCreated": "now", "quizzes": [{"quizId": 2, "name": "a"}]}),
+        )
+        .is_ok());
+        // Siblings of an ignored field are still compared.
+        assert!(matches!(
+            compare(
+                &options,
+                json!({"quizzes": [{"quizId": 1, "name": "a"}]}),
+                json!({"quizzes": [{"quizId": 1, "name": "b"}]}),
+            ),
+            Err(TesterError::ValueMismatch { path, .. }) if path == "/quizzes/0/name"
+        ));
+    }
+
+    #[test]
+    fn arrays_report_their_lengths() {
+        let types = CompareOptions::default();
+        match compare(&types, json!({"items": [1, 2, 3]}), json!({"items": [1]})) {
+            Err(TesterError::ArrayLengthMismatch {
+                path,
+                client_len,
+                actual_len,
+                ..
+            }) => assert_eq!((path.as_str(), client_len, actual_len), ("/items", 3, 1)),
+            other => panic!("expected a length mismatch, got {:?}", other),
+        }
+        // Ignoring one index doesn't excuse a different length.
+        let one = options(ComparisonMode::TypesOnly, &["/items/0"]);
+        assert!(matches!(
+            compare(&one, json!({"items": [1, 2]}), json!({"items": [1]})),
+            Err(TesterError::ArrayLengthMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn ignoring_every_index_skips_the_length_check() {
+        let options = options(ComparisonMode::Exact, &["/items/*"]);
+        assert!(compare(&options, json!({"items": [1, 2, 3]}), json!({"items": []})).is_ok());
+        assert!(compare(&options, json!({"items": ["a"]}), json!({"items": [{}]})).is_ok());
+        // The array itself still has to be an array.
+        assert!(matches!(
+            compare(&options, json!({"items": [1]}), json!({"items": {}})),
+            Err(TesterError::TypeMismatch { path, .. }) if path == "/items"
+        ));
+    }
+
+    fn headers(pairs: &[(&'static str, &'static str)]) -> HeaderMap {
+        let mut map = HeaderMap::new();
+        for (name, value) in pairs {
+            map.append(*name, value.parse().unwrap());
+        }
+        map
+    }
+
+    #[test]
+    fn only_opted_in_headers_are_compared() {
+        let client = headers(&[
+            ("content-type", "application/json"),
+            ("x-powered-by", "Express"),
+        ]);
+        let actual = headers(&[("content-type", "application/json")]);
+        assert!(compare_headers(&client, &actual, &case(), &CompareOptions::default()).is_ok());
+
+        let options = CompareOptions {
+            headers: vec![HeaderName::from_static("content-type")],
+            ..Default::default()
+        };
+        assert!(compare_headers(&client, &actual, &case(), &options).is_ok());
+
+        let options = CompareOptions {
+            headers: vec![HeaderName::from_static("x-powered-by")],
+            ..Default::default()
+        };
+        match compare_headers(&client, &actual, &case(), &options) {
+            Err(TesterError::HeaderMismatch {
+                header,
+                client_value,
+                actual_value,
+                ..
+            }) => {
+                assert_eq!(header, "x-powered-by");
+                assert_eq!(client_value.as_deref(), Some("Express"));
+                assert_eq!(actual_value, None);
+            }
+            other => panic!("expected a header mismatch, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn repeated_headers_are_folded_in_order() {
+        let options = CompareOptions {
+            headers: vec![HeaderName::from_static("vary")],
+            ..Default::default()
+        };
+        let folded = headers(&[("vary", "origin, accept")]);
+        let repeated = headers(&[("vary", "origin"), ("vary", "accept")]);
+        let reversed = headers(&[("vary", "accept"), ("vary", "origin")]);
+        assert!(compare_headers(&folded, &repeated, &case(), &options).is_ok());
+        assert!(compare_headers(&repeated, &reversed, &case(), &options).is_err());
+    }
+
+    fn numeric(numeric: NumericOptions) -> CompareOptions {
+        CompareOptions {
+            numeric,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn strict_integers_tell_floats_apart() {
+        let loose = CompareOptions::default();
+        assert!(compare(&loose, json!({"n": 3}), json!({"n": 3.0})).is_ok());
+        let strict = numeric(NumericOptions {
+            strict_integers: true,
+            ..Default::default()
+        });
+        assert!(compare(&strict, json!({"n": 3}), json!({"n": 4})).is_ok());
+        assert!(matches!(
+            compare(&strict, json!({"n": 3}), json!({"n": 3.0})),
+            Err(TesterError::TypeMismatch { path, .. }) if path == "/n"
+        ));
+    }
+
+    #[test]
+    fn ids_must_be_non_negative_integers_on_both_sides() {
+        let ids = numeric(NumericOptions {
+            non_negative_ids: true,
+            ..Default::default()
+        });
+        assert!(compare(
+            &ids,
+            json!({"quizId": 1, "score": -1}),
+            json!({"quizId": 2, "score": 0.5})
+        )
+        .is_ok());
+        assert!(matches!(
+            compare(&ids, json!({"quizId": 1}), json!({"quizId": -1})),
+            Err(TesterError::NumberOutOfRange { server: "actual", path, .. }) if path == "/quizId"
+        ));
+        assert!(matches!(
+            compare(&ids, json!([{"id": 1.5}]), json!([{"id": 1}])),
+            Err(TesterError::NumberOutOfRange { server: "client", path, .. }) if path == "/0/id"
+        ));
+    }
+
+    #[test]
+    fn ranges_apply_to_matching_pointers() {
+        let ranges = numeric(NumericOptions {
+            ranges: vec![(
+                PointerPattern::parse("/questions/*/points"),
+                NumberRange {
+                    min: Some(1.0),
+                    max: Some(10.0),
+                },
+            )],
+            ..Default::default()
+        });
+        let body = |points| json!({"questions": [{"points": 5}, {"points": points}], "points": 99});
+        assert!(compare(&ranges, body(10), body(1)).is_ok());
+        match compare(&ranges, body(5), body(11)) {
+            Err(TesterError::NumberOutOfRange { path, expected, .. }) => {
+                assert_eq!(path, "/questions/1/points");
+                assert_eq!(expected, "between 1 and 10");
+            }
+            outcome => panic!("expected an out-of-range number, got {:?}", outcome),
+        }
+    }
+
+    #[test]
+    fn element_schemas_allow_different_lengths_but_not_shapes() {
+        let schema = CompareOptions {
+            arrays: ArrayMode::ElementSchema,
+            ..Default::default()
+        };
+        let quiz = |id| json!({"quizId": id, "name": "q"});
+        assert!(compare(
+            &schema,
+            json!([quiz(1)]),
+            json!([quiz(2), quiz(3), quiz(4)])
+        )
+        .is_ok());
+        assert!(compare(&schema, json!([]), json!([quiz(1)])).is_ok());
+        assert!(compare(&schema, json!([]), json!([])).is_ok());
+        match compare(
+            &schema,
+            json!([quiz(1)]),
+            json!([quiz(2), {"quizId": "3", "name": "q"}]),
+        ) {
+            Err(TesterError::ArrayElementMismatch { server, path, .. }) => {
+                assert_eq!((server, path.as_str()), ("actual", "/1/quizId"));
+            }
+            outcome => panic!("expected an element mismatch, got {:?}", outcome),
+        }
+
+        // Exact comparisons stay element-wise.
+        let exact = CompareOptions {
+            mode: ComparisonMode::Exact,
+            ..schema
+        };
+        assert!(matches!(
+            compare(&exact, json!([1]), json!([1, 2])),
+            Err(TesterError::ArrayLengthMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn optional_fields_may_be_null_or_absent() {
+        let optional = CompareOptions {
+            optional: vec!["/*/thumbnail".into()],
+            ..Default::default()
+        };
+        let with = json!({"quiz": {"name": "q", "thumbnail": null}});
+        let without = json!({"quiz": {"name": "q"}});
+        assert!(compare(&optional, with.clone(), without.clone()).is_ok());
+        assert!(compare(&optional, without.clone(), with.clone()).is_ok());
+        // Only null stands in for absent.
+        assert!(compare(
+            &optional,
+            json!({"quiz": {"name": "q", "thumbnail": "x"}}),
+            without.clone()
+        )
+        .is_err());
+        assert!(compare(&CompareOptions::default(), with.clone(), without.clone()).is_err());
+
+        let strict = CompareOptions {
+            strict_optional: true,
+            ..optional
+        };
+        assert!(matches!(
+            compare(&strict, without, with),
+            Err(TesterError::TypeMismatch { path, .. }) if path == "/quiz/thumbnail"
+        ));
+    }
+
+    fn cookies(values: &[&'static str]) -> HeaderMap {
+        values
+            .iter()
+            .map(|value| (SET_COOKIE, reqwest::header::HeaderValue::from_static(value)))
+            .collect()
+    }
+
+    #[test]
+    fn cookies_compare_by_name_and_attributes_but_not_value() {
+        assert_eq!(
+            set_cookies(&cookies(&[
+                "sid=abc; path=/; HttpOnly; Max-Age=60; samesite=Lax",
+                "theme=dark",
+            ])),
+            Some("sid; HttpOnly; Path=/; Persistent; SameSite=lax, theme".to_string())
+        );
+        assert_eq!(set_cookies(&HeaderMap::new()), None);
+
+        let options = CompareOptions {
+            cookies: true,
+            ..Default::default()
+        };
+        let ours = cookies(&["sid=1; HttpOnly; Expires=Wed, 21 Oct 2015 07:28:00 GMT"]);
+        let theirs = cookies(&["sid=2; Max-Age=3600; HttpOnly"]);
+        assert!(compare_headers(&ours, &theirs, &case(), &options).is_ok());
+        let insecure = cookies(&["sid=2; Max-Age=3600"]);
+        assert!(matches!(
+            compare_headers(&ours, &insecure, &case(), &options),
+            Err(TesterError::HeaderMismatch { header, .. }) if header == "set-cookie"
+        ));
+        assert!(compare_headers(&ours, &insecure, &case(), &CompareOptions::default()).is_ok());
+    }
+
+    #[test]
+    fn timestamps_are_checked_against_each_request_time() {
+        let options = CompareOptions {
+            fields: vec![(
+                "/*/createdAt".into(),
+                FieldAnnotation::Timestamp { tolerance: 5.0 },
+            )],
+            ..Default::default()
+        };
+        let sent = |body: Value| ServerResponse {
+            sent_at: Some(UNIX_EPOCH + Duration::from_secs(1000)),
+            ..response(200, body, 2000)
+        };
+        let check = |client: Value, actual: Value| {
+            compare_json(&sent(client), &sent(actual), &case(), &options)
+        };
+
+        assert!(check(json!([{"createdAt": 996}]), json!([{"createdAt": 1006}])).is_ok());
+        assert!(matches!(
+            check(json!([{"createdAt": 994}]), json!([{"createdAt": 1000}])),
+            Err(TesterError::TimestampOutOfRange {
+                server: "client",
+                ..
+            })
+        ));
+        assert!(matches!(
+            check(json!([{"createdAt": 1000}]), json!([{"createdAt": "now"}])),
+            Err(TesterError::TimestampOutOfRange {
+                server: "actual",
+                ..
+            })
+        ));
+        // Without a request time, any number will do.
+        assert!(compare(
+            &options,
+            json!([{"createdAt": 1}]),
+            json!([{"createdAt": 2}])
+        )
+        .is_ok());
+        assert!(compare(
+            &options,
+            json!([{"createdAt": 1}]),
+            json!([{"createdAt": null}])
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn raw_bodies_compare_by_content_type_and_bytes_or_length() {
+        let raw = |raw| ServerResponse {
+            raw: Some(raw),
+            ..response(200, Value::Null, 0)
+        };
+        let png = |bytes: &[u8]| {
+            raw(RawResponse::Bytes {
+                content_type: "image/png".to_string(),
+                bytes: bytes.to_vec(),
+            })
+        };
+        let check = |options: &CompareOptions, a: &ServerResponse, b: &ServerResponse| {
+            compare_raw(a, b, &case(), options)
+        };
+        let bytes = CompareOptions::default();
+        let length = CompareOptions {
+            non_json: NonJsonMode::Length,
+            ..Default::default()
+        };
+
+        assert!(check(&bytes, &png(b"abc"), &png(b"abc")).is_ok());
+        match check(&bytes, &png(b"abc"), &png(b"abd")) {
+            Err(TesterError::ContentMismatch { message, .. }) => {
+                assert_eq!(message, "image/png (3 vs 3 bytes) differs from byte 2")
+            }
+            other => panic!("expected a content mismatch, got {:?}", other),
+        }
+        assert!(check(&length, &png(b"abc"), &png(b"abd")).is_ok());
+        assert!(check(&length, &png(b"abc"), &png(b"ab")).is_err());
+
+        let redirect = |location: &str| {
+            raw(RawResponse::Redirect {
+                location: Some(location.to_string()),
+            })
+        };
+        assert!(check(&bytes, &redirect("/a"), &redirect("/a")).is_ok());
+        assert!(matches!(
+            check(&bytes, &redirect("/a"), &redirect("/b")),
+            Err(TesterError::RedirectMismatch { .. })
+        ));
+        match check(&bytes, &redirect("/a"), &response(200, json!({}), 0)) {
+            Err(TesterError::ContentMismatch { message, .. }) => {
+                assert_eq!(message, "client sent a redirect to /a, actual sent JSON")
+            }
+            other => panic!("expected a content mismatch, got {:?}", other),
+        }
+    }
+
+    fn unordered(mode: ComparisonMode, key: Option<&str>) -> CompareOptions {
+        CompareOptions {
+            mode,
+            fields: vec![(
+                "/quizzes".into(),
+                FieldAnnotation::Unordered {
+                    key: key.map(str::to_string),
+                },
+            )],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn unordered_arrays_are_compared_as_multisets() {
+        let exact = unordered(ComparisonMode::Exact, None);
+        let a = json!({"quizzes": [1, 2, 2]});
+        assert!(compare(&exact, a.clone(), json!({"quizzes": [2, 1, 2]})).is_ok());
+        // Each element pairs with one other, so a repeat can't stand in twice.
+        assert!(matches!(
+            compare(&exact, a.clone(), json!({"quizzes": [2, 1, 1]})),
+            Err(TesterError::ValueMismatch { path, .. }) if path == "/quizzes/2"
+        ));
+        assert!(matches!(
+            compare(&exact, a, json!({"quizzes": [2, 1]})),
+            Err(TesterError::ArrayLengthMismatch {
+                client_len: 3,
+                actual_len: 2,
+                ..
+            })
+        ));
+        // Only the annotated array.
+        assert!(compare(&exact, json!({"other": [1, 2]}), json!({"other": [2, 1]})).is_err());
+    }
+
+    #[test]
+    fn keyed_unordered_arrays_pair_elements_by_key() {
+        let exact = unordered(ComparisonMode::Exact, Some("quizId"));
+        let a = json!({"quizzes": [{"quizId": 1, "name": "a"}, {"quizId": 2, "name": "b"}]});
+        let swapped = json!({"quizzes": [{"quizId": 2, "name": "b"}, {"quizId": 1, "name": "a"}]});
+        assert!(compare(&exact, a.clone(), swapped).is_ok());
+        // The pair is compared, so a renamed quiz is caught at its client index.
+        let renamed = json!({"quizzes": [{"quizId": 2, "name": "b"}, {"quizId": 1, "name": "x"}]});
+        assert!(matches!(
+            compare(&exact, a.clone(), renamed),
+            Err(TesterError::ValueMismatch { path, .. }) if path == "/quizzes/0/name"
+        ));
+        let missing = json!({"quizzes": [{"quizId": 2, "name": "b"}, {"quizId": 3, "name": "a"}]});
+        assert!(matches!(
+            compare(&exact, a, missing),
+            Err(TesterError::TypeMismatch { path, actual_value, .. })
+                if path == "/quizzes/0" && *actual_value == Value::Null
+        ));
+    }
+
+    fn annotated(annotation: FieldAnnotation) -> CompareOptions {
+        CompareOptions {
+            mode: ComparisonMode::Exact,
+            fields: vec![("/error".into(), annotation)],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn free_text_only_has_to_be_there_on_both_sides() {
+        let non_empty = annotated(FieldAnnotation::NonEmpty);
+        assert!(compare(
+            &non_empty,
+            json!({"error": "Quiz not found"}),
+            json!({"error": "No such quiz"})
+        )
+        .is_ok());
+        assert!(matches!(
+            compare(&non_empty, json!({"error": "x"}), json!({"error": "  "})),
+            Err(TesterError::FieldRuleViolation { server: "actual", path, .. }) if path == "/error"
+        ));
+        assert!(matches!(
+            compare(&non_empty, json!({"error": null}), json!({"error": "x"})),
+            Err(TesterError::TypeMismatch { .. })
+        ));
+        assert!(compare(&non_empty, json!({"error": []}), json!({"error": []})).is_err());
+        assert!(compare(&non_empty, json!({"error": 0}), json!({"error": 1})).is_ok());
+    }
+
+    #[test]
+    fn matching_fields_pass_whatever_their_values() {
+        let pattern = FieldPattern::new("^[A-Z][a-z ]+$").unwrap();
+        let matches = annotated(FieldAnnotation::Matches { pattern });
+        assert!(compare(
+            &matches,
+            json!({"error": "Bad name"}),
+            json!({"error": "Too long"})
+        )
+        .is_ok());
+        match compare(
+            &matches,
+            json!({"error": "Bad name"}),
+            json!({"error": 400}),
+        ) {
+            Err(err @ TesterError::FieldRuleViolation { .. }) => assert!(err
+                .to_string()
+                .ends_with("400 is not a string matching `^[A-Z][a-z ]+$`")),
+            other => panic!("expected a field rule violation, got {:?}", other),
+        }
+        assert!(matches!(
+            compare(&matches, json!({"error": "bad"}), json!({"error": "Bad"})),
+            Err(TesterError::FieldRuleViolation {
+                server: "client",
+                ..
+            })
+        ));
+        // Unanchored unless it says otherwise.
+        assert!(FieldPattern::new("quiz")
+            .unwrap()
+            .is_match("no such quiz here"));
+        assert!(FieldPattern::new("(").is_err());
+    }
+}
@@ -1,21 +1,867 @@
-pub enum endpoints {
-  adminAuthRegister = "/admin/auth/register",
-  adminAuthLogin = "/admin/auth/login",
-  adminUserDetails = "/admin/user/details",
-  adminUserPassword = "/admin/user/password",
-  adminQuizList = "/admin/quiz/list",
-  adminQuiz = "/admin/quiz",
-  adminQuizId = "/admin/quiz/{}",
-  adminQuizIdName = "/admin/quiz/{}/name",
-  adminQuizIdDescription = "/admin/quiz/{}/description",
-  clear = "/clear", // Not available in actual api
-  adminAuthLogout = "/admin/auth/logout",
-  adminQuizTrash = "/admin/quiz/trash",
-  adminQuizIdRestore = "/admin/quiz/{}/restore",
-  adminQuizTrashEmpty = "/admin/quiz/trash/empty",
-  adminQuizIdTransfer = "/admin/quiz/{}/transfer",
-  adminQuizIdQuestion = "/admin/quiz/{}/question",
-  adminQuizIdQuestionId = "/admin/quiz/{}/question/{}",
-  adminQuizIdQuestionIdMove = "/admin/quiz/{}/question/{}/move",
-  adminQuizIdQuestionIdduplicate = "/admin/quiz/{}/question/{}/duplicate",
-}
\ No newline at end of file
+use crate::fuzz::generator::Generator;
+use crate::fuzz::presets::Preset;
+use crate::models::auth::{AuthStrategy, BodyField, Header};
+use crate::models::client::TestCase;
+use crate::schema::{ResponseSchemas, Schema, SchemaRegistry, SchemaSource};
+use crate::spec::{template_matches, without_query};
+use rand::Rng;
+use reqwest::header::HeaderName;
+use reqwest::Method;
+use serde::Deserialize;
+use serde_json::Value;
+use std::sync::Arc;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ApiVersion {
+    // Iteration 2: the token travels in the body, or the query for GET/DELETE.
+    V1,
+    // Iteration 3: authenticated routes move here and take the token as a header.
+    V2,
+}
+
+impl ApiVersion {
+    pub fn prefix(self) -> &'static str {
+        match self {
+            ApiVersion::V1 => "/v1",
+            ApiVersion::V2 => "/v2",
+        }
+    }
+
+    pub fn auth_strategy(self) -> Arc<dyn AuthStrategy> {
+        match self {
+            ApiVersion::V1 => Arc::new(BodyField("token".to_string())),
+            ApiVersion::V2 => Arc::new(Header(HeaderName::from_static("token"))),
+        }
+    }
+}
+
+const V1_ONLY: &[ApiVersion] = &[ApiVersion::V1];
+const BOTH: &[ApiVersion] = &[ApiVersion::V1, ApiVersion::V2];
+
+// A `{...}` segment of a route's path and the preset its values come from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PathParam {
+    pub name: &'static str,
+    pub preset: Preset,
+}
+
+const QUIZ_ID: PathParam = PathParam {
+    name: "quizid",
+    preset: Preset::Id,
+};
+const QUESTION_ID: PathParam = PathParam {
+    name: "questionid",
+    preset: Preset::Id,
+};
+const SESSION_ID: PathParam = PathParam {
+    name: "sessionid",
+    preset: Preset::Id,
+};
+const PLAYER_ID: PathParam = PathParam {
+    name: "playerid",
+    preset: Preset::Id,
+};
+// 1-based.
+const QUESTION_POSITION: PathParam = PathParam {
+    name: "questionposition",
+    preset: Preset::Id,
+};
+
+// One method of a route, with what it takes and what it returns on success in
+// the config's schema shorthand. GET and DELETE requests take their fields in
+// the query rather than a body.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RouteMethod {
+    pub method: Method,
+    pub request: Option<&'static str>,
+    pub response: &'static str,
+}
+
+impl RouteMethod {
+    pub fn request_schema(&self) -> Option<Schema> {
+        self.request.map(parse_shorthand)
+    }
+
+    pub fn response_schema(&self) -> Schema {
+        parse_shorthand(self.response)
+    }
+}
+
+fn parse_shorthand(source: &str) -> Schema {
+    let value = serde_json::from_str(source).expect("registry schemas are JSON");
+    SchemaSource::Shorthand(value)
+        .to_schema()
+        .expect("registry schemas are valid shorthand")
+}
+
+// Error bodies of every route.
+const ERROR_SCHEMA: &str = r#"{ "error": "string" }"#;
+
+const EMPTY: &str = "{}";
+const QUIZZES: &str = r#"{ "quizzes": [{ "quizId": "integer", "name": "string" }] }"#;
+// A finished session's results, to the quiz owner and to its players alike.
+const SESSION_RESULTS: &str = r#"{ "usersRankedByScore": [{ "name": "string", "score": "number" }],
+    "questionResults": [{ "questionId": "integer", "playersCorrectList": ["string"],
+        "averageAnswerTime": "number", "percentCorrect": "number" }] }"#;
+const QUESTION_RESULTS: &str = r#"{ "questionId": "integer", "playersCorrectList": ["string"],
+    "averageAnswerTime": "number", "percentCorrect": "number" }"#;
+
+// A route of the COMP1531 quiz API. `params` are the body or query fields the
+// route takes, not counting the token.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Endpoint {
+    pub path: &'static str,
+    pub methods: &'static [RouteMethod],
+    pub requires_auth: bool,
+    // API versions the route is served under, oldest first.
+    pub versions: &'static [ApiVersion],
+    pub params: &'static [&'static str],
+    // In the order they appear in the path.
+    pub path_params: &'static [PathParam],
+}
+
+impl Endpoint {
+    pub fn path_param(&self, name: &str) -> Option<&PathParam> {
+        self.path_params.iter().find(|param| param.name == name)
+    }
+
+    pub fn method(&self, method: &Method) -> Option<&RouteMethod> {
+        self.methods.iter().find(|route| route.method == *method)
+    }
+
+    // The newest version of the route no later than `version`; routes that were
+    // never re-versioned stay on the one they were introduced in.
+    pub fn version_for(&self, version: ApiVersion) -> Option<ApiVersion> {
+        self.versions
+            .iter()
+            .copied()
+            .filter(|v| *v <= version)
+            .max()
+    }
+
+    pub fn routes(&self) -> impl Iterator<Item = (Method, &'static str)> + '_ {
+        self.methods
+            .iter()
+            .map(|route| (route.method.clone(), self.path))
+    }
+
+    // The path with every parameter filled with a valid value.
+    pub fn fill_path<R: Rng + ?Sized>(&self, rng: &mut R) -> String {
+        let mut path = self.path.to_string();
+        for param in self.path_params {
+            let value = match param.preset.valid(rng) {
+                Value::String(s) => s,
+                other => other.to_string(),
+            };
+            path = path.replace(&format!("{{{}}}", param.name), &value);
+        }
+        path
+    }
+
+    // A request the route takes with `method`: path parameters and fields with
+    // a preset get valid values, other fields made-up ones of the right type.
+    // No token yet.
+    pub fn valid_case<R: Rng + ?Sized>(&self, method: &Method, rng: &mut R) -> Option<TestCase> {
+        let route = self.method(method)?;
+        let fields = match route.request_schema() {
+            Some(Schema::Object { properties, .. }) => properties
+                .iter()
+                .map(|(name, schema)| {
+                    let value = match schema {
+                        Schema::Preset(preset) => preset.valid(rng),
+                        other => Generator::default().generate(other, rng),
+                    };
+                    (name.clone(), value)
+                })
+                .collect(),
+            _ => serde_json::Map::new(),
+        };
+        let path = self.fill_path(rng);
+        Some(if *method == Method::GET || *method == Method::DELETE {
+            let query: Vec<(String, String)> = fields
+                .into_iter()
+                .map(|(name, value)| match value {
+                    Value::String(s) => (name, s),
+                    other => (name, other.to_string()),
+                })
+                .collect();
+            TestCase::new(path, method.clone(), None).with_query(query)
+        } else {
+            let body = route.request.map(|_| Value::Object(fields));
+            TestCase::new(path, method.clone(), body)
+        })
+    }
+
+    const fn with_path_params(mut self, path_params: &'static [PathParam]) -> Self {
+        self.path_params = path_params;
+        self
+    }
+}
+
+const fn endpoint(
+    path: &'static str,
+    methods: &'static [RouteMethod],
+    requires_auth: bool,
+    versions: &'static [ApiVersion],
+    params: &'static [&'static str],
+) -> Endpoint {
+    Endpoint {
+        path,
+        methods,
+        requires_auth,
+        versions,
+        params,
+        path_params: &[],
+    }
+}
+
+static ENDPOINTS: &[Endpoint] = &[
+    endpoint(
+        "/admin/auth/register",
+        &[RouteMethod {
+            method: Method::POST,
+            request: Some(
+                r#"{ "email": "email", "password": "password", "nameFirst": "name", "nameLast": "name" }"#,
+            ),
+            response: r#"{ "token": "string" }"#,
+        }],
+        false,
+        V1_ONLY,
+        &["email", "password", "nameFirst", "nameLast"],
+    ),
+    endpoint(
+        "/admin/auth/login",
+        &[RouteMethod {
+            method: Method::POST,
+            request: Some(r#"{ "email": "email", "password": "password" }"#),
+            response: r#"{ "token": "string" }"#,
+        }],
+        false,
+        V1_ONLY,
+        &["email", "password"],
+    ),
+    endpoint(
+        "/admin/user/details",
+        &[
+            RouteMethod {
+                method: Method::GET,
+                request: None,
+                response: r#"{ "user": { "userId": "integer", "name": "string", "email": "string",
+                    "numSuccessfulLogins": "integer", "numFailedPasswordsSinceLastLogin": "integer" } }"#,
+            },
+            RouteMethod {
+                method: Method::PUT,
+                request: Some(r#"{ "email": "email", "nameFirst": "name", "nameLast": "name" }"#),
+                response: EMPTY,
+            },
+        ],
+        true,
+        BOTH,
+        &["email", "nameFirst", "nameLast"],
+    ),
+    endpoint(
+        "/admin/user/password",
+        &[RouteMethod {
+            method: Method::PUT,
+            request: Some(r#"{ "oldPassword": "password", "newPassword": "password" }"#),
+            response: EMPTY,
+        }],
+        true,
+        BOTH,
+        &["oldPassword", "newPassword"],
+    ),
+    endpoint(
+        "/admin/quiz/list",
+        &[RouteMethod {
+            method: Method::GET,
+            request: None,
+            response: QUIZZES,
+        }],
+        true,
+        BOTH,
+        &[],
+    ),
+    endpoint(
+        "/admin/quiz",
+        &[RouteMethod {
+            method: Method::POST,
+            request: Some(r#"{ "name": "quiz_name", "description": "quiz_description" }"#),
+            response: r#"{ "quizId": "integer" }"#,
+        }],
+        true,
+        BOTH,
+        &["name", "description"],
+    ),
+    endpoint(
+        "/admin/quiz/{quizid}",
+        &[
+            RouteMethod {
+                method: Method::GET,
+                request: None,
+                response: r#"{ "quizId": "integer", "name": "string", "timeCreated": "integer",
+                    "timeLastEdited": "integer", "description": "string" }"#,
+            },
+            RouteMethod {
+                method: Method::DELETE,
+                request: None,
+                response: EMPTY,
+            },
+        ],
+        true,
+        BOTH,
+        &[],
+    )
+    .with_path_params(&[QUIZ_ID]),
+    endpoint(
+        "/admin/quiz/{quizid}/name",
+        &[RouteMethod {
+            method: Method::PUT,
+            request: Some(r#"{ "name": "quiz_name" }"#),
+            response: EMPTY,
+        }],
+        true,
+        BOTH,
+        &["name"],
+    )
+    .with_path_params(&[QUIZ_ID]),
+    endpoint(
+        "/admin/quiz/{quizid}/description",
+        &[RouteMethod {
+            method: Method::PUT,
+            request: Some(r#"{ "description": "quiz_description" }"#),
+            response: EMPTY,
+        }],
+        true,
+        BOTH,
+        &["description"],
+    )
+    .with_path_params(&[QUIZ_ID]),
+    // Not part of the real API, but every student server implements it.
+    endpoint(
+        "/clear",
+        &[RouteMethod {
+            method: Method::DELETE,
+            request: None,
+            response: EMPTY,
+        }],
+        false,
+        V1_ONLY,
+        &[],
+    ),
+    endpoint(
+        "/admin/auth/logout",
+        &[RouteMethod {
+            method: Method::POST,
+            request: None,
+            response: EMPTY,
+        }],
+        true,
+        BOTH,
+        &[],
+    ),
+    endpoint(
+        "/admin/quiz/trash",
+        &[RouteMethod {
+            method: Method::GET,
+            request: None,
+            response: QUIZZES,
+        }],
+        true,
+        BOTH,
+        &[],
+    ),
+    endpoint(
+        "/admin/quiz/{quizid}/restore",
+        &[RouteMethod {
+            method: Method::POST,
+            request: None,
+            response: EMPTY,
+        }],
+        true,
+        BOTH,
+        &[],
+    )
+    .with_path_params(&[QUIZ_ID]),
+    endpoint(
+        "/admin/quiz/trash/empty",
+        // `quizIds` is a JSON array of IDs, as a string.
+        &[RouteMethod {
+            method: Method::DELETE,
+            request: Some(r#"{ "quizIds": "string" }"#),
+            response: EMPTY,
+        }],
+        true,
+        BOTH,
+        &["quizIds"],
+    ),
+    endpoint(
+        "/admin/quiz/{quizid}/transfer",
+        &[RouteMethod {
+            method: Method::POST,
+            request: Some(r#"{ "userEmail": "email" }"#),
+            response: EMPTY,
+        }],
+        true,
+        BOTH,
+        &["userEmail"],
+    )
+    .with_path_params(&[QUIZ_ID]),
+    endpoint(
+        "/admin/quiz/{quizid}/question",
+        &[RouteMethod {
+            method: Method::POST,
+            request: Some(r#"{ "questionBody": "any" }"#),
+            response: r#"{ "questionId": "integer" }"#,
+        }],
+        true,
+        BOTH,
+        &["questionBody"],
+    )
+    .with_path_params(&[QUIZ_ID]),
+    endpoint(
+        "/admin/quiz/{quizid}/question/{questionid}",
+        &[
+            RouteMethod {
+                method: Method::PUT,
+                request: Some(r#"{ "questionBody": "any" }"#),
+                response: EMPTY,
+            },
+            RouteMethod {
+                method: Method::DELETE,
+                request: None,
+                response: EMPTY,
+            },
+        ],
+        true,
+        BOTH,
+        &["questionBody"],
+    )
+    .with_path_params(&[QUIZ_ID, QUESTION_ID]),
+    endpoint(
+        "/admin/quiz/{quizid}/question/{questionid}/move",
+        &[RouteMethod {
+            method: Method::PUT,
+            request: Some(r#"{ "newPosition": "integer" }"#),
+            response: EMPTY,
+        }],
+        true,
+        BOTH,
+        &["newPosition"],
+    )
+    .with_path_params(&[QUIZ_ID, QUESTION_ID]),
+    endpoint(
+        "/admin/quiz/{quizid}/question/{questionid}/duplicate",
+        &[RouteMethod {
+            method: Method::POST,
+            request: None,
+            response: r#"{ "newQuestionId": "integer" }"#,
+        }],
+        true,
+        BOTH,
+        &[],
+    )
+    .with_path_params(&[QUIZ_ID, QUESTION_ID]),
+    // Iteration 3's game sessions. They were only ever served under `/v1`,
+    // with the token wherever the configured version puts it.
+    endpoint(
+        "/admin/quiz/{quizid}/sessions",
+        &[RouteMethod {
+            method: Method::GET,
+            request: None,
+            response: r#"{ "activeSessions": ["integer"], "inactiveSessions": ["integer"] }"#,
+        }],
+        true,
+        V1_ONLY,
+        &[],
+    )
+    .with_path_params(&[QUIZ_ID]),
+    // Ahead of `/admin/quiz/{quizid}/session/{sessionid}`, which would
+    // otherwise take `start` for a session ID.
+    endpoint(
+        "/admin/quiz/{quizid}/session/start",
+        &[RouteMethod {
+            method: Method::POST,
+            request: Some(r#"{ "autoStartNum": "integer" }"#),
+            response: r#"{ "sessionId": "integer" }"#,
+        }],
+        true,
+        V1_ONLY,
+        &["autoStartNum"],
+    )
+    .with_path_params(&[QUIZ_ID]),
+    endpoint(
+        "/admin/quiz/{quizid}/session/{sessionid}",
+        &[
+            // `action` is one of NEXT_QUESTION, SKIP_COUNTDOWN, GO_TO_ANSWER,
+            // GO_TO_FINAL_RESULTS and END.
+            RouteMethod {
+                method: Method::PUT,
+                request: Some(r#"{ "action": "string" }"#),
+                response: EMPTY,
+            },
+            RouteMethod {
+                method: Method::GET,
+                request: None,
+                response: r#"{ "state": "string", "atQuestion": "integer", "players": ["string"],
+                    "metadata": { "quizId": "integer", "name": "string", "timeCreated": "integer",
+                        "timeLastEdited": "integer", "description": "string",
+                        "numQuestions": "integer", "questions": ["any"], "duration": "integer" } }"#,
+            },
+        ],
+        true,
+        V1_ONLY,
+        &["action"],
+    )
+    .with_path_params(&[QUIZ_ID, SESSION_ID]),
+    endpoint(
+        "/admin/quiz/{quizid}/session/{sessionid}/results",
+        &[RouteMethod {
+            method: Method::GET,
+            request: None,
+            response: SESSION_RESULTS,
+        }],
+        true,
+        V1_ONLY,
+        &[],
+    )
+    .with_path_params(&[QUIZ_ID, SESSION_ID]),
+    endpoint(
+        "/admin/quiz/{quizid}/session/{sessionid}/results/csv",
+        &[RouteMethod {
+            method: Method::GET,
+            request: None,
+            response: r#"{ "url": "string" }"#,
+        }],
+        true,
+        V1_ONLY,
+        &[],
+    )
+    .with_path_params(&[QUIZ_ID, SESSION_ID]),
+    // Players join with nothing but a session ID and a name (one is made up
+    // if it is empty), and are known by their player ID from then on.
+    endpoint(
+        "/player/join",
+        &[RouteMethod {
+            method: Method::POST,
+            request: Some(r#"{ "sessionId": "integer", "name": "string" }"#),
+            response: r#"{ "playerId": "integer" }"#,
+        }],
+        false,
+        V1_ONLY,
+        &["sessionId", "name"],
+    ),
+    endpoint(
+        "/player/{playerid}",
+        &[RouteMethod {
+            method: Method::GET,
+            request: None,
+            response: r#"{ "state": "string", "numQuestions": "integer", "atQuestion": "integer" }"#,
+        }],
+        false,
+        V1_ONLY,
+        &[],
+    )
+    .with_path_params(&[PLAYER_ID]),
+    endpoint(
+        "/player/{playerid}/question/{questionposition}",
+        &[RouteMethod {
+            method: Method::GET,
+            request: None,
+            response: r#"{ "questionId": "integer", "question": "string", "duration": "integer",
+                "thumbnailUrl?": "string", "points": "integer",
+                "answers": [{ "answerId": "integer", "answer": "string", "colour": "string" }] }"#,
+        }],
+        false,
+        V1_ONLY,
+        &[],
+    )
+    .with_path_params(&[PLAYER_ID, QUESTION_POSITION]),
+    endpoint(
+        "/player/{playerid}/question/{questionposition}/answer",
+        &[RouteMethod {
+            method: Method::PUT,
+            request: Some(r#"{ "answerIds": ["integer"] }"#),
+            response: EMPTY,
+        }],
+        false,
+        V1_ONLY,
+        &["answerIds"],
+    )
+    .with_path_params(&[PLAYER_ID, QUESTION_POSITION]),
+    endpoint(
+        "/player/{playerid}/question/{questionposition}/results",
+        &[RouteMethod {
+            method: Method::GET,
+            request: None,
+            response: QUESTION_RESULTS,
+        }],
+        false,
+        V1_ONLY,
+        &[],
+    )
+    .with_path_params(&[PLAYER_ID, QUESTION_POSITION]),
+    endpoint(
+        "/player/{playerid}/results",
+        &[RouteMethod {
+            method: Method::GET,
+            request: None,
+            response: SESSION_RESULTS,
+        }],
+        false,
+        V1_ONLY,
+        &[],
+    )
+    .with_path_params(&[PLAYER_ID]),
+    endpoint(
+        "/player/{playerid}/chat",
+        &[
+            RouteMethod {
+                method: Method::GET,
+                request: None,
+                response: r#"{ "messages": [{ "messageBody": "string", "playerId": "integer",
+                    "playerName": "string", "timeSent": "integer" }] }"#,
+            },
+            RouteMethod {
+                method: Method::POST,
+                request: Some(r#"{ "message": { "messageBody": "string" } }"#),
+                response: EMPTY,
+            },
+        ],
+        false,
+        V1_ONLY,
+        &["message"],
+    )
+    .with_path_params(&[PLAYER_ID]),
+];
+
+pub fn all() -> impl Iterator<Item = &'static Endpoint> {
+    ENDPOINTS.iter()
+}
+
+// Every route's success schema, with literal routes ahead of templated ones
+// so `GET /admin/quiz/list` isn't held to `GET /admin/quiz/{quizid}`'s.
+pub fn response_schemas() -> SchemaRegistry {
+    let mut endpoints: Vec<&Endpoint> = all().collect();
+    endpoints.sort_by_key(|endpoint| endpoint.path.contains('{'));
+    let error = parse_shorthand(ERROR_SCHEMA);
+    let mut registry = SchemaRegistry::new();
+    for endpoint in endpoints {
+        for route in endpoint.methods {
+            let schemas = ResponseSchemas {
+                success: Some(route.response_schema()),
+                error: Some(error.clone()),
+            };
+            registry = registry.with(route.method.clone(), endpoint.path, schemas);
+        }
+    }
+    registry
+}
+
+// Every (method, path template) pair in the registry.
+pub fn routes() -> impl Iterator<Item = (Method, &'static str)> {
+    all().flat_map(Endpoint::routes)
+}
+
+pub fn find(path: &str) -> Option<&'static Endpoint> {
+    all().find(|endpoint| endpoint.path == path)
+}
+
+// The route's name in the course's camelCase style, from the path's literal
+// segments: `/admin/quiz/{quizid}/name` is `adminQuizName`.
+pub fn route_name(path: &str) -> String {
+    let mut name = String::new();
+    for segment in path
+        .split('/')
+        .filter(|s| !s.is_empty() && !s.starts_with('{'))
+    {
+        let mut chars = segment.chars();
+        if let Some(first) = chars.next() {
+            if name.is_empty() {
+                name.push(first);
+            } else {
+                name.extend(first.to_uppercase());
+            }
+            name.extend(chars);
+        }
+    }
+    name
+}
+
+// The path template of the route called `name` that takes `method`, matched
+// case-insensitively.
+pub fn find_by_name(method: &Method, name: &str) -> Option<&'static str> {
+    routes()
+        .find(|(m, path)| m == method && route_name(path).eq_ignore_ascii_case(name))
+        .map(|(_, path)| path)
+}
+
+// The registry route a concrete path belongs to, whatever its query. Literal
+// routes win over templated ones, so `/admin/quiz/trash` is not taken for
+// `/admin/quiz/{quizid}`.
+pub fn resolve(path: &str) -> Option<&'static Endpoint> {
+    let path = without_query(path);
+    find(path).or_else(|| all().find(|endpoint| template_matches(endpoint.path, path)))
+}
+
+// Prefixes `path` with the version it is served under. Paths outside the
+// registry get `version` itself, and already versioned paths are left alone.
+pub fn versioned_path(version: ApiVersion, path: &str) -> String {
+    if [ApiVersion::V1, ApiVersion::V2]
+        .iter()
+        .any(|v| path.starts_with(&format!("{}/", v.prefix())))
+    {
+        return path.to_string();
+    }
+    let version = resolve(path)
+        .and_then(|endpoint| endpoint.version_for(version))
+        .unwrap_or(version);
+    format!("{}{}", version.prefix(), path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paths_are_unique_and_name_their_params() {
+        let paths: Vec<&str> = all().map(|endpoint| endpoint.path).collect();
+        let mut unique = paths.clone();
+        unique.sort();
+        unique.dedup();
+        assert_eq!(paths.len(), unique.len());
+
+        let question = find("/admin/quiz/{quizid}/question/{questionid}").unwrap();
+        assert_eq!(
+            question
+                .path_params
+                .iter()
+                .map(|param| param.name)
+                .collect::<Vec<_>>(),
+            ["quizid", "questionid"]
+        );
+        assert!(question.requires_auth);
+        assert!(!find("/clear").unwrap().requires_auth);
+        // Templates are matched as written, not against concrete paths.
+        assert_eq!(find("/admin/quiz/1"), None);
+    }
+
+    #[test]
+    fn routes_list_every_method() {
+        let details: Vec<(Method, &str)> = find("/admin/user/details").unwrap().routes().collect();
+        assert_eq!(
+            details,
+            [
+                (Method::GET, "/admin/user/details"),
+                (Method::PUT, "/admin/user/details")
+            ]
+        );
+        assert_eq!(
+            routes().count(),
+            all().map(|endpoint| endpoint.methods.len()).sum::<usize>()
+        );
+    }
+
+    #[test]
+    fn routes_stay_on_the_newest_version_they_have() {
+        let register = find("/admin/auth/register").unwrap();
+        assert_eq!(register.version_for(ApiVersion::V2), Some(ApiVersion::V1));
+        let list = find("/admin/quiz/list").unwrap();
+        assert_eq!(list.version_for(ApiVersion::V1), Some(ApiVersion::V1));
+        assert_eq!(list.version_for(ApiVersion::V2), Some(ApiVersion::V2));
+    }
+
+    #[test]
+    fn versioned_paths_follow_the_registry() {
+        assert_eq!(
+            versioned_path(ApiVersion::V2, "/admin/quiz/7"),
+            "/v2/admin/quiz/7"
+        );
+        assert_eq!(
+            versioned_path(ApiVersion::V2, "/admin/auth/login"),
+            "/v1/admin/auth/login"
+        );
+        assert_eq!(versioned_path(ApiVersion::V2, "/unknown"), "/v2/unknown");
+        assert_eq!(
+            versioned_path(ApiVersion::V2, "/v1/admin/quiz/list"),
+            "/v1/admin/quiz/list"
+        );
+    }
+
+    #[test]
+    fn literal_routes_resolve_before_templates() {
+        assert_eq!(
+            resolve("/admin/quiz/trash").map(|endpoint| endpoint.path),
+            Some("/admin/quiz/trash")
+        );
+        assert_eq!(
+            resolve("/admin/quiz/7").map(|endpoint| endpoint.path),
+            Some("/admin/quiz/{quizid}")
+        );
+        assert_eq!(resolve("/nowhere"), None);
+    }
+
+    #[test]
+    fn queries_do_not_change_the_route() {
+        assert_eq!(
+            resolve("/admin/quiz/trash?token=abc").map(|endpoint| endpoint.path),
+            Some("/admin/quiz/trash")
+        );
+        assert_eq!(
+            resolve("/admin/quiz/7?token=abc").map(|endpoint| endpoint.path),
+            Some("/admin/quiz/{quizid}")
+        );
+        assert_eq!(
+            versioned_path(ApiVersion::V2, "/admin/auth/login?x=1"),
+            "/v1/admin/auth/login?x=1"
+        );
+    }
+
+    #[test]
+    fn every_path_param_is_declared_and_every_schema_parses() {
+        for endpoint in all() {
+            let segments: Vec<&str> = endpoint
+                .path
+                .split('/')
+                .filter_map(|segment| segment.strip_prefix('{')?.strip_suffix('}'))
+                .collect();
+            let declared: Vec<&str> = endpoint
+                .path_params
+                .iter()
+                .map(|param| param.name)
+                .collect();
+            assert_eq!(segments, declared, "{}", endpoint.path);
+            for route in endpoint.methods {
+                route.request_schema();
+                route.response_schema();
+            }
+        }
+    }
+
+    #[test]
+    fn valid_cases_fill_the_path_and_put_fields_where_the_method_takes_them() {
+        let mut rng = rand::thread_rng();
+        let name = find("/admin/quiz/{quizid}/name").unwrap();
+        let case = name.valid_case(&Method::PUT, &mut rng).unwrap();
+        assert!(!case.endpoint.contains('{'), "{}", case.endpoint);
+        assert!(case.body.as_ref().unwrap()["name"].is_string());
+        assert!(name.valid_case(&Method::GET, &mut rng).is_none());
+
+        let trash = find("/admin/quiz/trash/empty").unwrap();
+        let case = trash.valid_case(&Method::DELETE, &mut rng).unwrap();
+        assert_eq!(case.body, None);
+        assert_eq!(case.query[0].0, "quizIds");
+        assert_eq!(trash.path_param("quizid"), None);
+    }
+
+    #[test]
+    fn routes_are_found_by_their_camel_case_name() {
+        assert_eq!(route_name("/admin/quiz/{quizid}/name"), "adminQuizName");
+        assert_eq!(route_name("/clear"), "clear");
+        assert_eq!(
+            find_by_name(&Method::PUT, "ADMINQUIZNAME"),
+            Some("/admin/quiz/{quizid}/name")
+        );
+        assert_eq!(find_by_name(&Method::GET, "adminQuizName"), None);
+    }
+}
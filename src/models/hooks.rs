@@ -0,0 +1,67 @@
+use crate::models::client::TestCase;
+use reqwest::Method;
+
+pub const CLEAR_ENDPOINT: &str = "/clear";
+
+// Requests sent to both servers around every test case, typically to reset
+// server state so one case can't influence the next.
+#[derive(Debug, Clone, Default)]
+pub struct Hooks {
+    pub before: Vec<TestCase>,
+    pub after: Vec<TestCase>,
+}
+
+impl Hooks {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Resets both servers with `DELETE /clear` before each case.
+    pub fn clear() -> Self {
+        Self::new().with_before(clear_case(CLEAR_ENDPOINT))
+    }
+
+    pub fn with_before(mut self, case: TestCase) -> Self {
+        self.before.push(case);
+        self
+    }
+
+    pub fn with_after(mut self, case: TestCase) -> Self {
+        self.after.push(case);
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.before.is_empty() && self.after.is_empty()
+    }
+}
+
+pub fn clear_case(endpoint: &str) -> TestCase {
+    TestCase::new(endpoint, Method::DELETE, None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clear_resets_before_each_case() {
+        let hooks = Hooks::clear();
+        assert!(!hooks.is_empty());
+        assert!(hooks.after.is_empty());
+        assert_eq!(hooks.before[0].method, Method::DELETE);
+        assert_eq!(hooks.before[0].endpoint, CLEAR_ENDPOINT);
+        assert!(Hooks::new().is_empty());
+    }
+
+    #[test]
+    fn hooks_run_in_the_order_they_were_added() {
+        let hooks = Hooks::new()
+            .with_before(clear_case("/first"))
+            .with_before(clear_case("/second"))
+            .with_after(clear_case("/last"));
+        let endpoints: Vec<&str> = hooks.before.iter().map(|c| c.endpoint.as_str()).collect();
+        assert_eq!(endpoints, ["/first", "/second"]);
+        assert_eq!(hooks.after[0].endpoint, "/last");
+    }
+}
@@ -0,0 +1,296 @@
+use crate::models::pointer::pointer_child;
+use serde_json::{Map, Value};
+use std::fmt;
+
+// Deeper bodies are rejected, as `serde_json` does.
+const MAX_DEPTH: usize = 128;
+
+// Something in a JSON body that `serde_json::Value` either hides (duplicate
+// keys collapse to the last one) or refuses outright.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JsonIssue {
+    DuplicateKey { pointer: String, key: String },
+    // `NaN`, `Infinity` or `-Infinity`, read as null.
+    NonFinite { pointer: String, literal: String },
+    // Anything but whitespace after the first value, which is kept.
+    TrailingData { offset: usize },
+}
+
+impl JsonIssue {
+    // Whether both servers made the same mistake; trailing data needn't start
+    // at the same byte.
+    pub fn same(&self, other: &JsonIssue) -> bool {
+        match (self, other) {
+            (JsonIssue::TrailingData { .. }, JsonIssue::TrailingData { .. }) => true,
+            _ => self == other,
+        }
+    }
+}
+
+impl fmt::Display for JsonIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JsonIssue::DuplicateKey { pointer, key } => {
+                write!(f, "duplicate key `{}` in `{}`", key, display(pointer))
+            }
+            JsonIssue::NonFinite { pointer, literal } => {
+                write!(f, "non-standard `{}` at `{}`", literal, display(pointer))
+            }
+            JsonIssue::TrailingData { offset } => {
+                write!(f, "trailing data after the value at byte {}", offset)
+            }
+        }
+    }
+}
+
+fn display(pointer: &str) -> &str {
+    if pointer.is_empty() {
+        "/"
+    } else {
+        pointer
+    }
+}
+
+// A body read more leniently than `serde_json` would, with whatever it had to
+// overlook.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Scanned {
+    pub value: Value,
+    pub issues: Vec<JsonIssue>,
+}
+
+// `None` when `bytes` isn't JSON even allowing for the issues above.
+pub fn scan(bytes: &[u8]) -> Option<Scanned> {
+    let mut scanner = Scanner {
+        bytes,
+        at: 0,
+        issues: Vec::new(),
+    };
+    scanner.whitespace();
+    let value = scanner.value("", 0)?;
+    scanner.whitespace();
+    if scanner.at < bytes.len() {
+        scanner
+            .issues
+            .push(JsonIssue::TrailingData { offset: scanner.at });
+    }
+    Some(Scanned {
+        value,
+        issues: scanner.issues,
+    })
+}
+
+struct Scanner<'a> {
+    bytes: &'a [u8],
+    at: usize,
+    issues: Vec<JsonIssue>,
+}
+
+impl Scanner<'_> {
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.at).copied()
+    }
+
+    fn whitespace(&mut self) {
+        while matches!(self.peek(), Some(b' ' | b'\t' | b'\n' | b'\r')) {
+            self.at += 1;
+        }
+    }
+
+    fn literal(&mut self, literal: &str) -> bool {
+        let matched = self.bytes[self.at..].starts_with(literal.as_bytes());
+        if matched {
+            self.at += literal.len();
+        }
+        matched
+    }
+
+    fn value(&mut self, pointer: &str, depth: usize) -> Option<Value> {
+        match self.peek()? {
+            b'{' if depth < MAX_DEPTH => self.object(pointer, depth),
+            b'[' if depth < MAX_DEPTH => self.array(pointer, depth),
+            b'"' => self.string().map(Value::String),
+            b't' if self.literal("true") => Some(Value::Bool(true)),
+            b'f' if self.literal("false") => Some(Value::Bool(false)),
+            b'n' if self.literal("null") => Some(Value::Null),
+            _ => {
+                for literal in ["NaN", "Infinity", "-Infinity"] {
+                    if self.literal(literal) {
+                        self.issues.push(JsonIssue::NonFinite {
+                            pointer: pointer.to_string(),
+                            literal: literal.to_string(),
+                        });
+                        return Some(Value::Null);
+                    }
+                }
+                self.number()
+            }
+        }
+    }
+
+    fn object(&mut self, pointer: &str, depth: usize) -> Option<Value> {
+        self.at += 1;
+        let mut fields = Map::new();
+        self.whitespace();
+        if self.peek()? == b'}' {
+            self.at += 1;
+            return Some(Value::Object(fields));
+        }
+        loop {
+            self.whitespace();
+            let key = self.string()?;
+            self.whitespace();
+            if self.peek()? != b':' {
+                return None;
+            }
+            self.at += 1;
+            self.whitespace();
+            let value = self.value(&pointer_child(pointer, &key), depth + 1)?;
+            // The last one wins, as with `serde_json`.
+            if fields.insert(key.clone(), value).is_some() {
+                self.issues.push(JsonIssue::DuplicateKey {
+                    pointer: pointer.to_string(),
+                    key,
+                });
+            }
+            self.whitespace();
+            match self.peek()? {
+                b',' => self.at += 1,
+                b'}' => {
+                    self.at += 1;
+                    return Some(Value::Object(fields));
+                }
+                _ => return None,
+            }
+        }
+    }
+
+    fn array(&mut self, pointer: &str, depth: usize) -> Option<Value> {
+        self.at += 1;
+        let mut items = Vec::new();
+        self.whitespace();
+        if self.peek()? == b']' {
+            self.at += 1;
+            return Some(Value::Array(items));
+        }
+        loop {
+            self.whitespace();
+            let index = items.len().to_string();
+            items.push(self.value(&pointer_child(pointer, &index), depth + 1)?);
+            self.whitespace();
+            match self.peek()? {
+                b',' => self.at += 1,
+                b']' => {
+                    self.at += 1;
+                    return Some(Value::Array(items));
+                }
+                _ => return None,
+            }
+        }
+    }
+
+    // The string's extent is found here; escapes are left to `serde_json`.
+    fn string(&mut self) -> Option<String> {
+        if self.peek()? != b'"' {
+            return None;
+        }
+        let start = self.at;
+        self.at += 1;
+        loop {
+            match self.peek()? {
+                b'"' => break,
+                b'\\' => self.at += 2,
+                _ => self.at += 1,
+            }
+        }
+        self.at += 1;
+        serde_json::from_slice(&self.bytes[start..self.at]).ok()
+    }
+
+    fn number(&mut self) -> Option<Value> {
+        let start = self.at;
+        while matches!(
+            self.peek(),
+            Some(b'0'..=b'9' | b'-' | b'+' | b'.' | b'e' | b'E')
+        ) {
+            self.at += 1;
+        }
+        if self.at == start {
+            return None;
+        }
+        serde_json::from_slice(&self.bytes[start..self.at]).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn issues(body: &str) -> Vec<JsonIssue> {
+        scan(body.as_bytes()).unwrap().issues
+    }
+
+    #[test]
+    fn clean_bodies_read_as_serde_json_reads_them() {
+        let body = r#" { "a": [1, -2.5e3, "x\"y"], "b": { "c": null, "d": true } } "#;
+        let scanned = scan(body.as_bytes()).unwrap();
+        assert_eq!(scanned.value, serde_json::from_str::<Value>(body).unwrap());
+        assert!(scanned.issues.is_empty());
+        for broken in ["", "{", r#"{"a" 1}"#, "[1,]", "tru", r#""open"#] {
+            assert_eq!(scan(broken.as_bytes()), None, "{:?}", broken);
+        }
+    }
+
+    #[test]
+    fn hidden_and_refused_issues_are_noted_where_they_are() {
+        let scanned = scan(br#"{"a": 1, "b": {"c": NaN}, "a": 2}"#).unwrap();
+        assert_eq!(scanned.value, json!({ "a": 2, "b": { "c": null } }));
+        assert_eq!(
+            scanned.issues,
+            [
+                JsonIssue::NonFinite {
+                    pointer: "/b/c".to_string(),
+                    literal: "NaN".to_string()
+                },
+                JsonIssue::DuplicateKey {
+                    pointer: String::new(),
+                    key: "a".to_string()
+                },
+            ]
+        );
+        assert_eq!(
+            issues("[-Infinity]"),
+            [JsonIssue::NonFinite {
+                pointer: "/0".to_string(),
+                literal: "-Infinity".to_string()
+            }]
+        );
+        assert_eq!(issues("{} \n{}"), [JsonIssue::TrailingData { offset: 4 }]);
+        assert_eq!(
+            issues(r#"{"a": 1, "a": 1}"#)[0].to_string(),
+            "duplicate key `a` in `/`"
+        );
+    }
+
+    #[test]
+    fn trailing_data_anywhere_is_the_same_mistake() {
+        let early = JsonIssue::TrailingData { offset: 2 };
+        let late = JsonIssue::TrailingData { offset: 9 };
+        assert!(early.same(&late));
+        let duplicate = |key: &str| JsonIssue::DuplicateKey {
+            pointer: String::new(),
+            key: key.to_string(),
+        };
+        assert!(duplicate("a").same(&duplicate("a")));
+        assert!(!duplicate("a").same(&duplicate("b")));
+    }
+
+    #[test]
+    fn bodies_nested_too_deep_are_refused() {
+        let deep = format!("{}{}", "[".repeat(MAX_DEPTH + 1), "]".repeat(MAX_DEPTH + 1));
+        assert_eq!(scan(deep.as_bytes()), None);
+        let shallow = format!("{}{}", "[".repeat(MAX_DEPTH), "]".repeat(MAX_DEPTH));
+        assert!(scan(shallow.as_bytes()).is_some());
+    }
+}
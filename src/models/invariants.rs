@@ -0,0 +1,186 @@
+use crate::corpus::RecordedRequest;
+use crate::models::assertion::Finding;
+use crate::models::client::{ServerResponse, TestCase};
+use crate::spec::template_matches;
+use reqwest::Method;
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+// The fields each create route mints, and the kind of ID they are. A
+// duplicated question's `newQuestionId` is a question ID like any other, and
+// logging in mints a token but hands back an existing `authUserId`.
+const CREATED: &[(&str, &str, &str)] = &[
+    ("/admin/auth/register", "token", "token"),
+    ("/admin/auth/register", "authUserId", "authUserId"),
+    ("/admin/auth/login", "token", "token"),
+    ("/admin/quiz", "quizId", "quizId"),
+    ("/admin/quiz/{quizid}/question", "questionId", "questionId"),
+    (
+        "/admin/quiz/{quizid}/question/{questionid}/duplicate",
+        "newQuestionId",
+        "questionId",
+    ),
+];
+
+// Built-in checks spanning several calls to one server, fed its responses in
+// the order they came back:
+//
+// - every ID a create route returns is new, and
+// - the same GET sent twice with no other request in between returns the
+//   same status and body.
+//
+// Servers are checked independently, each with its own `Invariants`, so both
+// hold even where the servers legitimately hand out different IDs.
+#[derive(Debug, Default)]
+pub struct Invariants {
+    // Each ID returned so far, by kind, with the route that returned it.
+    created: BTreeMap<&'static str, BTreeMap<String, String>>,
+    // The last response to each GET since the last other request, by the
+    // request's content hash.
+    reads: BTreeMap<u64, (u16, Value)>,
+}
+
+impl Invariants {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn observe(&mut self, case: &TestCase, response: &ServerResponse) -> Vec<Finding> {
+        let mut findings = Vec::new();
+        let route = format!("{} {}", case.method, case.endpoint);
+        // A cleared server may hand out the same IDs again.
+        if case.method == Method::DELETE && template_matches("/clear", &case.endpoint) {
+            self.created.clear();
+        }
+        if case.method != Method::GET {
+            self.reads.clear();
+            if (200..300).contains(&response.status) && case.method == Method::POST {
+                self.check_created(case, &route, &response.body, &mut findings);
+            }
+            return findings;
+        }
+
+        let key = RecordedRequest::new(case, 0).content_hash();
+        let current = (response.status, response.body.clone());
+        if let Some((status, body)) = self.reads.insert(key, current) {
+            if status != response.status || body != response.body {
+                findings.push(Finding::new(format!(
+                    "{} changed with no write in between: {} {} then {} {}",
+                    route, status, body, response.status, response.body
+                )));
+            }
+        }
+        findings
+    }
+
+    fn check_created(
+        &mut self,
+        case: &TestCase,
+        route: &str,
+        body: &Value,
+        findings: &mut Vec<Finding>,
+    ) {
+        for (template, field, kind) in CREATED {
+            if !template_matches(template, &case.endpoint) {
+                continue;
+            }
+            let Some(id) = body.get(field).filter(|id| !id.is_null()) else {
+                continue;
+            };
+            let id = id.to_string();
+            let seen = self.created.entry(kind).or_default();
+            match seen.get(&id) {
+                Some(earlier) => findings.push(Finding::new(format!(
+                    "{} returned {} {}, which {} already returned",
+                    route, field, id, earlier
+                ))),
+                None => {
+                    seen.insert(id, route.to_string());
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runner::tests::response;
+    use serde_json::json;
+
+    fn observe(
+        invariants: &mut Invariants,
+        method: Method,
+        endpoint: &str,
+        body: Value,
+    ) -> Vec<String> {
+        let case = TestCase::new(endpoint, method, None);
+        invariants
+            .observe(&case, &response(200, body, 0))
+            .into_iter()
+            .map(|finding| finding.message)
+            .collect()
+    }
+
+    #[test]
+    fn created_ids_must_be_new_until_the_server_is_cleared() {
+        let mut invariants = Invariants::new();
+        let question = "/admin/quiz/1/question";
+        assert!(observe(
+            &mut invariants,
+            Method::POST,
+            question,
+            json!({"questionId": 5})
+        )
+        .is_empty());
+        // A quiz ID is no question ID.
+        assert!(observe(
+            &mut invariants,
+            Method::POST,
+            "/admin/quiz",
+            json!({"quizId": 5})
+        )
+        .is_empty());
+        assert_eq!(
+            observe(
+                &mut invariants,
+                Method::POST,
+                "/admin/quiz/1/question/5/duplicate",
+                json!({"newQuestionId": 5})
+            ),
+            ["POST /admin/quiz/1/question/5/duplicate returned newQuestionId 5, which POST /admin/quiz/1/question already returned"]
+        );
+        observe(&mut invariants, Method::DELETE, "/clear", json!({}));
+        assert!(observe(
+            &mut invariants,
+            Method::POST,
+            question,
+            json!({"questionId": 5})
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn repeated_reads_must_match_unless_something_was_written() {
+        let mut invariants = Invariants::new();
+        let list = "/admin/quiz/list";
+        assert!(observe(&mut invariants, Method::GET, list, json!({"quizzes": []})).is_empty());
+        assert_eq!(
+            observe(&mut invariants, Method::GET, list, json!({"quizzes": [1]})),
+            ["GET /admin/quiz/list changed with no write in between: 200 {\"quizzes\":[]} then 200 {\"quizzes\":[1]}"]
+        );
+        observe(
+            &mut invariants,
+            Method::POST,
+            "/admin/quiz",
+            json!({"quizId": 2}),
+        );
+        assert!(observe(
+            &mut invariants,
+            Method::GET,
+            list,
+            json!({"quizzes": [1, 2]})
+        )
+        .is_empty());
+    }
+}
@@ -1 +1,18 @@
-pub mod client;
\ No newline at end of file
+pub mod assertion;
+pub mod auth;
+pub mod body;
+pub mod client;
+pub mod compare;
+pub mod endpoints;
+pub mod hooks;
+pub mod hygiene;
+pub mod invariants;
+pub mod multi;
+pub mod network;
+pub mod observer;
+pub mod oracle;
+pub mod pointer;
+pub mod rate_limit;
+pub mod retry;
+pub mod stream;
+pub mod transport;
@@ -0,0 +1,197 @@
+use crate::models::client::{Comparison, NotReady, ServerResponse, TestCase, Tester, TesterError};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+// One case as answered by the reference and then by every candidate.
+#[derive(Debug)]
+pub struct MultiComparison {
+    pub reference: Result<ServerResponse, TesterError>,
+    // In candidate order; empty when the reference itself failed.
+    pub candidates: Vec<Comparison>,
+}
+
+// Marks many servers against a single reference. Each case is sent to the
+// reference once and its response is shared by every candidate, which are
+// then compared concurrently.
+#[derive(Clone)]
+pub struct MultiTester {
+    reference: Tester,
+    candidates: Vec<(String, Tester)>,
+}
+
+impl MultiTester {
+    // Every server gets `template`'s settings (comparison mode, hooks, auth,
+    // timeouts, ...); its own URLs are not used. The reference is reached the
+    // way `template` reaches its reference, and candidates the way it reaches
+    // the server under test.
+    pub fn new(
+        template: &Tester,
+        reference_url: String,
+        candidates: impl IntoIterator<Item = (String, String)>,
+    ) -> Self {
+        Self {
+            reference: template
+                .for_reference()
+                .unwrap_or_else(|| template.clone())
+                .for_server(reference_url),
+            candidates: candidates
+                .into_iter()
+                .map(|(name, url)| (name, template.for_server(url)))
+                .collect(),
+        }
+    }
+
+    pub fn candidates(&self) -> impl Iterator<Item = &str> {
+        self.candidates.iter().map(|(name, _)| name.as_str())
+    }
+
+    // `Tester::wait_ready` for the reference and every candidate in turn, all
+    // within the one `timeout`, with the candidate's name in the error.
+    pub async fn wait_ready(&self, timeout: Duration) -> Result<(), NotReady> {
+        let deadline = Instant::now() + timeout;
+        let servers = std::iter::once(("reference server".to_string(), &self.reference)).chain(
+            self.candidates
+                .iter()
+                .map(|(name, tester)| (format!("candidate `{}`", name), tester)),
+        );
+        for (server, tester) in servers {
+            tester
+                .wait_ready(deadline.saturating_duration_since(Instant::now()))
+                .await
+                .map_err(|err| NotReady {
+                    server,
+                    waited: timeout,
+                    ..err
+                })?;
+        }
+        Ok(())
+    }
+
+    pub async fn compare_case(&self, case: &TestCase) -> MultiComparison {
+        let reference = match self.reference.fetch(case).await {
+            Ok(reference) => reference,
+            Err(err) => {
+                return MultiComparison {
+                    reference: Err(err),
+                    candidates: Vec::new(),
+                }
+            }
+        };
+
+        let mut tasks = JoinSet::new();
+        for (index, (_, tester)) in self.candidates.iter().enumerate() {
+            let tester = tester.clone();
+            let case = case.clone();
+            let expected = reference.clone();
+            tasks.spawn(async move { (index, tester.compare_against(&case, expected).await) });
+        }
+        let mut candidates = Vec::with_capacity(tasks.len());
+        while let Some(joined) = tasks.join_next().await {
+            candidates.push(joined.expect("candidate comparison panicked"));
+        }
+        candidates.sort_by_key(|(index, _)| *index);
+
+        MultiComparison {
+            reference: Ok(reference),
+            candidates: candidates
+                .into_iter()
+                .map(|(_, comparison)| comparison)
+                .collect(),
+        }
+    }
+
+    // Results come back in the same order as `cases`. Cases run one at a time
+    // while hooks are set, as with `Tester::compare_all`.
+    pub async fn compare_all(&self, cases: Vec<TestCase>) -> Vec<(TestCase, MultiComparison)> {
+        let permits = if self.reference.hooks().is_empty() {
+            self.reference.concurrency()
+        } else {
+            1
+        };
+        let semaphore = Arc::new(Semaphore::new(permits));
+        let mut tasks = JoinSet::new();
+
+        for (index, case) in cases.into_iter().enumerate() {
+            let tester = self.clone();
+            let semaphore = semaphore.clone();
+            tasks.spawn(async move {
+                let _permit = semaphore.acquire_owned().await.expect("semaphore closed");
+                let comparison = tester.compare_case(&case).await;
+                (index, case, comparison)
+            });
+        }
+
+        let mut results = Vec::with_capacity(tasks.len());
+        while let Some(joined) = tasks.join_next().await {
+            results.push(joined.expect("comparison task panicked"));
+        }
+        results.sort_by_key(|(index, _, _)| *index);
+        results
+            .into_iter()
+            .map(|(_, case, comparison)| (case, comparison))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::client::tests::raw_server;
+    use reqwest::Method;
+
+    const QUIZ: &str = "HTTP/1.1 200 OK\r\ncontent-length: 9\r\n\r\n{\"id\": 1}";
+    const STRING_ID: &str = "HTTP/1.1 200 OK\r\ncontent-length: 11\r\n\r\n{\"id\": \"1\"}";
+
+    #[tokio::test]
+    async fn every_candidate_is_marked_against_one_reference_response() {
+        let template = Tester::new(String::new(), String::new());
+        let multi = MultiTester::new(
+            &template,
+            raw_server(QUIZ).await,
+            [
+                ("good".to_string(), raw_server(QUIZ).await),
+                ("bad".to_string(), raw_server(STRING_ID).await),
+                ("down".to_string(), "http://127.0.0.1:1".to_string()),
+            ],
+        );
+        assert_eq!(
+            multi.candidates().collect::<Vec<_>>(),
+            ["good", "bad", "down"]
+        );
+
+        let comparison = multi
+            .compare_case(&TestCase::new("/quiz", Method::GET, None))
+            .await;
+        assert_eq!(comparison.reference.unwrap().body["id"], 1);
+        let outcomes: Vec<_> = comparison.candidates.iter().map(|c| &c.outcome).collect();
+        assert!(outcomes[0].is_ok());
+        assert!(matches!(outcomes[1], Err(TesterError::TypeMismatch { .. })));
+        assert!(matches!(outcomes[2], Err(TesterError::Network { .. })));
+    }
+
+    #[tokio::test]
+    async fn candidates_are_skipped_when_the_reference_fails() {
+        let template = Tester::new(String::new(), String::new());
+        let multi = MultiTester::new(
+            &template,
+            "http://127.0.0.1:1".to_string(),
+            [("good".to_string(), raw_server(QUIZ).await)],
+        );
+        let cases = vec![
+            TestCase::new("/a", Method::GET, None),
+            TestCase::new("/b", Method::GET, None),
+        ];
+        let results = multi.compare_all(cases).await;
+        let endpoints: Vec<&str> = results
+            .iter()
+            .map(|(case, _)| case.endpoint.as_str())
+            .collect();
+        assert_eq!(endpoints, ["/a", "/b"]);
+        for (_, comparison) in results {
+            assert!(comparison.reference.is_err());
+            assert!(comparison.candidates.is_empty());
+        }
+    }
+}
@@ -0,0 +1,44 @@
+use reqwest::{Certificate, ClientBuilder, Proxy};
+
+// How requests reach one server, for targets behind a proxy or serving HTTPS
+// with a certificate the system doesn't trust.
+#[derive(Debug, Clone, Default)]
+pub struct Network {
+    proxy: Option<Proxy>,
+    roots: Vec<Certificate>,
+    accept_invalid_certs: bool,
+}
+
+impl Network {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Both HTTP and HTTPS requests go through `proxy`.
+    pub fn with_proxy(mut self, proxy: Proxy) -> Self {
+        self.proxy = Some(proxy);
+        self
+    }
+
+    // Trusted along with the system's roots.
+    pub fn with_root_certificate(mut self, certificate: Certificate) -> Self {
+        self.roots.push(certificate);
+        self
+    }
+
+    // Accepts any certificate, expired, self-signed or for another host.
+    pub fn with_invalid_certs(mut self) -> Self {
+        self.accept_invalid_certs = true;
+        self
+    }
+
+    pub(crate) fn apply(&self, mut builder: ClientBuilder) -> ClientBuilder {
+        if let Some(proxy) = &self.proxy {
+            builder = builder.proxy(proxy.clone());
+        }
+        for certificate in &self.roots {
+            builder = builder.add_root_certificate(certificate.clone());
+        }
+        builder.danger_accept_invalid_certs(self.accept_invalid_certs)
+    }
+}
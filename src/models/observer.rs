@@ -0,0 +1,19 @@
+use crate::models::client::{ServerResponse, TestCase, TesterError};
+
+// Watches every request a `RequestClient` sends, for mirroring traffic into
+// metrics, tracing spans or logs of one's own. Observers only look: they run
+// inline, outside the comparison, and can't change what is sent or received.
+pub trait Observer: Send + Sync {
+    // Before the request goes out. `server` is the client's base URL.
+    fn on_request(&self, _server: &str, _case: &TestCase) {}
+
+    // Once the whole response is in, or the request failed for good (after
+    // any retries).
+    fn on_response(
+        &self,
+        _server: &str,
+        _case: &TestCase,
+        _response: Result<&ServerResponse, &TesterError>,
+    ) {
+    }
+}
@@ -0,0 +1,93 @@
+use crate::models::client::TestCase;
+use crate::schema::Schema;
+use crate::spec::template_matches;
+use reqwest::Method;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expectation {
+    pub status: u16,
+    pub body: Schema,
+}
+
+impl Expectation {
+    pub fn new(status: u16) -> Self {
+        Self {
+            status,
+            body: Schema::Any,
+        }
+    }
+
+    pub fn with_body(mut self, schema: Schema) -> Self {
+        self.body = schema;
+        self
+    }
+}
+
+// An in-process model of the reference server, used in place of a second live
+// server. Returning `None` means the model has no opinion on the case, which
+// then only has to satisfy the spec (if any).
+pub trait Oracle: Send + Sync {
+    fn expect(&self, case: &TestCase) -> Option<Expectation>;
+}
+
+// Fixed expectations per (method, path template); the first matching rule wins.
+#[derive(Debug, Clone, Default)]
+pub struct StaticOracle {
+    rules: Vec<(Method, String, Expectation)>,
+}
+
+impl StaticOracle {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_rule(
+        mut self,
+        method: Method,
+        template: impl Into<String>,
+        expectation: Expectation,
+    ) -> Self {
+        self.rules.push((method, template.into(), expectation));
+        self
+    }
+}
+
+impl Oracle for StaticOracle {
+    fn expect(&self, case: &TestCase) -> Option<Expectation> {
+        self.rules
+            .iter()
+            .find(|(method, template, _)| {
+                *method == case.method && template_matches(template, &case.endpoint)
+            })
+            .map(|(_, _, expectation)| expectation.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_first_matching_rule_wins() {
+        let oracle = StaticOracle::new()
+            .with_rule(Method::GET, "/quiz/trash", Expectation::new(200))
+            .with_rule(
+                Method::GET,
+                "/quiz/{id}",
+                Expectation::new(400).with_body(Schema::Object {
+                    properties: Default::default(),
+                    required: Vec::new(),
+                }),
+            )
+            .with_rule(Method::GET, "/quiz/{id}", Expectation::new(500));
+
+        let expect = |method, endpoint| oracle.expect(&TestCase::new(endpoint, method, None));
+        assert_eq!(
+            expect(Method::GET, "/quiz/trash"),
+            Some(Expectation::new(200))
+        );
+        assert_eq!(expect(Method::GET, "/quiz/7?token=a").unwrap().status, 400);
+        assert_eq!(expect(Method::DELETE, "/quiz/7"), None);
+        assert_eq!(expect(Method::GET, "/quiz/7/question"), None);
+    }
+}
@@ -0,0 +1,152 @@
+use serde::Deserialize;
+use std::fmt;
+
+// Appends a reference token to a JSON pointer, escaping it per RFC 6901.
+pub fn pointer_child(path: &str, token: &str) -> String {
+    format!("{}/{}", path, token.replace('~', "~0").replace('/', "~1"))
+}
+
+fn unescape(token: &str) -> String {
+    token.replace("~1", "/").replace("~0", "~")
+}
+
+// A JSON pointer where a `*` token matches any single key or array index,
+// e.g. `/quizzes/*/quizId`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(from = "String")]
+pub struct PointerPattern {
+    tokens: Vec<String>,
+}
+
+impl PointerPattern {
+    // The leading `/` is optional; `""` is the whole document and `/` its
+    // empty-named key, as in RFC 6901.
+    pub fn parse(pattern: &str) -> Self {
+        let tokens = match pattern {
+            "" => Vec::new(),
+            _ => pattern
+                .strip_prefix('/')
+                .unwrap_or(pattern)
+                .split('/')
+                .map(unescape)
+                .collect(),
+        };
+        Self { tokens }
+    }
+
+    pub fn matches(&self, path: &str) -> bool {
+        let tokens = path_tokens(path);
+        tokens.len() == self.tokens.len() && self.matches_tokens(&tokens)
+    }
+
+    // True if `path` is the matched location itself or anywhere below it.
+    pub fn contains(&self, path: &str) -> bool {
+        let tokens = path_tokens(path);
+        tokens.len() >= self.tokens.len() && self.matches_tokens(&tokens)
+    }
+
+    // True if the pattern ends in `*` right below `path`, i.e. matches every
+    // key or index of the value there.
+    pub fn matches_every_child(&self, path: &str) -> bool {
+        let tokens = path_tokens(path);
+        self.tokens.len() == tokens.len() + 1
+            && self.tokens.last().is_some_and(|last| last == "*")
+            && self.matches_tokens(&tokens)
+    }
+
+    fn matches_tokens(&self, tokens: &[String]) -> bool {
+        self.tokens
+            .iter()
+            .zip(tokens)
+            .all(|(pattern, token)| pattern == "*" || pattern == token)
+    }
+}
+
+fn path_tokens(path: &str) -> Vec<String> {
+    match path.strip_prefix('/') {
+        Some(rest) => rest.split('/').map(unescape).collect(),
+        None => Vec::new(),
+    }
+}
+
+impl From<String> for PointerPattern {
+    fn from(pattern: String) -> Self {
+        Self::parse(&pattern)
+    }
+}
+
+impl From<&str> for PointerPattern {
+    fn from(pattern: &str) -> Self {
+        Self::parse(pattern)
+    }
+}
+
+impl fmt::Display for PointerPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for token in &self.tokens {
+            write!(f, "/{}", token.replace('~', "~0").replace('/', "~1"))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokens_are_escaped_and_unescaped() {
+        assert_eq!(pointer_child("", "a"), "/a");
+        assert_eq!(pointer_child("/a", "b/c"), "/a/b~1c");
+        assert_eq!(pointer_child("/a", "~1"), "/a/~01");
+
+        let pattern = PointerPattern::parse("/a~1b/~01");
+        assert!(pattern.matches(&pointer_child(&pointer_child("", "a/b"), "~1")));
+        assert!(!pattern.matches("/a/b/~1"));
+        assert_eq!(pattern.to_string(), "/a~1b/~01");
+    }
+
+    #[test]
+    fn the_leading_slash_is_optional() {
+        assert_eq!(
+            PointerPattern::parse("quizId"),
+            PointerPattern::parse("/quizId")
+        );
+        assert!(PointerPattern::parse("").matches(""));
+        assert!(!PointerPattern::parse("").matches("/"));
+        // `/` is the key named "", not the root.
+        assert!(PointerPattern::parse("/").matches("/"));
+        assert!(!PointerPattern::parse("/").matches(""));
+    }
+
+    #[test]
+    fn wildcards_match_exactly_one_token() {
+        let pattern = PointerPattern::from("/quizzes/*/quizId");
+        assert!(pattern.matches("/quizzes/0/quizId"));
+        assert!(pattern.matches("/quizzes/name/quizId"));
+        assert!(!pattern.matches("/quizzes/quizId"));
+        assert!(!pattern.matches("/quizzes/0/1/quizId"));
+        assert!(!pattern.matches("/quizzes/0/quizId/x"));
+    }
+
+    #[test]
+    fn contains_covers_everything_below_a_match() {
+        let pattern = PointerPattern::from("/quizzes/*");
+        assert!(pattern.contains("/quizzes/3"));
+        assert!(pattern.contains("/quizzes/3/questions/0"));
+        assert!(!pattern.contains("/quizzes"));
+        assert!(!pattern.contains("/users/3"));
+        assert!(PointerPattern::from("").contains("/anything"));
+    }
+
+    #[test]
+    fn every_child_needs_a_trailing_wildcard() {
+        assert!(PointerPattern::from("/items/*").matches_every_child("/items"));
+        assert!(PointerPattern::from("/*/*").matches_every_child("/items"));
+        assert!(PointerPattern::from("/*").matches_every_child(""));
+        assert!(!PointerPattern::from("/items/0").matches_every_child("/items"));
+        assert!(!PointerPattern::from("/items/*").matches_every_child("/other"));
+        assert!(!PointerPattern::from("/items/*/id").matches_every_child("/items"));
+        assert!(!PointerPattern::from("/items").matches_every_child("/items"));
+    }
+}
@@ -0,0 +1,152 @@
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio::time::Instant;
+
+// A token bucket per server: up to `burst` requests may start at once, after
+// which they are spaced out to `requests_per_second`.
+#[derive(Debug)]
+pub struct RateLimiter {
+    requests_per_second: f64,
+    burst: f64,
+    state: Mutex<Bucket>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    // Negative while requests are queued for tokens not yet refilled.
+    tokens: f64,
+    refilled_at: Instant,
+    // Set after a 429 so no request goes out before the server asked.
+    paused_until: Option<Instant>,
+}
+
+impl RateLimiter {
+    pub fn new(requests_per_second: f64) -> Self {
+        Self::with_burst(requests_per_second, 1)
+    }
+
+    pub fn with_burst(requests_per_second: f64, burst: u32) -> Self {
+        let burst = f64::from(burst.max(1));
+        Self {
+            requests_per_second: requests_per_second.max(f64::MIN_POSITIVE),
+            burst,
+            state: Mutex::new(Bucket {
+                tokens: burst,
+                refilled_at: Instant::now(),
+                paused_until: None,
+            }),
+        }
+    }
+
+    pub async fn acquire(&self) {
+        let start = {
+            let mut bucket = self.state.lock().await;
+            let now = Instant::now();
+            let elapsed = now.duration_since(bucket.refilled_at).as_secs_f64();
+            bucket.tokens = (bucket.tokens + elapsed * self.requests_per_second).min(self.burst);
+            bucket.refilled_at = now;
+            bucket.tokens -= 1.0;
+
+            let wait =
+                Duration::from_secs_f64((-bucket.tokens).max(0.0) / self.requests_per_second);
+            let start = now + wait;
+            bucket
+                .paused_until
+                .map_or(start, |paused| start.max(paused))
+        };
+        tokio::time::sleep_until(start).await;
+    }
+
+    // Holds back every request through this limiter for `duration`.
+    pub async fn pause(&self, duration: Duration) {
+        let mut bucket = self.state.lock().await;
+        let until = Instant::now() + duration;
+        bucket.paused_until = Some(
+            bucket
+                .paused_until
+                .map_or(until, |paused| paused.max(until)),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn acquires_are_spaced_by_the_interval() {
+        let limiter = RateLimiter::new(50.0);
+        let start = Instant::now();
+        for _ in 0..5 {
+            limiter.acquire().await;
+        }
+        // The first slot is immediate, the other four 20ms apart.
+        let elapsed = start.elapsed();
+        assert!(elapsed >= Duration::from_millis(80), "{:?}", elapsed);
+        assert!(elapsed < Duration::from_millis(500), "{:?}", elapsed);
+    }
+
+    #[tokio::test]
+    async fn concurrent_acquires_get_distinct_slots() {
+        let limiter = Arc::new(RateLimiter::new(100.0));
+        let start = Instant::now();
+        let tasks: Vec<_> = (0..4)
+            .map(|_| {
+                let limiter = limiter.clone();
+                tokio::spawn(async move {
+                    limiter.acquire().await;
+                    start.elapsed()
+                })
+            })
+            .collect();
+        let mut times = Vec::new();
+        for task in tasks {
+            times.push(task.await.unwrap());
+        }
+        times.sort();
+        for pair in times.windows(2) {
+            assert!(pair[1] - pair[0] >= Duration::from_millis(9), "{:?}", times);
+        }
+    }
+
+    #[tokio::test]
+    async fn idle_time_isnt_saved_up_as_a_burst() {
+        let limiter = RateLimiter::new(20.0);
+        limiter.acquire().await;
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        let start = Instant::now();
+        limiter.acquire().await;
+        limiter.acquire().await;
+        assert!(start.elapsed() >= Duration::from_millis(45));
+    }
+
+    #[tokio::test]
+    async fn a_burst_goes_out_at_once_then_the_rate_applies() {
+        let limiter = RateLimiter::with_burst(20.0, 3);
+        let start = Instant::now();
+        for _ in 0..3 {
+            limiter.acquire().await;
+        }
+        assert!(start.elapsed() < Duration::from_millis(30));
+        limiter.acquire().await;
+        assert!(start.elapsed() >= Duration::from_millis(45));
+    }
+
+    #[tokio::test]
+    async fn pauses_hold_back_every_request() {
+        let limiter = Arc::new(RateLimiter::with_burst(1000.0, 10));
+        let start = Instant::now();
+        limiter.pause(Duration::from_millis(100)).await;
+        // A shorter pause doesn't cut the longer one short.
+        limiter.pause(Duration::from_millis(10)).await;
+        let waiting = limiter.clone();
+        let other = tokio::spawn(async move {
+            waiting.acquire().await;
+            start.elapsed()
+        });
+        limiter.acquire().await;
+        assert!(start.elapsed() >= Duration::from_millis(100));
+        assert!(other.await.unwrap() >= Duration::from_millis(100));
+    }
+}
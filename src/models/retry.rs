@@ -0,0 +1,78 @@
+use std::time::Duration;
+
+// Retries requests that never reached the server (connection refused, ...) and
+// GET, HEAD and OPTIONS requests that timed out, and separately ones answered
+// with 429 Too Many Requests. The delay doubles after each attempt, up to
+// `max_backoff`; a 429's `Retry-After` is used instead when present, but no
+// longer than `max_backoff` either.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub max_rate_limited: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl RetryPolicy {
+    pub fn new(max_retries: u32) -> Self {
+        Self {
+            max_retries,
+            ..Self::default()
+        }
+    }
+
+    pub fn none() -> Self {
+        Self::new(0)
+    }
+
+    pub fn with_max_rate_limited(mut self, retries: u32) -> Self {
+        self.max_rate_limited = retries;
+        self
+    }
+
+    pub fn with_initial_backoff(mut self, backoff: Duration) -> Self {
+        self.initial_backoff = backoff;
+        self
+    }
+
+    pub fn with_max_backoff(mut self, backoff: Duration) -> Self {
+        self.max_backoff = backoff;
+        self
+    }
+
+    // Delay before retry number `attempt`, counting from zero.
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt);
+        self.initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff)
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 0,
+            max_rate_limited: 0,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(5),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_doubles_up_to_the_cap() {
+        let policy = RetryPolicy::new(10)
+            .with_initial_backoff(Duration::from_millis(100))
+            .with_max_backoff(Duration::from_millis(500));
+        let delays: Vec<u128> = (0..5).map(|n| policy.backoff(n).as_millis()).collect();
+        assert_eq!(delays, [100, 200, 400, 500, 500]);
+
+        // Far past the point where the factor overflows.
+        assert_eq!(policy.backoff(u32::MAX), Duration::from_millis(500));
+    }
+}
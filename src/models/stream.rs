@@ -0,0 +1,994 @@
+use crate::models::client::{TestCase, TesterError};
+use crate::models::compare::{ArrayMode, CompareOptions, ComparisonMode, NumericOptions};
+use crate::models::pointer::pointer_child;
+use bytes::Bytes;
+use reqwest::Response;
+use serde::de::IgnoredAny;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+// Bytes of each body looked at a time, however the server chunks it.
+const LOOKAHEAD: usize = 4096;
+// Of a string value, kept only to be shown should it differ.
+const SHOWN: usize = 256;
+// Deeper nesting is refused, as `serde_json` refuses it.
+const MAX_DEPTH: usize = 128;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Kind {
+    Object,
+    Array,
+    String,
+    Number,
+    Bool,
+    Null,
+}
+
+// Why a body couldn't be read to the end.
+#[derive(Debug)]
+pub enum Fault {
+    Read(reqwest::Error),
+    TooLarge(usize),
+    // Not JSON; with what was in the lookahead buffer when that was found.
+    Malformed(Vec<u8>),
+}
+
+// How `compare` stopped short.
+#[derive(Debug)]
+pub enum Failure {
+    Diverged(TesterError),
+    Client(Fault),
+    Actual(Fault),
+}
+
+// One response body: what has been read of it, then whatever of `rest` is
+// still to arrive. `limit` bounds the whole body.
+pub struct Body {
+    head: Bytes,
+    rest: Option<Response>,
+    limit: Option<usize>,
+    read: usize,
+}
+
+impl Body {
+    pub fn new(head: Vec<u8>, rest: Option<Response>, limit: Option<usize>) -> Self {
+        Self {
+            read: head.len(),
+            head: Bytes::from(head),
+            rest,
+            limit,
+        }
+    }
+
+    async fn chunk(&mut self) -> Result<Option<Bytes>, Fault> {
+        if !self.head.is_empty() {
+            return Ok(Some(std::mem::take(&mut self.head)));
+        }
+        let Some(response) = &mut self.rest else {
+            return Ok(None);
+        };
+        let Some(chunk) = response.chunk().await.map_err(Fault::Read)? else {
+            self.rest = None;
+            return Ok(None);
+        };
+        self.read += chunk.len();
+        match self.limit {
+            Some(limit) if self.read > limit => Err(Fault::TooLarge(limit)),
+            _ => Ok(Some(chunk)),
+        }
+    }
+}
+
+// A body seen through a fixed-size window, refilled from the chunks as they
+// arrive, so no token needs more than the window in memory to be read.
+struct Reader {
+    body: Body,
+    chunk: Bytes,
+    window: Box<[u8]>,
+    at: usize,
+    len: usize,
+}
+
+impl Reader {
+    fn new(body: Body, lookahead: usize) -> Self {
+        Self {
+            body,
+            chunk: Bytes::new(),
+            window: vec![0; lookahead].into_boxed_slice(),
+            at: 0,
+            len: 0,
+        }
+    }
+
+    async fn peek(&mut self) -> Result<Option<u8>, Fault> {
+        if self.at == self.len {
+            self.refill().await?;
+        }
+        Ok(self.window[..self.len].get(self.at).copied())
+    }
+
+    async fn refill(&mut self) -> Result<(), Fault> {
+        (self.at, self.len) = (0, 0);
+        while self.chunk.is_empty() {
+            match self.body.chunk().await? {
+                Some(chunk) => self.chunk = chunk,
+                None => return Ok(()),
+            }
+        }
+        let len = self.chunk.len().min(self.window.len());
+        self.window[..len].copy_from_slice(&self.chunk.split_to(len));
+        self.len = len;
+        Ok(())
+    }
+
+    fn bump(&mut self) {
+        self.at += 1;
+    }
+
+    fn malformed(&self) -> Fault {
+        Fault::Malformed(self.window[..self.len].to_vec())
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Event {
+    Open(Kind),
+    Close,
+    Key(String),
+    Scalar(Scalar),
+    End,
+}
+
+// `text` is the scalar as it was sent, or for a long string, only its start.
+#[derive(Debug, PartialEq)]
+struct Scalar {
+    kind: Kind,
+    text: Vec<u8>,
+    cut: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Expect {
+    Value,
+    ItemOrClose,
+    KeyOrClose,
+    Key,
+    CommaOrClose,
+    End,
+}
+
+// A pull parser handing out one event at a time. It checks the body is JSON
+// as it goes, since nothing has checked it before.
+struct Tokens {
+    reader: Reader,
+    open: Vec<Kind>,
+    expect: Expect,
+}
+
+impl Tokens {
+    fn new(body: Body, lookahead: usize) -> Self {
+        Self {
+            reader: Reader::new(body, lookahead),
+            open: Vec::new(),
+            expect: Expect::Value,
+        }
+    }
+
+    async fn next(&mut self) -> Result<Event, Fault> {
+        loop {
+            let byte = self.whitespace().await?;
+            let inside = self.open.last().copied();
+            match (self.expect, byte) {
+                (Expect::End, None) => return Ok(Event::End),
+                (Expect::CommaOrClose, _) if inside.is_none() => self.expect = Expect::End,
+                (Expect::CommaOrClose, Some(b',')) => {
+                    self.reader.bump();
+                    self.expect = match inside {
+                        Some(Kind::Object) => Expect::Key,
+                        _ => Expect::Value,
+                    };
+                }
+                (Expect::CommaOrClose | Expect::KeyOrClose, Some(b'}'))
+                    if inside == Some(Kind::Object) =>
+                {
+                    return Ok(self.close())
+                }
+                (Expect::CommaOrClose | Expect::ItemOrClose, Some(b']'))
+                    if inside == Some(Kind::Array) =>
+                {
+                    return Ok(self.close())
+                }
+                (Expect::Key | Expect::KeyOrClose, Some(b'"')) => return self.key().await,
+                (Expect::Value | Expect::ItemOrClose, Some(byte)) => return self.value(byte).await,
+                _ => return Err(self.reader.malformed()),
+            }
+        }
+    }
+
+    async fn whitespace(&mut self) -> Result<Option<u8>, Fault> {
+        loop {
+            match self.reader.peek().await? {
+                Some(b' ' | b'\t' | b'\n' | b'\r') => self.reader.bump(),
+                byte => return Ok(byte),
+            }
+        }
+    }
+
+    fn close(&mut self) -> Event {
+        self.reader.bump();
+        self.open.pop();
+        self.expect = Expect::CommaOrClose;
+        Event::Close
+    }
+
+    async fn key(&mut self) -> Result<Event, Fault> {
+        let mut text = Vec::new();
+        self.string(&mut text, usize::MAX).await?;
+        if self.whitespace().await? != Some(b':') {
+            return Err(self.reader.malformed());
+        }
+        self.reader.bump();
+        self.expect = Expect::Value;
+        let key = serde_json::from_slice(&text).map_err(|_| self.reader.malformed())?;
+        Ok(Event::Key(key))
+    }
+
+    async fn value(&mut self, byte: u8) -> Result<Event, Fault> {
+        if let b'{' | b'[' = byte {
+            if self.open.len() == MAX_DEPTH {
+                return Err(self.reader.malformed());
+            }
+            self.reader.bump();
+            let (kind, expect) = match byte {
+                b'{' => (Kind::Object, Expect::KeyOrClose),
+                _ => (Kind::Array, Expect::ItemOrClose),
+            };
+            self.open.push(kind);
+            self.expect = expect;
+            return Ok(Event::Open(kind));
+        }
+        let mut text = Vec::new();
+        let mut cut = false;
+        let kind = match byte {
+            b'"' => {
+                cut = self.string(&mut text, SHOWN).await?;
+                Kind::String
+            }
+            b't' => self.literal(&mut text, b"true", Kind::Bool).await?,
+            b'f' => self.literal(&mut text, b"false", Kind::Bool).await?,
+            b'n' => self.literal(&mut text, b"null", Kind::Null).await?,
+            b'-' | b'0'..=b'9' => {
+                while let Some(byte @ (b'-' | b'+' | b'.' | b'e' | b'E' | b'0'..=b'9')) =
+                    self.reader.peek().await?
+                {
+                    text.push(byte);
+                    self.reader.bump();
+                }
+                if serde_json::from_slice::<IgnoredAny>(&text).is_err() {
+                    return Err(self.reader.malformed());
+                }
+                Kind::Number
+            }
+            _ => return Err(self.reader.malformed()),
+        };
+        self.expect = Expect::CommaOrClose;
+        Ok(Event::Scalar(Scalar { kind, text, cut }))
+    }
+
+    async fn literal(
+        &mut self,
+        text: &mut Vec<u8>,
+        literal: &[u8],
+        kind: Kind,
+    ) -> Result<Kind, Fault> {
+        for &expected in literal {
+            if self.reader.peek().await? != Some(expected) {
+                return Err(self.reader.malformed());
+            }
+            self.reader.bump();
+        }
+        text.extend_from_slice(literal);
+        Ok(kind)
+    }
+
+    // From the opening quote to past the closing one, keeping the first
+    // `keep` bytes in `text`; whether any were left out.
+    async fn string(&mut self, text: &mut Vec<u8>, keep: usize) -> Result<bool, Fault> {
+        let mut cut = false;
+        let mut push = |text: &mut Vec<u8>, byte| {
+            cut |= text.len() >= keep;
+            if !cut {
+                text.push(byte);
+            }
+            cut
+        };
+        self.reader.bump();
+        push(text, b'"');
+        loop {
+            let Some(byte) = self.reader.peek().await? else {
+                return Err(self.reader.malformed());
+            };
+            self.reader.bump();
+            let cut = push(text, byte);
+            match byte {
+                b'"' => return Ok(cut),
+                b'\\' => {
+                    let escaped = self.reader.peek().await?;
+                    let digits = match escaped {
+                        Some(b'"' | b'\\' | b'/' | b'b' | b'f' | b'n' | b'r' | b't') => 0,
+                        Some(b'u') => 4,
+                        _ => return Err(self.reader.malformed()),
+                    };
+                    self.reader.bump();
+                    push(text, escaped.unwrap_or_default());
+                    for _ in 0..digits {
+                        match self.reader.peek().await? {
+                            Some(digit) if digit.is_ascii_hexdigit() => {
+                                self.reader.bump();
+                                push(text, digit);
+                            }
+                            _ => return Err(self.reader.malformed()),
+                        }
+                    }
+                }
+                0..=0x1f => return Err(self.reader.malformed()),
+                _ => {}
+            }
+        }
+    }
+}
+
+// Whether `compare` finds a difference exactly when decoding both bodies
+// would: types only, element-wise arrays and no per-field or numeric checks.
+pub fn supports(options: &CompareOptions) -> bool {
+    options.mode == ComparisonMode::TypesOnly
+        && options.arrays == ArrayMode::ElementWise
+        && options.fields.is_empty()
+        && options.numeric == NumericOptions::default()
+}
+
+// Compares two JSON bodies by type as both arrive, a token from each at a
+// time, and returns at the first difference without reading either further.
+// Needs `supports(options)`.
+pub async fn compare(
+    client: Body,
+    actual: Body,
+    case: &TestCase,
+    options: &CompareOptions,
+) -> Result<(), Failure> {
+    Walker::new(client, actual, case, options, LOOKAHEAD)
+        .run()
+        .await
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Side {
+    Client,
+    Actual,
+}
+
+struct Frame {
+    kind: Kind,
+    path: String,
+    index: usize,
+}
+
+// What's kept of an object entry once the two objects' keys stop lining up.
+struct Entry {
+    print: u64,
+    shown: Value,
+    null: bool,
+}
+
+// A value's types being hashed, one container at a time.
+struct Print {
+    kind: Kind,
+    path: String,
+    index: usize,
+    key: Option<String>,
+    items: DefaultHasher,
+    entries: u64,
+}
+
+impl Print {
+    fn new(kind: Kind, path: String) -> Self {
+        Self {
+            kind,
+            path,
+            index: 0,
+            key: None,
+            items: DefaultHasher::new(),
+            entries: 0,
+        }
+    }
+
+    // Entries are summed so their order doesn't matter; items are hashed in
+    // turn so it does.
+    fn add(&mut self, print: u64) {
+        match self.key.take() {
+            Some(key) => self.entries = self.entries.wrapping_add(hash(&(key, print))),
+            None => print.hash(&mut self.items),
+        }
+    }
+
+    fn finish(mut self) -> u64 {
+        match self.kind {
+            Kind::Object => hash(&(Kind::Object, self.entries)),
+            _ => {
+                self.index.hash(&mut self.items);
+                hash(&(Kind::Array, self.items.finish()))
+            }
+        }
+    }
+}
+
+fn hash(value: &impl Hash) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+// The value an event starts, as far as it's known: scalars are decoded,
+// containers are shown empty.
+fn shown(event: &Event) -> Value {
+    match event {
+        Event::Open(Kind::Object) => Value::Object(Default::default()),
+        Event::Open(_) => Value::Array(Vec::new()),
+        Event::Scalar(scalar) if scalar.cut => {
+            Value::String(format!("{}…", String::from_utf8_lossy(&scalar.text[1..])))
+        }
+        Event::Scalar(scalar) => serde_json::from_slice(&scalar.text).unwrap_or(Value::Null),
+        _ => Value::Null,
+    }
+}
+
+// Walks both bodies in step. While two objects' keys come in the same order,
+// their values are compared in place; from the first key that doesn't line up,
+// the rest of each object is read into a hash of its values' types per key,
+// and those are compared instead.
+struct Walker<'a> {
+    case: &'a TestCase,
+    options: &'a CompareOptions,
+    client: Tokens,
+    actual: Tokens,
+}
+
+impl<'a> Walker<'a> {
+    fn new(
+        client: Body,
+        actual: Body,
+        case: &'a TestCase,
+        options: &'a CompareOptions,
+        lookahead: usize,
+    ) -> Self {
+        Self {
+            case,
+            options,
+            client: Tokens::new(client, lookahead),
+            actual: Tokens::new(actual, lookahead),
+        }
+    }
+
+    async fn next(&mut self) -> Result<(Event, Event), Failure> {
+        let (client, actual) = tokio::join!(self.client.next(), self.actual.next());
+        Ok((
+            client.map_err(Failure::Client)?,
+            actual.map_err(Failure::Actual)?,
+        ))
+    }
+
+    async fn next_on(&mut self, side: Side) -> Result<Event, Failure> {
+        match side {
+            Side::Client => self.client.next().await.map_err(Failure::Client),
+            Side::Actual => self.actual.next().await.map_err(Failure::Actual),
+        }
+    }
+
+    async fn run(mut self) -> Result<(), Failure> {
+        let mut frames = Vec::new();
+        let (a, b) = self.next().await?;
+        self.value(a, b, String::new(), &mut frames).await?;
+        while let Some(frame) = frames.last_mut() {
+            let (a, b) = self.next().await?;
+            match (frame.kind, a, b) {
+                (_, Event::Close, Event::Close) => {
+                    frames.pop();
+                }
+                (Kind::Array, a, b) if (a == Event::Close) != (b == Event::Close) => {
+                    let (path, shorter) = (frame.path.clone(), frame.index);
+                    let (client_len, actual_len) = match a {
+                        Event::Close => (shorter, shorter + self.count(Side::Actual, b).await?),
+                        a => (shorter + self.count(Side::Client, a).await?, shorter),
+                    };
+                    return Err(Failure::Diverged(TesterError::ArrayLengthMismatch {
+                        endpoint: self.case.endpoint.clone(),
+                        method: self.case.method.clone(),
+                        path,
+                        client_len,
+                        actual_len,
+                    }));
+                }
+                (Kind::Array, a, b) => {
+                    let path = pointer_child(&frame.path, &frame.index.to_string());
+                    frame.index += 1;
+                    self.value(a, b, path, &mut frames).await?;
+                }
+                (_, Event::Key(key_a), Event::Key(key_b)) if key_a == key_b => {
+                    let path = pointer_child(&frame.path, &key_a);
+                    let (a, b) = self.next().await?;
+                    self.value(a, b, path, &mut frames).await?;
+                }
+                (_, a, b) => {
+                    let frame = frames.pop().expect("an object is open");
+                    self.unordered(&frame.path, a, b).await?;
+                }
+            }
+        }
+        // Both are read to the end so trailing garbage is still caught.
+        self.next().await?;
+        Ok(())
+    }
+
+    async fn value(
+        &mut self,
+        a: Event,
+        b: Event,
+        path: String,
+        frames: &mut Vec<Frame>,
+    ) -> Result<(), Failure> {
+        let skipped = match (&a, &b) {
+            _ if self.options.is_ignored(&path) => true,
+            (Event::Open(Kind::Array), Event::Open(Kind::Array))
+                if self.options.ignores_every_index(&path) =>
+            {
+                true
+            }
+            (Event::Open(kind_a), Event::Open(kind_b)) if kind_a == kind_b => {
+                frames.push(Frame {
+                    kind: *kind_a,
+                    path,
+                    index: 0,
+                });
+                false
+            }
+            (Event::Scalar(scalar_a), Event::Scalar(scalar_b))
+                if scalar_a.kind == scalar_b.kind =>
+            {
+                false
+            }
+            _ => return Err(self.mismatch(&path, shown(&a), shown(&b))),
+        };
+        if skipped {
+            self.skip(Side::Client, a).await?;
+            self.skip(Side::Actual, b).await?;
+        }
+        Ok(())
+    }
+
+    // Reads past the value `first` starts.
+    async fn skip(&mut self, side: Side, first: Event) -> Result<(), Failure> {
+        let mut depth = usize::from(matches!(first, Event::Open(_)));
+        while depth > 0 {
+            match self.next_on(side).await? {
+                Event::Open(_) => depth += 1,
+                Event::Close => depth -= 1,
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    // Items left in an array, `first` starting the next.
+    async fn count(&mut self, side: Side, first: Event) -> Result<usize, Failure> {
+        let mut count = 0;
+        let mut event = first;
+        while event != Event::Close {
+            self.skip(side, event).await?;
+            count += 1;
+            event = self.next_on(side).await?;
+        }
+        Ok(count)
+    }
+
+    // `a` and `b` are what each object had next, a key or its end, and don't
+    // match. The entries already compared in step aren't compared again.
+    async fn unordered(&mut self, path: &str, a: Event, b: Event) -> Result<(), Failure> {
+        let entries_a = self.entries(Side::Client, path, a).await?;
+        let entries_b = self.entries(Side::Actual, path, b).await?;
+        for (key, entry_a) in &entries_a {
+            let child = pointer_child(path, key);
+            match entries_b.get(key) {
+                Some(entry_b) if entry_a.print == entry_b.print => {}
+                Some(entry_b) => {
+                    return Err(self.mismatch(&child, entry_a.shown.clone(), entry_b.shown.clone()))
+                }
+                None if self.absent_matches(&child, entry_a) => {}
+                None => return Err(self.mismatch(&child, entry_a.shown.clone(), Value::Null)),
+            }
+        }
+        for (key, entry_b) in &entries_b {
+            let child = pointer_child(path, key);
+            if !entries_a.contains_key(key) && !self.absent_matches(&child, entry_b) {
+                return Err(self.mismatch(&child, Value::Null, entry_b.shown.clone()));
+            }
+        }
+        Ok(())
+    }
+
+    // The rest of an object, `first` being its next key or its end. The last
+    // of duplicate keys wins, as with `serde_json`.
+    async fn entries(
+        &mut self,
+        side: Side,
+        path: &str,
+        first: Event,
+    ) -> Result<BTreeMap<String, Entry>, Failure> {
+        let mut entries = BTreeMap::new();
+        let mut event = first;
+        while let Event::Key(key) = event {
+            let child = pointer_child(path, &key);
+            let value = self.next_on(side).await?;
+            if self.options.is_ignored(&child) {
+                self.skip(side, value).await?;
+            } else {
+                let entry = Entry {
+                    shown: shown(&value),
+                    null: matches!(&value, Event::Scalar(scalar) if scalar.kind == Kind::Null),
+                    print: self.print(side, child, value).await?,
+                };
+                entries.insert(key, entry);
+            }
+            event = self.next_on(side).await?;
+        }
+        Ok(entries)
+    }
+
+    // A hash of the types throughout the value `first` starts, the same for
+    // two values exactly when `compare_json` would find them alike by type
+    // (but for collisions).
+    async fn print(&mut self, side: Side, path: String, first: Event) -> Result<u64, Failure> {
+        let mut open: Vec<Print> = Vec::new();
+        let mut value = Some((path, first));
+        let mut finished = None;
+        loop {
+            match value.take() {
+                Some((path, event @ Event::Open(Kind::Array)))
+                    if self.options.ignores_every_index(&path) =>
+                {
+                    self.skip(side, event).await?;
+                    finished = Some(hash(&Kind::Array));
+                }
+                Some((path, Event::Open(kind))) => open.push(Print::new(kind, path)),
+                Some((_, Event::Scalar(scalar))) => finished = Some(hash(&scalar.kind)),
+                _ => {}
+            }
+            if let Some(print) = finished.take() {
+                match open.last_mut() {
+                    Some(container) => container.add(print),
+                    None => return Ok(print),
+                }
+            }
+            let container = open.last_mut().expect("a container is open");
+            match self.next_on(side).await? {
+                Event::Close => finished = open.pop().map(Print::finish),
+                Event::Key(key) => {
+                    let child = pointer_child(&container.path, &key);
+                    let event = self.next_on(side).await?;
+                    let null = matches!(&event, Event::Scalar(scalar) if scalar.kind == Kind::Null);
+                    // Either way it's as if the entry weren't there.
+                    if self.options.is_ignored(&child) {
+                        self.skip(side, event).await?;
+                    } else if !(null && self.options.absent_matches(&child, &Value::Null)) {
+                        container.key = Some(key);
+                        value = Some((child, event));
+                    }
+                }
+                event => {
+                    let child = pointer_child(&container.path, &container.index.to_string());
+                    container.index += 1;
+                    if self.options.is_ignored(&child) {
+                        self.skip(side, event).await?;
+                        container.add(0);
+                    } else {
+                        value = Some((child, event));
+                    }
+                }
+            }
+        }
+    }
+
+    fn absent_matches(&self, path: &str, present: &Entry) -> bool {
+        present.null && self.options.absent_matches(path, &Value::Null)
+    }
+
+    fn mismatch(&self, path: &str, client_value: Value, actual_value: Value) -> Failure {
+        Failure::Diverged(TesterError::TypeMismatch {
+            endpoint: self.case.endpoint.clone(),
+            method: self.case.method.clone(),
+            path: path.to_string(),
+            client_value: Box::new(client_value),
+            actual_value: Box::new(actual_value),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::compare::compare_json;
+    use crate::runner::tests::response;
+    use reqwest::Method;
+    use serde_json::json;
+
+    fn body(json: &str) -> Body {
+        Body::new(json.as_bytes().to_vec(), None, None)
+    }
+
+    async fn events(json: &str, lookahead: usize) -> Result<Vec<Event>, Fault> {
+        let mut tokens = Tokens::new(body(json), lookahead);
+        let mut events = Vec::new();
+        loop {
+            match tokens.next().await? {
+                Event::End => return Ok(events),
+                event => events.push(event),
+            }
+        }
+    }
+
+    fn scalar(kind: Kind, text: &str) -> Event {
+        Event::Scalar(Scalar {
+            kind,
+            text: text.as_bytes().to_vec(),
+            cut: false,
+        })
+    }
+
+    fn case() -> TestCase {
+        TestCase::new("/admin/quiz/list", Method::GET, None)
+    }
+
+    fn options(ignored: &[&str], optional: &[&str]) -> CompareOptions {
+        CompareOptions {
+            ignored: ignored.iter().map(|&p| p.into()).collect(),
+            optional: optional.iter().map(|&p| p.into()).collect(),
+            ..Default::default()
+        }
+    }
+
+    async fn streamed(options: &CompareOptions, a: &str, b: &str) -> Result<(), Failure> {
+        Walker::new(body(a), body(b), &case(), options, 3)
+            .run()
+            .await
+    }
+
+    #[tokio::test]
+    async fn events_are_the_same_through_any_window() {
+        let json = r#" {"a\"b": [1, -2.5e3, "xé\n", true, null, {}], "c": []} "#;
+        let expected = vec![
+            Event::Open(Kind::Object),
+            Event::Key("a\"b".to_string()),
+            Event::Open(Kind::Array),
+            scalar(Kind::Number, "1"),
+            scalar(Kind::Number, "-2.5e3"),
+            scalar(Kind::String, r#""xé\n""#),
+            scalar(Kind::Bool, "true"),
+            scalar(Kind::Null, "null"),
+            Event::Open(Kind::Object),
+            Event::Close,
+            Event::Close,
+            Event::Key("c".to_string()),
+            Event::Open(Kind::Array),
+            Event::Close,
+            Event::Close,
+        ];
+        for lookahead in [1, 2, 3, 7, LOOKAHEAD] {
+            assert_eq!(events(json, lookahead).await.unwrap(), expected);
+        }
+    }
+
+    #[tokio::test]
+    async fn bodies_that_arent_json_are_faults() {
+        let deep = format!("{}{}", "[".repeat(MAX_DEPTH + 1), "]".repeat(MAX_DEPTH + 1));
+        for json in [
+            "",
+            r#"{"a" 1}"#,
+            r#"{"a": 1,}"#,
+            "[1,]",
+            "[1 2]",
+            "[1}",
+            "tru",
+            "nul",
+            r#""abc"#,
+            r#""\x""#,
+            r#""\u12g4""#,
+            "\"a\u{1}\"",
+            "01",
+            "1.",
+            "{} x",
+            r#"{"a": 1"#,
+            &deep,
+        ] {
+            assert!(
+                matches!(events(json, 2).await, Err(Fault::Malformed(_))),
+                "{:?} was taken for JSON",
+                json
+            );
+        }
+        let nested = format!("{}{}", "[".repeat(MAX_DEPTH), "]".repeat(MAX_DEPTH));
+        assert!(events(&nested, 2).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn long_strings_are_only_kept_to_be_shown() {
+        let long = "q".repeat(SHOWN * 2);
+        let events = events(&format!(r#"["{}"]"#, long), 5).await.unwrap();
+        let Event::Scalar(scalar) = &events[1] else {
+            panic!("expected a string, got {:?}", events[1]);
+        };
+        assert!(scalar.cut);
+        assert_eq!(scalar.text.len(), SHOWN);
+        let expected = format!("{}…", &long[..SHOWN - 1]);
+        assert_eq!(shown(&events[1]), Value::String(expected));
+    }
+
+    #[tokio::test]
+    async fn differences_are_found_where_decoding_finds_them() {
+        let lists = |names: &[&str]| {
+            let quizzes: Vec<String> = names
+                .iter()
+                .enumerate()
+                .map(|(id, name)| format!(r#"{{"quizId": {}, "name": {}}}"#, id, name))
+                .collect();
+            format!(r#"{{"quizzes": [{}]}}"#, quizzes.join(", "))
+        };
+        let default = options(&[], &[]);
+        let ignoring = options(&["/quizzes/*/name", "/meta"], &[]);
+        let optional = options(&[], &["/quizzes/*/name", "/x/quizzes/*/name"]);
+        let pairs = [
+            (
+                &default,
+                lists(&[r#""a""#, r#""b""#]),
+                lists(&[r#""c""#, r#""d""#]),
+                None,
+            ),
+            (
+                &default,
+                lists(&[r#""a""#, r#""b""#]),
+                lists(&[r#""c""#, "2"]),
+                Some("/quizzes/1/name"),
+            ),
+            (
+                &ignoring,
+                lists(&[r#""a""#, r#""b""#]),
+                lists(&[r#""c""#, "2"]),
+                None,
+            ),
+            (
+                &default,
+                r#"{"b": [1, {"c": "x"}], "a": null}"#.to_string(),
+                r#"{"a": null, "b": [2, {"c": "y"}]}"#.to_string(),
+                None,
+            ),
+            (
+                &default,
+                r#"{"b": [1, {"c": "x"}], "a": null}"#.to_string(),
+                r#"{"a": null, "b": [2, {"c": 3}]}"#.to_string(),
+                Some("/b"),
+            ),
+            (
+                &default,
+                r#"{"a": 1, "b": 2}"#.to_string(),
+                r#"{"a": 1}"#.to_string(),
+                Some("/b"),
+            ),
+            (
+                &ignoring,
+                r#"{"a": 1, "meta": {"x": 1}}"#.to_string(),
+                r#"{"meta": [1], "a": 1}"#.to_string(),
+                None,
+            ),
+            (
+                &optional,
+                r#"{"quizzes": [{"name": null, "quizId": 1}]}"#.to_string(),
+                r#"{"quizzes": [{"quizId": 2}]}"#.to_string(),
+                None,
+            ),
+            (
+                &default,
+                r#"{"quizzes": [{"name": null, "quizId": 1}]}"#.to_string(),
+                r#"{"quizzes": [{"quizId": 2}]}"#.to_string(),
+                Some("/quizzes/0/name"),
+            ),
+            (
+                &optional,
+                r#"{"x": {"quizzes": [{"quizId": 1, "name": null}]}, "y": 1}"#.to_string(),
+                r#"{"y": 2, "x": {"quizzes": [{"quizId": 2}]}}"#.to_string(),
+                None,
+            ),
+        ];
+        for (options, a, b, path) in pairs {
+            let decoded = compare_json(
+                &response(200, serde_json::from_str(&a).unwrap(), 0),
+                &response(200, serde_json::from_str(&b).unwrap(), 0),
+                &case(),
+                options,
+            );
+            let found = match streamed(options, &a, &b).await {
+                Ok(()) => None,
+                Err(Failure::Diverged(err)) => Some(err),
+                Err(failure) => panic!("{} vs {} failed: {:?}", a, b, failure),
+            };
+            assert_eq!(decoded.is_err(), found.is_some(), "{} vs {}", a, b);
+            let found_at = found.as_ref().and_then(|err| err.path());
+            assert_eq!(found_at, path, "{} vs {}", a, b);
+        }
+    }
+
+    #[tokio::test]
+    async fn arrays_of_different_lengths_are_counted_out() {
+        let failure = streamed(&options(&[], &[]), "[[1, 2], 3]", "[[1, 2, [3], {}], 3]").await;
+        match failure {
+            Err(Failure::Diverged(TesterError::ArrayLengthMismatch {
+                path,
+                client_len,
+                actual_len,
+                ..
+            })) => assert_eq!((path.as_str(), client_len, actual_len), ("/0", 2, 4)),
+            failure => panic!("expected an array length mismatch, got {:?}", failure),
+        }
+        let every = options(&["/items/*"], &[]);
+        assert!(streamed(&every, r#"{"items": [1]}"#, r#"{"items": []}"#)
+            .await
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn nothing_past_the_first_difference_is_read() {
+        // Whatever follows the difference would be a fault if it were read.
+        let failure = streamed(
+            &options(&[], &[]),
+            r#"{"quizzes": [{"quizId": 1}, ]]]"#,
+            r#"{"quizzes": [{"quizId": "1"}, }}}"#,
+        )
+        .await;
+        match failure {
+            Err(Failure::Diverged(TesterError::TypeMismatch {
+                path,
+                client_value,
+                actual_value,
+                ..
+            })) => {
+                assert_eq!(path, "/quizzes/0/quizId");
+                assert_eq!((*client_value, *actual_value), (json!(1), json!("1")));
+            }
+            failure => panic!("expected a type mismatch, got {:?}", failure),
+        }
+        // Which is only so when they differ.
+        assert!(matches!(
+            streamed(&options(&[], &[]), "[1, ]", "[2, ]").await,
+            Err(Failure::Client(Fault::Malformed(_)))
+        ));
+        assert!(matches!(
+            streamed(&options(&[], &[]), "[1] ", "[2] x").await,
+            Err(Failure::Actual(Fault::Malformed(_)))
+        ));
+    }
+
+    #[test]
+    fn only_type_comparisons_stream() {
+        assert!(supports(&CompareOptions::default()));
+        for options in [
+            CompareOptions {
+                mode: ComparisonMode::Exact,
+                ..Default::default()
+            },
+            CompareOptions {
+                arrays: ArrayMode::ElementSchema,
+                ..Default::default()
+            },
+        ] {
+            assert!(!supports(&options));
+        }
+    }
+}
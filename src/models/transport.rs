@@ -0,0 +1,210 @@
+use reqwest::{Client, Request, Response};
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use tower_service::Service;
+
+pub type TransportFuture<'a> =
+    Pin<Box<dyn Future<Output = Result<Response, reqwest::Error>> + Send + 'a>>;
+
+// Sends a built request to a server, however it is reached. Retries, rate
+// limits, timeouts and body size limits stay with the `RequestClient`; a
+// transport only carries one request and its response.
+pub trait Transport: Send + Sync {
+    fn execute(&self, request: Request) -> TransportFuture<'_>;
+}
+
+// Over HTTP, with the client's own cookie jar and timeout.
+impl Transport for Client {
+    fn execute(&self, request: Request) -> TransportFuture<'_> {
+        Box::pin(Client::execute(self, request))
+    }
+}
+
+type Handler = dyn Fn(http::Request<Vec<u8>>) -> Pin<Box<dyn Future<Output = http::Response<Vec<u8>>> + Send>>
+    + Send
+    + Sync;
+
+// Hands every request to a function in this process instead of a socket, for
+// servers embedded in Rust and for testing the fuzzer itself. The request
+// carries the full URL, including the client's base URL.
+pub struct InProcess {
+    handler: Box<Handler>,
+}
+
+impl InProcess {
+    pub fn new<F, Fut>(handler: F) -> Self
+    where
+        F: Fn(http::Request<Vec<u8>>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = http::Response<Vec<u8>>> + Send + 'static,
+    {
+        Self {
+            handler: Box::new(move |request| Box::pin(handler(request))),
+        }
+    }
+
+    // For handlers that answer straight away.
+    pub fn sync<F>(handler: F) -> Self
+    where
+        F: Fn(http::Request<Vec<u8>>) -> http::Response<Vec<u8>> + Send + Sync + 'static,
+    {
+        Self::new(move |request| std::future::ready(handler(request)))
+    }
+
+    // From a `tower` service, such as a web framework's router, taking and
+    // returning bodies as bytes. It is cloned for every request, as `tower`
+    // expects, and a service that fails answers 500 with the error as its
+    // plain-text body.
+    pub fn service<S>(service: S) -> Self
+    where
+        S: Service<http::Request<Vec<u8>>, Response = http::Response<Vec<u8>>>
+            + Clone
+            + Send
+            + Sync
+            + 'static,
+        S::Error: fmt::Display + Send,
+        S::Future: Send,
+    {
+        Self::new(move |request| {
+            let mut service = service.clone();
+            async move {
+                let response = match std::future::poll_fn(|cx| service.poll_ready(cx)).await {
+                    Ok(()) => service.call(request).await,
+                    Err(err) => Err(err),
+                };
+                response.unwrap_or_else(|err| {
+                    let mut failed = http::Response::new(err.to_string().into_bytes());
+                    *failed.status_mut() = http::StatusCode::INTERNAL_SERVER_ERROR;
+                    failed.headers_mut().insert(
+                        http::header::CONTENT_TYPE,
+                        http::HeaderValue::from_static("text/plain"),
+                    );
+                    failed
+                })
+            }
+        })
+    }
+}
+
+// Takes the request's body out as bytes.
+pub(crate) async fn take_body(request: &mut Request) -> Result<Vec<u8>, reqwest::Error> {
+    // Multipart bodies are streams; reading one as if it were a response body
+    // is the only way reqwest offers to collect it.
+    match request.body_mut().take() {
+        Some(body) => Ok(Response::from(http::Response::new(body))
+            .bytes()
+            .await?
+            .to_vec()),
+        None => Ok(Vec::new()),
+    }
+}
+
+impl Transport for InProcess {
+    fn execute(&self, mut request: Request) -> TransportFuture<'_> {
+        Box::pin(async move {
+            let body = take_body(&mut request).await?;
+            let mut forwarded = http::Request::new(body);
+            *forwarded.method_mut() = request.method().clone();
+            *forwarded.uri_mut() = request
+                .url()
+                .as_str()
+                .parse()
+                .expect("a parsed URL is a valid URI");
+            *forwarded.headers_mut() = request.headers().clone();
+            Ok(Response::from((self.handler)(forwarded).await))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::client::{RequestClient, TestCase, TesterError};
+    use crate::models::retry::RetryPolicy;
+    use reqwest::Method;
+    use serde_json::json;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::task::{Context, Poll};
+    use std::time::Duration;
+
+    #[derive(Clone)]
+    struct Router {
+        fail: bool,
+    }
+
+    impl Service<http::Request<Vec<u8>>> for Router {
+        type Response = http::Response<Vec<u8>>;
+        type Error = String;
+        type Future = std::future::Ready<Result<Self::Response, String>>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), String>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, request: http::Request<Vec<u8>>) -> Self::Future {
+            if self.fail {
+                return std::future::ready(Err("router is down".to_string()));
+            }
+            let body = json!({ "path": request.uri().path(), "sent": request.body().len() });
+            std::future::ready(Ok(http::Response::new(body.to_string().into_bytes())))
+        }
+    }
+
+    fn client(transport: impl Transport + 'static) -> RequestClient {
+        RequestClient::new("http://in-process".to_string())
+            .with_transport(Arc::new(transport))
+            .with_timeout(Duration::from_millis(100))
+    }
+
+    #[tokio::test]
+    async fn in_process_handlers_get_the_full_request() {
+        let list = TestCase::new("/admin/quiz/list", Method::GET, None);
+        let handler = InProcess::sync(|request| {
+            assert_eq!(request.uri(), "http://in-process/admin/quiz/list");
+            http::Response::new(br#"{"quizzes": []}"#.to_vec())
+        });
+        let response = client(handler).fetch(&list).await.unwrap();
+        assert_eq!(response.body, json!({ "quizzes": [] }));
+
+        let create = TestCase::new("/admin/quiz", Method::POST, Some(json!({ "name": "q" })));
+        let response = client(InProcess::service(Router { fail: false }))
+            .fetch(&create)
+            .await
+            .unwrap();
+        assert_eq!(response.body, json!({ "path": "/admin/quiz", "sent": 12 }));
+
+        let response = client(InProcess::service(Router { fail: true }))
+            .fetch(&list)
+            .await
+            .unwrap();
+        assert_eq!(response.status, 500);
+    }
+
+    #[tokio::test]
+    async fn hung_handlers_time_out_and_only_safe_methods_retry() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counted = calls.clone();
+        let hung = client(InProcess::new(move |_| {
+            counted.fetch_add(1, Ordering::SeqCst);
+            std::future::pending()
+        }))
+        .with_retry(RetryPolicy::new(1));
+
+        let list = TestCase::new("/admin/quiz/list", Method::GET, None);
+        match hung.fetch(&list).await {
+            Err(TesterError::Timeout { timeout, .. }) => {
+                assert_eq!(timeout, Duration::from_millis(100))
+            }
+            outcome => panic!("expected a timeout, got {:?}", outcome),
+        }
+        assert_eq!(calls.swap(0, Ordering::SeqCst), 2);
+
+        let create = TestCase::new("/admin/quiz", Method::POST, None);
+        assert!(matches!(
+            hung.fetch(&create).await,
+            Err(TesterError::Timeout { .. })
+        ));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+}
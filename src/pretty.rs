@@ -0,0 +1,273 @@
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+// Strings longer than this (in characters) are cut short.
+const MAX_STRING: usize = 60;
+// Arrays longer than this show only their first elements.
+const MAX_ITEMS: usize = 10;
+// Beyond this many cells the LCS table is too big; the lines are paired up in
+// order instead.
+const MAX_DIFF_CELLS: usize = 250_000;
+
+// `value` pretty-printed with sorted keys and with long strings and arrays cut
+// short, so big payloads stay readable and equal values print the same.
+pub fn canonical(value: &Value) -> String {
+    let mut out = String::new();
+    write_value(&mut out, value, Some(0));
+    out
+}
+
+// Like `canonical`, on a single line.
+pub fn compact(value: &Value) -> String {
+    let mut out = String::new();
+    write_value(&mut out, value, None);
+    out
+}
+
+// `indent` is `None` for single-line output.
+fn write_value(out: &mut String, value: &Value, indent: Option<usize>) {
+    match value {
+        Value::String(s) => write_string(out, s),
+        Value::Array(items) => {
+            let entries: Vec<(Option<&str>, &Value)> = items
+                .iter()
+                .take(MAX_ITEMS)
+                .map(|item| (None, item))
+                .collect();
+            let hidden = items.len() - entries.len();
+            write_block(out, ('[', ']'), &entries, hidden, indent);
+        }
+        Value::Object(fields) => {
+            let sorted: BTreeMap<&str, &Value> =
+                fields.iter().map(|(k, v)| (k.as_str(), v)).collect();
+            let entries: Vec<(Option<&str>, &Value)> =
+                sorted.into_iter().map(|(k, v)| (Some(k), v)).collect();
+            write_block(out, ('{', '}'), &entries, 0, indent);
+        }
+        other => out.push_str(&other.to_string()),
+    }
+}
+
+// `hidden` entries that were left out are noted after the rest.
+fn write_block(
+    out: &mut String,
+    (open, close): (char, char),
+    entries: &[(Option<&str>, &Value)],
+    hidden: usize,
+    indent: Option<usize>,
+) {
+    out.push(open);
+    if entries.is_empty() {
+        out.push(close);
+        return;
+    }
+    let separator = |out: &mut String, first: bool| {
+        if !first {
+            out.push(',');
+        }
+        match indent {
+            Some(level) => {
+                out.push('\n');
+                out.push_str(&"  ".repeat(level + 1));
+            }
+            None if !first => out.push(' '),
+            None => {}
+        }
+    };
+    for (index, (key, value)) in entries.iter().enumerate() {
+        separator(out, index == 0);
+        if let Some(key) = key {
+            let _ = write!(out, "{}: ", Value::from(*key));
+        }
+        write_value(out, value, indent.map(|level| level + 1));
+    }
+    if hidden > 0 {
+        separator(out, false);
+        let _ = write!(out, "... {} more", hidden);
+    }
+    if let Some(level) = indent {
+        out.push('\n');
+        out.push_str(&"  ".repeat(level));
+    }
+    out.push(close);
+}
+
+fn write_string(out: &mut String, s: &str) {
+    let length = s.chars().count();
+    if length <= MAX_STRING {
+        out.push_str(&Value::from(s).to_string());
+        return;
+    }
+    let kept: String = s.chars().take(MAX_STRING).collect();
+    let quoted = Value::from(kept).to_string();
+    let _ = write!(
+        out,
+        "{}...\" (+{} chars)",
+        &quoted[..quoted.len() - 1],
+        length - MAX_STRING
+    );
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Line<'a> {
+    Same(&'a str),
+    Removed(&'a str),
+    Added(&'a str),
+}
+
+// Line diff of `a` against `b` by longest common subsequence. Inputs too big
+// for that come out as all of `a` removed and all of `b` added.
+pub fn diff_lines<'a>(a: &'a str, b: &'a str) -> Vec<Line<'a>> {
+    let a: Vec<&str> = a.lines().collect();
+    let b: Vec<&str> = b.lines().collect();
+    if a.len().saturating_mul(b.len()) > MAX_DIFF_CELLS {
+        return a
+            .into_iter()
+            .map(Line::Removed)
+            .chain(b.into_iter().map(Line::Added))
+            .collect();
+    }
+
+    // Filled from the end so the walk below can go forwards.
+    let mut lcs = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i][j] = if a[i] == b[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut lines = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && a[i] == b[j] {
+            lines.push(Line::Same(a[i]));
+            i += 1;
+            j += 1;
+        } else if j >= b.len() || (i < a.len() && lcs[i + 1][j] >= lcs[i][j + 1]) {
+            lines.push(Line::Removed(a[i]));
+            i += 1;
+        } else {
+            lines.push(Line::Added(b[j]));
+            j += 1;
+        }
+    }
+    lines
+}
+
+// The canonical forms of `left` and `right` in two columns, marked like
+// `diff --side-by-side`: `|` for changed lines, `<` and `>` for lines on only
+// one side.
+pub fn side_by_side(left_label: &str, left: &Value, right_label: &str, right: &Value) -> String {
+    let (left, right) = (canonical(left), canonical(right));
+    let mut rows: Vec<(&str, char, &str)> = Vec::new();
+    let (mut removed, mut added) = (Vec::new(), Vec::new());
+    for line in diff_lines(&left, &right)
+        .into_iter()
+        .chain(std::iter::once(Line::Same("")))
+    {
+        match line {
+            Line::Removed(line) => removed.push(line),
+            Line::Added(line) => added.push(line),
+            Line::Same(line) => {
+                // A run of removals and additions pairs up into changed rows.
+                for k in 0..removed.len().max(added.len()) {
+                    let marker = match (removed.get(k), added.get(k)) {
+                        (Some(_), Some(_)) => '|',
+                        (Some(_), None) => '<',
+                        _ => '>',
+                    };
+                    rows.push((
+                        removed.get(k).copied().unwrap_or_default(),
+                        marker,
+                        added.get(k).copied().unwrap_or_default(),
+                    ));
+                }
+                removed.clear();
+                added.clear();
+                rows.push((line, ' ', line));
+            }
+        }
+    }
+    // The sentinel that flushed the last run.
+    rows.pop();
+
+    let width = rows
+        .iter()
+        .map(|(left, _, _)| left.chars().count())
+        .chain(std::iter::once(left_label.chars().count()))
+        .max()
+        .unwrap_or_default();
+    let mut out = format!("{:width$}   {}", left_label, right_label);
+    for (left, marker, right) in rows {
+        let _ = write!(out, "\n{:width$} {} {}", left, marker, right);
+        out.truncate(out.trim_end().len());
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn canonical_forms_sort_keys_and_cut_long_values() {
+        let value = json!({"b": [1, 2], "a": "x".repeat(65), "c": {}});
+        assert_eq!(
+            compact(&value),
+            format!(
+                "{{\"a\": \"{}...\" (+5 chars), \"b\": [1, 2], \"c\": {{}}}}",
+                "x".repeat(60)
+            )
+        );
+        assert_eq!(
+            canonical(&json!({"z": 1, "y": [true]})),
+            "{\n  \"y\": [\n    true\n  ],\n  \"z\": 1\n}"
+        );
+        assert_eq!(
+            compact(&json!((0..12).collect::<Vec<_>>())),
+            "[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, ... 2 more]"
+        );
+    }
+
+    #[test]
+    fn line_diffs_keep_the_common_lines() {
+        assert_eq!(
+            diff_lines("a\nb\nc", "a\nx\nc\nd"),
+            [
+                Line::Same("a"),
+                Line::Removed("b"),
+                Line::Added("x"),
+                Line::Same("c"),
+                Line::Added("d"),
+            ]
+        );
+    }
+
+    #[test]
+    fn side_by_side_marks_changed_and_one_sided_lines() {
+        let out = side_by_side(
+            "Client",
+            &json!({"id": "1", "name": "q"}),
+            "Actual",
+            &json!({"id": 1, "name": "q", "extra": null}),
+        );
+        assert_eq!(
+            out,
+            [
+                "Client          Actual",
+                "{               {",
+                "  \"id\": \"1\",  |   \"extra\": null,",
+                "              >   \"id\": 1,",
+                "  \"name\": \"q\"     \"name\": \"q\"",
+                "}               }",
+            ]
+            .join("\n")
+        );
+    }
+}
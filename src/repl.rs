@@ -0,0 +1,358 @@
+use crate::models::auth::AuthStrategy;
+use crate::models::client::{ServerResponse, TestCase, Tester};
+use crate::models::endpoints::{self, ApiVersion};
+use crate::pretty;
+use reqwest::Method;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::Arc;
+
+pub const HELP: &str = "\
+Commands:
+  <METHOD> <route> [json]  send to every server; <route> is a path like
+                           /admin/quiz/{quizid}/name or a name like
+                           adminQuizName. JSON fields go in the query for GET
+                           and DELETE.
+  vars                     show each server's session state
+  set <name> <value>       set a variable on every server
+  clear                    run the before hooks and forget all variables
+  help                     show this message
+  quit                     leave";
+
+// Any other word is taken for a mistyped command, not an extension method.
+const METHODS: [Method; 7] = [
+    Method::GET,
+    Method::POST,
+    Method::PUT,
+    Method::DELETE,
+    Method::PATCH,
+    Method::HEAD,
+    Method::OPTIONS,
+];
+
+// What `execute` wants done after printing its output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Flow {
+    Continue,
+    Quit,
+}
+
+// An interactive session against one or more servers. Each server keeps its
+// own variables: the top-level fields of its successful responses (`token`,
+// `quizId`, ...). Path parameters like `{quizid}` are filled from them, and
+// the latest `token` is sent to routes that need a session unless the command
+// gives one.
+pub struct Repl {
+    servers: Vec<(String, Tester, BTreeMap<String, Value>)>,
+    auth: Arc<dyn AuthStrategy>,
+}
+
+impl Repl {
+    pub fn new(version: Option<ApiVersion>) -> Self {
+        Self {
+            servers: Vec::new(),
+            auth: version.unwrap_or(ApiVersion::V1).auth_strategy(),
+        }
+    }
+
+    // Only `tester`'s server under test is used.
+    pub fn with_server(mut self, name: impl Into<String>, tester: Tester) -> Self {
+        self.servers.push((name.into(), tester, BTreeMap::new()));
+        self
+    }
+
+    pub fn with_auth(mut self, auth: Arc<dyn AuthStrategy>) -> Self {
+        self.auth = auth;
+        self
+    }
+
+    pub async fn execute(&mut self, line: &str) -> (String, Flow) {
+        let line = line.trim();
+        let (command, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let rest = rest.trim();
+        let output = match command {
+            "" => String::new(),
+            "quit" | "exit" => return (String::new(), Flow::Quit),
+            "help" => HELP.to_string(),
+            "vars" => self.vars(),
+            "set" => self.set(rest),
+            "clear" => self.clear().await,
+            _ => match METHODS
+                .iter()
+                .find(|m| m.as_str().eq_ignore_ascii_case(command))
+            {
+                Some(method) => self.send(method.clone(), rest).await,
+                None => format!("unknown command `{}` (try `help`)", command),
+            },
+        };
+        (output, Flow::Continue)
+    }
+
+    fn vars(&self) -> String {
+        let mut out = String::new();
+        for (server, _, vars) in &self.servers {
+            let _ = writeln!(out, "{}:", server);
+            if vars.is_empty() {
+                let _ = writeln!(out, "  (none)");
+            }
+            for (name, value) in vars {
+                let _ = writeln!(out, "  {} = {}", name, value);
+            }
+        }
+        out.trim_end().to_string()
+    }
+
+    fn set(&mut self, rest: &str) -> String {
+        let Some((name, value)) = rest.split_once(char::is_whitespace) else {
+            return "usage: set <name> <value>".to_string();
+        };
+        let value = value.trim();
+        let value = serde_json::from_str(value).unwrap_or_else(|_| Value::from(value));
+        for (_, _, vars) in &mut self.servers {
+            vars.insert(name.to_string(), value.clone());
+        }
+        format!("{} = {}", name, value)
+    }
+
+    async fn clear(&mut self) -> String {
+        let mut out = String::new();
+        for (server, tester, vars) in &mut self.servers {
+            vars.clear();
+            if let Err(err) = tester.reset().await {
+                let _ = writeln!(out, "{}: {}", server, err);
+            }
+        }
+        if out.is_empty() {
+            "cleared".to_string()
+        } else {
+            out.trim_end().to_string()
+        }
+    }
+
+    async fn send(&mut self, method: Method, rest: &str) -> String {
+        let (route, json) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+        let template = if route.starts_with('/') {
+            route.to_string()
+        } else {
+            match endpoints::find_by_name(&method, route) {
+                Some(path) => path.to_string(),
+                None => return format!("no route called `{}` takes {}", route, method),
+            }
+        };
+        let fields = match json.trim() {
+            "" => None,
+            json => match serde_json::from_str::<Value>(json) {
+                Ok(value) => Some(value),
+                Err(err) => return format!("invalid JSON: {}", err),
+            },
+        };
+
+        let mut responses = Vec::with_capacity(self.servers.len());
+        let mut out = String::new();
+        for (server, tester, vars) in &mut self.servers {
+            let case = match case(&method, &template, fields.clone(), vars, &*self.auth) {
+                Ok(case) => case,
+                Err(message) => {
+                    let _ = writeln!(out, "{}: {}", server, message);
+                    continue;
+                }
+            };
+            match tester.send(&case).await {
+                Ok(response) => {
+                    let _ = writeln!(
+                        out,
+                        "{}: {} ({:.1}ms)",
+                        server,
+                        response.status,
+                        response.latency.as_secs_f64() * 1000.0
+                    );
+                    remember(vars, &response);
+                    responses.push((server.as_str(), response));
+                }
+                Err(err) => {
+                    let _ = writeln!(out, "{}: {}", server, err);
+                }
+            }
+        }
+
+        match &responses[..] {
+            [(_, only)] => out.push_str(&shown(only)),
+            [(_, left), (_, right)] if left.body == right.body && left.raw == right.raw => {
+                out.push_str(&shown(left));
+                out.push_str("\n(bodies match)");
+            }
+            [(left_name, left), (right_name, right)]
+                if left.raw.is_some() || right.raw.is_some() =>
+            {
+                let _ = write!(
+                    out,
+                    "{}: {}\n{}: {}",
+                    left_name,
+                    shown(left),
+                    right_name,
+                    shown(right)
+                );
+            }
+            [(left_name, left), (right_name, right)] => out.push_str(&pretty::side_by_side(
+                left_name,
+                &left.body,
+                right_name,
+                &right.body,
+            )),
+            _ => {}
+        }
+        out.trim_end().to_string()
+    }
+}
+
+// The request for one server, with its own variables filled in.
+fn case(
+    method: &Method,
+    template: &str,
+    fields: Option<Value>,
+    vars: &BTreeMap<String, Value>,
+    auth: &dyn AuthStrategy,
+) -> Result<TestCase, String> {
+    let mut path = String::new();
+    for segment in template.split('/').filter(|s| !s.is_empty()) {
+        path.push('/');
+        match segment.strip_prefix('{').and_then(|s| s.strip_suffix('}')) {
+            Some(param) => {
+                let value = vars
+                    .iter()
+                    .find(|(name, _)| name.eq_ignore_ascii_case(param))
+                    .map(|(_, value)| value)
+                    .ok_or_else(|| {
+                        format!("no `{}` yet; set one with `set {} <value>`", param, param)
+                    })?;
+                match value {
+                    Value::String(s) => path.push_str(s),
+                    other => path.push_str(&other.to_string()),
+                }
+            }
+            None => path.push_str(segment),
+        }
+    }
+
+    let mut case = if *method == Method::GET || *method == Method::DELETE {
+        let query: Vec<(String, String)> = match fields {
+            Some(Value::Object(fields)) => fields
+                .into_iter()
+                .map(|(name, value)| match value {
+                    Value::String(s) => (name, s),
+                    other => (name, other.to_string()),
+                })
+                .collect(),
+            _ => Vec::new(),
+        };
+        TestCase::new(path.clone(), method.clone(), None).with_query(query)
+    } else {
+        TestCase::new(path.clone(), method.clone(), fields)
+    };
+    let needs_session = endpoints::resolve(&path).is_some_and(|endpoint| endpoint.requires_auth);
+    if let (true, Some(Value::String(token))) = (needs_session, vars.get("token")) {
+        auth.apply(token, &mut case);
+    }
+    Ok(case)
+}
+
+fn shown(response: &ServerResponse) -> String {
+    match &response.raw {
+        Some(raw) => format!("({})", raw),
+        None => pretty::canonical(&response.body),
+    }
+}
+
+fn remember(vars: &mut BTreeMap<String, Value>, response: &ServerResponse) {
+    if response.status != 200 {
+        return;
+    }
+    if let Value::Object(fields) = &response.body {
+        for (name, value) in fields {
+            if value.is_string() || value.is_number() {
+                vars.insert(name.clone(), value.clone());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runner::tests::response;
+    use serde_json::json;
+
+    #[test]
+    fn cases_fill_path_parameters_and_the_session() {
+        let auth = ApiVersion::V2.auth_strategy();
+        let mut vars = BTreeMap::new();
+        let fields = Some(json!({"name": "Quiz"}));
+        assert!(case(
+            &Method::PUT,
+            "/admin/quiz/{quizid}/name",
+            fields.clone(),
+            &vars,
+            &*auth
+        )
+        .unwrap_err()
+        .contains("no `quizid` yet"));
+
+        remember(
+            &mut vars,
+            &response(200, json!({"quizId": 5, "token": "t", "list": []}), 0),
+        );
+        remember(&mut vars, &response(400, json!({"quizId": 6}), 0));
+        assert_eq!(vars.keys().collect::<Vec<_>>(), ["quizId", "token"]);
+
+        let put = case(
+            &Method::PUT,
+            "/admin/quiz/{quizid}/name",
+            fields,
+            &vars,
+            &*auth,
+        )
+        .unwrap();
+        assert_eq!(put.endpoint, "/admin/quiz/5/name");
+        assert_eq!(put.body, Some(json!({"name": "Quiz"})));
+        assert_eq!(put.headers.get("token").unwrap(), "t");
+
+        let get = case(
+            &Method::GET,
+            "/admin/quiz/list",
+            Some(json!({"n": 1})),
+            &vars,
+            &*auth,
+        )
+        .unwrap();
+        assert_eq!(get.query, [("n".to_string(), "1".to_string())]);
+        // Routes that don't need a session don't get the token.
+        let clear = case(&Method::DELETE, "/clear", None, &vars, &*auth).unwrap();
+        assert!(clear.headers.is_empty());
+    }
+
+    #[tokio::test]
+    async fn commands_that_send_nothing() {
+        let tester = Tester::new("http://localhost:1".into(), String::new());
+        let mut repl = Repl::new(None).with_server("client", tester);
+        assert_eq!(repl.execute("vars").await.0, "client:\n  (none)");
+        assert_eq!(repl.execute("set quizId 3").await.0, "quizId = 3");
+        assert_eq!(repl.execute("  set name a b ").await.0, "name = \"a b\"");
+        assert_eq!(
+            repl.execute("vars").await.0,
+            "client:\n  name = \"a b\"\n  quizId = 3"
+        );
+        assert!(repl
+            .execute("post noSuchRoute")
+            .await
+            .0
+            .starts_with("no route called"));
+        assert!(repl
+            .execute("fetch /x")
+            .await
+            .0
+            .starts_with("unknown command"));
+        assert!(repl.execute("put /x {").await.0.starts_with("invalid JSON"));
+        assert_eq!(repl.execute("exit").await.1, Flow::Quit);
+    }
+}
@@ -0,0 +1,178 @@
+use crate::report::triage::{FindingGroup, Severity};
+use crate::report::RunReport;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::Path;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum BaselineError {
+    #[error("Baseline I/O failed: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Invalid baseline: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+// What makes two finding groups the same finding across runs; seeds and case
+// counts change from run to run.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct FindingKey {
+    pub method: String,
+    pub target: String,
+    pub kind: String,
+    #[serde(default)]
+    pub path: Option<String>,
+}
+
+impl FindingGroup<'_> {
+    pub fn key(&self) -> FindingKey {
+        FindingKey {
+            method: self.method.clone(),
+            target: self.target.clone(),
+            kind: self.kind.clone(),
+            path: self.path.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct KnownFinding {
+    #[serde(flatten)]
+    pub key: FindingKey,
+    pub severity: Severity,
+    // As of the run the baseline was saved from.
+    pub count: usize,
+    pub seed: u64,
+}
+
+impl fmt::Display for KnownFinding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "[{}] {} {}: {}",
+            self.severity, self.key.method, self.key.target, self.key.kind
+        )?;
+        if let Some(path) = &self.key.path {
+            write!(f, " at `{}`", path)?;
+        }
+        Ok(())
+    }
+}
+
+// The finding groups of an earlier run, for reporting only what changed since.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Baseline {
+    pub findings: Vec<KnownFinding>,
+}
+
+// A run's finding groups split by whether the baseline has them, plus the
+// baseline's findings the run no longer has.
+pub struct BaselineDiff<'a> {
+    pub new: Vec<FindingGroup<'a>>,
+    pub known: Vec<FindingGroup<'a>>,
+    pub fixed: Vec<KnownFinding>,
+}
+
+impl Baseline {
+    pub fn of(report: &RunReport) -> Self {
+        Self {
+            findings: report
+                .triage()
+                .iter()
+                .map(|group| KnownFinding {
+                    key: group.key(),
+                    severity: group.severity,
+                    count: group.count,
+                    seed: group.example.seed,
+                })
+                .collect(),
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Self, BaselineError> {
+        Ok(serde_json::from_slice(&std::fs::read(path)?)?)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), BaselineError> {
+        std::fs::write(path, serde_json::to_vec_pretty(self)?)?;
+        Ok(())
+    }
+
+    // Findings the run didn't reach at all, like those on routes a shorter
+    // run never sent, count as fixed too.
+    pub fn diff<'a>(&self, groups: Vec<FindingGroup<'a>>) -> BaselineDiff<'a> {
+        let keys: Vec<FindingKey> = groups.iter().map(FindingGroup::key).collect();
+        let fixed = self
+            .findings
+            .iter()
+            .filter(|finding| !keys.contains(&finding.key))
+            .cloned()
+            .collect();
+        let (known, new) = groups.into_iter().partition(|group| {
+            let key = group.key();
+            self.findings.iter().any(|finding| finding.key == key)
+        });
+        BaselineDiff { new, known, fixed }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::client::TesterError;
+    use crate::runner::tests::case_result;
+    use reqwest::Method;
+
+    fn report(targets: &[&str]) -> RunReport {
+        let mut report = RunReport::new(1);
+        for target in targets {
+            report.cases.push(case_result(
+                Method::GET,
+                target,
+                Err(TesterError::StatusMismatch {
+                    endpoint: target.to_string(),
+                    method: Method::GET,
+                    client_status: 200,
+                    actual_status: 404,
+                }),
+            ));
+        }
+        report
+    }
+
+    #[test]
+    fn runs_are_split_into_new_known_and_fixed_findings() {
+        let baseline = Baseline::of(&report(&["/a", "/b"]));
+        assert_eq!(baseline.findings.len(), 2);
+        assert_eq!(
+            baseline.findings[0].to_string(),
+            "[status] GET /a: status mismatch"
+        );
+
+        let later = report(&["/b", "/c"]);
+        let diff = baseline.diff(later.triage());
+        let targets = |groups: &[FindingGroup]| -> Vec<String> {
+            groups.iter().map(|group| group.target.clone()).collect()
+        };
+        assert_eq!(targets(&diff.new), ["/c"]);
+        assert_eq!(targets(&diff.known), ["/b"]);
+        assert_eq!(diff.fixed.len(), 1);
+        assert_eq!(diff.fixed[0].key.target, "/a");
+    }
+
+    #[test]
+    fn baselines_round_trip_through_json() {
+        let path = std::env::temp_dir().join(format!("fuzzer1531-baseline-{}", std::process::id()));
+        let baseline = Baseline::of(&report(&["/a"]));
+        baseline.save(&path).unwrap();
+        let saved = std::fs::read_to_string(&path).unwrap();
+        assert!(saved.contains("\"severity\": \"status\""), "{}", saved);
+        assert!(saved.contains("\"target\": \"/a\""), "{}", saved);
+        assert_eq!(Baseline::load(&path).unwrap(), baseline);
+
+        std::fs::write(&path, "[]").unwrap();
+        assert!(matches!(Baseline::load(&path), Err(BaselineError::Json(_))));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
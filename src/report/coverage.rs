@@ -0,0 +1,113 @@
+use crate::report::RunReport;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+// A status code rounded down to its hundred, shown as `2xx`, `4xx`, ...
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct StatusClass(u16);
+
+impl StatusClass {
+    pub fn of(status: u16) -> Self {
+        Self(status / 100)
+    }
+}
+
+impl fmt::Display for StatusClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}xx", self.0)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Coverage {
+    // Status classes returned by the reference server for each known (method,
+    // path template). Routes that only ever failed before a response arrived
+    // are present with no classes.
+    pub hits: BTreeMap<(String, String), BTreeSet<StatusClass>>,
+    // Known routes that no case was sent to.
+    pub untested: Vec<(String, String)>,
+}
+
+impl Coverage {
+    pub fn new(report: &RunReport) -> Self {
+        let mut hits: BTreeMap<(String, String), BTreeSet<StatusClass>> = BTreeMap::new();
+        for case in &report.cases {
+            let route = (case.method.to_string(), case.target.clone());
+            if !report.routes.contains(&route) {
+                continue;
+            }
+            let classes = hits.entry(route).or_default();
+            // The reference server decides which behaviour was exercised; the
+            // server under test only stands in when the reference gave nothing.
+            if let Some(response) = case.actual.as_ref().or(case.client.as_ref()) {
+                classes.insert(StatusClass::of(response.status));
+            }
+        }
+
+        let untested = report
+            .routes
+            .iter()
+            .filter(|route| !hits.contains_key(*route))
+            .cloned()
+            .collect();
+        Self { hits, untested }
+    }
+
+    pub fn tested(&self) -> usize {
+        self.hits.len()
+    }
+
+    pub fn total(&self) -> usize {
+        self.hits.len() + self.untested.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runner::tests::{case_result, response};
+    use reqwest::Method;
+    use serde_json::json;
+
+    #[test]
+    fn status_classes_round_down_to_the_hundred() {
+        assert_eq!(StatusClass::of(204).to_string(), "2xx");
+        assert_eq!(StatusClass::of(499), StatusClass::of(400));
+    }
+
+    #[test]
+    fn routes_without_cases_are_untested() {
+        let mut report = RunReport::new(0);
+        report.routes = vec![
+            ("GET".to_string(), "/quiz/{id}".to_string()),
+            ("DELETE".to_string(), "/quiz/{id}".to_string()),
+            ("POST".to_string(), "/quiz".to_string()),
+        ];
+
+        let mut ok = case_result(Method::GET, "/quiz/{id}", Ok(()));
+        ok.actual = Some(response(200, json!({}), 1));
+        let mut bad = case_result(Method::GET, "/quiz/{id}", Ok(()));
+        // Only the reference counts when both servers answered.
+        bad.client = Some(response(500, json!({}), 1));
+        bad.actual = Some(response(400, json!({}), 1));
+        // Neither server answered.
+        let lost = case_result(Method::POST, "/quiz", Ok(()));
+        // A method probe, not a known route.
+        let mut probe = case_result(Method::PATCH, "/quiz", Ok(()));
+        probe.actual = Some(response(405, json!({}), 1));
+        report.cases = vec![ok, bad, lost, probe];
+
+        let coverage = report.coverage();
+        let get = &coverage.hits[&("GET".to_string(), "/quiz/{id}".to_string())];
+        assert_eq!(
+            get.iter().map(ToString::to_string).collect::<Vec<_>>(),
+            ["2xx", "4xx"]
+        );
+        assert!(coverage.hits[&("POST".to_string(), "/quiz".to_string())].is_empty());
+        assert_eq!(
+            coverage.untested,
+            [("DELETE".to_string(), "/quiz/{id}".to_string())]
+        );
+        assert_eq!((coverage.tested(), coverage.total()), (2, 3));
+    }
+}
@@ -0,0 +1,278 @@
+use crate::models::client::{RawResponse, ServerResponse};
+use crate::models::endpoints::{versioned_path, ApiVersion};
+use crate::report::RunReport;
+use crate::runner::CaseResult;
+use reqwest::header::CONTENT_TYPE;
+use reqwest::StatusCode;
+use serde_json::{json, Value};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Where the run's requests went, which the report itself doesn't keep.
+#[derive(Debug, Clone, Copy)]
+pub struct Servers<'a> {
+    pub client: &'a str,
+    // The reference server, if the run had one rather than an oracle.
+    pub actual: Option<&'a str>,
+    pub version: Option<ApiVersion>,
+}
+
+// A HAR 1.2 log with an entry per request to each server, for opening a run in
+// browser devtools or other HTTP tooling. Requests are as generated, before
+// default headers and session tokens were added. Responses that never arrived
+// get status 0, like a browser's failed requests.
+pub fn render(report: &RunReport, servers: Servers) -> Value {
+    let started = iso8601(report.started);
+    let mut entries = Vec::new();
+    for case in &report.cases {
+        entries.push(entry(
+            case,
+            servers.client,
+            "client",
+            case.client.as_ref(),
+            servers.version,
+            &started,
+        ));
+        if let Some(actual) = servers.actual {
+            entries.push(entry(
+                case,
+                actual,
+                "reference",
+                case.actual.as_ref(),
+                servers.version,
+                &started,
+            ));
+        }
+    }
+    json!({
+        "log": {
+            "version": "1.2",
+            "creator": { "name": "fuzzer1531", "version": env!("CARGO_PKG_VERSION") },
+            "entries": entries,
+        }
+    })
+}
+
+pub fn write(report: &RunReport, servers: Servers, path: impl AsRef<Path>) -> std::io::Result<()> {
+    let har = serde_json::to_string_pretty(&render(report, servers))?;
+    std::fs::write(path, har)
+}
+
+fn entry(
+    case: &CaseResult,
+    base_url: &str,
+    server: &str,
+    response: Option<&ServerResponse>,
+    version: Option<ApiVersion>,
+    started: &str,
+) -> Value {
+    let path = match version {
+        Some(version) => versioned_path(version, &case.endpoint),
+        None => case.endpoint.clone(),
+    };
+    let mut url = format!(
+        "{}/{}",
+        base_url.trim_end_matches('/'),
+        path.trim_start_matches('/')
+    );
+    if let Ok(mut parsed) = reqwest::Url::parse(&url) {
+        parsed.query_pairs_mut().extend_pairs(&case.query);
+        if case.query.is_empty() {
+            parsed.set_query(None);
+        }
+        url = parsed.to_string();
+    }
+
+    let query_string: Vec<Value> = case
+        .query
+        .iter()
+        .map(|(name, value)| json!({ "name": name, "value": value }))
+        .collect();
+    let mut request = json!({
+        "method": case.method.as_str(),
+        "url": url,
+        "httpVersion": "HTTP/1.1",
+        "cookies": [],
+        "headers": [],
+        "queryString": query_string,
+        "headersSize": -1,
+        "bodySize": -1,
+    });
+    if let Some(body) = &case.body {
+        request["postData"] = json!({ "mimeType": "application/json", "text": body.to_string() });
+    }
+
+    let latency_ms = response.map_or(0.0, |response| response.latency.as_secs_f64() * 1000.0);
+    let mut comment = match (&case.outcome, case.malformation) {
+        (_, Some(malformation)) => format!(
+            "{} server, seed {}, sent malformed ({:?})",
+            server, case.seed, malformation
+        ),
+        (Ok(()), None) => format!("{} server, seed {}, passed", server, case.seed),
+        (Err(err), None) => format!(
+            "{} server, seed {}, {}: {}",
+            server,
+            case.seed,
+            if case.flaky.is_some() {
+                "flaky"
+            } else {
+                "failed"
+            },
+            err.to_string().lines().next().unwrap_or_default()
+        ),
+    };
+    if let Some(perturbation) = case.header_perturbation {
+        comment.push_str(&format!(", token header {:?}", perturbation));
+    }
+    json!({
+        "startedDateTime": started,
+        "time": latency_ms,
+        "request": request,
+        "response": response.map_or_else(missing_response, har_response),
+        "cache": {},
+        "timings": { "send": 0, "wait": latency_ms, "receive": 0 },
+        "comment": comment,
+    })
+}
+
+fn har_response(response: &ServerResponse) -> Value {
+    // Binary bodies are left out rather than base64-encoded.
+    let (text, size, redirect) = match &response.raw {
+        None => {
+            let text = response.body.to_string();
+            (text.clone(), text.len(), String::new())
+        }
+        Some(RawResponse::Bytes { bytes, .. }) => (
+            String::from_utf8(bytes.clone()).unwrap_or_default(),
+            bytes.len(),
+            String::new(),
+        ),
+        // Only the start of a streamed body was kept.
+        Some(RawResponse::Streamed { head }) => (
+            String::from_utf8_lossy(head).into_owned(),
+            head.len(),
+            String::new(),
+        ),
+        Some(RawResponse::Redirect { location }) => {
+            (String::new(), 0, location.clone().unwrap_or_default())
+        }
+    };
+    let mime_type = response
+        .headers
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or("application/json");
+    json!({
+        "status": response.status,
+        "statusText": StatusCode::from_u16(response.status)
+            .ok()
+            .and_then(|status| status.canonical_reason())
+            .unwrap_or_default(),
+        "httpVersion": "HTTP/1.1",
+        "cookies": [],
+        "headers": response
+            .headers
+            .iter()
+            .map(|(name, value)| json!({
+                "name": name.as_str(),
+                "value": String::from_utf8_lossy(value.as_bytes()),
+            }))
+            .collect::<Vec<_>>(),
+        "content": { "size": size, "mimeType": mime_type, "text": text },
+        "redirectURL": redirect,
+        "headersSize": -1,
+        "bodySize": -1,
+    })
+}
+
+fn missing_response() -> Value {
+    json!({
+        "status": 0,
+        "statusText": "",
+        "httpVersion": "",
+        "cookies": [],
+        "headers": [],
+        "content": { "size": 0, "mimeType": "x-unknown" },
+        "redirectURL": "",
+        "headersSize": -1,
+        "bodySize": -1,
+    })
+}
+
+// `2024-03-01T09:30:00.000Z`, from the days-since-epoch conversion at
+// https://howardhinnant.github.io/date_algorithms.html.
+fn iso8601(time: SystemTime) -> String {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let seconds = since_epoch.as_secs();
+    let days = (seconds / 86_400) as i64;
+    let seconds_of_day = seconds % 86_400;
+
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        seconds_of_day / 3600,
+        seconds_of_day / 60 % 60,
+        seconds_of_day % 60,
+        since_epoch.subsec_millis()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runner::tests::{case_result, response};
+    use reqwest::Method;
+    use std::time::Duration;
+
+    #[test]
+    fn timestamps_are_utc_with_milliseconds() {
+        assert_eq!(iso8601(UNIX_EPOCH), "1970-01-01T00:00:00.000Z");
+        let leap_day = UNIX_EPOCH + Duration::from_millis(1_709_199_000_250);
+        assert_eq!(iso8601(leap_day), "2024-02-29T09:30:00.250Z");
+    }
+
+    #[test]
+    fn entries_cover_each_server_with_the_versioned_url() {
+        let mut report = RunReport::new(3);
+        let mut case = case_result(Method::GET, "/admin/quiz/list", Ok(()));
+        case.query = vec![("token".to_string(), "a b".to_string())];
+        case.client = Some(response(200, json!({"quizzes": []}), 12));
+        report.cases.push(case);
+
+        let har = render(
+            &report,
+            Servers {
+                client: "http://client/",
+                actual: Some("http://reference"),
+                version: Some(ApiVersion::V2),
+            },
+        );
+        let entries = har["log"]["entries"].as_array().unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(
+            entries[0]["request"]["url"],
+            "http://client/v2/admin/quiz/list?token=a+b"
+        );
+        assert_eq!(entries[0]["response"]["status"], 200);
+        assert_eq!(entries[0]["time"], 12.0);
+        assert_eq!(entries[0]["comment"], "client server, seed 0, passed");
+        assert!(entries[1]["request"]["url"]
+            .as_str()
+            .unwrap()
+            .starts_with("http://reference/v2/"));
+        assert_eq!(entries[1]["response"]["status"], 0);
+    }
+}
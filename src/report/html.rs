@@ -0,0 +1,322 @@
+use crate::pretty::{diff_lines, Line};
+use crate::report::{escape, LatencyStats, RunReport};
+use crate::runner::CaseResult;
+use serde_json::Value;
+use std::fmt::Write;
+use std::path::Path;
+
+const STYLE: &str = "
+body { font-family: system-ui, sans-serif; margin: 2rem; color: #222; }
+table { border-collapse: collapse; margin-bottom: 2rem; }
+th, td { border: 1px solid #ccc; padding: 0.3rem 0.6rem; text-align: left; }
+th { background: #f3f3f3; }
+.pass { color: #1a7f37; }
+.fail { color: #cf222e; }
+details { border: 1px solid #ddd; border-radius: 4px; margin-bottom: 0.5rem; padding: 0.4rem 0.8rem; }
+summary { cursor: pointer; font-family: monospace; }
+pre { background: #f6f8fa; padding: 0.6rem; overflow-x: auto; }
+.diff .del { background: #ffebe9; }
+.diff .add { background: #dafbe1; }
+";
+
+pub fn render(report: &RunReport) -> String {
+    let mut out = String::new();
+    let passed = report.passed();
+    let failed = report.failed();
+
+    out.push_str("<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n");
+    out.push_str("<title>fuzzer1531 report</title>\n");
+    let _ = writeln!(out, "<style>{}</style>\n</head>\n<body>", STYLE);
+
+    out.push_str("<h1>fuzzer1531 report</h1>\n<ul>\n");
+    let _ = writeln!(
+        out,
+        "<li>Seed: <code>{}</code> (set <code>seed = {}</code> in the config to reproduce)</li>",
+        report.seed, report.seed
+    );
+    let _ = writeln!(out, "<li>Duration: {:.2?}</li>", report.duration);
+    let _ = writeln!(
+        out,
+        "<li>Cases: {} (<span class=\"pass\">{} passed</span>, <span class=\"fail\">{} failed</span>)</li>",
+        report.cases.len(),
+        passed,
+        failed
+    );
+    let flaky = report.flaky().count();
+    if flaky > 0 {
+        let _ = writeln!(
+            out,
+            "<li>Flaky: {} (diverged, but not often enough on rerun to count as failures)</li>",
+            flaky
+        );
+    }
+    if report.timed_out {
+        out.push_str("<li class=\"fail\">Run timed out before all cases finished</li>\n");
+    }
+    if report.cancelled {
+        out.push_str("<li class=\"fail\">Run was interrupted before all cases finished</li>\n");
+    }
+    if report.finding_limit {
+        out.push_str("<li class=\"fail\">Run stopped after reaching its limit on findings</li>\n");
+    }
+    if report.cases.len() < report.planned {
+        let _ = writeln!(
+            out,
+            "<li>Ran {} of {} planned cases</li>",
+            report.cases.len(),
+            report.planned
+        );
+    }
+    out.push_str("</ul>\n");
+
+    out.push_str("<h2>Endpoints</h2>\n<table>\n<tr><th>Method</th><th>Path</th><th>Passed</th><th>Failed</th>");
+    out.push_str("<th>Client latency (p50 / p95 / p99 / max)</th><th>Actual latency (p50 / p95 / p99 / max)</th></tr>\n");
+    for ((method, path), stats) in report.by_endpoint() {
+        let _ = writeln!(
+            out,
+            "<tr><td>{}</td><td><code>{}</code></td><td class=\"pass\">{}</td><td class=\"fail\">{}</td><td>{}</td><td>{}</td></tr>",
+            escape(&method),
+            escape(&path),
+            stats.passed,
+            stats.failed,
+            latency(&stats.client_latency),
+            latency(&stats.actual_latency)
+        );
+    }
+    out.push_str("</table>\n");
+
+    let coverage = report.coverage();
+    let _ = writeln!(
+        out,
+        "<h2>Coverage</h2>\n<p>{} of {} routes exercised.</p>",
+        coverage.tested(),
+        coverage.total()
+    );
+    out.push_str("<table>\n<tr><th>Method</th><th>Path</th><th>Status classes</th></tr>\n");
+    for ((method, path), classes) in &coverage.hits {
+        let classes: Vec<String> = classes.iter().map(ToString::to_string).collect();
+        let _ = writeln!(
+            out,
+            "<tr><td>{}</td><td><code>{}</code></td><td>{}</td></tr>",
+            escape(method),
+            escape(path),
+            classes.join(", ")
+        );
+    }
+    for (method, path) in &coverage.untested {
+        let _ = writeln!(
+            out,
+            "<tr><td>{}</td><td><code>{}</code></td><td class=\"fail\">untested</td></tr>",
+            escape(method),
+            escape(path)
+        );
+    }
+    out.push_str("</table>\n");
+
+    let groups = report.triage();
+    if !groups.is_empty() {
+        out.push_str("<h2>Findings by root cause</h2>\n<table>\n<tr><th>Severity</th><th>Method</th><th>Path</th><th>Kind</th><th>At</th><th>Cases</th><th>Example seed</th></tr>\n");
+        for group in &groups {
+            let _ = writeln!(
+                out,
+                "<tr><td class=\"fail\">{}</td><td>{}</td><td><code>{}</code></td><td>{}</td><td><code>{}</code></td><td>{}</td><td><code>{}</code></td></tr>",
+                group.severity,
+                escape(&group.method),
+                escape(&group.target),
+                escape(&group.kind),
+                escape(group.path.as_deref().unwrap_or_default()),
+                group.count,
+                group.example.seed
+            );
+        }
+        out.push_str("</table>\n");
+    }
+
+    out.push_str("<h2>Mismatches</h2>\n");
+    if failed == 0 {
+        out.push_str("<p class=\"pass\">No mismatches found.</p>\n");
+    }
+    for case in report.failures() {
+        render_failure(&mut out, case);
+    }
+    if flaky > 0 {
+        out.push_str("<h2>Flaky</h2>\n");
+        for case in report.flaky() {
+            render_failure(&mut out, case);
+        }
+    }
+
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+pub fn write(report: &RunReport, path: impl AsRef<Path>) -> std::io::Result<()> {
+    std::fs::write(path, render(report))
+}
+
+fn render_failure(out: &mut String, case: &CaseResult) {
+    let error = match &case.outcome {
+        Err(err) => err.to_string(),
+        Ok(()) => return,
+    };
+    let headline = error.lines().next().unwrap_or_default();
+
+    let _ = writeln!(
+        out,
+        "<details>\n<summary>{} {} &mdash; {}</summary>",
+        escape(case.method.as_str()),
+        escape(&case.endpoint),
+        escape(headline)
+    );
+    let _ = writeln!(
+        out,
+        "<p>Iteration {}, case seed <code>{}</code></p>",
+        case.iteration, case.seed
+    );
+    if let Some(flaky) = &case.flaky {
+        let _ = writeln!(out, "<p>{}</p>", escape(&flaky.to_string()));
+    }
+    let _ = writeln!(out, "<pre>{}</pre>", escape(&error));
+
+    if !case.query.is_empty() {
+        out.push_str("<h4>Query parameters</h4>\n<table>\n");
+        for (name, value) in &case.query {
+            let _ = writeln!(
+                out,
+                "<tr><td><code>{}</code></td><td><code>{}</code></td></tr>",
+                escape(name),
+                escape(value)
+            );
+        }
+        out.push_str("</table>\n");
+    }
+    if let Some(body) = &case.body {
+        let _ = writeln!(
+            out,
+            "<h4>Request body</h4>\n<pre>{}</pre>",
+            escape(&pretty(body))
+        );
+    }
+    if let Some(malformation) = &case.malformation {
+        let _ = writeln!(
+            out,
+            "<p>Sent malformed: <code>{:?}</code></p>",
+            malformation
+        );
+    }
+    if let Some(perturbation) = &case.header_perturbation {
+        let _ = writeln!(
+            out,
+            "<p>Token header sent: <code>{:?}</code></p>",
+            perturbation
+        );
+    }
+    if let Some(minimized) = &case.minimized {
+        let _ = writeln!(
+            out,
+            "<h4>Minimized body</h4>\n<pre>{}</pre>",
+            escape(&pretty(minimized))
+        );
+    }
+
+    if let (Some(client), Some(actual)) = (&case.client, &case.actual) {
+        let _ = writeln!(
+            out,
+            "<h4>Response diff (<span class=\"del\">client {}</span> / <span class=\"add\">actual {}</span>)</h4>",
+            client.status, actual.status
+        );
+        out.push_str("<pre class=\"diff\">");
+        render_diff(out, &pretty(&client.body), &pretty(&actual.body));
+        out.push_str("</pre>\n");
+    }
+
+    out.push_str("</details>\n");
+}
+
+fn render_diff(out: &mut String, client: &str, actual: &str) {
+    for line in diff_lines(client, actual) {
+        let _ = match line {
+            Line::Same(line) => writeln!(out, "  {}", escape(line)),
+            Line::Removed(line) => writeln!(out, "<span class=\"del\">- {}</span>", escape(line)),
+            Line::Added(line) => writeln!(out, "<span class=\"add\">+ {}</span>", escape(line)),
+        };
+    }
+}
+
+fn latency(stats: &LatencyStats) -> String {
+    if stats.count == 0 {
+        return "&ndash;".to_string();
+    }
+    format!(
+        "{:.1?} / {:.1?} / {:.1?} / {:.1?}",
+        stats.p50, stats.p95, stats.p99, stats.max
+    )
+}
+
+fn pretty(value: &Value) -> String {
+    serde_json::to_string_pretty(value).unwrap_or_else(|_| value.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::client::TesterError;
+    use crate::runner::tests::{case_result, response};
+    use reqwest::Method;
+    use serde_json::json;
+
+    fn diff(client: &str, actual: &str) -> String {
+        let mut out = String::new();
+        render_diff(&mut out, client, actual);
+        out
+    }
+
+    #[test]
+    fn diffs_mark_removed_and_added_lines() {
+        assert_eq!(
+            diff("a\nb\nc", "a\nc"),
+            "  a\n<span class=\"del\">- b</span>\n  c\n"
+        );
+        assert_eq!(
+            diff("x", "<y>"),
+            "<span class=\"del\">- x</span>\n<span class=\"add\">+ &lt;y&gt;</span>\n"
+        );
+        assert_eq!(diff("same", "same"), "  same\n");
+    }
+
+    #[test]
+    fn reports_show_failures_with_their_responses() {
+        let mut report = RunReport::new(99);
+        assert!(render(&report).contains("No mismatches found."));
+
+        let mut case = case_result(
+            Method::POST,
+            "/quiz/<new>",
+            Err(TesterError::TypeMismatch {
+                endpoint: "/quiz/<new>".to_string(),
+                method: Method::GET,
+                path: "/quizId".to_string(),
+                client_value: Box::new(json!("1")),
+                actual_value: Box::new(json!(1)),
+            }),
+        );
+        case.client = Some(response(200, json!({"quizId": "1"}), 5));
+        case.actual = Some(response(200, json!({"quizId": 1}), 5));
+        report.cases.push(case);
+
+        let html = render(&report);
+        assert!(html.contains("<code>99</code>"));
+        assert!(html.contains("POST /quiz/&lt;new&gt;"));
+        assert!(html.contains("<span class=\"del\">-   &quot;quizId&quot;: &quot;1&quot;</span>"));
+        assert!(html.contains("<span class=\"add\">+   &quot;quizId&quot;: 1</span>"));
+        assert!(!html.contains("No mismatches found."));
+    }
+
+    #[test]
+    fn interrupted_runs_say_so() {
+        let mut report = RunReport::new(1);
+        assert!(!render(&report).contains("interrupted"));
+        report.cancelled = true;
+        assert!(render(&report).contains("Run was interrupted before all cases finished"));
+    }
+}
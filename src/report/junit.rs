@@ -0,0 +1,187 @@
+use crate::report::{escape, RunReport};
+use crate::runner::CaseResult;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::path::Path;
+
+// One <testsuite> per (method, path template), with a <testcase> for every
+// generated case against that route.
+pub fn render(report: &RunReport) -> String {
+    let mut suites: BTreeMap<String, Vec<&CaseResult>> = BTreeMap::new();
+    for case in &report.cases {
+        suites
+            .entry(format!("{} {}", case.method, case.target))
+            .or_default()
+            .push(case);
+    }
+
+    let mut out = String::new();
+    out.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    let _ = writeln!(
+        out,
+        "<testsuites name=\"fuzzer1531\" tests=\"{}\" failures=\"{}\" time=\"{:.3}\">",
+        report.cases.len(),
+        report.failed(),
+        report.duration.as_secs_f64()
+    );
+    for (name, cases) in &suites {
+        let failures = cases.iter().filter(|c| c.failed()).count();
+        let _ = writeln!(
+            out,
+            "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" time=\"{:.3}\">",
+            escape(name),
+            cases.len(),
+            failures,
+            cases.iter().map(|c| case_time(c)).sum::<f64>()
+        );
+        let _ = writeln!(
+            out,
+            "    <properties>\n      <property name=\"seed\" value=\"{}\"/>\n    </properties>",
+            report.seed
+        );
+        for case in cases {
+            render_case(&mut out, name, case);
+        }
+        out.push_str("  </testsuite>\n");
+    }
+
+    out.push_str("</testsuites>\n");
+    out
+}
+
+pub fn write(report: &RunReport, path: impl AsRef<Path>) -> std::io::Result<()> {
+    std::fs::write(path, render(report))
+}
+
+fn render_case(out: &mut String, suite: &str, case: &CaseResult) {
+    let _ = write!(
+        out,
+        "    <testcase classname=\"{}\" name=\"{} {} #{} (seed {})\" time=\"{:.3}\"",
+        escape(suite),
+        escape(case.method.as_str()),
+        escape(&case.endpoint),
+        case.iteration,
+        case.seed,
+        case_time(case)
+    );
+
+    let err = match &case.outcome {
+        Ok(()) => {
+            out.push_str("/>\n");
+            return;
+        }
+        Err(err) => err.to_string(),
+    };
+
+    let mut details = err.clone();
+    if let Some(body) = &case.body {
+        let _ = write!(details, "\n\nRequest body: {}", body);
+    }
+    if let Some(minimized) = &case.minimized {
+        let _ = write!(details, "\nMinimized body: {}", minimized);
+    }
+
+    if let Some(flaky) = &case.flaky {
+        let _ = write!(details, "\n\n{}", flaky);
+    }
+
+    // Surefire's element for a test that failed but passed on rerun.
+    let element = if case.flaky.is_some() {
+        "flakyFailure"
+    } else {
+        "failure"
+    };
+    let _ = writeln!(
+        out,
+        ">\n      <{} message=\"{}\" type=\"mismatch\">{}</{}>\n    </testcase>",
+        element,
+        escape(err.lines().next().unwrap_or_default()),
+        escape(&details),
+        element
+    );
+}
+
+// The slower of the two servers, since they are queried one after the other.
+fn case_time(case: &CaseResult) -> f64 {
+    [&case.client, &case.actual]
+        .into_iter()
+        .flatten()
+        .map(|r| r.latency.as_secs_f64())
+        .fold(0.0, f64::max)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::client::TesterError;
+    use crate::runner::tests::{case_result, response};
+    use crate::runner::Flaky;
+    use reqwest::Method;
+    use serde_json::json;
+    use std::time::Duration;
+
+    #[test]
+    fn suites_group_cases_by_route() {
+        let mut report = RunReport::new(5);
+        report.duration = Duration::from_millis(1500);
+        let mut slow = case_result(Method::GET, "/quiz/{id}", Ok(()));
+        slow.client = Some(response(200, json!({}), 250));
+        slow.actual = Some(response(200, json!({}), 100));
+        report.cases.push(slow);
+        report
+            .cases
+            .push(case_result(Method::GET, "/quiz/{id}", Ok(())));
+        report
+            .cases
+            .push(case_result(Method::POST, "/quiz", Ok(())));
+
+        let xml = render(&report);
+        assert!(xml.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n"));
+        assert!(xml.contains(
+            "<testsuites name=\"fuzzer1531\" tests=\"3\" failures=\"0\" time=\"1.500\">"
+        ));
+        // Timed by the slower server.
+        assert!(xml.contains(
+            "<testsuite name=\"GET /quiz/{id}\" tests=\"2\" failures=\"0\" time=\"0.250\">"
+        ));
+        assert!(xml.contains("<testsuite name=\"POST /quiz\" tests=\"1\""));
+        assert!(xml.contains("<property name=\"seed\" value=\"5\"/>"));
+        assert!(xml.ends_with("</testsuites>\n"));
+    }
+
+    #[test]
+    fn failures_carry_the_error_and_bodies() {
+        let mut report = RunReport::new(1);
+        let mut case = case_result(
+            Method::PUT,
+            "/quiz/{id}",
+            Err(TesterError::TypeMismatch {
+                endpoint: "/quiz/1".to_string(),
+                method: Method::GET,
+                path: "/name".to_string(),
+                client_value: Box::new(json!(null)),
+                actual_value: Box::new(json!("<q>")),
+            }),
+        );
+        case.body = Some(json!({"name": "a & b"}));
+        case.minimized = Some(json!({}));
+        report.cases.push(case);
+
+        let xml = render(&report);
+        assert!(xml.contains("failures=\"1\""));
+        // Only the first line of the error goes in the message.
+        assert!(xml.contains("(path `/name`).\" type=\"mismatch\">"));
+        assert!(xml.contains("Request body: {&quot;name&quot;:&quot;a &amp; b&quot;}"));
+        assert!(xml.contains("Minimized body: {}"));
+        assert!(xml.contains("&quot;&lt;q&gt;&quot;"));
+
+        report.cases[0].flaky = Some(Flaky {
+            reruns: 3,
+            reproduced: 1,
+        });
+        let xml = render(&report);
+        assert!(xml.contains("failures=\"0\""));
+        assert!(xml.contains("<flakyFailure message="));
+        assert!(xml.contains("flaky: diverged again on 1 of 3 reruns</flakyFailure>"));
+    }
+}
@@ -0,0 +1,331 @@
+pub mod baseline;
+pub mod coverage;
+pub mod har;
+pub mod html;
+pub mod junit;
+pub mod progress;
+pub mod prometheus;
+pub mod results;
+pub mod triage;
+
+use crate::corpus::RecordedRequest;
+use crate::models::client::TesterError;
+use crate::report::coverage::Coverage;
+use crate::runner::{CaseResult, Category, PlannedCase};
+use std::collections::BTreeMap;
+use std::time::{Duration, SystemTime};
+
+pub struct RunReport {
+    pub seed: u64,
+    // When the run began, by the wall clock.
+    pub started: SystemTime,
+    pub duration: Duration,
+    pub cases: Vec<CaseResult>,
+    // Set when the run hit its overall time limit before finishing.
+    pub timed_out: bool,
+    // Set when the run was interrupted (e.g. by Ctrl-C) before finishing.
+    pub cancelled: bool,
+    // Set when the run stopped after its limit on findings.
+    pub finding_limit: bool,
+    // Cases the run would have sent had it not stopped early.
+    pub planned: usize,
+    // Every (method, path template) the run knew about, tested or not.
+    pub routes: Vec<(String, String)>,
+    // For a resumed campaign, the cases sent before this run and what they
+    // found; `cases` only has this run's.
+    pub resumed: usize,
+    pub earlier_findings: Vec<RecordedRequest>,
+}
+
+// The result of marking several candidate servers against one reference.
+pub struct MultiReport {
+    pub candidates: Vec<(String, RunReport)>,
+    // Cases the reference itself failed to answer; no candidate was compared.
+    pub reference_failures: Vec<(PlannedCase, TesterError)>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct LatencyStats {
+    pub count: usize,
+    pub mean: Duration,
+    pub min: Duration,
+    pub max: Duration,
+    pub p50: Duration,
+    pub p95: Duration,
+    pub p99: Duration,
+}
+
+impl LatencyStats {
+    pub fn from_samples(samples: &[Duration]) -> Self {
+        if samples.is_empty() {
+            return Self::default();
+        }
+        let mut sorted = samples.to_vec();
+        sorted.sort();
+        let total: Duration = sorted.iter().sum();
+        Self {
+            count: sorted.len(),
+            mean: total / sorted.len() as u32,
+            min: sorted[0],
+            max: sorted[sorted.len() - 1],
+            p50: percentile(&sorted, 50),
+            p95: percentile(&sorted, 95),
+            p99: percentile(&sorted, 99),
+        }
+    }
+}
+
+// Nearest-rank percentile of already sorted, non-empty samples.
+fn percentile(sorted: &[Duration], p: usize) -> Duration {
+    let rank = (p * sorted.len()).div_ceil(100);
+    sorted[rank.saturating_sub(1).min(sorted.len() - 1)]
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Slowdown {
+    pub method: String,
+    pub path: String,
+    // Median latency of the server under test divided by the reference's.
+    pub ratio: f64,
+    pub client_p50: Duration,
+    pub actual_p50: Duration,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct EndpointStats {
+    pub passed: usize,
+    pub failed: usize,
+    pub client_latency: LatencyStats,
+    pub actual_latency: LatencyStats,
+}
+
+impl RunReport {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            started: SystemTime::now(),
+            duration: Duration::ZERO,
+            cases: Vec::new(),
+            timed_out: false,
+            cancelled: false,
+            finding_limit: false,
+            planned: 0,
+            routes: Vec::new(),
+            resumed: 0,
+            earlier_findings: Vec::new(),
+        }
+    }
+
+    // Flaky cases count as passed.
+    pub fn passed(&self) -> usize {
+        self.cases.iter().filter(|c| !c.failed()).count()
+    }
+
+    pub fn failed(&self) -> usize {
+        self.cases.len() - self.passed()
+    }
+
+    pub fn failures(&self) -> impl Iterator<Item = &CaseResult> {
+        self.cases.iter().filter(|c| c.failed())
+    }
+
+    pub fn flaky(&self) -> impl Iterator<Item = &CaseResult> {
+        self.cases.iter().filter(|c| c.flaky.is_some())
+    }
+
+    pub fn crashes(&self) -> impl Iterator<Item = &CaseResult> {
+        self.cases.iter().filter(|c| c.crash.is_some())
+    }
+
+    pub fn count(&self, category: Category) -> usize {
+        self.cases
+            .iter()
+            .filter(|c| c.category() == category)
+            .count()
+    }
+
+    // Endpoints where the server under test's median latency is more than
+    // `max_ratio` times the reference's. Medians keep a single slow request
+    // from flagging a whole endpoint.
+    pub fn slowdowns(&self, max_ratio: f64) -> Vec<Slowdown> {
+        self.by_endpoint()
+            .into_iter()
+            .filter(|(_, stats)| stats.client_latency.count > 0 && stats.actual_latency.count > 0)
+            .filter_map(|((method, path), stats)| {
+                let client_p50 = stats.client_latency.p50;
+                let actual_p50 = stats.actual_latency.p50;
+                let ratio =
+                    client_p50.as_secs_f64() / actual_p50.as_secs_f64().max(f64::MIN_POSITIVE);
+                (ratio > max_ratio).then_some(Slowdown {
+                    method,
+                    path,
+                    ratio,
+                    client_p50,
+                    actual_p50,
+                })
+            })
+            .collect()
+    }
+
+    pub fn coverage(&self) -> Coverage {
+        Coverage::new(self)
+    }
+
+    // Keyed by (method, path template) so every generated variant of a route
+    // is counted together.
+    pub fn by_endpoint(&self) -> BTreeMap<(String, String), EndpointStats> {
+        #[derive(Default)]
+        struct Group {
+            stats: EndpointStats,
+            client: Vec<Duration>,
+            actual: Vec<Duration>,
+        }
+
+        let mut grouped: BTreeMap<(String, String), Group> = BTreeMap::new();
+        for case in &self.cases {
+            let key = (case.method.to_string(), case.target.clone());
+            let group = grouped.entry(key).or_default();
+            if case.failed() {
+                group.stats.failed += 1;
+            } else {
+                group.stats.passed += 1;
+            }
+            group.client.extend(case.client.as_ref().map(|r| r.latency));
+            group.actual.extend(case.actual.as_ref().map(|r| r.latency));
+        }
+
+        grouped
+            .into_iter()
+            .map(|(key, mut group)| {
+                group.stats.client_latency = LatencyStats::from_samples(&group.client);
+                group.stats.actual_latency = LatencyStats::from_samples(&group.actual);
+                (key, group.stats)
+            })
+            .collect()
+    }
+}
+
+// Escapes text for both HTML and XML output.
+pub(crate) fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::client::TesterError;
+    use crate::runner::tests::{case_result, response};
+    use reqwest::Method;
+    use serde_json::{json, Value};
+
+    fn mismatch() -> TesterError {
+        TesterError::TypeMismatch {
+            endpoint: "/e".to_string(),
+            method: Method::GET,
+            path: "/a".to_string(),
+            client_value: Box::new(Value::Null),
+            actual_value: Box::new(json!(1)),
+        }
+    }
+
+    #[test]
+    fn text_is_escaped() {
+        assert_eq!(
+            escape(r#"<a href="x">'&'</a>"#),
+            "&lt;a href=&quot;x&quot;&gt;&#39;&amp;&#39;&lt;/a&gt;"
+        );
+    }
+
+    #[test]
+    fn latency_stats_summarise_samples() {
+        assert_eq!(LatencyStats::from_samples(&[]), LatencyStats::default());
+        let ms = Duration::from_millis;
+        assert_eq!(
+            LatencyStats::from_samples(&[ms(30), ms(10), ms(20)]),
+            LatencyStats {
+                count: 3,
+                mean: ms(20),
+                min: ms(10),
+                max: ms(30),
+                p50: ms(20),
+                p95: ms(30),
+                p99: ms(30),
+            }
+        );
+
+        let samples: Vec<Duration> = (1..=100).rev().map(ms).collect();
+        let stats = LatencyStats::from_samples(&samples);
+        assert_eq!((stats.p50, stats.p95, stats.p99), (ms(50), ms(95), ms(99)));
+        assert_eq!(LatencyStats::from_samples(&[ms(7)]).p99, ms(7));
+    }
+
+    #[test]
+    fn slowdowns_compare_median_latencies() {
+        let mut report = RunReport::new(1);
+        for (target, client_ms, actual_ms) in [
+            ("/slow", 100, 10),
+            ("/slow", 100, 10),
+            // A single outlier doesn't move the median.
+            ("/slow", 1, 1000),
+            ("/fast", 12, 10),
+            ("/instant", 5, 0),
+        ] {
+            let mut case = case_result(Method::GET, target, Ok(()));
+            case.client = Some(response(200, json!({}), client_ms));
+            case.actual = Some(response(200, json!({}), actual_ms));
+            report.cases.push(case);
+        }
+        // No reference latency to compare against.
+        let mut lost = case_result(Method::GET, "/lost", Ok(()));
+        lost.client = Some(response(200, json!({}), 500));
+        report.cases.push(lost);
+
+        let slowdowns = report.slowdowns(2.0);
+        let paths: Vec<&str> = slowdowns.iter().map(|s| s.path.as_str()).collect();
+        assert_eq!(paths, ["/instant", "/slow"]);
+        let slow = &slowdowns[1];
+        assert_eq!(slow.ratio, 10.0);
+        assert_eq!(slow.client_p50, Duration::from_millis(100));
+        assert!(report.slowdowns(20.0).iter().all(|s| s.path == "/instant"));
+    }
+
+    #[test]
+    fn endpoints_group_by_method_and_template() {
+        let mut report = RunReport::new(1);
+        let mut answered = case_result(Method::GET, "/quiz/{id}", Ok(()));
+        answered.endpoint = "/quiz/7".to_string();
+        answered.client = Some(response(200, json!({}), 10));
+        answered.actual = Some(response(200, json!({}), 30));
+        report.cases.push(answered);
+        report
+            .cases
+            .push(case_result(Method::GET, "/quiz/{id}", Err(mismatch())));
+        report
+            .cases
+            .push(case_result(Method::DELETE, "/quiz/{id}", Ok(())));
+
+        assert_eq!((report.passed(), report.failed()), (2, 1));
+        assert_eq!(report.failures().count(), 1);
+
+        let endpoints = report.by_endpoint();
+        let get = &endpoints[&("GET".to_string(), "/quiz/{id}".to_string())];
+        assert_eq!((get.passed, get.failed), (1, 1));
+        // Only cases that got a response contribute latencies.
+        assert_eq!(get.client_latency.count, 1);
+        assert_eq!(get.actual_latency.mean, Duration::from_millis(30));
+        let delete = &endpoints[&("DELETE".to_string(), "/quiz/{id}".to_string())];
+        assert_eq!((delete.passed, delete.failed), (1, 0));
+        assert_eq!(endpoints.len(), 2);
+    }
+}
@@ -0,0 +1,263 @@
+use crate::runner::CaseResult;
+use std::collections::BTreeMap;
+use std::io::{IsTerminal, Write};
+use std::time::{Duration, Instant};
+
+const BAR_WIDTH: usize = 30;
+// Endpoints listed under the bar, those with the most findings (then cases)
+// first.
+const ENDPOINT_LINES: usize = 5;
+const REDRAW_EVERY: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Tally {
+    pub cases: usize,
+    // Failed or crashed cases.
+    pub findings: usize,
+}
+
+// A live view of a run for a terminal: a bar with cases per second, findings
+// so far and the time left, over a tally for the busiest endpoints. It is
+// redrawn in place on stderr with ANSI escapes, so anything else printed
+// meanwhile has to `clear` it first.
+pub struct Progress {
+    planned: usize,
+    started: Instant,
+    total: Tally,
+    endpoints: BTreeMap<(String, String), Tally>,
+    drawn: usize,
+    last_draw: Option<Instant>,
+}
+
+impl Progress {
+    pub fn new(planned: usize) -> Self {
+        Self {
+            planned,
+            started: Instant::now(),
+            total: Tally::default(),
+            endpoints: BTreeMap::new(),
+            drawn: 0,
+            last_draw: None,
+        }
+    }
+
+    // `None` when the escapes would end up as garbage: with `quiet` set, when
+    // stderr is redirected to a file or pipe, or on a terminal that says it
+    // can't move the cursor.
+    pub fn for_stderr(planned: usize, quiet: bool) -> Option<Self> {
+        let term = std::env::var("TERM").ok();
+        enabled(quiet, std::io::stderr().is_terminal(), term.as_deref()).then(|| Self::new(planned))
+    }
+
+    pub fn record(&mut self, case: &CaseResult) {
+        let finding = usize::from(case.failed() || case.crash.is_some());
+        for tally in [
+            &mut self.total,
+            self.endpoints
+                .entry((case.method.to_string(), case.target.clone()))
+                .or_default(),
+        ] {
+            tally.cases += 1;
+            tally.findings += finding;
+        }
+    }
+
+    pub fn rate(&self) -> f64 {
+        let elapsed = self.started.elapsed().as_secs_f64();
+        if elapsed > 0.0 {
+            self.total.cases as f64 / elapsed
+        } else {
+            0.0
+        }
+    }
+
+    // Unknown until the first case is in.
+    pub fn eta(&self) -> Option<Duration> {
+        let rate = self.rate();
+        let left = self.planned.saturating_sub(self.total.cases);
+        (rate > 0.0).then(|| Duration::from_secs_f64(left as f64 / rate))
+    }
+
+    pub fn lines(&self) -> Vec<String> {
+        let filled = match self.planned {
+            0 => BAR_WIDTH,
+            planned => (self.total.cases * BAR_WIDTH / planned).min(BAR_WIDTH),
+        };
+        let eta = self.eta().map_or_else(|| "?".to_string(), format_duration);
+        let mut lines = vec![format!(
+            "[{}{}] {}/{} cases  {:.1}/s  {}  ETA {}",
+            "=".repeat(filled),
+            " ".repeat(BAR_WIDTH - filled),
+            self.total.cases,
+            self.planned,
+            self.rate(),
+            count(self.total.findings, "finding"),
+            eta
+        )];
+        let mut busiest: Vec<_> = self.endpoints.iter().collect();
+        busiest.sort_by_key(|(_, tally)| std::cmp::Reverse((tally.findings, tally.cases)));
+        for ((method, target), tally) in busiest.into_iter().take(ENDPOINT_LINES) {
+            lines.push(format!(
+                "  {} {}: {}, {}",
+                method,
+                target,
+                count(tally.cases, "case"),
+                count(tally.findings, "finding")
+            ));
+        }
+        lines
+    }
+
+    // Redraws at most every 100ms unless `force` is set.
+    pub fn draw(&mut self, force: bool) {
+        let now = Instant::now();
+        if !force
+            && self
+                .last_draw
+                .is_some_and(|last| now.duration_since(last) < REDRAW_EVERY)
+        {
+            return;
+        }
+        self.last_draw = Some(now);
+        let lines = self.lines();
+        let mut out = std::io::stderr().lock();
+        let _ = write!(out, "{}", erase(self.drawn));
+        for line in &lines {
+            let _ = writeln!(out, "{}", line);
+        }
+        let _ = out.flush();
+        self.drawn = lines.len();
+    }
+
+    pub fn clear(&mut self) {
+        let mut out = std::io::stderr().lock();
+        let _ = write!(out, "{}", erase(self.drawn));
+        let _ = out.flush();
+        self.drawn = 0;
+    }
+}
+
+fn enabled(quiet: bool, terminal: bool, term: Option<&str>) -> bool {
+    !quiet && terminal && term != Some("dumb")
+}
+
+// Moves the cursor up over `lines` lines and clears from there down.
+fn erase(lines: usize) -> String {
+    match lines {
+        0 => String::new(),
+        lines => format!("\x1b[{}A\x1b[J", lines),
+    }
+}
+
+fn count(n: usize, noun: &str) -> String {
+    format!("{} {}{}", n, noun, if n == 1 { "" } else { "s" })
+}
+
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    match secs {
+        0..=59 => format!("{}s", secs),
+        60..=3599 => format!("{}m{:02}s", secs / 60, secs % 60),
+        _ => format!("{}h{:02}m", secs / 3600, secs % 3600 / 60),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::client::TesterError;
+    use crate::runner::tests::case_result;
+    use reqwest::Method;
+
+    fn case(method: Method, target: &str, failed: bool) -> CaseResult {
+        let outcome = match failed {
+            false => Ok(()),
+            true => Err(TesterError::StatusMismatch {
+                endpoint: target.to_string(),
+                method: method.clone(),
+                client_status: 200,
+                actual_status: 404,
+            }),
+        };
+        case_result(method, target, outcome)
+    }
+
+    // As if the run began `elapsed` ago.
+    fn started(planned: usize, elapsed: Duration) -> Progress {
+        Progress {
+            started: Instant::now() - elapsed,
+            ..Progress::new(planned)
+        }
+    }
+
+    #[test]
+    fn eta_is_unknown_until_a_case_is_in() {
+        let mut progress = started(10, Duration::from_secs(10));
+        assert_eq!(progress.eta(), None);
+        for _ in 0..5 {
+            progress.record(&case(Method::GET, "/admin/quiz/list", false));
+        }
+        // Five cases in ten seconds leaves ten more seconds for the other five.
+        let eta = progress.eta().unwrap();
+        assert!(eta > Duration::from_secs(9) && eta < Duration::from_secs(11));
+        assert_eq!(format_duration(eta).chars().last(), Some('s'));
+    }
+
+    #[test]
+    fn eta_is_zero_past_the_plan() {
+        let mut progress = started(1, Duration::from_secs(1));
+        progress.record(&case(Method::GET, "/admin/quiz/list", false));
+        progress.record(&case(Method::GET, "/admin/quiz/list", false));
+        assert_eq!(progress.eta(), Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn lines_show_the_bar_then_the_busiest_endpoints() {
+        let mut progress = started(4, Duration::from_secs(30));
+        progress.record(&case(Method::GET, "/admin/quiz/list", false));
+        progress.record(&case(Method::GET, "/admin/quiz/list", false));
+        progress.record(&case(Method::POST, "/admin/quiz", true));
+        let lines = progress.lines();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with(&format!(
+            "[{}{}] 3/4 cases  0.1/s  1 finding  ETA ",
+            "=".repeat(22),
+            " ".repeat(8)
+        )));
+        assert_eq!(lines[1], "  POST /admin/quiz: 1 case, 1 finding");
+        assert_eq!(lines[2], "  GET /admin/quiz/list: 2 cases, 0 findings");
+    }
+
+    #[test]
+    fn lines_list_at_most_five_endpoints() {
+        let mut progress = Progress::new(0);
+        for id in 0..8 {
+            progress.record(&case(Method::GET, &format!("/admin/quiz/{}", id), false));
+        }
+        let lines = progress.lines();
+        assert_eq!(lines.len(), 1 + ENDPOINT_LINES);
+        // Without a plan the bar is full.
+        assert!(lines[0].starts_with(&format!("[{}] 8/0 cases", "=".repeat(BAR_WIDTH))));
+    }
+
+    #[test]
+    fn durations_and_erasing() {
+        assert_eq!(format_duration(Duration::from_secs(59)), "59s");
+        assert_eq!(format_duration(Duration::from_secs(61)), "1m01s");
+        assert_eq!(
+            format_duration(Duration::from_secs(3 * 3600 + 5 * 60)),
+            "3h05m"
+        );
+        assert_eq!(erase(0), "");
+        assert_eq!(erase(3), "\x1b[3A\x1b[J");
+    }
+
+    #[test]
+    fn only_capable_terminals_get_escapes() {
+        assert!(enabled(false, true, Some("xterm-256color")));
+        assert!(enabled(false, true, None));
+        assert!(!enabled(true, true, Some("xterm-256color")));
+        assert!(!enabled(false, false, Some("xterm-256color")));
+        assert!(!enabled(false, true, Some("dumb")));
+    }
+}
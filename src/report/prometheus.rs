@@ -0,0 +1,206 @@
+use crate::models::client::{ServerResponse, TestCase, TesterError};
+use crate::models::endpoints;
+use crate::models::observer::Observer;
+use crate::spec::without_query;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::path::Path;
+use std::sync::Mutex;
+
+// Upper bounds of the latency histogram, in seconds.
+const BUCKETS: [f64; 10] = [0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 10.0];
+
+// (server, method, route) where the route is the registry's path template
+// when there is one, so IDs don't each get a series.
+type Route = (String, String, String);
+
+#[derive(Debug, Default)]
+struct Latencies {
+    // Per bucket, not yet cumulative.
+    buckets: [u64; BUCKETS.len()],
+    count: u64,
+    sum: f64,
+}
+
+#[derive(Debug, Default)]
+struct Counters {
+    // Keyed by route and then status, or "error" for requests without one.
+    requests: BTreeMap<(Route, String), u64>,
+    latencies: BTreeMap<Route, Latencies>,
+}
+
+// Counts requests by server, route and status, with a latency histogram per
+// route, in Prometheus' text format: `render` for scraping it from a process
+// embedding the fuzzer, `write` for node_exporter's textfile collector.
+#[derive(Debug, Default)]
+pub struct PrometheusMetrics {
+    counters: Mutex<Counters>,
+}
+
+impl PrometheusMetrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn render(&self) -> String {
+        let counters = self.counters.lock().unwrap();
+        let mut out = String::new();
+        out.push_str("# HELP fuzzer_requests_total Requests sent, by response status.\n");
+        out.push_str("# TYPE fuzzer_requests_total counter\n");
+        for ((route, status), count) in &counters.requests {
+            let labels = format!("{},status=\"{}\"", labels(route), status);
+            let _ = writeln!(out, "fuzzer_requests_total{{{}}} {}", labels, count);
+        }
+        out.push_str(
+            "# HELP fuzzer_request_duration_seconds Time until the whole response was read.\n",
+        );
+        out.push_str("# TYPE fuzzer_request_duration_seconds histogram\n");
+        for (route, latencies) in &counters.latencies {
+            let labels = labels(route);
+            let mut cumulative = 0;
+            for (bound, count) in BUCKETS.iter().zip(latencies.buckets) {
+                cumulative += count;
+                let _ = writeln!(
+                    out,
+                    "fuzzer_request_duration_seconds_bucket{{{},le=\"{}\"}} {}",
+                    labels, bound, cumulative
+                );
+            }
+            let _ = writeln!(
+                out,
+                "fuzzer_request_duration_seconds_bucket{{{},le=\"+Inf\"}} {}",
+                labels, latencies.count
+            );
+            let _ = writeln!(
+                out,
+                "fuzzer_request_duration_seconds_sum{{{}}} {}",
+                labels, latencies.sum
+            );
+            let _ = writeln!(
+                out,
+                "fuzzer_request_duration_seconds_count{{{}}} {}",
+                labels, latencies.count
+            );
+        }
+        out
+    }
+
+    pub fn write(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        std::fs::write(path, self.render())
+    }
+}
+
+impl Observer for PrometheusMetrics {
+    fn on_response(
+        &self,
+        server: &str,
+        case: &TestCase,
+        response: Result<&ServerResponse, &TesterError>,
+    ) {
+        let path = without_query(&case.endpoint);
+        let template = endpoints::resolve(path).map_or(path, |endpoint| endpoint.path);
+        let route = (
+            server.to_string(),
+            case.method.to_string(),
+            template.to_string(),
+        );
+        let status = match response {
+            Ok(response) => response.status.to_string(),
+            Err(_) => "error".to_string(),
+        };
+        let mut counters = self.counters.lock().unwrap();
+        *counters
+            .requests
+            .entry((route.clone(), status))
+            .or_default() += 1;
+        // Failed requests have no latency worth recording.
+        if let Ok(response) = response {
+            let seconds = response.latency.as_secs_f64();
+            let latencies = counters.latencies.entry(route).or_default();
+            if let Some(bucket) = BUCKETS.iter().position(|bound| seconds <= *bound) {
+                latencies.buckets[bucket] += 1;
+            }
+            latencies.count += 1;
+            latencies.sum += seconds;
+        }
+    }
+}
+
+fn labels((server, method, route): &Route) -> String {
+    format!(
+        "server=\"{}\",method=\"{}\",route=\"{}\"",
+        escape(server),
+        escape(method),
+        escape(route)
+    )
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runner::tests::response;
+    use reqwest::Method;
+    use serde_json::json;
+
+    #[test]
+    fn requests_are_counted_by_route_template_and_status() {
+        let metrics = PrometheusMetrics::new();
+        let info =
+            |id: &str| TestCase::new(format!("/admin/quiz/{}?token=a", id), Method::GET, None);
+        metrics.on_response("http://a", &info("1"), Ok(&response(200, json!({}), 3)));
+        metrics.on_response("http://a", &info("2"), Ok(&response(200, json!({}), 300)));
+        let err = TesterError::InvalidUsage {
+            endpoint: "/admin/quiz/3".to_string(),
+            method: Method::GET,
+            message: "no".to_string(),
+        };
+        metrics.on_response("http://a", &info("3"), Err(&err));
+
+        let rendered = metrics.render();
+        let labels = r#"server="http://a",method="GET",route="/admin/quiz/{quizid}""#;
+        for line in [
+            format!(r#"fuzzer_requests_total{{{},status="200"}} 2"#, labels),
+            format!(r#"fuzzer_requests_total{{{},status="error"}} 1"#, labels),
+            format!(
+                r#"fuzzer_request_duration_seconds_bucket{{{},le="0.005"}} 1"#,
+                labels
+            ),
+            format!(
+                r#"fuzzer_request_duration_seconds_bucket{{{},le="0.25"}} 1"#,
+                labels
+            ),
+            format!(
+                r#"fuzzer_request_duration_seconds_bucket{{{},le="0.5"}} 2"#,
+                labels
+            ),
+            format!(
+                r#"fuzzer_request_duration_seconds_bucket{{{},le="+Inf"}} 2"#,
+                labels
+            ),
+            format!(r#"fuzzer_request_duration_seconds_count{{{}}} 2"#, labels),
+        ] {
+            assert!(
+                rendered.lines().any(|l| l == line),
+                "{} not in\n{}",
+                line,
+                rendered
+            );
+        }
+    }
+
+    #[test]
+    fn label_values_are_escaped() {
+        let route = ("a\"b".to_string(), "GET".to_string(), "/x\\y\n".to_string());
+        assert_eq!(
+            labels(&route),
+            r#"server="a\"b",method="GET",route="/x\\y\n""#
+        );
+    }
+}
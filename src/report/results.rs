@@ -0,0 +1,167 @@
+use crate::models::client::ServerResponse;
+use crate::report::RunReport;
+use crate::runner::CaseResult;
+use serde_json::{json, Value};
+use std::fmt::Write;
+use std::path::Path;
+
+const COLUMNS: [&str; 13] = [
+    "iteration",
+    "seed",
+    "method",
+    "target",
+    "endpoint",
+    "outcome",
+    "client_status",
+    "actual_status",
+    "client_latency_ms",
+    "actual_latency_ms",
+    "mismatch_path",
+    "crash",
+    "error",
+];
+
+// One row per case, for loading into pandas or a spreadsheet. Fields that
+// don't apply to a case (a missing response, no mismatch) are left empty.
+pub fn csv(report: &RunReport) -> String {
+    let mut out = COLUMNS.join(",");
+    out.push('\n');
+    for case in &report.cases {
+        let fields: Vec<String> = row(case)
+            .iter()
+            .map(|value| match value {
+                Value::Null => String::new(),
+                Value::String(s) => quote(s),
+                other => other.to_string(),
+            })
+            .collect();
+        let _ = writeln!(out, "{}", fields.join(","));
+    }
+    out
+}
+
+// The same columns as `csv`, as one JSON object per line, with nulls for the
+// empty fields.
+pub fn json_lines(report: &RunReport) -> String {
+    let mut out = String::new();
+    for case in &report.cases {
+        let object: serde_json::Map<String, Value> = COLUMNS
+            .iter()
+            .map(|column| column.to_string())
+            .zip(row(case))
+            .collect();
+        let _ = writeln!(out, "{}", Value::Object(object));
+    }
+    out
+}
+
+pub fn write_csv(report: &RunReport, path: impl AsRef<Path>) -> std::io::Result<()> {
+    std::fs::write(path, csv(report))
+}
+
+pub fn write_json_lines(report: &RunReport, path: impl AsRef<Path>) -> std::io::Result<()> {
+    std::fs::write(path, json_lines(report))
+}
+
+fn row(case: &CaseResult) -> [Value; 13] {
+    let latency_ms = |response: Option<&ServerResponse>| {
+        response.map_or(Value::Null, |response| {
+            json!(response.latency.as_micros() as f64 / 1000.0)
+        })
+    };
+    let err = case.outcome.as_ref().err();
+    let summary = err.map(|err| {
+        let message = err.to_string();
+        message.lines().next().unwrap_or_default().to_string()
+    });
+    [
+        json!(case.iteration),
+        // As a string, since seeds don't fit in a double.
+        json!(case.seed.to_string()),
+        json!(case.method.as_str()),
+        json!(case.target),
+        json!(case.endpoint),
+        json!(if case.flaky.is_some() {
+            "flaky"
+        } else if err.is_some() {
+            "failed"
+        } else {
+            "passed"
+        }),
+        json!(case.client.as_ref().map(|response| response.status)),
+        json!(case.actual.as_ref().map(|response| response.status)),
+        latency_ms(case.client.as_ref()),
+        latency_ms(case.actual.as_ref()),
+        json!(err.and_then(|err| err.path())),
+        json!(case.crash.map(|crash| crash.to_string())),
+        json!(summary),
+    ]
+}
+
+// RFC 4180: quoted only when needed, with inner quotes doubled.
+fn quote(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::client::TesterError;
+    use crate::runner::tests::{case_result, response};
+    use reqwest::Method;
+
+    fn report() -> RunReport {
+        let mut report = RunReport::new(1);
+        let mut passed = case_result(Method::GET, "/a,b", Ok(()));
+        passed.seed = u64::MAX;
+        passed.client = Some(response(200, Value::Null, 3));
+        report.cases.push(passed);
+        report.cases.push(case_result(
+            Method::POST,
+            "/q",
+            Err(TesterError::TypeMismatch {
+                endpoint: "/q".to_string(),
+                method: Method::POST,
+                path: "/id".to_string(),
+                client_value: Box::new(Value::Null),
+                actual_value: Box::new(json!(1)),
+            }),
+        ));
+        report
+    }
+
+    #[test]
+    fn csv_rows_quote_only_when_needed() {
+        assert_eq!(quote("plain"), "plain");
+        assert_eq!(quote("a\"b"), "\"a\"\"b\"");
+        let csv = csv(&report());
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], COLUMNS.join(","));
+        assert_eq!(
+            lines[1],
+            "0,18446744073709551615,GET,\"/a,b\",\"/a,b\",passed,200,,3.0,,,,"
+        );
+        assert_eq!(
+            lines[2],
+            "0,0,POST,/q,/q,failed,,,,,/id,,JSON type mismatch for `POST /q` (path `/id`)."
+        );
+    }
+
+    #[test]
+    fn json_lines_use_nulls_for_empty_fields() {
+        let out = json_lines(&report());
+        let rows: Vec<Value> = out
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0]["seed"], "18446744073709551615");
+        assert_eq!(rows[0]["client_latency_ms"], 3.0);
+        assert_eq!(rows[0]["actual_status"], Value::Null);
+        assert_eq!(rows[1]["outcome"], "failed");
+    }
+}
@@ -0,0 +1,237 @@
+use crate::models::client::TesterError;
+use crate::report::RunReport;
+use crate::runner::{CaseResult, Category, Crash};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+
+// Most severe first, so sorting puts crashes at the top.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    // 5xx responses, dropped connections and timeouts.
+    Crash,
+    Status,
+    // Anything wrong with a response's shape: types, array elements, the spec
+    // or a response schema, bodies that aren't JSON.
+    Type,
+    // Everything else: values, numbers and timestamps out of range, headers,
+    // assertions.
+    Value,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Severity::Crash => "crash",
+            Severity::Status => "status",
+            Severity::Type => "type",
+            Severity::Value => "value",
+        })
+    }
+}
+
+// Cases that most likely share a root cause: the same kind of mismatch at the
+// same JSON path of the same route.
+#[derive(Clone)]
+pub struct FindingGroup<'a> {
+    pub severity: Severity,
+    pub method: String,
+    // The path template.
+    pub target: String,
+    pub kind: String,
+    pub path: Option<String>,
+    pub count: usize,
+    // The earliest case in the group.
+    pub example: &'a CaseResult,
+}
+
+impl fmt::Display for FindingGroup<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "[{}] {} {}: {}",
+            self.severity, self.method, self.target, self.kind
+        )?;
+        if let Some(path) = &self.path {
+            write!(f, " at `{}`", path)?;
+        }
+        write!(
+            f,
+            " ({} {}, e.g. seed {})",
+            self.count,
+            if self.count == 1 { "case" } else { "cases" },
+            self.example.seed
+        )
+    }
+}
+
+impl RunReport {
+    // Failures and crashes grouped by root cause, most severe first and then
+    // by how many cases each group has. Flaky and unreachable cases are left
+    // out.
+    pub fn triage(&self) -> Vec<FindingGroup<'_>> {
+        let mut groups: BTreeMap<(String, String, String, Option<String>), FindingGroup> =
+            BTreeMap::new();
+        for case in &self.cases {
+            let Some((severity, kind, path)) = classify(case) else {
+                continue;
+            };
+            let key = (
+                case.method.to_string(),
+                case.target.clone(),
+                kind.clone(),
+                path.clone(),
+            );
+            groups
+                .entry(key)
+                .and_modify(|group| group.count += 1)
+                .or_insert_with(|| FindingGroup {
+                    severity,
+                    method: case.method.to_string(),
+                    target: case.target.clone(),
+                    kind,
+                    path,
+                    count: 1,
+                    example: case,
+                });
+        }
+        let mut groups: Vec<FindingGroup> = groups.into_values().collect();
+        groups.sort_by(|a, b| a.severity.cmp(&b.severity).then(b.count.cmp(&a.count)));
+        groups
+    }
+}
+
+// A crash outranks whatever mismatch came with it. Cases that never reached a
+// server aren't findings at all.
+fn classify(case: &CaseResult) -> Option<(Severity, String, Option<String>)> {
+    if case.category() == Category::Unreachable {
+        return None;
+    }
+    match (case.crash, &case.outcome) {
+        (Some(Crash::ServerError(_)), _) => {
+            Some((Severity::Crash, "server error".to_string(), None))
+        }
+        (Some(Crash::ConnectionLost), _) => {
+            Some((Severity::Crash, "connection lost".to_string(), None))
+        }
+        (None, Err(err)) if case.failed() => {
+            let (severity, kind) = kind(err);
+            Some((severity, kind.to_string(), err.path().map(str::to_string)))
+        }
+        (None, _) => None,
+    }
+}
+
+fn kind(err: &TesterError) -> (Severity, &'static str) {
+    match err {
+        TesterError::Network { .. } => (Severity::Crash, "request failed"),
+        TesterError::Timeout { .. } => (Severity::Crash, "timeout"),
+        TesterError::TimeoutDivergence { .. } => (Severity::Crash, "timeout divergence"),
+        TesterError::StatusMismatch { .. } => (Severity::Status, "status mismatch"),
+        TesterError::RedirectMismatch { .. } => (Severity::Status, "redirect mismatch"),
+        TesterError::Json { .. } => (Severity::Type, "invalid JSON"),
+        TesterError::NonJsonBody { .. } => (Severity::Type, "non-JSON body"),
+        TesterError::JsonHygiene { .. } => (Severity::Type, "non-standard JSON"),
+        TesterError::BodyTooLarge { .. } => (Severity::Type, "body too large"),
+        TesterError::ContentMismatch { .. } => (Severity::Type, "content mismatch"),
+        TesterError::TypeMismatch { .. } => (Severity::Type, "type mismatch"),
+        TesterError::ArrayElementMismatch { .. } => (Severity::Type, "array element mismatch"),
+        TesterError::ArrayLengthMismatch { .. } => (Severity::Type, "array length mismatch"),
+        TesterError::SpecViolation { .. } => (Severity::Type, "spec violation"),
+        TesterError::SchemaViolation { .. } => (Severity::Type, "schema violation"),
+        TesterError::UnexpectedBody { .. } => (Severity::Type, "unexpected body"),
+        TesterError::ValueMismatch { .. } => (Severity::Value, "value mismatch"),
+        TesterError::NumberOutOfRange { .. } => (Severity::Value, "number out of range"),
+        TesterError::TimestampOutOfRange { .. } => (Severity::Value, "timestamp out of range"),
+        TesterError::FieldRuleViolation { .. } => (Severity::Value, "field rule broken"),
+        TesterError::HttpVersionMismatch { .. } => (Severity::Status, "HTTP version mismatch"),
+        TesterError::HeaderMismatch { .. } => (Severity::Value, "header mismatch"),
+        TesterError::AssertionFailed { .. } => (Severity::Value, "assertion failed"),
+        TesterError::InvalidUsage { .. } => (Severity::Value, "invalid usage"),
+        TesterError::RepeatMismatch { source, .. } => kind(source),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runner::tests::case_result;
+    use reqwest::Method;
+    use serde_json::Value;
+
+    fn type_mismatch(target: &str, path: &str) -> Result<(), TesterError> {
+        Err(TesterError::TypeMismatch {
+            endpoint: target.to_string(),
+            method: Method::GET,
+            path: path.to_string(),
+            client_value: Box::new(Value::Null),
+            actual_value: Box::new(Value::Bool(true)),
+        })
+    }
+
+    fn status_mismatch(target: &str) -> Result<(), TesterError> {
+        Err(TesterError::StatusMismatch {
+            endpoint: target.to_string(),
+            method: Method::GET,
+            client_status: 200,
+            actual_status: 404,
+        })
+    }
+
+    #[test]
+    fn findings_are_grouped_by_route_kind_and_path() {
+        let mut report = RunReport::new(1);
+        for (seed, path) in [(3, "/id"), (1, "/id"), (2, "/name")] {
+            let mut case = case_result(Method::GET, "/users", type_mismatch("/users", path));
+            case.seed = seed;
+            report.cases.push(case);
+        }
+        report
+            .cases
+            .push(case_result(Method::GET, "/users", Ok(())));
+        let groups = report.triage();
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].path.as_deref(), Some("/id"));
+        assert_eq!(groups[0].count, 2);
+        assert_eq!(groups[0].example.seed, 3);
+        assert_eq!(
+            groups[0].to_string(),
+            "[type] GET /users: type mismatch at `/id` (2 cases, e.g. seed 3)"
+        );
+        assert_eq!(groups[1].count, 1);
+    }
+
+    #[test]
+    fn crashes_come_first_and_flaky_cases_are_left_out() {
+        let mut report = RunReport::new(1);
+        report
+            .cases
+            .push(case_result(Method::GET, "/a", type_mismatch("/a", "/id")));
+        report
+            .cases
+            .push(case_result(Method::GET, "/b", status_mismatch("/b")));
+        let mut crashed = case_result(Method::GET, "/c", status_mismatch("/c"));
+        crashed.crash = Some(Crash::ServerError(500));
+        report.cases.push(crashed);
+        let mut flaky = case_result(Method::GET, "/d", status_mismatch("/d"));
+        flaky.flaky = Some(crate::runner::Flaky {
+            reruns: 3,
+            reproduced: 0,
+        });
+        report.cases.push(flaky);
+        let severities: Vec<(Severity, String)> = report
+            .triage()
+            .into_iter()
+            .map(|group| (group.severity, group.kind))
+            .collect();
+        assert_eq!(
+            severities,
+            [
+                (Severity::Crash, "server error".to_string()),
+                (Severity::Status, "status mismatch".to_string()),
+                (Severity::Type, "type mismatch".to_string()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,82 @@
+use rand::rngs::StdRng;
+use rand::{Rng, RngCore, SeedableRng};
+
+// The single source of randomness for a run. Everything random (generated
+// payloads, path parameters, scheduling order) is drawn from an instance of
+// this, so a run is fully determined by its seed.
+#[derive(Debug, Clone)]
+pub struct FuzzRng {
+    seed: u64,
+    rng: StdRng,
+}
+
+impl FuzzRng {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    pub fn from_entropy() -> Self {
+        Self::new(rand::random())
+    }
+
+    pub fn from_seed_or_entropy(seed: Option<u64>) -> Self {
+        seed.map(Self::new).unwrap_or_else(Self::from_entropy)
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    // Derives an independent generator whose own seed is enough to reproduce
+    // everything drawn from it.
+    pub fn fork(&mut self) -> FuzzRng {
+        FuzzRng::new(self.rng.gen())
+    }
+}
+
+impl RngCore for FuzzRng {
+    fn next_u32(&mut self) -> u32 {
+        self.rng.next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.rng.next_u64()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.rng.fill_bytes(dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.rng.try_fill_bytes(dest)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn draws(rng: &mut FuzzRng) -> Vec<u64> {
+        (0..8).map(|_| rng.next_u64()).collect()
+    }
+
+    #[test]
+    fn the_same_seed_draws_the_same_values() {
+        assert_eq!(draws(&mut FuzzRng::new(7)), draws(&mut FuzzRng::new(7)));
+        assert_ne!(draws(&mut FuzzRng::new(7)), draws(&mut FuzzRng::new(8)));
+    }
+
+    #[test]
+    fn forks_are_reproducible_from_their_own_seed() {
+        let mut parent = FuzzRng::new(7);
+        let mut fork = parent.fork();
+        let mut replay = FuzzRng::new(fork.seed());
+        assert_eq!(draws(&mut fork), draws(&mut replay));
+
+        // The parent moves on, so the next fork is a different one.
+        assert_ne!(parent.fork().seed(), fork.seed());
+    }
+}
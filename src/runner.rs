@@ -0,0 +1,1956 @@
+use crate::artifacts::{ArtifactError, Artifacts};
+use crate::campaign::{Campaign, CampaignError};
+use crate::config::{Config, ConfigError};
+use crate::corpus::{Corpus, CorpusError, CorpusInput, RecordedRequest};
+use crate::fuzz::generator::{Generator, GeneratorProfile};
+use crate::fuzz::headers::HeaderPerturbation;
+use crate::fuzz::mutator::{MutationEngine, Mutator};
+use crate::fuzz::paths::PathPerturbation;
+use crate::fuzz::presets::Preset;
+use crate::fuzz::protocol::Malformation;
+use crate::fuzz::scheduler::{self, Schedule, Weights};
+use crate::fuzz::shrink;
+use crate::fuzz::template::Templates;
+use crate::models::assertion::{Assertion, Assertions};
+use crate::models::body::BodyKind;
+use crate::models::client::{Comparison, ServerResponse, TestCase, Tester, TesterError};
+use crate::models::endpoints::{self, Endpoint};
+use crate::models::multi::MultiTester;
+use crate::models::observer::Observer;
+use crate::report::{MultiReport, RunReport};
+use crate::rng::FuzzRng;
+use crate::schema::Schema;
+use crate::spec::{Parameter, ParameterLocation, Spec};
+use rand::seq::SliceRandom;
+use rand::Rng;
+use reqwest::Method;
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::fmt;
+use std::future::Future;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio_util::sync::CancellationToken;
+
+const PROBE_METHODS: [Method; 5] = [
+    Method::GET,
+    Method::POST,
+    Method::PUT,
+    Method::DELETE,
+    Method::PATCH,
+];
+
+#[derive(Debug, Clone)]
+pub struct Target {
+    pub method: Method,
+    pub path: String,
+    pub body: Option<Value>,
+    pub body_kind: BodyKind,
+    pub examples: Vec<Value>,
+    pub body_schema: Option<Schema>,
+    pub path_params: Vec<(String, Schema)>,
+    // Query parameters sent as given on every case.
+    pub query: BTreeMap<String, String>,
+    // Query parameters from the spec, generated for each case unless fixed in
+    // `query`. Optional ones are only sent some of the time.
+    pub query_params: Vec<Parameter>,
+    // Sent with a method the path doesn't support; not a route of its own.
+    pub probe: bool,
+    // The runner's generator when unset.
+    pub generator: Option<Generator>,
+}
+
+impl Target {
+    fn param_schema(&self, name: &str) -> Schema {
+        self.path_params
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, s)| s.clone())
+            .unwrap_or(Schema::Integer)
+    }
+}
+
+// A sign the server under test fell over, as opposed to merely disagreeing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Crash {
+    ServerError(u16),
+    // The connection failed or was reset before a response arrived.
+    ConnectionLost,
+}
+
+impl Crash {
+    pub(crate) fn detect(comparison: &Comparison) -> Option<Self> {
+        match (&comparison.client, &comparison.outcome) {
+            (Some(response), _) if response.status >= 500 => {
+                Some(Crash::ServerError(response.status))
+            }
+            // The server under test is always asked first, so a network error
+            // without its response is its own.
+            (None, Err(TesterError::Network { .. })) => Some(Crash::ConnectionLost),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Crash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Crash::ServerError(status) => write!(f, "server error {}", status),
+            Crash::ConnectionLost => write!(f, "connection lost"),
+        }
+    }
+}
+
+pub struct CaseResult {
+    pub iteration: usize,
+    pub seed: u64,
+    pub method: Method,
+    // The path template the endpoint was generated from.
+    pub target: String,
+    pub endpoint: String,
+    pub query: Vec<(String, String)>,
+    pub body: Option<Value>,
+    pub malformation: Option<Malformation>,
+    pub header_perturbation: Option<HeaderPerturbation>,
+    pub client: Option<ServerResponse>,
+    pub actual: Option<ServerResponse>,
+    pub outcome: Result<(), TesterError>,
+    pub minimized: Option<Value>,
+    pub recorded: Option<Result<PathBuf, CorpusError>>,
+    pub artifacts: Option<Result<PathBuf, ArtifactError>>,
+    // Only set with crash detection on.
+    pub crash: Option<Crash>,
+    // Set when the case diverged but too few reruns diverged again for it to
+    // count as a failure.
+    pub flaky: Option<Flaky>,
+}
+
+// What a case came to, for telling a failing server from one that couldn't be
+// reached at all.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Category {
+    Passed,
+    Flaky,
+    // Failed or crashed.
+    Finding,
+    // A server refused the connection, so nothing was compared. A server the
+    // case itself brought down shows up as a finding (with crash detection
+    // on, a lost connection), and any refused after it as unreachable.
+    Unreachable,
+}
+
+impl CaseResult {
+    // Flaky cases keep their first error but don't count as failures.
+    pub fn failed(&self) -> bool {
+        self.outcome.is_err() && self.flaky.is_none()
+    }
+
+    pub fn category(&self) -> Category {
+        match &self.outcome {
+            Err(TesterError::Network { source, .. }) if source.is_connect() => {
+                Category::Unreachable
+            }
+            _ if self.failed() || self.crash.is_some() => Category::Finding,
+            _ if self.flaky.is_some() => Category::Flaky,
+            _ => Category::Passed,
+        }
+    }
+}
+
+// How often a diverging case diverged again when sent `reruns` more times.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Flaky {
+    pub reruns: usize,
+    pub reproduced: usize,
+}
+
+impl fmt::Display for Flaky {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "flaky: diverged again on {} of {} reruns",
+            self.reproduced, self.reruns
+        )
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PlannedCase {
+    pub iteration: usize,
+    pub seed: u64,
+    // The path template the case was generated from.
+    pub target: String,
+    pub case: TestCase,
+}
+
+pub struct Runner {
+    tester: Tester,
+    targets: Vec<Target>,
+    iterations: usize,
+    generator: Generator,
+    mutator: MutationEngine,
+    templates: Templates,
+    rng: FuzzRng,
+    shuffle: bool,
+    schedule: Schedule,
+    weights: Weights,
+    // Whether a registration has succeeded yet, which unlocks the targets
+    // needing a session under the weighted schedule.
+    registered: bool,
+    corpus: Option<Corpus>,
+    artifacts: Option<Artifacts>,
+    shrink_attempts: Option<usize>,
+    // Reruns of each diverging case, and how many must diverge again.
+    reruns: Option<(usize, usize)>,
+    run_timeout: Option<Duration>,
+    max_cases: Option<usize>,
+    max_findings: Option<usize>,
+    cancel: CancellationToken,
+    routes: Vec<(Method, String)>,
+    protocol_rate: f64,
+    header_rate: f64,
+    path_rate: f64,
+    crash_detection: bool,
+    assertions: Assertions,
+    // Corpus bodies still to be mutated, by target index; these go before any
+    // freshly generated input.
+    pending: BTreeMap<usize, VecDeque<Value>>,
+    // Features (see `features`) already reached, keyed by route.
+    seen: BTreeSet<(String, String, String)>,
+    state_file: Option<PathBuf>,
+    resumed: Option<Campaign>,
+}
+
+impl Runner {
+    pub fn new(tester: Tester, targets: Vec<Target>, iterations: usize, rng: FuzzRng) -> Self {
+        Self {
+            tester,
+            targets,
+            iterations,
+            generator: Generator::default(),
+            mutator: MutationEngine::default(),
+            templates: Templates::default(),
+            rng,
+            shuffle: false,
+            schedule: Schedule::default(),
+            weights: Weights::new(),
+            registered: false,
+            corpus: None,
+            artifacts: None,
+            shrink_attempts: None,
+            reruns: None,
+            run_timeout: None,
+            max_cases: None,
+            max_findings: None,
+            cancel: CancellationToken::new(),
+            routes: Vec::new(),
+            protocol_rate: 0.0,
+            header_rate: 0.0,
+            path_rate: 0.0,
+            crash_detection: false,
+            assertions: Assertions::new(),
+            pending: BTreeMap::new(),
+            seen: BTreeSet::new(),
+            state_file: None,
+            resumed: None,
+        }
+    }
+
+    pub fn with_shrinking(mut self, max_attempts: usize) -> Self {
+        self.shrink_attempts = Some(max_attempts);
+        self
+    }
+
+    // Sends each diverging case `reruns` more times and only reports it when
+    // at least `reproduce` of them diverge too (a higher `reproduce` is
+    // lowered to `reruns`); the rest are marked flaky. Flaky cases are neither
+    // shrunk nor recorded.
+    pub fn with_flaky_reruns(mut self, reruns: usize, reproduce: usize) -> Self {
+        self.reruns = Some((reruns, reproduce.min(reruns)));
+        self
+    }
+
+    // Placeholders like `{{ email }}` in fixed bodies, examples and fixed query
+    // values are expanded with fresh data on every case, before mutation.
+    pub fn with_templates(mut self, templates: Templates) -> Self {
+        self.templates = templates;
+        self
+    }
+
+    // For targets without a generator of their own.
+    pub fn with_generator(mut self, generator: Generator) -> Self {
+        self.generator = generator;
+        self
+    }
+
+    pub fn with_mutation_engine(mut self, mutator: MutationEngine) -> Self {
+        self.mutator = mutator;
+        self
+    }
+
+    pub fn with_mutator(mut self, mutator: impl Mutator + 'static) -> Self {
+        self.mutator = self.mutator.with(mutator);
+        self
+    }
+
+    // Visit the targets in a different (seeded) order on every iteration.
+    pub fn with_shuffle(mut self, shuffle: bool) -> Self {
+        self.shuffle = shuffle;
+        self
+    }
+
+    // How targets are picked for each iteration; see `Schedule`. `weights` only
+    // matter to the weighted schedule.
+    pub fn with_schedule(mut self, schedule: Schedule, weights: Weights) -> Self {
+        self.schedule = schedule;
+        self.weights = weights;
+        self
+    }
+
+    pub fn with_corpus(mut self, corpus: Corpus) -> Self {
+        self.corpus = Some(corpus);
+        self
+    }
+
+    // Saves a `Campaign` to `path` after every iteration.
+    pub fn with_state_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.state_file = Some(path.into());
+        self
+    }
+
+    // Carries on from `campaign`: the cases it already sent are skipped, and
+    // its corpus inputs replace any given with `with_corpus_inputs`. The
+    // runner must have been built with the campaign's seed and targets.
+    pub fn resume(mut self, campaign: Campaign) -> Result<Self, CampaignError> {
+        if campaign.seed != self.seed() {
+            return Err(CampaignError::Mismatch(format!(
+                "it is for seed {}, not {}",
+                campaign.seed,
+                self.seed()
+            )));
+        }
+        if campaign.targets != self.target_names() {
+            return Err(CampaignError::Mismatch(
+                "the targets have changed since it was saved".to_string(),
+            ));
+        }
+        self.pending = campaign
+            .pending
+            .iter()
+            .map(|(index, bodies)| (*index, bodies.iter().cloned().collect()))
+            .collect();
+        self.seen = campaign.seen.iter().cloned().collect();
+        self.resumed = Some(campaign);
+        Ok(self)
+    }
+
+    fn target_names(&self) -> Vec<String> {
+        self.targets
+            .iter()
+            .map(|target| format!("{} {}", target.method, target.path))
+            .collect()
+    }
+
+    // See `Tester::with_observer`.
+    pub fn with_observer(mut self, observer: Arc<dyn Observer>) -> Self {
+        self.tester = self.tester.with_observer(observer);
+        self
+    }
+
+    // Dumps every failure and crash that isn't flaky; see `Artifacts`.
+    pub fn with_artifacts(mut self, artifacts: Artifacts) -> Self {
+        self.tester = self.tester.with_capture();
+        self.artifacts = Some(artifacts);
+        self
+    }
+
+    // Earlier runs' interesting inputs are mutated first, one per case, and
+    // their features don't count as new again. Inputs for targets this run
+    // doesn't have are dropped.
+    pub fn with_corpus_inputs(mut self, inputs: Vec<CorpusInput>) -> Self {
+        for input in inputs {
+            let Some(index) = self.targets.iter().position(|target| {
+                target.method.as_str() == input.request.method && target.path == input.target
+            }) else {
+                continue;
+            };
+            for feature in input.features {
+                self.seen
+                    .insert((input.request.method.clone(), input.target.clone(), feature));
+            }
+            if let Some(body) = input.request.body {
+                self.pending.entry(index).or_default().push_back(body);
+            }
+        }
+        self
+    }
+
+    // Stops the run once this much time has passed; cases still in flight are
+    // abandoned and the report is marked as timed out.
+    pub fn with_run_timeout(mut self, timeout: Duration) -> Self {
+        self.run_timeout = Some(timeout);
+        self
+    }
+
+    // Sends no more than `cases` cases in all, cutting the last iteration
+    // short if need be.
+    pub fn with_max_cases(mut self, cases: usize) -> Self {
+        self.max_cases = Some(cases);
+        self
+    }
+
+    // Stops the run as soon as `findings` cases have failed or crashed, e.g. 1
+    // to stop at the first one. Flaky cases don't count.
+    pub fn with_max_findings(mut self, findings: usize) -> Self {
+        self.max_findings = Some(findings);
+        self
+    }
+
+    // Stops the run once `cancel` is cancelled. Cases still in flight are
+    // abandoned, while everything finished so far stays in the report (and the
+    // corpus), which is marked as cancelled.
+    pub fn with_cancellation(mut self, cancel: CancellationToken) -> Self {
+        self.cancel = cancel;
+        self
+    }
+
+    // Routes that coverage is measured against, on top of the targets
+    // themselves.
+    pub fn with_routes(mut self, routes: impl IntoIterator<Item = (Method, String)>) -> Self {
+        self.routes.extend(routes);
+        self
+    }
+
+    // Adds a target for every method a known path doesn't support, so both
+    // servers' 404/405 handling is compared too.
+    pub fn with_method_probes(mut self) -> Self {
+        let routes = self.routes();
+        let mut paths: Vec<&str> = Vec::new();
+        for (_, path) in &routes {
+            if !paths.contains(&path.as_str()) {
+                paths.push(path);
+            }
+        }
+
+        let mut probes = Vec::new();
+        for path in paths {
+            for method in PROBE_METHODS {
+                if routes.iter().any(|(m, p)| *m == method && p == path) {
+                    continue;
+                }
+                probes.push(Target {
+                    method,
+                    path: path.to_string(),
+                    body: None,
+                    body_kind: BodyKind::default(),
+                    examples: Vec::new(),
+                    body_schema: None,
+                    path_params: Vec::new(),
+                    query: BTreeMap::new(),
+                    query_params: Vec::new(),
+                    probe: true,
+                    generator: None,
+                });
+            }
+        }
+        self.targets.extend(probes);
+        self
+    }
+
+    // Sends this fraction of cases (other than GETs) with a malformed body
+    // instead: truncated or invalid JSON, the wrong Content-Type, a huge or an
+    // empty body.
+    pub fn with_protocol_fuzzing(mut self, rate: f64) -> Self {
+        // `clamp` keeps NaN, which `gen_bool` panics on.
+        self.protocol_rate = if rate.is_nan() {
+            0.0
+        } else {
+            rate.clamp(0.0, 1.0)
+        };
+        self
+    }
+
+    // Sends this fraction of cases with the session token's header mangled:
+    // left out, empty, padded with whitespace, with Unicode in it, twice or
+    // far too long.
+    pub fn with_header_fuzzing(mut self, rate: f64) -> Self {
+        self.header_rate = if rate.is_nan() {
+            0.0
+        } else {
+            rate.clamp(0.0, 1.0)
+        };
+        self
+    }
+
+    // Sends this fraction of cases (of targets with path parameters) with one
+    // parameter replaced by an adversarial value; see `PathPerturbation`.
+    pub fn with_path_fuzzing(mut self, rate: f64) -> Self {
+        self.path_rate = if rate.is_nan() {
+            0.0
+        } else {
+            rate.clamp(0.0, 1.0)
+        };
+        self
+    }
+
+    // Flags 5xx responses and dropped connections from the server under test
+    // as crashes and saves their inputs to the corpus even when both servers
+    // agree. A run of connection failures counts as one crash, caused by the
+    // first case in it.
+    pub fn with_crash_detection(mut self) -> Self {
+        self.crash_detection = true;
+        self
+    }
+
+    // Runs `assertion` on every case of `method` and `template` whose built-in
+    // comparisons passed; any findings fail the case.
+    pub fn with_assertion(
+        mut self,
+        method: Method,
+        template: impl Into<String>,
+        assertion: impl Assertion + 'static,
+    ) -> Self {
+        self.assertions = self.assertions.with(method, template, assertion);
+        self
+    }
+
+    pub fn from_config(config: &Config) -> Result<Self, ConfigError> {
+        let spec = config.load_spec()?;
+        let targets = targets(config, spec.as_ref())?;
+        let routes: Vec<(Method, String)> = match &spec {
+            Some(spec) => spec
+                .operations()
+                .iter()
+                .map(|op| (op.method.clone(), op.path.clone()))
+                .collect(),
+            None => endpoints::routes()
+                .map(|(method, path)| (method, path.to_string()))
+                .collect(),
+        };
+        let mut tester = config.tester(spec)?;
+        // Only here: models and races read the lists they fetch.
+        if let Some(bytes) = config.stream_above {
+            tester = tester.with_streaming(bytes);
+        }
+        let templates = Templates::default();
+        for target in &targets {
+            let query = target.query.values().map(|v| Value::from(v.as_str()));
+            for value in target
+                .body
+                .iter()
+                .chain(&target.examples)
+                .cloned()
+                .chain(query)
+            {
+                templates.check(&value)?;
+            }
+        }
+
+        let rng = FuzzRng::from_seed_or_entropy(config.seed);
+        let mut runner = Self::new(tester, targets, config.iterations, rng)
+            .with_shuffle(config.shuffle)
+            .with_mutation_engine(
+                MutationEngine::default().with_max_mutations(config.max_mutations),
+            )
+            .with_schedule(config.schedule, config.weights()?)
+            .with_templates(templates)
+            .with_routes(routes)
+            .with_generator(Generator::new(
+                config.generator.profile(&GeneratorProfile::default()),
+            ));
+        if let Some(dir) = &config.corpus {
+            let corpus = Corpus::new(dir);
+            let inputs = corpus.load_inputs()?;
+            runner = runner.with_corpus(corpus).with_corpus_inputs(inputs);
+        }
+        if let Some(path) = &config.campaign {
+            runner = runner.with_state_file(path);
+        }
+        if let Some(dir) = &config.artifacts {
+            runner = runner.with_artifacts(Artifacts::new(dir));
+        }
+        if config.shrink {
+            runner = runner.with_shrinking(shrink::DEFAULT_MAX_ATTEMPTS);
+        }
+        if let Some(flaky) = &config.flaky {
+            runner = runner.with_flaky_reruns(flaky.reruns, flaky.reproduce);
+        }
+        if let Some(rate) = config.protocol_fuzzing {
+            runner = runner.with_protocol_fuzzing(rate);
+        }
+        if let Some(rate) = config.header_fuzzing {
+            runner = runner.with_header_fuzzing(rate);
+        }
+        if let Some(rate) = config.path_fuzzing {
+            runner = runner.with_path_fuzzing(rate);
+        }
+        if config.probe_methods {
+            runner = runner.with_method_probes();
+        }
+        if config.crash_detection {
+            runner = runner.with_crash_detection();
+        }
+        if let Some(seconds) = config.run_timeout {
+            runner = runner.with_run_timeout(Duration::from_secs_f64(seconds));
+        }
+        if let Some(cases) = config.max_cases {
+            runner = runner.with_max_cases(cases);
+        }
+        let max_findings = match (config.max_findings, config.fail_fast) {
+            (_, true) => Some(1),
+            (max_findings, false) => max_findings,
+        };
+        if let Some(findings) = max_findings {
+            runner = runner.with_max_findings(findings);
+        }
+        Ok(runner)
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.rng.seed()
+    }
+
+    pub fn tester(&self) -> &Tester {
+        &self.tester
+    }
+
+    pub fn targets(&self) -> &[Target] {
+        &self.targets
+    }
+
+    // How many cases a run sends if nothing stops it early.
+    pub fn planned(&self) -> usize {
+        let planned = self.iterations * self.targets.len();
+        self.max_cases.map_or(planned, |max| planned.min(max))
+    }
+
+    pub fn routes(&self) -> Vec<(Method, String)> {
+        let mut routes = self.routes.clone();
+        for target in self.targets.iter().filter(|t| !t.probe) {
+            let route = (target.method.clone(), target.path.clone());
+            if !routes.contains(&route) {
+                routes.push(route);
+            }
+        }
+        routes
+    }
+
+    pub fn case(&self, target: &Target, seed: u64) -> TestCase {
+        self.case_from(target, seed, None)
+    }
+
+    // `base`, when given, is mutated in place of the target's own body source.
+    fn case_from(&self, target: &Target, seed: u64, base: Option<&Value>) -> TestCase {
+        let mut rng = FuzzRng::new(seed);
+        let generator = target.generator.as_ref().unwrap_or(&self.generator);
+        let mut endpoint =
+            generator.fill_path(&target.path, |name| target.param_schema(name), &mut rng);
+        // Only drawn when enabled, like the header perturbation below.
+        let params: Vec<usize> = target
+            .path
+            .split('/')
+            .enumerate()
+            .filter(|(_, segment)| segment.starts_with('{'))
+            .map(|(index, _)| index)
+            .collect();
+        if self.path_rate > 0.0 && !params.is_empty() && rng.gen_bool(self.path_rate) {
+            let perturbation = PathPerturbation::ALL[rng.gen_range(0..PathPerturbation::ALL.len())];
+            let param = params[rng.gen_range(0..params.len())];
+            let mut segments: Vec<String> = endpoint.split('/').map(str::to_string).collect();
+            segments[param] = perturbation.apply(&segments[param]);
+            endpoint = segments.join("/");
+        }
+        let body = match (&target.body, &target.body_schema) {
+            _ if base.is_some() => base.map(|base| self.mutator.mutate(base, &mut rng)),
+            (Some(body), _) => Some(self.expand(body, &mut rng)),
+            (None, _) if !target.examples.is_empty() => {
+                let example = &target.examples[rng.gen_range(0..target.examples.len())];
+                let example = self.expand(example, &mut rng);
+                Some(self.mutator.mutate(&example, &mut rng))
+            }
+            (None, Some(schema)) => Some(generator.generate(schema, &mut rng)),
+            (None, None) => None,
+        };
+        let mut query: Vec<(String, String)> = target
+            .query
+            .iter()
+            .map(
+                |(k, v)| match self.expand(&Value::from(v.as_str()), &mut rng) {
+                    Value::String(s) => (k.clone(), s),
+                    other => (k.clone(), other.to_string()),
+                },
+            )
+            .collect();
+        for param in &target.query_params {
+            if target.query.contains_key(&param.name) || !(param.required || rng.gen_bool(0.5)) {
+                continue;
+            }
+            let value = generator.generate_param(&param.schema, &mut rng);
+            query.push((param.name.clone(), value));
+        }
+        let mut case = TestCase::new(endpoint, target.method.clone(), body)
+            .with_query(query)
+            .with_body_kind(target.body_kind)
+            .with_seed(seed);
+        if target.method != Method::GET
+            && self.protocol_rate > 0.0
+            && rng.gen_bool(self.protocol_rate)
+        {
+            let malformation = Malformation::ALL[rng.gen_range(0..Malformation::ALL.len())];
+            case = case.with_malformation(malformation);
+        }
+        // Only drawn when enabled, so seeds from runs without it still
+        // reproduce.
+        if self.header_rate > 0.0 && rng.gen_bool(self.header_rate) {
+            let perturbation =
+                HeaderPerturbation::ALL[rng.gen_range(0..HeaderPerturbation::ALL.len())];
+            case = case.with_header_perturbation(perturbation);
+        }
+        case
+    }
+
+    // Templates are checked up front by `from_config`; one that still fails
+    // is sent as written.
+    fn expand(&self, value: &Value, rng: &mut FuzzRng) -> Value {
+        self.templates
+            .expand(value, rng)
+            .unwrap_or_else(|_| value.clone())
+    }
+
+    // Every case `run` would send, in order, without sending any of them. The
+    // RNG is restored afterwards, so a following `run` sends exactly this plan;
+    // under the weighted schedule, only until a registration succeeds, since
+    // the plan can't know when one will.
+    pub fn plan(&mut self) -> Vec<PlannedCase> {
+        let rng = self.rng.clone();
+        let pending = self.pending.clone();
+        let mut plan = Vec::new();
+        for iteration in 0..self.iterations {
+            let Some((order, seeds, cases)) = self.next_iteration(plan.len()) else {
+                break;
+            };
+            for ((index, seed), case) in order.into_iter().zip(seeds).zip(cases) {
+                plan.push(PlannedCase {
+                    iteration,
+                    seed,
+                    target: self.targets[index].path.clone(),
+                    case,
+                });
+            }
+        }
+        self.rng = rng;
+        self.pending = pending;
+        plan
+    }
+
+    // The next iteration's cases, cut short so that no more than `max_cases`
+    // are sent after `sent`, or `None` once they all have been.
+    fn next_iteration(&mut self, sent: usize) -> Option<(Vec<usize>, Vec<u64>, Vec<TestCase>)> {
+        let left = self
+            .max_cases
+            .map_or(usize::MAX, |max| max.saturating_sub(sent));
+        if left == 0 {
+            return None;
+        }
+        let (mut order, mut seeds, mut cases) = self.iteration();
+        order.truncate(left);
+        seeds.truncate(left);
+        cases.truncate(left);
+        Some((order, seeds, cases))
+    }
+
+    // The target indices, seeds and cases of the next iteration.
+    fn iteration(&mut self) -> (Vec<usize>, Vec<u64>, Vec<TestCase>) {
+        let count = self.targets.len();
+        let order = match self.schedule {
+            Schedule::RoundRobin => {
+                let mut order: Vec<usize> = (0..count).collect();
+                if self.shuffle {
+                    order.shuffle(&mut self.rng);
+                }
+                order
+            }
+            Schedule::Weighted => {
+                let eligible = self.eligible();
+                scheduler::draw(&mut self.rng, &eligible, count)
+            }
+            Schedule::Uniform => {
+                let all: Vec<(usize, f64)> = (0..count).map(|index| (index, 1.0)).collect();
+                scheduler::draw(&mut self.rng, &all, count)
+            }
+        };
+
+        // Every case gets its own seed so a single failure can be regenerated
+        // without replaying the whole run.
+        let seeds: Vec<u64> = order.iter().map(|_| self.rng.fork().seed()).collect();
+        let bases: Vec<Option<Value>> = order
+            .iter()
+            .map(|index| self.pending.get_mut(index)?.pop_front())
+            .collect();
+        let cases = order
+            .iter()
+            .zip(&seeds)
+            .zip(&bases)
+            .map(|((&index, &seed), base)| {
+                self.case_from(&self.targets[index], seed, base.as_ref())
+            })
+            .collect();
+        (order, seeds, cases)
+    }
+
+    // Targets the weighted schedule may draw, with their weights. Until a
+    // registration succeeds, ones needing a session are left out, unless there
+    // is no register target to unlock them.
+    fn eligible(&self) -> Vec<(usize, f64)> {
+        let locked = !self.registered
+            && self
+                .targets
+                .iter()
+                .any(|target| scheduler::is_register(&target.method, &target.path));
+        self.targets
+            .iter()
+            .enumerate()
+            .filter(|(_, target)| !(locked && scheduler::needs_session(&target.path)))
+            .map(|(index, target)| (index, self.weights.weight(&target.method, &target.path)))
+            .collect()
+    }
+
+    // Records a successful registration, from `response` to a `target` case.
+    fn note_registration(&mut self, index: usize, response: Option<&ServerResponse>) {
+        let target = &self.targets[index];
+        if scheduler::is_register(&target.method, &target.path)
+            && response.is_some_and(|response| (200..300).contains(&response.status))
+        {
+            self.registered = true;
+        }
+    }
+
+    pub async fn run(&mut self, mut on_case: impl FnMut(&CaseResult)) -> RunReport {
+        let started = Instant::now();
+        let mut report = RunReport::new(self.seed());
+        report.routes = self
+            .routes()
+            .into_iter()
+            .map(|(method, path)| (method.to_string(), path))
+            .collect();
+        report.planned = self.planned();
+        let deadline = self.run_timeout.map(|timeout| started + timeout);
+        let mut connection_lost = false;
+        let mut campaign = self.resumed.take().unwrap_or_else(|| Campaign {
+            seed: self.seed(),
+            targets: self.target_names(),
+            pending: self
+                .pending
+                .iter()
+                .map(|(index, bodies)| (*index, bodies.iter().cloned().collect()))
+                .collect(),
+            ..Campaign::default()
+        });
+        report.resumed = campaign.cases;
+        report.earlier_findings = campaign.findings.clone();
+        let mut findings = campaign.findings.len();
+        // Cases sent by this and earlier sessions of the campaign.
+        let mut sent = 0;
+
+        'iterations: for iteration in 0..self.iterations {
+            let Some((mut order, mut seeds, mut cases)) = self.next_iteration(sent) else {
+                break;
+            };
+            // Generated again only to keep the schedule where it was.
+            let skipped = campaign.cases.saturating_sub(sent).min(cases.len());
+            order.drain(..skipped);
+            seeds.drain(..skipped);
+            cases.drain(..skipped);
+            sent += skipped;
+            if campaign.registered_at.is_some_and(|at| at <= iteration) {
+                self.registered = true;
+            }
+            if cases.is_empty() {
+                continue;
+            }
+            let results =
+                match until_stopped(deadline, &self.cancel, self.tester.compare_all(cases)).await {
+                    Ok(results) => results,
+                    Err(Stop::TimedOut) => {
+                        report.timed_out = true;
+                        break;
+                    }
+                    Err(Stop::Cancelled) => {
+                        report.cancelled = true;
+                        break;
+                    }
+                };
+            for (((case, comparison), seed), index) in results.into_iter().zip(seeds).zip(order) {
+                sent += 1;
+                if !self.registered {
+                    self.note_registration(index, comparison.client.as_ref());
+                    if self.registered {
+                        campaign.registered_at = Some(iteration);
+                    }
+                }
+                let target = &self.targets[index];
+                let crash = Crash::detect(&comparison).filter(|_| self.crash_detection);
+                let was_lost =
+                    std::mem::replace(&mut connection_lost, crash == Some(Crash::ConnectionLost));
+                let crash = crash.filter(|crash| !(was_lost && *crash == Crash::ConnectionLost));
+                let outcome = match comparison.outcome {
+                    Ok(()) if !self.assertions.is_empty() => self.check_assertions(
+                        &case,
+                        seed,
+                        comparison.client.as_ref(),
+                        comparison.actual.as_ref(),
+                    ),
+                    outcome => outcome,
+                };
+                let flaky = match (&outcome, self.reruns) {
+                    (Err(_), Some((reruns, reproduce))) if !self.cancel.is_cancelled() => {
+                        self.rerun(&case, seed, reruns, reproduce).await
+                    }
+                    _ => None,
+                };
+                let minimized = match (&outcome, self.shrink_attempts) {
+                    (Err(err), Some(attempts))
+                        if flaky.is_none() && !self.cancel.is_cancelled() =>
+                    {
+                        shrink::shrink_case(&self.tester, &case, err, attempts).await
+                    }
+                    _ => None,
+                };
+                let finding = (outcome.is_err() && flaky.is_none()) || crash.is_some();
+                let finding = finding.then(|| {
+                    let mut request = RecordedRequest::new(&case, seed);
+                    request.error = match (&outcome, crash) {
+                        (_, Some(crash)) => Some(format!("crash: {}", crash)),
+                        (Err(err), None) => Some(err.to_string()),
+                        (Ok(()), None) => None,
+                    };
+                    if minimized.is_some() {
+                        request.body = minimized.clone();
+                    }
+                    request
+                });
+                let recorded = finding
+                    .as_ref()
+                    .zip(self.corpus.as_ref())
+                    .map(|(request, corpus)| corpus.record(request));
+                campaign.findings.extend(finding.clone());
+                let artifacts =
+                    finding
+                        .as_ref()
+                        .zip(self.artifacts.as_ref())
+                        .map(|(request, artifacts)| {
+                            artifacts.write(
+                                request,
+                                comparison.client.as_ref(),
+                                comparison.actual.as_ref(),
+                            )
+                        });
+                if let Some(corpus) = &self.corpus {
+                    let reached = features(
+                        comparison.client.as_ref(),
+                        comparison.actual.as_ref(),
+                        &outcome,
+                    );
+                    let features = new_features(&mut self.seen, &case, &target.path, reached);
+                    if !features.is_empty() {
+                        let input = CorpusInput {
+                            target: target.path.clone(),
+                            request: RecordedRequest::new(&case, seed),
+                            features,
+                        };
+                        if let Err(err) = corpus.save_input(&input) {
+                            tracing::warn!(error = %err, "failed to save corpus input");
+                        }
+                    }
+                }
+                report.cases.push(CaseResult {
+                    iteration,
+                    seed,
+                    method: case.method,
+                    target: target.path.clone(),
+                    endpoint: case.endpoint,
+                    query: case.query,
+                    body: case.body,
+                    malformation: case.malformation,
+                    header_perturbation: case.header_perturbation,
+                    client: comparison.client,
+                    actual: comparison.actual,
+                    outcome,
+                    minimized,
+                    recorded,
+                    artifacts,
+                    crash,
+                    flaky,
+                });
+                let result = report.cases.last().expect("case was just pushed");
+                on_case(result);
+                if result.failed() || result.crash.is_some() {
+                    findings += 1;
+                }
+                if self.max_findings.is_some_and(|max| findings >= max) {
+                    report.finding_limit = true;
+                    self.save_campaign(&mut campaign, sent);
+                    break 'iterations;
+                }
+            }
+            self.save_campaign(&mut campaign, sent);
+        }
+
+        report.duration = started.elapsed();
+        report
+    }
+
+    fn save_campaign(&self, campaign: &mut Campaign, sent: usize) {
+        let Some(path) = &self.state_file else {
+            return;
+        };
+        campaign.cases = sent;
+        campaign.seen = self.seen.iter().cloned().collect();
+        if let Err(err) = campaign.save(path) {
+            tracing::warn!(error = %err, "failed to save the campaign state");
+        }
+    }
+
+    // Runs the same schedule against every candidate of `multi`, with one
+    // report per candidate. Shrinking, the corpus, assertions and the limit on
+    // findings are skipped: they belong to single-server runs.
+    pub async fn run_candidates(&mut self, multi: &MultiTester) -> MultiReport {
+        let started = Instant::now();
+        let routes: Vec<(String, String)> = self
+            .routes()
+            .into_iter()
+            .map(|(method, path)| (method.to_string(), path))
+            .collect();
+        let mut report = MultiReport {
+            candidates: multi
+                .candidates()
+                .map(|name| {
+                    let mut report = RunReport::new(self.seed());
+                    report.routes = routes.clone();
+                    report.planned = self.planned();
+                    (name.to_string(), report)
+                })
+                .collect(),
+            reference_failures: Vec::new(),
+        };
+        let deadline = self.run_timeout.map(|timeout| started + timeout);
+        let mut connection_lost = vec![false; report.candidates.len()];
+        let mut sent = 0;
+
+        'iterations: for iteration in 0..self.iterations {
+            let Some((order, seeds, cases)) = self.next_iteration(sent) else {
+                break;
+            };
+            sent += cases.len();
+            let results =
+                match until_stopped(deadline, &self.cancel, multi.compare_all(cases)).await {
+                    Ok(results) => results,
+                    Err(stop) => {
+                        for (_, candidate) in &mut report.candidates {
+                            match stop {
+                                Stop::TimedOut => candidate.timed_out = true,
+                                Stop::Cancelled => candidate.cancelled = true,
+                            }
+                        }
+                        break 'iterations;
+                    }
+                };
+            for (((case, comparison), seed), index) in results.into_iter().zip(seeds).zip(order) {
+                self.note_registration(index, comparison.reference.as_ref().ok());
+                let target = self.targets[index].path.clone();
+                if let Err(err) = comparison.reference {
+                    report.reference_failures.push((
+                        PlannedCase {
+                            iteration,
+                            seed,
+                            target,
+                            case,
+                        },
+                        err,
+                    ));
+                    continue;
+                }
+                for ((comparison, (_, candidate)), lost) in comparison
+                    .candidates
+                    .into_iter()
+                    .zip(&mut report.candidates)
+                    .zip(&mut connection_lost)
+                {
+                    let crash = Crash::detect(&comparison).filter(|_| self.crash_detection);
+                    let was_lost = std::mem::replace(lost, crash == Some(Crash::ConnectionLost));
+                    candidate.cases.push(CaseResult {
+                        iteration,
+                        seed,
+                        method: case.method.clone(),
+                        target: target.clone(),
+                        endpoint: case.endpoint.clone(),
+                        query: case.query.clone(),
+                        body: case.body.clone(),
+                        malformation: case.malformation,
+                        header_perturbation: case.header_perturbation,
+                        client: comparison.client,
+                        actual: comparison.actual,
+                        outcome: comparison.outcome,
+                        minimized: None,
+                        recorded: None,
+                        artifacts: None,
+                        crash: crash.filter(|crash| !(was_lost && *crash == Crash::ConnectionLost)),
+                        flaky: None,
+                    });
+                }
+            }
+        }
+
+        for (_, candidate) in &mut report.candidates {
+            candidate.duration = started.elapsed();
+        }
+        report
+    }
+
+    // `None` once the divergence reproduces `reproduce` times; stops early
+    // when it can no longer get there.
+    async fn rerun(
+        &self,
+        case: &TestCase,
+        seed: u64,
+        reruns: usize,
+        reproduce: usize,
+    ) -> Option<Flaky> {
+        let mut reproduced = 0;
+        for attempt in 0..reruns {
+            if reproduced >= reproduce || reproduced + (reruns - attempt) < reproduce {
+                break;
+            }
+            let comparison = self.tester.compare_case(case).await;
+            let outcome = match comparison.outcome {
+                Ok(()) if !self.assertions.is_empty() => self.check_assertions(
+                    case,
+                    seed,
+                    comparison.client.as_ref(),
+                    comparison.actual.as_ref(),
+                ),
+                outcome => outcome,
+            };
+            if outcome.is_err() {
+                reproduced += 1;
+            }
+        }
+        (reproduced < reproduce).then_some(Flaky { reruns, reproduced })
+    }
+
+    fn check_assertions(
+        &self,
+        case: &TestCase,
+        seed: u64,
+        client: Option<&ServerResponse>,
+        actual: Option<&ServerResponse>,
+    ) -> Result<(), TesterError> {
+        let body =
+            |response: Option<&ServerResponse>| response.map_or(Value::Null, |r| r.body.clone());
+        let findings = self.assertions.check(
+            &RecordedRequest::new(case, seed),
+            &body(client),
+            &body(actual),
+        );
+        if findings.is_empty() {
+            Ok(())
+        } else {
+            Err(TesterError::AssertionFailed {
+                endpoint: case.endpoint.clone(),
+                method: case.method.clone(),
+                findings,
+            })
+        }
+    }
+}
+
+// What a case reached: each server's status code and the path of any mismatch.
+fn features(
+    client: Option<&ServerResponse>,
+    actual: Option<&ServerResponse>,
+    outcome: &Result<(), TesterError>,
+) -> Vec<String> {
+    let mut features = Vec::new();
+    if let Some(client) = client {
+        features.push(format!("client:{}", client.status));
+    }
+    if let Some(actual) = actual {
+        features.push(format!("actual:{}", actual.status));
+    }
+    if let Some(path) = outcome.as_ref().err().and_then(TesterError::path) {
+        features.push(format!("mismatch:{}", path));
+    }
+    features
+}
+
+// Marks the case's features as seen and returns the ones that weren't.
+fn new_features(
+    seen: &mut BTreeSet<(String, String, String)>,
+    case: &TestCase,
+    target: &str,
+    features: Vec<String>,
+) -> Vec<String> {
+    features
+        .into_iter()
+        .filter(|feature| {
+            seen.insert((case.method.to_string(), target.to_string(), feature.clone()))
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Stop {
+    TimedOut,
+    Cancelled,
+}
+
+// Runs `work` unless the deadline passes or `cancel` fires first, in which
+// case it is dropped along with any requests it still had in flight.
+async fn until_stopped<T>(
+    deadline: Option<Instant>,
+    cancel: &CancellationToken,
+    work: impl Future<Output = T>,
+) -> Result<T, Stop> {
+    let deadline = async {
+        match deadline {
+            Some(deadline) => tokio::time::sleep_until(deadline.into()).await,
+            None => std::future::pending().await,
+        }
+    };
+    tokio::select! {
+        results = work => Ok(results),
+        () = deadline => Err(Stop::TimedOut),
+        () = cancel.cancelled() => Err(Stop::Cancelled),
+    }
+}
+
+fn targets(config: &Config, spec: Option<&Spec>) -> Result<Vec<Target>, ConfigError> {
+    let mut targets = Vec::new();
+
+    if config.endpoints.is_empty() {
+        for op in spec.map(Spec::operations).unwrap_or_default() {
+            targets.push(Target {
+                method: op.method.clone(),
+                path: op.path.clone(),
+                body: None,
+                body_kind: BodyKind::default(),
+                examples: Vec::new(),
+                body_schema: op.request_body.clone(),
+                path_params: op
+                    .path_params()
+                    .map(|p| (p.name.clone(), p.schema.clone()))
+                    .collect(),
+                query: BTreeMap::new(),
+                query_params: op.query_params().cloned().collect(),
+                probe: false,
+                generator: None,
+            });
+        }
+    }
+
+    // Without a spec or any configured endpoints, every known route is fuzzed.
+    if config.endpoints.is_empty() && spec.is_none() {
+        for endpoint in endpoints::all() {
+            for (method, path) in endpoint.routes() {
+                targets.push(registry_target(endpoint, method, path));
+            }
+        }
+    }
+
+    let profile = config.generator.profile(&GeneratorProfile::default());
+    for endpoint in &config.endpoints {
+        let method = endpoint.method()?;
+        let op = spec.and_then(|s| {
+            s.operations()
+                .iter()
+                .find(|op| op.method == method && op.path == endpoint.path)
+        });
+        targets.push(Target {
+            method,
+            path: endpoint.path.clone(),
+            body: endpoint.body.clone(),
+            body_kind: endpoint.body_kind,
+            examples: endpoint.examples.clone(),
+            body_schema: with_presets(op.and_then(|op| op.request_body.clone()), &endpoint.presets),
+            path_params: op
+                .map(|op| {
+                    op.path_params()
+                        .map(|p| (p.name.clone(), p.schema.clone()))
+                        .collect()
+                })
+                .unwrap_or_default(),
+            query: endpoint.query.clone(),
+            query_params: op
+                .map(|op| op.query_params().cloned().collect())
+                .unwrap_or_default(),
+            probe: false,
+            generator: endpoint
+                .generator
+                .as_ref()
+                .map(|generator| Generator::new(generator.profile(&profile))),
+        });
+    }
+
+    Ok(targets)
+}
+
+// Registry routes have no schemas, so fields are generated from the preset
+// matching their name where there is one. GET and DELETE take them as query
+// parameters, everything else in the body.
+fn registry_target(endpoint: &Endpoint, method: Method, path: &str) -> Target {
+    let in_query = method == Method::GET || method == Method::DELETE;
+    let request = endpoint
+        .method(&method)
+        .and_then(|route| route.request_schema());
+
+    let (body_schema, query_params) = match request {
+        Some(Schema::Object {
+            properties,
+            required,
+        }) if in_query => {
+            let params = properties
+                .into_iter()
+                .map(|(name, schema)| Parameter {
+                    required: required.contains(&name),
+                    name,
+                    location: ParameterLocation::Query,
+                    schema,
+                })
+                .collect();
+            (None, params)
+        }
+        request => (request, Vec::new()),
+    };
+
+    Target {
+        method,
+        path: path.to_string(),
+        body: None,
+        body_kind: BodyKind::default(),
+        examples: Vec::new(),
+        body_schema,
+        path_params: endpoint
+            .path_params
+            .iter()
+            .map(|param| (param.name.to_string(), Schema::Preset(param.preset)))
+            .collect(),
+        query: BTreeMap::new(),
+        query_params,
+        probe: false,
+        generator: None,
+    }
+}
+
+fn with_presets(schema: Option<Schema>, presets: &BTreeMap<String, Preset>) -> Option<Schema> {
+    if presets.is_empty() {
+        return schema;
+    }
+    let (mut properties, mut required) = match schema {
+        Some(Schema::Object {
+            properties,
+            required,
+        }) => (properties, required),
+        // Presets only make sense for object bodies, so any other schema is
+        // replaced outright.
+        _ => (BTreeMap::new(), Vec::new()),
+    };
+    for (field, preset) in presets {
+        properties.insert(field.clone(), Schema::Preset(*preset));
+        if !required.contains(field) {
+            required.push(field.clone());
+        }
+    }
+    Some(Schema::Object {
+        properties,
+        required,
+    })
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::models::client::tests::raw_server;
+    use reqwest::header::HeaderMap;
+    use std::time::Duration;
+
+    // A result with only the fields reports look at filled in.
+    pub(crate) fn case_result(
+        method: Method,
+        target: &str,
+        outcome: Result<(), TesterError>,
+    ) -> CaseResult {
+        CaseResult {
+            iteration: 0,
+            seed: 0,
+            method,
+            target: target.to_string(),
+            endpoint: target.to_string(),
+            query: Vec::new(),
+            body: None,
+            malformation: None,
+            header_perturbation: None,
+            client: None,
+            actual: None,
+            outcome,
+            minimized: None,
+            recorded: None,
+            artifacts: None,
+            crash: None,
+            flaky: None,
+        }
+    }
+
+    pub(crate) fn response(status: u16, body: Value, latency_ms: u64) -> ServerResponse {
+        ServerResponse {
+            status,
+            headers: HeaderMap::new(),
+            body,
+            raw: None,
+            latency: Duration::from_millis(latency_ms),
+            sent_at: None,
+            http_version: None,
+            exchange: None,
+            json_issues: Vec::new(),
+        }
+    }
+
+    fn target(method: Method, path: &str) -> Target {
+        Target {
+            method,
+            path: path.to_string(),
+            body: None,
+            body_kind: BodyKind::default(),
+            examples: Vec::new(),
+            body_schema: None,
+            path_params: Vec::new(),
+            query: BTreeMap::new(),
+            query_params: Vec::new(),
+            probe: false,
+            generator: None,
+        }
+    }
+
+    fn runner(target: &Target) -> Runner {
+        let tester = Tester::new("http://localhost:1".into(), "http://localhost:2".into());
+        Runner::new(tester, vec![target.clone()], 1, FuzzRng::new(0))
+    }
+
+    #[test]
+    fn cases_are_regenerated_from_their_seed() {
+        let target = Target {
+            body_schema: Some(Schema::Array(Box::new(Schema::String))),
+            body_kind: BodyKind::Form,
+            ..target(Method::POST, "/quiz/{id}")
+        };
+        let runner = runner(&target);
+
+        let first = runner.case(&target, 42);
+        let again = runner.case(&target, 42);
+        assert_eq!(first.endpoint, again.endpoint);
+        assert_eq!(first.body, again.body);
+        assert_eq!(first.method, Method::POST);
+        assert_eq!(first.body_kind, BodyKind::Form);
+        assert!(!first.endpoint.contains('{'));
+    }
+
+    #[test]
+    fn examples_are_mutated_before_falling_back_to_the_schema() {
+        struct Tag;
+        impl Mutator for Tag {
+            fn name(&self) -> &str {
+                "tag"
+            }
+            fn mutate(&self, value: &mut Value, _rng: &mut dyn rand::RngCore) {
+                value["tagged"] = Value::Bool(true);
+            }
+        }
+
+        let target = Target {
+            examples: vec![serde_json::json!({"name": "quiz"})],
+            body_schema: Some(Schema::Integer),
+            ..target(Method::POST, "/quiz")
+        };
+        let runner = runner(&target).with_mutation_engine(MutationEngine::empty().with(Tag));
+
+        let body = runner.case(&target, 1).body.unwrap();
+        assert_eq!(body, serde_json::json!({"name": "quiz", "tagged": true}));
+    }
+
+    #[test]
+    fn routes_include_every_target_once() {
+        let runner = runner(&target(Method::GET, "/quiz")).with_routes([
+            (Method::GET, "/quiz".to_string()),
+            (Method::DELETE, "/quiz".to_string()),
+        ]);
+        assert_eq!(
+            runner.routes(),
+            [
+                (Method::GET, "/quiz".to_string()),
+                (Method::DELETE, "/quiz".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn probes_cover_the_methods_a_path_lacks() {
+        let runner = runner(&target(Method::GET, "/quiz"))
+            .with_routes([(Method::DELETE, "/quiz/{id}".to_string())])
+            .with_method_probes();
+        let probes: Vec<(Method, &str)> = runner
+            .targets()
+            .iter()
+            .filter(|t| t.probe)
+            .map(|t| (t.method.clone(), t.path.as_str()))
+            .collect();
+        assert_eq!(probes.len(), 8);
+        assert!(probes.contains(&(Method::PATCH, "/quiz")));
+        assert!(probes.contains(&(Method::GET, "/quiz/{id}")));
+        assert!(!probes.contains(&(Method::GET, "/quiz")));
+        // Probes aren't routes, so coverage doesn't count them.
+        assert_eq!(runner.routes().len(), 2);
+    }
+
+    #[test]
+    fn fixed_query_parameters_win_over_generated_ones() {
+        let param = |name: &str, required| Parameter {
+            name: name.to_string(),
+            location: crate::spec::ParameterLocation::Query,
+            required,
+            schema: Schema::Integer,
+        };
+        let target = Target {
+            query: BTreeMap::from([("token".to_string(), "abc".to_string())]),
+            query_params: vec![
+                param("token", true),
+                param("quizId", true),
+                param("page", false),
+            ],
+            ..target(Method::GET, "/quiz")
+        };
+        let runner = runner(&target);
+
+        let mut sent_page = false;
+        for seed in 0..20 {
+            let query = runner.case(&target, seed).query;
+            assert_eq!(query[0], ("token".to_string(), "abc".to_string()));
+            assert_eq!(query[1].0, "quizId");
+            assert!(query[1].1.parse::<i64>().is_ok());
+            assert_eq!(query.iter().filter(|(name, _)| name == "token").count(), 1);
+            sent_page |= query.len() == 3;
+        }
+        // Optional parameters are left out some of the time, but not always.
+        assert!(sent_page);
+    }
+
+    #[test]
+    fn registry_fields_go_in_the_query_or_the_body_by_method() {
+        let login = endpoints::find("/admin/auth/login").unwrap();
+        let target = registry_target(login, Method::POST, login.path);
+        assert!(target.query_params.is_empty());
+        assert_eq!(
+            target.body_schema,
+            Some(Schema::Object {
+                properties: BTreeMap::from([
+                    ("email".to_string(), Schema::Preset(Preset::Email)),
+                    ("password".to_string(), Schema::Preset(Preset::Password)),
+                ]),
+                required: vec!["email".to_string(), "password".to_string()],
+            })
+        );
+
+        let empty = endpoints::find("/admin/quiz/trash/empty").unwrap();
+        let target = registry_target(empty, Method::DELETE, empty.path);
+        assert_eq!(target.body_schema, None);
+        assert_eq!(target.query_params[0].name, "quizIds");
+        assert_eq!(target.query_params[0].schema, Schema::String);
+    }
+
+    #[test]
+    fn presets_are_merged_into_object_schemas() {
+        let presets = BTreeMap::from([("email".to_string(), Preset::Email)]);
+        let schema = Schema::Object {
+            properties: BTreeMap::from([("name".to_string(), Schema::String)]),
+            required: vec!["name".to_string()],
+        };
+        assert_eq!(
+            with_presets(Some(schema), &presets),
+            Some(Schema::Object {
+                properties: BTreeMap::from([
+                    ("email".to_string(), Schema::Preset(Preset::Email)),
+                    ("name".to_string(), Schema::String),
+                ]),
+                required: vec!["name".to_string(), "email".to_string()],
+            })
+        );
+
+        // Anything that isn't an object is replaced by one with only the presets.
+        assert_eq!(
+            with_presets(Some(Schema::Integer), &presets),
+            Some(Schema::Object {
+                properties: BTreeMap::from([("email".to_string(), Schema::Preset(Preset::Email))]),
+                required: vec!["email".to_string()],
+            })
+        );
+        assert_eq!(with_presets(None, &BTreeMap::new()), None);
+    }
+
+    #[test]
+    fn protocol_fuzzing_malforms_only_bodies_it_is_asked_to() {
+        let post = target(Method::POST, "/quiz");
+        let get = target(Method::GET, "/quiz");
+        for rate in [0.0, -1.0, f64::NAN] {
+            let runner = runner(&post).with_protocol_fuzzing(rate);
+            assert!((0..50).all(|seed| runner.case(&post, seed).malformation.is_none()));
+        }
+
+        let runner = runner(&post).with_protocol_fuzzing(1.0);
+        assert!((0..50).all(|seed| runner.case(&post, seed).malformation.is_some()));
+        assert!((0..50).all(|seed| runner.case(&get, seed).malformation.is_none()));
+    }
+
+    #[test]
+    fn header_fuzzing_mangles_only_the_fraction_asked_for() {
+        let get = target(Method::GET, "/quiz");
+        for rate in [0.0, -1.0, f64::NAN] {
+            let runner = runner(&get).with_header_fuzzing(rate);
+            assert!((0..50).all(|seed| runner.case(&get, seed).header_perturbation.is_none()));
+        }
+        let runner = runner(&get).with_header_fuzzing(1.0);
+        assert!((0..50).all(|seed| runner.case(&get, seed).header_perturbation.is_some()));
+    }
+
+    #[test]
+    fn path_fuzzing_perturbs_only_path_parameters() {
+        let info = target(Method::GET, "/quiz/{quizid}");
+        let list = target(Method::GET, "/quiz/list");
+        for rate in [0.0, f64::NAN] {
+            let runner = runner(&info).with_path_fuzzing(rate);
+            assert_eq!(
+                runner.case(&info, 3).endpoint,
+                runner.case(&info, 3).endpoint
+            );
+            assert!((0..50).all(|seed| runner.case(&info, seed).endpoint.len() < 40));
+        }
+        let plain = runner(&info);
+        let runner = runner(&info).with_path_fuzzing(1.0);
+        for seed in 0..50 {
+            let endpoint = runner.case(&info, seed).endpoint;
+            // Still exactly one segment after `/quiz/`.
+            assert_eq!(endpoint.matches('/').count(), 2, "{}", endpoint);
+            assert_ne!(endpoint, plain.case(&info, seed).endpoint);
+            assert_eq!(runner.case(&list, seed).endpoint, "/quiz/list");
+        }
+    }
+
+    #[test]
+    fn plans_regenerate_and_leave_the_rng_alone() {
+        let targets = vec![target(Method::GET, "/a"), target(Method::POST, "/b")];
+        let tester = Tester::new("http://localhost:1".into(), "http://localhost:2".into());
+        let mut runner = Runner::new(tester, targets, 3, FuzzRng::new(5)).with_shuffle(true);
+        let plan = runner.plan();
+        assert_eq!(plan.len(), 6);
+        assert_eq!(plan[5].iteration, 2);
+        for planned in &plan {
+            let target = runner
+                .targets()
+                .iter()
+                .find(|target| target.path == planned.target)
+                .unwrap();
+            assert_eq!(runner.case(target, planned.seed).body, planned.case.body);
+        }
+
+        let again = runner.plan();
+        let seeds = |plan: &[PlannedCase]| plan.iter().map(|p| p.seed).collect::<Vec<_>>();
+        assert_eq!(seeds(&plan), seeds(&again));
+    }
+
+    #[tokio::test]
+    async fn crashes_are_5xx_or_a_lost_connection_to_the_server_under_test() {
+        let comparison = |client: Option<u16>, outcome| Comparison {
+            client: client.map(|status| response(status, Value::Null, 1)),
+            actual: None,
+            outcome,
+        };
+        let refused = || async {
+            let source = reqwest::get("http://127.0.0.1:1").await.unwrap_err();
+            TesterError::from(source)
+        };
+
+        assert_eq!(
+            Crash::detect(&comparison(Some(503), Ok(()))),
+            Some(Crash::ServerError(503))
+        );
+        assert_eq!(Crash::detect(&comparison(Some(404), Ok(()))), None);
+        assert_eq!(
+            Crash::detect(&comparison(None, Err(refused().await))),
+            Some(Crash::ConnectionLost)
+        );
+        // The reference failing after the server under test answered is not
+        // a crash of the server under test.
+        assert_eq!(
+            Crash::detect(&comparison(Some(200), Err(refused().await))),
+            None
+        );
+    }
+
+    #[test]
+    fn assertion_findings_fail_the_case() {
+        use crate::models::assertion::Finding;
+
+        let target = target(Method::GET, "/quiz");
+        let runner = runner(&target).with_assertion(
+            Method::GET,
+            "/quiz",
+            |_: &RecordedRequest, a: &Value, _: &Value| {
+                a.get("secret")
+                    .map(|_| vec![Finding::new("leaks a secret")])
+                    .unwrap_or_default()
+            },
+        );
+        let case = TestCase::new("/quiz", Method::GET, None);
+        let leaky = response(200, serde_json::json!({"secret": 1}), 1);
+        match runner.check_assertions(&case, 0, Some(&leaky), None) {
+            Err(TesterError::AssertionFailed { findings, .. }) => {
+                assert_eq!(findings, [Finding::new("leaks a secret")]);
+            }
+            outcome => panic!("expected the assertion to fail, got {:?}", outcome),
+        }
+        let clean = response(200, serde_json::json!({}), 1);
+        assert!(runner
+            .check_assertions(&case, 0, Some(&clean), None)
+            .is_ok());
+    }
+
+    #[test]
+    fn corpus_inputs_are_mutated_before_anything_else() {
+        let target = Target {
+            body_schema: Some(Schema::Integer),
+            ..target(Method::POST, "/quiz")
+        };
+        let input = |target: &str, body| CorpusInput {
+            target: target.to_string(),
+            request: RecordedRequest::new(&TestCase::new(target, Method::POST, Some(body)), 0),
+            features: vec!["client:400".to_string()],
+        };
+        let mut runner = runner(&target)
+            .with_mutation_engine(MutationEngine::empty())
+            .with_corpus_inputs(vec![
+                input("/quiz", serde_json::json!({"kept": 1})),
+                input("/elsewhere", serde_json::json!({"dropped": 1})),
+            ]);
+        runner.iterations = 2;
+
+        let bodies: Vec<Value> = runner
+            .plan()
+            .into_iter()
+            .map(|planned| planned.case.body.unwrap())
+            .collect();
+        assert_eq!(bodies[0], serde_json::json!({"kept": 1}));
+        assert!(bodies[1].is_i64());
+        assert!(runner.seen.contains(&(
+            "POST".to_string(),
+            "/quiz".to_string(),
+            "client:400".to_string()
+        )));
+    }
+
+    #[test]
+    fn only_unseen_features_are_new() {
+        let case = TestCase::new("/quiz/1", Method::GET, None);
+        let mismatch = Err(TesterError::TypeMismatch {
+            endpoint: "/quiz/1".to_string(),
+            method: Method::GET,
+            path: "/id".to_string(),
+            client_value: Box::new(Value::Null),
+            actual_value: Box::new(Value::Null),
+        });
+        let reached = features(
+            Some(&response(200, Value::Null, 1)),
+            Some(&response(404, Value::Null, 1)),
+            &mismatch,
+        );
+        assert_eq!(reached, ["client:200", "actual:404", "mismatch:/id"]);
+
+        let mut seen = BTreeSet::new();
+        assert_eq!(
+            new_features(&mut seen, &case, "/quiz/{id}", reached.clone()).len(),
+            3
+        );
+        assert!(new_features(&mut seen, &case, "/quiz/{id}", reached.clone()).is_empty());
+        assert_eq!(new_features(&mut seen, &case, "/other", reached).len(), 3);
+    }
+
+    #[test]
+    fn weighted_schedules_hold_session_targets_until_registration() {
+        let register = target(Method::POST, scheduler::REGISTER_PATH);
+        let list = target(Method::GET, "/admin/quiz/list");
+        let mut runner = runner(&register);
+        runner.targets.push(list);
+        runner = runner.with_schedule(Schedule::Weighted, Weights::new());
+
+        let (order, _, _) = runner.iteration();
+        assert_eq!(order, [0, 0]);
+        runner.note_registration(1, Some(&response(200, Value::Null, 1)));
+        runner.note_registration(0, Some(&response(400, Value::Null, 1)));
+        assert!(!runner.registered);
+        runner.note_registration(0, Some(&response(200, Value::Null, 1)));
+        assert_eq!(runner.eligible(), [(0, 1.0), (1, 1.0)]);
+    }
+
+    #[tokio::test]
+    async fn work_stops_at_the_deadline_or_on_cancellation() {
+        let cancel = CancellationToken::new();
+        assert!(matches!(
+            until_stopped(None, &cancel, async { 1 }).await,
+            Ok(1)
+        ));
+
+        let soon = Instant::now() + Duration::from_millis(10);
+        let pending = std::future::pending::<()>();
+        assert!(matches!(
+            until_stopped(Some(soon), &cancel, pending).await,
+            Err(Stop::TimedOut)
+        ));
+
+        let stopper = cancel.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            stopper.cancel();
+        });
+        assert!(matches!(
+            until_stopped(None, &cancel, std::future::pending::<()>()).await,
+            Err(Stop::Cancelled)
+        ));
+    }
+
+    #[tokio::test]
+    async fn divergences_that_rarely_reproduce_are_flaky() {
+        const FOUND: &str = "HTTP/1.1 200 OK\r\nconnection: close\r\ncontent-length: 1\r\n\r\n1";
+        const GONE: &str =
+            "HTTP/1.1 404 Not Found\r\nconnection: close\r\ncontent-length: 1\r\n\r\n1";
+        let case = TestCase::new("/", Method::GET, None);
+        let runner = |client, reference| {
+            Runner::new(
+                Tester::new(client, reference),
+                Vec::new(),
+                1,
+                FuzzRng::new(0),
+            )
+            .with_flaky_reruns(3, 5)
+        };
+
+        let found = raw_server(FOUND).await;
+        let agreeing = runner(found.clone(), found.clone());
+        assert_eq!(agreeing.reruns, Some((3, 3)));
+        assert_eq!(
+            agreeing.rerun(&case, 0, 3, 2).await,
+            Some(Flaky {
+                reruns: 3,
+                reproduced: 0
+            })
+        );
+        let diverging = runner(raw_server(GONE).await, found);
+        assert_eq!(diverging.rerun(&case, 0, 3, 2).await, None);
+    }
+
+    #[test]
+    fn max_cases_cuts_the_last_iteration_short() {
+        let targets = vec![target(Method::GET, "/a"), target(Method::GET, "/b")];
+        let tester = Tester::new("http://localhost:1".into(), "http://localhost:2".into());
+        let mut runner = Runner::new(tester, targets, 3, FuzzRng::new(0));
+        assert_eq!(runner.planned(), 6);
+        runner = runner.with_max_cases(5);
+        assert_eq!(runner.planned(), 5);
+        let plan = runner.plan();
+        assert_eq!(plan.len(), 5);
+        assert_eq!(plan[4].iteration, 2);
+    }
+
+    #[tokio::test]
+    async fn runs_stop_once_enough_cases_have_failed() {
+        const FOUND: &str = "HTTP/1.1 200 OK\r\nconnection: close\r\ncontent-length: 1\r\n\r\n1";
+        const GONE: &str =
+            "HTTP/1.1 404 Not Found\r\nconnection: close\r\ncontent-length: 1\r\n\r\n1";
+        let tester = Tester::new(raw_server(GONE).await, raw_server(FOUND).await);
+        let mut runner = Runner::new(tester, vec![target(Method::GET, "/")], 5, FuzzRng::new(0))
+            .with_max_findings(2);
+        let report = runner.run(|_| {}).await;
+        assert!(report.finding_limit);
+        assert_eq!(report.cases.len(), 2);
+        assert_eq!(report.planned, 5);
+    }
+
+    #[tokio::test]
+    async fn refused_connections_are_neither_findings_nor_passes() {
+        let mut passed = case_result(Method::GET, "/a", Ok(()));
+        assert_eq!(passed.category(), Category::Passed);
+        passed.crash = Some(Crash::ServerError(500));
+        assert_eq!(passed.category(), Category::Finding);
+
+        let mismatch = || TesterError::StatusMismatch {
+            endpoint: "/a".to_string(),
+            method: Method::GET,
+            client_status: 200,
+            actual_status: 400,
+        };
+        let mut flaky = case_result(Method::GET, "/a", Err(mismatch()));
+        assert_eq!(flaky.category(), Category::Finding);
+        flaky.flaky = Some(Flaky {
+            reruns: 3,
+            reproduced: 1,
+        });
+        assert_eq!(flaky.category(), Category::Flaky);
+
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let source = reqwest::get(format!("http://127.0.0.1:{}", port))
+            .await
+            .unwrap_err();
+        let refused = TesterError::Network {
+            endpoint: "/a".to_string(),
+            method: Method::GET,
+            source,
+        };
+        let mut unreachable = case_result(Method::GET, "/a", Err(refused));
+        unreachable.crash = Some(Crash::ConnectionLost);
+        assert_eq!(unreachable.category(), Category::Unreachable);
+    }
+
+    #[test]
+    fn campaigns_only_resume_runs_they_were_saved_from() {
+        let seeded = |seed| {
+            let tester = Tester::new("http://localhost:1".into(), "http://localhost:2".into());
+            Runner::new(
+                tester,
+                vec![target(Method::GET, "/a")],
+                1,
+                FuzzRng::new(seed),
+            )
+        };
+        let campaign = Campaign {
+            seed: 3,
+            targets: vec!["GET /a".to_string()],
+            cases: 2,
+            ..Campaign::default()
+        };
+        let foreign = Campaign {
+            targets: vec!["POST /b".to_string()],
+            ..campaign.clone()
+        };
+        match seeded(3).resume(foreign) {
+            Err(CampaignError::Mismatch(message)) => {
+                assert_eq!(message, "the targets have changed since it was saved")
+            }
+            other => panic!("expected a mismatch, got {:?}", other.err()),
+        }
+        assert!(seeded(3).resume(campaign.clone()).is_ok());
+        assert!(matches!(
+            seeded(4).resume(campaign),
+            Err(CampaignError::Mismatch(message)) if message == "it is for seed 3, not 4"
+        ));
+    }
+}
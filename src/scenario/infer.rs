@@ -0,0 +1,481 @@
+use crate::fuzz::generator::Generator;
+use crate::fuzz::presets::Preset;
+use crate::fuzz::scheduler;
+use crate::rng::FuzzRng;
+use crate::scenario::{Scenario, Step};
+use crate::schema::Schema;
+use crate::spec::{Operation, ParameterLocation, Spec};
+use rand::Rng;
+use reqwest::Method;
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum InputLocation {
+    Path,
+    Query,
+    Header,
+    Body,
+}
+
+// A response field of one operation that can fill an input of another, by
+// index into `DependencyGraph::operations`. `field` is the path to it in the
+// producer's response body, with `0` for array items.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Link {
+    pub producer: usize,
+    pub field: Vec<String>,
+    pub consumer: usize,
+    pub input: String,
+    pub location: InputLocation,
+}
+
+struct Input<'a> {
+    name: &'a str,
+    location: InputLocation,
+    required: bool,
+}
+
+// Which response fields feed which request inputs, learned from the spec's
+// success responses and from any responses observed since. Fields and inputs
+// are matched by name, ignoring case and underscores (so `quizId` fills
+// `{quizid}`), and only for identifiers and tokens: a user's `email` in one
+// response says nothing about the email a new registration should use.
+#[derive(Debug, Clone)]
+pub struct DependencyGraph {
+    operations: Vec<Operation>,
+    // Response fields of each operation.
+    produces: Vec<Vec<Vec<String>>>,
+}
+
+impl DependencyGraph {
+    pub fn from_spec(spec: &Spec) -> Self {
+        let operations = spec.operations().to_vec();
+        let produces = operations
+            .iter()
+            .map(|op| {
+                let mut fields = Vec::new();
+                for (_, schema) in op
+                    .responses
+                    .iter()
+                    .filter(|(code, _)| code.starts_with('2'))
+                {
+                    schema_fields(schema, &mut Vec::new(), &mut fields);
+                }
+                fields
+            })
+            .collect();
+        Self {
+            operations,
+            produces,
+        }
+    }
+
+    // Adds the fields of a response `method endpoint` actually returned, for
+    // operations whose spec leaves them out. Unknown routes are ignored.
+    pub fn observe(&mut self, method: &Method, endpoint: &str, body: &Value) {
+        let Some(index) = self
+            .operations
+            .iter()
+            .position(|op| op.method == *method && op.matches(endpoint))
+        else {
+            return;
+        };
+        let mut fields = Vec::new();
+        value_fields(body, &mut Vec::new(), &mut fields);
+        for field in fields {
+            if !self.produces[index].contains(&field) {
+                self.produces[index].push(field);
+            }
+        }
+    }
+
+    pub fn operations(&self) -> &[Operation] {
+        &self.operations
+    }
+
+    pub fn links(&self) -> Vec<Link> {
+        let mut links = Vec::new();
+        for (consumer, op) in self.operations.iter().enumerate() {
+            for input in inputs(op).filter(|input| is_linkable(input.name)) {
+                for (producer, fields) in self.produces.iter().enumerate() {
+                    for field in fields.iter().filter(|field| matches(field, input.name)) {
+                        links.push(Link {
+                            producer,
+                            field: field.clone(),
+                            consumer,
+                            input: input.name.to_string(),
+                            location: input.location,
+                        });
+                    }
+                }
+            }
+        }
+        links
+    }
+
+    // A scenario of up to `steps` calls in which every identifier and token
+    // some operation produces comes from the latest earlier step that produced
+    // it. Operations needing one that no step has produced yet wait; other
+    // inputs are generated, with valid values for the quiz API's fields.
+    // Operations that would use more earlier results are picked more often.
+    pub fn generate(&self, name: impl Into<String>, steps: usize, rng: &mut FuzzRng) -> Scenario {
+        let generator = Generator::default();
+        let producible: Vec<String> = self
+            .produces
+            .iter()
+            .flatten()
+            .filter_map(|field| field.last())
+            .map(|name| normalize(name))
+            .collect();
+        // Fields some operation returns outside of any list. An empty list has
+        // no first item to refer to, so lists only stand in for fields nothing
+        // else returns.
+        let direct: Vec<String> = self
+            .produces
+            .iter()
+            .flatten()
+            .filter(|field| !in_array(field))
+            .filter_map(|field| field.last())
+            .map(|name| normalize(name))
+            .collect();
+        // The latest step producing each field, by normalized name.
+        let mut available: BTreeMap<String, (String, Vec<String>)> = BTreeMap::new();
+        let mut scenario = Scenario::new(name);
+
+        for index in 0..steps {
+            let ready: Vec<(usize, f64)> = self
+                .operations
+                .iter()
+                .enumerate()
+                .filter_map(|(i, op)| {
+                    let mut fed = 0;
+                    for input in inputs(op) {
+                        let name = normalize(input.name);
+                        if !is_linkable(input.name) || !producible.contains(&name) {
+                            continue;
+                        }
+                        if available.contains_key(&name) {
+                            fed += 1;
+                        } else if input.required {
+                            return None;
+                        }
+                    }
+                    Some((i, 1.0 + fed as f64))
+                })
+                .collect();
+            if ready.is_empty() {
+                break;
+            }
+            let op_index = scheduler::draw(rng, &ready, 1)[0];
+            let op = &self.operations[op_index];
+            let step_name = format!("step{}", index + 1);
+            let reference = |input: &str| {
+                available
+                    .get(&normalize(input))
+                    .filter(|_| is_linkable(input))
+                    .map(|(step, field)| {
+                        format!("{{{{ steps.{}.body.{} }}}}", step, field.join("."))
+                    })
+            };
+
+            let mut endpoint = op.path.clone();
+            for param in op.path_params() {
+                let value = reference(&param.name)
+                    .unwrap_or_else(|| generator.generate_param(&param.schema, rng));
+                endpoint = endpoint.replace(&format!("{{{}}}", param.name), &value);
+            }
+            let mut step = Step::new(step_name.clone(), op.method.clone(), endpoint);
+            if let Some(token) = inputs(op)
+                .find(|input| normalize(input.name) == "token")
+                .and_then(|input| reference(input.name))
+            {
+                step = step.with_token(token);
+            }
+            for param in op.query_params().filter(|p| normalize(&p.name) != "token") {
+                match reference(&param.name) {
+                    Some(value) => step = step.with_query(param.name.clone(), value),
+                    None if param.required || rng.gen_bool(0.5) => {
+                        let value = generator.generate_param(&param.schema, rng);
+                        step = step.with_query(param.name.clone(), value);
+                    }
+                    None => {}
+                }
+            }
+            if let Some(schema) = &op.request_body {
+                let mut body = generator.generate(schema, rng);
+                if let (Value::Object(fields), Schema::Object { properties, .. }) =
+                    (&mut body, schema)
+                {
+                    fields.retain(|name, _| normalize(name) != "token");
+                    for (name, value) in fields.iter_mut() {
+                        let preset = match properties.get(name) {
+                            Some(Schema::Preset(preset)) => Some(*preset),
+                            _ => Preset::for_field(name),
+                        };
+                        if let Some(reference) = reference(name) {
+                            *value = Value::String(reference);
+                        } else if let Some(preset) = preset {
+                            *value = preset.valid(rng);
+                        }
+                    }
+                }
+                step = step.with_body(body);
+            }
+            scenario = scenario.with_step(step);
+
+            for field in &self.produces[op_index] {
+                let Some(name) = field.last().filter(|name| is_linkable(name)) else {
+                    continue;
+                };
+                let name = normalize(name);
+                if !in_array(field) || !direct.contains(&name) {
+                    available.insert(name, (step_name.clone(), field.clone()));
+                }
+            }
+        }
+        scenario
+    }
+}
+
+fn inputs(op: &Operation) -> impl Iterator<Item = Input<'_>> {
+    let params = op.parameters.iter().filter_map(|param| {
+        let location = match param.location {
+            ParameterLocation::Path => InputLocation::Path,
+            ParameterLocation::Query => InputLocation::Query,
+            ParameterLocation::Header => InputLocation::Header,
+            ParameterLocation::Cookie => return None,
+        };
+        Some(Input {
+            name: &param.name,
+            location,
+            required: param.required,
+        })
+    });
+    let body = match &op.request_body {
+        Some(Schema::Object {
+            properties,
+            required,
+        }) => Some(properties.keys().map(|name| Input {
+            name,
+            location: InputLocation::Body,
+            required: required.contains(name),
+        })),
+        _ => None,
+    };
+    params.chain(body.into_iter().flatten())
+}
+
+fn normalize(name: &str) -> String {
+    name.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+// Identifiers (`quizId`, `questionid`, `playerId`) and session tokens.
+fn is_linkable(name: &str) -> bool {
+    let name = normalize(name);
+    name == "token" || (name.len() > 2 && name.ends_with("id"))
+}
+
+fn in_array(field: &[String]) -> bool {
+    field.iter().any(|part| part == "0")
+}
+
+fn matches(field: &[String], input: &str) -> bool {
+    field
+        .last()
+        .is_some_and(|name| normalize(name) == normalize(input))
+}
+
+fn schema_fields(schema: &Schema, path: &mut Vec<String>, out: &mut Vec<Vec<String>>) {
+    match schema {
+        Schema::Object { properties, .. } => {
+            for (name, property) in properties {
+                path.push(name.clone());
+                out.push(path.clone());
+                schema_fields(property, path, out);
+                path.pop();
+            }
+        }
+        Schema::Array(items) => {
+            path.push("0".to_string());
+            schema_fields(items, path, out);
+            path.pop();
+        }
+        Schema::Nullable(inner) => schema_fields(inner, path, out),
+        Schema::OneOf(options) => {
+            for option in options {
+                schema_fields(option, path, out);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn value_fields(value: &Value, path: &mut Vec<String>, out: &mut Vec<Vec<String>>) {
+    match value {
+        Value::Object(fields) => {
+            for (name, field) in fields {
+                path.push(name.clone());
+                out.push(path.clone());
+                value_fields(field, path, out);
+                path.pop();
+            }
+        }
+        Value::Array(items) => {
+            if let Some(first) = items.first() {
+                path.push("0".to_string());
+                value_fields(first, path, out);
+                path.pop();
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const SPEC: &str = r##"
+openapi: 3.0.0
+paths:
+  /v1/admin/auth/register:
+    post:
+      requestBody:
+        content:
+          application/json:
+            schema:
+              type: object
+              required: [email]
+              properties:
+                email: { type: string }
+      responses:
+        '200':
+          content:
+            application/json:
+              schema:
+                type: object
+                properties:
+                  token: { type: string }
+  /v1/admin/quiz:
+    post:
+      parameters:
+        - name: token
+          in: header
+          required: true
+          schema: { type: string }
+      responses:
+        '200':
+          content:
+            application/json:
+              schema:
+                type: object
+                properties:
+                  quizId: { type: integer }
+  /v1/admin/quiz/{quizid}:
+    get:
+      parameters:
+        - name: quizid
+          in: path
+          required: true
+          schema: { type: integer }
+        - name: token
+          in: header
+          required: true
+          schema: { type: string }
+      responses:
+        '200':
+          content:
+            application/json:
+              schema: { type: object }
+"##;
+
+    fn graph() -> DependencyGraph {
+        DependencyGraph::from_spec(&Spec::parse(SPEC).unwrap())
+    }
+
+    fn find(graph: &DependencyGraph, method: Method, path: &str) -> usize {
+        graph
+            .operations()
+            .iter()
+            .position(|op| op.method == method && op.path == path)
+            .unwrap()
+    }
+
+    #[test]
+    fn identifiers_and_tokens_link_producers_to_consumers() {
+        let graph = graph();
+        let register = find(&graph, Method::POST, "/v1/admin/auth/register");
+        let create = find(&graph, Method::POST, "/v1/admin/quiz");
+        let info = find(&graph, Method::GET, "/v1/admin/quiz/{quizid}");
+        let links = graph.links();
+        assert!(links.contains(&Link {
+            producer: create,
+            field: vec!["quizId".to_string()],
+            consumer: info,
+            input: "quizid".to_string(),
+            location: InputLocation::Path,
+        }));
+        assert!(links
+            .iter()
+            .any(|link| link.producer == register && link.consumer == create));
+        // Nothing produces an email, and it isn't an identifier anyway.
+        assert!(links.iter().all(|link| link.input != "email"));
+    }
+
+    #[test]
+    fn observed_responses_add_fields_the_spec_leaves_out() {
+        let mut graph = graph();
+        let info = find(&graph, Method::GET, "/v1/admin/quiz/{quizid}");
+        assert!(graph.links().iter().all(|link| link.producer != info));
+        graph.observe(
+            &Method::GET,
+            "/v1/admin/quiz/5",
+            &json!({ "questions": [{ "questionId": 1 }], "quizId": 5 }),
+        );
+        graph.observe(&Method::GET, "/unknown", &json!({ "quizId": 1 }));
+        assert!(graph
+            .links()
+            .iter()
+            .any(|link| link.producer == info && link.field == ["quizId"]));
+        assert!(graph.produces[info].contains(&vec![
+            "questions".to_string(),
+            "0".to_string(),
+            "questionId".to_string()
+        ]));
+    }
+
+    #[test]
+    fn generated_steps_wait_for_what_they_need() {
+        let graph = graph();
+        for seed in 0..20 {
+            let scenario = graph.generate("inferred", 4, &mut FuzzRng::new(seed));
+            assert_eq!(scenario.steps.len(), 4);
+            // Registering is the only step that needs no token.
+            assert_eq!(scenario.steps[0].endpoint, "/v1/admin/auth/register");
+            assert!(scenario.steps[0].body.as_ref().unwrap()["email"]
+                .as_str()
+                .unwrap()
+                .contains('@'));
+            for step in scenario.steps[1..]
+                .iter()
+                .filter(|step| step.endpoint != "/v1/admin/auth/register")
+            {
+                let token = step.token.as_deref().unwrap();
+                assert!(token.starts_with("{{ steps.step"), "{}", token);
+                assert!(token.ends_with(".body.token }}"), "{}", token);
+                if step.method == Method::GET {
+                    assert!(
+                        step.endpoint.contains(".body.quizId }}"),
+                        "{}",
+                        step.endpoint
+                    );
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,560 @@
+pub mod infer;
+pub mod pack;
+pub mod yaml;
+
+use crate::fuzz::template::{pieces, Piece, TemplateError, Templates};
+use crate::models::auth::AuthStrategy;
+use crate::models::client::{ServerResponse, TestCase, Tester, TesterError};
+use crate::models::endpoints::ApiVersion;
+use crate::models::invariants::Invariants;
+use crate::rng::FuzzRng;
+use reqwest::Method;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::Arc;
+use thiserror::Error;
+
+// One request of a scenario. Strings in the endpoint, query, body and token
+// may refer to earlier steps' responses as `{{ steps.<name>.body.<field> }}`
+// (or `.status`); a string that is nothing but a reference takes the value's
+// JSON type. Other placeholders, like `{{ email }}`, are filled from the
+// runner's data providers once per scenario, so every server gets the same
+// data.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Step {
+    pub name: String,
+    pub method: Method,
+    pub endpoint: String,
+    pub query: Vec<(String, String)>,
+    pub body: Option<Value>,
+    // Sent where the API version expects it. Steps pick their token
+    // explicitly, so a logged out or made up one can be used on purpose.
+    pub token: Option<String>,
+    pub expect: Option<u16>,
+}
+
+impl Step {
+    pub fn new(name: impl Into<String>, method: Method, endpoint: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            method,
+            endpoint: endpoint.into(),
+            query: Vec::new(),
+            body: None,
+            token: None,
+            expect: None,
+        }
+    }
+
+    pub fn with_query(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.query.push((name.into(), value.into()));
+        self
+    }
+
+    pub fn with_body(mut self, body: Value) -> Self {
+        self.body = Some(body);
+        self
+    }
+
+    pub fn with_token(mut self, token: impl Into<String>) -> Self {
+        self.token = Some(token.into());
+        self
+    }
+
+    pub fn expect(mut self, status: u16) -> Self {
+        self.expect = Some(status);
+        self
+    }
+
+    fn expand(&self, templates: &Templates, rng: &mut FuzzRng) -> Result<Step, TemplateError> {
+        let mut expand = |template: &str| templates.expand(&Value::from(template), rng).map(string);
+        Ok(Step {
+            endpoint: expand(&self.endpoint)?,
+            query: self
+                .query
+                .iter()
+                .map(|(name, value)| Ok((name.clone(), expand(value)?)))
+                .collect::<Result<_, TemplateError>>()?,
+            token: self.token.as_deref().map(&mut expand).transpose()?,
+            body: self
+                .body
+                .as_ref()
+                .map(|body| templates.expand(body, rng))
+                .transpose()?,
+            ..self.clone()
+        })
+    }
+
+    // The request with every reference filled in from `steps`.
+    fn case(
+        &self,
+        steps: &BTreeMap<String, Value>,
+        auth: &dyn AuthStrategy,
+    ) -> Result<TestCase, String> {
+        let endpoint = string(render(&Value::from(self.endpoint.as_str()), steps)?);
+        let body = self
+            .body
+            .as_ref()
+            .map(|body| render(body, steps))
+            .transpose()?;
+        let mut case = TestCase::new(endpoint, self.method.clone(), body);
+        for (name, value) in &self.query {
+            let value = string(render(&Value::from(value.as_str()), steps)?);
+            case = case.with_query([(name.clone(), value)]);
+        }
+        if let Some(token) = &self.token {
+            let token = string(render(&Value::from(token.as_str()), steps)?);
+            auth.apply(&token, &mut case);
+        }
+        Ok(case)
+    }
+}
+
+impl fmt::Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "`{}` ({} {})", self.name, self.method, self.endpoint)
+    }
+}
+
+// Steps run in order against one server, starting from its before hooks (by
+// default `DELETE /clear`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Scenario {
+    pub name: String,
+    pub steps: Vec<Step>,
+}
+
+impl Scenario {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            steps: Vec::new(),
+        }
+    }
+
+    pub fn with_step(mut self, step: Step) -> Self {
+        self.steps.push(step);
+        self
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum ScenarioError {
+    #[error("step {step}: {message}")]
+    Template { step: String, message: String },
+
+    #[error("{0}")]
+    Request(Box<TesterError>),
+}
+
+impl From<TesterError> for ScenarioError {
+    fn from(err: TesterError) -> Self {
+        ScenarioError::Request(Box::new(err))
+    }
+}
+
+// Each step's status on one server, up to the step that couldn't be sent, and
+// what the built-in invariants caught in its responses, by step index.
+#[derive(Debug)]
+pub struct ServerRun {
+    pub server: String,
+    pub statuses: Vec<u16>,
+    // Each step as sent, with the whole response.
+    pub responses: Vec<(TestCase, ServerResponse)>,
+    pub invariants: Vec<(usize, String)>,
+    pub outcome: Result<(), ScenarioError>,
+}
+
+#[derive(Debug)]
+pub struct ScenarioResult {
+    pub scenario: Scenario,
+    pub runs: Vec<ServerRun>,
+}
+
+// Something a scenario caught, by step index.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Failure {
+    Unexpected {
+        step: usize,
+        server: String,
+        expected: u16,
+        status: u16,
+    },
+    // The servers answered the step with different statuses.
+    Disagreement {
+        step: usize,
+        statuses: Vec<(String, u16)>,
+    },
+    // A response broke one of the built-in invariants.
+    Invariant {
+        step: usize,
+        server: String,
+        message: String,
+    },
+}
+
+impl ScenarioResult {
+    // Unexpected statuses first for each step, then any disagreement, then
+    // broken invariants. Servers
+    // that stopped early are left out of the steps they never sent.
+    pub fn failures(&self) -> Vec<Failure> {
+        let mut failures = Vec::new();
+        for (index, step) in self.scenario.steps.iter().enumerate() {
+            let statuses: Vec<(String, u16)> = self
+                .runs
+                .iter()
+                .filter_map(|run| Some((run.server.clone(), *run.statuses.get(index)?)))
+                .collect();
+            if let Some(expected) = step.expect {
+                for (server, status) in &statuses {
+                    if *status != expected {
+                        failures.push(Failure::Unexpected {
+                            step: index,
+                            server: server.clone(),
+                            expected,
+                            status: *status,
+                        });
+                    }
+                }
+            }
+            if statuses.windows(2).any(|pair| pair[0].1 != pair[1].1) {
+                failures.push(Failure::Disagreement {
+                    step: index,
+                    statuses,
+                });
+            }
+            for run in &self.runs {
+                for (_, message) in run.invariants.iter().filter(|(step, _)| *step == index) {
+                    failures.push(Failure::Invariant {
+                        step: index,
+                        server: run.server.clone(),
+                        message: message.clone(),
+                    });
+                }
+            }
+        }
+        failures
+    }
+
+    pub fn passed(&self) -> bool {
+        self.runs.iter().all(|run| run.outcome.is_ok()) && self.failures().is_empty()
+    }
+}
+
+// Runs scenarios against every server in turn. Failed expectations don't stop
+// a scenario, so one run shows every step a server gets wrong.
+pub struct ScenarioRunner {
+    servers: Vec<(String, Tester)>,
+    auth: Arc<dyn AuthStrategy>,
+    templates: Templates,
+}
+
+impl ScenarioRunner {
+    pub fn new(version: Option<ApiVersion>) -> Self {
+        Self {
+            servers: Vec::new(),
+            auth: version.unwrap_or(ApiVersion::V1).auth_strategy(),
+            templates: Templates::default(),
+        }
+    }
+
+    // Only `tester`'s server under test is used.
+    pub fn with_server(mut self, name: impl Into<String>, tester: Tester) -> Self {
+        self.servers.push((name.into(), tester));
+        self
+    }
+
+    pub fn with_templates(mut self, templates: Templates) -> Self {
+        self.templates = templates;
+        self
+    }
+
+    pub fn with_auth(mut self, auth: Arc<dyn AuthStrategy>) -> Self {
+        self.auth = auth;
+        self
+    }
+
+    // `rng` makes up the data for placeholders. Results hold the scenarios
+    // as sent, with that data filled in.
+    pub async fn run(&self, scenarios: &[Scenario], rng: &mut FuzzRng) -> Vec<ScenarioResult> {
+        let mut results = Vec::with_capacity(scenarios.len());
+        for scenario in scenarios {
+            let mut runs = Vec::with_capacity(self.servers.len());
+            let expanded = scenario
+                .steps
+                .iter()
+                .map(|step| {
+                    step.expand(&self.templates, rng)
+                        .map_err(|err| (step.to_string(), err))
+                })
+                .collect::<Result<Vec<Step>, _>>();
+            let scenario = match expanded {
+                Ok(steps) => Scenario {
+                    name: scenario.name.clone(),
+                    steps,
+                },
+                Err((step, err)) => {
+                    for (server, _) in &self.servers {
+                        runs.push(ServerRun {
+                            server: server.clone(),
+                            statuses: Vec::new(),
+                            responses: Vec::new(),
+                            invariants: Vec::new(),
+                            outcome: Err(ScenarioError::Template {
+                                step: step.clone(),
+                                message: err.to_string(),
+                            }),
+                        });
+                    }
+                    results.push(ScenarioResult {
+                        scenario: scenario.clone(),
+                        runs,
+                    });
+                    continue;
+                }
+            };
+            for (server, tester) in &self.servers {
+                runs.push(self.run_on(server, tester, &scenario).await);
+            }
+            results.push(ScenarioResult { scenario, runs });
+        }
+        results
+    }
+
+    async fn run_on(&self, server: &str, tester: &Tester, scenario: &Scenario) -> ServerRun {
+        let mut run = ServerRun {
+            server: server.to_string(),
+            statuses: Vec::new(),
+            responses: Vec::new(),
+            invariants: Vec::new(),
+            outcome: Ok(()),
+        };
+        if let Err(err) = tester.reset().await {
+            run.outcome = Err(err.into());
+            return run;
+        }
+        let mut steps = BTreeMap::new();
+        let mut invariants = Invariants::new();
+        for (index, step) in scenario.steps.iter().enumerate() {
+            let case = match step.case(&steps, &*self.auth) {
+                Ok(case) => case,
+                Err(message) => {
+                    run.outcome = Err(ScenarioError::Template {
+                        step: step.to_string(),
+                        message,
+                    });
+                    break;
+                }
+            };
+            match tester.send(&case).await {
+                Ok(response) => {
+                    run.statuses.push(response.status);
+                    for finding in invariants.observe(&case, &response) {
+                        run.invariants.push((index, finding.message));
+                    }
+                    steps.insert(
+                        step.name.clone(),
+                        json!({ "status": response.status, "body": response.body }),
+                    );
+                    run.responses.push((case, response));
+                }
+                Err(err) => {
+                    run.outcome = Err(err.into());
+                    break;
+                }
+            }
+        }
+        run
+    }
+}
+
+// `value` with references in its strings (and nested values) filled in.
+fn render(value: &Value, steps: &BTreeMap<String, Value>) -> Result<Value, String> {
+    match value {
+        Value::String(s) => render_string(s, steps),
+        Value::Array(items) => items.iter().map(|item| render(item, steps)).collect(),
+        Value::Object(fields) => fields
+            .iter()
+            .map(|(key, value)| Ok((key.clone(), render(value, steps)?)))
+            .collect::<Result<serde_json::Map<_, _>, String>>()
+            .map(Value::Object),
+        other => Ok(other.clone()),
+    }
+}
+
+fn render_string(template: &str, steps: &BTreeMap<String, Value>) -> Result<Value, String> {
+    let pieces = pieces(template)?;
+    if let [Piece::Reference(reference)] = pieces[..] {
+        return lookup(reference, steps);
+    }
+    let mut out = String::new();
+    for piece in pieces {
+        match piece {
+            Piece::Text(text) => out.push_str(text),
+            Piece::Reference(reference) => out.push_str(&string(lookup(reference, steps)?)),
+        }
+    }
+    Ok(Value::String(out))
+}
+
+// The references in `template`, without filling them in.
+pub fn references(template: &str) -> Result<Vec<&str>, String> {
+    Ok(pieces(template)?
+        .into_iter()
+        .filter_map(|piece| match piece {
+            Piece::Reference(reference) => Some(reference),
+            Piece::Text(_) => None,
+        })
+        .collect())
+}
+
+// The step named by `steps.<name>.status` or `steps.<name>.body.<field>...`,
+// where fields may also be array indices.
+pub fn referenced_step(reference: &str) -> Result<&str, String> {
+    let mut parts = reference.split('.');
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some("steps"), Some(name), Some("status")) if parts.next().is_none() => Ok(name),
+        (Some("steps"), Some(name), Some("body")) if !name.is_empty() => Ok(name),
+        _ => Err(format!(
+            "`{}` should look like `steps.<name>.body.<field>` or `steps.<name>.status`",
+            reference
+        )),
+    }
+}
+
+fn lookup(reference: &str, steps: &BTreeMap<String, Value>) -> Result<Value, String> {
+    let name = referenced_step(reference)?;
+    let mut value = steps.get(name).ok_or_else(|| {
+        format!(
+            "`{}` refers to step `{}`, which hasn't run",
+            reference, name
+        )
+    })?;
+    for part in reference.split('.').skip(2) {
+        value = match value {
+            Value::Array(items) => part.parse::<usize>().ok().and_then(|i| items.get(i)),
+            other => other.get(part),
+        }
+        .ok_or_else(|| format!("`{}` has no `{}` in {}", reference, part, value))?;
+    }
+    Ok(value.clone())
+}
+
+fn string(value: Value) -> String {
+    match value {
+        Value::String(s) => s,
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn steps() -> BTreeMap<String, Value> {
+        BTreeMap::from([(
+            "quiz".to_string(),
+            json!({ "status": 200, "body": { "quizId": 7, "ids": [3, 4] } }),
+        )])
+    }
+
+    #[test]
+    fn references_keep_their_type_unless_embedded() {
+        let steps = steps();
+        assert_eq!(
+            render(&json!("{{ steps.quiz.body.quizId }}"), &steps),
+            Ok(json!(7))
+        );
+        assert_eq!(
+            render(
+                &json!({ "path": "/quiz/{{steps.quiz.body.ids.1}}/x", "s": ["{{ steps.quiz.status }}"] }),
+                &steps
+            ),
+            Ok(json!({ "path": "/quiz/4/x", "s": [200] }))
+        );
+        assert!(render_string("{{ steps.later.status }}", &steps)
+            .unwrap_err()
+            .contains("hasn't run"));
+        assert!(render_string("{{ steps.quiz.body.nope }}", &steps).is_err());
+        assert!(render_string("{{ steps.quiz", &steps).is_err());
+        assert!(lookup("quiz.status", &steps).is_err());
+    }
+
+    #[test]
+    fn steps_become_requests_with_their_token() {
+        let auth = ApiVersion::V2.auth_strategy();
+        let step = Step::new(
+            "q",
+            Method::DELETE,
+            "/admin/quiz/{{ steps.quiz.body.quizId }}",
+        )
+        .with_query("ids", "{{ steps.quiz.body.ids }}")
+        .with_token("t");
+        let case = step.case(&steps(), &*auth).unwrap();
+        assert_eq!(case.endpoint, "/admin/quiz/7");
+        assert_eq!(case.query, [("ids".to_string(), "[3,4]".to_string())]);
+        assert_eq!(case.headers.get("token").unwrap(), "t");
+    }
+
+    #[test]
+    fn failures_list_unexpected_statuses_then_disagreements_then_invariants() {
+        let scenario = Scenario::new("s")
+            .with_step(Step::new("a", Method::GET, "/a").expect(200))
+            .with_step(Step::new("b", Method::GET, "/b"));
+        let run = |server: &str, statuses: Vec<u16>| ServerRun {
+            server: server.to_string(),
+            statuses,
+            responses: Vec::new(),
+            invariants: Vec::new(),
+            outcome: Ok(()),
+        };
+        let mut client = run("client", vec![400, 200]);
+        client.invariants.push((0, "duplicate quizId".to_string()));
+        let result = ScenarioResult {
+            scenario,
+            runs: vec![client, run("reference", vec![200])],
+        };
+        assert_eq!(
+            result.failures(),
+            [
+                Failure::Unexpected {
+                    step: 0,
+                    server: "client".to_string(),
+                    expected: 200,
+                    status: 400,
+                },
+                Failure::Disagreement {
+                    step: 0,
+                    statuses: vec![("client".to_string(), 400), ("reference".to_string(), 200)],
+                },
+                Failure::Invariant {
+                    step: 0,
+                    server: "client".to_string(),
+                    message: "duplicate quizId".to_string(),
+                },
+            ]
+        );
+        assert!(!result.passed());
+    }
+
+    #[test]
+    fn placeholders_are_filled_before_references() {
+        let step = Step::new("s", Method::POST, "/admin/quiz")
+            .with_body(json!({"id": "{{ id(7,7) }}", "quiz": "{{ steps.quiz.body.quizId }}"}))
+            .with_query("n", "{{ id(3,3) }}")
+            .with_token("{{ steps.quiz.body.token }}");
+        let expanded = step
+            .expand(&Templates::default(), &mut FuzzRng::new(0))
+            .unwrap();
+        assert_eq!(
+            expanded.body,
+            Some(json!({"id": 7, "quiz": "{{ steps.quiz.body.quizId }}"}))
+        );
+        assert_eq!(expanded.query, [("n".to_string(), "3".to_string())]);
+        assert_eq!(expanded.token, step.token);
+        assert!(Step::new("s", Method::GET, "/{{ nope }}")
+            .expand(&Templates::default(), &mut FuzzRng::new(0))
+            .is_err());
+    }
+}
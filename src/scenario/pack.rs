@@ -0,0 +1,335 @@
+use crate::models::endpoints::{self, ApiVersion};
+use crate::scenario::{Scenario, Step};
+use reqwest::Method;
+use serde_json::{json, Value};
+
+const EMAIL: &str = "hayden.smith@unsw.edu.au";
+const OTHER_EMAIL: &str = "giuliana.debellis@unsw.edu.au";
+const PASSWORD: &str = "password123";
+const QUIZ_ID: &str = "{{ steps.quiz.body.quizId }}";
+
+// Logout semantics: once a session is logged out its token gets 401 on every
+// protected route (logging out again included), and no other session is
+// affected.
+pub fn logout() -> Vec<Scenario> {
+    let mut stale = Scenario::new("a logged out token is rejected everywhere")
+        .with_step(register("register", EMAIL))
+        .with_step(register("other", OTHER_EMAIL))
+        .with_step(
+            Step::new("quiz", Method::POST, "/admin/quiz")
+                .with_body(json!({ "name": "Quiz A", "description": "" }))
+                .with_token(token("register"))
+                .expect(200),
+        )
+        .with_step(logout_step("logout", "register"));
+    for endpoint in endpoints::all().filter(|endpoint| endpoint.requires_auth) {
+        for (method, path) in endpoint.routes() {
+            // The token is checked before anything the path names is looked up.
+            let path = endpoint
+                .path_params
+                .iter()
+                .fold(path.to_string(), |path, param| {
+                    let value = if param.name == "quizid" { QUIZ_ID } else { "0" };
+                    path.replace(&format!("{{{}}}", param.name), value)
+                });
+            let mut step = Step::new(format!("{} {}", method, path), method.clone(), path);
+            let fields = endpoint.params.iter().map(|name| (*name, field(name)));
+            if method == Method::DELETE {
+                for (name, value) in fields {
+                    step = step.with_query(name, value.as_str().unwrap_or_default());
+                }
+            } else if method != Method::GET {
+                step = step.with_body(Value::Object(
+                    fields
+                        .map(|(name, value)| (name.to_string(), value))
+                        .collect(),
+                ));
+            }
+            stale = stale.with_step(step.with_token(token("register")).expect(401));
+        }
+    }
+
+    let others = Scenario::new("logging out one session keeps the others")
+        .with_step(register("register", EMAIL))
+        .with_step(login("login"))
+        .with_step(logout_step("logout", "register"))
+        .with_step(list("list with the other session", "login", 200))
+        .with_step(list("list with the logged out session", "register", 401));
+
+    let relogin = Scenario::new("logging in again doesn't revive a logged out token")
+        .with_step(register("register", EMAIL))
+        .with_step(logout_step("logout", "register"))
+        .with_step(login("login"))
+        .with_step(list("list with the old token", "register", 401))
+        .with_step(list("list with the new token", "login", 200));
+
+    vec![stale, others, relogin]
+}
+
+// The built-in scenarios for servers of `version`: the game session flows only
+// exist from iteration 3 on.
+pub fn builtin(version: Option<ApiVersion>) -> Vec<Scenario> {
+    let mut scenarios = logout();
+    if version == Some(ApiVersion::V2) {
+        scenarios.extend(sessions());
+    }
+    scenarios
+}
+
+const SESSION_ID: &str = "{{ steps.start.body.sessionId }}";
+const PLAYER_ID: &str = "{{ steps.join.body.playerId }}";
+
+// Game sessions end to end: a quiz owner starts a session, players join it
+// and answer while the owner steps it through its states, and both sides see
+// the same results in the end.
+pub fn sessions() -> Vec<Scenario> {
+    let game = with_session(Scenario::new(
+        "a game session runs from the lobby to the results",
+    ))
+    .with_step(join("join", "Yuchao"))
+    .with_step(session_state("lobby", 200))
+    .with_step(action("next question", "NEXT_QUESTION", 200))
+    .with_step(action("skip countdown", "SKIP_COUNTDOWN", 200))
+    .with_step(player("status", Method::GET, "", 200))
+    .with_step(player("question", Method::GET, "/question/1", 200))
+    .with_step(
+        player("answer", Method::PUT, "/question/1/answer", 200).with_body(json!({
+            "answerIds": ["{{ steps.question.body.answers.0.answerId }}"],
+        })),
+    )
+    .with_step(action("go to answer", "GO_TO_ANSWER", 200))
+    .with_step(player(
+        "question results",
+        Method::GET,
+        "/question/1/results",
+        200,
+    ))
+    .with_step(action("go to final results", "GO_TO_FINAL_RESULTS", 200))
+    .with_step(
+        Step::new(
+            "session results",
+            Method::GET,
+            format!("/admin/quiz/{}/session/{}/results", QUIZ_ID, SESSION_ID),
+        )
+        .with_token(token("register"))
+        .expect(200),
+    )
+    .with_step(player("player results", Method::GET, "/results", 200))
+    .with_step(action("end", "END", 200))
+    .with_step(session_state("ended", 200));
+
+    let names = with_session(Scenario::new("players in a session have distinct names"))
+        .with_step(join("join", "Yuchao"))
+        .with_step(join("join again", "Yuchao").expect(400))
+        .with_step(join("join as someone else", "Zhang").expect(200));
+
+    let invalid = with_session(Scenario::new(
+        "sessions refuse moves their state doesn't allow",
+    ))
+    .with_step(action("go to answer from the lobby", "GO_TO_ANSWER", 400))
+    .with_step(action("made up action", "DANCE", 400))
+    .with_step(action("end", "END", 200))
+    .with_step(action("next question after the end", "NEXT_QUESTION", 400))
+    .with_step(join("join after the end", "Yuchao").expect(400));
+
+    let delete = with_session(Scenario::new(
+        "a quiz with an active session can't be deleted",
+    ))
+    .with_step(
+        Step::new("delete", Method::DELETE, format!("/admin/quiz/{}", QUIZ_ID))
+            .with_token(token("register"))
+            .expect(400),
+    )
+    .with_step(action("end", "END", 200))
+    .with_step(
+        Step::new(
+            "delete once ended",
+            Method::DELETE,
+            format!("/admin/quiz/{}", QUIZ_ID),
+        )
+        .with_token(token("register"))
+        .expect(200),
+    );
+
+    vec![game, names, invalid, delete]
+}
+
+// A registered owner's quiz with one question, and a session of it in the
+// lobby.
+fn with_session(scenario: Scenario) -> Scenario {
+    scenario
+        .with_step(register("register", EMAIL))
+        .with_step(
+            Step::new("quiz", Method::POST, "/admin/quiz")
+                .with_body(json!({ "name": "Quiz A", "description": "" }))
+                .with_token(token("register"))
+                .expect(200),
+        )
+        .with_step(
+            Step::new(
+                "add question",
+                Method::POST,
+                format!("/admin/quiz/{}/question", QUIZ_ID),
+            )
+            .with_body(json!({
+                "questionBody": {
+                    "question": "Who is the Monarch of England?",
+                    "duration": 4,
+                    "points": 5,
+                    "answers": [
+                        { "answer": "Prince Charles", "correct": true },
+                        { "answer": "Queen Elizabeth", "correct": false },
+                    ],
+                    "thumbnailUrl": "http://google.com/some/image/path.jpg",
+                },
+            }))
+            .with_token(token("register"))
+            .expect(200),
+        )
+        .with_step(
+            Step::new(
+                "start",
+                Method::POST,
+                format!("/admin/quiz/{}/session/start", QUIZ_ID),
+            )
+            .with_body(json!({ "autoStartNum": 0 }))
+            .with_token(token("register"))
+            .expect(200),
+        )
+}
+
+fn join(name: &str, player: &str) -> Step {
+    Step::new(name, Method::POST, "/player/join")
+        .with_body(json!({ "sessionId": SESSION_ID, "name": player }))
+        .expect(200)
+}
+
+fn action(name: &str, action: &str, status: u16) -> Step {
+    Step::new(
+        name,
+        Method::PUT,
+        format!("/admin/quiz/{}/session/{}", QUIZ_ID, SESSION_ID),
+    )
+    .with_body(json!({ "action": action }))
+    .with_token(token("register"))
+    .expect(status)
+}
+
+fn session_state(name: &str, status: u16) -> Step {
+    Step::new(
+        name,
+        Method::GET,
+        format!("/admin/quiz/{}/session/{}", QUIZ_ID, SESSION_ID),
+    )
+    .with_token(token("register"))
+    .expect(status)
+}
+
+// `route` is what follows `/player/{playerid}`.
+fn player(name: &str, method: Method, route: &str, status: u16) -> Step {
+    Step::new(name, method, format!("/player/{}{}", PLAYER_ID, route)).expect(status)
+}
+
+fn token(step: &str) -> String {
+    format!("{{{{ steps.{}.body.token }}}}", step)
+}
+
+fn register(name: &str, email: &str) -> Step {
+    Step::new(name, Method::POST, "/admin/auth/register")
+        .with_body(json!({
+            "email": email,
+            "password": PASSWORD,
+            "nameFirst": "Hayden",
+            "nameLast": "Smith",
+        }))
+        .expect(200)
+}
+
+fn login(name: &str) -> Step {
+    Step::new(name, Method::POST, "/admin/auth/login")
+        .with_body(json!({ "email": EMAIL, "password": PASSWORD }))
+        .expect(200)
+}
+
+fn logout_step(name: &str, session: &str) -> Step {
+    Step::new(name, Method::POST, "/admin/auth/logout")
+        .with_token(token(session))
+        .expect(200)
+}
+
+fn list(name: &str, session: &str, status: u16) -> Step {
+    Step::new(name, Method::GET, "/admin/quiz/list")
+        .with_token(token(session))
+        .expect(status)
+}
+
+// A value the field would accept, so the token is the only thing wrong.
+fn field(name: &str) -> Value {
+    match name {
+        "email" => json!(EMAIL),
+        "userEmail" => json!(OTHER_EMAIL),
+        "nameFirst" => json!("Hayden"),
+        "nameLast" => json!("Smith"),
+        "oldPassword" => json!(PASSWORD),
+        "newPassword" => json!("password456"),
+        "name" => json!("Quiz B"),
+        "description" => json!("A quiz about things"),
+        "quizIds" => json!(format!("[{}]", QUIZ_ID)),
+        "newPosition" => json!(0),
+        "autoStartNum" => json!(0),
+        "action" => json!("END"),
+        _ => json!({}),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stale_tokens_are_tried_on_every_protected_route() {
+        let scenarios = logout();
+        assert_eq!(scenarios.len(), 3);
+        let protected: usize = endpoints::all()
+            .filter(|endpoint| endpoint.requires_auth)
+            .map(|endpoint| endpoint.routes().count())
+            .sum();
+        let stale = &scenarios[0];
+        assert_eq!(stale.steps.len(), 4 + protected);
+        assert!(stale.steps[4..].iter().all(|step| step.expect == Some(401)
+            && step.token.as_deref() == Some("{{ steps.register.body.token }}")
+            && !step.endpoint.replace("{{", "").contains('{')));
+    }
+
+    #[test]
+    fn session_scenarios_only_come_with_iteration_3() {
+        assert_eq!(builtin(None).len(), logout().len());
+        assert_eq!(builtin(Some(ApiVersion::V1)).len(), logout().len());
+        let all = builtin(Some(ApiVersion::V2));
+        assert_eq!(all.len(), logout().len() + sessions().len());
+
+        for scenario in sessions() {
+            let names: Vec<&str> = scenario
+                .steps
+                .iter()
+                .map(|step| step.name.as_str())
+                .collect();
+            assert_eq!(names[..4], ["register", "quiz", "add question", "start"]);
+            assert!(scenario.steps.iter().all(|step| step.expect.is_some()));
+            // Every session or player ID comes from an earlier step.
+            for (index, step) in scenario.steps.iter().enumerate() {
+                for source in ["start", "join", "question"] {
+                    let reference = format!("steps.{}.", source);
+                    if step.endpoint.contains(&reference)
+                        || step
+                            .body
+                            .as_ref()
+                            .is_some_and(|body| body.to_string().contains(&reference))
+                    {
+                        assert!(names[..index].contains(&source), "{}", step.name);
+                    }
+                }
+            }
+        }
+    }
+}
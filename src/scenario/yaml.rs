@@ -0,0 +1,321 @@
+use crate::fuzz::template::{is_step_reference, strings, Call};
+use crate::scenario::{referenced_step, references, Scenario, Step};
+use reqwest::Method;
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum ScenarioFileError {
+    #[error("Failed to read scenario `{}`: {source}", path.display())]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+
+    // `line` is 1-based.
+    #[error("{}:{line}: {message}", path.display())]
+    Invalid {
+        path: PathBuf,
+        line: usize,
+        message: String,
+    },
+}
+
+// A scenario as written by hand:
+//
+//   name: create a quiz
+//   steps:
+//     - name: register
+//       method: POST
+//       endpoint: /admin/auth/register
+//       body: { email: a@b.com, password: abcd1234, nameFirst: A, nameLast: B }
+//       expect: 200
+//     - method: POST
+//       endpoint: /admin/quiz
+//       token: "{{ steps.register.body.token }}"
+//       body: { name: Quiz, description: "" }
+//
+// Steps without a name are called `step<N>`, counting from 1.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ScenarioFile {
+    #[serde(default)]
+    name: Option<String>,
+    steps: Vec<StepFile>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct StepFile {
+    #[serde(default)]
+    name: Option<String>,
+    method: String,
+    endpoint: String,
+    #[serde(default)]
+    query: BTreeMap<String, String>,
+    #[serde(default)]
+    body: Option<Value>,
+    #[serde(default)]
+    token: Option<String>,
+    #[serde(default)]
+    expect: Option<u16>,
+}
+
+pub fn load(path: impl AsRef<Path>) -> Result<Scenario, ScenarioFileError> {
+    let path = path.as_ref();
+    let source = std::fs::read_to_string(path).map_err(|source| ScenarioFileError::Io {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    parse(&source, path)
+}
+
+// `path` is only used in errors, and for the name of a scenario without one.
+pub fn parse(source: &str, path: &Path) -> Result<Scenario, ScenarioFileError> {
+    let invalid = |line: usize, message: String| ScenarioFileError::Invalid {
+        path: path.to_path_buf(),
+        line,
+        message,
+    };
+    let file: ScenarioFile = serde_yaml::from_str(source).map_err(|err| {
+        let line = err.location().map_or(1, |location| location.line());
+        // The location is already in the path prefix.
+        let message = err.to_string();
+        let message = match message.rfind(" at line ") {
+            Some(index) => message[..index].to_string(),
+            None => message,
+        };
+        invalid(line, message)
+    })?;
+
+    let lines = StepLines::new(source);
+    let name = file.name.unwrap_or_else(|| {
+        path.file_stem()
+            .map_or_else(String::new, |stem| stem.to_string_lossy().into_owned())
+    });
+    let mut scenario = Scenario::new(name);
+    for (index, step) in file.steps.into_iter().enumerate() {
+        let line = |field: &str| lines.field(index, field);
+        let name = step.name.unwrap_or_else(|| format!("step{}", index + 1));
+        if name.is_empty() || name.contains('.') {
+            return Err(invalid(
+                line("name"),
+                format!("step name {:?} must be non-empty and without dots", name),
+            ));
+        }
+        if scenario.steps.iter().any(|earlier| earlier.name == name) {
+            return Err(invalid(
+                line("name"),
+                format!("there is already a step named `{}`", name),
+            ));
+        }
+        let method =
+            step.method.to_uppercase().parse::<Method>().map_err(|_| {
+                invalid(line("method"), format!("invalid method `{}`", step.method))
+            })?;
+        if let Some(status) = step.expect.filter(|status| !(100..=599).contains(status)) {
+            return Err(invalid(
+                line("expect"),
+                format!("`expect` must be an HTTP status, not {}", status),
+            ));
+        }
+
+        // Every reference must name a step that runs before this one, and
+        // every other placeholder must at least look like a provider call.
+        let mut templates = vec![("endpoint", step.endpoint.as_str())];
+        templates.extend(step.query.values().map(|value| ("query", value.as_str())));
+        templates.extend(step.token.as_deref().map(|token| ("token", token)));
+        if let Some(body) = &step.body {
+            strings(body, &mut |s| templates.push(("body", s)));
+        }
+        for (field, template) in templates {
+            let line = || lines.containing(index, template).unwrap_or(line(field));
+            for reference in references(template).map_err(|message| invalid(line(), message))? {
+                if !is_step_reference(reference) {
+                    Call::parse(reference).map_err(|message| {
+                        invalid(line(), format!("`{}`: {}", reference, message))
+                    })?;
+                    continue;
+                }
+                let target =
+                    referenced_step(reference).map_err(|message| invalid(line(), message))?;
+                if !scenario.steps.iter().any(|earlier| earlier.name == target) {
+                    return Err(invalid(
+                        line(),
+                        format!(
+                            "`{}` refers to step `{}`, which doesn't run before `{}`",
+                            reference, target, name
+                        ),
+                    ));
+                }
+            }
+        }
+
+        let mut built = Step::new(name, method, step.endpoint);
+        for (key, value) in step.query {
+            built = built.with_query(key, value);
+        }
+        if let Some(body) = step.body {
+            built = built.with_body(body);
+        }
+        if let Some(token) = step.token {
+            built = built.with_token(token);
+        }
+        if let Some(status) = step.expect {
+            built = built.expect(status);
+        }
+        scenario = scenario.with_step(built);
+    }
+    Ok(scenario)
+}
+
+// Where each step of a block-style `steps:` list starts, found from the
+// indentation of its `- ` items, so errors can point at a line. Flow-style
+// lists all point at the `steps:` line.
+struct StepLines<'a> {
+    lines: Vec<&'a str>,
+    // 0-based line indices.
+    steps_line: usize,
+    starts: Vec<usize>,
+}
+
+impl<'a> StepLines<'a> {
+    fn new(source: &'a str) -> Self {
+        let lines: Vec<&str> = source.lines().collect();
+        let steps_line = lines
+            .iter()
+            .position(|line| line.starts_with("steps:"))
+            .unwrap_or_default();
+        let mut starts = Vec::new();
+        let mut indent = None;
+        for (index, line) in lines.iter().enumerate().skip(steps_line + 1) {
+            let trimmed = line.trim_start();
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                continue;
+            }
+            let depth = line.len() - trimmed.len();
+            let item = trimmed == "-" || trimmed.starts_with("- ");
+            match indent {
+                None if item => indent = Some(depth),
+                None => break,
+                Some(expected) if depth < expected || (depth == expected && !item) => break,
+                _ => {}
+            }
+            if item && Some(depth) == indent {
+                starts.push(index);
+            }
+        }
+        Self {
+            lines,
+            steps_line,
+            starts,
+        }
+    }
+
+    fn range(&self, step: usize) -> std::ops::Range<usize> {
+        match self.starts.get(step) {
+            Some(&start) => {
+                let end = self
+                    .starts
+                    .get(step + 1)
+                    .copied()
+                    .unwrap_or(self.lines.len());
+                start..end
+            }
+            None => self.steps_line..self.steps_line + 1,
+        }
+    }
+
+    // The 1-based line of `field` in the step, or of the step itself.
+    fn field(&self, step: usize, field: &str) -> usize {
+        let range = self.range(step);
+        let start = range.start;
+        let key = format!("{}:", field);
+        range
+            .into_iter()
+            .find(|&index| {
+                let line = self.lines[index].trim_start();
+                line.trim_start_matches("- ").starts_with(&key)
+            })
+            .unwrap_or(start)
+            + 1
+    }
+
+    fn containing(&self, step: usize, text: &str) -> Option<usize> {
+        self.range(step)
+            .find(|&index| self.lines[index].contains(text))
+            .map(|index| index + 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VALID: &str = "\
+steps:
+  - name: register
+    method: post
+    endpoint: /admin/auth/register
+    body: { email: a@b.com }
+    expect: 200
+  - method: GET
+    endpoint: /admin/quiz/list
+    token: \"{{ steps.register.body.token }}\"
+";
+
+    // The line and message of the error for `source`.
+    fn error(source: &str) -> (usize, String) {
+        match parse(source, Path::new("s.yml")) {
+            Err(ScenarioFileError::Invalid { line, message, .. }) => (line, message),
+            other => panic!("expected an invalid scenario, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn steps_are_named_and_parsed() {
+        let scenario = parse(VALID, Path::new("dir/create-quiz.yml")).unwrap();
+        assert_eq!(scenario.name, "create-quiz");
+        assert_eq!(scenario.steps.len(), 2);
+        assert_eq!(scenario.steps[0].method, Method::POST);
+        assert_eq!(scenario.steps[0].expect, Some(200));
+        assert_eq!(scenario.steps[1].name, "step2");
+        assert_eq!(
+            scenario.steps[1].token.as_deref(),
+            Some("{{ steps.register.body.token }}")
+        );
+    }
+
+    #[test]
+    fn errors_point_at_the_offending_line() {
+        let (line, message) = error(&VALID.replace("method: GET", "method: \"NOT VALID\""));
+        assert_eq!((line, message.as_str()), (7, "invalid method `NOT VALID`"));
+
+        let (line, message) = error(&VALID.replace("steps.register", "steps.later"));
+        assert_eq!(line, 9);
+        assert!(
+            message.contains("doesn't run before `step2`"),
+            "{}",
+            message
+        );
+
+        let (line, message) = error(&VALID.replace("register.body.token", "register.token"));
+        assert_eq!(line, 9);
+        assert!(message.contains("should look like"), "{}", message);
+
+        let (line, _) = error(&VALID.replace("expect: 200", "expect: 999"));
+        assert_eq!(line, 6);
+        let (line, message) = error(&VALID.replace("    expect: 200", "    expected: 200"));
+        assert_eq!(line, 6);
+        assert!(message.contains("unknown field `expected`"), "{}", message);
+
+        let duplicate = format!("{}    name: register\n", VALID);
+        let (line, message) = error(&duplicate);
+        assert_eq!(line, 10);
+        assert_eq!(message, "there is already a step named `register`");
+    }
+}
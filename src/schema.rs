@@ -0,0 +1,376 @@
+use crate::fuzz::presets::Preset;
+use crate::spec::{parse_schema, template_matches, SpecError};
+use reqwest::Method;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::fmt;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum SchemaError {
+    #[error("Invalid schema `{0}`")]
+    Dsl(String),
+
+    #[error("Invalid JSON Schema: {0}")]
+    JsonSchema(#[from] SpecError),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Schema {
+    Any,
+    Null,
+    Boolean,
+    Integer,
+    Number,
+    String,
+    Array(Box<Schema>),
+    Object {
+        properties: BTreeMap<String, Schema>,
+        required: Vec<String>,
+    },
+    Nullable(Box<Schema>),
+    OneOf(Vec<Schema>),
+    // A field with COMP1531-specific generation rules; validated as its base type.
+    Preset(Preset),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SchemaViolation {
+    pub path: String,
+    pub expected: String,
+    pub actual: Value,
+}
+
+impl fmt::Display for SchemaViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "expected {} at `{}`, got {}",
+            self.expected, self.path, self.actual
+        )
+    }
+}
+
+impl Schema {
+    pub fn validate(&self, value: &Value) -> Result<(), SchemaViolation> {
+        self.validate_at(value, "")
+    }
+
+    fn validate_at(&self, value: &Value, path: &str) -> Result<(), SchemaViolation> {
+        let violation = || SchemaViolation {
+            path: if path.is_empty() {
+                "/".to_string()
+            } else {
+                path.to_string()
+            },
+            expected: self.describe(),
+            actual: value.clone(),
+        };
+
+        match (self, value) {
+            (Schema::Any, _) => Ok(()),
+            (Schema::Null, Value::Null) => Ok(()),
+            (Schema::Boolean, Value::Bool(_)) => Ok(()),
+            (Schema::Integer, Value::Number(n)) if n.is_i64() || n.is_u64() => Ok(()),
+            (Schema::Number, Value::Number(_)) => Ok(()),
+            (Schema::String, Value::String(_)) => Ok(()),
+            (Schema::Preset(preset), _) => preset.base_schema().validate_at(value, path),
+            (Schema::Nullable(_), Value::Null) => Ok(()),
+            (Schema::Nullable(inner), _) => inner.validate_at(value, path),
+            (Schema::OneOf(options), _) => {
+                if options.iter().any(|s| s.validate_at(value, path).is_ok()) {
+                    Ok(())
+                } else {
+                    Err(violation())
+                }
+            }
+            (Schema::Array(items), Value::Array(elems)) => {
+                for (i, elem) in elems.iter().enumerate() {
+                    items.validate_at(elem, &format!("{}/{}", path, i))?;
+                }
+                Ok(())
+            }
+            (
+                Schema::Object {
+                    properties,
+                    required,
+                },
+                Value::Object(map),
+            ) => {
+                for key in required {
+                    if !map.contains_key(key) {
+                        return Err(SchemaViolation {
+                            path: format!("{}/{}", path, key),
+                            expected: "required field".to_string(),
+                            actual: Value::Null,
+                        });
+                    }
+                }
+                for (key, schema) in properties {
+                    if let Some(field) = map.get(key) {
+                        schema.validate_at(field, &format!("{}/{}", path, key))?;
+                    }
+                }
+                Ok(())
+            }
+            _ => Err(violation()),
+        }
+    }
+
+    // A JSON Schema document `SchemaSource::JsonSchema` reads back as the same
+    // schema, except presets, which are written as their base type.
+    pub fn to_json_schema(&self) -> Value {
+        match self {
+            Schema::Any => json!({}),
+            Schema::Null => json!({ "type": "null" }),
+            Schema::Boolean => json!({ "type": "boolean" }),
+            Schema::Integer => json!({ "type": "integer" }),
+            Schema::Number => json!({ "type": "number" }),
+            Schema::String => json!({ "type": "string" }),
+            Schema::Array(items) => json!({ "type": "array", "items": items.to_json_schema() }),
+            Schema::Object {
+                properties,
+                required,
+            } => {
+                let properties: serde_json::Map<String, Value> = properties
+                    .iter()
+                    .map(|(name, schema)| (name.clone(), schema.to_json_schema()))
+                    .collect();
+                json!({ "type": "object", "properties": properties, "required": required })
+            }
+            Schema::Nullable(inner) => {
+                let mut schema = inner.to_json_schema();
+                if let Value::Object(fields) = &mut schema {
+                    fields.insert("nullable".to_string(), Value::Bool(true));
+                }
+                schema
+            }
+            Schema::OneOf(options) => {
+                json!({ "oneOf": options.iter().map(Schema::to_json_schema).collect::<Vec<_>>() })
+            }
+            Schema::Preset(preset) => preset.base_schema().to_json_schema(),
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            Schema::Any => "any".to_string(),
+            Schema::Null => "null".to_string(),
+            Schema::Boolean => "boolean".to_string(),
+            Schema::Integer => "integer".to_string(),
+            Schema::Number => "number".to_string(),
+            Schema::String => "string".to_string(),
+            Schema::Array(items) => format!("array of {}", items.describe()),
+            Schema::Object { .. } => "object".to_string(),
+            Schema::Nullable(inner) => format!("nullable {}", inner.describe()),
+            Schema::Preset(preset) => preset.base_schema().describe(),
+            Schema::OneOf(options) => options
+                .iter()
+                .map(Schema::describe)
+                .collect::<Vec<_>>()
+                .join(" | "),
+        }
+    }
+}
+
+// How a schema is written in the config: either a JSON Schema under a
+// `json_schema` key, or the shorthand below.
+//
+// The shorthand is a type name (`any`, `null`, `boolean`, `integer`, `number`,
+// `string`, or a generator preset such as `email`), optionally wrapped as
+// `[type]` for an array and/or suffixed with `?` to allow null. Objects map
+// field names to shorthand values; a field name ending in `?` is optional.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum SchemaSource {
+    JsonSchema { json_schema: Value },
+    Shorthand(Value),
+}
+
+impl SchemaSource {
+    pub fn to_schema(&self) -> Result<Schema, SchemaError> {
+        match self {
+            SchemaSource::JsonSchema { json_schema } => Ok(parse_schema(json_schema)?),
+            SchemaSource::Shorthand(value) => shorthand(value),
+        }
+    }
+}
+
+fn shorthand(value: &Value) -> Result<Schema, SchemaError> {
+    match value {
+        Value::String(expr) => shorthand_type(expr.trim()),
+        Value::Array(items) if items.len() == 1 => {
+            Ok(Schema::Array(Box::new(shorthand(&items[0])?)))
+        }
+        Value::Object(fields) => {
+            let mut properties = BTreeMap::new();
+            let mut required = Vec::new();
+            for (name, field) in fields {
+                let name = match name.strip_suffix('?') {
+                    Some(optional) => optional.to_string(),
+                    None => {
+                        required.push(name.clone());
+                        name.clone()
+                    }
+                };
+                properties.insert(name, shorthand(field)?);
+            }
+            Ok(Schema::Object {
+                properties,
+                required,
+            })
+        }
+        other => Err(SchemaError::Dsl(other.to_string())),
+    }
+}
+
+fn shorthand_type(expr: &str) -> Result<Schema, SchemaError> {
+    if let Some(inner) = expr.strip_suffix('?') {
+        return Ok(Schema::Nullable(Box::new(shorthand_type(inner)?)));
+    }
+    if let Some(inner) = expr.strip_prefix('[').and_then(|e| e.strip_suffix(']')) {
+        return Ok(Schema::Array(Box::new(shorthand_type(inner.trim())?)));
+    }
+    match expr {
+        "any" => Ok(Schema::Any),
+        "null" => Ok(Schema::Null),
+        "boolean" | "bool" => Ok(Schema::Boolean),
+        "integer" | "int" => Ok(Schema::Integer),
+        "number" => Ok(Schema::Number),
+        "string" => Ok(Schema::String),
+        other => Preset::parse(other)
+            .map(Schema::Preset)
+            .ok_or_else(|| SchemaError::Dsl(other.to_string())),
+    }
+}
+
+// Contracts for an endpoint's responses: `success` applies to 2xx responses
+// and `error` to everything else.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ResponseSchemas {
+    pub success: Option<Schema>,
+    pub error: Option<Schema>,
+}
+
+impl ResponseSchemas {
+    pub fn for_status(&self, status: u16) -> Option<&Schema> {
+        if (200..300).contains(&status) {
+            self.success.as_ref()
+        } else {
+            self.error.as_ref()
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SchemaRegistry {
+    entries: Vec<(Method, String, ResponseSchemas)>,
+}
+
+impl SchemaRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with(
+        mut self,
+        method: Method,
+        template: impl Into<String>,
+        schemas: ResponseSchemas,
+    ) -> Self {
+        self.entries.push((method, template.into(), schemas));
+        self
+    }
+
+    // Appends `other`'s entries, which only apply where none of these do.
+    pub fn with_all(mut self, other: SchemaRegistry) -> Self {
+        self.entries.extend(other.entries);
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn find(&self, method: &Method, endpoint: &str) -> Option<&ResponseSchemas> {
+        self.entries
+            .iter()
+            .find(|(m, template, _)| m == method && template_matches(template, endpoint))
+            .map(|(_, _, schemas)| schemas)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn parse(source: Value) -> Result<Schema, SchemaError> {
+        serde_json::from_value::<SchemaSource>(source)
+            .unwrap()
+            .to_schema()
+    }
+
+    #[test]
+    fn shorthand_covers_arrays_nulls_and_optional_fields() {
+        let schema = parse(json!({
+            "quizId": "int",
+            "tags": "[string]",
+            "owner?": "email?",
+            "questions": [{"points": "number"}],
+        }))
+        .unwrap();
+        schema
+            .validate(&json!({
+                "quizId": 1,
+                "tags": ["a"],
+                "owner": null,
+                "questions": [{"points": 1.5}],
+            }))
+            .unwrap();
+        schema
+            .validate(&json!({"quizId": 1, "tags": [], "questions": []}))
+            .unwrap();
+
+        let violation = schema
+            .validate(&json!({"quizId": 1, "tags": [2], "questions": []}))
+            .unwrap_err();
+        assert_eq!(violation.path, "/tags/0");
+        assert_eq!(violation.expected, "string");
+        let violation = schema.validate(&json!({"tags": []})).unwrap_err();
+        assert_eq!(violation.expected, "required field");
+    }
+
+    #[test]
+    fn json_schema_and_unknown_types() {
+        let schema = parse(json!({"json_schema": {"type": "array", "items": {"type": "integer"}}}));
+        assert_eq!(schema.unwrap(), Schema::Array(Box::new(Schema::Integer)));
+        assert!(matches!(parse(json!("float")), Err(SchemaError::Dsl(t)) if t == "float"));
+        assert!(matches!(parse(json!(["a", "b"])), Err(SchemaError::Dsl(_))));
+        // Presets validate as their base type.
+        assert_eq!(
+            parse(json!("email")).unwrap(),
+            Schema::Preset(Preset::Email)
+        );
+        assert_eq!(Schema::Preset(Preset::Id).describe(), "integer");
+    }
+
+    #[test]
+    fn responses_pick_their_schema_by_status() {
+        let schemas = ResponseSchemas {
+            success: Some(Schema::Object {
+                properties: BTreeMap::new(),
+                required: Vec::new(),
+            }),
+            error: Some(Schema::String),
+        };
+        assert_eq!(schemas.for_status(204), schemas.success.as_ref());
+        assert_eq!(schemas.for_status(301), Some(&Schema::String));
+
+        let registry = SchemaRegistry::new().with(Method::GET, "/quiz/{id}", schemas.clone());
+        assert_eq!(registry.find(&Method::GET, "/quiz/3"), Some(&schemas));
+        assert_eq!(registry.find(&Method::PUT, "/quiz/3"), None);
+        assert!(SchemaRegistry::new().is_empty());
+    }
+}
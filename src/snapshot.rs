@@ -0,0 +1,265 @@
+use crate::corpus::{json_files, slug, CorpusError, RecordedRequest};
+use crate::models::client::{Comparison, RawResponse, ServerResponse, Tester, TesterError};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, UNIX_EPOCH};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum SnapshotError {
+    #[error("Snapshot I/O failed: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Invalid snapshot: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("Invalid snapshot request: {0}")]
+    Request(#[from] CorpusError),
+
+    #[error("Invalid header `{0}` in snapshot")]
+    InvalidHeader(String),
+
+    #[error("Recording snapshots needs a reference server (`server_url`)")]
+    NoReference,
+
+    #[error("Failed to record snapshot: {0}")]
+    Reference(Box<TesterError>),
+}
+
+// A request and the reference server's response to it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub request: RecordedRequest,
+    pub status: u16,
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    pub body: Value,
+    // Redirects and non-JSON bodies, kept as they are.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub raw: Option<RawResponse>,
+    // When the request was sent, in seconds since the epoch, for checking
+    // timestamp fields in the recorded body.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sent_at: Option<u64>,
+}
+
+impl Snapshot {
+    pub fn new(request: RecordedRequest, response: &ServerResponse) -> Self {
+        Self {
+            request,
+            status: response.status,
+            headers: response
+                .headers
+                .iter()
+                .map(|(name, value)| {
+                    (
+                        name.to_string(),
+                        String::from_utf8_lossy(value.as_bytes()).into_owned(),
+                    )
+                })
+                .collect(),
+            body: response.body.clone(),
+            raw: response.raw.clone(),
+            sent_at: response
+                .sent_at
+                .and_then(|sent_at| sent_at.duration_since(UNIX_EPOCH).ok())
+                .map(|since_epoch| since_epoch.as_secs()),
+        }
+    }
+
+    // Latency isn't recorded, so slowdowns can't be measured offline.
+    pub fn response(&self) -> Result<ServerResponse, SnapshotError> {
+        let mut headers = HeaderMap::new();
+        for (name, value) in &self.headers {
+            let header = HeaderName::from_bytes(name.as_bytes())
+                .map_err(|_| SnapshotError::InvalidHeader(name.clone()))?;
+            let value = HeaderValue::from_str(value)
+                .map_err(|_| SnapshotError::InvalidHeader(name.clone()))?;
+            headers.append(header, value);
+        }
+        Ok(ServerResponse {
+            status: self.status,
+            headers,
+            body: self.body.clone(),
+            raw: self.raw.clone(),
+            latency: Duration::ZERO,
+            sent_at: self
+                .sent_at
+                .map(|seconds| UNIX_EPOCH + Duration::from_secs(seconds)),
+            http_version: None,
+            exchange: None,
+            json_issues: Vec::new(),
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SnapshotStore {
+    dir: PathBuf,
+}
+
+impl SnapshotStore {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    // Files are prefixed with `index` so the suite loads back in recording
+    // order, which matters when cases build on each other's server state.
+    pub fn write(&self, index: usize, snapshot: &Snapshot) -> Result<PathBuf, SnapshotError> {
+        std::fs::create_dir_all(&self.dir)?;
+        let path = self.dir.join(format!(
+            "{:05}-{}-{}.json",
+            index,
+            snapshot.request.method.to_lowercase(),
+            slug(&snapshot.request.endpoint)
+        ));
+        std::fs::write(&path, serde_json::to_vec_pretty(snapshot)?)?;
+        Ok(path)
+    }
+
+    pub fn load(&self) -> Result<Vec<Snapshot>, SnapshotError> {
+        json_files(&self.dir)?
+            .iter()
+            .map(|path| Ok(serde_json::from_slice(&std::fs::read(path)?)?))
+            .collect()
+    }
+}
+
+// Sends every request to the reference server and stores its responses,
+// replacing any earlier recording in the same directory. Nothing is replaced
+// unless every request got a response.
+pub async fn record(
+    tester: &Tester,
+    requests: Vec<RecordedRequest>,
+    store: &SnapshotStore,
+) -> Result<Vec<PathBuf>, SnapshotError> {
+    let mut snapshots = Vec::with_capacity(requests.len());
+    for request in requests {
+        let case = request.to_case()?;
+        let response = tester
+            .fetch_reference(&case)
+            .await
+            .ok_or(SnapshotError::NoReference)?
+            .map_err(|err| SnapshotError::Reference(Box::new(err)))?;
+        snapshots.push(Snapshot::new(request, &response));
+    }
+
+    if store.dir().exists() {
+        for path in json_files(store.dir())? {
+            std::fs::remove_file(path)?;
+        }
+    }
+    snapshots
+        .iter()
+        .enumerate()
+        .map(|(index, snapshot)| store.write(index, snapshot))
+        .collect()
+}
+
+// Compares the server under test against each recorded response in turn; no
+// reference server needs to be running.
+pub async fn compare(
+    tester: &Tester,
+    snapshots: Vec<Snapshot>,
+) -> Result<Vec<(Snapshot, Comparison)>, SnapshotError> {
+    let mut results = Vec::with_capacity(snapshots.len());
+    for snapshot in snapshots {
+        let case = snapshot.request.to_case()?;
+        let comparison = tester.compare_against(&case, snapshot.response()?).await;
+        results.push((snapshot, comparison));
+    }
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::client::TestCase;
+    use crate::models::oracle::StaticOracle;
+    use reqwest::Method;
+    use serde_json::json;
+
+    fn scratch(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "fuzzer1531-snapshot-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn snapshot(endpoint: &str) -> Snapshot {
+        let case = TestCase::new(endpoint, Method::POST, Some(json!({"name": "q"})));
+        let mut headers = HeaderMap::new();
+        headers.append("set-cookie", HeaderValue::from_static("a=1"));
+        headers.append("set-cookie", HeaderValue::from_static("b=2"));
+        let response = ServerResponse {
+            status: 400,
+            headers,
+            body: json!({"error": "bad"}),
+            raw: None,
+            latency: Duration::from_millis(12),
+            sent_at: None,
+            http_version: None,
+            exchange: None,
+            json_issues: Vec::new(),
+        };
+        Snapshot::new(RecordedRequest::new(&case, 7), &response)
+    }
+
+    #[test]
+    fn snapshots_give_back_the_recorded_response() {
+        let response = snapshot("/admin/quiz").response().unwrap();
+        assert_eq!(response.status, 400);
+        assert_eq!(response.body, json!({"error": "bad"}));
+        assert_eq!(response.latency, Duration::ZERO);
+        assert!(response.headers.contains_key("set-cookie"));
+
+        let mut broken = snapshot("/admin/quiz");
+        broken
+            .headers
+            .insert("bad name".to_string(), "x".to_string());
+        assert!(matches!(
+            broken.response(),
+            Err(SnapshotError::InvalidHeader(name)) if name == "bad name"
+        ));
+    }
+
+    #[test]
+    fn stores_load_in_recording_order() {
+        let store = SnapshotStore::new(scratch("order"));
+        for (index, endpoint) in ["/z/last", "/a/first", "/m/middle"].iter().enumerate() {
+            store.write(index, &snapshot(endpoint)).unwrap();
+        }
+        let endpoints: Vec<String> = store
+            .load()
+            .unwrap()
+            .into_iter()
+            .map(|snapshot| snapshot.request.endpoint)
+            .collect();
+        assert_eq!(endpoints, ["/z/last", "/a/first", "/m/middle"]);
+        std::fs::remove_dir_all(store.dir()).unwrap();
+    }
+
+    #[tokio::test]
+    async fn recording_needs_a_reference_and_keeps_the_old_suite() {
+        let store = SnapshotStore::new(scratch("reference"));
+        store.write(0, &snapshot("/kept")).unwrap();
+        let tester = Tester::against_oracle("http://localhost:1".into(), StaticOracle::new());
+        let requests = vec![snapshot("/new").request];
+        assert!(matches!(
+            record(&tester, requests, &store).await,
+            Err(SnapshotError::NoReference)
+        ));
+        assert_eq!(store.load().unwrap()[0].request.endpoint, "/kept");
+        std::fs::remove_dir_all(store.dir()).unwrap();
+    }
+}
@@ -0,0 +1,694 @@
+use crate::fuzz::presets::Preset;
+use crate::schema::{Schema, SchemaViolation};
+use reqwest::Method;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::Path;
+use thiserror::Error;
+
+const MAX_REF_DEPTH: usize = 32;
+
+#[derive(Debug, Error)]
+pub enum SpecError {
+    #[error("Failed to read spec: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Failed to parse spec: {0}")]
+    Parse(#[from] serde_yaml::Error),
+
+    #[error("Unresolvable reference `{0}`")]
+    UnresolvedRef(String),
+
+    #[error("Invalid spec: {0}")]
+    Invalid(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParameterLocation {
+    Path,
+    Query,
+    Header,
+    Cookie,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Parameter {
+    pub name: String,
+    pub location: ParameterLocation,
+    pub required: bool,
+    pub schema: Schema,
+}
+
+#[derive(Debug, Clone)]
+pub struct Operation {
+    pub method: Method,
+    pub path: String,
+    pub operation_id: Option<String>,
+    pub parameters: Vec<Parameter>,
+    pub request_body: Option<Schema>,
+    pub responses: BTreeMap<String, Schema>,
+}
+
+impl Operation {
+    pub fn matches(&self, endpoint: &str) -> bool {
+        template_matches(&self.path, endpoint)
+    }
+
+    pub fn response_schema(&self, status: u16) -> Option<&Schema> {
+        let exact = status.to_string();
+        let range = format!("{}XX", status / 100);
+        self.responses
+            .get(&exact)
+            .or_else(|| self.responses.get(&range))
+            .or_else(|| self.responses.get(&range.to_lowercase()))
+            .or_else(|| self.responses.get("default"))
+    }
+
+    pub fn path_params(&self) -> impl Iterator<Item = &Parameter> {
+        self.parameters
+            .iter()
+            .filter(|p| p.location == ParameterLocation::Path)
+    }
+
+    pub fn query_params(&self) -> impl Iterator<Item = &Parameter> {
+        self.parameters
+            .iter()
+            .filter(|p| p.location == ParameterLocation::Query)
+    }
+}
+
+// The endpoint's path, without the query string sent along with it.
+pub fn without_query(endpoint: &str) -> &str {
+    endpoint.split('?').next().unwrap_or_default()
+}
+
+// `{name}` segments in the template match any single segment of the endpoint.
+pub fn template_matches(template: &str, endpoint: &str) -> bool {
+    let endpoint = without_query(endpoint);
+    let template: Vec<&str> = split_path(template).collect();
+    let actual: Vec<&str> = split_path(endpoint).collect();
+
+    template.len() == actual.len()
+        && template
+            .iter()
+            .zip(actual.iter())
+            .all(|(t, a)| is_placeholder(t) || t == a)
+}
+
+fn split_path(path: &str) -> impl Iterator<Item = &str> {
+    path.split('/').filter(|s| !s.is_empty())
+}
+
+fn is_placeholder(segment: &str) -> bool {
+    segment.starts_with('{') && segment.ends_with('}')
+}
+
+#[derive(Debug, Clone)]
+pub struct Spec {
+    operations: Vec<Operation>,
+}
+
+impl Spec {
+    pub fn parse(source: &str) -> Result<Self, SpecError> {
+        let doc: Value = serde_yaml::from_str(source)?;
+        Parser { doc: &doc }.parse()
+    }
+
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, SpecError> {
+        let source = std::fs::read_to_string(path)?;
+        Self::parse(&source)
+    }
+
+    pub fn operations(&self) -> &[Operation] {
+        &self.operations
+    }
+
+    pub fn find(&self, method: &Method, endpoint: &str) -> Option<&Operation> {
+        // Literal segments win over placeholders, leftmost first, so
+        // `/admin/quiz/trash` never resolves to `/admin/quiz/{quizid}`.
+        self.operations
+            .iter()
+            .filter(|op| op.method == *method && op.matches(endpoint))
+            .min_by_key(|op| split_path(&op.path).map(is_placeholder).collect::<Vec<_>>())
+    }
+
+    pub fn validate_response(
+        &self,
+        method: &Method,
+        endpoint: &str,
+        status: u16,
+        body: &Value,
+    ) -> Result<(), SchemaViolation> {
+        match self
+            .find(method, endpoint)
+            .and_then(|op| op.response_schema(status))
+        {
+            Some(schema) => schema.validate(body),
+            None => Ok(()),
+        }
+    }
+}
+
+// Parses a standalone JSON Schema document; `$ref`s resolve within it.
+pub(crate) fn parse_schema(doc: &Value) -> Result<Schema, SpecError> {
+    Parser { doc }.schema(doc, 0)
+}
+
+struct Parser<'a> {
+    doc: &'a Value,
+}
+
+impl<'a> Parser<'a> {
+    fn parse(&self) -> Result<Spec, SpecError> {
+        let version = self
+            .doc
+            .get("openapi")
+            .and_then(Value::as_str)
+            .ok_or_else(|| SpecError::Invalid("missing `openapi` version".to_string()))?;
+        if !version.starts_with('3') {
+            return Err(SpecError::Invalid(format!(
+                "unsupported OpenAPI version `{}`",
+                version
+            )));
+        }
+
+        let paths = match self.doc.get("paths").and_then(Value::as_object) {
+            Some(paths) => paths,
+            None => {
+                return Ok(Spec {
+                    operations: Vec::new(),
+                })
+            }
+        };
+
+        let mut operations = Vec::new();
+        for (path, item) in paths {
+            let item = self.resolve(item, 0)?;
+            let shared = self.parameters(item.get("parameters"))?;
+
+            for (key, op) in item.as_object().into_iter().flatten() {
+                let method = match key.to_uppercase().parse::<Method>() {
+                    Ok(m) if is_http_method(&m) => m,
+                    _ => continue,
+                };
+                operations.push(self.operation(path, method, op, &shared)?);
+            }
+        }
+
+        Ok(Spec { operations })
+    }
+
+    fn operation(
+        &self,
+        path: &str,
+        method: Method,
+        op: &Value,
+        shared: &[Parameter],
+    ) -> Result<Operation, SpecError> {
+        let mut parameters = shared.to_vec();
+        for param in self.parameters(op.get("parameters"))? {
+            parameters.retain(|p| !(p.name == param.name && p.location == param.location));
+            parameters.push(param);
+        }
+
+        let request_body = match op.get("requestBody") {
+            Some(body) => self.content_schema(self.resolve(body, 0)?)?,
+            None => None,
+        };
+
+        let mut responses = BTreeMap::new();
+        for (code, response) in op
+            .get("responses")
+            .and_then(Value::as_object)
+            .into_iter()
+            .flatten()
+        {
+            if let Some(schema) = self.content_schema(self.resolve(response, 0)?)? {
+                responses.insert(code.to_uppercase().replace("DEFAULT", "default"), schema);
+            }
+        }
+
+        Ok(Operation {
+            method,
+            path: path.to_string(),
+            operation_id: op
+                .get("operationId")
+                .and_then(Value::as_str)
+                .map(str::to_string),
+            parameters,
+            request_body,
+            responses,
+        })
+    }
+
+    fn parameters(&self, params: Option<&Value>) -> Result<Vec<Parameter>, SpecError> {
+        let mut out = Vec::new();
+        for param in params.and_then(Value::as_array).into_iter().flatten() {
+            let param = self.resolve(param, 0)?;
+            let name = match param.get("name").and_then(Value::as_str) {
+                Some(name) => name.to_string(),
+                None => continue,
+            };
+            let location = match param.get("in").and_then(Value::as_str) {
+                Some("path") => ParameterLocation::Path,
+                Some("query") => ParameterLocation::Query,
+                Some("header") => ParameterLocation::Header,
+                Some("cookie") => ParameterLocation::Cookie,
+                _ => continue,
+            };
+            let schema = match param.get("schema") {
+                Some(schema) => self.schema(schema, 0)?,
+                None => Schema::Any,
+            };
+            out.push(Parameter {
+                name,
+                required: location == ParameterLocation::Path
+                    || param
+                        .get("required")
+                        .and_then(Value::as_bool)
+                        .unwrap_or(false),
+                location,
+                schema,
+            });
+        }
+        Ok(out)
+    }
+
+    fn content_schema(&self, holder: &Value) -> Result<Option<Schema>, SpecError> {
+        let content = match holder.get("content").and_then(Value::as_object) {
+            Some(content) => content,
+            None => return Ok(None),
+        };
+        let media = content.get("application/json").or_else(|| {
+            content
+                .iter()
+                .find(|(k, _)| k.contains("json"))
+                .map(|(_, v)| v)
+        });
+
+        match media.and_then(|m| m.get("schema")) {
+            Some(schema) => Ok(Some(self.schema(schema, 0)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn resolve(&self, value: &'a Value, depth: usize) -> Result<&'a Value, SpecError> {
+        match value.get("$ref").and_then(Value::as_str) {
+            Some(reference) => {
+                if depth > MAX_REF_DEPTH {
+                    return Err(SpecError::UnresolvedRef(reference.to_string()));
+                }
+                let target = reference
+                    .strip_prefix('#')
+                    .and_then(|pointer| self.doc.pointer(pointer))
+                    .ok_or_else(|| SpecError::UnresolvedRef(reference.to_string()))?;
+                self.resolve(target, depth + 1)
+            }
+            None => Ok(value),
+        }
+    }
+
+    fn schema(&self, value: &'a Value, depth: usize) -> Result<Schema, SpecError> {
+        // Recursive schemas are cut off rather than expanded forever.
+        if depth > MAX_REF_DEPTH {
+            return Ok(Schema::Any);
+        }
+        let value = self.resolve(value, 0)?;
+
+        if let Some(preset) = value
+            .get("x-fuzz-preset")
+            .and_then(Value::as_str)
+            .and_then(Preset::parse)
+        {
+            return Ok(self.nullable(value, Schema::Preset(preset)));
+        }
+
+        for key in ["oneOf", "anyOf"] {
+            if let Some(options) = value.get(key).and_then(Value::as_array) {
+                let options = options
+                    .iter()
+                    .map(|o| self.schema(o, depth + 1))
+                    .collect::<Result<Vec<_>, _>>()?;
+                return Ok(self.nullable(value, Schema::OneOf(options)));
+            }
+        }
+
+        if let Some(parts) = value.get("allOf").and_then(Value::as_array) {
+            let mut properties = BTreeMap::new();
+            let mut required = Vec::new();
+            for part in parts {
+                match self.schema(part, depth + 1)? {
+                    Schema::Object {
+                        properties: p,
+                        required: r,
+                    } => {
+                        properties.extend(p);
+                        required.extend(r);
+                    }
+                    other if parts.len() == 1 => return Ok(other),
+                    _ => {}
+                }
+            }
+            return Ok(self.nullable(
+                value,
+                Schema::Object {
+                    properties,
+                    required,
+                },
+            ));
+        }
+
+        let schema = match value.get("type") {
+            Some(Value::String(ty)) => self.typed(ty, value, depth)?,
+            Some(Value::Array(types)) => {
+                let mut options = Vec::new();
+                for ty in types.iter().filter_map(Value::as_str) {
+                    options.push(self.typed(ty, value, depth)?);
+                }
+                match options.len() {
+                    1 => options.remove(0),
+                    _ => Schema::OneOf(options),
+                }
+            }
+            _ if value.get("properties").is_some() => self.typed("object", value, depth)?,
+            _ if value.get("items").is_some() => self.typed("array", value, depth)?,
+            _ => Schema::Any,
+        };
+
+        Ok(self.nullable(value, schema))
+    }
+
+    fn typed(&self, ty: &str, value: &'a Value, depth: usize) -> Result<Schema, SpecError> {
+        Ok(match ty {
+            "null" => Schema::Null,
+            "boolean" => Schema::Boolean,
+            "integer" => Schema::Integer,
+            "number" => Schema::Number,
+            "string" => Schema::String,
+            "array" => match value.get("items") {
+                Some(items) => Schema::Array(Box::new(self.schema(items, depth + 1)?)),
+                None => Schema::Array(Box::new(Schema::Any)),
+            },
+            "object" => {
+                let mut properties = BTreeMap::new();
+                for (key, prop) in value
+                    .get("properties")
+                    .and_then(Value::as_object)
+                    .into_iter()
+                    .flatten()
+                {
+                    properties.insert(key.clone(), self.schema(prop, depth + 1)?);
+                }
+                let required = value
+                    .get("required")
+                    .and_then(Value::as_array)
+                    .into_iter()
+                    .flatten()
+                    .filter_map(Value::as_str)
+                    .map(str::to_string)
+                    .collect();
+                Schema::Object {
+                    properties,
+                    required,
+                }
+            }
+            _ => Schema::Any,
+        })
+    }
+
+    fn nullable(&self, value: &Value, schema: Schema) -> Schema {
+        if value
+            .get("nullable")
+            .and_then(Value::as_bool)
+            .unwrap_or(false)
+        {
+            Schema::Nullable(Box::new(schema))
+        } else {
+            schema
+        }
+    }
+}
+
+fn is_http_method(method: &Method) -> bool {
+    matches!(
+        *method,
+        Method::GET
+            | Method::POST
+            | Method::PUT
+            | Method::DELETE
+            | Method::PATCH
+            | Method::HEAD
+            | Method::OPTIONS
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const SPEC: &str = r##"
+openapi: 3.0.0
+paths:
+  /admin/quiz/{quizid}:
+    parameters:
+      - $ref: '#/components/parameters/QuizId'
+    get:
+      operationId: quizInfo
+      parameters:
+        - name: token
+          in: query
+          required: true
+          schema: { type: string }
+      responses:
+        '200':
+          $ref: '#/components/responses/Quiz'
+        4XX:
+          content:
+            application/json:
+              schema: { $ref: '#/components/schemas/Error' }
+  /admin/quiz/trash:
+    get:
+      operationId: trash
+      responses:
+        default:
+          content:
+            application/json:
+              schema: { type: object }
+components:
+  parameters:
+    QuizId:
+      name: quizid
+      in: path
+      schema: { type: integer }
+  responses:
+    Quiz:
+      content:
+        application/json:
+          schema: { $ref: '#/components/schemas/Quiz' }
+  schemas:
+    Error:
+      type: object
+      required: [error]
+      properties:
+        error: { type: string }
+    Named:
+      type: object
+      required: [name]
+      properties:
+        name: { type: string }
+    Quiz:
+      allOf:
+        - $ref: '#/components/schemas/Named'
+        - type: object
+          required: [quizId]
+          properties:
+            quizId: { type: integer }
+            description: { type: string, nullable: true }
+"##;
+
+    fn spec() -> Spec {
+        Spec::parse(SPEC).unwrap()
+    }
+
+    fn operation_id(spec: &Spec, method: Method, endpoint: &str) -> Option<String> {
+        spec.find(&method, endpoint)
+            .and_then(|op| op.operation_id.clone())
+    }
+
+    #[test]
+    fn refs_resolve_through_components() {
+        let spec = spec();
+        let op = spec.find(&Method::GET, "/admin/quiz/5").unwrap();
+        assert_eq!(
+            op.response_schema(200),
+            Some(&Schema::Object {
+                properties: BTreeMap::from([
+                    (
+                        "description".to_string(),
+                        Schema::Nullable(Box::new(Schema::String))
+                    ),
+                    ("name".to_string(), Schema::String),
+                    ("quizId".to_string(), Schema::Integer),
+                ]),
+                required: vec!["name".to_string(), "quizId".to_string()],
+            })
+        );
+        let params: Vec<(&str, ParameterLocation, bool, &Schema)> = op
+            .parameters
+            .iter()
+            .map(|p| (p.name.as_str(), p.location, p.required, &p.schema))
+            .collect();
+        assert_eq!(
+            params,
+            vec![
+                ("quizid", ParameterLocation::Path, true, &Schema::Integer),
+                ("token", ParameterLocation::Query, true, &Schema::String),
+            ]
+        );
+    }
+
+    #[test]
+    fn literal_routes_win_over_templates() {
+        let spec = spec();
+        // Whichever order the document lists them in.
+        let reversed = Spec {
+            operations: spec.operations.iter().rev().cloned().collect(),
+        };
+        for spec in [&spec, &reversed] {
+            for endpoint in ["/admin/quiz/trash", "/admin/quiz/trash?token=x"] {
+                assert_eq!(
+                    operation_id(spec, Method::GET, endpoint).as_deref(),
+                    Some("trash")
+                );
+            }
+        }
+        assert_eq!(
+            operation_id(&spec, Method::GET, "/admin/quiz/3").as_deref(),
+            Some("quizInfo")
+        );
+        assert_eq!(operation_id(&spec, Method::POST, "/admin/quiz/3"), None);
+        assert_eq!(operation_id(&spec, Method::GET, "/admin/quiz/3/x"), None);
+    }
+
+    #[test]
+    fn response_schemas_fall_back_to_ranges_and_default() {
+        let spec = spec();
+        let info = spec.find(&Method::GET, "/admin/quiz/1").unwrap();
+        assert!(matches!(
+            info.response_schema(403),
+            Some(Schema::Object { required, .. }) if required == &["error"]
+        ));
+        assert_eq!(info.response_schema(500), None);
+        let trash = spec.find(&Method::GET, "/admin/quiz/trash").unwrap();
+        assert!(trash.response_schema(500).is_some());
+    }
+
+    #[test]
+    fn violations_name_the_failing_path() {
+        let spec = spec();
+        let check = |body: Value| spec.validate_response(&Method::GET, "/admin/quiz/1", 200, &body);
+
+        assert_eq!(check(json!({"name": "a", "quizId": 1})), Ok(()));
+        assert_eq!(
+            check(json!({"name": "a", "quizId": 1, "description": null})),
+            Ok(())
+        );
+        let violation = check(json!({"name": "a", "quizId": "1"})).unwrap_err();
+        assert_eq!(
+            (violation.path.as_str(), violation.expected.as_str()),
+            ("/quizId", "integer")
+        );
+        let violation = check(json!({"quizId": 1})).unwrap_err();
+        assert_eq!(
+            (violation.path.as_str(), violation.expected.as_str()),
+            ("/name", "required field")
+        );
+        // Routes the spec doesn't describe aren't checked.
+        assert_eq!(
+            spec.validate_response(&Method::GET, "/v1/other", 200, &json!(1)),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn bad_refs_are_reported() {
+        let missing = r##"
+openapi: 3.0.0
+paths:
+  /a:
+    get:
+      responses:
+        '200': { $ref: '#/components/responses/Missing' }
+"##;
+        assert!(matches!(
+            Spec::parse(missing),
+            Err(SpecError::UnresolvedRef(r)) if r == "#/components/responses/Missing"
+        ));
+
+        let looping = r##"
+openapi: 3.0.0
+paths:
+  /a:
+    $ref: '#/paths/~1a'
+"##;
+        assert!(matches!(
+            Spec::parse(looping),
+            Err(SpecError::UnresolvedRef(_))
+        ));
+        assert!(matches!(
+            Spec::parse("swagger: '2.0'"),
+            Err(SpecError::Invalid(_))
+        ));
+    }
+
+    #[test]
+    fn recursive_schemas_are_cut_off() {
+        let recursive = r##"
+openapi: 3.0.0
+paths:
+  /tree:
+    get:
+      responses:
+        '200':
+          content:
+            application/json:
+              schema: { $ref: '#/components/schemas/Node' }
+components:
+  schemas:
+    Node:
+      type: object
+      properties:
+        children:
+          type: array
+          items: { $ref: '#/components/schemas/Node' }
+"##;
+        let spec = Spec::parse(recursive).unwrap();
+        let tree = json!({"children": [{"children": []}, {"children": [{}]}]});
+        assert_eq!(
+            spec.validate_response(&Method::GET, "/tree", 200, &tree),
+            Ok(())
+        );
+        let violation = spec
+            .validate_response(&Method::GET, "/tree", 200, &json!({"children": [1]}))
+            .unwrap_err();
+        assert_eq!(violation.path, "/children/0");
+    }
+
+    #[test]
+    fn templates_match_whatever_the_query() {
+        assert_eq!(
+            without_query("/admin/quiz/7?token=abc&x=1"),
+            "/admin/quiz/7"
+        );
+        assert_eq!(without_query("/admin/quiz/7"), "/admin/quiz/7");
+        assert!(template_matches(
+            "/admin/quiz/{quizid}",
+            "/admin/quiz/7?token=abc"
+        ));
+        assert!(!template_matches(
+            "/admin/quiz/{quizid}",
+            "/admin/quiz?token=abc"
+        ));
+    }
+}
@@ -0,0 +1,274 @@
+pub mod model;
+pub mod operation;
+pub mod race;
+
+use crate::models::auth::AuthStrategy;
+use crate::models::client::{ServerResponse, TestCase, Tester, TesterError};
+use crate::models::endpoints::ApiVersion;
+use crate::models::invariants::Invariants;
+use crate::rng::FuzzRng;
+use crate::stateful::model::{Expected, QuizModel};
+use crate::stateful::operation::Operation;
+use reqwest::Method;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::Arc;
+use thiserror::Error;
+
+// A point where the server stopped agreeing with the model.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Violation {
+    // Index into the sequence's operations of the step that exposed it.
+    pub step: usize,
+    pub message: String,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "step {}: {}", self.step + 1, self.message)
+    }
+}
+
+// One run from a cleared server; it stops at the first violation, since the
+// model means nothing about the server's state after that.
+#[derive(Debug)]
+pub struct SequenceResult {
+    pub seed: u64,
+    pub operations: Vec<Operation>,
+    pub outcome: Result<(), SequenceError>,
+}
+
+#[derive(Debug, Error)]
+pub enum SequenceError {
+    #[error("{0}")]
+    Violation(Violation),
+
+    // The server could not be reached or sent something other than JSON.
+    #[error("{0}")]
+    Request(Box<TesterError>),
+}
+
+impl From<TesterError> for SequenceError {
+    fn from(err: TesterError) -> Self {
+        SequenceError::Request(Box::new(err))
+    }
+}
+
+// Applies random quiz lifecycle operations to the server under test and, after
+// each one, checks the quiz list, trash and quiz info of every logged in user
+// against an in-memory model. This catches servers whose responses look right
+// but whose state is wrong, which comparing responses alone cannot.
+pub struct ModelChecker {
+    tester: Tester,
+    auth: Arc<dyn AuthStrategy>,
+    sequences: usize,
+    steps: usize,
+}
+
+impl ModelChecker {
+    // Sends tokens wherever `tester`'s API version expects them.
+    pub fn new(tester: Tester, sequences: usize, steps: usize) -> Self {
+        let auth = tester.version().unwrap_or(ApiVersion::V1).auth_strategy();
+        Self {
+            tester,
+            auth,
+            sequences,
+            steps,
+        }
+    }
+
+    pub fn with_auth(mut self, auth: Arc<dyn AuthStrategy>) -> Self {
+        self.auth = auth;
+        self
+    }
+
+    // Each sequence gets its own seed, drawn from `rng`.
+    pub async fn run(&self, rng: &mut FuzzRng) -> Vec<SequenceResult> {
+        let mut results = Vec::with_capacity(self.sequences);
+        for _ in 0..self.sequences {
+            results.push(self.sequence(rng.fork()).await);
+        }
+        results
+    }
+
+    pub async fn sequence(&self, mut rng: FuzzRng) -> SequenceResult {
+        let mut result = SequenceResult {
+            seed: rng.seed(),
+            operations: Vec::new(),
+            outcome: Ok(()),
+        };
+        if let Err(err) = self.tester.reset().await {
+            result.outcome = Err(err.into());
+            return result;
+        }
+
+        let mut model = QuizModel::new();
+        let mut invariants = Invariants::new();
+        for step in 0..self.steps {
+            let operation = Operation::generate(&mut rng, &model);
+            result.operations.push(operation.clone());
+            let outcome = match self.step(&mut model, &mut invariants, &operation).await {
+                Ok(()) => self.observe(&model, &mut invariants).await,
+                Err(err) => Err(err),
+            };
+            if let Err(err) = outcome {
+                result.outcome = Err(match err {
+                    Check::Failed(message) => SequenceError::Violation(Violation { step, message }),
+                    Check::Request(err) => err.into(),
+                });
+                break;
+            }
+        }
+        result
+    }
+
+    async fn step(
+        &self,
+        model: &mut QuizModel,
+        invariants: &mut Invariants,
+        operation: &Operation,
+    ) -> Result<(), Check> {
+        let case = operation.case(&*self.auth);
+        let response = self.send(invariants, &case).await?;
+        let succeeded = response.status == 200;
+        let expected = model.expect(operation);
+        match (expected, response.status) {
+            (_, status) if status >= 500 => {
+                return Err(Check::Failed(format!(
+                    "{} failed with status {}",
+                    operation, status
+                )));
+            }
+            (Expected::Success, _) if !succeeded => {
+                return Err(Check::Failed(format!(
+                    "{} should succeed, but got status {}: {}",
+                    operation, response.status, response.body
+                )));
+            }
+            (Expected::Failure, _) if succeeded => {
+                return Err(Check::Failed(format!(
+                    "{} should fail, but got status 200: {}",
+                    operation, response.body
+                )));
+            }
+            _ => {}
+        }
+        if succeeded {
+            model
+                .apply(operation, &response.body)
+                .map_err(|err| Check::Failed(format!("{}: {}", operation, err)))?;
+        }
+        Ok(())
+    }
+
+    // Every logged in user's quizzes, trash and quiz details must match the
+    // model exactly.
+    async fn observe(&self, model: &QuizModel, invariants: &mut Invariants) -> Result<(), Check> {
+        for (user, token) in model.sessions() {
+            for (trashed, endpoint) in [(false, "/admin/quiz/list"), (true, "/admin/quiz/trash")] {
+                let response = self.get(invariants, endpoint, token).await?;
+                let found = quiz_names(&response.body).ok_or_else(|| {
+                    Check::Failed(format!(
+                        "GET {} for {} has no `quizzes` list: {}",
+                        endpoint, user, response.body
+                    ))
+                })?;
+                let expected = model.owned(user, trashed);
+                if found != expected {
+                    return Err(Check::Failed(format!(
+                        "GET {} for {} should list {:?}, but lists {:?}",
+                        endpoint, user, expected, found
+                    )));
+                }
+            }
+
+            for (id, quiz) in model.quizzes() {
+                if quiz.owner != user || quiz.trashed {
+                    continue;
+                }
+                let endpoint = format!("/admin/quiz/{}", id);
+                let response = self.get(invariants, &endpoint, token).await?;
+                let name = response.body.get("name").and_then(Value::as_str);
+                let description = response.body.get("description").and_then(Value::as_str);
+                if name != Some(&quiz.name) || description != Some(&quiz.description) {
+                    return Err(Check::Failed(format!(
+                        "GET {} for {} should have name {:?} and description {:?}, but has {}",
+                        endpoint, user, quiz.name, quiz.description, response.body
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn get(
+        &self,
+        invariants: &mut Invariants,
+        endpoint: &str,
+        token: &str,
+    ) -> Result<ServerResponse, Check> {
+        let mut case = TestCase::new(endpoint, Method::GET, None);
+        self.auth.apply(token, &mut case);
+        let response = self.send(invariants, &case).await?;
+        if response.status != 200 {
+            return Err(Check::Failed(format!(
+                "GET {} should succeed, but got status {}: {}",
+                endpoint, response.status, response.body
+            )));
+        }
+        Ok(response)
+    }
+
+    // The first invariant the response breaks is a violation like any other.
+    async fn send(
+        &self,
+        invariants: &mut Invariants,
+        case: &TestCase,
+    ) -> Result<ServerResponse, Check> {
+        let response = self.tester.send(case).await?;
+        match invariants.observe(case, &response).into_iter().next() {
+            Some(finding) => Err(Check::Failed(finding.message)),
+            None => Ok(response),
+        }
+    }
+}
+
+enum Check {
+    Failed(String),
+    Request(TesterError),
+}
+
+impl From<TesterError> for Check {
+    fn from(err: TesterError) -> Self {
+        Check::Request(err)
+    }
+}
+
+// `{ quizzes: [{ quizId, name }] }` as names by ID.
+fn quiz_names(body: &Value) -> Option<BTreeMap<u64, String>> {
+    body.get("quizzes")?
+        .as_array()?
+        .iter()
+        .map(|quiz| {
+            Some((
+                quiz.get("quizId")?.as_u64()?,
+                quiz.get("name")?.as_str()?.to_string(),
+            ))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn quiz_lists_need_an_id_and_name_for_every_quiz() {
+        let listed = quiz_names(&json!({"quizzes": [{"quizId": 1, "name": "A"}]})).unwrap();
+        assert_eq!(listed, BTreeMap::from([(1, "A".to_string())]));
+        assert_eq!(quiz_names(&json!({"quizzes": [{"quizId": 1}]})), None);
+        assert_eq!(quiz_names(&json!({})), None);
+    }
+}
@@ -0,0 +1,394 @@
+use crate::stateful::operation::{Operation, Session};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct User {
+    pub password: String,
+    pub name_first: String,
+    pub name_last: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Quiz {
+    // Email of the user who owns the quiz.
+    pub owner: String,
+    pub name: String,
+    pub description: String,
+    pub trashed: bool,
+}
+
+// What the model predicts the server does with an operation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Expected {
+    Success,
+    Failure,
+    // The spec leaves this case open (e.g. renaming a quiz in the trash), so
+    // the model follows whatever the server does.
+    Unknown,
+}
+
+// The state a correct server should be in after the operations applied so far.
+// Quizzes are keyed by the IDs the server handed out.
+#[derive(Debug, Clone, Default)]
+pub struct QuizModel {
+    users: BTreeMap<String, User>,
+    // The current session of each logged in user, by email.
+    sessions: BTreeMap<String, String>,
+    // Tokens of sessions that have been logged out.
+    revoked: Vec<String>,
+    quizzes: BTreeMap<u64, Quiz>,
+}
+
+impl QuizModel {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn users(&self) -> impl Iterator<Item = (&str, &User)> {
+        self.users
+            .iter()
+            .map(|(email, user)| (email.as_str(), user))
+    }
+
+    pub fn sessions(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.sessions
+            .iter()
+            .map(|(email, token)| (email.as_str(), token.as_str()))
+    }
+
+    pub fn revoked(&self) -> &[String] {
+        &self.revoked
+    }
+
+    pub fn quizzes(&self) -> impl Iterator<Item = (u64, &Quiz)> {
+        self.quizzes.iter().map(|(id, quiz)| (*id, quiz))
+    }
+
+    pub fn quiz(&self, id: u64) -> Option<&Quiz> {
+        self.quizzes.get(&id)
+    }
+
+    // Quiz names by ID for `owner`'s quizzes in (`trashed`) or out of the trash.
+    pub fn owned(&self, owner: &str, trashed: bool) -> BTreeMap<u64, String> {
+        self.quizzes
+            .iter()
+            .filter(|(_, quiz)| quiz.owner == owner && quiz.trashed == trashed)
+            .map(|(id, quiz)| (*id, quiz.name.clone()))
+            .collect()
+    }
+
+    fn name_taken(&self, owner: &str, name: &str, except: Option<u64>) -> Expected {
+        let mut expected = Expected::Success;
+        for (id, quiz) in &self.quizzes {
+            if Some(*id) == except || quiz.owner != owner || quiz.name != name {
+                continue;
+            }
+            if !quiz.trashed {
+                return Expected::Failure;
+            }
+            expected = Expected::Unknown;
+        }
+        expected
+    }
+
+    // The quiz `id` when `user` owns it, which every quiz route requires.
+    fn owned_quiz(&self, user: &str, id: u64) -> Option<&Quiz> {
+        self.quizzes.get(&id).filter(|quiz| quiz.owner == user)
+    }
+
+    pub fn expect(&self, operation: &Operation) -> Expected {
+        use Expected::*;
+
+        match operation {
+            Operation::Register { email, .. } => {
+                if self.users.contains_key(email) {
+                    Failure
+                } else {
+                    Success
+                }
+            }
+            Operation::Login { email, password } => match self.users.get(email) {
+                Some(user) if user.password == *password => Success,
+                _ => Failure,
+            },
+            _ => {
+                let Some(user) = operation.session().and_then(|s| s.user.as_deref()) else {
+                    return Failure;
+                };
+                self.expect_authorized(user, operation)
+            }
+        }
+    }
+
+    fn expect_authorized(&self, user: &str, operation: &Operation) -> Expected {
+        use Expected::*;
+
+        match operation {
+            Operation::Register { .. } | Operation::Login { .. } => {
+                unreachable!("sessionless operations are handled by `expect`")
+            }
+            Operation::Logout { .. } => Success,
+            Operation::CreateQuiz { name, .. } => self.name_taken(user, name, None),
+            Operation::RenameQuiz { quiz, name, .. } => match self.owned_quiz(user, *quiz) {
+                None => Failure,
+                Some(current) if current.trashed || current.name == *name => Unknown,
+                Some(_) => self.name_taken(user, name, Some(*quiz)),
+            },
+            Operation::UpdateDescription { quiz, .. } | Operation::RemoveQuiz { quiz, .. } => {
+                match self.owned_quiz(user, *quiz) {
+                    None => Failure,
+                    Some(current) if current.trashed => Unknown,
+                    Some(_) => Success,
+                }
+            }
+            Operation::RestoreQuiz { quiz, .. } => match self.owned_quiz(user, *quiz) {
+                Some(current) if current.trashed => {
+                    if self
+                        .owned(user, false)
+                        .values()
+                        .any(|name| *name == current.name)
+                    {
+                        Failure
+                    } else {
+                        Success
+                    }
+                }
+                _ => Failure,
+            },
+            Operation::EmptyTrash { quizzes, .. } => {
+                if quizzes.is_empty() {
+                    return Unknown;
+                }
+                let all_trashed = quizzes
+                    .iter()
+                    .all(|id| self.owned_quiz(user, *id).is_some_and(|quiz| quiz.trashed));
+                if all_trashed {
+                    Success
+                } else {
+                    Failure
+                }
+            }
+            Operation::TransferQuiz { quiz, email, .. } => match self.owned_quiz(user, *quiz) {
+                None => Failure,
+                Some(current) if current.trashed => Unknown,
+                Some(_) if email == user || !self.users.contains_key(email) => Failure,
+                Some(current) => self.name_taken(email, &current.name, Some(*quiz)),
+            },
+        }
+    }
+
+    // Updates the model after the server accepted `operation` with `body`.
+    // Fails when the body lacks what the model needs, like a new quiz's ID.
+    pub fn apply(&mut self, operation: &Operation, body: &Value) -> Result<(), String> {
+        match operation {
+            Operation::Register {
+                email,
+                password,
+                name_first,
+                name_last,
+            } => {
+                let token = token(body)?;
+                self.users.insert(
+                    email.clone(),
+                    User {
+                        password: password.clone(),
+                        name_first: name_first.clone(),
+                        name_last: name_last.clone(),
+                    },
+                );
+                self.log_in(email, token);
+            }
+            Operation::Login { email, .. } => {
+                let token = token(body)?;
+                self.log_in(email, token);
+            }
+            Operation::Logout { session } => {
+                if let Some(user) = &session.user {
+                    self.sessions.remove(user);
+                }
+                self.revoked.push(session.token.clone());
+            }
+            Operation::CreateQuiz {
+                session,
+                name,
+                description,
+            } => {
+                let id = body
+                    .get("quizId")
+                    .and_then(Value::as_u64)
+                    .ok_or("response has no numeric `quizId`")?;
+                self.quizzes.insert(
+                    id,
+                    Quiz {
+                        owner: owner(session)?,
+                        name: name.clone(),
+                        description: description.clone(),
+                        trashed: false,
+                    },
+                );
+            }
+            Operation::RenameQuiz { quiz, name, .. } => {
+                if let Some(quiz) = self.quizzes.get_mut(quiz) {
+                    quiz.name = name.clone();
+                }
+            }
+            Operation::UpdateDescription {
+                quiz, description, ..
+            } => {
+                if let Some(quiz) = self.quizzes.get_mut(quiz) {
+                    quiz.description = description.clone();
+                }
+            }
+            Operation::RemoveQuiz { quiz, .. } => {
+                if let Some(quiz) = self.quizzes.get_mut(quiz) {
+                    quiz.trashed = true;
+                }
+            }
+            Operation::RestoreQuiz { quiz, .. } => {
+                if let Some(quiz) = self.quizzes.get_mut(quiz) {
+                    quiz.trashed = false;
+                }
+            }
+            Operation::EmptyTrash { quizzes, .. } => {
+                for id in quizzes {
+                    self.quizzes.remove(id);
+                }
+            }
+            Operation::TransferQuiz { quiz, email, .. } => {
+                if let Some(quiz) = self.quizzes.get_mut(quiz) {
+                    quiz.owner = email.clone();
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Users may hold several sessions; only the newest is used from then on.
+    fn log_in(&mut self, email: &str, token: String) {
+        self.sessions.insert(email.to_string(), token);
+    }
+}
+
+fn token(body: &Value) -> Result<String, String> {
+    body.get("token")
+        .and_then(Value::as_str)
+        .map(str::to_string)
+        .ok_or_else(|| "response has no string `token`".to_string())
+}
+
+fn owner(session: &Session) -> Result<String, String> {
+    session
+        .user
+        .clone()
+        .ok_or_else(|| "an invalid session was accepted".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn session(user: &str, token: &str) -> Session {
+        Session {
+            token: token.to_string(),
+            user: Some(user.to_string()),
+        }
+    }
+
+    // Checks the model expected `operation` to succeed, then applies it.
+    fn accept(model: &mut QuizModel, operation: Operation, body: Value) {
+        assert_eq!(model.expect(&operation), Expected::Success, "{}", operation);
+        model.apply(&operation, &body).unwrap();
+    }
+
+    fn register(email: &str) -> Operation {
+        Operation::Register {
+            email: email.to_string(),
+            password: "password123".to_string(),
+            name_first: "Hayden".to_string(),
+            name_last: "Smith".to_string(),
+        }
+    }
+
+    fn create(user: &str, name: &str) -> Operation {
+        Operation::CreateQuiz {
+            session: session(user, user),
+            name: name.to_string(),
+            description: String::new(),
+        }
+    }
+
+    #[test]
+    fn quizzes_follow_their_lifecycle() {
+        let mut model = QuizModel::new();
+        accept(&mut model, register("a"), json!({"token": "a"}));
+        assert_eq!(model.expect(&register("a")), Expected::Failure);
+        accept(&mut model, create("a", "Quiz A"), json!({"quizId": 1}));
+        assert_eq!(model.expect(&create("a", "Quiz A")), Expected::Failure);
+
+        let remove = Operation::RemoveQuiz {
+            session: session("a", "a"),
+            quiz: 1,
+        };
+        accept(&mut model, remove, json!({}));
+        assert_eq!(model.owned("a", true).len(), 1);
+        // A trashed quiz's name may or may not be free again.
+        assert_eq!(model.expect(&create("a", "Quiz A")), Expected::Unknown);
+        accept(&mut model, create("a", "Quiz B"), json!({"quizId": 2}));
+
+        let restore = Operation::RestoreQuiz {
+            session: session("a", "a"),
+            quiz: 1,
+        };
+        accept(&mut model, restore, json!({}));
+        assert_eq!(model.owned("a", false).len(), 2);
+
+        let empty = |quizzes| Operation::EmptyTrash {
+            session: session("a", "a"),
+            quizzes,
+        };
+        assert_eq!(model.expect(&empty(vec![1])), Expected::Failure);
+        assert_eq!(model.expect(&empty(Vec::new())), Expected::Unknown);
+    }
+
+    #[test]
+    fn other_users_quizzes_and_dead_sessions_are_refused() {
+        let mut model = QuizModel::new();
+        accept(&mut model, register("a"), json!({"token": "a"}));
+        accept(&mut model, register("b"), json!({"token": "b"}));
+        accept(&mut model, create("a", "Quiz A"), json!({"quizId": 1}));
+
+        let rename = Operation::RenameQuiz {
+            session: session("b", "b"),
+            quiz: 1,
+            name: "Mine".to_string(),
+        };
+        assert_eq!(model.expect(&rename), Expected::Failure);
+
+        let logout = Operation::Logout {
+            session: session("a", "a"),
+        };
+        accept(&mut model, logout, json!({}));
+        assert_eq!(model.revoked(), ["a"]);
+        assert_eq!(model.sessions().count(), 1);
+        let stale = Operation::CreateQuiz {
+            session: Session {
+                token: "a".to_string(),
+                user: None,
+            },
+            name: "Quiz B".to_string(),
+            description: String::new(),
+        };
+        assert_eq!(model.expect(&stale), Expected::Failure);
+    }
+
+    #[test]
+    fn responses_missing_what_the_model_needs_are_errors() {
+        let mut model = QuizModel::new();
+        assert!(model.apply(&register("a"), &json!({})).is_err());
+        assert!(model
+            .apply(&create("a", "Quiz A"), &json!({"quizId": "1"}))
+            .is_err());
+    }
+}
@@ -0,0 +1,392 @@
+use crate::models::auth::AuthStrategy;
+use crate::models::client::TestCase;
+use crate::rng::FuzzRng;
+use crate::stateful::model::QuizModel;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use reqwest::Method;
+use serde_json::{json, Value};
+use std::fmt;
+
+// Small pools so operations keep running into each other: the same email
+// registered twice, two quizzes with one name, and so on.
+const EMAILS: &[&str] = &[
+    "hayden.smith@unsw.edu.au",
+    "giuliana.debellis@unsw.edu.au",
+    "yuchao.jiang@unsw.edu.au",
+];
+const PASSWORDS: &[&str] = &["password123", "hunter2isnotenough1"];
+const FIRST_NAMES: &[&str] = &["Hayden", "Giuliana", "Yuchao"];
+const LAST_NAMES: &[&str] = &["Smith", "De Bellis", "Jiang"];
+const QUIZ_NAMES: &[&str] = &["Quiz A", "Quiz B", "Quiz C"];
+const DESCRIPTIONS: &[&str] = &["", "A quiz about things", "Week 3 revision"];
+
+// A token for an operation, and the user it belongs to while it is valid.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Session {
+    pub token: String,
+    pub user: Option<String>,
+}
+
+impl Session {
+    // Picks mostly live sessions, but sometimes a logged out or made up one.
+    fn pick(rng: &mut FuzzRng, model: &QuizModel) -> Self {
+        let live: Vec<(&str, &str)> = model.sessions().collect();
+        let roll: f64 = rng.gen();
+        if let Some((user, token)) = live.choose(rng).filter(|_| roll < 0.85) {
+            return Self {
+                token: token.to_string(),
+                user: Some(user.to_string()),
+            };
+        }
+        match model.revoked().choose(rng).filter(|_| roll < 0.95) {
+            Some(token) => Self {
+                token: token.clone(),
+                user: None,
+            },
+            None => Self {
+                token: "not-a-valid-token".to_string(),
+                user: None,
+            },
+        }
+    }
+}
+
+// One step of a quiz lifecycle, as sent to the server.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Operation {
+    Register {
+        email: String,
+        password: String,
+        name_first: String,
+        name_last: String,
+    },
+    Login {
+        email: String,
+        password: String,
+    },
+    Logout {
+        session: Session,
+    },
+    CreateQuiz {
+        session: Session,
+        name: String,
+        description: String,
+    },
+    RenameQuiz {
+        session: Session,
+        quiz: u64,
+        name: String,
+    },
+    UpdateDescription {
+        session: Session,
+        quiz: u64,
+        description: String,
+    },
+    RemoveQuiz {
+        session: Session,
+        quiz: u64,
+    },
+    RestoreQuiz {
+        session: Session,
+        quiz: u64,
+    },
+    EmptyTrash {
+        session: Session,
+        quizzes: Vec<u64>,
+    },
+    TransferQuiz {
+        session: Session,
+        quiz: u64,
+        email: String,
+    },
+}
+
+impl Operation {
+    // A random next step given what the model knows; with nobody registered
+    // yet, that is almost always a registration.
+    pub fn generate(rng: &mut FuzzRng, model: &QuizModel) -> Self {
+        if model.sessions().next().is_none() && rng.gen_bool(0.9) {
+            return Self::register(rng);
+        }
+        match rng.gen_range(0..10) {
+            0 => Self::register(rng),
+            1 => {
+                let users: Vec<_> = model.users().collect();
+                let (email, password) = match users.choose(rng) {
+                    Some((email, user)) if rng.gen_bool(0.8) => {
+                        (email.to_string(), user.password.clone())
+                    }
+                    _ => (pick(rng, EMAILS), pick(rng, PASSWORDS)),
+                };
+                Self::Login { email, password }
+            }
+            2 => Self::Logout {
+                session: Session::pick(rng, model),
+            },
+            3 | 4 => Self::CreateQuiz {
+                session: Session::pick(rng, model),
+                name: pick(rng, QUIZ_NAMES),
+                description: pick(rng, DESCRIPTIONS),
+            },
+            5 => Self::RenameQuiz {
+                session: Session::pick(rng, model),
+                quiz: pick_quiz(rng, model),
+                name: pick(rng, QUIZ_NAMES),
+            },
+            6 => Self::UpdateDescription {
+                session: Session::pick(rng, model),
+                quiz: pick_quiz(rng, model),
+                description: pick(rng, DESCRIPTIONS),
+            },
+            7 => Self::RemoveQuiz {
+                session: Session::pick(rng, model),
+                quiz: pick_quiz(rng, model),
+            },
+            8 => {
+                let session = Session::pick(rng, model);
+                if rng.gen_bool(0.5) {
+                    Self::RestoreQuiz {
+                        session,
+                        quiz: pick_quiz(rng, model),
+                    }
+                } else {
+                    // Usually the whole trash of the session's user.
+                    let quizzes = match &session.user {
+                        Some(user) if rng.gen_bool(0.8) => {
+                            model.owned(user, true).into_keys().collect()
+                        }
+                        _ => vec![pick_quiz(rng, model)],
+                    };
+                    Self::EmptyTrash { session, quizzes }
+                }
+            }
+            _ => Self::TransferQuiz {
+                session: Session::pick(rng, model),
+                quiz: pick_quiz(rng, model),
+                email: pick(rng, EMAILS),
+            },
+        }
+    }
+
+    fn register(rng: &mut FuzzRng) -> Self {
+        Self::Register {
+            email: pick(rng, EMAILS),
+            password: pick(rng, PASSWORDS),
+            name_first: pick(rng, FIRST_NAMES),
+            name_last: pick(rng, LAST_NAMES),
+        }
+    }
+
+    pub fn session(&self) -> Option<&Session> {
+        match self {
+            Operation::Register { .. } | Operation::Login { .. } => None,
+            Operation::Logout { session }
+            | Operation::CreateQuiz { session, .. }
+            | Operation::RenameQuiz { session, .. }
+            | Operation::UpdateDescription { session, .. }
+            | Operation::RemoveQuiz { session, .. }
+            | Operation::RestoreQuiz { session, .. }
+            | Operation::EmptyTrash { session, .. }
+            | Operation::TransferQuiz { session, .. } => Some(session),
+        }
+    }
+
+    // The request for this operation, with the session token placed by `auth`.
+    pub fn case(&self, auth: &dyn AuthStrategy) -> TestCase {
+        let mut case = match self {
+            Operation::Register {
+                email,
+                password,
+                name_first,
+                name_last,
+            } => TestCase::new(
+                "/admin/auth/register",
+                Method::POST,
+                Some(json!({
+                    "email": email,
+                    "password": password,
+                    "nameFirst": name_first,
+                    "nameLast": name_last,
+                })),
+            ),
+            Operation::Login { email, password } => TestCase::new(
+                "/admin/auth/login",
+                Method::POST,
+                Some(json!({ "email": email, "password": password })),
+            ),
+            Operation::Logout { .. } => TestCase::new("/admin/auth/logout", Method::POST, None),
+            Operation::CreateQuiz {
+                name, description, ..
+            } => TestCase::new(
+                "/admin/quiz",
+                Method::POST,
+                Some(json!({ "name": name, "description": description })),
+            ),
+            Operation::RenameQuiz { quiz, name, .. } => TestCase::new(
+                format!("/admin/quiz/{}/name", quiz),
+                Method::PUT,
+                Some(json!({ "name": name })),
+            ),
+            Operation::UpdateDescription {
+                quiz, description, ..
+            } => TestCase::new(
+                format!("/admin/quiz/{}/description", quiz),
+                Method::PUT,
+                Some(json!({ "description": description })),
+            ),
+            Operation::RemoveQuiz { quiz, .. } => {
+                TestCase::new(format!("/admin/quiz/{}", quiz), Method::DELETE, None)
+            }
+            Operation::RestoreQuiz { quiz, .. } => {
+                TestCase::new(format!("/admin/quiz/{}/restore", quiz), Method::POST, None)
+            }
+            Operation::EmptyTrash { quizzes, .. } => {
+                TestCase::new("/admin/quiz/trash/empty", Method::DELETE, None)
+                    .with_query([("quizIds", Value::from(quizzes.clone()).to_string())])
+            }
+            Operation::TransferQuiz { quiz, email, .. } => TestCase::new(
+                format!("/admin/quiz/{}/transfer", quiz),
+                Method::POST,
+                Some(json!({ "userEmail": email })),
+            ),
+        };
+        if let Some(session) = self.session() {
+            auth.apply(&session.token, &mut case);
+        }
+        case
+    }
+}
+
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let as_user = |session: &Session| match &session.user {
+            Some(user) => user.clone(),
+            None => format!("invalid session {:?}", session.token),
+        };
+        match self {
+            Operation::Register { email, .. } => write!(f, "register {}", email),
+            Operation::Login { email, password } => {
+                write!(f, "log in as {} with {:?}", email, password)
+            }
+            Operation::Logout { session } => write!(f, "log out {}", as_user(session)),
+            Operation::CreateQuiz { session, name, .. } => {
+                write!(f, "{} creates quiz {:?}", as_user(session), name)
+            }
+            Operation::RenameQuiz {
+                session,
+                quiz,
+                name,
+            } => write!(
+                f,
+                "{} renames quiz {} to {:?}",
+                as_user(session),
+                quiz,
+                name
+            ),
+            Operation::UpdateDescription {
+                session,
+                quiz,
+                description,
+            } => write!(
+                f,
+                "{} sets quiz {}'s description to {:?}",
+                as_user(session),
+                quiz,
+                description
+            ),
+            Operation::RemoveQuiz { session, quiz } => {
+                write!(f, "{} moves quiz {} to the trash", as_user(session), quiz)
+            }
+            Operation::RestoreQuiz { session, quiz } => {
+                write!(f, "{} restores quiz {}", as_user(session), quiz)
+            }
+            Operation::EmptyTrash { session, quizzes } => {
+                write!(
+                    f,
+                    "{} empties {:?} from the trash",
+                    as_user(session),
+                    quizzes
+                )
+            }
+            Operation::TransferQuiz {
+                session,
+                quiz,
+                email,
+            } => write!(
+                f,
+                "{} transfers quiz {} to {}",
+                as_user(session),
+                quiz,
+                email
+            ),
+        }
+    }
+}
+
+fn pick(rng: &mut FuzzRng, values: &[&str]) -> String {
+    values.choose(rng).copied().unwrap_or_default().to_string()
+}
+
+// Usually a quiz the model knows about (possibly someone else's), otherwise
+// an ID nobody was given.
+fn pick_quiz(rng: &mut FuzzRng, model: &QuizModel) -> u64 {
+    let known: Vec<u64> = model.quizzes().map(|(id, _)| id).collect();
+    match known.choose(rng) {
+        Some(id) if rng.gen_bool(0.9) => *id,
+        _ => rng.gen_range(1_000_000..2_000_000),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::auth::{BodyField, Header};
+    use reqwest::header::HeaderName;
+
+    #[test]
+    fn cases_carry_the_session_where_auth_puts_it() {
+        let operation = Operation::EmptyTrash {
+            session: Session {
+                token: "abc".to_string(),
+                user: None,
+            },
+            quizzes: vec![1, 2],
+        };
+        let case = operation.case(&BodyField("token".to_string()));
+        assert_eq!(case.endpoint, "/admin/quiz/trash/empty");
+        assert_eq!(case.method, Method::DELETE);
+        assert_eq!(
+            case.query,
+            [
+                ("quizIds".to_string(), "[1,2]".to_string()),
+                ("token".to_string(), "abc".to_string())
+            ]
+        );
+
+        let header = HeaderName::from_static("token");
+        let case = operation.case(&Header(header.clone()));
+        assert_eq!(case.headers[&header], "abc");
+
+        let register = Operation::register(&mut FuzzRng::new(1));
+        let case = register.case(&BodyField("token".to_string()));
+        assert!(case.body.unwrap().get("token").is_none());
+    }
+
+    #[test]
+    fn sequences_start_by_registering_and_are_reproducible() {
+        let model = QuizModel::new();
+        let registrations = (0..50)
+            .filter(|&seed| {
+                matches!(
+                    Operation::generate(&mut FuzzRng::new(seed), &model),
+                    Operation::Register { .. }
+                )
+            })
+            .count();
+        assert!(registrations >= 40, "{}", registrations);
+
+        let first = Operation::generate(&mut FuzzRng::new(3), &model);
+        assert_eq!(first, Operation::generate(&mut FuzzRng::new(3), &model));
+    }
+}
@@ -0,0 +1,406 @@
+use crate::models::auth::AuthStrategy;
+use crate::models::client::{ServerResponse, Tester};
+use crate::models::invariants::Invariants;
+use crate::rng::FuzzRng;
+use crate::stateful::model::{Expected, QuizModel};
+use crate::stateful::operation::{Operation, Session};
+use crate::stateful::{Check, ModelChecker, SequenceError, Violation};
+use rand::seq::SliceRandom;
+use std::fmt;
+use std::sync::Arc;
+use tokio::sync::Barrier;
+
+const OWNER: &str = "hayden.smith@unsw.edu.au";
+const OTHERS: [&str; 2] = ["giuliana.debellis@unsw.edu.au", "yuchao.jiang@unsw.edu.au"];
+const PASSWORD: &str = "password123";
+
+// Two operations that conflict, sent to the server at the same moment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Race {
+    // The same email registered twice.
+    DoubleRegister,
+    // Two quizzes with one name created by the same user.
+    DoubleCreate,
+    // The same quiz moved to the trash twice.
+    DoubleRemove,
+    // Two of a user's quizzes renamed to the same name.
+    DoubleRename,
+    // One quiz transferred to two different users.
+    DoubleTransfer,
+    TransferWhileRenaming,
+    RestoreWhileEmptying,
+}
+
+impl Race {
+    pub const ALL: [Race; 7] = [
+        Race::DoubleRegister,
+        Race::DoubleCreate,
+        Race::DoubleRemove,
+        Race::DoubleRename,
+        Race::DoubleTransfer,
+        Race::TransferWhileRenaming,
+        Race::RestoreWhileEmptying,
+    ];
+
+    // Quizzes the owner creates before the race, and whether the first of them
+    // is then moved to the trash.
+    fn setup(self) -> (usize, bool) {
+        match self {
+            Race::DoubleRegister | Race::DoubleCreate => (0, false),
+            Race::DoubleRename => (2, false),
+            Race::RestoreWhileEmptying => (1, true),
+            Race::DoubleRemove | Race::DoubleTransfer | Race::TransferWhileRenaming => (1, false),
+        }
+    }
+
+    // Needs the setup's sessions and quiz IDs from `model`.
+    fn operations(self, model: &QuizModel, quizzes: &[u64]) -> [Operation; 2] {
+        let owner = session(model, OWNER);
+        let quiz = quizzes.first().copied().unwrap_or_default();
+        let rename = |quiz| Operation::RenameQuiz {
+            session: owner.clone(),
+            quiz,
+            name: "Quiz C".to_string(),
+        };
+        let transfer = |email: &str| Operation::TransferQuiz {
+            session: owner.clone(),
+            quiz,
+            email: email.to_string(),
+        };
+        match self {
+            Race::DoubleRegister => [register(OWNER), register(OWNER)],
+            Race::DoubleCreate => {
+                let create = Operation::CreateQuiz {
+                    session: owner.clone(),
+                    name: "Quiz A".to_string(),
+                    description: String::new(),
+                };
+                [create.clone(), create]
+            }
+            Race::DoubleRemove => {
+                let remove = Operation::RemoveQuiz {
+                    session: owner.clone(),
+                    quiz,
+                };
+                [remove.clone(), remove]
+            }
+            Race::DoubleRename => [
+                rename(quiz),
+                rename(quizzes.get(1).copied().unwrap_or_default()),
+            ],
+            Race::DoubleTransfer => [transfer(OTHERS[0]), transfer(OTHERS[1])],
+            Race::TransferWhileRenaming => [transfer(OTHERS[0]), rename(quiz)],
+            Race::RestoreWhileEmptying => [
+                Operation::RestoreQuiz {
+                    session: owner.clone(),
+                    quiz,
+                },
+                Operation::EmptyTrash {
+                    session: owner.clone(),
+                    quizzes: vec![quiz],
+                },
+            ],
+        }
+    }
+}
+
+impl fmt::Display for Race {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Race::DoubleRegister => "two registrations of one email",
+            Race::DoubleCreate => "two creates of one quiz name",
+            Race::DoubleRemove => "two removes of one quiz",
+            Race::DoubleRename => "two quizzes renamed to one name",
+            Race::DoubleTransfer => "one quiz transferred to two users",
+            Race::TransferWhileRenaming => "a transfer while renaming",
+            Race::RestoreWhileEmptying => "a restore while emptying the trash",
+        };
+        f.write_str(name)
+    }
+}
+
+#[derive(Debug)]
+pub struct RaceResult {
+    pub seed: u64,
+    pub race: Race,
+    // Everything sent, setup first; the last two were sent concurrently.
+    pub operations: Vec<Operation>,
+    // The racing operations' statuses, once both were answered.
+    pub statuses: Vec<u16>,
+    pub outcome: Result<(), SequenceError>,
+}
+
+// Sets up a small state, fires two conflicting operations at once (released
+// together by a barrier) and checks that the statuses and the server's state
+// afterwards match running them one after the other, in either order. A server
+// whose data store isn't atomic lets both through.
+pub struct RaceChecker {
+    checker: ModelChecker,
+    rounds: usize,
+}
+
+impl RaceChecker {
+    // Sends tokens wherever `tester`'s API version expects them.
+    pub fn new(tester: Tester, rounds: usize) -> Self {
+        Self {
+            checker: ModelChecker::new(tester, 0, 0),
+            rounds,
+        }
+    }
+
+    pub fn with_auth(mut self, auth: Arc<dyn AuthStrategy>) -> Self {
+        self.checker = self.checker.with_auth(auth);
+        self
+    }
+
+    // Each round gets its own seed, drawn from `rng`, which picks the race.
+    pub async fn run(&self, rng: &mut FuzzRng) -> Vec<RaceResult> {
+        let mut results = Vec::with_capacity(self.rounds);
+        for _ in 0..self.rounds {
+            let mut rng = rng.fork();
+            let race = *Race::ALL.choose(&mut rng).unwrap_or(&Race::DoubleCreate);
+            results.push(self.round(rng.seed(), race).await);
+        }
+        results
+    }
+
+    pub async fn round(&self, seed: u64, race: Race) -> RaceResult {
+        let mut result = RaceResult {
+            seed,
+            race,
+            operations: Vec::new(),
+            statuses: Vec::new(),
+            outcome: Ok(()),
+        };
+        result.outcome = self.check(race, &mut result).await;
+        result
+    }
+
+    async fn check(&self, race: Race, result: &mut RaceResult) -> Result<(), SequenceError> {
+        self.checker.tester.reset().await?;
+        let mut model = QuizModel::new();
+        let mut invariants = Invariants::new();
+        // Registering the owner first would decide the race for a double
+        // registration.
+        let mut setup = Vec::new();
+        if race != Race::DoubleRegister {
+            setup.push(register(OWNER));
+            setup.extend(OTHERS.map(register));
+        }
+        for operation in setup {
+            self.setup(&mut model, &mut invariants, result, operation)
+                .await?;
+        }
+        let (count, trash_first) = race.setup();
+        let owner = session(&model, OWNER);
+        let mut quizzes = Vec::new();
+        for index in 0..count {
+            let create = Operation::CreateQuiz {
+                session: owner.clone(),
+                name: format!("Quiz {}", index + 1),
+                description: String::new(),
+            };
+            self.setup(&mut model, &mut invariants, result, create)
+                .await?;
+            // The newest quiz is the one just created.
+            quizzes.extend(model.quizzes().map(|(id, _)| id).max());
+        }
+        if trash_first {
+            let remove = Operation::RemoveQuiz {
+                session: owner.clone(),
+                quiz: quizzes[0],
+            };
+            self.setup(&mut model, &mut invariants, result, remove)
+                .await?;
+        }
+
+        let racing = race.operations(&model, &quizzes);
+        let step = result.operations.len();
+        result.operations.extend(racing.iter().cloned());
+        let responses = self.send_together(&racing).await?;
+        result.statuses = responses.iter().map(|response| response.status).collect();
+        let violation = |message| SequenceError::Violation(Violation { step, message });
+        if let Some((operation, response)) = racing
+            .iter()
+            .zip(&responses)
+            .find(|(_, response)| response.status >= 500)
+        {
+            return Err(violation(format!(
+                "{} failed with status {}",
+                operation, response.status
+            )));
+        }
+
+        // Either order may have won, as long as the server ended up where that
+        // order leads.
+        let mut mismatch = None;
+        for order in [[0, 1], [1, 0]] {
+            let Some(after) = serial(&model, &racing, &responses, order) else {
+                continue;
+            };
+            match self.checker.observe(&after, &mut invariants).await {
+                Ok(()) => return Ok(()),
+                Err(Check::Failed(message)) => mismatch = Some(message),
+                Err(Check::Request(err)) => return Err(err.into()),
+            }
+        }
+        Err(violation(match mismatch {
+            Some(message) => format!("after the race, {}", message),
+            None => format!(
+                "statuses {} and {} fit neither order of {} and {}",
+                responses[0].status, responses[1].status, racing[0], racing[1]
+            ),
+        }))
+    }
+
+    async fn setup(
+        &self,
+        model: &mut QuizModel,
+        invariants: &mut Invariants,
+        result: &mut RaceResult,
+        operation: Operation,
+    ) -> Result<(), SequenceError> {
+        let step = result.operations.len();
+        result.operations.push(operation.clone());
+        self.checker
+            .step(model, invariants, &operation)
+            .await
+            .map_err(|err| match err {
+                Check::Failed(message) => SequenceError::Violation(Violation { step, message }),
+                Check::Request(err) => err.into(),
+            })
+    }
+
+    async fn send_together(
+        &self,
+        operations: &[Operation; 2],
+    ) -> Result<Vec<ServerResponse>, SequenceError> {
+        let barrier = Arc::new(Barrier::new(operations.len()));
+        let tasks: Vec<_> = operations
+            .iter()
+            .map(|operation| {
+                let case = operation.case(&*self.checker.auth);
+                let tester = self.checker.tester.clone();
+                let barrier = barrier.clone();
+                tokio::spawn(async move {
+                    barrier.wait().await;
+                    tester.send(&case).await
+                })
+            })
+            .collect();
+        let mut responses = Vec::with_capacity(tasks.len());
+        for task in tasks {
+            // Tasks only fail by panicking, which `send` doesn't.
+            let response = task.await.expect("race task panicked")?;
+            responses.push(response);
+        }
+        Ok(responses)
+    }
+}
+
+// `model` after the racing operations in `order`, or `None` when the statuses
+// rule that order out.
+fn serial(
+    model: &QuizModel,
+    racing: &[Operation; 2],
+    responses: &[ServerResponse],
+    order: [usize; 2],
+) -> Option<QuizModel> {
+    let mut model = model.clone();
+    for index in order {
+        let (operation, response) = (&racing[index], &responses[index]);
+        let succeeded = response.status == 200;
+        match model.expect(operation) {
+            Expected::Success if !succeeded => return None,
+            Expected::Failure if succeeded => return None,
+            _ => {}
+        }
+        if succeeded {
+            model.apply(operation, &response.body).ok()?;
+        }
+    }
+    Some(model)
+}
+
+fn register(email: &str) -> Operation {
+    Operation::Register {
+        email: email.to_string(),
+        password: PASSWORD.to_string(),
+        name_first: "Hayden".to_string(),
+        name_last: "Smith".to_string(),
+    }
+}
+
+fn session(model: &QuizModel, email: &str) -> Session {
+    Session {
+        token: model
+            .sessions()
+            .find(|(user, _)| *user == email)
+            .map(|(_, token)| token.to_string())
+            .unwrap_or_default(),
+        user: Some(email.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runner::tests::response;
+    use serde_json::json;
+
+    // The owner registered with one quiz, 1.
+    fn model() -> QuizModel {
+        let mut model = QuizModel::new();
+        model
+            .apply(&register(OWNER), &json!({"token": "t"}))
+            .unwrap();
+        let create = Operation::CreateQuiz {
+            session: session(&model, OWNER),
+            name: "Quiz 1".to_string(),
+            description: String::new(),
+        };
+        model.apply(&create, &json!({"quizId": 1})).unwrap();
+        model
+    }
+
+    #[test]
+    fn races_act_on_the_setup_quizzes_as_the_owner() {
+        let model = model();
+        assert_eq!(session(&model, OWNER).token, "t");
+        let [first, second] = Race::DoubleRename.operations(&model, &[1, 2]);
+        assert!(matches!(first, Operation::RenameQuiz { quiz: 1, .. }));
+        assert!(matches!(second, Operation::RenameQuiz { quiz: 2, .. }));
+        let [restore, empty] = Race::RestoreWhileEmptying.operations(&model, &[1]);
+        assert!(matches!(restore, Operation::RestoreQuiz { quiz: 1, .. }));
+        assert!(matches!(empty, Operation::EmptyTrash { ref quizzes, .. } if quizzes == &[1]));
+        assert_eq!(Race::RestoreWhileEmptying.setup(), (1, true));
+    }
+
+    #[test]
+    fn statuses_must_fit_one_order_or_the_other() {
+        let model = model();
+        let racing = Race::DoubleRemove.operations(&model, &[1]);
+        let statuses = |first, second| {
+            [
+                response(first, json!({}), 0),
+                response(second, json!({}), 0),
+            ]
+        };
+
+        let responses = statuses(200, 400);
+        let after = serial(&model, &racing, &responses, [0, 1]).unwrap();
+        assert_eq!(after.owned(OWNER, true).len(), 1);
+        assert!(serial(&model, &racing, &responses, [1, 0]).is_none());
+
+        // Creating one name twice can't succeed twice, whichever went first.
+        let racing = Race::DoubleCreate.operations(&model, &[]);
+        let responses = [
+            response(200, json!({"quizId": 2}), 0),
+            response(200, json!({"quizId": 3}), 0),
+        ];
+        assert!(serial(&model, &racing, &responses, [0, 1]).is_none());
+        assert!(serial(&model, &racing, &responses, [1, 0]).is_none());
+        let responses = [responses[0].clone(), response(400, json!({}), 0)];
+        assert!(serial(&model, &racing, &responses, [0, 1]).is_some());
+    }
+}
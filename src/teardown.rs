@@ -0,0 +1,236 @@
+use crate::models::client::{ServerResponse, TestCase, Tester};
+use crate::runner::CaseResult;
+use crate::spec::template_matches;
+use reqwest::Method;
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::BTreeSet;
+use std::fmt;
+
+// How to clean up after a run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Teardown {
+    // Moves each quiz the run created to the trash and empties it from there,
+    // leaving everything else alone. There is no route for removing users.
+    Delete,
+    // `DELETE /clear`, wiping the server, or `Delete` for servers that don't
+    // answer it.
+    Clear,
+}
+
+// What a run created on one server and never removed itself.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Created {
+    pub quizzes: BTreeSet<String>,
+    pub users: BTreeSet<String>,
+}
+
+impl Created {
+    // From each case's response from one server, picked by `response`.
+    pub fn from_cases<'a>(
+        cases: &'a [CaseResult],
+        response: impl Fn(&'a CaseResult) -> Option<&'a ServerResponse>,
+    ) -> Self {
+        let mut created = Self::default();
+        for case in cases {
+            let Some(response) = response(case) else {
+                continue;
+            };
+            let query: Vec<(&str, &str)> = case
+                .query
+                .iter()
+                .map(|(key, value)| (key.as_str(), value.as_str()))
+                .collect();
+            created.observe(&case.method, &case.endpoint, &query, response);
+        }
+        created
+    }
+
+    pub fn observe(
+        &mut self,
+        method: &Method,
+        endpoint: &str,
+        query: &[(&str, &str)],
+        response: &ServerResponse,
+    ) {
+        if !(200..300).contains(&response.status) {
+            return;
+        }
+        let route = |template| template_matches(template, endpoint);
+        match *method {
+            Method::POST if route("/admin/quiz") => {
+                self.quizzes.extend(id(&response.body, "quizId"));
+            }
+            Method::POST if route("/admin/auth/register") => {
+                self.users.extend(id(&response.body, "authUserId"));
+            }
+            Method::DELETE if route("/clear") => *self = Self::default(),
+            Method::DELETE if route("/admin/quiz/trash/empty") => {
+                let emptied = query
+                    .iter()
+                    .filter(|(key, _)| *key == "quizIds")
+                    .filter_map(|(_, ids)| serde_json::from_str::<Vec<Value>>(ids).ok())
+                    .flatten();
+                for quiz in emptied {
+                    self.quizzes.remove(&id_string(&quiz));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.quizzes.is_empty() && self.users.is_empty()
+    }
+}
+
+fn id(body: &Value, field: &str) -> Option<String> {
+    body.get(field).filter(|id| !id.is_null()).map(id_string)
+}
+
+fn id_string(id: &Value) -> String {
+    match id {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+// Something teardown couldn't remove, and why.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Leftover {
+    // e.g. `quiz 5` or `user 2`.
+    pub resource: String,
+    pub reason: String,
+}
+
+impl fmt::Display for Leftover {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.resource, self.reason)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Cleanup {
+    // Resources known to be gone.
+    pub removed: usize,
+    pub leftovers: Vec<Leftover>,
+}
+
+// Removes `created` from the server `tester` sends to (by itself, without
+// hooks), as its configured session.
+pub async fn tear_down(tester: &Tester, created: &Created, teardown: Teardown) -> Cleanup {
+    let mut cleanup = Cleanup::default();
+    if created.is_empty() {
+        return cleanup;
+    }
+    if teardown == Teardown::Clear {
+        let clear = TestCase::new("/clear", Method::DELETE, None);
+        if send(tester, &clear).await.is_ok() {
+            cleanup.removed = created.quizzes.len() + created.users.len();
+            return cleanup;
+        }
+    }
+
+    for quiz in &created.quizzes {
+        let trash = TestCase::new(format!("/admin/quiz/{}", quiz), Method::DELETE, None);
+        // A quiz the run already trashed can still be emptied.
+        let trashed = send(tester, &trash).await;
+        let empty = TestCase::new("/admin/quiz/trash/empty", Method::DELETE, None)
+            .with_query([("quizIds", format!("[{}]", quiz))]);
+        match (trashed, send(tester, &empty).await) {
+            (_, Ok(())) => cleanup.removed += 1,
+            (Err(reason), Err(_)) | (Ok(()), Err(reason)) => cleanup.leftovers.push(Leftover {
+                resource: format!("quiz {}", quiz),
+                reason,
+            }),
+        }
+    }
+    for user in &created.users {
+        cleanup.leftovers.push(Leftover {
+            resource: format!("user {}", user),
+            reason: "users can only be removed with `/clear`".to_string(),
+        });
+    }
+    cleanup
+}
+
+// Whether the server accepted `case`, or what it said instead.
+async fn send(tester: &Tester, case: &TestCase) -> Result<(), String> {
+    let route = format!("{} {}", case.method, case.endpoint);
+    match tester.send(case).await {
+        Ok(response) if (200..300).contains(&response.status) => Ok(()),
+        Ok(response) => Err(format!(
+            "{} returned {} {}",
+            route, response.status, response.body
+        )),
+        Err(err) => Err(err.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runner::tests::{case_result, response};
+    use serde_json::json;
+
+    #[test]
+    fn only_what_is_still_there_counts_as_created() {
+        let mut created = Created::default();
+        let ok = |body| response(200, body, 1);
+        created.observe(
+            &Method::POST,
+            "/admin/quiz",
+            &[],
+            &ok(json!({ "quizId": 1 })),
+        );
+        created.observe(
+            &Method::POST,
+            "/admin/quiz",
+            &[],
+            &ok(json!({ "quizId": 2 })),
+        );
+        created.observe(
+            &Method::POST,
+            "/admin/quiz",
+            &[],
+            &response(400, json!({ "quizId": 3 }), 1),
+        );
+        created.observe(
+            &Method::POST,
+            "/admin/auth/register",
+            &[],
+            &ok(json!({ "authUserId": "u" })),
+        );
+        assert_eq!(created.quizzes, ["1".to_string(), "2".to_string()].into());
+        assert_eq!(created.users, ["u".to_string()].into());
+
+        created.observe(
+            &Method::DELETE,
+            "/admin/quiz/trash/empty",
+            &[("quizIds", "[1]")],
+            &ok(json!({})),
+        );
+        assert_eq!(created.quizzes, ["2".to_string()].into());
+        created.observe(&Method::DELETE, "/clear", &[], &ok(json!({})));
+        assert!(created.is_empty());
+    }
+
+    #[test]
+    fn cases_are_read_through_the_response_picked() {
+        let mut case = case_result(Method::POST, "/admin/quiz", Ok(()));
+        case.actual = Some(response(200, json!({ "quizId": 5 }), 1));
+        let cases = [case];
+        let created = Created::from_cases(&cases, |case| case.actual.as_ref());
+        assert_eq!(created.quizzes, ["5".to_string()].into());
+        let created = Created::from_cases(&cases, |case| case.client.as_ref());
+        assert!(created.is_empty());
+    }
+
+    #[tokio::test]
+    async fn nothing_created_means_nothing_sent() {
+        let tester = Tester::new("http://127.0.0.1:1".into(), "http://127.0.0.1:1".into());
+        let cleanup = tear_down(&tester, &Created::default(), Teardown::Clear).await;
+        assert_eq!(cleanup, Cleanup::default());
+    }
+}
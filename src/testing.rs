@@ -0,0 +1,351 @@
+//! Mock server pairs for checking a comparison config against known
+//! divergences, and for this crate's own integration tests.
+//!
+//! The servers are a small hand-rolled harness on hyper rather than a mocking
+//! crate such as wiremock. hyper is already in the dependency tree through
+//! reqwest, so the `testing` feature adds no new crates to a build. The mocks
+//! also need things a general-purpose mocking crate doesn't offer out of the
+//! box: routes matched by the same path templates the fuzzer uses
+//! (`/admin/quiz/{quizid}`), divergences applied to one server of the pair
+//! while both keep the same mocks, a default `DELETE /clear`, and every
+//! received request kept for assertions.
+
+use crate::config::Config;
+use crate::models::client::Tester;
+use crate::spec::template_matches;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Request, Response, Server};
+use reqwest::Method;
+use serde_json::{json, Value};
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::oneshot;
+
+// A canned response to every request matching `method template`, where
+// `template` is a path like `/admin/quiz/{quizid}`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mock {
+    pub method: Method,
+    pub template: String,
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: MockBody,
+    pub delay: Duration,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MockBody {
+    Json(Value),
+    // Sent as `text/plain`.
+    Text(String),
+    // Sent as `application/json` exactly as written, for bodies a `Value`
+    // can't hold (duplicate keys, `NaN`).
+    RawJson(String),
+}
+
+impl Mock {
+    pub fn new(method: Method, template: impl Into<String>, status: u16, body: Value) -> Self {
+        Self {
+            method,
+            template: template.into(),
+            status,
+            headers: Vec::new(),
+            body: MockBody::Json(body),
+            delay: Duration::ZERO,
+        }
+    }
+
+    pub fn with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    pub fn with_delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    // The same mock, answering the way `divergence` says instead.
+    pub fn diverged(mut self, divergence: &Divergence) -> Self {
+        match divergence {
+            Divergence::Status(status) => self.status = *status,
+            Divergence::Field(pointer, value) => {
+                if let MockBody::Json(body) = &mut self.body {
+                    set_field(body, pointer, value.clone());
+                }
+            }
+            Divergence::MissingField(pointer) => {
+                if let MockBody::Json(body) = &mut self.body {
+                    remove_field(body, pointer);
+                }
+            }
+            Divergence::Header(name, value) => {
+                self.headers.push((name.clone(), value.clone()));
+            }
+            Divergence::Delay(delay) => self.delay = *delay,
+            Divergence::NotJson(text) => self.body = MockBody::Text(text.clone()),
+            Divergence::RawJson(text) => self.body = MockBody::RawJson(text.clone()),
+        }
+        self
+    }
+
+    fn matches(&self, method: &Method, path: &str) -> bool {
+        self.method == *method && template_matches(&self.template, path)
+    }
+}
+
+// How the server under test's response differs from the reference's, for a
+// comparison config to catch (or, if it ignores it, not).
+#[derive(Debug, Clone, PartialEq)]
+pub enum Divergence {
+    Status(u16),
+    // The field at the JSON pointer set to the value, added if missing.
+    Field(String, Value),
+    MissingField(String),
+    // An extra response header.
+    Header(String, String),
+    Delay(Duration),
+    // A plain text body instead of the JSON one.
+    NotJson(String),
+    // A JSON body written out by hand instead of the mock's.
+    RawJson(String),
+}
+
+// A request a mock server received.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Received {
+    pub method: Method,
+    // With the query, if any.
+    pub path: String,
+    // `Value::Null` when empty or not JSON.
+    pub body: Value,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    mocks: Vec<Mock>,
+    received: Vec<Received>,
+}
+
+// One mock server on a local port, stopped when dropped. Requests no mock
+// matches get a 404; the latest mock mounted for a route wins. `DELETE /clear`
+// answers 200 with `{}` until something else is mounted for it, so testers'
+// default before hook works.
+pub struct MockServer {
+    addr: SocketAddr,
+    state: Arc<Mutex<State>>,
+    shutdown: Option<oneshot::Sender<()>>,
+}
+
+impl MockServer {
+    pub async fn start() -> std::io::Result<Self> {
+        let state = Arc::new(Mutex::new(State::default()));
+        state
+            .lock()
+            .unwrap()
+            .mocks
+            .push(Mock::new(Method::DELETE, "/clear", 200, json!({})));
+        let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+        listener.set_nonblocking(true)?;
+        let addr = listener.local_addr()?;
+        let shared = state.clone();
+        let make_service = make_service_fn(move |_| {
+            let state = shared.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |request| respond(state.clone(), request)))
+            }
+        });
+        let (shutdown, stopped) = oneshot::channel();
+        let server = Server::from_tcp(listener)
+            .map_err(std::io::Error::other)?
+            .serve(make_service)
+            .with_graceful_shutdown(async {
+                let _ = stopped.await;
+            });
+        tokio::spawn(server);
+        Ok(Self {
+            addr,
+            state,
+            shutdown: Some(shutdown),
+        })
+    }
+
+    pub fn url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    pub fn mount(&self, mock: Mock) {
+        self.state.lock().unwrap().mocks.push(mock);
+    }
+
+    // The latest mock mounted for `method path`, if any.
+    pub fn mock(&self, method: &Method, path: &str) -> Option<Mock> {
+        find(&self.state.lock().unwrap().mocks, method, path)
+    }
+
+    // Every request so far, in the order they arrived.
+    pub fn received(&self) -> Vec<Received> {
+        self.state.lock().unwrap().received.clone()
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            let _ = shutdown.send(());
+        }
+    }
+}
+
+fn find(mocks: &[Mock], method: &Method, path: &str) -> Option<Mock> {
+    mocks
+        .iter()
+        .rev()
+        .find(|mock| mock.matches(method, path))
+        .cloned()
+}
+
+async fn respond(
+    state: Arc<Mutex<State>>,
+    request: Request<Body>,
+) -> Result<Response<Body>, Infallible> {
+    let method = request.method().clone();
+    let path = request
+        .uri()
+        .path_and_query()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_default();
+    let bytes = hyper::body::to_bytes(request.into_body())
+        .await
+        .unwrap_or_default();
+    let mock = {
+        let mut state = state.lock().unwrap();
+        state.received.push(Received {
+            method: method.clone(),
+            path: path.clone(),
+            body: serde_json::from_slice(&bytes).unwrap_or(Value::Null),
+        });
+        find(&state.mocks, &method, &path)
+    };
+    let mock = mock.unwrap_or_else(|| {
+        let error = format!("No mock for {} {}", method, path);
+        Mock::new(method, path, 404, json!({ "error": error }))
+    });
+    tokio::time::sleep(mock.delay).await;
+
+    let mut response = Response::builder().status(mock.status);
+    for (name, value) in &mock.headers {
+        response = response.header(name, value);
+    }
+    let response = match mock.body {
+        MockBody::Json(body) => response
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string())),
+        MockBody::Text(text) => response
+            .header("content-type", "text/plain")
+            .body(Body::from(text)),
+        MockBody::RawJson(text) => response
+            .header("content-type", "application/json")
+            .body(Body::from(text)),
+    };
+    Ok(response.unwrap_or_else(|err| {
+        let mut response = Response::new(Body::from(err.to_string()));
+        *response.status_mut() = hyper::StatusCode::INTERNAL_SERVER_ERROR;
+        response
+    }))
+}
+
+// A server under test and a reference server answering alike, except where
+// told to diverge:
+//
+//   let pair = MockPair::start().await?;
+//   pair.mount(Mock::new(Method::POST, "/admin/quiz", 200, json!({ "quizId": 1 })));
+//   pair.diverge(Method::POST, "/admin/quiz", Divergence::Status(400));
+//
+// A config pointed at the pair (with `configure`) should then report exactly
+// the divergences it is meant to catch.
+pub struct MockPair {
+    pub test: MockServer,
+    pub reference: MockServer,
+}
+
+impl MockPair {
+    pub async fn start() -> std::io::Result<Self> {
+        Ok(Self {
+            test: MockServer::start().await?,
+            reference: MockServer::start().await?,
+        })
+    }
+
+    pub fn test_url(&self) -> String {
+        self.test.url()
+    }
+
+    pub fn server_url(&self) -> String {
+        self.reference.url()
+    }
+
+    // Mounts `mock` on both servers.
+    pub fn mount(&self, mock: Mock) {
+        self.test.mount(mock.clone());
+        self.reference.mount(mock);
+    }
+
+    // Makes the server under test's answer to `method path` differ from the
+    // reference's, building on any divergences already there. Routes with no
+    // mock yet diverge from the 404 both servers send.
+    pub fn diverge(&self, method: Method, path: &str, divergence: Divergence) {
+        let mock = self.test.mock(&method, path).unwrap_or_else(|| {
+            let error = format!("No mock for {} {}", method, path);
+            Mock::new(method, path, 404, json!({ "error": error }))
+        });
+        self.test.mount(mock.diverged(&divergence));
+    }
+
+    pub fn tester(&self) -> Tester {
+        Tester::new(self.test_url(), self.server_url())
+    }
+
+    // `config` with its servers replaced by the pair.
+    pub fn configure(&self, config: Config) -> Config {
+        Config {
+            test_url: self.test_url(),
+            server_url: Some(self.server_url()),
+            ..config
+        }
+    }
+}
+
+fn set_field(body: &mut Value, pointer: &str, value: Value) {
+    if let Some(field) = body.pointer_mut(pointer) {
+        *field = value;
+        return;
+    }
+    let Some((parent, name)) = pointer.rsplit_once('/') else {
+        return;
+    };
+    let name = name.replace("~1", "/").replace("~0", "~");
+    if let Some(Value::Object(fields)) = body.pointer_mut(parent) {
+        fields.insert(name, value);
+    }
+}
+
+fn remove_field(body: &mut Value, pointer: &str) {
+    let Some((parent, name)) = pointer.rsplit_once('/') else {
+        return;
+    };
+    let name = name.replace("~1", "/").replace("~0", "~");
+    match body.pointer_mut(parent) {
+        Some(Value::Object(fields)) => {
+            fields.remove(&name);
+        }
+        Some(Value::Array(items)) => {
+            if let Some(index) = name.parse::<usize>().ok().filter(|i| *i < items.len()) {
+                items.remove(index);
+            }
+        }
+        _ => {}
+    }
+}
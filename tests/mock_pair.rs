@@ -0,0 +1,758 @@
+use fuzzer1531::campaign::Campaign;
+use fuzzer1531::chaos::{Chaos, ChaosProxy};
+use fuzzer1531::config::Config;
+use fuzzer1531::learn::LearnedSchemas;
+use fuzzer1531::models::client::{
+    RawResponse, RequestClient, ServerResponse, TestCase, Tester, TesterError,
+};
+use fuzzer1531::models::compare::{CompareOptions, ComparisonMode};
+use fuzzer1531::models::endpoints::{self, versioned_path, ApiVersion};
+use fuzzer1531::models::hygiene::JsonIssue;
+use fuzzer1531::models::observer::Observer;
+use fuzzer1531::models::oracle::{Expectation, StaticOracle};
+use fuzzer1531::report::prometheus::PrometheusMetrics;
+use fuzzer1531::runner::{Category, Crash, Runner};
+use fuzzer1531::scenario::pack;
+use fuzzer1531::schema::Schema;
+use fuzzer1531::teardown::{tear_down, Created, Teardown};
+use fuzzer1531::testing::{Divergence, Mock, MockPair};
+use reqwest::Method;
+use serde_json::json;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+async fn quiz_pair() -> MockPair {
+    let pair = MockPair::start().await.unwrap();
+    pair.mount(Mock::new(
+        Method::POST,
+        "/admin/quiz",
+        200,
+        json!({ "quizId": 1 }),
+    ));
+    pair.mount(Mock::new(
+        Method::GET,
+        "/admin/quiz/{quizid}",
+        200,
+        json!({ "quizId": 1, "name": "Quiz A", "description": "" }),
+    ));
+    pair
+}
+
+fn info() -> TestCase {
+    TestCase::new("/admin/quiz/1", Method::GET, None)
+}
+
+#[tokio::test]
+async fn agreeing_servers_pass() {
+    let pair = quiz_pair().await;
+    let tester = pair.tester();
+    tester.compare_case(&info()).await.outcome.unwrap();
+    tester
+        .compare(
+            "/admin/quiz",
+            Method::POST,
+            Some(json!({ "name": "Quiz A" })),
+        )
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn status_divergence_is_caught() {
+    let pair = quiz_pair().await;
+    pair.diverge(Method::POST, "/admin/quiz", Divergence::Status(400));
+    let outcome = pair
+        .tester()
+        .compare("/admin/quiz", Method::POST, None)
+        .await;
+    assert!(matches!(
+        outcome,
+        Err(TesterError::StatusMismatch {
+            client_status: 400,
+            actual_status: 200,
+            ..
+        })
+    ));
+}
+
+#[tokio::test]
+async fn value_divergence_depends_on_comparison_mode() {
+    let pair = quiz_pair().await;
+    pair.diverge(
+        Method::GET,
+        "/admin/quiz/1",
+        Divergence::Field("/name".to_string(), json!("Quiz B")),
+    );
+    // Types only by default.
+    pair.tester().compare_case(&info()).await.outcome.unwrap();
+
+    let config: Config = toml::from_str(r#"comparison = "exact""#).unwrap();
+    let tester = pair.configure(config).tester(None).unwrap();
+    let outcome = tester.compare_case(&info()).await.outcome;
+    assert!(matches!(outcome, Err(TesterError::ValueMismatch { .. })));
+
+    let config: Config =
+        toml::from_str(r#"comparison = { exact_ignoring_fields = ["/name"] }"#).unwrap();
+    let tester = pair.configure(config).tester(None).unwrap();
+    tester.compare_case(&info()).await.outcome.unwrap();
+}
+
+#[tokio::test]
+async fn missing_field_is_caught() {
+    let pair = quiz_pair().await;
+    pair.diverge(
+        Method::GET,
+        "/admin/quiz/1",
+        Divergence::MissingField("/name".to_string()),
+    );
+    let outcome = pair.tester().compare_case(&info()).await.outcome;
+    assert!(outcome.is_err());
+}
+
+#[tokio::test]
+async fn non_json_body_is_caught() {
+    let pair = quiz_pair().await;
+    pair.diverge(
+        Method::GET,
+        "/admin/quiz/1",
+        Divergence::NotJson("<h1>Error</h1>".to_string()),
+    );
+    let outcome = pair.tester().compare_case(&info()).await.outcome;
+    assert!(matches!(outcome, Err(TesterError::ContentMismatch { .. })));
+}
+
+#[tokio::test]
+async fn unmocked_routes_agree_on_404() {
+    let pair = quiz_pair().await;
+    let comparison = pair
+        .tester()
+        .compare_case(&TestCase::new("/admin/user/details", Method::GET, None))
+        .await;
+    comparison.outcome.unwrap();
+    assert_eq!(comparison.client.unwrap().status, 404);
+}
+
+#[tokio::test]
+async fn requests_are_recorded_in_order() {
+    let pair = quiz_pair().await;
+    let case = TestCase::new(
+        "/admin/quiz",
+        Method::POST,
+        Some(json!({ "name": "Quiz A" })),
+    )
+    .with_query([("token", "abc")]);
+    pair.tester().compare_case(&case).await.outcome.unwrap();
+    let received = pair.reference.received();
+    let last = received.last().unwrap();
+    assert_eq!(last.method, Method::POST);
+    assert_eq!(last.path, "/admin/quiz?token=abc");
+    assert_eq!(last.body, json!({ "name": "Quiz A" }));
+    assert_eq!(pair.test.received().len(), received.len());
+}
+
+#[tokio::test]
+async fn refused_connections_are_unreachable() {
+    let pair = quiz_pair().await;
+    let config = pair.configure(toml::from_str("iterations = 1\nseed = 1").unwrap());
+    // A port nothing listens on once the listener is dropped.
+    let port = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    let test_url = format!("http://127.0.0.1:{}", port);
+    let config = Config { test_url, ..config };
+    let report = Runner::from_config(&config).unwrap().run(|_| {}).await;
+    assert!(!report.cases.is_empty());
+    assert_eq!(report.count(Category::Unreachable), report.cases.len());
+    assert!(report.triage().is_empty());
+}
+
+#[tokio::test]
+async fn one_sided_timeouts_diverge() {
+    let pair = quiz_pair().await;
+    pair.diverge(
+        Method::GET,
+        "/admin/quiz/1",
+        Divergence::Delay(Duration::from_secs(2)),
+    );
+    let config: Config = toml::from_str("timeout = 0.2").unwrap();
+    let tester = pair.configure(config).tester(None).unwrap();
+    let comparison = tester.compare_case(&info()).await;
+    match comparison.outcome {
+        Err(TesterError::TimeoutDivergence {
+            hung,
+            elapsed,
+            status,
+            ..
+        }) => {
+            assert_eq!(hung, "server under test");
+            assert!(elapsed >= Duration::from_millis(200));
+            assert_eq!(status, 200);
+        }
+        outcome => panic!("expected a timeout divergence, got {:?}", outcome),
+    }
+    assert_eq!(comparison.actual.unwrap().status, 200);
+}
+
+#[tokio::test]
+async fn a_hung_reference_is_named_as_the_one_that_hung() {
+    let pair = quiz_pair().await;
+    let slow = pair.reference.mock(&Method::GET, "/admin/quiz/1").unwrap();
+    pair.reference
+        .mount(slow.with_delay(Duration::from_secs(2)));
+    let config: Config = toml::from_str("timeout = 0.2").unwrap();
+    let tester = pair.configure(config).tester(None).unwrap();
+    match tester.compare_case(&info()).await.outcome {
+        Err(TesterError::TimeoutDivergence { hung, status, .. }) => {
+            assert_eq!(hung, "reference server");
+            assert_eq!(status, 200);
+        }
+        outcome => panic!("expected a timeout divergence, got {:?}", outcome),
+    }
+}
+
+#[tokio::test]
+async fn timeouts_on_both_servers_are_ordinary_timeouts() {
+    let pair = quiz_pair().await;
+    let slow = pair.test.mock(&Method::GET, "/admin/quiz/1").unwrap();
+    pair.mount(slow.with_delay(Duration::from_secs(2)));
+    let config: Config = toml::from_str("timeout = 0.2").unwrap();
+    let tester = pair.configure(config).tester(None).unwrap();
+    let comparison = tester.compare_case(&info()).await;
+    match comparison.outcome {
+        Err(TesterError::Timeout { url, .. }) => assert!(url.starts_with(&pair.test_url())),
+        outcome => panic!("expected a plain timeout, got {:?}", outcome),
+    }
+    assert!(comparison.actual.is_none());
+}
+
+#[tokio::test]
+async fn teardown_removes_created_quizzes() {
+    let pair = quiz_pair().await;
+    pair.mount(Mock::new(
+        Method::DELETE,
+        "/admin/quiz/{quizid}",
+        200,
+        json!({}),
+    ));
+    pair.mount(Mock::new(
+        Method::DELETE,
+        "/admin/quiz/trash/empty",
+        200,
+        json!({}),
+    ));
+    // Only the server under test lacks `/clear`.
+    pair.diverge(Method::DELETE, "/clear", Divergence::Status(404));
+    let created = Created {
+        quizzes: ["1".to_string()].into(),
+        users: ["7".to_string()].into(),
+    };
+    let tester = pair.tester();
+
+    let test = tester.for_server(pair.test_url());
+    let cleanup = tear_down(&test, &created, Teardown::Clear).await;
+    assert_eq!(cleanup.removed, 1);
+    assert_eq!(cleanup.leftovers.len(), 1);
+    assert_eq!(cleanup.leftovers[0].resource, "user 7");
+    let paths: Vec<String> = pair.test.received().into_iter().map(|r| r.path).collect();
+    assert!(paths.contains(&"/admin/quiz/1".to_string()));
+    assert!(paths.contains(&"/admin/quiz/trash/empty?quizIds=%5B1%5D".to_string()));
+
+    let reference = tester.for_reference().unwrap();
+    let cleanup = tear_down(&reference, &created, Teardown::Clear).await;
+    assert_eq!(cleanup.removed, 2);
+    assert!(cleanup.leftovers.is_empty());
+}
+
+#[tokio::test]
+async fn learned_schemas_round_trip() {
+    let pair = quiz_pair().await;
+    let tester = pair.tester().for_reference().unwrap();
+    let mut learned = LearnedSchemas::new();
+    for name in [json!("Quiz A"), json!(null)] {
+        let case = TestCase::new("/admin/quiz/1", Method::GET, None);
+        pair.reference.mount(Mock::new(
+            Method::GET,
+            "/admin/quiz/{quizid}",
+            200,
+            json!({ "quizId": 1, "name": name }),
+        ));
+        let response = tester.send(&case).await.unwrap();
+        learned.observe(&case.method, &case.endpoint, &response);
+    }
+    let route = &learned.routes[&("GET".to_string(), "/admin/quiz/{quizid}".to_string())];
+    assert_eq!(route.samples, 2);
+    assert!(route.error.is_none());
+    let Some(Schema::Object {
+        properties,
+        required,
+    }) = &route.success
+    else {
+        panic!("expected an object schema, got {:?}", route.success);
+    };
+    assert_eq!(
+        properties["name"],
+        Schema::Nullable(Box::new(Schema::String))
+    );
+    assert_eq!(required, &["name".to_string(), "quizId".to_string()]);
+
+    let path = std::env::temp_dir().join(format!("learned-{}.json", std::process::id()));
+    learned.save(&path).unwrap();
+    let loaded = LearnedSchemas::load(&path);
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(loaded.unwrap(), learned);
+}
+
+#[tokio::test]
+async fn strict_json_catches_duplicate_keys() {
+    let pair = quiz_pair().await;
+    pair.diverge(
+        Method::GET,
+        "/admin/quiz/1",
+        Divergence::RawJson(
+            r#"{"quizId": 1, "name": "Quiz A", "name": "Quiz B", "description": ""}"#.to_string(),
+        ),
+    );
+    // The last `name` wins and has the right type.
+    pair.tester().compare_case(&info()).await.outcome.unwrap();
+
+    let config: Config = toml::from_str("strict_json = true").unwrap();
+    let tester = pair.configure(config).tester(None).unwrap();
+    match tester.compare_case(&info()).await.outcome {
+        Err(TesterError::JsonHygiene {
+            server,
+            issue: JsonIssue::DuplicateKey { pointer, key },
+            ..
+        }) => {
+            assert_eq!(server, "server under test");
+            assert_eq!(pointer, "");
+            assert_eq!(key, "name");
+        }
+        outcome => panic!("expected a duplicate key, got {:?}", outcome),
+    }
+
+    // Both servers sending `NaN` agree.
+    pair.mount(
+        Mock::new(Method::GET, "/admin/quiz/{quizid}", 200, json!({})).diverged(
+            &Divergence::RawJson(r#"{"quizId": 1, "score": NaN}"#.to_string()),
+        ),
+    );
+    let comparison = tester.compare_case(&info()).await;
+    comparison.outcome.unwrap();
+    assert_eq!(comparison.client.unwrap().body["score"], json!(null));
+}
+
+#[derive(Default)]
+struct Counting {
+    requests: AtomicUsize,
+    responses: AtomicUsize,
+}
+
+impl Observer for Counting {
+    fn on_request(&self, _server: &str, _case: &TestCase) {
+        self.requests.fetch_add(1, Ordering::SeqCst);
+    }
+
+    fn on_response(
+        &self,
+        _server: &str,
+        _case: &TestCase,
+        _response: Result<&ServerResponse, &TesterError>,
+    ) {
+        self.responses.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+#[tokio::test]
+async fn observers_see_both_servers() {
+    let pair = quiz_pair().await;
+    let counting = Arc::new(Counting::default());
+    let metrics = Arc::new(PrometheusMetrics::new());
+    let tester = pair
+        .tester()
+        .with_observer(counting.clone())
+        .with_observer(metrics.clone());
+    tester.compare_case(&info()).await.outcome.unwrap();
+    assert_eq!(counting.requests.load(Ordering::SeqCst), 2);
+    assert_eq!(counting.responses.load(Ordering::SeqCst), 2);
+
+    let rendered = metrics.render();
+    for url in [pair.test.url(), pair.reference.url()] {
+        let line = format!(
+            "fuzzer_requests_total{{server=\"{}\",method=\"GET\",route=\"/admin/quiz/{{quizid}}\",status=\"200\"}} 1",
+            url
+        );
+        assert!(rendered.contains(&line), "{} not in\n{}", line, rendered);
+    }
+    assert!(rendered.contains("fuzzer_request_duration_seconds_count{"));
+}
+
+#[test]
+fn session_routes_resolve_to_their_templates() {
+    for (path, template) in [
+        (
+            "/admin/quiz/5/session/start",
+            "/admin/quiz/{quizid}/session/start",
+        ),
+        (
+            "/admin/quiz/5/session/7",
+            "/admin/quiz/{quizid}/session/{sessionid}",
+        ),
+        ("/admin/quiz/5/sessions", "/admin/quiz/{quizid}/sessions"),
+        ("/player/join", "/player/join"),
+        (
+            "/player/3/question/1/answer",
+            "/player/{playerid}/question/{questionposition}/answer",
+        ),
+    ] {
+        assert_eq!(endpoints::resolve(path).unwrap().path, template);
+    }
+    // Only ever served under `/v1`.
+    assert_eq!(
+        versioned_path(ApiVersion::V2, "/player/3/chat"),
+        "/v1/player/3/chat"
+    );
+
+    // Every path parameter of the logout scenarios is filled in.
+    for step in pack::logout().iter().flat_map(|scenario| &scenario.steps) {
+        let unfilled = step.endpoint.replace("{{", "").replace("}}", "");
+        assert!(
+            !unfilled.contains('{'),
+            "{} is left unfilled",
+            step.endpoint
+        );
+    }
+    assert!(pack::builtin(Some(ApiVersion::V2)).len() > pack::builtin(None).len());
+}
+
+#[tokio::test]
+async fn resumed_campaigns_skip_sent_cases() {
+    let pair = quiz_pair().await;
+    let path = std::env::temp_dir().join(format!("campaign-{}.json", std::process::id()));
+    let config = pair.configure(toml::from_str("iterations = 3\nseed = 4").unwrap());
+    let full = Runner::from_config(&config).unwrap().run(|_| {}).await;
+
+    let config = Config {
+        campaign: Some(path.clone()),
+        ..config
+    };
+    let interrupted = Config {
+        max_cases: Some(5),
+        ..config.clone()
+    };
+    Runner::from_config(&interrupted).unwrap().run(|_| {}).await;
+    let campaign = Campaign::load(&path).unwrap();
+    assert_eq!(campaign.cases, 5);
+
+    let received = pair.test.received().len();
+    let runner = Runner::from_config(&config).unwrap();
+    let resumed = runner.resume(campaign.clone()).unwrap().run(|_| {}).await;
+    assert_eq!(resumed.resumed, 5);
+    assert_eq!(resumed.cases.len() + 5, full.cases.len());
+    let sent: Vec<u64> = resumed.cases.iter().map(|case| case.seed).collect();
+    let expected: Vec<u64> = full.cases[5..].iter().map(|case| case.seed).collect();
+    assert_eq!(sent, expected);
+    assert!(pair.test.received().len() - received >= resumed.cases.len());
+    assert_eq!(Campaign::load(&path).unwrap().cases, full.cases.len());
+
+    let other = Config {
+        seed: Some(5),
+        ..config
+    };
+    let mismatch = Runner::from_config(&other).unwrap().resume(campaign);
+    assert!(mismatch.is_err());
+    let _ = std::fs::remove_file(path);
+}
+
+#[tokio::test]
+async fn chaos_proxies_delay_drop_and_truncate() {
+    let pair = quiz_pair().await;
+    let tester = pair.tester();
+    let through = |chaos: Chaos| {
+        let proxy = ChaosProxy::start(&pair.test_url(), chaos).unwrap();
+        let tester = tester.for_server(proxy.url().to_string());
+        (proxy, tester)
+    };
+
+    let (proxy, slow) = through(Chaos::new(1).with_latency(Duration::from_millis(200)));
+    let response = slow.send(&info()).await.unwrap();
+    assert_eq!(response.status, 200);
+    assert!(response.latency >= Duration::from_millis(200));
+    assert_eq!(proxy.counts().delayed, 1);
+
+    let (proxy, dropping) = through(Chaos::new(1).with_drop_rate(1.0));
+    assert!(dropping.send(&info()).await.is_err());
+    assert!(proxy.counts().dropped >= 1);
+
+    let (proxy, truncating) = through(Chaos::new(1).with_truncate_rate(1.0));
+    assert!(truncating.send(&info()).await.is_err());
+    assert!(proxy.counts().truncated >= 1);
+    assert_eq!(proxy.counts().dropped, 0);
+
+    // A dropped proxy stops listening.
+    let addr = proxy.url().trim_start_matches("http://").to_string();
+    drop(proxy);
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert!(tokio::net::TcpStream::connect(addr).await.is_err());
+}
+
+#[tokio::test]
+async fn error_messages_follow_field_rules() {
+    let pair = MockPair::start().await.unwrap();
+    pair.mount(Mock::new(
+        Method::GET,
+        "/admin/quiz/{quizid}",
+        400,
+        json!({ "error": "Quiz ID does not refer to a valid quiz" }),
+    ));
+    pair.diverge(
+        Method::GET,
+        "/admin/quiz/1",
+        Divergence::Field("/error".to_string(), json!("invalid quizId")),
+    );
+    let rules = |rule: &str| {
+        let source = format!("comparison = \"exact\"\n[fields]\n\"/error\" = {}", rule);
+        let config: Config = toml::from_str(&source).unwrap();
+        pair.configure(config).tester(None).unwrap()
+    };
+    let non_empty = rules(r#"{ type = "non_empty" }"#);
+    non_empty.compare_case(&info()).await.outcome.unwrap();
+    let matching = rules(r#"{ type = "matches", pattern = "(?i)quiz" }"#);
+    matching.compare_case(&info()).await.outcome.unwrap();
+    let strict = rules(r#"{ type = "matches", pattern = "^Quiz ID" }"#);
+    match strict.compare_case(&info()).await.outcome {
+        Err(TesterError::FieldRuleViolation { server, path, .. }) => {
+            assert_eq!(server, "client");
+            assert_eq!(path, "/error");
+        }
+        outcome => panic!("expected a broken field rule, got {:?}", outcome),
+    }
+
+    pair.diverge(
+        Method::GET,
+        "/admin/quiz/1",
+        Divergence::Field("/error".to_string(), json!("")),
+    );
+    let outcome = non_empty.compare_case(&info()).await.outcome;
+    assert!(matches!(
+        outcome,
+        Err(TesterError::FieldRuleViolation { .. })
+    ));
+    let invalid: Result<Config, _> =
+        toml::from_str("[fields]\n\"/error\" = { type = \"matches\", pattern = \"(\" }");
+    assert!(invalid.is_err());
+}
+
+#[tokio::test]
+async fn testers_run_cases_from_any_iterator() {
+    let pair = quiz_pair().await;
+    pair.diverge(
+        Method::GET,
+        "/admin/quiz/2",
+        Divergence::Field("/name".to_string(), json!("Quiz B")),
+    );
+    let tester = pair.tester().with_concurrency(2);
+    let exact = CompareOptions {
+        mode: ComparisonMode::Exact,
+        ..tester.options().clone()
+    };
+    let cases = (1..=3)
+        .map(|id| TestCase::new(format!("/admin/quiz/{}", id), Method::GET, None))
+        .chain([TestCase::new("/admin/quiz/2", Method::GET, None)
+            .with_seed(7)
+            .with_options(exact)]);
+    let report = tester.run(cases).await;
+    assert_eq!(report.cases.len(), 4);
+    assert_eq!(report.planned, 4);
+    assert_eq!(report.passed(), 3);
+    let failed = &report.cases[3];
+    assert_eq!((failed.iteration, failed.seed), (1, 7));
+    assert_eq!(failed.target, "/admin/quiz/{quizid}");
+    assert!(matches!(
+        failed.outcome,
+        Err(TesterError::ValueMismatch { .. })
+    ));
+    assert_eq!(
+        report.routes,
+        vec![("GET".to_string(), "/admin/quiz/{quizid}".to_string())]
+    );
+
+    // Crashes count even when both servers crash the same way.
+    pair.mount(Mock::new(
+        Method::GET,
+        "/admin/quiz/list",
+        500,
+        json!({ "error": "boom" }),
+    ));
+    let list = TestCase::new("/admin/quiz/list", Method::GET, None);
+    let report = tester.run([list]).await;
+    let crashes: Vec<_> = report.crashes().map(|case| case.crash).collect();
+    assert_eq!(crashes, vec![Some(Crash::ServerError(500))]);
+}
+
+#[tokio::test]
+async fn running_no_cases_or_fewer_than_the_concurrency() {
+    let pair = quiz_pair().await;
+    let tester = pair.tester().with_concurrency(8);
+    let report = tester.run(std::iter::empty()).await;
+    assert!(report.cases.is_empty());
+    assert_eq!(report.planned, 0);
+    assert!(report.routes.is_empty());
+    assert!(pair.test.received().is_empty());
+
+    let cases = (1..=3).map(|id| TestCase::new(format!("/admin/quiz/{}", id), Method::GET, None));
+    let report = tester.run(cases).await;
+    assert_eq!(report.cases.len(), 3);
+    assert_eq!(report.passed(), 3);
+    // One short batch is a single iteration.
+    assert!(report.cases.iter().all(|case| case.iteration == 0));
+    assert_eq!(pair.test.received().len(), 3);
+}
+
+#[tokio::test]
+async fn large_bodies_are_compared_as_streamed() {
+    let pair = MockPair::start().await.unwrap();
+    let quizzes: Vec<_> = (0..2000)
+        .map(|id| json!({ "quizId": id, "name": format!("Quiz {}", id) }))
+        .collect();
+    pair.mount(Mock::new(
+        Method::GET,
+        "/admin/quiz/list",
+        200,
+        json!({ "quizzes": quizzes }),
+    ));
+    let list = TestCase::new("/admin/quiz/list", Method::GET, None);
+    let tester = pair.tester().with_streaming(1024);
+
+    let comparison = tester.compare_case(&list).await;
+    comparison.outcome.unwrap();
+    assert!(matches!(
+        comparison.client.unwrap().raw,
+        Some(RawResponse::Streamed { .. })
+    ));
+
+    pair.diverge(
+        Method::GET,
+        "/admin/quiz/list",
+        Divergence::Field("/quizzes/1500/name".to_string(), json!(1500)),
+    );
+    match tester.compare_case(&list).await.outcome {
+        Err(TesterError::TypeMismatch { path, .. }) => assert_eq!(path, "/quizzes/1500/name"),
+        outcome => panic!("expected a type mismatch, got {:?}", outcome),
+    }
+    // Exact comparisons decode the bodies after all.
+    pair.diverge(
+        Method::GET,
+        "/admin/quiz/list",
+        Divergence::Field("/quizzes/1500/name".to_string(), json!("Quiz B")),
+    );
+    tester.compare_case(&list).await.outcome.unwrap();
+    let exact = tester.clone().with_mode(ComparisonMode::Exact);
+    let outcome = exact.compare_case(&list).await.outcome;
+    assert!(matches!(outcome, Err(TesterError::ValueMismatch { .. })));
+
+    // The same quizzes with their keys the other way round, one of them with
+    // a name that isn't a string.
+    let mut reordered: Vec<String> = (0..2000)
+        .map(|id| format!(r#"{{"quizId": {}, "name": "Quiz {}"}}"#, id, id))
+        .collect();
+    let raw = format!(r#"{{ "quizzes": [{}] }}"#, reordered.join(", "));
+    pair.diverge(Method::GET, "/admin/quiz/list", Divergence::RawJson(raw));
+    tester.compare_case(&list).await.outcome.unwrap();
+    exact.compare_case(&list).await.outcome.unwrap();
+    reordered[1200] = r#"{"quizId": 1200, "name": ["Quiz 1200"]}"#.to_string();
+    let raw = format!(r#"{{ "quizzes": [{}] }}"#, reordered.join(", "));
+    pair.diverge(Method::GET, "/admin/quiz/list", Divergence::RawJson(raw));
+    match tester.compare_case(&list).await.outcome {
+        Err(TesterError::TypeMismatch { path, .. }) => assert_eq!(path, "/quizzes/1200/name"),
+        outcome => panic!("expected a type mismatch, got {:?}", outcome),
+    }
+
+    pair.diverge(
+        Method::GET,
+        "/admin/quiz/list",
+        Divergence::RawJson(r#"{"quizzes": []}"#.to_string()),
+    );
+    // Only the reference's is streamed; the other is read in full.
+    match tester.compare_case(&list).await.outcome {
+        Err(TesterError::ArrayLengthMismatch {
+            path,
+            client_len,
+            actual_len,
+            ..
+        }) => assert_eq!(
+            (path.as_str(), client_len, actual_len),
+            ("/quizzes", 0, 2000)
+        ),
+        outcome => panic!("expected an array length mismatch, got {:?}", outcome),
+    }
+}
+
+#[tokio::test]
+async fn responses_read_as_typed_values() {
+    #[derive(Debug, PartialEq, serde::Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Quiz {
+        quiz_id: u64,
+        name: String,
+    }
+
+    let pair = MockPair::start().await.unwrap();
+    pair.mount(Mock::new(
+        Method::GET,
+        "/admin/quiz/{quizid}",
+        200,
+        json!({ "quizId": 7, "name": "Capitals", "description": "" }),
+    ));
+    let case = TestCase::new("/admin/quiz/7", Method::GET, None);
+    let quiz = Quiz {
+        quiz_id: 7,
+        name: "Capitals".to_string(),
+    };
+
+    let client = RequestClient::new(pair.server_url());
+    assert_eq!(client.request_json::<Quiz>(&case).await.unwrap(), quiz);
+    let (test, reference) = pair.tester().compare_as::<Quiz>(&case).await.unwrap();
+    assert_eq!((&test, &reference), (&quiz, &quiz));
+    // Large bodies are read in full to be decoded.
+    let streamed = pair.tester().with_streaming(8);
+    let (test, _) = streamed.compare_as::<Quiz>(&case).await.unwrap();
+    assert_eq!(test, quiz);
+
+    // Both servers agree on types, but neither fits the struct.
+    pair.mount(Mock::new(
+        Method::GET,
+        "/admin/quiz/{quizid}",
+        200,
+        json!({ "quizId": 7, "name": null }),
+    ));
+    match pair.tester().compare_as::<Quiz>(&case).await {
+        Err(TesterError::UnexpectedBody { server, status, .. }) => {
+            assert_eq!((server.as_str(), status), ("client", 200))
+        }
+        outcome => panic!("expected an unexpected body, got {:?}", outcome),
+    }
+    // Differences are reported before the bodies are read.
+    pair.diverge(
+        Method::GET,
+        "/admin/quiz/{quizid}",
+        Divergence::Field("/quizId".to_string(), json!("7")),
+    );
+    let outcome = pair.tester().compare_as::<Quiz>(&case).await;
+    assert!(matches!(outcome, Err(TesterError::TypeMismatch { .. })));
+
+    // An oracle has no body to read, so nothing is sent.
+    let requests = pair.test.received().len();
+    let oracle =
+        StaticOracle::new().with_rule(Method::GET, "/admin/quiz/{quizid}", Expectation::new(200));
+    let tester = Tester::against_oracle(pair.test_url(), oracle);
+    match tester.compare_as::<Quiz>(&case).await {
+        Err(TesterError::InvalidUsage { message, .. }) => {
+            assert_eq!(message, "compare_as needs a reference server")
+        }
+        outcome => panic!("expected invalid usage, got {:?}", outcome),
+    }
+    assert_eq!(pair.test.received().len(), requests);
+}